@@ -0,0 +1,545 @@
+// Copyright (C) 2025 GStreamer Rust Plugins Developers
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_analytics::*;
+use gst_base::subclass::prelude::*;
+use gst_video::subclass::prelude::*;
+
+use ort::session::builder::GraphOptimizationLevel;
+use ort::session::Session;
+use ort::tensor::TensorElementType;
+use ort::value::{Tensor, ValueType};
+
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use super::ssd;
+use super::OnnxInputImageFormat;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rsonnxinference",
+        gst::DebugColorFlags::empty(),
+        Some("ONNX Runtime inference element"),
+    )
+});
+
+const DEFAULT_INPUT_IMAGE_FORMAT: OnnxInputImageFormat = OnnxInputImageFormat::Hwc;
+const DEFAULT_INPUT_TENSOR_OFFSET: f64 = 0.0;
+const DEFAULT_INPUT_TENSOR_SCALE: f64 = 1.0;
+const DEFAULT_SCORE_THRESHOLD: f32 = 0.3;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    model_file: Option<String>,
+    labels_file: Option<String>,
+    input_image_format: OnnxInputImageFormat,
+    input_tensor_offset: f64,
+    input_tensor_scale: f64,
+    score_threshold: f32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            model_file: None,
+            labels_file: None,
+            input_image_format: DEFAULT_INPUT_IMAGE_FORMAT,
+            input_tensor_offset: DEFAULT_INPUT_TENSOR_OFFSET,
+            input_tensor_scale: DEFAULT_INPUT_TENSOR_SCALE,
+            score_threshold: DEFAULT_SCORE_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputType {
+    U8,
+    F32,
+}
+
+struct State {
+    session: Session,
+    input_name: String,
+    input_type: InputType,
+    // Height and width of the model input, negative if dynamic
+    input_height: i64,
+    input_width: i64,
+    has_num_detections: bool,
+    labels: Vec<glib::Quark>,
+}
+
+#[derive(Default)]
+pub struct OnnxInference {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for OnnxInference {
+    const NAME: &'static str = "GstRsOnnxInference";
+    type Type = super::OnnxInference;
+    type ParentType = gst_video::VideoFilter;
+}
+
+impl OnnxInference {
+    fn load_model(&self, settings: &Settings) -> Result<State, gst::ErrorMessage> {
+        let Some(ref model_file) = settings.model_file else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No model file set"]
+            ));
+        };
+
+        let session = Session::builder()
+            .and_then(|builder| builder.with_optimization_level(GraphOptimizationLevel::Level3))
+            .and_then(|builder| builder.commit_from_file(model_file))
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::LibraryError::Init,
+                    ["Failed to load model '{}': {}", model_file, err]
+                )
+            })?;
+
+        let Some(input) = session.inputs.first() else {
+            return Err(gst::error_msg!(
+                gst::LibraryError::Settings,
+                ["Model has no inputs"]
+            ));
+        };
+
+        let ValueType::Tensor {
+            ty, ref dimensions, ..
+        } = input.input_type
+        else {
+            return Err(gst::error_msg!(
+                gst::LibraryError::Settings,
+                ["Model input '{}' is not a tensor", input.name]
+            ));
+        };
+
+        let input_type = match ty {
+            TensorElementType::Uint8 => InputType::U8,
+            TensorElementType::Float32 => InputType::F32,
+            _ => {
+                return Err(gst::error_msg!(
+                    gst::LibraryError::Settings,
+                    ["Unsupported model input type {:?}", ty]
+                ));
+            }
+        };
+
+        let (input_height, input_width, channels) =
+            match (settings.input_image_format, &dimensions[..]) {
+                (OnnxInputImageFormat::Hwc, [_, h, w, c]) => (*h, *w, *c),
+                (OnnxInputImageFormat::Chw, [_, c, h, w]) => (*h, *w, *c),
+                _ => {
+                    return Err(gst::error_msg!(
+                        gst::LibraryError::Settings,
+                        [
+                            "Model input dimensions {:?} don't match image format {:?}",
+                            dimensions,
+                            settings.input_image_format
+                        ]
+                    ));
+                }
+            };
+
+        if channels > 0 && channels != 3 {
+            return Err(gst::error_msg!(
+                gst::LibraryError::Settings,
+                [
+                    "Model input has {} channels, only 3 are supported",
+                    channels
+                ]
+            ));
+        }
+
+        for output in [ssd::BOXES_OUTPUT, ssd::CLASSES_OUTPUT, ssd::SCORES_OUTPUT] {
+            if !session.outputs.iter().any(|o| o.name == output) {
+                return Err(gst::error_msg!(
+                    gst::LibraryError::Settings,
+                    ["Model has no output named '{}'", output]
+                ));
+            }
+        }
+        let has_num_detections = session
+            .outputs
+            .iter()
+            .any(|o| o.name == ssd::NUM_DETECTIONS_OUTPUT);
+
+        let labels = match settings.labels_file {
+            Some(ref labels_file) => std::fs::read_to_string(labels_file)
+                .map_err(|err| {
+                    gst::error_msg!(
+                        gst::ResourceError::OpenRead,
+                        ["Failed to read labels file '{}': {}", labels_file, err]
+                    )
+                })?
+                .lines()
+                .map(|label| glib::Quark::from_str(label.trim()))
+                .collect(),
+            None => vec![],
+        };
+
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Loaded model '{}' with input '{}' {:?} {:?}, {} labels",
+            model_file,
+            input.name,
+            ty,
+            dimensions,
+            labels.len()
+        );
+
+        Ok(State {
+            input_name: input.name.clone(),
+            input_type,
+            input_height,
+            input_width,
+            has_num_detections,
+            labels,
+            session,
+        })
+    }
+
+    fn input_tensor<T: Copy + Default>(
+        frame: &gst_video::VideoFrameRef<&mut gst::BufferRef>,
+        format: OnnxInputImageFormat,
+        convert: impl Fn(u8) -> T,
+    ) -> (Vec<usize>, Vec<T>) {
+        let width = frame.width() as usize;
+        let height = frame.height() as usize;
+        let stride = frame.plane_stride()[0] as usize;
+        let data = frame.plane_data(0).unwrap();
+
+        let mut tensor = vec![T::default(); width * height * 3];
+        for (y, line) in data.chunks(stride).take(height).enumerate() {
+            for (x, pixel) in line[..width * 3].chunks_exact(3).enumerate() {
+                for (c, value) in pixel.iter().enumerate() {
+                    let idx = match format {
+                        OnnxInputImageFormat::Chw => (c * height + y) * width + x,
+                        _ => (y * width + x) * 3 + c,
+                    };
+                    tensor[idx] = convert(*value);
+                }
+            }
+        }
+
+        let shape = match format {
+            OnnxInputImageFormat::Chw => vec![1, 3, height, width],
+            _ => vec![1, height, width, 3],
+        };
+
+        (shape, tensor)
+    }
+
+    fn infer(
+        &self,
+        state: &State,
+        settings: &Settings,
+        frame: &gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    ) -> Result<Vec<ssd::Detection>, ort::Error> {
+        let input = match state.input_type {
+            InputType::U8 => Tensor::from_array(Self::input_tensor(
+                frame,
+                settings.input_image_format,
+                |v| v,
+            ))?
+            .into_dyn(),
+            InputType::F32 => {
+                let offset = settings.input_tensor_offset;
+                let scale = settings.input_tensor_scale;
+                Tensor::from_array(Self::input_tensor(
+                    frame,
+                    settings.input_image_format,
+                    |v| ((v as f64 + offset) / scale) as f32,
+                ))?
+                .into_dyn()
+            }
+        };
+
+        let outputs = state
+            .session
+            .run(ort::inputs![state.input_name.as_str() => input]?)?;
+
+        let (_, boxes) = outputs[ssd::BOXES_OUTPUT].try_extract_raw_tensor::<f32>()?;
+        let (_, classes) = outputs[ssd::CLASSES_OUTPUT].try_extract_raw_tensor::<f32>()?;
+        let (_, scores) = outputs[ssd::SCORES_OUTPUT].try_extract_raw_tensor::<f32>()?;
+        let num_detections = if state.has_num_detections {
+            let (_, num_detections) =
+                outputs[ssd::NUM_DETECTIONS_OUTPUT].try_extract_raw_tensor::<f32>()?;
+            num_detections.first().copied()
+        } else {
+            None
+        };
+
+        Ok(ssd::decode(
+            boxes,
+            classes,
+            scores,
+            num_detections,
+            settings.score_threshold,
+            frame.width(),
+            frame.height(),
+        ))
+    }
+}
+
+impl ObjectImpl for OnnxInference {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecString::builder("model-file")
+                    .nick("Model File")
+                    .blurb("Path to the ONNX model file")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("labels-file")
+                    .nick("Labels File")
+                    .blurb("Path to a file with one label per line, indexed by class id")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default(
+                    "input-image-format",
+                    DEFAULT_INPUT_IMAGE_FORMAT,
+                )
+                .nick("Input Image Format")
+                .blurb("Layout of the model input tensor")
+                .mutable_ready()
+                .build(),
+                glib::ParamSpecDouble::builder("input-tensor-offset")
+                    .nick("Input Tensor Offset")
+                    .blurb("Offset added to each pixel value of floating point input tensors")
+                    .default_value(DEFAULT_INPUT_TENSOR_OFFSET)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("input-tensor-scale")
+                    .nick("Input Tensor Scale")
+                    .blurb("Divisor applied to each pixel value of floating point input tensors after the offset")
+                    .minimum(f64::MIN_POSITIVE)
+                    .default_value(DEFAULT_INPUT_TENSOR_SCALE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecFloat::builder("score-threshold")
+                    .nick("Score Threshold")
+                    .blurb("Minimum score of detections attached to the buffers")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_SCORE_THRESHOLD)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "model-file" => {
+                settings.model_file = value.get().expect("type checked upstream");
+            }
+            "labels-file" => {
+                settings.labels_file = value.get().expect("type checked upstream");
+            }
+            "input-image-format" => {
+                settings.input_image_format = value.get().expect("type checked upstream");
+            }
+            "input-tensor-offset" => {
+                settings.input_tensor_offset = value.get().expect("type checked upstream");
+            }
+            "input-tensor-scale" => {
+                settings.input_tensor_scale = value.get().expect("type checked upstream");
+            }
+            "score-threshold" => {
+                settings.score_threshold = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "model-file" => settings.model_file.to_value(),
+            "labels-file" => settings.labels_file.to_value(),
+            "input-image-format" => settings.input_image_format.to_value(),
+            "input-tensor-offset" => settings.input_tensor_offset.to_value(),
+            "input-tensor-scale" => settings.input_tensor_scale.to_value(),
+            "score-threshold" => settings.score_threshold.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for OnnxInference {}
+
+impl ElementImpl for OnnxInference {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "ONNX Runtime inference",
+                "Filter/Analyzer/Video",
+                "Runs an ONNX object detection model and attaches the results as analytics metadata",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format(gst_video::VideoFormat::Rgb)
+                .build();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for OnnxInference {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+        let state = self.load_model(&settings)?;
+        *self.state.lock().unwrap() = Some(state);
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = None;
+
+        Ok(())
+    }
+}
+
+impl VideoFilterImpl for OnnxInference {
+    fn set_info(
+        &self,
+        incaps: &gst::Caps,
+        in_info: &gst_video::VideoInfo,
+        outcaps: &gst::Caps,
+        out_info: &gst_video::VideoInfo,
+    ) -> Result<(), gst::LoggableError> {
+        let state = self.state.lock().unwrap();
+        let Some(ref state) = *state else {
+            return Err(gst::loggable_error!(CAT, "Model not loaded"));
+        };
+
+        if (state.input_width > 0 && state.input_width != in_info.width() as i64)
+            || (state.input_height > 0 && state.input_height != in_info.height() as i64)
+        {
+            return Err(gst::loggable_error!(
+                CAT,
+                "Frame size {}x{} doesn't match model input size {}x{}",
+                in_info.width(),
+                in_info.height(),
+                state.input_width,
+                state.input_height
+            ));
+        }
+        drop(state);
+
+        self.parent_set_info(incaps, in_info, outcaps, out_info)
+    }
+
+    fn transform_frame_ip(
+        &self,
+        frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = self.settings.lock().unwrap().clone();
+        let state = self.state.lock().unwrap();
+        let Some(ref state) = *state else {
+            return Err(gst::FlowError::Flushing);
+        };
+
+        let detections = self.infer(state, &settings, frame).map_err(|err| {
+            gst::element_imp_error!(
+                self,
+                gst::LibraryError::Failed,
+                ["Inference failed: {}", err]
+            );
+            gst::FlowError::Error
+        })?;
+
+        gst::trace!(CAT, imp = self, "Detected {} objects", detections.len());
+
+        if detections.is_empty() {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let mut arm = AnalyticsRelationMeta::add(frame.buffer_mut());
+        for detection in detections {
+            let quark = state
+                .labels
+                .get(detection.class_id)
+                .copied()
+                .unwrap_or_else(|| glib::Quark::from_str(detection.class_id.to_string()));
+
+            gst::log!(
+                CAT,
+                imp = self,
+                "Object {} with score {} at {}x{}@({},{})",
+                quark.as_str(),
+                detection.score,
+                detection.w,
+                detection.h,
+                detection.x,
+                detection.y
+            );
+
+            if let Err(err) = arm.add_od_mtd(
+                quark,
+                detection.x,
+                detection.y,
+                detection.w,
+                detection.h,
+                detection.score,
+            ) {
+                gst::warning!(CAT, imp = self, "Failed to add detection: {}", err);
+                continue;
+            }
+            let _ = arm.add_one_cls_mtd(detection.score, quark);
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,62 @@
+// Copyright (C) 2025 GStreamer Rust Plugins Developers
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-rsonnxinference:
+ * @short_description: Runs an object detection model with ONNX Runtime on video frames.
+ *
+ * Each RGB frame is converted into the input tensor of the model configured with `model-file`,
+ * using `input-image-format` for the tensor layout and `input-tensor-offset` and
+ * `input-tensor-scale` for normalizing the pixel values of floating point inputs. The frame
+ * size must match the input size of the model if the latter is not dynamic.
+ *
+ * The model is expected to produce SSD-style outputs named `detection_boxes`,
+ * `detection_classes`, `detection_scores` and, optionally, `num_detections`. Every detection
+ * with a score of at least `score-threshold` is attached to the buffer as object detection
+ * metadata in an analytics relation meta, with the object type taken from `labels-file` if set.
+ * The metadata can then be consumed by `relationmeta2onvifmeta` and the ONVIF elements.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 v4l2src ! videoconvertscale ! video/x-raw,format=RGB,width=300,height=300 \
+ *   ! rsonnxinference model-file=ssd_mobilenet_v1.onnx labels-file=coco.txt \
+ *   ! relationmeta2onvifmeta ! onvifmetadataoverlay ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+mod ssd;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum, Default)]
+#[repr(u32)]
+#[enum_type(name = "GstOnnxInputImageFormat")]
+#[non_exhaustive]
+pub enum OnnxInputImageFormat {
+    #[default]
+    #[enum_value(name = "HWC: Height, width, channels (interleaved).", nick = "hwc")]
+    Hwc = 0,
+
+    #[enum_value(name = "CHW: Channels, height, width (planar).", nick = "chw")]
+    Chw = 1,
+}
+
+glib::wrapper! {
+    pub struct OnnxInference(ObjectSubclass<imp::OnnxInference>) @extends gst_video::VideoFilter, gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rsonnxinference",
+        gst::Rank::NONE,
+        OnnxInference::static_type(),
+    )
+}
@@ -0,0 +1,110 @@
+// Copyright (C) 2025 GStreamer Rust Plugins Developers
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Decoding of the outputs of SSD-style object detection models.
+
+pub const BOXES_OUTPUT: &str = "detection_boxes";
+pub const CLASSES_OUTPUT: &str = "detection_classes";
+pub const SCORES_OUTPUT: &str = "detection_scores";
+pub const NUM_DETECTIONS_OUTPUT: &str = "num_detections";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub class_id: usize,
+    pub score: f32,
+    /// Bounding box in pixels
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32,
+}
+
+/// Decode the detections of the first batch item.
+///
+/// `boxes` contains `[ymin, xmin, ymax, xmax]` for each detection, normalized to the frame
+/// size. If `num_detections` is not given, the number of scores is used instead.
+pub fn decode(
+    boxes: &[f32],
+    classes: &[f32],
+    scores: &[f32],
+    num_detections: Option<f32>,
+    score_threshold: f32,
+    width: u32,
+    height: u32,
+) -> Vec<Detection> {
+    let count = num_detections
+        .map(|n| n.max(0.0) as usize)
+        .unwrap_or(scores.len())
+        .min(scores.len())
+        .min(classes.len())
+        .min(boxes.len() / 4);
+
+    let clamp = |v: f32, max: u32| (v.clamp(0.0, 1.0) * max as f32).round() as i32;
+
+    (0..count)
+        .filter(|&i| scores[i] >= score_threshold)
+        .map(|i| {
+            let b = &boxes[i * 4..i * 4 + 4];
+            let (y1, x1) = (clamp(b[0], height), clamp(b[1], width));
+            let (y2, x2) = (clamp(b[2], height), clamp(b[3], width));
+
+            Detection {
+                class_id: classes[i].max(0.0) as usize,
+                score: scores[i],
+                x: x1.min(x2),
+                y: y1.min(y2),
+                w: (x2 - x1).abs(),
+                h: (y2 - y1).abs(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_detections() {
+        let boxes = [
+            0.1, 0.2, 0.5, 0.6, // first
+            0.0, 0.0, 1.0, 1.0, // below threshold
+            -0.5, 0.5, 0.5, 1.5, // clamped
+            0.2, 0.2, 0.3, 0.3, // beyond num_detections
+        ];
+        let classes = [1.0, 2.0, 3.0, 4.0];
+        let scores = [0.9, 0.2, 0.5, 0.9];
+
+        let detections = decode(&boxes, &classes, &scores, Some(3.0), 0.3, 200, 100);
+        assert_eq!(
+            detections,
+            vec![
+                Detection {
+                    class_id: 1,
+                    score: 0.9,
+                    x: 40,
+                    y: 10,
+                    w: 80,
+                    h: 40,
+                },
+                Detection {
+                    class_id: 3,
+                    score: 0.5,
+                    x: 100,
+                    y: 0,
+                    w: 100,
+                    h: 50,
+                },
+            ]
+        );
+
+        let detections = decode(&boxes, &classes, &scores, None, 0.3, 200, 100);
+        assert_eq!(detections.len(), 3);
+        assert_eq!(detections[2].class_id, 4);
+    }
+}
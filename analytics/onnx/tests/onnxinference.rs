@@ -0,0 +1,115 @@
+// Copyright (C) 2025 GStreamer Rust Plugins Developers
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst_analytics::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsonnx::plugin_register_static().unwrap();
+    });
+}
+
+// Path to an SSD-style object detection model with a 300x300 RGB input, e.g.
+// ssd_mobilenet_v1 from the ONNX model zoo. Tests needing a model are skipped if unset.
+fn test_model() -> Option<String> {
+    let model = std::env::var("GST_ONNX_TEST_MODEL").ok();
+    if model.is_none() {
+        println!("GST_ONNX_TEST_MODEL not set, skipping");
+    }
+
+    model
+}
+
+#[test]
+fn test_properties() {
+    init();
+
+    let element = gst::ElementFactory::make("rsonnxinference")
+        .build()
+        .unwrap();
+
+    assert_eq!(element.property::<Option<String>>("model-file"), None);
+    assert_eq!(element.property::<Option<String>>("labels-file"), None);
+    assert_eq!(
+        element.property::<gstrsonnx::OnnxInputImageFormat>("input-image-format"),
+        gstrsonnx::OnnxInputImageFormat::Hwc
+    );
+    assert_eq!(element.property::<f64>("input-tensor-offset"), 0.0);
+    assert_eq!(element.property::<f64>("input-tensor-scale"), 1.0);
+    assert_eq!(element.property::<f32>("score-threshold"), 0.3);
+}
+
+#[test]
+fn test_no_model_file() {
+    init();
+
+    let element = gst::ElementFactory::make("rsonnxinference")
+        .build()
+        .unwrap();
+
+    assert!(element.set_state(gst::State::Paused).is_err());
+    element.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_missing_model_file() {
+    init();
+
+    let element = gst::ElementFactory::make("rsonnxinference")
+        .property("model-file", "/nonexistent/model.onnx")
+        .build()
+        .unwrap();
+
+    assert!(element.set_state(gst::State::Paused).is_err());
+    element.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_inference() {
+    init();
+
+    let Some(model) = test_model() else {
+        return;
+    };
+
+    let mut h = gst_check::Harness::new("rsonnxinference");
+    h.element().unwrap().set_property("model-file", &model);
+    h.element().unwrap().set_property("score-threshold", 0.5f32);
+    h.set_src_caps_str("video/x-raw,format=RGB,width=300,height=300,framerate=30/1");
+
+    let info = gst_video::VideoInfo::builder(gst_video::VideoFormat::Rgb, 300, 300)
+        .build()
+        .unwrap();
+    let mut buffer = gst::Buffer::with_size(info.size()).unwrap();
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.map_writable().unwrap().fill(0x80);
+        buffer.set_pts(gst::ClockTime::ZERO);
+    }
+
+    let buffer = h.push_and_pull(buffer).unwrap();
+
+    // The frame itself is passed through unchanged
+    let map = buffer.map_readable().unwrap();
+    assert!(map.iter().all(|&v| v == 0x80));
+
+    // Only detections above the threshold are attached, within the frame bounds
+    if let Some(meta) = buffer.meta::<AnalyticsRelationMeta>() {
+        for od in meta.iter::<AnalyticsODMtd>() {
+            let loc = od.location().unwrap();
+            assert!(loc.loc_conf_lvl >= 0.5);
+            assert!(loc.x >= 0 && loc.y >= 0);
+            assert!(loc.x + loc.w <= 300 && loc.y + loc.h <= 300);
+        }
+    }
+}
@@ -0,0 +1,515 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "audioclicksuppressor",
+        gst::DebugColorFlags::empty(),
+        Some("Audio Click Suppressor"),
+    )
+});
+
+const DEFAULT_FADE_DURATION: gst::ClockTime = gst::ClockTime::from_mseconds(10);
+const MAX_FADE_DURATION: gst::ClockTime = gst::ClockTime::SECOND;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    pub fade_duration: gst::ClockTime,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            fade_duration: DEFAULT_FADE_DURATION,
+        }
+    }
+}
+
+struct State {
+    info: gst_audio::AudioInfo,
+    /// Length of the fade-in and fade-out in frames
+    fade_frames: usize,
+    /// Audio held back so it can be faded out if the next buffer is discontinuous, at most
+    /// `fade_frames` long
+    adapter: gst_base::UniqueAdapter,
+    /// Timestamp of the first frame after the last discontinuity
+    base_pts: Option<gst::ClockTime>,
+    /// Number of frames output since `base_pts`
+    base_frames: u64,
+    /// Position inside the fade-in after a discontinuity
+    fade_in_pos: Option<usize>,
+    /// Whether any audio was received since the start or the last flush
+    had_data: bool,
+    /// Whether the next output buffer is discontinuous
+    discont: bool,
+}
+
+impl State {
+    fn new(settings: &Settings, info: gst_audio::AudioInfo) -> Self {
+        let fade_frames = (settings.fade_duration * (info.rate() as u64)).seconds() as usize;
+
+        State {
+            info,
+            fade_frames,
+            adapter: gst_base::UniqueAdapter::new(),
+            base_pts: None,
+            base_frames: 0,
+            fade_in_pos: None,
+            had_data: false,
+            discont: true,
+        }
+    }
+
+    fn frames_to_time(&self, frames: u64) -> gst::ClockTime {
+        frames
+            .mul_div_floor(*gst::ClockTime::SECOND, self.info.rate() as u64)
+            .unwrap()
+            .nseconds()
+    }
+
+    /// Takes `frames` from the adapter and timestamps them
+    fn take(&mut self, frames: usize) -> Result<gst::Buffer, gst::FlowError> {
+        let bpf = self.info.bpf() as usize;
+        let mut buffer = self
+            .adapter
+            .take_buffer(frames * bpf)
+            .map_err(|_| gst::FlowError::Error)?;
+
+        let start = self.frames_to_time(self.base_frames);
+        self.base_frames += frames as u64;
+        let end = self.frames_to_time(self.base_frames);
+
+        {
+            let buffer = buffer.make_mut();
+            buffer.set_pts(self.base_pts.opt_add(start));
+            buffer.set_duration(end - start);
+            buffer.set_offset(gst::BUFFER_OFFSET_NONE);
+            buffer.set_offset_end(gst::BUFFER_OFFSET_NONE);
+            if self.discont {
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+            } else {
+                buffer.unset_flags(gst::BufferFlags::DISCONT);
+            }
+        }
+        self.discont = false;
+
+        Ok(buffer)
+    }
+
+    /// Queues `buffer`, fading it in if a fade-in is in progress, and returns all audio that
+    /// doesn't have to be held back anymore
+    fn push(&mut self, mut buffer: gst::Buffer) -> Result<Option<gst::Buffer>, gst::FlowError> {
+        let bpf = self.info.bpf() as usize;
+
+        if self.adapter.available() == 0 && self.base_frames == 0 {
+            self.base_pts = buffer.pts();
+        }
+
+        if let Some(pos) = self.fade_in_pos {
+            let fade_frames = self.fade_frames;
+            let n_frames = buffer.size() / bpf;
+
+            apply_gain(&self.info, buffer.make_mut(), |idx| {
+                ((pos + idx) as f64 / fade_frames as f64).min(1.0)
+            })?;
+
+            self.fade_in_pos = Some(pos + n_frames).filter(|pos| *pos < fade_frames);
+        }
+
+        self.had_data = true;
+        self.adapter.push(buffer);
+
+        let available = self.adapter.available() / bpf;
+        if available <= self.fade_frames {
+            return Ok(None);
+        }
+
+        self.take(available - self.fade_frames).map(Some)
+    }
+
+    /// Outputs all held back audio, faded out if requested
+    fn drain(&mut self, fade_out: bool) -> Result<Option<gst::Buffer>, gst::FlowError> {
+        let frames = self.adapter.available() / self.info.bpf() as usize;
+        if frames == 0 {
+            return Ok(None);
+        }
+
+        let mut buffer = self.take(frames)?;
+        if fade_out {
+            apply_gain(&self.info, buffer.make_mut(), |idx| {
+                (frames - idx - 1) as f64 / frames as f64
+            })?;
+        }
+
+        Ok(Some(buffer))
+    }
+
+    /// Fades out the held back audio and starts a fade-in for the audio after the
+    /// discontinuity
+    fn discontinuity(&mut self) -> Result<Option<gst::Buffer>, gst::FlowError> {
+        let buffer = self.drain(true)?;
+
+        if self.had_data && self.fade_frames > 0 {
+            self.fade_in_pos = Some(0);
+        }
+        self.base_pts = None;
+        self.base_frames = 0;
+        self.discont = true;
+
+        Ok(buffer)
+    }
+}
+
+/// Multiplies every frame of `buffer` with the gain returned by `gain` for its index
+fn apply_gain(
+    info: &gst_audio::AudioInfo,
+    buffer: &mut gst::BufferRef,
+    gain: impl Fn(usize) -> f64,
+) -> Result<(), gst::FlowError> {
+    fn apply<T: Copy + ToPrimitive + FromPrimitive>(
+        data: &mut [T],
+        channels: usize,
+        gain: impl Fn(usize) -> f64,
+    ) {
+        for (idx, frame) in data.chunks_exact_mut(channels).enumerate() {
+            let gain = gain(idx);
+            for sample in frame {
+                *sample = T::from_f64(sample.to_f64().unwrap() * gain).unwrap();
+            }
+        }
+    }
+
+    let channels = info.channels() as usize;
+    let mut map = buffer.map_writable().map_err(|_| gst::FlowError::Error)?;
+
+    match info.format() {
+        gst_audio::AUDIO_FORMAT_F64 => apply(map.as_mut_slice_of::<f64>().unwrap(), channels, gain),
+        gst_audio::AUDIO_FORMAT_F32 => apply(map.as_mut_slice_of::<f32>().unwrap(), channels, gain),
+        gst_audio::AUDIO_FORMAT_S32 => apply(map.as_mut_slice_of::<i32>().unwrap(), channels, gain),
+        gst_audio::AUDIO_FORMAT_S16 => apply(map.as_mut_slice_of::<i16>().unwrap(), channels, gain),
+        _ => return Err(gst::FlowError::NotNegotiated),
+    }
+
+    Ok(())
+}
+
+pub struct AudioClickSuppressor {
+    sinkpad: gst::Pad,
+    srcpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+impl AudioClickSuppressor {
+    fn push_buffers(&self, buffers: Vec<gst::Buffer>) -> Result<gst::FlowSuccess, gst::FlowError> {
+        for buffer in buffers {
+            gst::log!(CAT, imp = self, "Outputting buffer {:?}", buffer);
+            self.srcpad.push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_chain(
+        &self,
+        _pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, imp = self, "Handling buffer {:?}", buffer);
+
+        let mut state_guard = self.state.lock().unwrap();
+        let state = match *state_guard {
+            None => {
+                gst::error!(CAT, imp = self, "Not negotiated yet");
+                return Err(gst::FlowError::NotNegotiated);
+            }
+            Some(ref mut state) => state,
+        };
+
+        let mut outbufs = vec![];
+        if buffer.flags().contains(gst::BufferFlags::DISCONT) {
+            gst::debug!(CAT, imp = self, "Fading around discontinuity");
+            outbufs.extend(state.discontinuity()?);
+        }
+        outbufs.extend(state.push(buffer)?);
+        drop(state_guard);
+
+        self.push_buffers(outbufs)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {:?}", event);
+
+        let res = match event.view() {
+            EventView::Caps(c) => {
+                let caps = c.caps();
+                gst::info!(CAT, obj = pad, "Got caps {:?}", caps);
+
+                let info = match gst_audio::AudioInfo::from_caps(caps) {
+                    Ok(info) => info,
+                    Err(_) => {
+                        gst::error!(CAT, obj = pad, "Failed to parse caps");
+                        return false;
+                    }
+                };
+
+                let mut state = self.state.lock().unwrap();
+                if state.as_ref().is_some_and(|state| state.info == info) {
+                    Ok(None)
+                } else {
+                    let mut new_state = State::new(&self.settings.lock().unwrap(), info);
+
+                    // Audio in the old format is faded out and the audio in the new format faded
+                    // in
+                    let res = if let Some(ref mut state) = *state {
+                        let res = state.discontinuity();
+                        if state.had_data && new_state.fade_frames > 0 {
+                            new_state.fade_in_pos = Some(0);
+                        }
+                        res
+                    } else {
+                        Ok(None)
+                    };
+                    *state = Some(new_state);
+
+                    res
+                }
+            }
+            EventView::Segment(_) | EventView::Gap(_) => {
+                let mut state = self.state.lock().unwrap();
+                match *state {
+                    Some(ref mut state) => state.discontinuity(),
+                    None => Ok(None),
+                }
+            }
+            EventView::Eos(_) => {
+                let mut state = self.state.lock().unwrap();
+                match *state {
+                    Some(ref mut state) => state.drain(false),
+                    None => Ok(None),
+                }
+            }
+            EventView::FlushStop(_) => {
+                // Resetting our whole state
+                let mut state = self.state.lock().unwrap();
+
+                if let Some(info) = state.as_ref().map(|s| s.info.clone()) {
+                    let settings = *self.settings.lock().unwrap();
+                    *state = Some(State::new(&settings, info));
+                }
+
+                Ok(None)
+            }
+            _ => Ok(None),
+        };
+
+        match res {
+            Ok(Some(outbuf)) => {
+                if let Err(err) = self.push_buffers(vec![outbuf]) {
+                    gst::error!(CAT, imp = self, "Failed to push drained data: {}", err);
+
+                    return false;
+                }
+            }
+            Ok(None) => (),
+            Err(err) => {
+                gst::error!(CAT, imp = self, "Failed to drain data: {}", err);
+
+                return false;
+            }
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    #[allow(clippy::single_match)]
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj = pad, "Handling query {:?}", query);
+        match query.view_mut() {
+            QueryViewMut::Latency(q) => {
+                let mut peer_query = gst::query::Latency::new();
+                if self.sinkpad.peer_query(&mut peer_query) {
+                    let fade_duration = self.settings.lock().unwrap().fade_duration;
+                    let (live, min_latency, max_latency) = peer_query.result();
+                    q.set(
+                        live,
+                        min_latency + fade_duration,
+                        max_latency.opt_add(fade_duration),
+                    );
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AudioClickSuppressor {
+    const NAME: &'static str = "GstAudioClickSuppressor";
+    type Type = super::AudioClickSuppressor;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                Self::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Self::catch_panic_pad_function(parent, || false, |this| this.sink_event(pad, event))
+            })
+            .flags(gst::PadFlags::PROXY_CAPS)
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                Self::catch_panic_pad_function(parent, || false, |this| this.src_query(pad, query))
+            })
+            .flags(gst::PadFlags::PROXY_CAPS)
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            settings: Mutex::new(Default::default()),
+            state: Mutex::new(None),
+        }
+    }
+}
+
+impl ObjectImpl for AudioClickSuppressor {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![glib::ParamSpecUInt64::builder("fade-duration")
+                .nick("Fade Duration")
+                .blurb("Duration of the fade-out before and fade-in after a discontinuity in nanoseconds, also the latency of the element")
+                .maximum(MAX_FADE_DURATION.nseconds())
+                .default_value(DEFAULT_FADE_DURATION.nseconds())
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "fade-duration" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.fade_duration = value.get::<u64>().unwrap().nseconds();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "fade-duration" => {
+                let settings = self.settings.lock().unwrap();
+                settings.fade_duration.nseconds().to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for AudioClickSuppressor {}
+
+impl ElementImpl for AudioClickSuppressor {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Audio click suppressor",
+                "Filter/Effect/Audio",
+                "Fades out and in around discontinuities to avoid clicks when switching sources",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format_list([
+                    gst_audio::AUDIO_FORMAT_F32,
+                    gst_audio::AUDIO_FORMAT_F64,
+                    gst_audio::AUDIO_FORMAT_S16,
+                    gst_audio::AUDIO_FORMAT_S32,
+                ])
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let res = self.parent_change_state(transition);
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                // Drop state
+                *self.state.lock().unwrap() = None;
+            }
+            _ => (),
+        }
+
+        res
+    }
+}
@@ -0,0 +1,655 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_audio::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_transform::BaseTransformImplExt;
+use gst_base::subclass::base_transform::GenerateOutputSuccess;
+
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "audioresampler",
+        gst::DebugColorFlags::empty(),
+        Some("Adaptive audio resampler"),
+    )
+});
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstAudioResamplerQuality")]
+pub(crate) enum Quality {
+    #[enum_value(name = "Linear interpolation", nick = "linear")]
+    Linear = 0,
+    #[enum_value(name = "Cubic interpolation", nick = "cubic")]
+    Cubic = 1,
+    #[enum_value(name = "Windowed sinc interpolation", nick = "sinc")]
+    Sinc = 2,
+}
+
+const SINC_HALF_WIDTH: usize = 8;
+// Slightly below Nyquist to leave room for the transition band of the filter
+const SINC_CUTOFF: f64 = 0.95;
+
+impl Quality {
+    /// Number of input frames needed on each side of the interpolated position
+    fn half_width(self) -> usize {
+        match self {
+            Quality::Linear => 1,
+            Quality::Cubic => 2,
+            Quality::Sinc => SINC_HALF_WIDTH,
+        }
+    }
+
+    /// Fills the filter weights for the input frames `1 - half_width ..= half_width`
+    /// around the interpolated position, `frac` being the fractional part of it
+    fn weights(self, frac: f64, weights: &mut [f64]) {
+        match self {
+            Quality::Linear => {
+                weights[0] = 1.0 - frac;
+                weights[1] = frac;
+            }
+            Quality::Cubic => {
+                // Catmull-Rom spline
+                let frac2 = frac * frac;
+                let frac3 = frac2 * frac;
+                weights[0] = 0.5 * (-frac3 + 2.0 * frac2 - frac);
+                weights[1] = 0.5 * (3.0 * frac3 - 5.0 * frac2 + 2.0);
+                weights[2] = 0.5 * (-3.0 * frac3 + 4.0 * frac2 + frac);
+                weights[3] = 0.5 * (frac3 - frac2);
+            }
+            Quality::Sinc => {
+                let half_width = SINC_HALF_WIDTH as f64;
+                let mut sum = 0.0;
+                for (i, weight) in weights.iter_mut().enumerate() {
+                    let x = (i as f64 + 1.0 - half_width) - frac;
+                    let sinc = if x == 0.0 {
+                        1.0
+                    } else {
+                        let x = std::f64::consts::PI * SINC_CUTOFF * x;
+                        x.sin() / x
+                    };
+                    // Blackman window over [-half_width, half_width]
+                    let t = std::f64::consts::PI * (x / half_width + 1.0);
+                    let window = 0.42 - 0.5 * t.cos() + 0.08 * (2.0 * t).cos();
+
+                    *weight = sinc * window;
+                    sum += *weight;
+                }
+
+                // Normalize for unity gain at DC
+                for weight in weights.iter_mut() {
+                    *weight /= sum;
+                }
+            }
+        }
+    }
+}
+
+const DEFAULT_QUALITY: Quality = Quality::Cubic;
+const DEFAULT_RATE_RATIO: f64 = 0.0;
+const DEFAULT_MAX_DEVIATION: u32 = 1000;
+const DEFAULT_TRACKING_PERIOD: gst::ClockTime = gst::ClockTime::from_seconds(10);
+
+#[derive(Debug, Clone)]
+struct Settings {
+    quality: Quality,
+    reference_clock: Option<gst::Clock>,
+    rate_ratio: f64,
+    max_deviation: u32,
+    tracking_period: gst::ClockTime,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            quality: DEFAULT_QUALITY,
+            reference_clock: None,
+            rate_ratio: DEFAULT_RATE_RATIO,
+            max_deviation: DEFAULT_MAX_DEVIATION,
+            tracking_period: DEFAULT_TRACKING_PERIOD,
+        }
+    }
+}
+
+/// Measures the rate at which frames arrive against the reference clock
+#[derive(Debug, Default)]
+struct RateTracker {
+    // Reference clock time at the start of the current measurement window
+    window_start: Option<gst::ClockTime>,
+    // Number of frames that arrived since then
+    window_frames: u64,
+}
+
+impl RateTracker {
+    /// Returns the ratio between the measured and the nominal rate once per period
+    fn update(
+        &mut self,
+        now: gst::ClockTime,
+        frames: u64,
+        rate: u32,
+        period: gst::ClockTime,
+    ) -> Option<f64> {
+        let Some(window_start) = self.window_start else {
+            // The frames of the first buffer were captured before the window started
+            self.window_start = Some(now);
+            self.window_frames = 0;
+            return None;
+        };
+
+        self.window_frames += frames;
+
+        let elapsed = now.saturating_sub(window_start);
+        if elapsed < period {
+            return None;
+        }
+
+        let nominal_frames = elapsed.nseconds() as f64 * rate as f64 / 1_000_000_000.0;
+        let measured = self.window_frames as f64 / nominal_frames;
+
+        self.window_start = Some(now);
+        self.window_frames = 0;
+
+        Some(measured)
+    }
+}
+
+struct State {
+    info: gst_audio::AudioInfo,
+    quality: Quality,
+    // Interleaved input frames that were not consumed yet, preceded by the
+    // frames needed by the filter for the next output frame
+    history: Vec<f32>,
+    // Position of the next output frame in `history`, in frames
+    pos: f64,
+    // Number of input frames consumed per output frame
+    ratio: f64,
+    // Ratio that `ratio` is slowly converging to
+    target_ratio: Option<f64>,
+    tracker: RateTracker,
+    // Timestamp of the first output frame since the last discontinuity
+    base_pts: Option<gst::ClockTime>,
+    out_frames: u64,
+    weights: Vec<f64>,
+}
+
+impl State {
+    fn new(info: gst_audio::AudioInfo, quality: Quality) -> Self {
+        let half_width = quality.half_width();
+        let mut state = State {
+            history: Vec::new(),
+            pos: 0.0,
+            ratio: 1.0,
+            target_ratio: None,
+            tracker: RateTracker::default(),
+            base_pts: None,
+            out_frames: 0,
+            weights: vec![0.0; 2 * half_width],
+            info,
+            quality,
+        };
+        state.reset();
+
+        state
+    }
+
+    fn reset(&mut self) {
+        let half_width = self.quality.half_width();
+        let channels = self.info.channels() as usize;
+
+        // Silence before the first frame so that the first output frame
+        // corresponds to the first input frame
+        self.history.clear();
+        self.history.resize((half_width - 1) * channels, 0.0);
+        self.pos = (half_width - 1) as f64;
+        self.tracker = RateTracker::default();
+        self.base_pts = None;
+        self.out_frames = 0;
+    }
+
+    fn history_frames(&self) -> usize {
+        self.history.len() / self.info.channels() as usize
+    }
+
+    fn frames_to_time(&self, frames: u64) -> Option<gst::ClockTime> {
+        frames
+            .mul_div_round(*gst::ClockTime::SECOND, self.info.rate() as u64)
+            .map(gst::ClockTime::from_nseconds)
+    }
+
+    /// Produces all output frames that can be interpolated from the history,
+    /// without going past `end` if given
+    fn process(&mut self, end: Option<f64>) -> Vec<f32> {
+        let channels = self.info.channels() as usize;
+        let half_width = self.quality.half_width();
+        let history_frames = self.history_frames();
+
+        let mut output = Vec::with_capacity(
+            ((history_frames as f64 - self.pos) / self.ratio).max(0.0) as usize * channels,
+        );
+
+        while (self.pos as usize) + half_width < history_frames
+            && end.map_or(true, |end| self.pos < end)
+        {
+            let index = self.pos as usize;
+            self.quality.weights(self.pos.fract(), &mut self.weights);
+
+            let first = (index + 1 - half_width) * channels;
+            for channel in 0..channels {
+                let sample = self
+                    .weights
+                    .iter()
+                    .enumerate()
+                    .map(|(i, weight)| self.history[first + i * channels + channel] as f64 * weight)
+                    .sum::<f64>();
+                output.push(sample as f32);
+            }
+
+            self.pos += self.ratio;
+        }
+
+        // Drop the frames that won't be needed anymore
+        let consumed = (self.pos as usize + 1)
+            .saturating_sub(half_width)
+            .min(history_frames);
+        self.history.drain(..consumed * channels);
+        self.pos -= consumed as f64;
+
+        output
+    }
+
+    fn output_buffer(&mut self, data: Vec<f32>) -> gst::Buffer {
+        let frames = (data.len() / self.info.channels() as usize) as u64;
+        let pts = self.base_pts.opt_add(self.frames_to_time(self.out_frames));
+        let end_pts = self
+            .base_pts
+            .opt_add(self.frames_to_time(self.out_frames + frames));
+        self.out_frames += frames;
+
+        let mut buffer = gst::Buffer::from_mut_slice(data.into_byte_vec());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(pts);
+            buffer.set_duration(end_pts.opt_checked_sub(pts).ok().flatten());
+        }
+
+        buffer
+    }
+}
+
+#[derive(Default)]
+pub struct AudioResampler {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+impl AudioResampler {
+    fn update_ratio(&self, state: &mut State, frames: u64) {
+        let settings = self.settings.lock().unwrap().clone();
+
+        if settings.rate_ratio > 0.0 {
+            state.ratio = settings.rate_ratio;
+            return;
+        }
+
+        let clock = settings.reference_clock.or_else(|| self.obj().clock());
+        let Some(now) = clock.and_then(|clock| clock.time()) else {
+            return;
+        };
+
+        let max_deviation = settings.max_deviation as f64 / 1_000_000.0;
+        if let Some(measured) =
+            state
+                .tracker
+                .update(now, frames, state.info.rate(), settings.tracking_period)
+        {
+            let measured = measured.clamp(1.0 - max_deviation, 1.0 + max_deviation);
+            let target = match state.target_ratio {
+                Some(target) => (target + measured) / 2.0,
+                None => measured,
+            };
+
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Measured rate ratio {measured}, new target {target}"
+            );
+            state.target_ratio = Some(target);
+        }
+
+        // Slew towards the target over the tracking period to avoid audible jumps
+        if let Some(target) = state.target_ratio {
+            let duration = state.frames_to_time(frames).unwrap_or(gst::ClockTime::ZERO);
+            let step =
+                (duration.nseconds() as f64 / settings.tracking_period.nseconds() as f64).min(1.0);
+            state.ratio += (target - state.ratio) * step;
+            state.ratio = state.ratio.clamp(1.0 - max_deviation, 1.0 + max_deviation);
+
+            gst::trace!(CAT, imp = self, "Current rate ratio {}", state.ratio);
+        }
+    }
+
+    fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state_lock = self.state.lock().unwrap();
+        let Some(state) = state_lock.as_mut() else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        // Pad with silence so that the remaining frames can be interpolated
+        let end = state.history_frames() as f64;
+        let channels = state.info.channels() as usize;
+        let padding = state.quality.half_width() * channels;
+        state.history.resize(state.history.len() + padding, 0.0);
+
+        let output = state.process(Some(end));
+        let buffer = (!output.is_empty()).then(|| state.output_buffer(output));
+        state.reset();
+        drop(state_lock);
+
+        if let Some(buffer) = buffer {
+            gst::debug!(CAT, imp = self, "Draining {buffer:?}");
+            self.obj().src_pad().push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn generate_output(
+        &self,
+        state: &mut State,
+        buffer: gst::Buffer,
+    ) -> Result<GenerateOutputSuccess, gst::FlowError> {
+        let channels = state.info.channels() as usize;
+        let frames = (buffer.size() / state.info.bpf() as usize) as u64;
+
+        if state.base_pts.is_none() {
+            // Align the output timestamps with the first input frame
+            state.base_pts = buffer.pts();
+        }
+
+        self.update_ratio(state, frames);
+
+        {
+            let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+            let data = map
+                .as_slice_of::<f32>()
+                .map_err(|_| gst::FlowError::Error)?;
+            state
+                .history
+                .extend_from_slice(&data[..frames as usize * channels]);
+        }
+
+        let output = state.process(None);
+        if output.is_empty() {
+            return Ok(GenerateOutputSuccess::NoOutput);
+        }
+
+        let outbuf = state.output_buffer(output);
+        gst::trace!(CAT, imp = self, "Produced {outbuf:?}");
+
+        Ok(GenerateOutputSuccess::Buffer(outbuf))
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AudioResampler {
+    const NAME: &'static str = "GstAudioResampler";
+    type Type = super::AudioResampler;
+    type ParentType = gst_audio::AudioFilter;
+}
+
+impl ObjectImpl for AudioResampler {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("quality", DEFAULT_QUALITY)
+                    .nick("Quality")
+                    .blurb("Interpolation used for resampling")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecObject::builder::<gst::Clock>("reference-clock")
+                    .nick("Reference Clock")
+                    .blurb("Clock to track the rate of the incoming audio against (NULL = pipeline clock)")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecDouble::builder("rate-ratio")
+                    .nick("Rate Ratio")
+                    .blurb("Fixed ratio between the input and output rate (0 = track the reference clock)")
+                    .minimum(0.0)
+                    .maximum(2.0)
+                    .default_value(DEFAULT_RATE_RATIO)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-deviation")
+                    .nick("Max Deviation")
+                    .blurb("Maximum deviation of the tracked rate from the nominal rate in ppm")
+                    .minimum(1)
+                    .maximum(100_000)
+                    .default_value(DEFAULT_MAX_DEVIATION)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("tracking-period")
+                    .nick("Tracking Period")
+                    .blurb("Period in nanoseconds over which the rate is measured and adjusted")
+                    .minimum(gst::ClockTime::SECOND.nseconds())
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_TRACKING_PERIOD.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("current-ratio")
+                    .nick("Current Ratio")
+                    .blurb("Ratio between the input and output rate currently in use")
+                    .minimum(0.0)
+                    .maximum(2.0)
+                    .default_value(1.0)
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "quality" => {
+                settings.quality = value.get().expect("type checked upstream");
+            }
+            "reference-clock" => {
+                settings.reference_clock = value.get().expect("type checked upstream");
+            }
+            "rate-ratio" => {
+                let rate_ratio = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp = self,
+                    "Changing rate ratio from {} to {}",
+                    settings.rate_ratio,
+                    rate_ratio
+                );
+                settings.rate_ratio = rate_ratio;
+            }
+            "max-deviation" => {
+                settings.max_deviation = value.get().expect("type checked upstream");
+            }
+            "tracking-period" => {
+                settings.tracking_period = value.get::<u64>().unwrap().nseconds();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "current-ratio" => self
+                .state
+                .lock()
+                .unwrap()
+                .as_ref()
+                .map_or(1.0, |state| state.ratio)
+                .to_value(),
+            name => {
+                let settings = self.settings.lock().unwrap();
+
+                match name {
+                    "quality" => settings.quality.to_value(),
+                    "reference-clock" => settings.reference_clock.to_value(),
+                    "rate-ratio" => settings.rate_ratio.to_value(),
+                    "max-deviation" => settings.max_deviation.to_value(),
+                    "tracking-period" => settings.tracking_period.nseconds().to_value(),
+                    _ => unimplemented!(),
+                }
+            }
+        }
+    }
+}
+
+impl GstObjectImpl for AudioResampler {}
+
+impl ElementImpl for AudioResampler {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Adaptive Audio Resampler",
+                "Filter/Converter/Audio",
+                "Resamples audio to compensate for the drift against a reference clock",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+}
+
+impl BaseTransformImpl for AudioResampler {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::NeverInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn generate_output(&self) -> Result<GenerateOutputSuccess, gst::FlowError> {
+        let Some(buffer) = self.take_queued_buffer() else {
+            return Ok(GenerateOutputSuccess::NoOutput);
+        };
+
+        if buffer.flags().contains(gst::BufferFlags::DISCONT) {
+            self.drain()?;
+        }
+
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(
+                self,
+                gst::CoreError::Negotiation,
+                ["Can not generate an output without State"]
+            );
+            gst::FlowError::NotNegotiated
+        })?;
+
+        self.generate_output(state, buffer)
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        match event.view() {
+            EventView::Eos(_) => {
+                gst::debug!(CAT, imp = self, "Handling EOS");
+                if self.drain().is_err() {
+                    return false;
+                }
+            }
+            EventView::FlushStop(_) => {
+                if let Some(state) = self.state.lock().unwrap().as_mut() {
+                    state.reset();
+                }
+            }
+            _ => (),
+        }
+
+        self.parent_sink_event(event)
+    }
+
+    fn query(&self, direction: gst::PadDirection, query: &mut gst::QueryRef) -> bool {
+        if direction == gst::PadDirection::Src {
+            if let gst::QueryViewMut::Latency(q) = query.view_mut() {
+                let mut upstream_query = gst::query::Latency::new();
+                if self.obj().sink_pad().peer_query(&mut upstream_query) {
+                    let (live, mut min, mut max) = upstream_query.result();
+
+                    // Frames after the interpolated position are needed for each output frame
+                    let latency = self
+                        .state
+                        .lock()
+                        .unwrap()
+                        .as_ref()
+                        .and_then(|state| state.frames_to_time(state.quality.half_width() as u64))
+                        .unwrap_or(gst::ClockTime::ZERO);
+                    gst::debug!(
+                        CAT,
+                        imp = self,
+                        "Peer latency: live {} min {} max {}, own latency {}",
+                        live,
+                        min,
+                        max.display(),
+                        latency,
+                    );
+
+                    min += latency;
+                    max = max.opt_add(latency);
+                    q.set(live, min, max);
+                    return true;
+                }
+            }
+        }
+
+        BaseTransformImplExt::parent_query(self, direction, query)
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        // Drop state
+        let _ = self.state.lock().unwrap().take();
+
+        Ok(())
+    }
+}
+
+impl AudioFilterImpl for AudioResampler {
+    fn allowed_caps() -> &'static gst::Caps {
+        static CAPS: LazyLock<gst::Caps> = LazyLock::new(|| {
+            gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_F32)
+                .build()
+        });
+
+        &CAPS
+    }
+
+    fn setup(&self, info: &gst_audio::AudioInfo) -> Result<(), gst::LoggableError> {
+        // Flush previous state
+        if self.state.lock().unwrap().is_some() {
+            self.drain().map_err(|e| {
+                gst::loggable_error!(CAT, "Error flushing previous state data {:?}", e)
+            })?;
+        }
+
+        gst::debug!(CAT, imp = self, "Set caps to {:?}", info);
+
+        let quality = self.settings.lock().unwrap().quality;
+        *self.state.lock().unwrap() = Some(State::new(info.clone(), quality));
+
+        Ok(())
+    }
+}
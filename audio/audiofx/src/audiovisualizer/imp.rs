@@ -0,0 +1,462 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_pbutils::subclass::prelude::*;
+
+use std::collections::VecDeque;
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use byte_slice_cast::*;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "audiovisualizer",
+        gst::DebugColorFlags::empty(),
+        Some("Spectrogram and waveform audio visualizer"),
+    )
+});
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRsAudioVisualizerMode")]
+pub(crate) enum Mode {
+    #[enum_value(name = "Scrolling spectrogram", nick = "spectrogram")]
+    Spectrogram = 0,
+    #[enum_value(name = "Scrolling waveform", nick = "waveform")]
+    Waveform = 1,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRsAudioVisualizerColormap")]
+pub(crate) enum Colormap {
+    #[enum_value(name = "Grayscale", nick = "grayscale")]
+    Grayscale = 0,
+    #[enum_value(name = "Black, red, yellow and white", nick = "heat")]
+    Heat = 1,
+    #[enum_value(name = "Perceptually uniform blue, green and yellow", nick = "viridis")]
+    Viridis = 2,
+}
+
+impl Colormap {
+    /// Color of an intensity between 0.0 and 1.0, as 0x00RRGGBB
+    fn color(self, intensity: f32) -> u32 {
+        let stops: &[[f32; 3]] = match self {
+            Colormap::Grayscale => &[[0.0, 0.0, 0.0], [1.0, 1.0, 1.0]],
+            Colormap::Heat => &[
+                [0.0, 0.0, 0.0],
+                [0.5, 0.0, 0.0],
+                [1.0, 0.0, 0.0],
+                [1.0, 0.5, 0.0],
+                [1.0, 1.0, 0.0],
+                [1.0, 1.0, 1.0],
+            ],
+            Colormap::Viridis => &[
+                [0.267, 0.005, 0.329],
+                [0.283, 0.141, 0.458],
+                [0.254, 0.265, 0.530],
+                [0.207, 0.372, 0.553],
+                [0.164, 0.471, 0.558],
+                [0.128, 0.567, 0.551],
+                [0.135, 0.659, 0.518],
+                [0.267, 0.749, 0.441],
+                [0.478, 0.821, 0.318],
+                [0.741, 0.873, 0.150],
+                [0.993, 0.906, 0.144],
+            ],
+        };
+
+        let pos = intensity.clamp(0.0, 1.0) * (stops.len() - 1) as f32;
+        let idx = (pos as usize).min(stops.len() - 2);
+        let frac = pos - idx as f32;
+
+        let [r, g, b] = std::array::from_fn(|c| {
+            let value = stops[idx][c] + (stops[idx + 1][c] - stops[idx][c]) * frac;
+            (value * 255.0).round() as u32
+        });
+
+        (r << 16) | (g << 8) | b
+    }
+}
+
+const DEFAULT_MODE: Mode = Mode::Spectrogram;
+const DEFAULT_COLORMAP: Colormap = Colormap::Heat;
+const DEFAULT_WINDOW_SIZE: u32 = 1024;
+const MIN_WINDOW_SIZE: u32 = 64;
+const MAX_WINDOW_SIZE: u32 = 16384;
+// Level mapped to the lowest intensity of the spectrogram
+const MIN_DB: f32 = -90.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    mode: Mode,
+    colormap: Colormap,
+    window_size: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            mode: DEFAULT_MODE,
+            colormap: DEFAULT_COLORMAP,
+            window_size: DEFAULT_WINDOW_SIZE,
+        }
+    }
+}
+
+/// In-place iterative radix-2 FFT, the length of `re` and `im` must be a power of two
+fn fft(re: &mut [f32], im: &mut [f32]) {
+    let n = re.len();
+
+    // Bit-reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+
+        if i < j {
+            re.swap(i, j);
+            im.swap(i, j);
+        }
+    }
+
+    let mut len = 2;
+    while len <= n {
+        let (w_im, w_re) = (-2.0 * std::f32::consts::PI / len as f32).sin_cos();
+
+        for start in (0..n).step_by(len) {
+            let (mut cur_re, mut cur_im) = (1.0f32, 0.0f32);
+
+            for a in start..start + len / 2 {
+                let b = a + len / 2;
+                let t_re = re[b] * cur_re - im[b] * cur_im;
+                let t_im = re[b] * cur_im + im[b] * cur_re;
+                re[b] = re[a] - t_re;
+                im[b] = im[a] - t_im;
+                re[a] += t_re;
+                im[a] += t_im;
+
+                (cur_re, cur_im) = (cur_re * w_re - cur_im * w_im, cur_re * w_im + cur_im * w_re);
+            }
+        }
+
+        len <<= 1;
+    }
+}
+
+struct State {
+    channels: usize,
+    width: usize,
+    height: usize,
+    /// Current picture as 0x00RRGGBB, scrolled by one column per rendered frame
+    canvas: Vec<u32>,
+    /// Last `window_size` samples, downmixed to mono
+    samples: VecDeque<f32>,
+    /// Hann window for the spectrogram
+    window: Vec<f32>,
+    re: Vec<f32>,
+    im: Vec<f32>,
+}
+
+impl State {
+    fn new(channels: usize, width: usize, height: usize, window_size: usize) -> Self {
+        State {
+            channels,
+            width,
+            height,
+            canvas: vec![0; width * height],
+            samples: VecDeque::with_capacity(window_size),
+            window: (0..window_size)
+                .map(|i| {
+                    let x = std::f32::consts::PI * i as f32 / (window_size - 1) as f32;
+                    x.sin().powi(2)
+                })
+                .collect(),
+            re: vec![0.0; window_size],
+            im: vec![0.0; window_size],
+        }
+    }
+
+    /// Draws the spectrum of the last `window_size` samples in the last column, with the highest
+    /// frequency at the top
+    fn draw_spectrogram_column(&mut self, colormap: Colormap) {
+        let window_size = self.window.len();
+
+        let padding = window_size - self.samples.len();
+        for (i, (re, im)) in self.re.iter_mut().zip(self.im.iter_mut()).enumerate() {
+            *re = if i < padding {
+                0.0
+            } else {
+                self.samples[i - padding] * self.window[i]
+            };
+            *im = 0.0;
+        }
+
+        fft(&mut self.re, &mut self.im);
+
+        let bins = window_size / 2;
+        // Hann window has a coherent gain of 0.5
+        let scale = 4.0 / window_size as f32;
+        for y in 0..self.height {
+            let bin = ((self.height - 1 - y) * bins / self.height).min(bins - 1);
+            let magnitude = self.re[bin].hypot(self.im[bin]) * scale;
+            let db = 20.0 * magnitude.max(1e-9).log10();
+
+            self.canvas[y * self.width + self.width - 1] = colormap.color((db - MIN_DB) / -MIN_DB);
+        }
+    }
+
+    /// Draws the range of the samples of the last frame in the last column
+    fn draw_waveform_column(&mut self, colormap: Colormap, min: f32, max: f32) {
+        let to_row = |v: f32| ((1.0 - v.clamp(-1.0, 1.0)) * 0.5 * (self.height - 1) as f32).round();
+        let (top, bottom) = (to_row(max) as usize, to_row(min) as usize);
+
+        let background = colormap.color(0.0);
+        let foreground = colormap.color(1.0);
+        for y in 0..self.height {
+            self.canvas[y * self.width + self.width - 1] = if (top..=bottom).contains(&y) {
+                foreground
+            } else {
+                background
+            };
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct AudioVisualizer {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AudioVisualizer {
+    const NAME: &'static str = "GstRsAudioVisualizer";
+    type Type = super::AudioVisualizer;
+    type ParentType = gst_pbutils::AudioVisualizer;
+}
+
+impl ObjectImpl for AudioVisualizer {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("mode", DEFAULT_MODE)
+                    .nick("Mode")
+                    .blurb("Visualization to render")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("colormap", DEFAULT_COLORMAP)
+                    .nick("Colormap")
+                    .blurb("Colors used to render the intensities")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("window-size")
+                    .nick("Window Size")
+                    .blurb("Number of samples analyzed for each column of the spectrogram, rounded up to a power of two")
+                    .minimum(MIN_WINDOW_SIZE)
+                    .maximum(MAX_WINDOW_SIZE)
+                    .default_value(DEFAULT_WINDOW_SIZE)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "mode" => {
+                settings.mode = value.get().expect("type checked upstream");
+            }
+            "colormap" => {
+                settings.colormap = value.get().expect("type checked upstream");
+            }
+            "window-size" => {
+                settings.window_size = value
+                    .get::<u32>()
+                    .expect("type checked upstream")
+                    .next_power_of_two();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "mode" => settings.mode.to_value(),
+            "colormap" => settings.colormap.to_value(),
+            "window-size" => settings.window_size.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for AudioVisualizer {}
+
+impl ElementImpl for AudioVisualizer {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Audio Visualizer",
+                "Visualization",
+                "Renders a scrolling spectrogram or waveform of an audio stream",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format(if cfg!(target_endian = "little") {
+                    gst_video::VideoFormat::Bgrx
+                } else {
+                    gst_video::VideoFormat::Xrgb
+                })
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst_audio::AudioCapsBuilder::new_interleaved()
+                .format(gst_audio::AUDIO_FORMAT_S16)
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let res = self.parent_change_state(transition);
+
+        if transition == gst::StateChange::PausedToReady {
+            *self.state.lock().unwrap() = None;
+        }
+
+        res
+    }
+}
+
+impl AudioVisualizerImpl for AudioVisualizer {
+    fn render(
+        &self,
+        audio_buffer: &gst::BufferRef,
+        video_frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    ) -> Result<(), gst::LoggableError> {
+        let settings = *self.settings.lock().unwrap();
+        let width = video_frame.width() as usize;
+        let height = video_frame.height() as usize;
+        let window_size = settings.window_size as usize;
+
+        let mut state_guard = self.state.lock().unwrap();
+        if !state_guard.as_ref().is_some_and(|state| {
+            state.width == width && state.height == height && state.window.len() == window_size
+        }) {
+            let caps = self
+                .obj()
+                .static_pad("sink")
+                .unwrap()
+                .current_caps()
+                .ok_or_else(|| gst::loggable_error!(CAT, "No audio caps"))?;
+            let info = gst_audio::AudioInfo::from_caps(&caps)
+                .map_err(|_| gst::loggable_error!(CAT, "Invalid audio caps {caps}"))?;
+
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Rendering {width}x{height} from {} channels",
+                info.channels()
+            );
+            *state_guard = Some(State::new(
+                info.channels() as usize,
+                width,
+                height,
+                window_size,
+            ));
+        }
+        let state = state_guard.as_mut().unwrap();
+
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+
+        let map = audio_buffer
+            .map_readable()
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to map audio buffer"))?;
+        let samples = map
+            .as_slice_of::<i16>()
+            .map_err(|_| gst::loggable_error!(CAT, "Invalid audio buffer size"))?;
+
+        let (mut min, mut max) = (0.0f32, 0.0f32);
+        for frame in samples.chunks_exact(state.channels) {
+            let sample =
+                frame.iter().map(|s| *s as f32 / 32768.0).sum::<f32>() / state.channels as f32;
+            min = min.min(sample);
+            max = max.max(sample);
+
+            if state.samples.len() == window_size {
+                state.samples.pop_front();
+            }
+            state.samples.push_back(sample);
+        }
+
+        for row in state.canvas.chunks_exact_mut(width) {
+            row.copy_within(1.., 0);
+        }
+
+        match settings.mode {
+            Mode::Spectrogram => state.draw_spectrogram_column(settings.colormap),
+            Mode::Waveform => state.draw_waveform_column(settings.colormap, min, max),
+        }
+
+        let stride = video_frame.plane_stride()[0] as usize;
+        let data = video_frame
+            .plane_data_mut(0)
+            .map_err(|_| gst::loggable_error!(CAT, "Failed to map video frame"))?;
+        for (row, line) in state
+            .canvas
+            .chunks_exact(width)
+            .zip(data.chunks_mut(stride))
+        {
+            for (pixel, dest) in row.iter().zip(line.chunks_exact_mut(4)) {
+                dest.copy_from_slice(&pixel.to_ne_bytes());
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct AudioVisualizer(ObjectSubclass<imp::AudioVisualizer>) @extends gst_pbutils::AudioVisualizer, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    {
+        imp::Mode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        imp::Colormap::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
+
+    gst::Element::register(
+        Some(plugin),
+        "audiovisualizer",
+        gst::Rank::NONE,
+        AudioVisualizer::static_type(),
+    )
+}
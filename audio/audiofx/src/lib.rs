@@ -14,15 +14,21 @@
  */
 use gst::glib;
 
+mod audioclicksuppressor;
 mod audioecho;
 mod audioloudnorm;
+mod audioresampler;
 mod audiornnoise;
+mod audiovisualizer;
 mod ebur128level;
 mod hrtfrender;
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    audioclicksuppressor::register(plugin)?;
     audioecho::register(plugin)?;
     audioloudnorm::register(plugin)?;
+    audioresampler::register(plugin)?;
+    audiovisualizer::register(plugin)?;
     audiornnoise::register(plugin)?;
     ebur128level::register(plugin)?;
     hrtfrender::register(plugin)?;
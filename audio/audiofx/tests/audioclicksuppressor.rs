@@ -0,0 +1,195 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+// With a fade duration of 10ms this results in fades of 10 frames
+const RATE: u32 = 1000;
+const FADE_FRAMES: usize = 10;
+
+fn setup_harness() -> gst_check::Harness {
+    let mut h = gst_check::Harness::new("audioclicksuppressor");
+    h.element()
+        .unwrap()
+        .set_property("fade-duration", 10_000_000u64);
+
+    let caps = gst_audio::AudioInfo::builder(gst_audio::AUDIO_FORMAT_F32, RATE, 1)
+        .build()
+        .unwrap()
+        .to_caps()
+        .unwrap();
+    h.set_src_caps(caps);
+    h.play();
+
+    h
+}
+
+fn constant_buffer(pts: gst::ClockTime, frames: usize, discont: bool) -> gst::Buffer {
+    let mut buffer = gst::Buffer::from_mut_slice(vec![1.0f32; frames].into_byte_vec());
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(pts);
+        if discont {
+            buffer.set_flags(gst::BufferFlags::DISCONT);
+        }
+    }
+
+    buffer
+}
+
+fn pull_buffers(h: &mut gst_check::Harness) -> Vec<(gst::ClockTime, Vec<f32>, bool)> {
+    let mut buffers = Vec::new();
+    while let Some(buffer) = h.try_pull() {
+        let map = buffer.map_readable().unwrap();
+        buffers.push((
+            buffer.pts().unwrap(),
+            map.as_slice_of::<f32>().unwrap().to_vec(),
+            buffer.flags().contains(gst::BufferFlags::DISCONT),
+        ));
+    }
+
+    buffers
+}
+
+fn assert_samples(samples: &[f32], expected: impl Iterator<Item = f32>) {
+    let expected = expected.collect::<Vec<_>>();
+    assert_eq!(samples.len(), expected.len());
+    for (i, (sample, expected)) in samples.iter().zip(expected).enumerate() {
+        assert!(
+            (sample - expected).abs() < 1e-6,
+            "{i}: {sample} != {expected}"
+        );
+    }
+}
+
+#[test]
+fn test_continuous() {
+    init();
+
+    let mut h = setup_harness();
+
+    assert_eq!(
+        h.push(constant_buffer(gst::ClockTime::ZERO, 100, true)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        h.push(constant_buffer(
+            gst::ClockTime::from_mseconds(100),
+            100,
+            false
+        )),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    h.push_event(gst::event::Eos::new());
+
+    // Audio is only delayed, the initial discontinuity is not faded
+    let buffers = pull_buffers(&mut h);
+    assert_eq!(buffers.len(), 3);
+    let mut pts = gst::ClockTime::ZERO;
+    for (i, (buffer_pts, samples, discont)) in buffers.into_iter().enumerate() {
+        assert_eq!(buffer_pts, pts);
+        assert_eq!(discont, i == 0);
+        assert_samples(&samples, std::iter::repeat(1.0).take(samples.len()));
+        pts += gst::ClockTime::from_mseconds(samples.len() as u64);
+    }
+    assert_eq!(pts, gst::ClockTime::from_mseconds(200));
+}
+
+#[test]
+fn test_discont() {
+    init();
+
+    let mut h = setup_harness();
+
+    assert_eq!(
+        h.push(constant_buffer(gst::ClockTime::ZERO, 100, false)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        h.push(constant_buffer(
+            gst::ClockTime::from_mseconds(500),
+            100,
+            true
+        )),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    let buffers = pull_buffers(&mut h);
+    assert_eq!(buffers.len(), 3);
+
+    assert_eq!(buffers[0].0, gst::ClockTime::ZERO);
+    assert_samples(
+        &buffers[0].1,
+        std::iter::repeat(1.0).take(100 - FADE_FRAMES),
+    );
+
+    // Fade-out before the discontinuity
+    assert_eq!(buffers[1].0, gst::ClockTime::from_mseconds(90));
+    assert_samples(
+        &buffers[1].1,
+        (0..FADE_FRAMES).map(|i| (FADE_FRAMES - i - 1) as f32 / FADE_FRAMES as f32),
+    );
+    assert!(!buffers[1].2);
+
+    // Fade-in after the discontinuity
+    assert_eq!(buffers[2].0, gst::ClockTime::from_mseconds(500));
+    assert_samples(
+        &buffers[2].1,
+        (0..100 - FADE_FRAMES).map(|i| (i as f32 / FADE_FRAMES as f32).min(1.0)),
+    );
+    assert!(buffers[2].2);
+}
+
+#[test]
+fn test_segment_change() {
+    init();
+
+    let mut h = setup_harness();
+
+    assert_eq!(
+        h.push(constant_buffer(gst::ClockTime::ZERO, 100, false)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    let _ = pull_buffers(&mut h);
+
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    assert!(h.push_event(gst::event::Segment::new(&segment)));
+
+    // The held back audio is faded out before the new segment
+    let buffers = pull_buffers(&mut h);
+    assert_eq!(buffers.len(), 1);
+    assert_samples(
+        &buffers[0].1,
+        (0..FADE_FRAMES).map(|i| (FADE_FRAMES - i - 1) as f32 / FADE_FRAMES as f32),
+    );
+
+    assert_eq!(
+        h.push(constant_buffer(gst::ClockTime::ZERO, 20, false)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    h.push_event(gst::event::Eos::new());
+
+    let samples = pull_buffers(&mut h)
+        .into_iter()
+        .flat_map(|(_, samples, _)| samples)
+        .collect::<Vec<_>>();
+    assert_samples(
+        &samples,
+        (0..20).map(|i| (i as f32 / FADE_FRAMES as f32).min(1.0)),
+    );
+}
@@ -0,0 +1,175 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+const RATE: u32 = 48000;
+
+fn setup_harness(quality: &str, properties: &[(&str, &dyn ToValue)]) -> gst_check::Harness {
+    let mut builder =
+        gst::ElementFactory::make("audioresampler").property_from_str("quality", quality);
+    for (name, value) in properties {
+        builder = builder.property(name, value.to_value());
+    }
+    let resampler = builder.build().unwrap();
+
+    let mut h = gst_check::Harness::with_element(&resampler, Some("sink"), Some("src"));
+    let caps = gst_audio::AudioInfo::builder(gst_audio::AUDIO_FORMAT_F32, RATE, 2)
+        .build()
+        .unwrap()
+        .to_caps()
+        .unwrap();
+    h.set_caps(caps.clone(), caps);
+    h.play();
+
+    h
+}
+
+fn ramp_buffer(start: usize, frames: usize) -> gst::Buffer {
+    let data = (start..start + frames)
+        .flat_map(|i| {
+            let sample = i as f32 / 100_000.0;
+            [sample, -sample]
+        })
+        .collect::<Vec<f32>>();
+
+    let mut buffer = gst::Buffer::from_mut_slice(data.into_byte_vec());
+    buffer
+        .get_mut()
+        .unwrap()
+        .set_pts(gst::ClockTime::from_nseconds(
+            start as u64 * *gst::ClockTime::SECOND / RATE as u64,
+        ));
+
+    buffer
+}
+
+fn pull_samples(h: &mut gst_check::Harness) -> Vec<f32> {
+    let mut samples = Vec::new();
+    while let Some(buffer) = h.try_pull() {
+        let map = buffer.map_readable().unwrap();
+        samples.extend_from_slice(map.as_slice_of::<f32>().unwrap());
+    }
+
+    samples
+}
+
+#[test]
+fn test_unity_ratio() {
+    init();
+
+    for quality in ["linear", "cubic", "sinc"] {
+        let mut h = setup_harness(quality, &[("rate-ratio", &1.0f64)]);
+
+        let frames = 480;
+        for i in 0..10 {
+            assert_eq!(
+                h.push(ramp_buffer(i * frames, frames)),
+                Ok(gst::FlowSuccess::Ok)
+            );
+        }
+        h.push_event(gst::event::Eos::new());
+
+        let first = h.pull().unwrap();
+        assert_eq!(first.pts(), Some(gst::ClockTime::ZERO));
+
+        let mut samples = first
+            .map_readable()
+            .unwrap()
+            .as_slice_of::<f32>()
+            .unwrap()
+            .to_vec();
+        samples.extend(pull_samples(&mut h));
+
+        // All frames are output once, and a ramp is preserved by all interpolations
+        assert_eq!(samples.len(), 10 * frames * 2, "{quality}");
+        if quality != "sinc" {
+            for (i, frame) in samples.chunks(2).enumerate() {
+                let expected = i as f32 / 100_000.0;
+                assert!((frame[0] - expected).abs() < 1e-6, "{quality}: {i}");
+                assert!((frame[1] + expected).abs() < 1e-6, "{quality}: {i}");
+            }
+        }
+    }
+}
+
+#[test]
+fn test_fixed_ratio() {
+    init();
+
+    let mut h = setup_harness("linear", &[("rate-ratio", &1.01f64)]);
+
+    let frames = 4800;
+    for i in 0..10 {
+        assert_eq!(
+            h.push(ramp_buffer(i * frames, frames)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let samples = pull_samples(&mut h);
+    let expected_frames = (10 * frames) as f64 / 1.01;
+    assert!(
+        ((samples.len() / 2) as f64 - expected_frames).abs() <= 1.0,
+        "{} frames instead of {expected_frames}",
+        samples.len() / 2
+    );
+
+    // Each output frame is interpolated at a position 1.01 frames further,
+    // except for the last one which is interpolated with the padding
+    for (i, frame) in samples.chunks(2).take(samples.len() / 2 - 1).enumerate() {
+        let expected = (i as f64 * 1.01 / 100_000.0) as f32;
+        assert!((frame[0] - expected).abs() < 1e-5, "{i}");
+    }
+}
+
+#[test]
+fn test_clock_tracking() {
+    init();
+
+    let clock = gst_check::TestClock::new();
+    let mut h = setup_harness(
+        "cubic",
+        &[
+            ("reference-clock", &clock),
+            ("tracking-period", &gst::ClockTime::SECOND.nseconds()),
+            ("max-deviation", &5000u32),
+        ],
+    );
+
+    // The producer runs about 0.2% faster than the reference clock
+    let frames = 481;
+    for i in 0..3000 {
+        assert_eq!(
+            h.push(ramp_buffer(i * frames, frames)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+        clock.advance_time(gst::ClockTime::from_mseconds(10).nseconds() as i64);
+    }
+
+    let ratio = h.element().unwrap().property::<f64>("current-ratio");
+    assert!((ratio - 481.0 / 480.0).abs() < 1e-5, "{ratio}");
+
+    // About 480 frames are output per 10ms of the reference clock
+    let out_frames = pull_samples(&mut h).len() / 2;
+    assert!(
+        out_frames.abs_diff(3000 * 480) < 3000 * 480 / 1000,
+        "{out_frames}"
+    );
+}
@@ -0,0 +1,104 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+use byte_slice_cast::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsaudiofx::plugin_register_static().expect("Failed to register rsaudiofx plugin");
+    });
+}
+
+const RATE: u32 = 44100;
+const WIDTH: u32 = 64;
+const HEIGHT: u32 = 48;
+
+fn setup_harness(mode: &str) -> gst_check::Harness {
+    let visualizer = gst::ElementFactory::make("audiovisualizer")
+        .property_from_str("mode", mode)
+        .property("window-size", 256u32)
+        .build()
+        .unwrap();
+
+    let mut h = gst_check::Harness::with_element(&visualizer, Some("sink"), Some("src"));
+    let audio_caps = gst_audio::AudioInfo::builder(gst_audio::AUDIO_FORMAT_S16, RATE, 1)
+        .build()
+        .unwrap()
+        .to_caps()
+        .unwrap();
+    let video_caps = gst_video::VideoCapsBuilder::new()
+        .format(if cfg!(target_endian = "little") {
+            gst_video::VideoFormat::Bgrx
+        } else {
+            gst_video::VideoFormat::Xrgb
+        })
+        .width(WIDTH as i32)
+        .height(HEIGHT as i32)
+        .framerate(gst::Fraction::new(25, 1))
+        .build();
+    h.set_caps(audio_caps, video_caps);
+    h.play();
+
+    h
+}
+
+fn sine_buffer(frames: usize) -> gst::Buffer {
+    let data = (0..frames)
+        .map(|i| {
+            let t = i as f32 / RATE as f32;
+            ((2.0 * std::f32::consts::PI * 1000.0 * t).sin() * 16384.0) as i16
+        })
+        .collect::<Vec<i16>>();
+
+    let mut buffer = gst::Buffer::from_mut_slice(data.into_byte_vec());
+    buffer.get_mut().unwrap().set_pts(gst::ClockTime::ZERO);
+
+    buffer
+}
+
+fn check_frames(mode: &str) {
+    let mut h = setup_harness(mode);
+
+    assert_eq!(h.push(sine_buffer(RATE as usize)), Ok(gst::FlowSuccess::Ok));
+
+    let mut frames = 0;
+    let mut last = None;
+    while let Some(buffer) = h.try_pull() {
+        assert_eq!(buffer.size(), (WIDTH * HEIGHT * 4) as usize);
+        frames += 1;
+        last = Some(buffer);
+    }
+    assert!(frames > 0);
+
+    // The newest column contains the rendered audio and differs from the background
+    let last = last.unwrap();
+    let map = last.map_readable().unwrap();
+    let pixels = map.as_slice_of::<u32>().unwrap();
+    let column = (0..HEIGHT as usize)
+        .map(|y| pixels[y * WIDTH as usize + WIDTH as usize - 1])
+        .collect::<Vec<u32>>();
+    assert!(column.iter().any(|pixel| *pixel != column[0]));
+}
+
+#[test]
+fn test_spectrogram() {
+    init();
+
+    check_frames("spectrogram");
+}
+
+#[test]
+fn test_waveform() {
+    init();
+
+    check_frames("waveform");
+}
@@ -57,7 +57,7 @@ impl Settings {
                 .build(),
             glib::ParamSpecString::builder("track")
                 .nick("Spotify URI")
-                .blurb("Spotify track URI, in the form 'spotify:track:$SPOTIFY_ID'")
+                .blurb("Spotify URI, in the form 'spotify:track:$SPOTIFY_ID', or 'spotify:album:$SPOTIFY_ID' and 'spotify:playlist:$SPOTIFY_ID' for spotifyaudiosrc")
                 .default_value(Some(""))
                 .mutable_ready()
                 .build(),
@@ -7,7 +7,9 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
+use anyhow::Context;
 use futures::future::{AbortHandle, Abortable};
 use std::sync::LazyLock;
 use tokio::{runtime, task::JoinHandle};
@@ -17,6 +19,11 @@ use gst::prelude::*;
 use gst::subclass::prelude::*;
 use gst_base::subclass::{base_src::CreateSuccess, prelude::*};
 
+use librespot_core::{
+    session::Session,
+    spotify_id::{SpotifyId, SpotifyItemType},
+};
+use librespot_metadata::{Album, Metadata, Playlist, Track};
 use librespot_playback::{
     audio_backend::{Sink, SinkResult},
     config::PlayerConfig,
@@ -26,7 +33,7 @@ use librespot_playback::{
     player::{Player, PlayerEvent},
 };
 
-use super::Bitrate;
+use super::{Bitrate, RepeatMode};
 use crate::common::SetupThread;
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
@@ -61,10 +68,30 @@ struct State {
     player_channel_handle: JoinHandle<()>,
 }
 
-#[derive(Default)]
+const DEFAULT_PREFETCH_TIME: u32 = 10_000;
+const DEFAULT_SHUFFLE: bool = false;
+const DEFAULT_AUTOPLAY: bool = false;
+
 struct Settings {
     common: crate::common::Settings,
     bitrate: Bitrate,
+    prefetch_time: u32,
+    shuffle: bool,
+    repeat_mode: RepeatMode,
+    autoplay: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            common: Default::default(),
+            bitrate: Default::default(),
+            prefetch_time: DEFAULT_PREFETCH_TIME,
+            shuffle: DEFAULT_SHUFFLE,
+            repeat_mode: Default::default(),
+            autoplay: DEFAULT_AUTOPLAY,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -95,6 +122,40 @@ impl ObjectImpl for SpotifyAudioSrc {
                     .mutable_ready()
                     .build(),
             );
+            props.push(
+                glib::ParamSpecUInt::builder("prefetch-time")
+                    .nick("Prefetch time")
+                    .blurb("When playing an album or playlist, time in ms before the end of the current track at which the next track is fetched, or 0 to disable prefetching")
+                    .default_value(DEFAULT_PREFETCH_TIME)
+                    .mutable_ready()
+                    .build(),
+            );
+            props.push(
+                glib::ParamSpecBoolean::builder("shuffle")
+                    .nick("Shuffle")
+                    .blurb("When playing an album or playlist, play its tracks in random order")
+                    .default_value(DEFAULT_SHUFFLE)
+                    .mutable_ready()
+                    .build(),
+            );
+            props.push(
+                glib::ParamSpecEnum::builder_with_default::<RepeatMode>(
+                    "repeat-mode",
+                    default.repeat_mode,
+                )
+                .nick("Repeat mode")
+                .blurb("Whether to repeat the current track or the whole album or playlist")
+                .mutable_ready()
+                .build(),
+            );
+            props.push(
+                glib::ParamSpecBoolean::builder("autoplay")
+                    .nick("Autoplay")
+                    .blurb("Once the track, album or playlist is over, keep playing tracks from the Spotify radio of the last one")
+                    .default_value(DEFAULT_AUTOPLAY)
+                    .mutable_ready()
+                    .build(),
+            );
             props
         });
 
@@ -108,6 +169,18 @@ impl ObjectImpl for SpotifyAudioSrc {
             "bitrate" => {
                 settings.bitrate = value.get().expect("type checked upstream");
             }
+            "prefetch-time" => {
+                settings.prefetch_time = value.get().expect("type checked upstream");
+            }
+            "shuffle" => {
+                settings.shuffle = value.get().expect("type checked upstream");
+            }
+            "repeat-mode" => {
+                settings.repeat_mode = value.get().expect("type checked upstream");
+            }
+            "autoplay" => {
+                settings.autoplay = value.get().expect("type checked upstream");
+            }
             _ => settings.common.set_property(value, pspec),
         }
     }
@@ -117,6 +190,10 @@ impl ObjectImpl for SpotifyAudioSrc {
 
         match pspec.name() {
             "bitrate" => settings.bitrate.to_value(),
+            "prefetch-time" => settings.prefetch_time.to_value(),
+            "shuffle" => settings.shuffle.to_value(),
+            "repeat-mode" => settings.repeat_mode.to_value(),
+            "autoplay" => settings.autoplay.to_value(),
             _ => settings.common.property(pspec),
         }
     }
@@ -381,19 +458,42 @@ impl SpotifyAudioSrc {
 
         let src = self.obj();
 
-        let (session, track, bitrate) = {
-            let (common, bitrate) = {
+        let (session, mut tracks, bitrate, prefetch_time, repeat_mode, autoplay) = {
+            let (common, bitrate, prefetch_time, shuffle, repeat_mode, autoplay) = {
                 let settings = self.settings.lock().unwrap();
                 let bitrate = settings.bitrate.into();
 
-                (settings.common.clone(), bitrate)
+                (
+                    settings.common.clone(),
+                    bitrate,
+                    Duration::from_millis(settings.prefetch_time.into()),
+                    settings.shuffle,
+                    settings.repeat_mode,
+                    settings.autoplay,
+                )
             };
 
             let session = common.connect_session(src.clone(), &CAT).await?;
-            let track = common.track_id()?;
+            let mut tracks = Self::context_tracks(&session, common.track_id()?).await?;
+            if shuffle {
+                shuffle_tracks(&mut tracks);
+            }
             gst::debug!(CAT, imp = self, "Requesting bitrate {:?}", bitrate);
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Playing {} track(s), shuffle: {shuffle}, repeat: {repeat_mode:?}, autoplay: {autoplay}",
+                tracks.len()
+            );
 
-            (session, track, bitrate)
+            (
+                session,
+                tracks,
+                bitrate,
+                prefetch_time,
+                repeat_mode,
+                autoplay,
+            )
         };
 
         let player_config = PlayerConfig {
@@ -406,27 +506,155 @@ impl SpotifyAudioSrc {
         let (sender, receiver) = mpsc::sync_channel(2);
         let sender_clone = sender.clone();
 
-        let player = Player::new(player_config, session, Box::new(NoOpVolume), || {
+        let player = Player::new(player_config, session.clone(), Box::new(NoOpVolume), || {
             Box::new(BufferSink { sender })
         });
         let mut player_event_channel = player.get_player_event_channel();
 
-        player.load(track, true, 0);
+        player.load(tracks[0], true, 0);
 
+        let player_clone = player.clone();
+        let self_ = self.obj().downgrade();
         let player_channel_handle = RUNTIME.spawn(async move {
             let sender = sender_clone;
+            let player = player_clone;
+            let mut current = 0;
+            let mut prefetch_handle: Option<JoinHandle<()>> = None;
+            // consecutive unavailable tracks, to stop when none of them can be played
+            let mut n_unavailable = 0;
 
             while let Some(event) = player_event_channel.recv().await {
                 match event {
-                    PlayerEvent::EndOfTrack { .. } => {
-                        let _ = sender.send(Message::Eos);
+                    PlayerEvent::Playing {
+                        track_id,
+                        position_ms,
+                        ..
+                    } => {
+                        // Playing is also emitted when resuming, re-schedule the prefetch
+                        if let Some(handle) = prefetch_handle.take() {
+                            handle.abort();
+                        }
+                        n_unavailable = 0;
+
+                        let Some(next) = next_track(current, tracks.len(), repeat_mode) else {
+                            continue;
+                        };
+                        let next = tracks[next];
+                        if prefetch_time.is_zero() {
+                            continue;
+                        }
+
+                        prefetch_handle = Some(RUNTIME.spawn({
+                            let session = session.clone();
+                            let player = player.clone();
+                            let self_ = self_.clone();
+                            async move {
+                                let remaining = match Track::get(&session, &track_id).await {
+                                    Ok(track) => Duration::from_millis(
+                                        (track.duration.max(0) as u32).saturating_sub(position_ms)
+                                            as u64,
+                                    ),
+                                    Err(err) => {
+                                        if let Some(src) = self_.upgrade() {
+                                            gst::warning!(
+                                                CAT,
+                                                obj = src,
+                                                "Failed to retrieve track duration: {err:?}"
+                                            );
+                                        }
+                                        return;
+                                    }
+                                };
+
+                                tokio::time::sleep(remaining.saturating_sub(prefetch_time)).await;
+
+                                if let Some(src) = self_.upgrade() {
+                                    gst::debug!(CAT, obj = src, "Prefetching next track {next:?}");
+                                }
+                                player.preload(next);
+                            }
+                        }));
                     }
-                    PlayerEvent::Unavailable { .. } => {
-                        let _ = sender.send(Message::Unavailable);
+                    PlayerEvent::EndOfTrack { .. } | PlayerEvent::Unavailable { .. } => {
+                        let unavailable = matches!(event, PlayerEvent::Unavailable { .. });
+                        if let Some(handle) = prefetch_handle.take() {
+                            handle.abort();
+                        }
+
+                        let next = if unavailable {
+                            n_unavailable += 1;
+                            if let Some(src) = self_.upgrade() {
+                                gst::warning!(
+                                    CAT,
+                                    obj = src,
+                                    "Track {:?} is not available, skipping",
+                                    tracks[current]
+                                );
+                            }
+
+                            // don't repeat a track which can't be played
+                            let repeat_mode = match repeat_mode {
+                                RepeatMode::Track => RepeatMode::Context,
+                                other => other,
+                            };
+                            next_track(current, tracks.len(), repeat_mode)
+                                .filter(|_| n_unavailable < tracks.len())
+                        } else {
+                            next_track(current, tracks.len(), repeat_mode)
+                        };
+
+                        let next = match next {
+                            Some(next) => Some(next),
+                            None if autoplay => {
+                                match Self::radio_tracks(&session, tracks[current]).await {
+                                    Ok(radio) => {
+                                        if let Some(src) = self_.upgrade() {
+                                            gst::debug!(
+                                                CAT,
+                                                obj = src,
+                                                "Autoplaying {} track(s) from radio",
+                                                radio.len()
+                                            );
+                                        }
+                                        tracks.extend(radio);
+                                        n_unavailable = 0;
+                                        Some(current + 1)
+                                    }
+                                    Err(err) => {
+                                        if let Some(src) = self_.upgrade() {
+                                            gst::warning!(
+                                                CAT,
+                                                obj = src,
+                                                "Failed to retrieve radio tracks: {err:?}"
+                                            );
+                                        }
+                                        None
+                                    }
+                                }
+                            }
+                            None => None,
+                        };
+
+                        match next {
+                            Some(next) => {
+                                current = next;
+                                player.load(tracks[current], true, 0);
+                            }
+                            None if unavailable => {
+                                let _ = sender.send(Message::Unavailable);
+                            }
+                            None => {
+                                let _ = sender.send(Message::Eos);
+                            }
+                        }
                     }
                     _ => {}
                 }
             }
+
+            if let Some(handle) = prefetch_handle.take() {
+                handle.abort();
+            }
         });
 
         let mut state = self.state.lock().unwrap();
@@ -439,4 +667,63 @@ impl SpotifyAudioSrc {
 
         Ok(())
     }
+
+    /// Retrieve the tracks to play for a track, album or playlist URI
+    async fn context_tracks(session: &Session, id: SpotifyId) -> anyhow::Result<Vec<SpotifyId>> {
+        let tracks = match id.item_type {
+            SpotifyItemType::Track => vec![id],
+            SpotifyItemType::Album => Album::get(session, &id).await?.tracks().copied().collect(),
+            SpotifyItemType::Playlist => Playlist::get(session, &id)
+                .await?
+                .tracks()
+                .copied()
+                .collect(),
+            other => anyhow::bail!("unsupported Spotify URI type {other:?}"),
+        };
+
+        if tracks.is_empty() {
+            anyhow::bail!("no tracks to play");
+        }
+
+        Ok(tracks)
+    }
+
+    /// Retrieve the tracks of the Spotify radio seeded by `track`
+    async fn radio_tracks(session: &Session, track: SpotifyId) -> anyhow::Result<Vec<SpotifyId>> {
+        let reply = session.spclient().get_radio_for_track(&track).await?;
+        let reply: serde_json::Value = serde_json::from_slice(&reply)?;
+        let uri = reply["mediaItems"][0]["uri"]
+            .as_str()
+            .context("no radio for track")?;
+
+        let tracks = Self::context_tracks(session, SpotifyId::from_uri(uri)?)
+            .await?
+            .into_iter()
+            .filter(|id| *id != track)
+            .collect::<Vec<_>>();
+
+        if tracks.is_empty() {
+            anyhow::bail!("empty radio for track");
+        }
+
+        Ok(tracks)
+    }
+}
+
+/// Index of the track to play after `current`, if any
+fn next_track(current: usize, n_tracks: usize, repeat_mode: RepeatMode) -> Option<usize> {
+    match repeat_mode {
+        RepeatMode::Track => Some(current),
+        _ if current + 1 < n_tracks => Some(current + 1),
+        RepeatMode::Context => Some(0),
+        RepeatMode::None => None,
+    }
+}
+
+fn shuffle_tracks(tracks: &mut [SpotifyId]) {
+    // Fisher-Yates
+    for i in (1..tracks.len()).rev() {
+        let j = glib::random_int_range(0, i as i32 + 1) as usize;
+        tracks.swap(i, j);
+    }
 }
@@ -39,6 +39,19 @@ impl From<Bitrate> for librespot_playback::config::Bitrate {
     }
 }
 
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRsSpotifyRepeatMode")]
+enum RepeatMode {
+    #[default]
+    #[enum_value(name = "Do not repeat", nick = "none")]
+    None,
+    #[enum_value(name = "Repeat the whole album or playlist", nick = "context")]
+    Context,
+    #[enum_value(name = "Repeat the current track", nick = "track")]
+    Track,
+}
+
 glib::wrapper! {
     pub struct SpotifyAudioSrc(ObjectSubclass<imp::SpotifyAudioSrc>) @extends gst_base::PushSrc, gst_base::BaseSrc, gst::Element, gst::Object, @implements gst::URIHandler;
 }
@@ -46,6 +59,8 @@ glib::wrapper! {
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     #[cfg(feature = "doc")]
     Bitrate::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    #[cfg(feature = "doc")]
+    RepeatMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
 
     gst::Element::register(
         Some(plugin),
@@ -1,6 +1,7 @@
 import os
 
 DIRS = [
+    'analytics',
     'audio',
     'generic',
     'mux',
@@ -25,6 +26,7 @@ RS_PREFIXED = [
     'rtsp',
     'inter',
     'relationmeta',
+    'onnx',
 ]
 
 OVERRIDE = {
@@ -23,6 +23,7 @@ mod inputselector;
 mod jitterbuffer;
 mod proxy;
 mod queue;
+mod rtpsession;
 pub mod socket;
 mod tcpclientsrc;
 mod udpsink;
@@ -37,6 +38,7 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     jitterbuffer::register(plugin)?;
     proxy::register(plugin)?;
     queue::register(plugin)?;
+    rtpsession::register(plugin)?;
     tcpclientsrc::register(plugin)?;
     udpsink::register(plugin)?;
     udpsrc::register(plugin)?;
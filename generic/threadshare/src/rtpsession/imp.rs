@@ -0,0 +1,1133 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Lightweight RTCP-only RTP session.
+//!
+//! The RTP packets are forwarded untouched while the element keeps the statistics needed to
+//! generate RTCP Sender and Receiver Reports and to interpret the reports of the remote peers.
+//! There is no jitterbuffer and no per-SSRC pad, which makes it suitable for forwarding servers
+//! that route the RTP packets themselves but still need to answer RTCP.
+
+use futures::future::BoxFuture;
+use futures::prelude::*;
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_rtp::RTPBuffer;
+
+use rtcp_types::*;
+
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::runtime::prelude::*;
+use crate::runtime::{self, task, timer, PadSink, PadSrc, Task};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "ts-rtpsession",
+        gst::DebugColorFlags::empty(),
+        Some("Thread-sharing RTCP-only RTP session"),
+    )
+});
+
+const DEFAULT_CONTEXT: &str = "";
+const DEFAULT_CONTEXT_WAIT: Duration = Duration::ZERO;
+const DEFAULT_RTCP_INTERVAL: Duration = Duration::from_secs(5);
+
+const RTCP_MTU: usize = 1200;
+// The report count of SR and RR is 5 bits
+const MAX_REPORT_BLOCKS: usize = 31;
+const BYE_REASON: &str = "End of Stream";
+// Seconds between 1900-01-01 and 1970-01-01
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+static RTP_CAPS: LazyLock<gst::Caps> =
+    LazyLock::new(|| gst::Caps::builder("application/x-rtp").build());
+static RTCP_CAPS: LazyLock<gst::Caps> =
+    LazyLock::new(|| gst::Caps::builder("application/x-rtcp").build());
+
+#[derive(Debug, Clone)]
+struct Settings {
+    context: String,
+    context_wait: Duration,
+    rtcp_interval: Duration,
+    cname: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            context: DEFAULT_CONTEXT.into(),
+            context_wait: DEFAULT_CONTEXT_WAIT,
+            rtcp_interval: DEFAULT_RTCP_INTERVAL,
+            cname: None,
+        }
+    }
+}
+
+/// Current wallclock time as a 32.32 fixed point NTP timestamp
+fn ntp_now() -> u64 {
+    let dur = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        + Duration::from_secs(NTP_UNIX_OFFSET);
+
+    (dur.as_secs() << 32) | ((dur.subsec_nanos() as u64) << 32) / 1_000_000_000
+}
+
+/// Middle 32 bits of a NTP timestamp, as used in report blocks
+fn ntp_short(ntp: u64) -> u32 {
+    ((ntp >> 16) & 0xffff_ffff) as u32
+}
+
+fn duration_to_ntp_short(dur: Duration) -> u32 {
+    (dur.as_secs_f64() * 65536.0) as u32
+}
+
+fn ntp_short_to_duration(ntp: u32) -> Duration {
+    Duration::from_secs_f64(ntp as f64 / 65536.0)
+}
+
+/// Last Sender Report received from a remote sender
+#[derive(Debug, Clone, Copy)]
+struct LastSr {
+    ntp_timestamp: u64,
+    arrival: Instant,
+}
+
+/// Statistics of a remote sender, see RFC 3550 Appendix A.1 and A.8
+#[derive(Debug)]
+struct ReceiveSource {
+    base_seq: u16,
+    max_seq: u16,
+    cycles: u64,
+    received: u64,
+    expected_prior: u64,
+    received_prior: u64,
+    transit: Option<u32>,
+    // Interarrival jitter in clock rate units, scaled by 16
+    jitter: u32,
+}
+
+impl ReceiveSource {
+    fn new(seq: u16) -> Self {
+        ReceiveSource {
+            base_seq: seq,
+            max_seq: seq,
+            cycles: 0,
+            received: 0,
+            expected_prior: 0,
+            received_prior: 0,
+            transit: None,
+            jitter: 0,
+        }
+    }
+
+    /// `arrival` is the arrival time of the packet in clock rate units, if the clock rate is known
+    fn packet_received(&mut self, seq: u16, rtp_ts: u32, arrival: Option<u64>) {
+        let delta = seq.wrapping_sub(self.max_seq);
+        if delta != 0 && delta < 0x8000 {
+            if seq < self.max_seq {
+                self.cycles += 1 << 16;
+            }
+            self.max_seq = seq;
+        }
+        self.received += 1;
+
+        if let Some(arrival) = arrival {
+            let transit = (arrival as u32).wrapping_sub(rtp_ts);
+            if let Some(last_transit) = self.transit {
+                let d = (transit.wrapping_sub(last_transit) as i32).unsigned_abs() as i64;
+                let jitter = self.jitter as i64;
+                self.jitter = (jitter + d - ((jitter + 8) >> 4)) as u32;
+            }
+            self.transit = Some(transit);
+        }
+    }
+
+    fn extended_max_seq(&self) -> u64 {
+        self.cycles + self.max_seq as u64
+    }
+
+    fn packets_lost(&self) -> i64 {
+        let expected = self.extended_max_seq() - self.base_seq as u64 + 1;
+        expected as i64 - self.received as i64
+    }
+
+    fn report_block(
+        &mut self,
+        ssrc: u32,
+        last_sr: Option<&LastSr>,
+        now: Instant,
+    ) -> ReportBlockBuilder {
+        let expected = self.extended_max_seq() - self.base_seq as u64 + 1;
+        let expected_interval = expected - self.expected_prior;
+        let received_interval = self.received - self.received_prior;
+        self.expected_prior = expected;
+        self.received_prior = self.received;
+
+        let lost_interval = expected_interval as i64 - received_interval as i64;
+        let fraction_lost = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / expected_interval as i64) as u8
+        };
+        let cumulative_lost =
+            (self.packets_lost().clamp(-0x80_0000, 0x7f_ffff) as i32 as u32) & 0xff_ffff;
+
+        let (lsr, dlsr) = last_sr
+            .map(|sr| {
+                (
+                    ntp_short(sr.ntp_timestamp),
+                    duration_to_ntp_short(now.saturating_duration_since(sr.arrival)),
+                )
+            })
+            .unwrap_or((0, 0));
+
+        ReportBlock::builder(ssrc)
+            .fraction_lost(fraction_lost)
+            .cumulative_lost(cumulative_lost)
+            .extended_sequence_number((self.extended_max_seq() & 0xffff_ffff) as u32)
+            .interarrival_jitter(self.jitter >> 4)
+            .last_sender_report_timestamp(lsr)
+            .delay_since_last_sender_report_timestamp(dlsr)
+    }
+}
+
+/// Report block about one of our senders received from a remote receiver
+#[derive(Debug, Clone, Copy)]
+struct ReceivedRb {
+    reporter_ssrc: u32,
+    fraction_lost: u8,
+    cumulative_lost: u32,
+    jitter: u32,
+    round_trip_time: Option<Duration>,
+}
+
+/// Statistics of a local sender
+#[derive(Debug)]
+struct SendSource {
+    packets: u64,
+    octets: u64,
+    last_rtp_ts: u32,
+    last_instant: Instant,
+    last_rb: Option<ReceivedRb>,
+}
+
+#[derive(Debug)]
+struct State {
+    // Reference for the arrival times used for the jitter calculation
+    epoch: Instant,
+    recv_clock_rate: Option<u32>,
+    send_clock_rate: Option<u32>,
+    receive_sources: HashMap<u32, ReceiveSource>,
+    sender_reports: HashMap<u32, LastSr>,
+    send_sources: HashMap<u32, SendSource>,
+    internal_ssrc: u32,
+    cname: String,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        let internal_ssrc = rand::random::<u32>();
+
+        State {
+            epoch: Instant::now(),
+            recv_clock_rate: None,
+            send_clock_rate: None,
+            receive_sources: HashMap::new(),
+            sender_reports: HashMap::new(),
+            send_sources: HashMap::new(),
+            internal_ssrc,
+            cname: format!("user{internal_ssrc:08x}@ts-rtpsession"),
+        }
+    }
+}
+
+impl State {
+    fn handle_rb(&mut self, reporter_ssrc: u32, rb: &ReportBlock<'_>, ntp_now: u64) {
+        let Some(source) = self.send_sources.get_mut(&rb.ssrc()) else {
+            return;
+        };
+
+        let lsr = rb.last_sender_report_timestamp();
+        let round_trip_time = (lsr != 0).then(|| {
+            ntp_short_to_duration(
+                ntp_short(ntp_now)
+                    .wrapping_sub(lsr)
+                    .wrapping_sub(rb.delay_since_last_sender_report_timestamp()),
+            )
+        });
+
+        source.last_rb = Some(ReceivedRb {
+            reporter_ssrc,
+            fraction_lost: rb.fraction_lost(),
+            cumulative_lost: rb.cumulative_lost(),
+            jitter: rb.interarrival_jitter(),
+            round_trip_time,
+        });
+    }
+}
+
+/// Add the report blocks that didn't fit into the first SR / RR as additional RRs from the same
+/// SSRC, which have to follow it immediately (RFC 3550 6.4.2)
+fn add_remaining_report_blocks<'a>(
+    mut rtcp: CompoundBuilder<'a>,
+    ssrc: u32,
+    report_blocks: &mut impl Iterator<Item = ReportBlockBuilder>,
+) -> CompoundBuilder<'a> {
+    let mut report_blocks = report_blocks.peekable();
+    while report_blocks.peek().is_some() {
+        let mut rr = ReceiverReport::builder(ssrc);
+        for rb in report_blocks.by_ref().take(MAX_REPORT_BLOCKS) {
+            rr = rr.add_report_block(rb);
+        }
+        rtcp = rtcp.add_packet(rr);
+    }
+    rtcp
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stream {
+    Recv,
+    Send,
+}
+
+#[derive(Clone, Debug)]
+struct RtpSinkHandler(Stream);
+
+impl RtpSinkHandler {
+    fn handle_buffer(&self, imp: &RtpSession, buffer: &gst::BufferRef) {
+        let Ok(rtp) = RTPBuffer::from_buffer_readable(buffer) else {
+            gst::warning!(CAT, imp = imp, "Forwarding invalid RTP packet {buffer:?}");
+            return;
+        };
+
+        let now = Instant::now();
+        let mut state = imp.state.lock().unwrap();
+        match self.0 {
+            Stream::Recv => {
+                let arrival = state.recv_clock_rate.map(|clock_rate| {
+                    (now.duration_since(state.epoch).as_nanos() * clock_rate as u128
+                        / 1_000_000_000) as u64
+                });
+                let seq = rtp.seq();
+                state
+                    .receive_sources
+                    .entry(rtp.ssrc())
+                    .or_insert_with(|| {
+                        gst::debug!(CAT, imp = imp, "New receive ssrc {:08x}", rtp.ssrc());
+                        ReceiveSource::new(seq)
+                    })
+                    .packet_received(seq, rtp.timestamp(), arrival);
+            }
+            Stream::Send => {
+                let source = state.send_sources.entry(rtp.ssrc()).or_insert_with(|| {
+                    gst::debug!(CAT, imp = imp, "New send ssrc {:08x}", rtp.ssrc());
+                    SendSource {
+                        packets: 0,
+                        octets: 0,
+                        last_rtp_ts: rtp.timestamp(),
+                        last_instant: now,
+                        last_rb: None,
+                    }
+                });
+                source.packets += 1;
+                source.octets += rtp.payload_size() as u64;
+                source.last_rtp_ts = rtp.timestamp();
+                source.last_instant = now;
+            }
+        }
+    }
+}
+
+impl PadSinkHandler for RtpSinkHandler {
+    type ElementImpl = RtpSession;
+
+    fn sink_chain(
+        self,
+        _pad: gst::Pad,
+        elem: super::RtpSession,
+        buffer: gst::Buffer,
+    ) -> BoxFuture<'static, Result<gst::FlowSuccess, gst::FlowError>> {
+        async move {
+            let imp = elem.imp();
+            self.handle_buffer(imp, &buffer);
+            imp.rtp_src_pad(self.0).push(buffer).await
+        }
+        .boxed()
+    }
+
+    fn sink_chain_list(
+        self,
+        _pad: gst::Pad,
+        elem: super::RtpSession,
+        list: gst::BufferList,
+    ) -> BoxFuture<'static, Result<gst::FlowSuccess, gst::FlowError>> {
+        async move {
+            let imp = elem.imp();
+            for buffer in list.iter() {
+                self.handle_buffer(imp, buffer);
+            }
+            imp.rtp_src_pad(self.0).push_list(list).await
+        }
+        .boxed()
+    }
+
+    fn sink_event(self, pad: &gst::Pad, imp: &RtpSession, event: gst::Event) -> bool {
+        gst::log!(CAT, obj = pad, "Forwarding {event:?}");
+        imp.rtp_src_pad(self.0).gst_pad().push_event(event)
+    }
+
+    fn sink_event_serialized(
+        self,
+        pad: gst::Pad,
+        elem: super::RtpSession,
+        event: gst::Event,
+    ) -> BoxFuture<'static, bool> {
+        async move {
+            let imp = elem.imp();
+
+            if let gst::EventView::Caps(c) = event.view() {
+                let clock_rate = c
+                    .caps()
+                    .structure(0)
+                    .and_then(|s| s.get::<i32>("clock-rate").ok())
+                    .filter(|clock_rate| *clock_rate > 0)
+                    .map(|clock_rate| clock_rate as u32);
+                gst::debug!(CAT, obj = pad, "Clock rate {clock_rate:?}");
+
+                let mut state = imp.state.lock().unwrap();
+                match self.0 {
+                    Stream::Recv => state.recv_clock_rate = clock_rate,
+                    Stream::Send => state.send_clock_rate = clock_rate,
+                }
+            }
+
+            gst::log!(CAT, obj = pad, "Forwarding serialized {event:?}");
+            imp.rtp_src_pad(self.0).push_event(event).await
+        }
+        .boxed()
+    }
+
+    fn sink_query(self, pad: &gst::Pad, imp: &RtpSession, query: &mut gst::QueryRef) -> bool {
+        if query.is_serialized() {
+            gst::log!(CAT, obj = pad, "Dropping serialized {query:?}");
+            false
+        } else {
+            gst::log!(CAT, obj = pad, "Forwarding {query:?}");
+            imp.rtp_src_pad(self.0).gst_pad().peer_query(query)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RtpSrcHandler(Stream);
+
+impl PadSrcHandler for RtpSrcHandler {
+    type ElementImpl = RtpSession;
+
+    fn src_event(self, pad: &gst::Pad, imp: &RtpSession, event: gst::Event) -> bool {
+        gst::log!(CAT, obj = pad, "Forwarding {event:?}");
+        imp.rtp_sink_pad(self.0).gst_pad().push_event(event)
+    }
+
+    fn src_query(self, pad: &gst::Pad, imp: &RtpSession, query: &mut gst::QueryRef) -> bool {
+        gst::log!(CAT, obj = pad, "Forwarding {query:?}");
+        imp.rtp_sink_pad(self.0).gst_pad().peer_query(query)
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RtcpSinkHandler;
+
+impl PadSinkHandler for RtcpSinkHandler {
+    type ElementImpl = RtpSession;
+
+    fn sink_chain(
+        self,
+        pad: gst::Pad,
+        elem: super::RtpSession,
+        buffer: gst::Buffer,
+    ) -> BoxFuture<'static, Result<gst::FlowSuccess, gst::FlowError>> {
+        async move {
+            let Ok(map) = buffer.map_readable() else {
+                gst::warning!(CAT, obj = pad, "Failed to map {buffer:?}");
+                return Ok(gst::FlowSuccess::Ok);
+            };
+            elem.imp().handle_rtcp(&map);
+
+            Ok(gst::FlowSuccess::Ok)
+        }
+        .boxed()
+    }
+
+    fn sink_chain_list(
+        self,
+        pad: gst::Pad,
+        elem: super::RtpSession,
+        list: gst::BufferList,
+    ) -> BoxFuture<'static, Result<gst::FlowSuccess, gst::FlowError>> {
+        async move {
+            for buffer in list.iter() {
+                let Ok(map) = buffer.map_readable() else {
+                    gst::warning!(CAT, obj = pad, "Failed to map {buffer:?}");
+                    continue;
+                };
+                elem.imp().handle_rtcp(&map);
+            }
+
+            Ok(gst::FlowSuccess::Ok)
+        }
+        .boxed()
+    }
+
+    fn sink_event(self, pad: &gst::Pad, _imp: &RtpSession, event: gst::Event) -> bool {
+        gst::log!(CAT, obj = pad, "Dropping {event:?}");
+        true
+    }
+
+    fn sink_event_serialized(
+        self,
+        pad: gst::Pad,
+        _elem: super::RtpSession,
+        event: gst::Event,
+    ) -> BoxFuture<'static, bool> {
+        gst::log!(CAT, obj = pad, "Dropping serialized {event:?}");
+        future::ready(true).boxed()
+    }
+}
+
+#[derive(Clone, Debug)]
+struct RtcpSrcHandler;
+
+impl PadSrcHandler for RtcpSrcHandler {
+    type ElementImpl = RtpSession;
+
+    fn src_event(self, pad: &gst::Pad, _imp: &RtpSession, event: gst::Event) -> bool {
+        gst::log!(CAT, obj = pad, "Dropping {event:?}");
+        false
+    }
+
+    fn src_query(self, pad: &gst::Pad, _imp: &RtpSession, query: &mut gst::QueryRef) -> bool {
+        gst::log!(CAT, obj = pad, "Handling {query:?}");
+
+        match query.view_mut() {
+            gst::QueryViewMut::Latency(q) => {
+                q.set(true, gst::ClockTime::ZERO, gst::ClockTime::NONE);
+                true
+            }
+            gst::QueryViewMut::Caps(q) => {
+                let caps = q
+                    .filter()
+                    .map(|filter| {
+                        filter.intersect_with_mode(&RTCP_CAPS, gst::CapsIntersectMode::First)
+                    })
+                    .unwrap_or_else(|| RTCP_CAPS.clone());
+                q.set_result(&caps);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RtcpTask {
+    elem: super::RtpSession,
+    interval: Duration,
+    need_initial_events: bool,
+}
+
+impl RtcpTask {
+    fn new(elem: super::RtpSession) -> Self {
+        RtcpTask {
+            elem,
+            interval: DEFAULT_RTCP_INTERVAL,
+            need_initial_events: true,
+        }
+    }
+}
+
+impl TaskImpl for RtcpTask {
+    type Item = gst::Buffer;
+
+    fn prepare(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        gst::log!(CAT, obj = self.elem, "Preparing Task");
+        self.interval = self.elem.imp().settings.lock().unwrap().rtcp_interval;
+
+        future::ok(()).boxed()
+    }
+
+    fn start(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        async move {
+            gst::log!(CAT, obj = self.elem, "Starting Task");
+
+            if self.need_initial_events {
+                let imp = self.elem.imp();
+
+                let stream_id =
+                    format!("{:08x}{:08x}", rand::random::<u32>(), rand::random::<u32>());
+                imp.rtcp_src_pad
+                    .push_event(gst::event::StreamStart::new(&stream_id))
+                    .await;
+                imp.rtcp_src_pad
+                    .push_event(gst::event::Caps::new(&RTCP_CAPS))
+                    .await;
+                imp.rtcp_src_pad
+                    .push_event(gst::event::Segment::new(&gst::FormattedSegment::<
+                        gst::format::Time,
+                    >::new()))
+                    .await;
+
+                self.need_initial_events = false;
+            }
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn stop(&mut self) -> BoxFuture<'_, Result<(), gst::ErrorMessage>> {
+        async move {
+            gst::log!(CAT, obj = self.elem, "Stopping Task");
+
+            // Say goodbye if anything was sent on the RTCP pad before
+            if !self.need_initial_events {
+                let imp = self.elem.imp();
+                if let Ok(buffer) = imp.generate_rtcp(true) {
+                    gst::debug!(CAT, imp = imp, "Pushing BYE {buffer:?}");
+                    let _ = imp.rtcp_src_pad.push(buffer).await;
+                }
+            }
+            self.need_initial_events = true;
+
+            Ok(())
+        }
+        .boxed()
+    }
+
+    fn try_next(&mut self) -> BoxFuture<'_, Result<gst::Buffer, gst::FlowError>> {
+        async move {
+            timer::delay_for_at_least(self.interval).await;
+
+            self.elem.imp().generate_rtcp(false)
+        }
+        .boxed()
+    }
+
+    fn handle_item(&mut self, buffer: gst::Buffer) -> BoxFuture<'_, Result<(), gst::FlowError>> {
+        async move {
+            let imp = self.elem.imp();
+
+            gst::log!(CAT, imp = imp, "Pushing RTCP {buffer:?}");
+            imp.rtcp_src_pad.push(buffer).await.map(drop)
+        }
+        .boxed()
+    }
+
+    fn handle_loop_error(&mut self, err: gst::FlowError) -> BoxFuture<'_, task::Trigger> {
+        async move {
+            match err {
+                gst::FlowError::Flushing => {
+                    gst::debug!(CAT, obj = self.elem, "Flushing");
+
+                    task::Trigger::FlushStart
+                }
+                gst::FlowError::Eos => {
+                    gst::debug!(CAT, obj = self.elem, "EOS");
+
+                    task::Trigger::Stop
+                }
+                err => {
+                    gst::error!(CAT, obj = self.elem, "Got error {err}");
+                    gst::element_error!(
+                        &self.elem,
+                        gst::StreamError::Failed,
+                        ("Internal data stream error"),
+                        ["streaming stopped, reason {}", err]
+                    );
+
+                    task::Trigger::Error
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+#[derive(Debug)]
+pub struct RtpSession {
+    recv_rtp_sink_pad: PadSink,
+    recv_rtp_src_pad: PadSrc,
+    recv_rtcp_sink_pad: PadSink,
+    send_rtp_sink_pad: PadSink,
+    send_rtp_src_pad: PadSrc,
+    rtcp_src_pad: PadSrc,
+    task: Task,
+    state: Mutex<State>,
+    settings: Mutex<Settings>,
+}
+
+impl RtpSession {
+    fn rtp_sink_pad(&self, stream: Stream) -> &PadSink {
+        match stream {
+            Stream::Recv => &self.recv_rtp_sink_pad,
+            Stream::Send => &self.send_rtp_sink_pad,
+        }
+    }
+
+    fn rtp_src_pad(&self, stream: Stream) -> &PadSrc {
+        match stream {
+            Stream::Recv => &self.recv_rtp_src_pad,
+            Stream::Send => &self.send_rtp_src_pad,
+        }
+    }
+
+    fn handle_rtcp(&self, data: &[u8]) {
+        let rtcp = match Compound::parse(data) {
+            Ok(rtcp) => rtcp,
+            Err(err) => {
+                gst::warning!(CAT, imp = self, "Failed to parse RTCP packet: {err:?}");
+                return;
+            }
+        };
+
+        let now = Instant::now();
+        let ntp_now = ntp_now();
+        let mut state = self.state.lock().unwrap();
+
+        for packet in rtcp {
+            match packet {
+                Ok(Packet::Sr(sr)) => {
+                    gst::trace!(CAT, imp = self, "Received SR from {:08x}", sr.ssrc());
+                    state.sender_reports.insert(
+                        sr.ssrc(),
+                        LastSr {
+                            ntp_timestamp: sr.ntp_timestamp(),
+                            arrival: now,
+                        },
+                    );
+                    for rb in sr.report_blocks() {
+                        state.handle_rb(sr.ssrc(), &rb, ntp_now);
+                    }
+                }
+                Ok(Packet::Rr(rr)) => {
+                    gst::trace!(CAT, imp = self, "Received RR from {:08x}", rr.ssrc());
+                    for rb in rr.report_blocks() {
+                        state.handle_rb(rr.ssrc(), &rb, ntp_now);
+                    }
+                }
+                Ok(Packet::Bye(bye)) => {
+                    for ssrc in bye.ssrcs() {
+                        gst::debug!(CAT, imp = self, "Received BYE from {ssrc:08x}");
+                        state.receive_sources.remove(&ssrc);
+                        state.sender_reports.remove(&ssrc);
+                    }
+                }
+                Ok(_) => (),
+                Err(err) => {
+                    gst::warning!(CAT, imp = self, "Failed to parse RTCP packet: {err:?}");
+                }
+            }
+        }
+    }
+
+    /// Generate a compound RTCP packet with a SR for each local sender, or a RR from the
+    /// internal SSRC if there is none, followed by the CNAME of all reporting SSRCs and, if `bye`
+    /// is set, a BYE for all of them
+    fn generate_rtcp(&self, bye: bool) -> Result<gst::Buffer, gst::FlowError> {
+        let now = Instant::now();
+        let ntp_now = ntp_now();
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        let mut report_blocks = state
+            .receive_sources
+            .iter_mut()
+            .map(|(ssrc, source)| source.report_block(*ssrc, state.sender_reports.get(ssrc), now))
+            .collect::<Vec<_>>()
+            .into_iter();
+
+        let mut rtcp = Compound::builder();
+        let mut sdes = Sdes::builder();
+        let mut bye_packet = Bye::builder().reason_owned(BYE_REASON);
+
+        if state.send_sources.is_empty() {
+            let mut rr = ReceiverReport::builder(state.internal_ssrc);
+            for rb in report_blocks.by_ref().take(MAX_REPORT_BLOCKS) {
+                rr = rr.add_report_block(rb);
+            }
+            rtcp = rtcp.add_packet(rr);
+            rtcp = add_remaining_report_blocks(rtcp, state.internal_ssrc, &mut report_blocks);
+            bye_packet = bye_packet.add_source(state.internal_ssrc);
+            sdes = sdes.add_chunk(
+                SdesChunk::builder(state.internal_ssrc)
+                    .add_item_owned(SdesItem::builder(SdesItem::CNAME, &state.cname)),
+            );
+        } else {
+            for (ssrc, source) in state.send_sources.iter() {
+                let rtp_timestamp = state
+                    .send_clock_rate
+                    .map(|clock_rate| {
+                        let elapsed = now.saturating_duration_since(source.last_instant);
+                        let elapsed_rtp =
+                            (elapsed.as_nanos() * clock_rate as u128 / 1_000_000_000) as u32;
+                        source.last_rtp_ts.wrapping_add(elapsed_rtp)
+                    })
+                    .unwrap_or(source.last_rtp_ts);
+
+                let mut sr = SenderReport::builder(*ssrc)
+                    .packet_count((source.packets & 0xffff_ffff) as u32)
+                    .octet_count((source.octets & 0xffff_ffff) as u32)
+                    .ntp_timestamp(ntp_now)
+                    .rtp_timestamp(rtp_timestamp);
+                // Only the first SR carries the report blocks
+                for rb in report_blocks.by_ref().take(MAX_REPORT_BLOCKS) {
+                    sr = sr.add_report_block(rb);
+                }
+                rtcp = rtcp.add_packet(sr);
+                rtcp = add_remaining_report_blocks(rtcp, *ssrc, &mut report_blocks);
+                bye_packet = bye_packet.add_source(*ssrc);
+                sdes = sdes.add_chunk(
+                    SdesChunk::builder(*ssrc)
+                        .add_item_owned(SdesItem::builder(SdesItem::CNAME, &state.cname)),
+                );
+            }
+        }
+        rtcp = rtcp.add_packet(sdes);
+        if bye {
+            rtcp = rtcp.add_packet(bye_packet);
+        }
+
+        let size = rtcp.calculate_size().map_err(|err| {
+            gst::error!(CAT, imp = self, "Failed to generate RTCP packet: {err:?}");
+            gst::FlowError::Error
+        })?;
+        if size > RTCP_MTU {
+            gst::warning!(
+                CAT,
+                imp = self,
+                "Generated RTCP packet of {size} bytes exceeds MTU"
+            );
+        }
+        let mut data = vec![0; size];
+        rtcp.write_into(&mut data).unwrap();
+
+        Ok(gst::Buffer::from_mut_slice(data))
+    }
+
+    fn stats(&self) -> gst::Structure {
+        let state = self.state.lock().unwrap();
+
+        let received = state.receive_sources.iter().map(|(ssrc, source)| {
+            gst::Structure::builder("application/x-ts-rtpsession-receive-stats")
+                .field("ssrc", *ssrc)
+                .field("packets-received", source.received)
+                .field("packets-lost", source.packets_lost())
+                .field("extended-seqnum", source.extended_max_seq())
+                .field("jitter", source.jitter >> 4)
+                .field("have-sr", state.sender_reports.contains_key(ssrc))
+                .build()
+        });
+
+        let sent = state.send_sources.iter().map(|(ssrc, source)| {
+            let mut s = gst::Structure::builder("application/x-ts-rtpsession-send-stats")
+                .field("ssrc", *ssrc)
+                .field("packets-sent", source.packets)
+                .field("octets-sent", source.octets);
+            if let Some(rb) = source.last_rb {
+                s = s
+                    .field("rb-reporter-ssrc", rb.reporter_ssrc)
+                    .field("rb-fraction-lost", rb.fraction_lost as u32)
+                    .field("rb-packets-lost", rb.cumulative_lost)
+                    .field("rb-jitter", rb.jitter)
+                    .field_if_some(
+                        "rb-round-trip-time",
+                        rb.round_trip_time
+                            .map(|rtt| gst::ClockTime::from_nseconds(rtt.as_nanos() as u64)),
+                    );
+            }
+            s.build()
+        });
+
+        gst::Structure::builder("application/x-ts-rtpsession-stats")
+            .field("internal-ssrc", state.internal_ssrc)
+            .field("received", gst::Array::new(received))
+            .field("sent", gst::Array::new(sent))
+            .build()
+    }
+
+    fn prepare(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp = self, "Preparing");
+
+        let settings = self.settings.lock().unwrap();
+        let context =
+            runtime::Context::acquire(&settings.context, settings.context_wait).map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::OpenRead,
+                    ["Failed to acquire Context: {}", err]
+                )
+            })?;
+        let cname = settings.cname.clone();
+        drop(settings);
+
+        let mut state = self.state.lock().unwrap();
+        *state = State::default();
+        if let Some(cname) = cname {
+            state.cname = cname;
+        }
+        drop(state);
+
+        self.task
+            .prepare(RtcpTask::new(self.obj().clone()), context)
+            .block_on()?;
+
+        gst::debug!(CAT, imp = self, "Prepared");
+
+        Ok(())
+    }
+
+    fn unprepare(&self) {
+        gst::debug!(CAT, imp = self, "Unpreparing");
+        self.task.unprepare().block_on().unwrap();
+        gst::debug!(CAT, imp = self, "Unprepared");
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp = self, "Stopping");
+        self.task.stop().block_on()?;
+        gst::debug!(CAT, imp = self, "Stopped");
+
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp = self, "Starting");
+        self.task.start().block_on()?;
+        gst::debug!(CAT, imp = self, "Started");
+
+        Ok(())
+    }
+
+    fn pause(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp = self, "Pausing");
+        self.task.pause().block_on()?;
+        gst::debug!(CAT, imp = self, "Paused");
+
+        Ok(())
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpSession {
+    const NAME: &'static str = "TsRtpSession";
+    type Type = super::RtpSession;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        Self {
+            recv_rtp_sink_pad: PadSink::new(
+                gst::Pad::from_template(&klass.pad_template("recv_rtp_sink").unwrap()),
+                RtpSinkHandler(Stream::Recv),
+            ),
+            recv_rtp_src_pad: PadSrc::new(
+                gst::Pad::from_template(&klass.pad_template("recv_rtp_src").unwrap()),
+                RtpSrcHandler(Stream::Recv),
+            ),
+            recv_rtcp_sink_pad: PadSink::new(
+                gst::Pad::from_template(&klass.pad_template("recv_rtcp_sink").unwrap()),
+                RtcpSinkHandler,
+            ),
+            send_rtp_sink_pad: PadSink::new(
+                gst::Pad::from_template(&klass.pad_template("send_rtp_sink").unwrap()),
+                RtpSinkHandler(Stream::Send),
+            ),
+            send_rtp_src_pad: PadSrc::new(
+                gst::Pad::from_template(&klass.pad_template("send_rtp_src").unwrap()),
+                RtpSrcHandler(Stream::Send),
+            ),
+            rtcp_src_pad: PadSrc::new(
+                gst::Pad::from_template(&klass.pad_template("send_rtcp_src").unwrap()),
+                RtcpSrcHandler,
+            ),
+            task: Task::default(),
+            state: Default::default(),
+            settings: Default::default(),
+        }
+    }
+}
+
+impl ObjectImpl for RtpSession {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecString::builder("context")
+                    .nick("Context")
+                    .blurb("Context name to share threads with")
+                    .default_value(Some(DEFAULT_CONTEXT))
+                    .build(),
+                glib::ParamSpecUInt::builder("context-wait")
+                    .nick("Context Wait")
+                    .blurb("Throttle poll loop to run at most once every this many ms")
+                    .maximum(1000)
+                    .default_value(DEFAULT_CONTEXT_WAIT.as_millis() as u32)
+                    .build(),
+                glib::ParamSpecUInt::builder("rtcp-interval")
+                    .nick("RTCP Interval")
+                    .blurb("Interval between RTCP reports in ms")
+                    .minimum(1)
+                    .default_value(DEFAULT_RTCP_INTERVAL.as_millis() as u32)
+                    .build(),
+                glib::ParamSpecString::builder("cname")
+                    .nick("CNAME")
+                    .blurb("CNAME to report in the RTCP SDES (NULL = generated)")
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Statistics about the sent and received streams")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "context" => {
+                settings.context = value
+                    .get::<Option<String>>()
+                    .unwrap()
+                    .unwrap_or_else(|| DEFAULT_CONTEXT.into());
+            }
+            "context-wait" => {
+                settings.context_wait = Duration::from_millis(value.get::<u32>().unwrap().into());
+            }
+            "rtcp-interval" => {
+                settings.rtcp_interval = Duration::from_millis(value.get::<u32>().unwrap().into());
+            }
+            "cname" => {
+                settings.cname = value.get::<Option<String>>().unwrap();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "stats" => self.stats().to_value(),
+            name => {
+                let settings = self.settings.lock().unwrap();
+                match name {
+                    "context" => settings.context.to_value(),
+                    "context-wait" => (settings.context_wait.as_millis() as u32).to_value(),
+                    "rtcp-interval" => (settings.rtcp_interval.as_millis() as u32).to_value(),
+                    "cname" => settings.cname.to_value(),
+                    _ => unimplemented!(),
+                }
+            }
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(self.recv_rtp_sink_pad.gst_pad()).unwrap();
+        obj.add_pad(self.recv_rtp_src_pad.gst_pad()).unwrap();
+        obj.add_pad(self.recv_rtcp_sink_pad.gst_pad()).unwrap();
+        obj.add_pad(self.send_rtp_sink_pad.gst_pad()).unwrap();
+        obj.add_pad(self.send_rtp_src_pad.gst_pad()).unwrap();
+        obj.add_pad(self.rtcp_src_pad.gst_pad()).unwrap();
+    }
+}
+
+impl GstObjectImpl for RtpSession {}
+
+impl ElementImpl for RtpSession {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Thread-sharing RTP session",
+                "Network/RTP",
+                "Thread-sharing RTCP-only RTP session generating and parsing sender and receiver reports",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let rtp_template = |name, direction| {
+                gst::PadTemplate::new(name, direction, gst::PadPresence::Always, &RTP_CAPS).unwrap()
+            };
+            let rtcp_template = |name, direction| {
+                gst::PadTemplate::new(name, direction, gst::PadPresence::Always, &RTCP_CAPS)
+                    .unwrap()
+            };
+
+            vec![
+                rtp_template("recv_rtp_sink", gst::PadDirection::Sink),
+                rtp_template("recv_rtp_src", gst::PadDirection::Src),
+                rtcp_template("recv_rtcp_sink", gst::PadDirection::Sink),
+                rtp_template("send_rtp_sink", gst::PadDirection::Sink),
+                rtp_template("send_rtp_src", gst::PadDirection::Src),
+                rtcp_template("send_rtcp_src", gst::PadDirection::Src),
+            ]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {transition:?}");
+
+        match transition {
+            gst::StateChange::NullToReady => {
+                self.prepare().map_err(|err| {
+                    self.post_error_message(err);
+                    gst::StateChangeError
+                })?;
+            }
+            gst::StateChange::PlayingToPaused => {
+                self.pause().map_err(|_| gst::StateChangeError)?;
+            }
+            gst::StateChange::PausedToReady => {
+                // Stop before the pads are deactivated so that the BYE can still be sent
+                self.stop().map_err(|_| gst::StateChangeError)?;
+            }
+            gst::StateChange::ReadyToNull => {
+                self.unprepare();
+            }
+            _ => (),
+        }
+
+        let mut success = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                self.pause().map_err(|_| gst::StateChangeError)?;
+                success = gst::StateChangeSuccess::NoPreroll;
+            }
+            gst::StateChange::PausedToPlaying => {
+                self.start().map_err(|_| gst::StateChangeError)?;
+            }
+            gst::StateChange::PlayingToPaused => {
+                success = gst::StateChangeSuccess::NoPreroll;
+            }
+            _ => (),
+        }
+
+        Ok(success)
+    }
+}
@@ -0,0 +1,279 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use rtcp_types::*;
+
+const SSRC: u32 = 0x1234_5678;
+const SENDER_SSRC: u32 = 0x8765_4321;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstthreadshare::plugin_register_static().expect("gstthreadshare rtpsession test");
+    });
+}
+
+fn rtp_buffer(ssrc: u32, seq: u16, ts: u32) -> gst::Buffer {
+    let mut data = vec![0x80, 96];
+    data.extend_from_slice(&seq.to_be_bytes());
+    data.extend_from_slice(&ts.to_be_bytes());
+    data.extend_from_slice(&ssrc.to_be_bytes());
+    data.extend_from_slice(&[0; 16]);
+
+    gst::Buffer::from_mut_slice(data)
+}
+
+fn rtcp_buffer(rtcp: CompoundBuilder) -> gst::Buffer {
+    let mut data = vec![0; rtcp.calculate_size().unwrap()];
+    rtcp.write_into(&mut data).unwrap();
+
+    gst::Buffer::from_mut_slice(data)
+}
+
+#[test]
+fn test_reports() {
+    init();
+
+    let pipeline = gst::Pipeline::default();
+    let recv_rtp_src = gst_app::AppSrc::builder()
+        .caps(
+            &gst::Caps::builder("application/x-rtp")
+                .field("clock-rate", 90_000i32)
+                .build(),
+        )
+        .format(gst::Format::Time)
+        .build();
+    let recv_rtcp_src = gst_app::AppSrc::builder()
+        .caps(&gst::Caps::builder("application/x-rtcp").build())
+        .format(gst::Format::Time)
+        .build();
+    let send_rtp_src = gst_app::AppSrc::builder()
+        .caps(
+            &gst::Caps::builder("application/x-rtp")
+                .field("clock-rate", 90_000i32)
+                .build(),
+        )
+        .format(gst::Format::Time)
+        .build();
+    let session = gst::ElementFactory::make("ts-rtpsession")
+        .property("rtcp-interval", 50u32)
+        .property("cname", "test@example.org")
+        .build()
+        .unwrap();
+    let recv_rtp_sink = gst_app::AppSink::builder().sync(false).build();
+    let send_rtp_sink = gst_app::AppSink::builder().sync(false).build();
+    let rtcp_sink = gst_app::AppSink::builder()
+        .sync(false)
+        .async_(false)
+        .build();
+
+    pipeline
+        .add_many([
+            recv_rtp_src.upcast_ref(),
+            recv_rtcp_src.upcast_ref(),
+            send_rtp_src.upcast_ref(),
+            &session,
+            recv_rtp_sink.upcast_ref(),
+            send_rtp_sink.upcast_ref(),
+            rtcp_sink.upcast_ref(),
+        ])
+        .unwrap();
+    recv_rtp_src
+        .link_pads(None, &session, Some("recv_rtp_sink"))
+        .unwrap();
+    recv_rtcp_src
+        .link_pads(None, &session, Some("recv_rtcp_sink"))
+        .unwrap();
+    send_rtp_src
+        .link_pads(None, &session, Some("send_rtp_sink"))
+        .unwrap();
+    session
+        .link_pads(Some("recv_rtp_src"), &recv_rtp_sink, None)
+        .unwrap();
+    session
+        .link_pads(Some("send_rtp_src"), &send_rtp_sink, None)
+        .unwrap();
+    session
+        .link_pads(Some("send_rtcp_src"), &rtcp_sink, None)
+        .unwrap();
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    // Receive 10 packets with seqnum 5 missing and forward 3 packets
+    for seq in (0..11).filter(|seq| *seq != 5) {
+        recv_rtp_src
+            .push_buffer(rtp_buffer(SSRC, seq, seq as u32 * 3000))
+            .unwrap();
+    }
+    for seq in 0..3 {
+        send_rtp_src
+            .push_buffer(rtp_buffer(SENDER_SSRC, seq, seq as u32 * 3000))
+            .unwrap();
+    }
+    for _ in 0..10 {
+        recv_rtp_sink
+            .try_pull_sample(gst::ClockTime::from_seconds(5))
+            .unwrap();
+    }
+    for _ in 0..3 {
+        send_rtp_sink
+            .try_pull_sample(gst::ClockTime::from_seconds(5))
+            .unwrap();
+    }
+
+    // A Receiver Report about our sender
+    recv_rtcp_src
+        .push_buffer(rtcp_buffer(
+            Compound::builder().add_packet(
+                ReceiverReport::builder(SSRC).add_report_block(
+                    ReportBlock::builder(SENDER_SSRC)
+                        .fraction_lost(64)
+                        .cumulative_lost(2)
+                        .extended_sequence_number(2),
+                ),
+            ),
+        ))
+        .unwrap();
+
+    // Wait for a Sender Report with a report block covering all received packets
+    let mut found = false;
+    for _ in 0..100 {
+        let sample = rtcp_sink
+            .try_pull_sample(gst::ClockTime::from_seconds(5))
+            .unwrap();
+        let buffer = sample.buffer().unwrap();
+        let map = buffer.map_readable().unwrap();
+
+        // Reports generated before the first forwarded packet are RRs
+        let mut packets = Compound::parse(&map).unwrap();
+        let Some(Ok(Packet::Sr(sr))) = packets.next() else {
+            continue;
+        };
+        assert_eq!(sr.ssrc(), SENDER_SSRC);
+        assert_eq!(sr.packet_count(), 3);
+        assert_eq!(sr.octet_count(), 3 * 16);
+
+        let Some(Ok(Packet::Sdes(sdes))) = packets.next() else {
+            panic!("Expected a SDES after the SR");
+        };
+        let chunk = sdes.chunks().next().unwrap();
+        assert_eq!(chunk.ssrc(), SENDER_SSRC);
+        let item = chunk.items().next().unwrap();
+        assert_eq!(item.type_(), SdesItem::CNAME);
+        assert_eq!(item.value(), b"test@example.org");
+
+        if let Some(rb) = sr.report_blocks().find(|rb| rb.ssrc() == SSRC) {
+            if rb.extended_sequence_number() == 10 {
+                assert_eq!(rb.cumulative_lost(), 1);
+                found = true;
+                break;
+            }
+        }
+    }
+    assert!(found);
+
+    // The Receiver Report is handled asynchronously
+    let mut stats = session.property::<gst::Structure>("stats");
+    for _ in 0..100 {
+        let sent = stats.get::<gst::Array>("sent").unwrap();
+        if sent[0]
+            .get::<gst::Structure>()
+            .unwrap()
+            .has_field("rb-reporter-ssrc")
+        {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        stats = session.property::<gst::Structure>("stats");
+    }
+
+    let received = stats.get::<gst::Array>("received").unwrap();
+    assert_eq!(received.len(), 1);
+    let received = received[0].get::<gst::Structure>().unwrap();
+    assert_eq!(received.get::<u32>("ssrc").unwrap(), SSRC);
+    assert_eq!(received.get::<u64>("packets-received").unwrap(), 10);
+    assert_eq!(received.get::<i64>("packets-lost").unwrap(), 1);
+
+    let sent = stats.get::<gst::Array>("sent").unwrap();
+    assert_eq!(sent.len(), 1);
+    let sent = sent[0].get::<gst::Structure>().unwrap();
+    assert_eq!(sent.get::<u32>("ssrc").unwrap(), SENDER_SSRC);
+    assert_eq!(sent.get::<u64>("packets-sent").unwrap(), 3);
+    assert_eq!(sent.get::<u32>("rb-reporter-ssrc").unwrap(), SSRC);
+    assert_eq!(sent.get::<u32>("rb-fraction-lost").unwrap(), 64);
+    assert_eq!(sent.get::<u32>("rb-packets-lost").unwrap(), 2);
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn test_many_sources_and_bye() {
+    init();
+
+    let session = gst::ElementFactory::make("ts-rtpsession")
+        .property("rtcp-interval", 50u32)
+        .build()
+        .unwrap();
+    let mut h_rtp =
+        gst_check::Harness::with_element(&session, Some("recv_rtp_sink"), Some("recv_rtp_src"));
+    let mut h_rtcp = gst_check::Harness::with_element(&session, None, Some("send_rtcp_src"));
+    h_rtp.set_src_caps(
+        gst::Caps::builder("application/x-rtp")
+            .field("clock-rate", 90_000i32)
+            .build(),
+    );
+    h_rtp.play();
+    h_rtcp.play();
+
+    for i in 0..40 {
+        h_rtp.push(rtp_buffer(SSRC + i, 0, 0)).unwrap();
+    }
+
+    // The report blocks don't fit into a single RR and are split over two of them
+    let mut internal_ssrc = None;
+    for _ in 0..100 {
+        let buffer = h_rtcp.pull().unwrap();
+        let map = buffer.map_readable().unwrap();
+
+        let rrs = Compound::parse(&map)
+            .unwrap()
+            .filter_map(|packet| match packet {
+                Ok(Packet::Rr(rr)) => Some((rr.ssrc(), rr.report_blocks().count())),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        if rrs
+            .iter()
+            .map(|(_, n_reports)| *n_reports as usize)
+            .sum::<usize>()
+            < 40
+        {
+            continue;
+        }
+
+        assert_eq!(rrs.len(), 2);
+        assert_eq!(rrs[0].0, rrs[1].0);
+        assert_eq!(rrs[0].1, 31);
+        assert_eq!(rrs[1].1, 9);
+        internal_ssrc = Some(rrs[0].0);
+        break;
+    }
+    let internal_ssrc = internal_ssrc.expect("No RTCP packet with all report blocks");
+
+    // A BYE is sent when stopping
+    session.set_state(gst::State::Ready).unwrap();
+    let mut bye = false;
+    while let Some(buffer) = h_rtcp.try_pull() {
+        let map = buffer.map_readable().unwrap();
+        for packet in Compound::parse(&map).unwrap() {
+            if let Ok(Packet::Bye(packet)) = packet {
+                assert_eq!(packet.ssrcs().collect::<Vec<_>>(), vec![internal_ssrc]);
+                bye = true;
+            }
+        }
+    }
+    assert!(bye);
+}
@@ -123,6 +123,8 @@ struct Settings {
     movie_timescale: u32,
     offset_to_zero: bool,
     write_edts_mode: WriteEdtsMode,
+    trim_start: Option<gst::ClockTime>,
+    trim_end: Option<gst::ClockTime>,
 }
 
 impl Default for Settings {
@@ -138,6 +140,8 @@ impl Default for Settings {
             movie_timescale: 0,
             offset_to_zero: false,
             write_edts_mode: DEFAULT_WRITE_EDTS_MODE,
+            trim_start: None,
+            trim_end: None,
         }
     }
 }
@@ -249,9 +253,138 @@ struct Stream {
 
     /// Edit list entries for this stream.
     elst_infos: Vec<super::ElstInfo>,
+
+    /// Encoder delay in samples, from the clipping meta of the first buffer
+    encoder_delay: u64,
+
+    /// Encoder padding in samples, from the clipping meta of the last buffer
+    encoder_padding: u64,
+
+    /// Set once a GOP starting after `trim-end` was dropped
+    past_trim_end: bool,
 }
 
 impl Stream {
+    /// Converts a running time to the time used for this stream's buffers, i.e. the UTC
+    /// time in ONVIF mode and the running time otherwise.
+    fn running_time_to_stream_time(&self, running_time: gst::ClockTime) -> Option<gst::ClockTime> {
+        match self.running_time_utc_time_mapping {
+            Some(mapping) => running_time_to_utc_time(running_time, mapping),
+            None => Some(running_time),
+        }
+    }
+
+    /// Gapless playback information for the header. The padding and the number of samples
+    /// are only known at the end of the stream.
+    fn get_gapless_info(&self, at_eos: bool) -> Option<super::GaplessInfo> {
+        if self.encoder_delay == 0 && self.encoder_padding == 0 {
+            return None;
+        }
+
+        if !at_eos {
+            return Some(super::GaplessInfo {
+                delay: self.encoder_delay,
+                padding: 0,
+                samples: 0,
+            });
+        }
+
+        let rate = self.caps.structure(0).unwrap().get::<i32>("rate").ok()?;
+        let samples = self
+            .end_pts?
+            .checked_sub(self.earliest_pts?)?
+            .nseconds()
+            .mul_div_round(rate as u64, gst::ClockTime::SECOND.nseconds())?;
+
+        Some(super::GaplessInfo {
+            delay: self.encoder_delay,
+            padding: self.encoder_padding,
+            samples: samples.saturating_sub(self.encoder_delay + self.encoder_padding),
+        })
+    }
+
+    /// Start and end of this stream after applying `trim-start` and `trim-end`.
+    fn trimmed_range(&self, settings: &Settings) -> Option<(gst::ClockTime, gst::ClockTime)> {
+        let earliest_pts = self.earliest_pts?;
+        let end_pts = self.end_pts?;
+
+        let start = settings
+            .trim_start
+            .and_then(|t| self.running_time_to_stream_time(t))
+            .map_or(earliest_pts, |t| std::cmp::max(t, earliest_pts));
+        let end = settings
+            .trim_end
+            .and_then(|t| self.running_time_to_stream_time(t))
+            .map_or(end_pts, |t| std::cmp::min(t, end_pts));
+
+        Some((start, std::cmp::max(start, end)))
+    }
+
+    /// Edit list that only presents the trimmed range.
+    ///
+    /// Without other edits this is a single entry, otherwise the existing entries (e.g. from
+    /// audio clipping) are cut to the trimmed range of the presentation timeline.
+    fn get_trimmed_elst_infos(
+        &self,
+        settings: &Settings,
+        timeline_offset: Option<gst::ClockTime>,
+    ) -> Result<Vec<super::ElstInfo>, anyhow::Error> {
+        let timescale = self.timescale();
+        let (start, end) = self
+            .trimmed_range(settings)
+            .context("no buffers in the stream")?;
+
+        let to_timescale = |t: gst::ClockTime| {
+            t.nseconds()
+                .mul_div_round(timescale as u64, gst::ClockTime::SECOND.nseconds())
+                .context("too big timestamp")
+        };
+
+        if self.elst_infos.is_empty() {
+            let media_start = start
+                .checked_sub(timeline_offset.unwrap_or(gst::ClockTime::ZERO))
+                .context("trim start before timeline start")?;
+
+            return Ok(vec![super::ElstInfo {
+                start: to_timescale(media_start)? as i64,
+                duration: Some(to_timescale(end - start)?),
+            }]);
+        }
+
+        // Both are relative to the start of the presentation, i.e. the earliest PTS
+        let earliest_pts = self.earliest_pts.unwrap_or(gst::ClockTime::ZERO);
+        let mut skip = to_timescale(start.saturating_sub(earliest_pts))?;
+        let mut remaining = to_timescale(end - start)?;
+
+        let mut elst_infos = Vec::new();
+        for mut elst_info in self.get_elst_infos()? {
+            if remaining == 0 {
+                break;
+            }
+
+            let duration = elst_info
+                .duration
+                .expect("Should have been set by `get_elst_infos`");
+            if skip >= duration {
+                skip -= duration;
+                continue;
+            }
+
+            // Empty edits have no media time to move forward
+            if elst_info.start >= 0 {
+                elst_info.start += skip as i64;
+            }
+            let duration = std::cmp::min(duration - skip, remaining);
+            skip = 0;
+            remaining -= duration;
+
+            elst_info.duration = Some(duration);
+            elst_infos.push(elst_info);
+        }
+
+        Ok(elst_infos)
+    }
+
     fn get_elst_infos(&self) -> Result<Vec<super::ElstInfo>, anyhow::Error> {
         let mut elst_infos = self.elst_infos.clone();
         let timescale = self.timescale();
@@ -375,6 +508,11 @@ struct State {
 
     /// Manually requested fragment boundaries
     manual_fragment_boundaries: BTreeSet<gst::ClockTime>,
+
+    /// Timed ID3 metadata and its running time, sorted by running time
+    timed_metadata: VecDeque<(gst::ClockTime, gst::Buffer)>,
+    /// Id of the next `emsg` box
+    emsg_id: u32,
 }
 
 #[derive(Default)]
@@ -422,6 +560,14 @@ impl FMP4Mux {
                 ));
             }
 
+            // Clipping at the start of the stream is the encoder delay, at the end the padding
+            if let Some(start) = start.filter(|_| stream.elst_infos.is_empty()) {
+                stream.encoder_delay = start;
+            }
+            if let Some(end) = end {
+                stream.encoder_padding = end;
+            }
+
             let start = if let Some(start) = generic_to_samples(cmeta.start())? {
                 start + gstclocktime_to_samples(buffer.pts)?
             } else {
@@ -981,6 +1127,7 @@ impl FMP4Mux {
     /// Queue incoming buffer as individual GOPs.
     fn queue_gops(
         &self,
+        settings: &Settings,
         stream: &mut Stream,
         mut pre_queued_buffer: PreQueuedBuffer,
     ) -> Result<(), gst::FlowError> {
@@ -1086,6 +1233,25 @@ impl FMP4Mux {
 
         let pts_position = buffer.pts().unwrap();
 
+        // Drop complete GOPs after the trim end, partial GOPs are cut via the edit list
+        if !stream.past_trim_end
+            && !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT)
+            && settings
+                .trim_end
+                .and_then(|t| stream.running_time_to_stream_time(t))
+                .is_some_and(|trim_end| pts >= trim_end)
+        {
+            gst::debug!(
+                CAT,
+                obj = stream.sinkpad,
+                "Dropping buffers starting at PTS {pts} after trim end",
+            );
+            stream.past_trim_end = true;
+        }
+        if stream.past_trim_end {
+            return Ok(());
+        }
+
         if !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT) {
             gst::debug!(
                 CAT,
@@ -1117,6 +1283,22 @@ impl FMP4Mux {
                     })?;
             }
 
+            // All queued GOPs end before the trim start if this one starts before it
+            if !stream.queued_gops.is_empty()
+                && settings
+                    .trim_start
+                    .and_then(|t| stream.running_time_to_stream_time(t))
+                    .is_some_and(|trim_start| pts <= trim_start)
+            {
+                gst::debug!(
+                    CAT,
+                    obj = stream.sinkpad,
+                    "Dropping {} queued GOPs before trim start",
+                    stream.queued_gops.len(),
+                );
+                stream.queued_gops.clear();
+            }
+
             let gop = Gop {
                 start_pts: pts,
                 start_dts: dts,
@@ -1282,7 +1464,7 @@ impl FMP4Mux {
             let pre_queued_buffer = Self::pop_buffer(self, stream);
 
             // Queue up the buffer and update GOP tracking state
-            self.queue_gops(stream, pre_queued_buffer)?;
+            self.queue_gops(settings, stream, pre_queued_buffer)?;
 
             // Check if this stream is filled enough now.
             self.check_stream_filled(
@@ -2653,12 +2835,8 @@ impl FMP4Mux {
         // instead of using the UTC time verbatim. This would be used for the tfdt box later.
         // FIXME: Should this use the original DTS-or-PTS running time instead?
         //        That might be negative though!
-        if self.obj().class().as_ref().variant == super::Variant::ONVIF || settings.offset_to_zero {
-            let offset = if let Some(start_dts) = state.start_dts {
-                std::cmp::min(start_dts, state.earliest_pts.unwrap())
-            } else {
-                state.earliest_pts.unwrap()
-            };
+        let timeline_offset = self.timeline_offset(state, settings);
+        if let Some(offset) = timeline_offset {
             for stream in &mut streams {
                 if let Some(start_time) = stream.start_time {
                     stream.start_time = Some(start_time.checked_sub(offset).unwrap());
@@ -2709,6 +2887,8 @@ impl FMP4Mux {
             state.sent_headers = true;
         }
 
+        let emsgs = self.drain_timed_metadata(state, chunk_end_pts, timeline_offset);
+
         // TODO: Write prft boxes before moof
         // TODO: Write sidx boxes before moof and rewrite once offsets are known
 
@@ -2729,6 +2909,7 @@ impl FMP4Mux {
                 chunk: !fragment_start,
                 streams: streams.as_slice(),
                 buffers: interleaved_buffers.as_slice(),
+                emsgs: emsgs.as_slice(),
             })
             .map_err(|err| {
                 gst::error!(
@@ -2930,6 +3111,44 @@ impl FMP4Mux {
         }
     }
 
+    /// Takes all timed metadata up to the end of the current chunk for writing as `emsg` boxes.
+    fn drain_timed_metadata(
+        &self,
+        state: &mut State,
+        chunk_end_pts: gst::ClockTime,
+        timeline_offset: Option<gst::ClockTime>,
+    ) -> Vec<super::Emsg> {
+        let mut emsgs = vec![];
+
+        while state
+            .timed_metadata
+            .front()
+            .is_some_and(|(running_time, _)| *running_time < chunk_end_pts)
+        {
+            let (running_time, data) = state.timed_metadata.pop_front().unwrap();
+            let presentation_time = match timeline_offset {
+                Some(offset) => running_time.saturating_sub(offset),
+                None => running_time,
+            };
+
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Writing timed metadata for running time {running_time} at {presentation_time}"
+            );
+
+            emsgs.push(super::Emsg {
+                presentation_time,
+                duration: data.duration(),
+                id: state.emsg_id,
+                data,
+            });
+            state.emsg_id = state.emsg_id.wrapping_add(1);
+        }
+
+        emsgs
+    }
+
     /// Create all streams.
     fn create_streams(&self, state: &mut State) -> Result<(), gst::FlowError> {
         for pad in self
@@ -3030,6 +3249,9 @@ impl FMP4Mux {
                 earliest_pts: None,
                 end_pts: None,
                 elst_infos: Vec::new(),
+                encoder_delay: 0,
+                encoder_padding: 0,
+                past_trim_end: false,
             });
         }
 
@@ -3067,6 +3289,24 @@ impl FMP4Mux {
         Ok(())
     }
 
+    /// Offset of the stream start times in the `tfdt` box, if any.
+    ///
+    /// Streams start at 0 in ONVIF mode, or if 'offset-to-zero' is enabled, instead of using the
+    /// UTC time / running time verbatim.
+    fn timeline_offset(&self, state: &State, settings: &Settings) -> Option<gst::ClockTime> {
+        if self.obj().class().as_ref().variant != super::Variant::ONVIF && !settings.offset_to_zero
+        {
+            return None;
+        }
+
+        let earliest_pts = state.earliest_pts?;
+        Some(if let Some(start_dts) = state.start_dts {
+            std::cmp::min(start_dts, earliest_pts)
+        } else {
+            earliest_pts
+        })
+    }
+
     /// Generate an updated header at the end and the corresponding caps with the new streamheader.
     fn update_header(
         &self,
@@ -3084,33 +3324,56 @@ impl FMP4Mux {
 
         assert!(!at_eos || state.streams.iter().all(|s| s.queued_gops.is_empty()));
 
-        let duration = state
-            .end_pts
-            .opt_checked_sub(state.earliest_pts)
-            .ok()
-            .flatten();
+        // Only present the trimmed range when finalizing, earlier the end is not known yet
+        let trim = at_eos && (settings.trim_start.is_some() || settings.trim_end.is_some());
+
+        let duration = if trim {
+            state
+                .streams
+                .iter()
+                .filter_map(|s| s.trimmed_range(settings))
+                .map(|(start, end)| end - start)
+                .max()
+        } else {
+            state
+                .end_pts
+                .opt_checked_sub(state.earliest_pts)
+                .ok()
+                .flatten()
+        };
+
+        let timeline_offset = self.timeline_offset(state, settings);
 
         let streams = state
             .streams
             .iter()
             .map(|s| {
                 let trak_timescale = { s.sinkpad.imp().settings.lock().unwrap().trak_timescale };
+                let loudness = { s.sinkpad.imp().state.lock().unwrap().loudness };
+                let elst_infos = if trim {
+                    s.get_trimmed_elst_infos(settings, timeline_offset)
+                } else {
+                    s.get_elst_infos()
+                };
                 super::HeaderStream {
                     trak_timescale,
                     delta_frames: s.delta_frames,
                     caps: s.caps.clone(),
                     extra_header_data: s.extra_header_data.clone(),
-                    elst_infos: s.get_elst_infos().unwrap_or_else(|e| {
+                    elst_infos: elst_infos.unwrap_or_else(|e| {
                         gst::error!(CAT, "Could not prepare edit lists: {e:?}");
 
                         Vec::new()
                     }),
+                    loudness,
+                    gapless: s.get_gapless_info(at_eos),
                 }
             })
             .collect::<Vec<_>>();
 
         let write_edts = match settings.write_edts_mode {
-            WriteEdtsMode::Auto => self.obj().latency().is_none(),
+            // Trimming is only done through the edit list, so it is also needed in live mode
+            WriteEdtsMode::Auto => trim || self.obj().latency().is_none(),
             WriteEdtsMode::Always => true,
             WriteEdtsMode::Never => false,
         };
@@ -3253,6 +3516,7 @@ impl ObjectSubclass for FMP4Mux {
 
 static FMP4_SIGNAL_SEND_HEADERS: &str = "send-headers";
 static FMP4_SIGNAL_SPLIT_AT_RUNNING_TIME: &str = "split-at-running-time";
+static FMP4_SIGNAL_INSERT_TIMED_METADATA: &str = "insert-timed-metadata";
 
 impl ObjectImpl for FMP4Mux {
     fn signals() -> &'static [glib::subclass::Signal] {
@@ -3293,6 +3557,37 @@ impl ObjectImpl for FMP4Mux {
                         None
                     })
                     .build(),
+                glib::subclass::Signal::builder(FMP4_SIGNAL_INSERT_TIMED_METADATA)
+                    .param_types([gst::ClockTime::static_type(), gst::Buffer::static_type()])
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::FMP4Mux>().expect("signal arg");
+                        let imp = element.imp();
+                        let time = args[1]
+                            .get::<Option<gst::ClockTime>>()
+                            .expect("time arg")
+                            .unwrap_or(gst::ClockTime::ZERO);
+                        let data = args[2].get::<gst::Buffer>().expect("data arg");
+
+                        if element.class().as_ref().variant == super::Variant::ONVIF {
+                            gst::warning!(
+                                CAT,
+                                obj = element,
+                                "Timed metadata not supported in ONVIF mode"
+                            );
+                            return None;
+                        }
+
+                        let mut state = imp.state.lock().unwrap();
+                        let idx = state
+                            .timed_metadata
+                            .partition_point(|(running_time, _)| *running_time <= time);
+                        state.timed_metadata.insert(idx, (time, data));
+                        gst::debug!(CAT, obj = element, "Timed metadata added at {:?}", time);
+
+                        None
+                    })
+                    .build(),
             ]
         });
 
@@ -3353,6 +3648,42 @@ impl ObjectImpl for FMP4Mux {
                     .blurb("Mode for writing EDTS, when in auto mode, edts written only for non-live streams.")
                     .mutable_ready()
                     .build(),
+                /**
+                 * GstFMP4Mux:trim-start:
+                 *
+                 * Running time at which the presentation of the finalized file should start.
+                 *
+                 * GOPs that end before this running time are dropped if they were not output
+                 * yet. The remaining content before it is cut via the edit list of the header
+                 * that is written when finalizing the file, so this requires a
+                 * #GstFMP4Mux:header-update-mode other than `none`.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecUInt64::builder("trim-start")
+                    .nick("Trim Start")
+                    .blurb("Running time at which to start the presentation of the finalized file (requires a header-update-mode enabled)")
+                    .default_value(u64::MAX)
+                    .mutable_playing()
+                    .build(),
+                /**
+                 * GstFMP4Mux:trim-end:
+                 *
+                 * Running time at which the presentation of the finalized file should end.
+                 *
+                 * GOPs that start at or after this running time are dropped. The content of the
+                 * last GOP after it is cut via the edit list of the header that is written when
+                 * finalizing the file, so this requires a #GstFMP4Mux:header-update-mode other
+                 * than `none`.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecUInt64::builder("trim-end")
+                    .nick("Trim End")
+                    .blurb("Running time at which to end the presentation of the finalized file (requires a header-update-mode enabled)")
+                    .default_value(u64::MAX)
+                    .mutable_playing()
+                    .build(),
             ]
         });
 
@@ -3426,6 +3757,14 @@ impl ObjectImpl for FMP4Mux {
                 let mut settings = self.settings.lock().unwrap();
                 settings.write_edts_mode = value.get().expect("type checked upstream");
             }
+            "trim-start" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.trim_start = value.get().expect("type checked upstream");
+            }
+            "trim-end" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.trim_end = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -3475,6 +3814,14 @@ impl ObjectImpl for FMP4Mux {
                 let settings = self.settings.lock().unwrap();
                 settings.write_edts_mode.to_value()
             }
+            "trim-start" => {
+                let settings = self.settings.lock().unwrap();
+                settings.trim_start.to_value()
+            }
+            "trim-end" => {
+                let settings = self.settings.lock().unwrap();
+                settings.trim_end.to_value()
+            }
 
             _ => unimplemented!(),
         }
@@ -3729,6 +4076,33 @@ impl AggregatorImpl for FMP4Mux {
                     }
                 }
 
+                let pad = aggregator_pad.downcast_ref::<super::FMP4MuxPad>().unwrap();
+                let loudness_changed = {
+                    let mut pad_state = pad.imp().state.lock().unwrap();
+                    let changed = pad_state.loudness.update_from_tags(ev.tag());
+                    if changed {
+                        gst::trace!(
+                            CAT,
+                            obj = pad,
+                            "Received loudness from tags: {:?}",
+                            pad_state.loudness
+                        );
+                    }
+                    changed
+                };
+
+                // With header-update-mode the header is rewritten at EOS and picks up the
+                // loudness then, otherwise send a new header right away.
+                if loudness_changed
+                    && self.settings.lock().unwrap().header_update_mode
+                        == super::HeaderUpdateMode::None
+                {
+                    let mut state = self.state.lock().unwrap();
+                    if !state.streams.is_empty() {
+                        state.need_new_header = true;
+                    }
+                }
+
                 self.parent_sink_event(aggregator_pad, event)
             }
             _ => self.parent_sink_event(aggregator_pad, event),
@@ -3771,11 +4145,13 @@ impl AggregatorImpl for FMP4Mux {
             stream.fragment_filled = false;
             stream.pre_queue.clear();
             stream.running_time_utc_time_mapping = None;
+            stream.past_trim_end = false;
         }
 
         state.current_offset = 0;
         state.fragment_offsets.clear();
         state.manual_fragment_boundaries.clear();
+        state.timed_metadata.clear();
 
         drop(state);
 
@@ -3798,7 +4174,9 @@ impl AggregatorImpl for FMP4Mux {
         for pad in self.obj().sink_pads() {
             let pad = pad.downcast_ref::<super::FMP4MuxPad>().unwrap().imp();
 
-            pad.state.lock().unwrap().trak_timescale = pad.settings.lock().unwrap().trak_timescale;
+            let mut pad_state = pad.state.lock().unwrap();
+            pad_state.trak_timescale = pad.settings.lock().unwrap().trak_timescale;
+            pad_state.loudness = Default::default();
         }
 
         self.parent_start()?;
@@ -4448,6 +4826,8 @@ struct PadSettings {
 struct PadState {
     // the selected trak_timescale
     trak_timescale: u32,
+    // loudness measurements from tags
+    loudness: super::Loudness,
 }
 
 #[derive(Default)]
@@ -4516,6 +4896,7 @@ impl AggregatorPadImpl for FMP4MuxPad {
                 stream.fragment_filled = false;
                 stream.pre_queue.clear();
                 stream.running_time_utc_time_mapping = None;
+                stream.past_trim_end = false;
                 break;
             }
         }
@@ -46,6 +46,8 @@ pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
         HeaderUpdateMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
         WriteEdtsMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     }
+    register_loudness_tags();
+
     gst::Element::register(
         Some(plugin),
         "isofmp4mux",
@@ -74,6 +76,105 @@ pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     Ok(())
 }
 
+/// Integrated (programme) loudness in LUFS, e.g. as measured by EBU R128.
+pub(crate) enum LoudnessIntegratedTag {}
+/// Loudness range in LU.
+pub(crate) enum LoudnessRangeTag {}
+/// Maximum true peak level in dBTP.
+pub(crate) enum LoudnessTruePeakTag {}
+/// Maximum sample peak level in dBFS.
+pub(crate) enum LoudnessSamplePeakTag {}
+
+macro_rules! loudness_tag {
+    ($tag:ident, $name:literal, $nick:literal, $description:literal) => {
+        impl<'a> gst::tags::Tag<'a> for $tag {
+            type TagType = f64;
+            const TAG_NAME: &'static glib::GStr = glib::gstr!($name);
+        }
+
+        impl<'a> gst::tags::CustomTag<'a> for $tag {
+            const FLAG: gst::TagFlag = gst::TagFlag::Meta;
+            const NICK: &'static glib::GStr = glib::gstr!($nick);
+            const DESCRIPTION: &'static glib::GStr = glib::gstr!($description);
+        }
+    };
+}
+
+loudness_tag!(
+    LoudnessIntegratedTag,
+    "loudness-integrated",
+    "integrated loudness",
+    "Integrated loudness in LUFS"
+);
+loudness_tag!(
+    LoudnessRangeTag,
+    "loudness-range",
+    "loudness range",
+    "Loudness range in LU"
+);
+loudness_tag!(
+    LoudnessTruePeakTag,
+    "loudness-true-peak",
+    "true peak",
+    "Maximum true peak level in dBTP"
+);
+loudness_tag!(
+    LoudnessSamplePeakTag,
+    "loudness-sample-peak",
+    "sample peak",
+    "Maximum sample peak level in dBFS"
+);
+
+fn register_loudness_tags() {
+    gst::tags::register::<LoudnessIntegratedTag>();
+    gst::tags::register::<LoudnessRangeTag>();
+    gst::tags::register::<LoudnessTruePeakTag>();
+    gst::tags::register::<LoudnessSamplePeakTag>();
+}
+
+/// Loudness measurements of a stream, written into the `ludt` box.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub(crate) struct Loudness {
+    /// Integrated loudness in LUFS
+    integrated: Option<f64>,
+    /// Loudness range in LU
+    range: Option<f64>,
+    /// True peak in dBTP
+    true_peak: Option<f64>,
+    /// Sample peak in dBFS
+    sample_peak: Option<f64>,
+}
+
+impl Loudness {
+    /// Update with the values from the tag list, keeping the previous values for all
+    /// measurements that are not included. Returns `true` if anything changed.
+    fn update_from_tags(&mut self, tags: &gst::TagListRef) -> bool {
+        let old = *self;
+
+        if let Some(v) = tags.get::<LoudnessIntegratedTag>() {
+            self.integrated = Some(v.get());
+        }
+        if let Some(v) = tags.get::<LoudnessRangeTag>() {
+            self.range = Some(v.get());
+        }
+        if let Some(v) = tags.get::<LoudnessTruePeakTag>() {
+            self.true_peak = Some(v.get());
+        }
+        if let Some(v) = tags.get::<LoudnessSamplePeakTag>() {
+            self.sample_peak = Some(v.get());
+        }
+
+        *self != old
+    }
+
+    fn is_empty(&self) -> bool {
+        self.integrated.is_none()
+            && self.range.is_none()
+            && self.true_peak.is_none()
+            && self.sample_peak.is_none()
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub(crate) enum ImageOrientation {
     Rotate0,
@@ -179,6 +280,17 @@ pub(crate) struct ElstInfo {
     duration: Option<u64>,
 }
 
+/// Gapless playback information of an audio stream, in samples
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GaplessInfo {
+    /// Encoder delay clipped at the beginning of the stream
+    delay: u64,
+    /// Padding clipped at the end of the stream, 0 until the end is known
+    padding: u64,
+    /// Number of samples of the stream without delay and padding, 0 until the end is known
+    samples: u64,
+}
+
 #[derive(Debug)]
 pub(crate) struct HeaderStream {
     /// Caps of this stream
@@ -195,6 +307,12 @@ pub(crate) struct HeaderStream {
 
     /// Edit list clipping information
     elst_infos: Vec<ElstInfo>,
+
+    /// Loudness measurements from tags
+    loudness: Loudness,
+
+    /// Encoder delay and padding from the audio clipping metas
+    gapless: Option<GaplessInfo>,
 }
 
 #[derive(Debug)]
@@ -209,6 +327,9 @@ pub(crate) struct FragmentHeaderConfiguration<'a> {
 
     streams: &'a [FragmentHeaderStream],
     buffers: &'a [Buffer],
+
+    /// Timed metadata to write as `emsg` boxes in front of the `moof`.
+    emsgs: &'a [Emsg],
 }
 
 #[derive(Debug)]
@@ -267,6 +388,21 @@ pub(crate) struct Buffer {
     composition_time_offset: Option<i64>,
 }
 
+#[derive(Debug)]
+pub(crate) struct Emsg {
+    /// Presentation time on the timeline of the track fragments
+    presentation_time: gst::ClockTime,
+
+    /// Duration of the event, if known
+    duration: Option<gst::ClockTime>,
+
+    /// Identifier of this event instance
+    id: u32,
+
+    /// ID3 tag
+    data: gst::Buffer,
+}
+
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum Variant {
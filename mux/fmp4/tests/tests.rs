@@ -2821,3 +2821,568 @@ fn test_caps_change_before_first_frame() {
 
     assert_eq!(h.buffers_in_queue(), 0);
 }
+
+fn find_emsg_boxes(fragment_header: &gst::BufferRef) -> Vec<Vec<u8>> {
+    let map = fragment_header.map_readable().unwrap();
+    let mut data = map.as_slice();
+    let mut boxes = vec![];
+
+    while data.len() >= 8 {
+        let size = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+        if &data[4..8] == b"emsg" {
+            boxes.push(data[8..size].to_vec());
+        }
+        // Stop at the mdat header that is not followed by its content here
+        if &data[4..8] == b"mdat" {
+            break;
+        }
+        data = &data[size..];
+    }
+
+    boxes
+}
+
+fn check_emsg(emsg: &[u8], presentation_time: u64, duration: u32, id: u32, payload: &[u8]) {
+    // version 1, no flags
+    assert_eq!(emsg[..4], [1, 0, 0, 0]);
+    assert_eq!(u32::from_be_bytes(emsg[4..8].try_into().unwrap()), 90_000);
+    assert_eq!(
+        u64::from_be_bytes(emsg[8..16].try_into().unwrap()),
+        presentation_time
+    );
+    assert_eq!(
+        u32::from_be_bytes(emsg[16..20].try_into().unwrap()),
+        duration
+    );
+    assert_eq!(u32::from_be_bytes(emsg[20..24].try_into().unwrap()), id);
+
+    let scheme = b"https://aomedia.org/emsg/ID3\0\0";
+    assert_eq!(&emsg[24..][..scheme.len()], scheme);
+    assert_eq!(&emsg[24 + scheme.len()..], payload);
+}
+
+#[test]
+fn test_timed_metadata() {
+    init();
+
+    let caps = gst::Caps::builder("video/x-h264")
+        .field("width", 1920i32)
+        .field("height", 1080i32)
+        .field("framerate", gst::Fraction::new(30, 1))
+        .field("stream-format", "avc")
+        .field("alignment", "au")
+        .field("codec_data", gst::Buffer::with_size(1).unwrap())
+        .build();
+
+    let mut h = gst_check::Harness::new("cmafmux");
+
+    h.element()
+        .unwrap()
+        .set_property("fragment-duration", 5.seconds());
+
+    h.set_src_caps(caps);
+    h.play();
+
+    // Insert in reverse order, the metadata must be sorted by running time
+    let mut id3 = gst::Buffer::from_slice(b"ID3 second");
+    id3.get_mut().unwrap().set_duration(1.seconds());
+    h.element()
+        .unwrap()
+        .emit_by_name::<()>("insert-timed-metadata", &[&6.seconds(), &id3]);
+    let id3 = gst::Buffer::from_slice(b"ID3 first");
+    h.element()
+        .unwrap()
+        .emit_by_name::<()>("insert-timed-metadata", &[&1.seconds(), &id3]);
+
+    // Push 20 buffers of 0.5s each, 1st and 11th buffer without DELTA_UNIT flag
+    for i in 0..20 {
+        let mut buffer = gst::Buffer::with_size(1).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(i * 500.mseconds());
+            buffer.set_dts(i * 500.mseconds());
+            buffer.set_duration(500.mseconds());
+            if i != 0 && i != 10 {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+
+    h.push_event(gst::event::Eos::new());
+
+    let header = h.pull().unwrap();
+    assert_eq!(
+        header.flags(),
+        gst::BufferFlags::HEADER | gst::BufferFlags::DISCONT
+    );
+    assert!(find_emsg_boxes(&header).is_empty());
+
+    let fragment_header = h.pull().unwrap();
+    assert_eq!(fragment_header.flags(), gst::BufferFlags::HEADER);
+    let emsgs = find_emsg_boxes(&fragment_header);
+    assert_eq!(emsgs.len(), 1);
+    check_emsg(&emsgs[0], 90_000, u32::MAX, 0, b"ID3 first");
+
+    for _ in 0..10 {
+        let buffer = h.pull().unwrap();
+        assert!(!buffer.flags().contains(gst::BufferFlags::HEADER));
+    }
+
+    let fragment_header = h.pull().unwrap();
+    assert_eq!(fragment_header.flags(), gst::BufferFlags::HEADER);
+    assert_eq!(fragment_header.pts(), Some(5.seconds()));
+    let emsgs = find_emsg_boxes(&fragment_header);
+    assert_eq!(emsgs.len(), 1);
+    check_emsg(&emsgs[0], 540_000, 90_000, 1, b"ID3 second");
+
+    for _ in 0..10 {
+        let buffer = h.pull().unwrap();
+        assert!(!buffer.flags().contains(gst::BufferFlags::HEADER));
+    }
+
+    assert_eq!(h.buffers_in_queue(), 0);
+}
+
+#[test]
+fn test_loudness_tags() {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("isofmp4mux", Some("sink_0"), Some("src"));
+    h.element()
+        .unwrap()
+        .set_property("fragment-duration", 5.seconds());
+
+    h.set_src_caps(
+        gst::Caps::builder("audio/mpeg")
+            .field("mpegversion", 4i32)
+            .field("channels", 1i32)
+            .field("rate", 44100i32)
+            .field("stream-format", "raw")
+            .field("base-profile", "lc")
+            .field("profile", "lc")
+            .field("level", "2")
+            .field(
+                "codec_data",
+                gst::Buffer::from_slice([0x12, 0x08, 0x56, 0xe5, 0x00]),
+            )
+            .build(),
+    );
+    h.play();
+
+    let mut tags = gst::TagList::new();
+    {
+        let tags = tags.get_mut().unwrap();
+        for (name, value) in [
+            ("loudness-integrated", -23.0f64),
+            ("loudness-range", 7.0),
+            ("loudness-true-peak", -1.0),
+            ("loudness-sample-peak", -2.0),
+        ] {
+            tags.add_generic(name, value, gst::TagMergeMode::Replace)
+                .unwrap();
+        }
+    }
+    h.push_event(gst::event::Tag::new(tags));
+
+    for i in 0..5 {
+        let mut buffer = gst::Buffer::with_size(1).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(i.seconds());
+            buffer.set_dts(i.seconds());
+            buffer.set_duration(gst::ClockTime::SECOND);
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+
+    h.push_event(gst::event::Eos::new());
+
+    let header = h.pull().unwrap();
+    assert_eq!(
+        header.flags(),
+        gst::BufferFlags::HEADER | gst::BufferFlags::DISCONT
+    );
+
+    let map = header.map_readable().unwrap();
+    let pos = map
+        .windows(8)
+        .position(|w| &w[4..] == b"tlou")
+        .expect("no tlou box");
+    assert_eq!(&map[pos - 4..pos], b"ludt");
+
+    let tlou = &map[pos + 8..];
+    // Version, flags, downmix ID and DRC set ID
+    assert_eq!(tlou[..6], [0; 6]);
+    // Sample peak -2 dBFS, true peak -1 dBTP measured accurately according to BS.1770-4
+    assert_eq!(tlou[6..10], [0x2c, 0x02, 0xa0, 0x23]);
+    // Program loudness -23 LUFS and loudness range 7 LU, both measured accurately
+    // according to EBU R128
+    assert_eq!(tlou[10..17], [2, 1, 139, 0x13, 6, 28, 0x13]);
+}
+
+#[test]
+fn test_trim() {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("isofmp4mux", Some("sink_0"), Some("src"));
+
+    let element = h.element().unwrap();
+    element.set_property("fragment-duration", 10.seconds());
+    element.set_property("trim-start", 3.seconds());
+    element.set_property("trim-end", 5.seconds());
+    element.set_property_from_str("header-update-mode", "update");
+
+    h.set_src_caps(
+        gst::Caps::builder("video/x-h264")
+            .field("width", 1920i32)
+            .field("height", 1080i32)
+            .field("framerate", gst::Fraction::new(30, 1))
+            .field("stream-format", "avc")
+            .field("alignment", "au")
+            .field("codec_data", gst::Buffer::with_size(1).unwrap())
+            .build(),
+    );
+    h.play();
+
+    let output_offset = (60 * 60 * 1000).seconds();
+
+    // Push 8 buffers of 1s each, 1st, 4th and 7th buffer without DELTA_UNIT flag
+    for i in 0..8 {
+        let mut buffer = gst::Buffer::with_size(1).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(i.seconds());
+            buffer.set_dts(i.seconds());
+            buffer.set_duration(gst::ClockTime::SECOND);
+            if i != 0 && i != 3 && i != 6 {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+
+    h.push_event(gst::event::Eos::new());
+
+    let header = h.pull().unwrap();
+    assert_eq!(
+        header.flags(),
+        gst::BufferFlags::HEADER | gst::BufferFlags::DISCONT
+    );
+
+    let fragment_header = h.pull().unwrap();
+    assert_eq!(fragment_header.flags(), gst::BufferFlags::HEADER);
+    assert_eq!(fragment_header.pts(), Some(3.seconds() + output_offset));
+
+    // Only the GOP containing the trimmed range is kept, the remaining
+    // frames are cut by the edit list
+    for i in 3..6 {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(i.seconds() + output_offset));
+        assert_eq!(buffer.dts(), Some(i.seconds() + output_offset));
+        assert_eq!(buffer.duration(), Some(gst::ClockTime::SECOND));
+    }
+
+    // The updated header presents exactly the trimmed range, also in live mode
+    let updated_header = h.pull().unwrap();
+    assert!(updated_header.flags().contains(gst::BufferFlags::HEADER));
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    let map = updated_header.map_readable().unwrap();
+    let pos = map
+        .windows(4)
+        .position(|w| w == b"mdhd")
+        .expect("no mdhd box");
+    // Version 1 with 64 bit creation and modification time
+    let timescale = u32::from_be_bytes(map[pos + 24..pos + 28].try_into().unwrap()) as u64;
+
+    let pos = map
+        .windows(4)
+        .position(|w| w == b"elst")
+        .expect("no elst box");
+    let elst = &map[pos + 4..];
+    // Version 1, no flags, one entry
+    assert_eq!(elst[..4], [1, 0, 0, 0]);
+    assert_eq!(u32::from_be_bytes(elst[4..8].try_into().unwrap()), 1);
+    // Segment duration and media time of the first trimmed frame
+    assert_eq!(
+        u64::from_be_bytes(elst[8..16].try_into().unwrap()),
+        2 * timescale
+    );
+    assert_eq!(
+        i64::from_be_bytes(elst[16..24].try_into().unwrap()),
+        3 * timescale as i64
+    );
+}
+
+fn check_roll_group(fragment_header: &gst::BufferRef, sample_count: u32, roll_distance: i16) {
+    let map = fragment_header.map_readable().unwrap();
+
+    let pos = map
+        .windows(8)
+        .position(|w| w == b"sbgproll")
+        .expect("no sbgp box");
+    let sbgp = &map[pos + 4..];
+    // Version 0, no flags, one entry
+    assert_eq!(sbgp[..4], [0; 4]);
+    assert_eq!(u32::from_be_bytes(sbgp[8..12].try_into().unwrap()), 1);
+    assert_eq!(
+        u32::from_be_bytes(sbgp[12..16].try_into().unwrap()),
+        sample_count
+    );
+    // First group description of this traf
+    assert_eq!(
+        u32::from_be_bytes(sbgp[16..20].try_into().unwrap()),
+        0x1_0001
+    );
+
+    let pos = map
+        .windows(4)
+        .position(|w| w == b"sgpd")
+        .expect("no sgpd box");
+    let sgpd = &map[pos + 4..];
+    // Version 1, no flags
+    assert_eq!(sgpd[..4], [1, 0, 0, 0]);
+    assert_eq!(&sgpd[4..8], b"roll");
+    // Default length and entry count
+    assert_eq!(u32::from_be_bytes(sgpd[8..12].try_into().unwrap()), 2);
+    assert_eq!(u32::from_be_bytes(sgpd[12..16].try_into().unwrap()), 1);
+    assert_eq!(
+        i16::from_be_bytes(sgpd[16..18].try_into().unwrap()),
+        roll_distance
+    );
+}
+
+fn test_roll_group(caps: gst::Caps, frame_duration: gst::ClockTime, roll_distance: i16) {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("cmafmux", Some("sink"), Some("src"));
+    h.element()
+        .unwrap()
+        .set_property("fragment-duration", 1.seconds());
+
+    h.set_src_caps(caps);
+    h.play();
+
+    let n_buffers = 1.seconds().nseconds() / frame_duration.nseconds();
+    for i in 0..n_buffers {
+        let mut buffer = gst::Buffer::with_size(1).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(frame_duration * i);
+            buffer.set_dts(frame_duration * i);
+            buffer.set_duration(frame_duration);
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+
+    h.push_event(gst::event::Eos::new());
+
+    let header = h.pull().unwrap();
+    assert_eq!(
+        header.flags(),
+        gst::BufferFlags::HEADER | gst::BufferFlags::DISCONT
+    );
+
+    let fragment_header = h.pull().unwrap();
+    assert_eq!(fragment_header.flags(), gst::BufferFlags::HEADER);
+    check_roll_group(&fragment_header, n_buffers as u32, roll_distance);
+}
+
+#[test]
+fn test_roll_group_aac() {
+    test_roll_group(
+        gst::Caps::builder("audio/mpeg")
+            .field("mpegversion", 4i32)
+            .field("channels", 1i32)
+            .field("rate", 48000i32)
+            .field("stream-format", "raw")
+            .field("base-profile", "lc")
+            .field("profile", "lc")
+            .field("level", "2")
+            .field(
+                "codec_data",
+                gst::Buffer::from_slice([0x11, 0x88, 0x56, 0xe5, 0x00]),
+            )
+            .build(),
+        // 1024 samples at 48kHz
+        gst::ClockTime::from_nseconds(21_333_333),
+        -1,
+    );
+}
+
+#[test]
+fn test_roll_group_opus() {
+    test_roll_group(
+        gst::Caps::builder("audio/x-opus")
+            .field("channel-mapping-family", 0i32)
+            .field("channels", 2i32)
+            .field("rate", 48000i32)
+            .build(),
+        // 80ms of pre-roll are 4 frames of 20ms
+        20.mseconds(),
+        -4,
+    );
+}
+
+fn find_itunes_smpb(header: &gst::BufferRef) -> Option<String> {
+    let map = header.map_readable().unwrap();
+
+    let pos = map.windows(8).position(|w| w == b"iTunSMPB")?;
+    let data = &map[pos + 8..];
+    assert_eq!(&data[4..8], b"data");
+    let size = u32::from_be_bytes(data[..4].try_into().unwrap()) as usize;
+    // Type UTF-8 and locale
+    assert_eq!(data[8..16], [0, 0, 0, 1, 0, 0, 0, 0]);
+
+    Some(String::from_utf8(data[16..size].to_vec()).unwrap())
+}
+
+fn push_clipped_audio(
+    h: &mut gst_check::Harness,
+    n_buffers: u64,
+    samples_per_buffer: u64,
+    delay: u64,
+    padding: u64,
+) {
+    for i in 0..n_buffers {
+        let pts = (i * samples_per_buffer)
+            .mul_div_floor(gst::ClockTime::SECOND.nseconds(), 48_000)
+            .map(gst::ClockTime::from_nseconds)
+            .unwrap();
+        let end_pts = ((i + 1) * samples_per_buffer)
+            .mul_div_floor(gst::ClockTime::SECOND.nseconds(), 48_000)
+            .map(gst::ClockTime::from_nseconds)
+            .unwrap();
+
+        let mut buffer = gst::Buffer::with_size(1).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(pts);
+            buffer.set_dts(pts);
+            buffer.set_duration(end_pts - pts);
+
+            if i == 0 {
+                gst_audio::AudioClippingMeta::add(
+                    buffer,
+                    gst::format::Default::from_u64(delay),
+                    gst::format::Default::ZERO,
+                );
+            } else if i == n_buffers - 1 {
+                gst_audio::AudioClippingMeta::add(
+                    buffer,
+                    gst::format::Default::ZERO,
+                    gst::format::Default::from_u64(padding),
+                );
+            }
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+}
+
+#[test]
+fn test_gapless_aac() {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("isofmp4mux", Some("sink_0"), Some("src"));
+
+    let element = h.element().unwrap();
+    element.set_property("fragment-duration", 10.seconds());
+    element.set_property_from_str("header-update-mode", "update");
+
+    h.set_src_caps(
+        gst::Caps::builder("audio/mpeg")
+            .field("mpegversion", 4i32)
+            .field("channels", 1i32)
+            .field("rate", 48000i32)
+            .field("stream-format", "raw")
+            .field("base-profile", "lc")
+            .field("profile", "lc")
+            .field("level", "2")
+            .field(
+                "codec_data",
+                gst::Buffer::from_slice([0x11, 0x88, 0x56, 0xe5, 0x00]),
+            )
+            .build(),
+    );
+    h.play();
+
+    // 10 frames of 1024 samples with 576 samples of delay and 300 samples of padding
+    push_clipped_audio(&mut h, 10, 1024, 576, 300);
+    h.push_event(gst::event::Eos::new());
+
+    // Only the delay is known for the initial header
+    let header = h.pull().unwrap();
+    assert_eq!(
+        header.flags(),
+        gst::BufferFlags::HEADER | gst::BufferFlags::DISCONT
+    );
+    assert_eq!(
+        find_itunes_smpb(&header).expect("no iTunSMPB"),
+        format!(
+            " 00000000 00000240 00000000 0000000000000000{}",
+            " 00000000".repeat(8)
+        )
+    );
+
+    let mut updated_header = None;
+    while let Some(buffer) = h.try_pull() {
+        if buffer.flags().contains(gst::BufferFlags::HEADER) {
+            updated_header = Some(buffer);
+        }
+    }
+
+    // The updated header also contains the padding and the number of samples
+    let updated_header = updated_header.expect("no updated header");
+    assert_eq!(
+        find_itunes_smpb(&updated_header).expect("no iTunSMPB"),
+        format!(
+            " 00000000 00000240 0000012C {:016X}{}",
+            10 * 1024 - 576 - 300,
+            " 00000000".repeat(8)
+        )
+    );
+}
+
+#[test]
+fn test_gapless_opus() {
+    init();
+
+    let mut h = gst_check::Harness::with_padnames("isofmp4mux", Some("sink_0"), Some("src"));
+
+    h.element()
+        .unwrap()
+        .set_property("fragment-duration", 10.seconds());
+
+    h.set_src_caps(
+        gst::Caps::builder("audio/x-opus")
+            .field("channel-mapping-family", 0i32)
+            .field("channels", 2i32)
+            .field("rate", 48000i32)
+            .build(),
+    );
+    h.play();
+
+    // 10 frames of 20ms with 312 samples of pre-skip
+    push_clipped_audio(&mut h, 10, 960, 312, 100);
+    h.push_event(gst::event::Eos::new());
+
+    let header = h.pull().unwrap();
+    assert_eq!(
+        header.flags(),
+        gst::BufferFlags::HEADER | gst::BufferFlags::DISCONT
+    );
+
+    // The encoder delay is written as pre-skip, iTunSMPB is only written for AAC
+    assert_eq!(find_itunes_smpb(&header), None);
+
+    let map = header.map_readable().unwrap();
+    let pos = map
+        .windows(4)
+        .position(|w| w == b"dOps")
+        .expect("no dOps box");
+    let dops = &map[pos + 4..];
+    // Version 0 and two channels
+    assert_eq!(dops[..2], [0, 2]);
+    assert_eq!(u16::from_be_bytes(dops[2..4].try_into().unwrap()), 312);
+}
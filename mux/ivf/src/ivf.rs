@@ -0,0 +1,347 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use bitstream_io::{BigEndian, BitRead, BitReader};
+use gst::prelude::*;
+
+pub const FILE_HEADER_SIZE: usize = 32;
+pub const FRAME_HEADER_SIZE: usize = 12;
+
+const SIGNATURE: &[u8; 4] = b"DKIF";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Av1,
+    Vp8,
+    Vp9,
+}
+
+impl Codec {
+    pub fn from_fourcc(fourcc: &[u8; 4]) -> Option<Self> {
+        match fourcc {
+            b"AV01" => Some(Codec::Av1),
+            b"VP80" => Some(Codec::Vp8),
+            b"VP90" => Some(Codec::Vp9),
+            _ => None,
+        }
+    }
+
+    pub fn fourcc(self) -> &'static [u8; 4] {
+        match self {
+            Codec::Av1 => b"AV01",
+            Codec::Vp8 => b"VP80",
+            Codec::Vp9 => b"VP90",
+        }
+    }
+
+    pub fn from_caps(caps: &gst::CapsRef) -> Option<Self> {
+        match caps.structure(0)?.name().as_str() {
+            "video/x-av1" => Some(Codec::Av1),
+            "video/x-vp8" => Some(Codec::Vp8),
+            "video/x-vp9" => Some(Codec::Vp9),
+            _ => None,
+        }
+    }
+
+    pub fn caps_builder(self) -> gst::caps::Builder<gst::caps::NoFeature> {
+        match self {
+            Codec::Av1 => gst::Caps::builder("video/x-av1")
+                .field("stream-format", "obu-stream")
+                .field("alignment", "tu"),
+            Codec::Vp8 => gst::Caps::builder("video/x-vp8"),
+            Codec::Vp9 => gst::Caps::builder("video/x-vp9"),
+        }
+    }
+
+    /// Caps for all supported codecs
+    pub fn all_caps() -> gst::Caps {
+        [Codec::Av1, Codec::Vp8, Codec::Vp9]
+            .into_iter()
+            .map(|codec| {
+                codec
+                    .caps_builder()
+                    .field("width", gst::IntRange::new(1, u16::MAX as i32))
+                    .field("height", gst::IntRange::new(1, u16::MAX as i32))
+                    .build()
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileHeader {
+    pub codec: Codec,
+    pub width: u16,
+    pub height: u16,
+    /// Timestamps are in units of `timebase_num / timebase_den` seconds
+    pub timebase_den: u32,
+    pub timebase_num: u32,
+    pub num_frames: u32,
+}
+
+impl FileHeader {
+    pub fn parse(data: &[u8]) -> Result<Self, gst::ErrorMessage> {
+        if data.len() < FILE_HEADER_SIZE || data[0..4] != *SIGNATURE {
+            return Err(gst::error_msg!(
+                gst::StreamError::WrongType,
+                ["Not an IVF stream"]
+            ));
+        }
+
+        let u16_at = |pos: usize| u16::from_le_bytes([data[pos], data[pos + 1]]);
+        let u32_at = |pos: usize| u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+
+        let version = u16_at(4);
+        let header_size = u16_at(6);
+        if version != 0 || header_size as usize != FILE_HEADER_SIZE {
+            return Err(gst::error_msg!(
+                gst::StreamError::Format,
+                ["Unsupported IVF version {version} with header size {header_size}"]
+            ));
+        }
+
+        let fourcc = data[8..12].try_into().unwrap();
+        let Some(codec) = Codec::from_fourcc(fourcc) else {
+            return Err(gst::error_msg!(
+                gst::StreamError::CodecNotFound,
+                ["Unsupported codec {}", String::from_utf8_lossy(fourcc)]
+            ));
+        };
+
+        let timebase_den = u32_at(16);
+        let timebase_num = u32_at(20);
+        if timebase_den == 0 || timebase_num == 0 {
+            return Err(gst::error_msg!(
+                gst::StreamError::Format,
+                ["Invalid timebase {timebase_num}/{timebase_den}"]
+            ));
+        }
+
+        Ok(FileHeader {
+            codec,
+            width: u16_at(12),
+            height: u16_at(14),
+            timebase_den,
+            timebase_num,
+            num_frames: u32_at(24),
+        })
+    }
+
+    pub fn to_bytes(self) -> [u8; FILE_HEADER_SIZE] {
+        let mut data = [0u8; FILE_HEADER_SIZE];
+        data[0..4].copy_from_slice(SIGNATURE);
+        data[4..6].copy_from_slice(&0u16.to_le_bytes());
+        data[6..8].copy_from_slice(&(FILE_HEADER_SIZE as u16).to_le_bytes());
+        data[8..12].copy_from_slice(self.codec.fourcc());
+        data[12..14].copy_from_slice(&self.width.to_le_bytes());
+        data[14..16].copy_from_slice(&self.height.to_le_bytes());
+        data[16..20].copy_from_slice(&self.timebase_den.to_le_bytes());
+        data[20..24].copy_from_slice(&self.timebase_num.to_le_bytes());
+        data[24..28].copy_from_slice(&self.num_frames.to_le_bytes());
+
+        data
+    }
+
+    pub fn pts_to_clock_time(self, pts: u64) -> Option<gst::ClockTime> {
+        pts.mul_div_floor(
+            self.timebase_num as u64 * *gst::ClockTime::SECOND,
+            self.timebase_den as u64,
+        )
+        .map(gst::ClockTime::from_nseconds)
+    }
+
+    pub fn clock_time_to_pts(self, pts: gst::ClockTime) -> Option<u64> {
+        pts.nseconds().mul_div_round(
+            self.timebase_den as u64,
+            self.timebase_num as u64 * *gst::ClockTime::SECOND,
+        )
+    }
+}
+
+pub fn frame_header(size: u32, pts: u64) -> [u8; FRAME_HEADER_SIZE] {
+    let mut data = [0u8; FRAME_HEADER_SIZE];
+    data[0..4].copy_from_slice(&size.to_le_bytes());
+    data[4..12].copy_from_slice(&pts.to_le_bytes());
+
+    data
+}
+
+pub fn parse_frame_header(data: &[u8; FRAME_HEADER_SIZE]) -> (u32, u64) {
+    (
+        u32::from_le_bytes(data[0..4].try_into().unwrap()),
+        u64::from_le_bytes(data[4..12].try_into().unwrap()),
+    )
+}
+
+/// Returns the frame size signalled in the frame if it is a keyframe
+pub fn keyframe_size(codec: Codec, data: &[u8]) -> Option<(u16, u16)> {
+    match codec {
+        Codec::Vp8 => vp8_keyframe_size(data),
+        Codec::Av1 => av1_sequence_header_size(data),
+        // Not parsed, rely on the IVF header
+        Codec::Vp9 => None,
+    }
+}
+
+fn vp8_keyframe_size(data: &[u8]) -> Option<(u16, u16)> {
+    // Frame tag with the keyframe flag in the lowest bit, followed by the start code
+    if data.len() < 10 || data[0] & 0x01 != 0 || data[3..6] != [0x9d, 0x01, 0x2a] {
+        return None;
+    }
+
+    let width = u16::from_le_bytes([data[6], data[7]]) & 0x3fff;
+    let height = u16::from_le_bytes([data[8], data[9]]) & 0x3fff;
+
+    Some((width, height))
+}
+
+const OBU_SEQUENCE_HEADER: u8 = 1;
+
+fn av1_sequence_header_size(mut data: &[u8]) -> Option<(u16, u16)> {
+    while !data.is_empty() {
+        let header = data[0];
+        let obu_type = (header >> 3) & 0x0f;
+        let has_extension = header & 0x04 != 0;
+        let has_size = header & 0x02 != 0;
+
+        let mut pos = 1 + has_extension as usize;
+        let size = if has_size {
+            let (size, len) = leb128(data.get(pos..)?)?;
+            pos += len;
+            size as usize
+        } else {
+            data.len().checked_sub(pos)?
+        };
+        let payload = data.get(pos..pos.checked_add(size)?)?;
+
+        if obu_type == OBU_SEQUENCE_HEADER {
+            return parse_av1_sequence_header(payload).ok();
+        }
+
+        data = &data[pos + size..];
+    }
+
+    None
+}
+
+fn leb128(data: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().take(8).enumerate() {
+        value |= ((byte & 0x7f) as u64) << (i * 7);
+        if byte & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+
+    None
+}
+
+fn parse_av1_sequence_header(data: &[u8]) -> std::io::Result<(u16, u16)> {
+    let mut r = BitReader::endian(data, BigEndian);
+
+    let _seq_profile = r.read::<u8>(3)?;
+    let _still_picture = r.read_bit()?;
+    let reduced_still_picture_header = r.read_bit()?;
+
+    if reduced_still_picture_header {
+        let _seq_level_idx = r.read::<u8>(5)?;
+    } else {
+        let mut decoder_model_info_present = false;
+        let mut buffer_delay_length = 0;
+
+        let timing_info_present = r.read_bit()?;
+        if timing_info_present {
+            let _num_units_in_display_tick = r.read::<u32>(32)?;
+            let _time_scale = r.read::<u32>(32)?;
+            let equal_picture_interval = r.read_bit()?;
+            if equal_picture_interval {
+                // uvlc()
+                let leading_zeros = r.read_unary1()?;
+                if leading_zeros < 32 {
+                    r.skip(leading_zeros)?;
+                }
+            }
+
+            decoder_model_info_present = r.read_bit()?;
+            if decoder_model_info_present {
+                buffer_delay_length = r.read::<u32>(5)? + 1;
+                let _num_units_in_decoding_tick = r.read::<u32>(32)?;
+                let _buffer_removal_time_length = r.read::<u8>(5)?;
+                let _frame_presentation_time_length = r.read::<u8>(5)?;
+            }
+        }
+
+        let initial_display_delay_present = r.read_bit()?;
+        let operating_points_cnt = r.read::<u8>(5)? + 1;
+        for _ in 0..operating_points_cnt {
+            let _operating_point_idc = r.read::<u16>(12)?;
+            let seq_level_idx = r.read::<u8>(5)?;
+            if seq_level_idx > 7 {
+                let _seq_tier = r.read_bit()?;
+            }
+            if decoder_model_info_present {
+                let decoder_model_present = r.read_bit()?;
+                if decoder_model_present {
+                    // decoder_buffer_delay, encoder_buffer_delay, low_delay_mode_flag
+                    r.skip(2 * buffer_delay_length + 1)?;
+                }
+            }
+            if initial_display_delay_present {
+                let initial_display_delay_present_for_op = r.read_bit()?;
+                if initial_display_delay_present_for_op {
+                    let _initial_display_delay = r.read::<u8>(4)?;
+                }
+            }
+        }
+    }
+
+    let frame_width_bits = r.read::<u32>(4)? + 1;
+    let frame_height_bits = r.read::<u32>(4)? + 1;
+    let max_frame_width = r.read::<u32>(frame_width_bits)? + 1;
+    let max_frame_height = r.read::<u32>(frame_height_bits)? + 1;
+
+    Ok((
+        u16::try_from(max_frame_width).unwrap_or(u16::MAX),
+        u16::try_from(max_frame_height).unwrap_or(u16::MAX),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_header_roundtrip() {
+        let header = FileHeader {
+            codec: Codec::Av1,
+            width: 320,
+            height: 240,
+            timebase_den: 30,
+            timebase_num: 1,
+            num_frames: 10,
+        };
+
+        assert_eq!(FileHeader::parse(&header.to_bytes()).unwrap(), header);
+        assert_eq!(
+            header.pts_to_clock_time(3),
+            Some(gst::ClockTime::from_mseconds(100))
+        );
+        assert_eq!(
+            header.clock_time_to_pts(gst::ClockTime::from_mseconds(100)),
+            Some(3)
+        );
+    }
+
+    #[test]
+    fn av1_sequence_header() {
+        // Temporal delimiter followed by a sequence header of a 352x288 stream
+        let data = [
+            0x12, 0x00, 0x0a, 0x07, 0x00, 0x00, 0x00, 0x04, 0x45, 0x7e, 0x3e,
+        ];
+
+        assert_eq!(keyframe_size(Codec::Av1, &data), Some((352, 288)));
+    }
+}
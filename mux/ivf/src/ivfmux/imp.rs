@@ -0,0 +1,305 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::{LazyLock, Mutex};
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use crate::ivf::{self, Codec, FileHeader};
+
+#[derive(Default)]
+struct State {
+    header: Option<FileHeader>,
+    header_sent: bool,
+    num_frames: u32,
+}
+
+pub struct IvfMux {
+    sinkpad: gst::Pad,
+    srcpad: gst::Pad,
+
+    state: Mutex<State>,
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new("ivfmux", gst::DebugColorFlags::empty(), Some("IVF muxer"))
+});
+
+impl IvfMux {
+    fn push_header(&self, header: FileHeader) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let segment = gst::FormattedSegment::<gst::format::Bytes>::new();
+        if !self.srcpad.push_event(gst::event::Segment::new(&segment)) {
+            gst::warning!(CAT, imp = self, "Failed to push segment");
+        }
+
+        let mut buffer = gst::Buffer::from_slice(header.to_bytes());
+        buffer.get_mut().unwrap().set_offset(0);
+
+        self.srcpad.push(buffer)
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, obj = pad, "Handling buffer {:?}", buffer);
+
+        let mut state = self.state.lock().unwrap();
+        let Some(header) = state.header else {
+            gst::error!(CAT, imp = self, "No caps set");
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        let Some(pts) = buffer.pts().or(buffer.dts()) else {
+            gst::element_imp_error!(self, gst::StreamError::Format, ["Buffer without timestamp"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        let send_header = !state.header_sent;
+        state.header_sent = true;
+        state.num_frames += 1;
+        drop(state);
+
+        if send_header {
+            gst::debug!(CAT, imp = self, "Writing file header {header:?}");
+            self.push_header(header)?;
+        }
+
+        let ivf_pts = header.clock_time_to_pts(pts).unwrap_or(0);
+        let size = u32::try_from(buffer.size()).map_err(|_| {
+            gst::error!(CAT, imp = self, "Frame too big");
+            gst::FlowError::Error
+        })?;
+
+        let mut outbuf = gst::Buffer::from_slice(ivf::frame_header(size, ivf_pts));
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            outbuf.set_pts(buffer.pts());
+            outbuf.set_dts(buffer.dts());
+            outbuf.set_flags(buffer.flags() & gst::BufferFlags::DELTA_UNIT);
+        }
+        outbuf.append(buffer);
+
+        self.srcpad.push(outbuf)
+    }
+
+    fn set_caps(&self, caps: &gst::CapsRef) -> bool {
+        let Some(codec) = Codec::from_caps(caps) else {
+            gst::error!(CAT, imp = self, "Unsupported caps {caps:?}");
+            return false;
+        };
+        let s = caps.structure(0).unwrap();
+        let width = s.get::<i32>("width").unwrap_or(0) as u16;
+        let height = s.get::<i32>("height").unwrap_or(0) as u16;
+        let framerate = s
+            .get::<gst::Fraction>("framerate")
+            .ok()
+            .filter(|fps| fps.numer() > 0 && fps.denom() > 0);
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(header) = state.header {
+            if header.codec != codec {
+                gst::error!(
+                    CAT,
+                    imp = self,
+                    "Can't change codec from {:?} to {codec:?}",
+                    header.codec
+                );
+                return false;
+            }
+
+            if state.header_sent {
+                // The file header only contains the initial size, the bitstream signals changes
+                gst::debug!(CAT, imp = self, "Resolution changed to {width}x{height}");
+                return true;
+            }
+        }
+
+        // Timestamps are in frames if the framerate is known, otherwise in milliseconds
+        let (timebase_den, timebase_num) = framerate
+            .map(|fps| (fps.numer() as u32, fps.denom() as u32))
+            .unwrap_or((1000, 1));
+
+        let header = FileHeader {
+            codec,
+            width,
+            height,
+            timebase_den,
+            timebase_num,
+            num_frames: 0,
+        };
+        gst::debug!(CAT, imp = self, "Configured header {header:?}");
+
+        let first_caps = state.header.is_none();
+        state.header = Some(header);
+        drop(state);
+
+        if first_caps {
+            return self
+                .srcpad
+                .push_event(gst::event::Caps::new(&self.srcpad.pad_template_caps()));
+        }
+
+        true
+    }
+
+    fn finalize_header(&self) {
+        let state = self.state.lock().unwrap();
+        let Some(mut header) = state.header.filter(|_| state.header_sent) else {
+            return;
+        };
+        header.num_frames = state.num_frames;
+        drop(state);
+
+        let mut query = gst::query::Seeking::new(gst::Format::Bytes);
+        if !self.srcpad.peer_query(&mut query) || !query.result().0 {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Downstream not seekable, not updating header"
+            );
+            return;
+        }
+
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Updating header with {} frames",
+            header.num_frames
+        );
+        if let Err(err) = self.push_header(header) {
+            gst::warning!(CAT, imp = self, "Failed to update header: {err:?}");
+        }
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(ev) => self.set_caps(ev.caps()),
+            // A bytes segment is sent together with the file header
+            EventView::Segment(_) | EventView::Gap(_) => true,
+            EventView::Eos(_) => {
+                self.finalize_header();
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for IvfMux {
+    const NAME: &'static str = "GstIvfMux";
+    type Type = super::IvfMux;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                IvfMux::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                IvfMux::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for IvfMux {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for IvfMux {}
+
+impl ElementImpl for IvfMux {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "IVF Muxer",
+                "Codec/Muxer",
+                "Muxes AV1, VP8 and VP9 streams into IVF files",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("video/x-ivf").build(),
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &Codec::all_caps(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {:?}", transition);
+
+        let ret = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                *self.state.lock().unwrap() = State::default();
+            }
+            _ => (),
+        }
+
+        Ok(ret)
+    }
+}
@@ -0,0 +1,40 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+/**
+ * SECTION:element-ivfmux
+ *
+ * Muxes AV1, VP8 or VP9 streams into an IVF file, the simple container used by the reference
+ * encoders and decoders and in codec conformance suites.
+ *
+ * The timebase of the file is derived from the framerate of the input if known, otherwise
+ * timestamps are stored in milliseconds. If downstream is seekable, the number of frames in the
+ * file header is updated at EOS.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 videotestsrc num-buffers=100 ! rav1enc ! ivfmux ! filesink location=test.ivf
+ * ```
+ *
+ * Since: plugins-rs-0.14.0
+ */
+glib::wrapper! {
+    pub struct IvfMux(ObjectSubclass<imp::IvfMux>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "ivfmux",
+        gst::Rank::NONE,
+        IvfMux::static_type(),
+    )
+}
@@ -0,0 +1,304 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::{LazyLock, Mutex};
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use crate::ivf::{self, Codec, FileHeader};
+
+struct State {
+    adapter: gst_base::UniqueAdapter,
+    header: Option<FileHeader>,
+    // Resolution of the last caps that were sent downstream
+    size: Option<(u16, u16)>,
+    need_segment: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            adapter: gst_base::UniqueAdapter::new(),
+            header: None,
+            size: None,
+            need_segment: true,
+        }
+    }
+}
+
+pub struct IvfParse {
+    sinkpad: gst::Pad,
+    srcpad: gst::Pad,
+
+    state: Mutex<State>,
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "ivfparse",
+        gst::DebugColorFlags::empty(),
+        Some("IVF parser"),
+    )
+});
+
+impl IvfParse {
+    fn caps(&self, header: &FileHeader, (width, height): (u16, u16)) -> gst::Caps {
+        let mut caps = header.codec.caps_builder().field(
+            "framerate",
+            gst::Fraction::new(header.timebase_den as i32, header.timebase_num as i32),
+        );
+
+        if width > 0 && height > 0 {
+            caps = caps
+                .field("width", width as i32)
+                .field("height", height as i32);
+        }
+
+        caps.build()
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, obj = pad, "Handling buffer {:?}", buffer);
+
+        let mut state = self.state.lock().unwrap();
+        state.adapter.push(buffer);
+
+        loop {
+            let Some(header) = state.header else {
+                if state.adapter.available() < ivf::FILE_HEADER_SIZE {
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+
+                let data = state
+                    .adapter
+                    .take_buffer(ivf::FILE_HEADER_SIZE)
+                    .unwrap()
+                    .map_readable()
+                    .map_err(|_| gst::FlowError::Error)?
+                    .to_vec();
+                let header = FileHeader::parse(&data).map_err(|err| {
+                    self.post_error_message(err);
+                    gst::FlowError::Error
+                })?;
+
+                gst::debug!(CAT, imp = self, "Parsed file header {header:?}");
+                state.header = Some(header);
+                continue;
+            };
+
+            if state.adapter.available() < ivf::FRAME_HEADER_SIZE {
+                return Ok(gst::FlowSuccess::Ok);
+            }
+
+            let mut frame_header = [0u8; ivf::FRAME_HEADER_SIZE];
+            state.adapter.copy(0, &mut frame_header).unwrap();
+            let (frame_size, ivf_pts) = ivf::parse_frame_header(&frame_header);
+
+            if state.adapter.available() < ivf::FRAME_HEADER_SIZE + frame_size as usize {
+                return Ok(gst::FlowSuccess::Ok);
+            }
+
+            state.adapter.flush(ivf::FRAME_HEADER_SIZE);
+            let mut frame = state
+                .adapter
+                .take_buffer(frame_size as usize)
+                .map_err(|_| gst::FlowError::Error)?;
+
+            let (keyframe_size, is_vp8_delta) = {
+                let map = frame.map_readable().map_err(|_| gst::FlowError::Error)?;
+                (
+                    ivf::keyframe_size(header.codec, &map),
+                    header.codec == Codec::Vp8 && map.first().is_some_and(|tag| tag & 0x01 != 0),
+                )
+            };
+
+            let size = keyframe_size
+                .or(state.size)
+                .unwrap_or((header.width, header.height));
+            let caps = if state.size != Some(size) {
+                gst::debug!(CAT, imp = self, "Resolution {}x{}", size.0, size.1);
+                state.size = Some(size);
+                Some(self.caps(&header, size))
+            } else {
+                None
+            };
+            let need_segment = std::mem::take(&mut state.need_segment);
+            drop(state);
+
+            if let Some(caps) = caps {
+                if !self.srcpad.push_event(gst::event::Caps::new(&caps)) {
+                    gst::error!(CAT, imp = self, "Failed to negotiate {caps:?}");
+                    return Err(gst::FlowError::NotNegotiated);
+                }
+            }
+
+            if need_segment {
+                let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+                let _ = self.srcpad.push_event(gst::event::Segment::new(&segment));
+            }
+
+            {
+                let frame = frame.make_mut();
+                frame.set_pts(header.pts_to_clock_time(ivf_pts));
+                frame.set_dts(gst::ClockTime::NONE);
+                frame.set_offset(gst::BUFFER_OFFSET_NONE);
+                if is_vp8_delta {
+                    frame.set_flags(gst::BufferFlags::DELTA_UNIT);
+                }
+            }
+
+            gst::trace!(CAT, imp = self, "Pushing frame {frame:?}");
+            self.srcpad.push(frame)?;
+
+            state = self.state.lock().unwrap();
+        }
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {:?}", event);
+
+        match event.view() {
+            // Caps and a time segment are sent once the file header is parsed
+            EventView::Caps(_) | EventView::Segment(_) => true,
+            EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.adapter.clear();
+                state.need_segment = true;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            EventView::Eos(_) => {
+                if self.state.lock().unwrap().header.is_none() {
+                    gst::element_imp_error!(
+                        self,
+                        gst::StreamError::WrongType,
+                        ["No IVF file header found"]
+                    );
+                }
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for IvfParse {
+    const NAME: &'static str = "GstIvfParse";
+    type Type = super::IvfParse;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                IvfParse::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                IvfParse::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for IvfParse {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for IvfParse {}
+
+impl ElementImpl for IvfParse {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "IVF Parser",
+                "Codec/Demuxer",
+                "Parses IVF files containing AV1, VP8 and VP9 streams",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &Codec::all_caps(),
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("video/x-ivf").build(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {:?}", transition);
+
+        let ret = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                *self.state.lock().unwrap() = State::default();
+            }
+            _ => (),
+        }
+
+        Ok(ret)
+    }
+}
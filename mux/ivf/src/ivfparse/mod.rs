@@ -0,0 +1,39 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+/**
+ * SECTION:element-ivfparse
+ *
+ * Parses IVF files containing AV1, VP8 or VP9 streams and outputs the frames with their
+ * timestamps. AV1 is output as OBU stream aligned to temporal units.
+ *
+ * The caps are updated whenever a VP8 keyframe or an AV1 sequence header signals a different
+ * resolution than the one of the file header.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 filesrc location=test.ivf ! ivfparse ! dav1ddec ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.14.0
+ */
+glib::wrapper! {
+    pub struct IvfParse(ObjectSubclass<imp::IvfParse>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "ivfparse",
+        gst::Rank::MARGINAL,
+        IvfParse::static_type(),
+    )
+}
@@ -0,0 +1,147 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst::ClockTime;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsivf::plugin_register_static().unwrap();
+    });
+}
+
+fn vp8_keyframe(width: u16, height: u16) -> Vec<u8> {
+    let mut data = vec![0x00, 0x00, 0x00, 0x9d, 0x01, 0x2a];
+    data.extend_from_slice(&width.to_le_bytes());
+    data.extend_from_slice(&height.to_le_bytes());
+    data.extend_from_slice(&[0xaa; 6]);
+    data
+}
+
+fn vp8_delta_frame() -> Vec<u8> {
+    vec![0x01, 0x00, 0x00, 0xbb, 0xbb, 0xbb]
+}
+
+fn mux(caps: &str, frames: &[(Vec<u8>, bool)]) -> Vec<u8> {
+    let mut h = gst_check::Harness::new("ivfmux");
+    h.set_src_caps_str(caps);
+
+    for (i, (data, delta)) in frames.iter().enumerate() {
+        let mut buffer = gst::Buffer::from_slice(data.clone());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(ClockTime::from_mseconds(i as u64 * 100));
+            if *delta {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    let mut output = Vec::new();
+    while let Some(buffer) = h.try_pull() {
+        output.extend_from_slice(&buffer.map_readable().unwrap());
+    }
+
+    output
+}
+
+fn output_caps(h: &mut gst_check::Harness) -> Vec<gst::Caps> {
+    let mut caps = Vec::new();
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Caps(ev) = event.view() {
+            caps.push(ev.caps_owned());
+        }
+    }
+    caps
+}
+
+#[test]
+fn test_vp8_roundtrip() {
+    init();
+
+    let frames = vec![
+        (vp8_keyframe(320, 240), false),
+        (vp8_delta_frame(), true),
+        (vp8_delta_frame(), true),
+    ];
+    let data = mux("video/x-vp8,width=320,height=240,framerate=10/1", &frames);
+
+    assert_eq!(&data[0..4], b"DKIF");
+    assert_eq!(&data[8..12], b"VP80");
+    assert_eq!(
+        data.len(),
+        32 + frames.iter().map(|f| 12 + f.0.len()).sum::<usize>()
+    );
+
+    let mut h = gst_check::Harness::new("ivfparse");
+    h.set_src_caps_str("video/x-ivf");
+
+    // Feed the file in small chunks to exercise the frame reassembly
+    for chunk in data.chunks(7) {
+        assert_eq!(
+            h.push(gst::Buffer::from_slice(chunk.to_vec())),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+
+    for (i, (frame, delta)) in frames.iter().enumerate() {
+        let buffer = h.pull().unwrap();
+        assert_eq!(buffer.pts(), Some(ClockTime::from_mseconds(i as u64 * 100)));
+        assert_eq!(
+            buffer.flags().contains(gst::BufferFlags::DELTA_UNIT),
+            *delta
+        );
+        assert_eq!(&*buffer.map_readable().unwrap(), frame.as_slice());
+    }
+
+    let caps = output_caps(&mut h);
+    assert_eq!(
+        caps,
+        vec![gst::Caps::builder("video/x-vp8")
+            .field("framerate", gst::Fraction::new(10, 1))
+            .field("width", 320)
+            .field("height", 240)
+            .build()]
+    );
+}
+
+#[test]
+fn test_vp8_resolution_change() {
+    init();
+
+    let frames = vec![
+        (vp8_keyframe(320, 240), false),
+        (vp8_delta_frame(), true),
+        (vp8_keyframe(640, 480), false),
+    ];
+    let data = mux("video/x-vp8,width=320,height=240,framerate=10/1", &frames);
+
+    let mut h = gst_check::Harness::new("ivfparse");
+    h.set_src_caps_str("video/x-ivf");
+    assert_eq!(
+        h.push(gst::Buffer::from_slice(data)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    let sizes = output_caps(&mut h)
+        .iter()
+        .map(|caps| {
+            let s = caps.structure(0).unwrap();
+            (
+                s.get::<i32>("width").unwrap(),
+                s.get::<i32>("height").unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(sizes, vec![(320, 240), (640, 480)]);
+    assert_eq!(h.buffers_in_queue(), 3);
+}
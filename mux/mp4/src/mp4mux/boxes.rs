@@ -222,9 +222,70 @@ fn write_moov(v: &mut Vec<u8>, header: &super::Header) -> Result<(), Error> {
         })?;
     }
 
+    // Gapless playback information for AAC as written by iTunes
+    if let Some(gapless) = header
+        .streams
+        .iter()
+        .filter(|stream| stream.caps.structure(0).unwrap().name() == "audio/mpeg")
+        .find_map(|stream| stream.gapless)
+    {
+        write_box(v, b"udta", |v| {
+            write_full_box(v, b"meta", FULL_BOX_VERSION_0, FULL_BOX_FLAGS_NONE, |v| {
+                write_itunes_smpb(v, &gapless)
+            })
+        })?;
+    }
+
     Ok(())
 }
 
+fn write_itunes_smpb(v: &mut Vec<u8>, gapless: &super::GaplessInfo) -> Result<(), Error> {
+    write_full_box(v, b"hdlr", FULL_BOX_VERSION_0, FULL_BOX_FLAGS_NONE, |v| {
+        // Pre-defined
+        v.extend([0u8; 4]);
+        // Handler type
+        v.extend(b"mdir");
+        // Reserved, with the manufacturer as used by iTunes
+        v.extend(b"appl");
+        v.extend([0u8; 2 * 4]);
+        // Name
+        v.push(0);
+
+        Ok(())
+    })?;
+
+    write_box(v, b"ilst", |v| {
+        write_box(v, b"----", |v| {
+            write_full_box(v, b"mean", FULL_BOX_VERSION_0, FULL_BOX_FLAGS_NONE, |v| {
+                v.extend(b"com.apple.iTunes");
+                Ok(())
+            })?;
+            write_full_box(v, b"name", FULL_BOX_VERSION_0, FULL_BOX_FLAGS_NONE, |v| {
+                v.extend(b"iTunSMPB");
+                Ok(())
+            })?;
+            write_box(v, b"data", |v| {
+                // Type: UTF-8
+                v.extend(1u32.to_be_bytes());
+                // Locale
+                v.extend(0u32.to_be_bytes());
+                v.extend(
+                    format!(
+                        " 00000000 {:08X} {:08X} {:016X}{}",
+                        gapless.delay,
+                        gapless.padding,
+                        gapless.samples,
+                        " 00000000".repeat(8),
+                    )
+                    .as_bytes(),
+                );
+
+                Ok(())
+            })
+        })
+    })
+}
+
 fn header_to_timescale(header: &super::Header) -> u32 {
     if header.movie_timescale > 0 {
         header.movie_timescale
@@ -316,6 +377,84 @@ fn write_trak(
     }
     write_box(v, b"edts", |v| write_edts(v, stream))?;
 
+    if !stream.loudness.is_empty() {
+        write_box(v, b"udta", |v| write_ludt(v, &stream.loudness))?;
+    }
+
+    Ok(())
+}
+
+fn write_ludt(v: &mut Vec<u8>, loudness: &super::Loudness) -> Result<(), Error> {
+    write_box(v, b"ludt", |v| {
+        write_full_box(v, b"tlou", FULL_BOX_VERSION_0, FULL_BOX_FLAGS_NONE, |v| {
+            write_tlou(v, loudness)
+        })
+    })
+}
+
+/// Peak level in dB as 12 bit value in 1/32 dB steps below +20 dB, 0 if unknown.
+fn peak_level_to_bs(level: Option<f64>) -> u32 {
+    match level {
+        Some(level) => ((20.0 - level) * 32.0).round().clamp(1.0, 4095.0) as u32,
+        None => 0,
+    }
+}
+
+fn write_tlou(v: &mut Vec<u8>, loudness: &super::Loudness) -> Result<(), Error> {
+    // Measurement system: EBU R128 / ITU-R BS.1770-4
+    const MEASUREMENT_SYSTEM_EBU_R128: u8 = 1;
+    const MEASUREMENT_SYSTEM_BS_1770_4: u32 = 2;
+    // Reliability: measured, accurate
+    const RELIABILITY_ACCURATE: u8 = 3;
+    // Method definitions
+    const METHOD_PROGRAM_LOUDNESS: u8 = 1;
+    const METHOD_LOUDNESS_RANGE: u8 = 6;
+
+    // Reserved (3 bits), downmix ID (7 bits), DRC set ID (6 bits)
+    v.extend(0u16.to_be_bytes());
+
+    // Sample peak level (12 bits), true peak level (12 bits), true peak measurement
+    // system (4 bits), true peak reliability (4 bits)
+    let (tp_measurement_system, tp_reliability) = if loudness.true_peak.is_some() {
+        (MEASUREMENT_SYSTEM_BS_1770_4, RELIABILITY_ACCURATE as u32)
+    } else {
+        (0, 0)
+    };
+    v.extend(
+        ((peak_level_to_bs(loudness.sample_peak) << 20)
+            | (peak_level_to_bs(loudness.true_peak) << 8)
+            | (tp_measurement_system << 4)
+            | tp_reliability)
+            .to_be_bytes(),
+    );
+
+    let mut measurements = Vec::new();
+    if let Some(integrated) = loudness.integrated {
+        // 1/4 LU steps starting at -57.75 LUFS
+        let value = ((integrated + 57.75) * 4.0).round().clamp(0.0, 255.0) as u8;
+        measurements.push((METHOD_PROGRAM_LOUDNESS, value));
+    }
+    if let Some(range) = loudness.range {
+        // 1/4 LU steps up to 32 LU, 1/2 LU steps up to 70 LU, 1 LU steps up to 121 LU
+        let value = if range <= 32.0 {
+            (range * 4.0).round().max(0.0)
+        } else if range <= 70.0 {
+            ((range - 32.0) * 2.0).round() + 128.0
+        } else {
+            ((range - 70.0).round() + 204.0).min(255.0)
+        };
+        measurements.push((METHOD_LOUDNESS_RANGE, value as u8));
+    }
+
+    // Measurement count
+    v.push(measurements.len() as u8);
+    for (method_definition, method_value) in measurements {
+        v.push(method_definition);
+        v.push(method_value);
+        // Measurement system (4 bits), reliability (4 bits)
+        v.push((MEASUREMENT_SYSTEM_EBU_R128 << 4) | RELIABILITY_ACCURATE);
+    }
+
     Ok(())
 }
 
@@ -1155,7 +1294,11 @@ fn write_audio_sample_entry(
                 write_esds_aac(v, &map)?;
             }
             "audio/x-opus" => {
-                write_dops(v, &stream.caps)?;
+                write_dops(
+                    v,
+                    &stream.caps,
+                    stream.gapless.map_or(0, |gapless| gapless.delay),
+                )?;
             }
             "audio/x-flac" => {
                 write_dfla(v, &stream.caps)?;
@@ -1295,18 +1438,16 @@ fn write_esds_aac(v: &mut Vec<u8>, codec_data: &[u8]) -> Result<(), Error> {
     )
 }
 
-fn write_dops(v: &mut Vec<u8>, caps: &gst::Caps) -> Result<(), Error> {
+fn write_dops(v: &mut Vec<u8>, caps: &gst::Caps, encoder_delay: u64) -> Result<(), Error> {
     let rate;
     let channels;
     let channel_mapping_family;
     let stream_count;
     let coupled_count;
-    let pre_skip;
+    let mut pre_skip;
     let output_gain;
     let mut channel_mapping = [0; 256];
 
-    // TODO: Use audio clipping meta to calculate pre_skip
-
     if let Some(header) = caps
         .structure(0)
         .unwrap()
@@ -1336,6 +1477,14 @@ fn write_dops(v: &mut Vec<u8>, caps: &gst::Caps) -> Result<(), Error> {
         pre_skip = 0;
     }
 
+    // The encoder delay is in samples at the input rate but pre-skip is always at 48kHz
+    if pre_skip == 0 && encoder_delay > 0 && rate > 0 {
+        pre_skip = encoder_delay
+            .mul_div_round(48_000, rate as u64)
+            .and_then(|pre_skip| u16::try_from(pre_skip).ok())
+            .unwrap_or(u16::MAX);
+    }
+
     write_box(v, b"dOps", move |v| {
         // Version number
         v.push(0);
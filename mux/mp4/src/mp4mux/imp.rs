@@ -148,6 +148,15 @@ struct Stream {
 
     /// Orientation from tags
     orientation: Option<ImageOrientation>,
+
+    /// Loudness measurements from tags
+    loudness: super::Loudness,
+
+    /// Encoder delay in samples, from the clipping meta of the first buffer
+    encoder_delay: u64,
+
+    /// Encoder padding in samples, from the clipping meta of the last buffer
+    encoder_padding: u64,
 }
 
 impl Stream {
@@ -225,6 +234,27 @@ impl Stream {
         Ok(elst_infos)
     }
 
+    fn get_gapless_info(
+        &self,
+        earliest_pts: gst::ClockTime,
+        end_pts: gst::ClockTime,
+    ) -> Option<super::GaplessInfo> {
+        if self.encoder_delay == 0 && self.encoder_padding == 0 {
+            return None;
+        }
+
+        let rate = self.caps.structure(0).unwrap().get::<i32>("rate").ok()?;
+        let samples = (end_pts - earliest_pts)
+            .nseconds()
+            .mul_div_round(rate as u64, gst::ClockTime::SECOND.nseconds())?;
+
+        Some(super::GaplessInfo {
+            delay: self.encoder_delay,
+            padding: self.encoder_padding,
+            samples: samples.saturating_sub(self.encoder_delay + self.encoder_padding),
+        })
+    }
+
     fn timescale(&self) -> u32 {
         let trak_timescale = { self.sinkpad.imp().settings.lock().unwrap().trak_timescale };
 
@@ -367,6 +397,14 @@ impl MP4Mux {
             ));
         }
 
+        // Clipping at the start of the stream is the encoder delay, at the end the padding
+        if let Some(start) = start.filter(|_| stream.elst_infos.is_empty()) {
+            stream.encoder_delay = start;
+        }
+        if let Some(end) = end {
+            stream.encoder_padding = end;
+        }
+
         let start = if let Some(start) = generic_to_samples(cmeta.start())? {
             start + gstclocktime_to_samples(buffer.pts)?
         } else {
@@ -1222,6 +1260,9 @@ impl MP4Mux {
                 running_time_utc_time_mapping: None,
                 extra_header_data: None,
                 orientation: None,
+                loudness: Default::default(),
+                encoder_delay: 0,
+                encoder_padding: 0,
             });
         }
 
@@ -1483,6 +1524,23 @@ impl AggregatorImpl for MP4Mux {
                     }
                 }
 
+                let mut state = self.state.lock().unwrap();
+                if let Some(stream) = state
+                    .streams
+                    .iter_mut()
+                    .find(|s| &s.sinkpad == aggregator_pad)
+                {
+                    if stream.loudness.update_from_tags(ev.tag()) {
+                        gst::trace!(
+                            CAT,
+                            obj = aggregator_pad,
+                            "Loudness from tags: {:?}",
+                            stream.loudness
+                        );
+                    }
+                }
+                drop(state);
+
                 self.parent_sink_event_pre_queue(aggregator_pad, event)
             }
             _ => self.parent_sink_event_pre_queue(aggregator_pad, event),
@@ -1696,6 +1754,8 @@ impl AggregatorImpl for MP4Mux {
                     chunks: stream.chunks,
                     extra_header_data: stream.extra_header_data.clone(),
                     orientation: stream.orientation,
+                    loudness: stream.loudness,
+                    gapless: stream.get_gapless_info(earliest_pts, end_pts),
                 });
             }
 
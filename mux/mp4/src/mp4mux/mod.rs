@@ -35,6 +35,8 @@ pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
         MP4Mux::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
         MP4MuxPad::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     }
+    register_loudness_tags();
+
     gst::Element::register(
         Some(plugin),
         "isomp4mux",
@@ -51,6 +53,105 @@ pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     Ok(())
 }
 
+/// Integrated (programme) loudness in LUFS, e.g. as measured by EBU R128.
+pub(crate) enum LoudnessIntegratedTag {}
+/// Loudness range in LU.
+pub(crate) enum LoudnessRangeTag {}
+/// Maximum true peak level in dBTP.
+pub(crate) enum LoudnessTruePeakTag {}
+/// Maximum sample peak level in dBFS.
+pub(crate) enum LoudnessSamplePeakTag {}
+
+macro_rules! loudness_tag {
+    ($tag:ident, $name:literal, $nick:literal, $description:literal) => {
+        impl<'a> gst::tags::Tag<'a> for $tag {
+            type TagType = f64;
+            const TAG_NAME: &'static glib::GStr = glib::gstr!($name);
+        }
+
+        impl<'a> gst::tags::CustomTag<'a> for $tag {
+            const FLAG: gst::TagFlag = gst::TagFlag::Meta;
+            const NICK: &'static glib::GStr = glib::gstr!($nick);
+            const DESCRIPTION: &'static glib::GStr = glib::gstr!($description);
+        }
+    };
+}
+
+loudness_tag!(
+    LoudnessIntegratedTag,
+    "loudness-integrated",
+    "integrated loudness",
+    "Integrated loudness in LUFS"
+);
+loudness_tag!(
+    LoudnessRangeTag,
+    "loudness-range",
+    "loudness range",
+    "Loudness range in LU"
+);
+loudness_tag!(
+    LoudnessTruePeakTag,
+    "loudness-true-peak",
+    "true peak",
+    "Maximum true peak level in dBTP"
+);
+loudness_tag!(
+    LoudnessSamplePeakTag,
+    "loudness-sample-peak",
+    "sample peak",
+    "Maximum sample peak level in dBFS"
+);
+
+fn register_loudness_tags() {
+    gst::tags::register::<LoudnessIntegratedTag>();
+    gst::tags::register::<LoudnessRangeTag>();
+    gst::tags::register::<LoudnessTruePeakTag>();
+    gst::tags::register::<LoudnessSamplePeakTag>();
+}
+
+/// Loudness measurements of a stream, written into the `ludt` box.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub(crate) struct Loudness {
+    /// Integrated loudness in LUFS
+    integrated: Option<f64>,
+    /// Loudness range in LU
+    range: Option<f64>,
+    /// True peak in dBTP
+    true_peak: Option<f64>,
+    /// Sample peak in dBFS
+    sample_peak: Option<f64>,
+}
+
+impl Loudness {
+    /// Update with the values from the tag list, keeping the previous values for all
+    /// measurements that are not included. Returns `true` if anything changed.
+    fn update_from_tags(&mut self, tags: &gst::TagListRef) -> bool {
+        let old = *self;
+
+        if let Some(v) = tags.get::<LoudnessIntegratedTag>() {
+            self.integrated = Some(v.get());
+        }
+        if let Some(v) = tags.get::<LoudnessRangeTag>() {
+            self.range = Some(v.get());
+        }
+        if let Some(v) = tags.get::<LoudnessTruePeakTag>() {
+            self.true_peak = Some(v.get());
+        }
+        if let Some(v) = tags.get::<LoudnessSamplePeakTag>() {
+            self.sample_peak = Some(v.get());
+        }
+
+        *self != old
+    }
+
+    fn is_empty(&self) -> bool {
+        self.integrated.is_none()
+            && self.range.is_none()
+            && self.true_peak.is_none()
+            && self.sample_peak.is_none()
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 pub(crate) enum ImageOrientation {
     Rotate0,
@@ -178,6 +279,17 @@ pub(crate) struct ElstInfo {
     duration: Option<u64>,
 }
 
+/// Gapless playback information of an audio stream, in samples
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct GaplessInfo {
+    /// Encoder delay clipped at the beginning of the stream
+    delay: u64,
+    /// Padding clipped at the end of the stream
+    padding: u64,
+    /// Number of samples of the stream without delay and padding
+    samples: u64,
+}
+
 #[derive(Debug)]
 pub(crate) struct Stream {
     /// Caps of this stream
@@ -206,8 +318,14 @@ pub(crate) struct Stream {
     /// Orientation from tags
     orientation: Option<ImageOrientation>,
 
+    /// Loudness measurements from tags
+    loudness: Loudness,
+
     /// Edit list clipping information
     elst_infos: Vec<ElstInfo>,
+
+    /// Encoder delay and padding from the audio clipping metas
+    gapless: Option<GaplessInfo>,
 }
 
 #[derive(Debug)]
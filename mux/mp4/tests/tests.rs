@@ -172,3 +172,129 @@ fn test_roundtrip_av1_aac() {
         pipeline.into_completion();
     })
 }
+
+fn mux_clipped_audio(
+    caps: gst::Caps,
+    samples_per_buffer: u64,
+    delay: u64,
+    padding: u64,
+) -> Vec<u8> {
+    let mut h = gst_check::Harness::with_padnames("isomp4mux", Some("sink_0"), Some("src"));
+    h.set_src_caps(caps);
+    h.play();
+
+    for i in 0..10 {
+        let pts = (i * samples_per_buffer)
+            .mul_div_floor(gst::ClockTime::SECOND.nseconds(), 48_000)
+            .map(gst::ClockTime::from_nseconds)
+            .unwrap();
+        let end_pts = ((i + 1) * samples_per_buffer)
+            .mul_div_floor(gst::ClockTime::SECOND.nseconds(), 48_000)
+            .map(gst::ClockTime::from_nseconds)
+            .unwrap();
+
+        let mut buffer = gst::Buffer::with_size(1).unwrap();
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(pts);
+            buffer.set_dts(pts);
+            buffer.set_duration(end_pts - pts);
+
+            if i == 0 {
+                gst_audio::AudioClippingMeta::add(
+                    buffer,
+                    gst::format::Default::from_u64(delay),
+                    gst::format::Default::ZERO,
+                );
+            } else if i == 9 {
+                gst_audio::AudioClippingMeta::add(
+                    buffer,
+                    gst::format::Default::ZERO,
+                    gst::format::Default::from_u64(padding),
+                );
+            }
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // The moov box is written after all media data
+    let mut data = Vec::new();
+    while let Some(buffer) = h.try_pull() {
+        data.extend_from_slice(&buffer.map_readable().unwrap());
+    }
+
+    data
+}
+
+#[test]
+fn test_gapless_aac() {
+    init();
+
+    // 10 frames of 1024 samples with 576 samples of delay and 300 samples of padding
+    let data = mux_clipped_audio(
+        gst::Caps::builder("audio/mpeg")
+            .field("mpegversion", 4i32)
+            .field("channels", 1i32)
+            .field("rate", 48000i32)
+            .field("stream-format", "raw")
+            .field("base-profile", "lc")
+            .field("profile", "lc")
+            .field("level", "2")
+            .field(
+                "codec_data",
+                gst::Buffer::from_slice([0x11, 0x88, 0x56, 0xe5, 0x00]),
+            )
+            .build(),
+        1024,
+        576,
+        300,
+    );
+
+    let pos = data
+        .windows(8)
+        .position(|w| w == b"iTunSMPB")
+        .expect("no iTunSMPB");
+    let itunes_data = &data[pos + 8..];
+    assert_eq!(&itunes_data[4..8], b"data");
+    let size = u32::from_be_bytes(itunes_data[..4].try_into().unwrap()) as usize;
+    // Type UTF-8 and locale
+    assert_eq!(itunes_data[8..16], [0, 0, 0, 1, 0, 0, 0, 0]);
+    assert_eq!(
+        std::str::from_utf8(&itunes_data[16..size]).unwrap(),
+        format!(
+            " 00000000 00000240 0000012C {:016X}{}",
+            10 * 1024 - 576 - 300,
+            " 00000000".repeat(8)
+        )
+    );
+}
+
+#[test]
+fn test_gapless_opus() {
+    init();
+
+    // 10 frames of 20ms with 312 samples of pre-skip
+    let data = mux_clipped_audio(
+        gst::Caps::builder("audio/x-opus")
+            .field("channel-mapping-family", 0i32)
+            .field("channels", 2i32)
+            .field("rate", 48000i32)
+            .build(),
+        960,
+        312,
+        100,
+    );
+
+    // The encoder delay is written as pre-skip, iTunSMPB is only written for AAC
+    assert!(!data.windows(8).any(|w| w == b"iTunSMPB"));
+
+    let pos = data
+        .windows(4)
+        .position(|w| w == b"dOps")
+        .expect("no dOps box");
+    let dops = &data[pos + 4..];
+    // Version 0 and two channels
+    assert_eq!(dops[..2], [0, 2]);
+    assert_eq!(u16::from_be_bytes(dops[2..4].try_into().unwrap()), 312);
+}
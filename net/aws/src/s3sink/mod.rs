@@ -56,6 +56,74 @@ pub(crate) enum OnError {
     DoNothing,
 }
 
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstS3SinkStorageClass")]
+pub(crate) enum StorageClass {
+    #[default]
+    #[enum_value(
+        name = "Default: Use the storage class of the bucket.",
+        nick = "default"
+    )]
+    Default,
+    #[enum_value(name = "Standard", nick = "standard")]
+    Standard,
+    #[enum_value(name = "Standard - Infrequent Access", nick = "standard-ia")]
+    StandardIa,
+    #[enum_value(name = "One Zone - Infrequent Access", nick = "onezone-ia")]
+    OnezoneIa,
+    #[enum_value(name = "Intelligent-Tiering", nick = "intelligent-tiering")]
+    IntelligentTiering,
+    #[enum_value(name = "Glacier Instant Retrieval", nick = "glacier-ir")]
+    GlacierIr,
+    #[enum_value(name = "Glacier Flexible Retrieval", nick = "glacier")]
+    Glacier,
+    #[enum_value(name = "Glacier Deep Archive", nick = "deep-archive")]
+    DeepArchive,
+}
+
+impl From<StorageClass> for Option<aws_sdk_s3::types::StorageClass> {
+    fn from(value: StorageClass) -> Self {
+        use aws_sdk_s3::types::StorageClass as S3StorageClass;
+
+        match value {
+            StorageClass::Default => None,
+            StorageClass::Standard => Some(S3StorageClass::Standard),
+            StorageClass::StandardIa => Some(S3StorageClass::StandardIa),
+            StorageClass::OnezoneIa => Some(S3StorageClass::OnezoneIa),
+            StorageClass::IntelligentTiering => Some(S3StorageClass::IntelligentTiering),
+            StorageClass::GlacierIr => Some(S3StorageClass::GlacierIr),
+            StorageClass::Glacier => Some(S3StorageClass::Glacier),
+            StorageClass::DeepArchive => Some(S3StorageClass::DeepArchive),
+        }
+    }
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstS3SinkServerSideEncryption")]
+pub(crate) enum ServerSideEncryption {
+    #[default]
+    #[enum_value(name = "Default: Use the encryption of the bucket.", nick = "default")]
+    Default,
+    #[enum_value(name = "SSE-S3: Amazon S3 managed keys.", nick = "sse-s3")]
+    SseS3,
+    #[enum_value(name = "SSE-KMS: AWS KMS keys.", nick = "sse-kms")]
+    SseKms,
+}
+
+impl From<ServerSideEncryption> for Option<aws_sdk_s3::types::ServerSideEncryption> {
+    fn from(value: ServerSideEncryption) -> Self {
+        use aws_sdk_s3::types::ServerSideEncryption as S3ServerSideEncryption;
+
+        match value {
+            ServerSideEncryption::Default => None,
+            ServerSideEncryption::SseS3 => Some(S3ServerSideEncryption::Aes256),
+            ServerSideEncryption::SseKms => Some(S3ServerSideEncryption::AwsKms),
+        }
+    }
+}
+
 glib::wrapper! {
     pub struct S3Sink(ObjectSubclass<multipartsink::S3Sink>) @extends gst_base::BaseSink, gst::Element, gst::Object, @implements gst::URIHandler;
 }
@@ -34,7 +34,7 @@ use std::time::Duration;
 use crate::s3url::*;
 use crate::s3utils::{self, duration_from_millis, duration_to_millis, WaitError};
 
-use super::OnError;
+use super::{OnError, ServerSideEncryption, StorageClass};
 
 const DEFAULT_FORCE_PATH_STYLE: bool = false;
 const DEFAULT_RETRY_ATTEMPTS: u32 = 5;
@@ -112,6 +112,10 @@ struct Settings {
     secret_access_key: Option<String>,
     session_token: Option<String>,
     metadata: Option<gst::Structure>,
+    tagging: Option<gst::Structure>,
+    storage_class: StorageClass,
+    server_side_encryption: ServerSideEncryption,
+    sse_kms_key_id: Option<String>,
     retry_attempts: u32,
     multipart_upload_on_error: OnError,
     request_timeout: Duration,
@@ -152,6 +156,37 @@ impl Settings {
             hash
         })
     }
+
+    fn to_tagging(&self, imp: &S3Sink) -> Option<String> {
+        self.tagging.as_ref().map(|structure| {
+            let mut tagging = url::form_urlencoded::Serializer::new(String::new());
+
+            for (key, value) in structure.iter() {
+                if let Ok(Ok(value_str)) = value.transform::<String>().map(|v| v.get()) {
+                    gst::log!(CAT, imp = imp, "tag '{}' -> '{}'", key, value_str);
+                    tagging.append_pair(key, &value_str);
+                } else {
+                    gst::warning!(
+                        CAT,
+                        imp = imp,
+                        "Failed to convert tag '{}' to string ('{:?}')",
+                        key,
+                        value
+                    );
+                }
+            }
+
+            tagging.finish()
+        })
+    }
+
+    fn sse_kms_key_id(&self) -> Option<String> {
+        if self.server_side_encryption == ServerSideEncryption::SseKms {
+            self.sse_kms_key_id.clone()
+        } else {
+            None
+        }
+    }
 }
 
 impl Default for Settings {
@@ -169,6 +204,10 @@ impl Default for Settings {
             secret_access_key: None,
             session_token: None,
             metadata: None,
+            tagging: None,
+            storage_class: StorageClass::default(),
+            server_side_encryption: ServerSideEncryption::default(),
+            sse_kms_key_id: None,
             buffer_size: DEFAULT_BUFFER_SIZE,
             retry_attempts: DEFAULT_RETRY_ATTEMPTS,
             multipart_upload_on_error: DEFAULT_MULTIPART_UPLOAD_ON_ERROR,
@@ -368,6 +407,7 @@ impl S3Sink {
         let content_encoding = settings.content_encoding.clone();
         let content_language = settings.content_language.clone();
         let metadata = settings.to_metadata(self);
+        let tagging = settings.to_tagging(self);
 
         client
             .create_multipart_upload()
@@ -379,6 +419,10 @@ impl S3Sink {
             .set_content_encoding(content_encoding)
             .set_content_language(content_language)
             .set_metadata(metadata)
+            .set_tagging(tagging)
+            .set_storage_class(settings.storage_class.into())
+            .set_server_side_encryption(settings.server_side_encryption.into())
+            .set_ssekms_key_id(settings.sse_kms_key_id())
     }
 
     fn create_abort_multipart_upload_request(
@@ -716,6 +760,26 @@ impl ObjectImpl for S3Sink {
                     .blurb("A map of metadata to store with the object in S3; field values need to be convertible to strings.")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("tagging")
+                    .nick("Tagging")
+                    .blurb("A map of tags to set on the object in S3; field values need to be convertible to strings.")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("storage-class", StorageClass::default())
+                    .nick("Storage class")
+                    .blurb("Storage class of the object in S3")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("server-side-encryption", ServerSideEncryption::default())
+                    .nick("Server-side encryption")
+                    .blurb("Server-side encryption algorithm to use for the object in S3")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sse-kms-key-id")
+                    .nick("SSE-KMS key ID")
+                    .blurb("ID of the AWS KMS key to use with server-side-encryption=sse-kms, the AWS managed key is used if not set")
+                    .mutable_ready()
+                    .build(),
                 glib::ParamSpecEnum::builder_with_default("on-error", DEFAULT_MULTIPART_UPLOAD_ON_ERROR)
                     .nick("Whether to upload or complete the multipart upload on error")
                     .blurb("Do nothing, abort or complete a multipart upload request on error")
@@ -852,6 +916,23 @@ impl ObjectImpl for S3Sink {
             "metadata" => {
                 settings.metadata = value.get().expect("type checked upstream");
             }
+            "tagging" => {
+                settings.tagging = value.get().expect("type checked upstream");
+            }
+            "storage-class" => {
+                settings.storage_class =
+                    value.get::<StorageClass>().expect("type checked upstream");
+            }
+            "server-side-encryption" => {
+                settings.server_side_encryption = value
+                    .get::<ServerSideEncryption>()
+                    .expect("type checked upstream");
+            }
+            "sse-kms-key-id" => {
+                settings.sse_kms_key_id = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
             "on-error" => {
                 settings.multipart_upload_on_error =
                     value.get::<OnError>().expect("type checked upstream");
@@ -949,6 +1030,10 @@ impl ObjectImpl for S3Sink {
             "secret-access-key" => settings.secret_access_key.to_value(),
             "session-token" => settings.session_token.to_value(),
             "metadata" => settings.metadata.to_value(),
+            "tagging" => settings.tagging.to_value(),
+            "storage-class" => settings.storage_class.to_value(),
+            "server-side-encryption" => settings.server_side_encryption.to_value(),
+            "sse-kms-key-id" => settings.sse_kms_key_id.to_value(),
             "on-error" => settings.multipart_upload_on_error.to_value(),
             "retry-attempts" => settings.retry_attempts.to_value(),
             "request-timeout" => duration_to_millis(Some(settings.request_timeout)).to_value(),
@@ -981,6 +1066,10 @@ impl ElementImpl for S3Sink {
         static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
             #[cfg(feature = "doc")]
             OnError::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+            #[cfg(feature = "doc")]
+            StorageClass::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+            #[cfg(feature = "doc")]
+            ServerSideEncryption::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
             gst::subclass::ElementMetadata::new(
                 "Amazon S3 sink",
                 "Source/Network",
@@ -20,7 +20,7 @@ use aws_sdk_s3::{
     Client,
 };
 
-use super::NextFile;
+use super::{NextFile, ServerSideEncryption, StorageClass};
 use std::collections::HashMap;
 use std::convert::From;
 use std::sync::LazyLock;
@@ -92,6 +92,10 @@ struct Settings {
     secret_access_key: Option<String>,
     session_token: Option<String>,
     metadata: Option<gst::Structure>,
+    tagging: Option<gst::Structure>,
+    storage_class: StorageClass,
+    server_side_encryption: ServerSideEncryption,
+    sse_kms_key_id: Option<String>,
     retry_attempts: u32,
     request_timeout: Duration,
     endpoint_uri: Option<String>,
@@ -137,6 +141,37 @@ impl Settings {
             hash
         })
     }
+
+    fn to_tagging(&self, imp: &S3PutObjectSink) -> Option<String> {
+        self.tagging.as_ref().map(|structure| {
+            let mut tagging = url::form_urlencoded::Serializer::new(String::new());
+
+            for (key, value) in structure.iter() {
+                if let Ok(Ok(value_str)) = value.transform::<String>().map(|v| v.get()) {
+                    gst::log!(CAT, imp = imp, "tag '{}' -> '{}'", key, value_str);
+                    tagging.append_pair(key, &value_str);
+                } else {
+                    gst::warning!(
+                        CAT,
+                        imp = imp,
+                        "Failed to convert tag '{}' to string ('{:?}')",
+                        key,
+                        value
+                    );
+                }
+            }
+
+            tagging.finish()
+        })
+    }
+
+    fn sse_kms_key_id(&self) -> Option<String> {
+        if self.server_side_encryption == ServerSideEncryption::SseKms {
+            self.sse_kms_key_id.clone()
+        } else {
+            None
+        }
+    }
 }
 
 impl Default for Settings {
@@ -154,6 +189,10 @@ impl Default for Settings {
             secret_access_key: None,
             session_token: None,
             metadata: None,
+            tagging: None,
+            storage_class: StorageClass::default(),
+            server_side_encryption: ServerSideEncryption::default(),
+            sse_kms_key_id: None,
             retry_attempts: DEFAULT_RETRY_ATTEMPTS,
             request_timeout: Duration::from_millis(DEFAULT_REQUEST_TIMEOUT_MSEC),
             endpoint_uri: None,
@@ -401,6 +440,7 @@ impl S3PutObjectSink {
             Some(object)
         };
         let metadata = settings.to_metadata(self);
+        let tagging = settings.to_tagging(self);
         let client = &started_state.client;
 
         Ok(Some(
@@ -409,7 +449,11 @@ impl S3PutObjectSink {
                 .set_body(body)
                 .set_bucket(bucket)
                 .set_key(key)
-                .set_metadata(metadata),
+                .set_metadata(metadata)
+                .set_tagging(tagging)
+                .set_storage_class(settings.storage_class.into())
+                .set_server_side_encryption(settings.server_side_encryption.into())
+                .set_ssekms_key_id(settings.sse_kms_key_id()),
         ))
     }
 
@@ -577,6 +621,26 @@ impl ObjectImpl for S3PutObjectSink {
                     .blurb("A map of metadata to store with the object in S3; field values need to be convertible to strings.")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("tagging")
+                    .nick("Tagging")
+                    .blurb("A map of tags to set on the object in S3; field values need to be convertible to strings.")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("storage-class", StorageClass::default())
+                    .nick("Storage class")
+                    .blurb("Storage class of the object in S3")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("server-side-encryption", ServerSideEncryption::default())
+                    .nick("Server-side encryption")
+                    .blurb("Server-side encryption algorithm to use for the object in S3")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sse-kms-key-id")
+                    .nick("SSE-KMS key ID")
+                    .blurb("ID of the AWS KMS key to use with server-side-encryption=sse-kms, the AWS managed key is used if not set")
+                    .mutable_ready()
+                    .build(),
                 glib::ParamSpecUInt::builder("retry-attempts")
                     .nick("Retry attempts")
                     .blurb("Number of times AWS SDK attempts a request before abandoning the request")
@@ -705,6 +769,23 @@ impl ObjectImpl for S3PutObjectSink {
             "metadata" => {
                 settings.metadata = value.get().expect("type checked upstream");
             }
+            "tagging" => {
+                settings.tagging = value.get().expect("type checked upstream");
+            }
+            "storage-class" => {
+                settings.storage_class =
+                    value.get::<StorageClass>().expect("type checked upstream");
+            }
+            "server-side-encryption" => {
+                settings.server_side_encryption = value
+                    .get::<ServerSideEncryption>()
+                    .expect("type checked upstream");
+            }
+            "sse-kms-key-id" => {
+                settings.sse_kms_key_id = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
             "retry-attempts" => {
                 settings.retry_attempts = value.get::<u32>().expect("type checked upstream");
             }
@@ -795,6 +876,10 @@ impl ObjectImpl for S3PutObjectSink {
             "secret-access-key" => settings.secret_access_key.to_value(),
             "session-token" => settings.session_token.to_value(),
             "metadata" => settings.metadata.to_value(),
+            "tagging" => settings.tagging.to_value(),
+            "storage-class" => settings.storage_class.to_value(),
+            "server-side-encryption" => settings.server_side_encryption.to_value(),
+            "sse-kms-key-id" => settings.sse_kms_key_id.to_value(),
             "retry-attempts" => settings.retry_attempts.to_value(),
             "request-timeout" => duration_to_millis(Some(settings.request_timeout)).to_value(),
             "endpoint-uri" => settings.endpoint_uri.to_value(),
@@ -822,6 +907,10 @@ impl ElementImpl for S3PutObjectSink {
         static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
             #[cfg(feature = "doc")]
             NextFile::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+            #[cfg(feature = "doc")]
+            StorageClass::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+            #[cfg(feature = "doc")]
+            ServerSideEncryption::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
             gst::subclass::ElementMetadata::new(
                 "Amazon S3 PutObject sink",
                 "Source/Network",
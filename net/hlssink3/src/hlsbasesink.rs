@@ -7,7 +7,7 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use crate::playlist::Playlist;
+use crate::playlist::{write_multivariant_playlist, Playlist};
 use chrono::{DateTime, Duration, Utc};
 use gio::prelude::*;
 use gst::glib;
@@ -26,10 +26,12 @@ const DEFAULT_PLAYLIST_LENGTH: u32 = 5;
 const DEFAULT_PROGRAM_DATE_TIME_TAG: bool = false;
 const DEFAULT_CLOCK_TRACKING_FOR_PDT: bool = true;
 const DEFAULT_ENDLIST: bool = true;
+const DEFAULT_BANDWIDTH: u64 = 0;
 
 const SIGNAL_GET_PLAYLIST_STREAM: &str = "get-playlist-stream";
 const SIGNAL_GET_FRAGMENT_STREAM: &str = "get-fragment-stream";
 const SIGNAL_DELETE_FRAGMENT: &str = "delete-fragment";
+const SIGNAL_ADD_SESSION_DATA: &str = "add-session-data";
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -47,6 +49,9 @@ struct Settings {
     enable_program_date_time: bool,
     pdt_follows_pipeline_clock: bool,
     enable_endlist: bool,
+    multivariant_playlist_location: Option<String>,
+    bandwidth: u64,
+    session_data: Vec<(String, String)>,
 }
 
 impl Default for Settings {
@@ -59,6 +64,9 @@ impl Default for Settings {
             enable_program_date_time: DEFAULT_PROGRAM_DATE_TIME_TAG,
             pdt_follows_pipeline_clock: DEFAULT_CLOCK_TRACKING_FOR_PDT,
             enable_endlist: DEFAULT_ENDLIST,
+            multivariant_playlist_location: None,
+            bandwidth: DEFAULT_BANDWIDTH,
+            session_data: Vec::new(),
         }
     }
 }
@@ -72,6 +80,7 @@ pub struct PlaylistContext {
     playlist_location: String,
     max_num_segment_files: usize,
     playlist_length: u32,
+    multivariant_playlist_pending: bool,
 }
 
 #[derive(Default)]
@@ -141,6 +150,15 @@ impl ObjectImpl for HlsBaseSink {
                     .blurb("Write \"EXT-X-ENDLIST\" tag to manifest at the end of stream")
                     .default_value(DEFAULT_ENDLIST)
                     .build(),
+                glib::ParamSpecString::builder("multivariant-playlist-location")
+                    .nick("Multivariant Playlist Location")
+                    .blurb("Location of the multivariant playlist to write, referencing the playlist and carrying the session data. Not written if unset.")
+                    .build(),
+                glib::ParamSpecUInt64::builder("bandwidth")
+                    .nick("Bandwidth")
+                    .blurb("Peak bitrate in bits per second of the stream, as written to the multivariant playlist")
+                    .default_value(DEFAULT_BANDWIDTH)
+                    .build(),
             ]
         });
 
@@ -177,6 +195,14 @@ impl ObjectImpl for HlsBaseSink {
             "enable-endlist" => {
                 settings.enable_endlist = value.get().expect("type checked upstream");
             }
+            "multivariant-playlist-location" => {
+                settings.multivariant_playlist_location = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
+            "bandwidth" => {
+                settings.bandwidth = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         };
     }
@@ -194,6 +220,8 @@ impl ObjectImpl for HlsBaseSink {
             "enable-program-date-time" => settings.enable_program_date_time.to_value(),
             "pdt-follows-pipeline-clock" => settings.pdt_follows_pipeline_clock.to_value(),
             "enable-endlist" => settings.enable_endlist.to_value(),
+            "multivariant-playlist-location" => settings.multivariant_playlist_location.to_value(),
+            "bandwidth" => settings.bandwidth.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -250,6 +278,19 @@ impl ObjectImpl for HlsBaseSink {
                         false
                     })
                     .build(),
+                glib::subclass::Signal::builder(SIGNAL_ADD_SESSION_DATA)
+                    .param_types([String::static_type(), String::static_type()])
+                    .action()
+                    .class_handler(|_, args| {
+                        let elem = args[0].get::<super::HlsBaseSink>().expect("signal arg");
+                        let data_id = args[1].get::<String>().expect("signal arg");
+                        let value = args[2].get::<String>().expect("signal arg");
+                        let imp = elem.imp();
+
+                        imp.add_session_data(data_id, value);
+                        None
+                    })
+                    .build(),
             ]
         });
 
@@ -304,6 +345,7 @@ impl HlsBaseSink {
             playlist_location: settings.playlist_location.clone(),
             max_num_segment_files: settings.max_num_segment_files,
             playlist_length: settings.playlist_length,
+            multivariant_playlist_pending: true,
         });
     }
 
@@ -319,6 +361,31 @@ impl HlsBaseSink {
         }
     }
 
+    fn add_session_data(&self, data_id: String, value: String) {
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Adding session data {data_id} with value {value}"
+        );
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(context) = state.context.as_mut() {
+            // Written out together with the next segment
+            context.multivariant_playlist_pending = true;
+        }
+
+        let mut settings = self.settings.lock().unwrap();
+        if settings.multivariant_playlist_location.is_none() {
+            gst::warning!(
+                CAT,
+                imp = self,
+                "Session data is only written to the multivariant playlist, which is not configured"
+            );
+        }
+        settings.session_data.retain(|(id, _)| *id != data_id);
+        settings.session_data.push((data_id, value));
+    }
+
     pub fn get_fragment_stream(&self, fragment_id: u32) -> Option<(gio::OutputStream, String)> {
         let mut state = self.state.lock().unwrap();
         let context = match state.context.as_mut() {
@@ -503,6 +570,10 @@ impl HlsBaseSink {
             gst::FlowError::Error
         })?;
 
+        if context.multivariant_playlist_pending {
+            self.write_multivariant_playlist(context)?;
+        }
+
         if context.playlist.is_type_undefined() && context.max_num_segment_files > 0 {
             // Cleanup old segments from filesystem
             while context.old_segment_locations.len() > context.max_num_segment_files {
@@ -520,6 +591,55 @@ impl HlsBaseSink {
         Ok(gst::FlowSuccess::Ok)
     }
 
+    fn write_multivariant_playlist(
+        &self,
+        context: &mut PlaylistContext,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        context.multivariant_playlist_pending = false;
+
+        let (location, bandwidth, session_data) = {
+            let settings = self.settings.lock().unwrap();
+            let Some(location) = settings.multivariant_playlist_location.clone() else {
+                return Ok(gst::FlowSuccess::Ok);
+            };
+            (location, settings.bandwidth, settings.session_data.clone())
+        };
+
+        // The multivariant playlist is expected next to the playlist
+        let uri = path::Path::new(&context.playlist_location)
+            .file_name()
+            .and_then(|file_name| file_name.to_str())
+            .unwrap_or(&context.playlist_location)
+            .to_string();
+
+        let mut playlist_stream = self
+            .obj()
+            .emit_by_name::<Option<gio::OutputStream>>(SIGNAL_GET_PLAYLIST_STREAM, &[&location])
+            .ok_or_else(|| {
+                gst::error!(
+                    CAT,
+                    imp = self,
+                    "Could not get stream to write multivariant playlist content",
+                );
+                gst::FlowError::Error
+            })?
+            .into_write();
+
+        write_multivariant_playlist(&mut playlist_stream, &uri, bandwidth, &session_data)
+            .and_then(|_| playlist_stream.flush())
+            .map_err(|err| {
+                gst::error!(
+                    CAT,
+                    imp = self,
+                    "Could not write multivariant playlist: {err}"
+                );
+                gst::FlowError::Error
+            })?;
+
+        gst::debug!(CAT, imp = self, "Wrote new multivariant playlist file!");
+        Ok(gst::FlowSuccess::Ok)
+    }
+
     pub fn new_file_stream<P>(&self, location: &P) -> Result<gio::OutputStream, String>
     where
         P: AsRef<path::Path>,
@@ -28,6 +28,7 @@ const DEFAULT_LATENCY: gst::ClockTime =
     gst::ClockTime::from_mseconds((DEFAULT_TARGET_DURATION * 500) as u64);
 const SIGNAL_GET_INIT_STREAM: &str = "get-init-stream";
 const SIGNAL_NEW_PLAYLIST: &str = "new-playlist";
+const SIGNAL_INSERT_TIMED_METADATA: &str = "insert-timed-metadata";
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -274,6 +275,26 @@ impl ObjectImpl for HlsCmafSink {
                         None
                     })
                     .build(),
+                glib::subclass::Signal::builder(SIGNAL_INSERT_TIMED_METADATA)
+                    .param_types([gst::ClockTime::static_type(), gst::Buffer::static_type()])
+                    .action()
+                    .class_handler(|_token, args| {
+                        // Inserts an ID3 tag as `emsg` box in front of the segment that contains
+                        // the given running time.
+                        let elem = args[0].get::<super::HlsCmafSink>().expect("signal arg");
+                        let running_time =
+                            args[1].get::<Option<gst::ClockTime>>().expect("signal arg");
+                        let id3 = args[2].get::<gst::Buffer>().expect("signal arg");
+                        let cmafmux = elem.imp().settings.lock().unwrap().cmafmux.clone();
+
+                        cmafmux.emit_by_name::<()>(
+                            SIGNAL_INSERT_TIMED_METADATA,
+                            &[&running_time, &id3],
+                        );
+
+                        None
+                    })
+                    .build(),
             ]
         });
 
@@ -6,9 +6,14 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
-use m3u8_rs::{MediaPlaylist, MediaPlaylistType, MediaSegment};
+use m3u8_rs::{
+    ExtTag, MasterPlaylist, MediaPlaylist, MediaPlaylistType, MediaSegment, VariantStream,
+};
 use std::io::Write;
 
+/// Tag name of `EXT-X-SESSION-DATA` without the `#EXT` prefix
+const SESSION_DATA_TAG: &str = "-X-SESSION-DATA";
+
 /// An HLS playlist.
 ///
 /// Controls the changes that needs to happen in the playlist as new segments are added. This
@@ -107,6 +112,50 @@ impl Playlist {
     }
 }
 
+/// Writes a multivariant playlist with a single variant stream for the media playlist at `uri`,
+/// followed by one `EXT-X-SESSION-DATA` tag per `(DATA-ID, VALUE)` pair.
+pub fn write_multivariant_playlist<T: Write>(
+    w: &mut T,
+    uri: &str,
+    bandwidth: u64,
+    session_data: &[(String, String)],
+) -> std::io::Result<()> {
+    let playlist = MasterPlaylist {
+        variants: vec![VariantStream {
+            uri: String::from(uri),
+            bandwidth,
+            ..Default::default()
+        }],
+        unknown_tags: session_data
+            .iter()
+            .map(|(data_id, value)| ExtTag {
+                tag: String::from(SESSION_DATA_TAG),
+                rest: Some(format!(
+                    "DATA-ID=\"{}\",VALUE=\"{}\"",
+                    escape_quoted_string(data_id),
+                    escape_quoted_string(value)
+                )),
+            })
+            .collect(),
+        ..Default::default()
+    };
+
+    playlist.write_to(w)
+}
+
+/// Percent-encodes the characters that can't be part of a quoted-string (RFC 8216 4.2), and the
+/// percent sign itself so that the original value can be recovered.
+fn escape_quoted_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' | '\r' | '\n' | '%' => escaped.push_str(&format!("%{:02X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum PlaylistRenderState {
     Init,
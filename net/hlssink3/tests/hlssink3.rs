@@ -497,3 +497,103 @@ segments/my-own-filename-000.ts
 
     Ok(())
 }
+
+#[test]
+fn test_hlssink3_session_data() -> Result<(), ()> {
+    init();
+
+    const BUFFER_NB: i32 = 50;
+
+    let pipeline = gst::Pipeline::with_name("video_pipeline");
+
+    let video_src = try_create_element!("videotestsrc");
+    video_src.set_property("is-live", false);
+    video_src.set_property("num-buffers", BUFFER_NB);
+
+    let x264enc = try_create_element!("x264enc");
+    let h264parse = try_create_element!("h264parse");
+
+    let hlssink3 = gst::ElementFactory::make("hlssink3")
+        .name("test_hlssink3")
+        .property("multivariant-playlist-location", "multivariant.m3u8")
+        .property("bandwidth", 1_000_000u64)
+        .build()
+        .expect("Must be able to instantiate hlssink3");
+
+    let playlist_content = Arc::new(Mutex::new(String::from("")));
+    let multivariant_content = Arc::new(Mutex::new(String::from("")));
+
+    hlssink3.connect("get-playlist-stream", false, {
+        let playlist_content = playlist_content.clone();
+        let multivariant_content = multivariant_content.clone();
+        move |args| {
+            let location = args[1].get::<String>().expect("No location given");
+            let handler = if location == "multivariant.m3u8" {
+                Arc::clone(&multivariant_content)
+            } else {
+                Arc::clone(&playlist_content)
+            };
+            let playlist = MemoryPlaylistFile { handler };
+            playlist.clear_content();
+            let output = gio::WriteOutputStream::new(playlist);
+            Some(output.to_value())
+        }
+    });
+
+    hlssink3.connect("get-fragment-stream", false, move |_args| {
+        let stream = gio::MemoryOutputStream::new_resizable();
+        Some(stream.to_value())
+    });
+
+    // The value of an already added data id is replaced
+    hlssink3.emit_by_name::<()>("add-session-data", &[&"com.example.title", &"Unknown"]);
+    hlssink3.emit_by_name::<()>(
+        "add-session-data",
+        &[&"com.example.title", &"An \"Example\" at 100%"],
+    );
+
+    try_or_pause!(pipeline.add_many([&video_src, &x264enc, &h264parse, &hlssink3,]));
+    try_or_pause!(gst::Element::link_many([
+        &video_src, &x264enc, &h264parse, &hlssink3
+    ]));
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let mut eos = false;
+    let bus = pipeline.bus().unwrap();
+    while let Some(msg) = bus.timed_pop(gst::ClockTime::NONE) {
+        use gst::MessageView;
+        match msg.view() {
+            MessageView::Eos(..) => {
+                eos = true;
+                break;
+            }
+            MessageView::Error(..) => unreachable!(),
+            _ => (),
+        }
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+    assert!(eos);
+
+    // Session data is only allowed in the multivariant playlist
+    let contents = playlist_content.lock().unwrap();
+    assert!(!contents.contains("#EXT-X-SESSION-DATA"));
+
+    let contents = multivariant_content.lock().unwrap();
+    let lines = contents.lines().collect::<Vec<_>>();
+    assert!(lines.contains(&"#EXT-X-STREAM-INF:BANDWIDTH=1000000"));
+    assert!(lines.contains(&"playlist.m3u8"));
+    let session_data = lines
+        .iter()
+        .filter(|line| line.starts_with("#EXT-X-SESSION-DATA"))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        session_data,
+        vec![
+            &r#"#EXT-X-SESSION-DATA:DATA-ID="com.example.title",VALUE="An %22Example%22 at 100%25""#
+        ]
+    );
+
+    Ok(())
+}
@@ -21,6 +21,12 @@ pub(crate) static DEFAULT_UDP_PAYLOAD_SIZE: u16 = 1452;
 pub(crate) static DEFAULT_MIN_UDP_PAYLOAD_SIZE: u16 = 1200;
 pub(crate) static DEFAULT_MAX_UDP_PAYLOAD_SIZE: u16 = 65527;
 pub(crate) static DEFAULT_DROP_BUFFER_FOR_DATAGRAM: bool = false;
+pub(crate) static DEFAULT_SHARED_CONNECTION: bool = false;
+/*
+ * Number of unidirectional streams the peer may open concurrently on a
+ * connection shared between multiple elements.
+ */
+pub(crate) static DEFAULT_MAX_SHARED_STREAMS: u32 = 32;
 
 /*
  * For QUIC transport parameters
@@ -0,0 +1,295 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/*
+ * Sharing of a single QUIC connection between multiple elements.
+ *
+ * Elements with the same role, addresses and server name which have
+ * `shared-connection` enabled use the same connection, each of them sending
+ * or receiving on its own unidirectional stream. The connection is
+ * established by whichever element starts first and is closed when the
+ * last element using it stops.
+ *
+ * Incoming streams are accepted by a task on the runtime and handed out to
+ * the sources asking for them, either by stream index or in the order in
+ * which they were opened by the peer.
+ */
+
+use crate::common::QuinnQuicRole;
+use crate::utils::{
+    make_connection, QuinnQuicEndpointConfig, WaitError, CONNECTION_CLOSE_CODE,
+    CONNECTION_CLOSE_MSG, RUNTIME,
+};
+use futures::channel::oneshot;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use quinn::{Connection, RecvStream, SendStream};
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "quinnquicconnection",
+        gst::DebugColorFlags::empty(),
+        Some("Quinn QUIC shared connections"),
+    )
+});
+
+static CONNECTIONS: LazyLock<Mutex<HashMap<ConnectionKey, Entry>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+static NEXT_ENTRY_ID: AtomicU64 = AtomicU64::new(0);
+
+type ConnectionFuture = Shared<BoxFuture<'static, Result<Connection, String>>>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ConnectionKey {
+    role: QuinnQuicRole,
+    server_addr: SocketAddr,
+    client_addr: SocketAddr,
+    server_name: String,
+}
+
+struct Entry {
+    id: u64,
+    users: usize,
+    connection: ConnectionFuture,
+    incoming: Arc<Mutex<IncomingStreams>>,
+}
+
+impl Entry {
+    fn is_closed(&self) -> bool {
+        match self.connection.peek() {
+            Some(Ok(connection)) => connection.close_reason().is_some(),
+            Some(Err(_)) => true,
+            None => false,
+        }
+    }
+}
+
+#[derive(Default)]
+struct IncomingStreams {
+    acceptor_started: bool,
+    error: Option<String>,
+    pending: BTreeMap<u64, RecvStream>,
+    waiters: Vec<(Option<u64>, oneshot::Sender<RecvStream>)>,
+}
+
+impl IncomingStreams {
+    fn dispatch(&mut self, index: u64, stream: RecvStream) {
+        self.waiters.retain(|(_, sender)| !sender.is_canceled());
+
+        let waiter = self
+            .waiters
+            .iter()
+            .position(|(wanted, _)| *wanted == Some(index))
+            .or_else(|| self.waiters.iter().position(|(wanted, _)| wanted.is_none()));
+
+        let stream = match waiter {
+            Some(pos) => {
+                let (_, sender) = self.waiters.remove(pos);
+                match sender.send(stream) {
+                    Ok(()) => return,
+                    Err(stream) => stream,
+                }
+            }
+            None => stream,
+        };
+
+        gst::debug!(CAT, "Queueing unclaimed stream {index}");
+        self.pending.insert(index, stream);
+    }
+}
+
+async fn accept_streams(connection: Connection, incoming: Arc<Mutex<IncomingStreams>>) {
+    loop {
+        match connection.accept_uni().await {
+            Ok(stream) => {
+                let index = stream.id().index();
+                gst::debug!(
+                    CAT,
+                    "Accepted stream {index} from {}",
+                    connection.remote_address()
+                );
+                incoming.lock().unwrap().dispatch(index, stream);
+            }
+            Err(err) => {
+                gst::debug!(CAT, "Stopped accepting streams: {err}");
+                let mut incoming = incoming.lock().unwrap();
+                incoming.error = Some(err.to_string());
+                // Wake up all waiters, unclaimed streams can still be taken
+                incoming.waiters.clear();
+                break;
+            }
+        }
+    }
+}
+
+/// Keeps the shared connection alive and closes it once the last user is gone
+struct User {
+    key: ConnectionKey,
+    id: u64,
+}
+
+impl Drop for User {
+    fn drop(&mut self) {
+        let mut connections = CONNECTIONS.lock().unwrap();
+
+        let Some(entry) = connections.get_mut(&self.key) else {
+            return;
+        };
+        // The entry was replaced after the connection was lost
+        if entry.id != self.id {
+            return;
+        }
+
+        entry.users -= 1;
+        if entry.users > 0 {
+            return;
+        }
+
+        let entry = connections.remove(&self.key).unwrap();
+        if let Some(Ok(connection)) = entry.connection.peek() {
+            gst::debug!(
+                CAT,
+                "Closing shared connection to {}",
+                connection.remote_address()
+            );
+            connection.close(
+                CONNECTION_CLOSE_CODE.into(),
+                CONNECTION_CLOSE_MSG.as_bytes(),
+            );
+        }
+    }
+}
+
+pub struct SharedConnection {
+    connection: Connection,
+    incoming: Arc<Mutex<IncomingStreams>>,
+    _user: User,
+}
+
+impl SharedConnection {
+    /// Get the connection for the given configuration, establishing it if
+    /// no other element is using it yet.
+    pub async fn acquire(
+        role: QuinnQuicRole,
+        ep_config: QuinnQuicEndpointConfig,
+    ) -> Result<Self, WaitError> {
+        let key = ConnectionKey {
+            role,
+            server_addr: ep_config.server_addr,
+            client_addr: ep_config.client_addr,
+            server_name: ep_config.server_name.clone(),
+        };
+
+        let (user, connection, incoming) = {
+            let mut connections = CONNECTIONS.lock().unwrap();
+
+            if connections.get(&key).is_some_and(Entry::is_closed) {
+                gst::debug!(CAT, "Replacing closed shared connection {key:?}");
+                connections.remove(&key);
+            }
+
+            let entry = connections.entry(key.clone()).or_insert_with(|| {
+                gst::debug!(CAT, "Creating shared connection {key:?}");
+
+                Entry {
+                    id: NEXT_ENTRY_ID.fetch_add(1, Ordering::Relaxed),
+                    users: 0,
+                    connection: async move {
+                        make_connection(role, &ep_config)
+                            .await
+                            .map_err(|err| err.to_string())
+                    }
+                    .boxed()
+                    .shared(),
+                    incoming: Arc::default(),
+                }
+            });
+            entry.users += 1;
+
+            let user = User {
+                key: key.clone(),
+                id: entry.id,
+            };
+
+            (user, entry.connection.clone(), entry.incoming.clone())
+        };
+
+        let connection = connection.await.map_err(|err| {
+            WaitError::FutureError(gst::error_msg!(
+                gst::ResourceError::Failed,
+                ["Shared connection error: {}", err]
+            ))
+        })?;
+
+        Ok(Self {
+            connection,
+            incoming,
+            _user: user,
+        })
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Open a new stream to the peer. Stream indices are allocated by the
+    /// connection in the order in which the streams are opened.
+    pub async fn open_uni(&self) -> Result<SendStream, WaitError> {
+        self.connection.open_uni().await.map_err(|err| {
+            WaitError::FutureError(gst::error_msg!(
+                gst::ResourceError::Failed,
+                ["Failed to open stream: {}", err]
+            ))
+        })
+    }
+
+    /// Wait for the stream with the given index from the peer, or for the
+    /// first unclaimed one if no index is given.
+    pub async fn accept_uni(&self, index: Option<u64>) -> Result<RecvStream, WaitError> {
+        let receiver = {
+            let mut incoming = self.incoming.lock().unwrap();
+
+            if !incoming.acceptor_started {
+                incoming.acceptor_started = true;
+                RUNTIME.spawn(accept_streams(
+                    self.connection.clone(),
+                    self.incoming.clone(),
+                ));
+            }
+
+            let stream = match index {
+                Some(index) => incoming.pending.remove(&index),
+                None => incoming.pending.pop_first().map(|(_, stream)| stream),
+            };
+            if let Some(stream) = stream {
+                return Ok(stream);
+            }
+
+            if let Some(ref err) = incoming.error {
+                return Err(WaitError::FutureError(gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["Failed to accept stream: {}", err]
+                )));
+            }
+
+            let (sender, receiver) = oneshot::channel();
+            incoming.waiters.push((index, sender));
+
+            receiver
+        };
+
+        receiver.await.map_err(|_| {
+            WaitError::FutureError(gst::error_msg!(
+                gst::ResourceError::Failed,
+                ["Connection lost before the stream was accepted"]
+            ))
+        })
+    }
+}
@@ -20,6 +20,7 @@
 use gst::glib;
 use gst::prelude::*;
 mod common;
+mod connection_manager;
 mod quinnquicsink;
 mod quinnquicsrc;
 mod utils;
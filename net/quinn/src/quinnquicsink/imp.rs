@@ -7,9 +7,10 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+use crate::connection_manager::SharedConnection;
 use crate::utils::{
-    client_endpoint, get_stats, make_socket_addr, server_endpoint, wait, QuinnQuicEndpointConfig,
-    WaitError, CONNECTION_CLOSE_CODE, CONNECTION_CLOSE_MSG,
+    get_stats, make_connection, make_socket_addr, wait, QuinnQuicEndpointConfig, WaitError,
+    CONNECTION_CLOSE_CODE, CONNECTION_CLOSE_MSG,
 };
 use crate::{common::*, utils};
 use bytes::Bytes;
@@ -34,6 +35,7 @@ static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
 struct Started {
     connection: Connection,
     stream: Option<SendStream>,
+    shared: Option<SharedConnection>,
 }
 
 #[derive(Default)]
@@ -60,6 +62,7 @@ struct Settings {
     private_key_file: Option<PathBuf>,
     transport_config: QuinnQuicTransportConfig,
     drop_buffer_for_datagram: bool,
+    shared_connection: bool,
 }
 
 impl Default for Settings {
@@ -80,6 +83,7 @@ impl Default for Settings {
             private_key_file: None,
             transport_config: QuinnQuicTransportConfig::default(),
             drop_buffer_for_datagram: DEFAULT_DROP_BUFFER_FOR_DATAGRAM,
+            shared_connection: DEFAULT_SHARED_CONNECTION,
         }
     }
 }
@@ -276,6 +280,18 @@ impl ObjectImpl for QuinnQuicSink {
                     .blurb("Drop buffers when using datagram if buffer size > max datagram size")
                     .default_value(DEFAULT_DROP_BUFFER_FOR_DATAGRAM)
                     .build(),
+                glib::ParamSpecBoolean::builder("shared-connection")
+                    .nick("Shared connection")
+                    .blurb("Share the QUIC connection with other elements using the same role, addresses and server name, each sending on its own stream")
+                    .default_value(DEFAULT_SHARED_CONNECTION)
+                    .build(),
+                glib::ParamSpecInt64::builder("stream-index")
+                    .nick("Stream index")
+                    .blurb("Index of the stream used for sending data (-1 = not started or using datagrams)")
+                    .minimum(-1)
+                    .default_value(-1)
+                    .read_only()
+                    .build(),
             ]
         });
 
@@ -366,6 +382,9 @@ impl ObjectImpl for QuinnQuicSink {
             "drop-buffer-for-datagram" => {
                 settings.drop_buffer_for_datagram = value.get().expect("type checked upstream");
             }
+            "shared-connection" => {
+                settings.shared_connection = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -425,6 +444,17 @@ impl ObjectImpl for QuinnQuicSink {
                 }
             }
             "drop-buffer-for-datagram" => settings.drop_buffer_for_datagram.to_value(),
+            "shared-connection" => settings.shared_connection.to_value(),
+            "stream-index" => {
+                let state = self.state.lock().unwrap();
+                match *state {
+                    State::Started(Started {
+                        stream: Some(ref stream),
+                        ..
+                    }) => (stream.id().index() as i64).to_value(),
+                    _ => (-1i64).to_value(),
+                }
+            }
             _ => unimplemented!(),
         }
     }
@@ -450,10 +480,11 @@ impl BaseSinkImpl for QuinnQuicSink {
         }
 
         match wait(&self.canceller, self.init_connection(), timeout) {
-            Ok(Ok((c, s))) => {
+            Ok(Ok((c, s, shared))) => {
                 *state = State::Started(Started {
                     connection: c,
                     stream: s,
+                    shared,
                 });
 
                 gst::info!(CAT, imp = self, "Started");
@@ -521,7 +552,10 @@ impl BaseSinkImpl for QuinnQuicSink {
                 };
             }
 
-            connection.close(CONNECTION_CLOSE_CODE.into(), close_msg.as_bytes());
+            // A shared connection is closed once its last user is gone
+            if state.shared.is_none() {
+                connection.close(CONNECTION_CLOSE_CODE.into(), close_msg.as_bytes());
+            }
         }
 
         *state = State::Stopped;
@@ -589,6 +623,7 @@ impl QuinnQuicSink {
             State::Started(Started {
                 ref connection,
                 ref mut stream,
+                ..
             }) => (connection, stream),
             State::Stopped => {
                 return Err(Some(gst::error_msg!(
@@ -650,8 +685,10 @@ impl QuinnQuicSink {
         }
     }
 
-    async fn init_connection(&self) -> Result<(Connection, Option<SendStream>), WaitError> {
-        let (role, use_datagram, endpoint_config) = {
+    async fn init_connection(
+        &self,
+    ) -> Result<(Connection, Option<SendStream>, Option<SharedConnection>), WaitError> {
+        let (role, use_datagram, shared_connection, endpoint_config) = {
             let settings = self.settings.lock().unwrap();
 
             let client_addr = make_socket_addr(
@@ -665,15 +702,29 @@ impl QuinnQuicSink {
             let alpns = settings.alpns.clone();
             let role = settings.role;
             let use_datagram = settings.use_datagram;
+            let shared_connection = settings.shared_connection;
             let keep_alive_interval = settings.keep_alive_interval;
             let secure_conn = settings.secure_conn;
             let certificate_file = settings.certificate_file.clone();
             let private_key_file = settings.private_key_file.clone();
             let transport_config = settings.transport_config;
+            let max_concurrent_uni_streams = if shared_connection {
+                DEFAULT_MAX_SHARED_STREAMS
+            } else {
+                1
+            };
+
+            if use_datagram && shared_connection {
+                return Err(WaitError::FutureError(gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["Datagrams can't be used with a shared connection"]
+                )));
+            }
 
             (
                 role,
                 use_datagram,
+                shared_connection,
                 QuinnQuicEndpointConfig {
                     server_addr,
                     server_name,
@@ -684,47 +735,27 @@ impl QuinnQuicSink {
                     private_key_file,
                     keep_alive_interval,
                     transport_config,
+                    max_concurrent_uni_streams,
                 },
             )
         };
 
-        let endpoint = match role {
-            QuinnQuicRole::Server => server_endpoint(&endpoint_config).map_err(|err| {
-                WaitError::FutureError(gst::error_msg!(
-                    gst::ResourceError::Failed,
-                    ["Failed to configure endpoint: {}", err]
-                ))
-            })?,
-            QuinnQuicRole::Client => client_endpoint(&endpoint_config).map_err(|err| {
-                WaitError::FutureError(gst::error_msg!(
-                    gst::ResourceError::Failed,
-                    ["Failed to configure endpoint: {}", err]
-                ))
-            })?,
-        };
+        if shared_connection {
+            let shared = SharedConnection::acquire(role, endpoint_config).await?;
+            let stream = shared.open_uni().await?;
 
-        let connection = match role {
-            QuinnQuicRole::Server => {
-                let incoming_conn = endpoint.accept().await.unwrap();
+            gst::info!(
+                CAT,
+                imp = self,
+                "Opened stream {} on shared connection to {}",
+                stream.id().index(),
+                shared.connection().remote_address()
+            );
 
-                incoming_conn.await.map_err(|err| {
-                    WaitError::FutureError(gst::error_msg!(
-                        gst::ResourceError::Failed,
-                        ["Connection error: {}", err]
-                    ))
-                })?
-            }
-            QuinnQuicRole::Client => endpoint
-                .connect(endpoint_config.server_addr, &endpoint_config.server_name)
-                .unwrap()
-                .await
-                .map_err(|err| {
-                    WaitError::FutureError(gst::error_msg!(
-                        gst::ResourceError::Failed,
-                        ["Connection error: {}", err]
-                    ))
-                })?,
-        };
+            return Ok((shared.connection().clone(), Some(stream), Some(shared)));
+        }
+
+        let connection = make_connection(role, &endpoint_config).await?;
 
         let stream = if !use_datagram {
             let res = connection.open_uni().await.map_err(|err| {
@@ -755,6 +786,6 @@ impl QuinnQuicSink {
             None
         };
 
-        Ok((connection, stream))
+        Ok((connection, stream, None))
     }
 }
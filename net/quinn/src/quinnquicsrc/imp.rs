@@ -7,9 +7,10 @@
 //
 // SPDX-License-Identifier: MPL-2.0
 
+use crate::connection_manager::SharedConnection;
 use crate::utils::{
-    client_endpoint, get_stats, make_socket_addr, server_endpoint, wait, Canceller,
-    QuinnQuicEndpointConfig, WaitError, CONNECTION_CLOSE_CODE, CONNECTION_CLOSE_MSG,
+    get_stats, make_connection, make_socket_addr, wait, Canceller, QuinnQuicEndpointConfig,
+    WaitError, CONNECTION_CLOSE_CODE, CONNECTION_CLOSE_MSG,
 };
 use crate::{common::*, utils};
 use bytes::Bytes;
@@ -24,6 +25,7 @@ use std::sync::LazyLock;
 use std::sync::Mutex;
 
 const DEFAULT_ROLE: QuinnQuicRole = QuinnQuicRole::Server;
+const DEFAULT_STREAM_INDEX: i64 = -1;
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -36,6 +38,7 @@ static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
 struct Started {
     connection: Connection,
     stream: Option<RecvStream>,
+    shared: Option<SharedConnection>,
 }
 
 #[derive(Default)]
@@ -62,6 +65,8 @@ struct Settings {
     certificate_file: Option<PathBuf>,
     private_key_file: Option<PathBuf>,
     transport_config: QuinnQuicTransportConfig,
+    shared_connection: bool,
+    stream_index: i64,
 }
 
 impl Default for Settings {
@@ -82,6 +87,8 @@ impl Default for Settings {
             certificate_file: None,
             private_key_file: None,
             transport_config: QuinnQuicTransportConfig::default(),
+            shared_connection: DEFAULT_SHARED_CONNECTION,
+            stream_index: DEFAULT_STREAM_INDEX,
         }
     }
 }
@@ -277,7 +284,18 @@ impl ObjectImpl for QuinnQuicSrc {
                     .nick("Connection statistics")
                     .blurb("Connection statistics")
                     .read_only()
-                    .build()
+                    .build(),
+                glib::ParamSpecBoolean::builder("shared-connection")
+                    .nick("Shared connection")
+                    .blurb("Share the QUIC connection with other elements using the same role, addresses and server name, each receiving from its own stream")
+                    .default_value(DEFAULT_SHARED_CONNECTION)
+                    .build(),
+                glib::ParamSpecInt64::builder("stream-index")
+                    .nick("Stream index")
+                    .blurb("Index of the stream to receive from on a shared connection (-1 = first unclaimed stream)")
+                    .minimum(-1)
+                    .default_value(DEFAULT_STREAM_INDEX)
+                    .build(),
             ]
         });
 
@@ -374,6 +392,12 @@ impl ObjectImpl for QuinnQuicSrc {
                 let value = value.get::<u64>().expect("type checked upstream");
                 settings.transport_config.datagram_send_buffer_size = value as usize;
             }
+            "shared-connection" => {
+                settings.shared_connection = value.get().expect("type checked upstream");
+            }
+            "stream-index" => {
+                settings.stream_index = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -433,6 +457,8 @@ impl ObjectImpl for QuinnQuicSrc {
                     State::Stopped => get_stats(None).to_value(),
                 }
             }
+            "shared-connection" => settings.shared_connection.to_value(),
+            "stream-index" => settings.stream_index.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -462,10 +488,11 @@ impl BaseSrcImpl for QuinnQuicSrc {
         }
 
         match wait(&self.canceller, self.init_connection(), timeout) {
-            Ok(Ok((c, s))) => {
+            Ok(Ok((c, s, shared))) => {
                 *state = State::Started(Started {
                     connection: c,
                     stream: s,
+                    shared,
                 });
 
                 gst::info!(CAT, imp = self, "Started");
@@ -492,12 +519,18 @@ impl BaseSrcImpl for QuinnQuicSrc {
         let mut state = self.state.lock().unwrap();
 
         if let State::Started(ref mut state) = *state {
-            let connection = &state.connection;
-
-            connection.close(
-                CONNECTION_CLOSE_CODE.into(),
-                CONNECTION_CLOSE_MSG.as_bytes(),
-            );
+            if state.shared.is_some() {
+                // Only stop our stream, the connection is closed once its last
+                // user is gone
+                if let Some(ref mut stream) = state.stream {
+                    let _ = stream.stop(CONNECTION_CLOSE_CODE.into());
+                }
+            } else {
+                state.connection.close(
+                    CONNECTION_CLOSE_CODE.into(),
+                    CONNECTION_CLOSE_MSG.as_bytes(),
+                );
+            }
         }
 
         *state = State::Stopped;
@@ -603,6 +636,7 @@ impl QuinnQuicSrc {
             State::Started(Started {
                 ref connection,
                 ref mut stream,
+                ..
             }) => (connection, stream),
             State::Stopped => {
                 return Err(Some(gst::error_msg!(
@@ -685,8 +719,10 @@ impl QuinnQuicSrc {
         }
     }
 
-    async fn init_connection(&self) -> Result<(Connection, Option<RecvStream>), WaitError> {
-        let (role, use_datagram, endpoint_config) = {
+    async fn init_connection(
+        &self,
+    ) -> Result<(Connection, Option<RecvStream>, Option<SharedConnection>), WaitError> {
+        let (role, use_datagram, shared_connection, stream_index, endpoint_config) = {
             let settings = self.settings.lock().unwrap();
 
             let client_addr = make_socket_addr(
@@ -700,15 +736,31 @@ impl QuinnQuicSrc {
             let alpns = settings.alpns.clone();
             let role = settings.role;
             let use_datagram = settings.use_datagram;
+            let shared_connection = settings.shared_connection;
+            let stream_index = u64::try_from(settings.stream_index).ok();
             let keep_alive_interval = settings.keep_alive_interval;
             let secure_conn = settings.secure_conn;
             let certificate_file = settings.certificate_file.clone();
             let private_key_file = settings.private_key_file.clone();
             let transport_config = settings.transport_config;
+            let max_concurrent_uni_streams = if shared_connection {
+                DEFAULT_MAX_SHARED_STREAMS
+            } else {
+                1
+            };
+
+            if use_datagram && shared_connection {
+                return Err(WaitError::FutureError(gst::error_msg!(
+                    gst::ResourceError::Settings,
+                    ["Datagrams can't be used with a shared connection"]
+                )));
+            }
 
             (
                 role,
                 use_datagram,
+                shared_connection,
+                stream_index,
                 QuinnQuicEndpointConfig {
                     server_addr,
                     server_name,
@@ -719,47 +771,27 @@ impl QuinnQuicSrc {
                     private_key_file,
                     keep_alive_interval,
                     transport_config,
+                    max_concurrent_uni_streams,
                 },
             )
         };
 
-        let endpoint = match role {
-            QuinnQuicRole::Server => server_endpoint(&endpoint_config).map_err(|err| {
-                WaitError::FutureError(gst::error_msg!(
-                    gst::ResourceError::Failed,
-                    ["Failed to configure endpoint: {}", err]
-                ))
-            })?,
-            QuinnQuicRole::Client => client_endpoint(&endpoint_config).map_err(|err| {
-                WaitError::FutureError(gst::error_msg!(
-                    gst::ResourceError::Failed,
-                    ["Failed to configure endpoint: {}", err]
-                ))
-            })?,
-        };
+        if shared_connection {
+            let shared = SharedConnection::acquire(role, endpoint_config).await?;
+            let stream = shared.accept_uni(stream_index).await?;
+
+            gst::info!(
+                CAT,
+                imp = self,
+                "Accepted stream {} on shared connection from {}",
+                stream.id().index(),
+                shared.connection().remote_address()
+            );
 
-        let connection = match role {
-            QuinnQuicRole::Server => {
-                let incoming_conn = endpoint.accept().await.unwrap();
+            return Ok((shared.connection().clone(), Some(stream), Some(shared)));
+        }
 
-                incoming_conn.await.map_err(|err| {
-                    WaitError::FutureError(gst::error_msg!(
-                        gst::ResourceError::Failed,
-                        ["Connection error: {}", err]
-                    ))
-                })?
-            }
-            QuinnQuicRole::Client => endpoint
-                .connect(endpoint_config.server_addr, &endpoint_config.server_name)
-                .unwrap()
-                .await
-                .map_err(|err| {
-                    WaitError::FutureError(gst::error_msg!(
-                        gst::ResourceError::Failed,
-                        ["Connection error: {}", err]
-                    ))
-                })?,
-        };
+        let connection = make_connection(role, &endpoint_config).await?;
 
         let stream = if !use_datagram {
             let res = connection.accept_uni().await.map_err(|err| {
@@ -797,6 +829,6 @@ impl QuinnQuicSrc {
             connection.remote_address()
         );
 
-        Ok((connection, stream))
+        Ok((connection, stream, None))
     }
 }
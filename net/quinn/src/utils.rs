@@ -41,6 +41,7 @@ pub struct QuinnQuicEndpointConfig {
     pub private_key_file: Option<PathBuf>,
     pub keep_alive_interval: u64,
     pub transport_config: QuinnQuicTransportConfig,
+    pub max_concurrent_uni_streams: u32,
 }
 
 #[derive(Error, Debug)]
@@ -258,7 +259,7 @@ fn configure_client(ep_config: &QuinnQuicEndpointConfig) -> Result<ClientConfig,
         transport_config
             .datagram_send_buffer_size(ep_config.transport_config.datagram_send_buffer_size);
         transport_config.max_concurrent_bidi_streams(0u32.into());
-        transport_config.max_concurrent_uni_streams(1u32.into());
+        transport_config.max_concurrent_uni_streams(ep_config.max_concurrent_uni_streams.into());
         transport_config.mtu_discovery_config(Some(mtu_config));
 
         transport_config
@@ -393,7 +394,7 @@ fn configure_server(
         transport_config
             .datagram_send_buffer_size(ep_config.transport_config.datagram_send_buffer_size);
         transport_config.max_concurrent_bidi_streams(0u32.into());
-        transport_config.max_concurrent_uni_streams(1u32.into());
+        transport_config.max_concurrent_uni_streams(ep_config.max_concurrent_uni_streams.into());
         transport_config.mtu_discovery_config(Some(mtu_config));
 
         transport_config
@@ -432,6 +433,44 @@ pub fn client_endpoint(ep_config: &QuinnQuicEndpointConfig) -> Result<Endpoint,
     Ok(endpoint)
 }
 
+pub async fn make_connection(
+    role: QuinnQuicRole,
+    ep_config: &QuinnQuicEndpointConfig,
+) -> Result<Connection, WaitError> {
+    let endpoint = match role {
+        QuinnQuicRole::Server => server_endpoint(ep_config),
+        QuinnQuicRole::Client => client_endpoint(ep_config),
+    }
+    .map_err(|err| {
+        WaitError::FutureError(gst::error_msg!(
+            gst::ResourceError::Failed,
+            ["Failed to configure endpoint: {}", err]
+        ))
+    })?;
+
+    let connection = match role {
+        QuinnQuicRole::Server => {
+            let incoming_conn = endpoint.accept().await.unwrap();
+
+            incoming_conn.await
+        }
+        QuinnQuicRole::Client => {
+            endpoint
+                .connect(ep_config.server_addr, &ep_config.server_name)
+                .unwrap()
+                .await
+        }
+    }
+    .map_err(|err| {
+        WaitError::FutureError(gst::error_msg!(
+            gst::ResourceError::Failed,
+            ["Connection error: {}", err]
+        ))
+    })?;
+
+    Ok(connection)
+}
+
 pub fn get_stats(connection: Option<Connection>) -> gst::Structure {
     match connection {
         Some(conn) => {
@@ -112,3 +112,64 @@ fn test_send_receive_with_datagram() {
 
     drop(h2);
 }
+
+#[test]
+#[serial]
+fn test_send_receive_shared_connection() {
+    init();
+
+    let receivers = (0..2)
+        .map(|index| {
+            thread::spawn(move || {
+                let mut h = gst_check::Harness::new_empty();
+                h.add_parse(&format!(
+                    "quinnquicsrc shared-connection=true stream-index={index} address=127.0.0.1 port=6100 secure-connection=false"
+                ));
+
+                h.play();
+
+                let buf = h.pull_until_eos().unwrap().unwrap();
+                let content = buf.into_mapped_buffer_readable().unwrap().to_vec();
+
+                h.element().unwrap().set_state(gst::State::Null).unwrap();
+
+                content
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let mut senders = (0..2)
+        .map(|index| {
+            let mut h = gst_check::Harness::new_empty();
+            h.add_parse("quinnquicsink shared-connection=true address=127.0.0.1 port=6100 secure-connection=false");
+
+            h.set_src_caps(gst::Caps::builder("text/plain").build());
+
+            h.play();
+
+            // Streams are allocated in the order in which the sinks start
+            let sink = h.element().unwrap();
+            assert_eq!(sink.property::<i64>("stream-index"), index);
+
+            h
+        })
+        .collect::<Vec<_>>();
+
+    // Push in reverse order, the streams must still reach the right source
+    for (index, h) in senders.iter_mut().enumerate().rev() {
+        let content = format!("Hello from stream {index}\n");
+        assert!(h.push(make_buffer(content.as_bytes())) == Ok(gst::FlowSuccess::Ok));
+        h.push_event(gst::event::Eos::new());
+    }
+
+    for h in senders {
+        h.element().unwrap().set_state(gst::State::Null).unwrap();
+    }
+
+    for (index, receiver) in receivers.into_iter().enumerate() {
+        assert_eq!(
+            receiver.join().unwrap(),
+            format!("Hello from stream {index}\n").as_bytes()
+        );
+    }
+}
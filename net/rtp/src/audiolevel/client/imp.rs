@@ -0,0 +1,198 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtphdrextclientaudiolevel2
+ * @see_also: rtphdrextmixeraudiolevel2, rtpsend, rtprecv
+ *
+ * RTP header extension for the client-to-mixer audio level indication as defined in RFC 6464.
+ *
+ * When writing the header extension, the level and voice activity of a `GstAudioLevelMeta` on
+ * the input buffer are used. Without such a meta the level is computed from the audio samples if
+ * the encoding is known, i.e. for PCMU, PCMA and L16 packets with a static payload type or for
+ * raw 16 bit, µ-law and A-law input of a payloader. The voice activity flag is not set in that
+ * case.
+ *
+ * When reading the header extension, a `GstAudioLevelMeta` is added to the buffer.
+ *
+ * The `vad=off` extmap attribute disables the voice activity flag.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::sync::{LazyLock, Mutex};
+
+use gst::{glib, subclass::prelude::*};
+use gst_rtp::{prelude::*, subclass::prelude::*};
+
+use super::super::InputFormat;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtphdrextclientaudiolevel2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP Client-to-Mixer Audio Level Header Extension"),
+    )
+});
+
+const URI: &str = "urn:ietf:params:rtp-hdrext:ssrc-audio-level";
+
+const VOICE_ACTIVITY: u8 = 0x80;
+const LEVEL_MASK: u8 = 0x7f;
+
+#[derive(Debug)]
+struct State {
+    vad: bool,
+    input_format: InputFormat,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            vad: true,
+            input_format: InputFormat::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RtpHdrExtClientAudioLevel {
+    state: Mutex<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpHdrExtClientAudioLevel {
+    const NAME: &'static str = "GstRtpHdrExtClientAudioLevel2";
+    type Type = super::RtpHdrExtClientAudioLevel;
+    type ParentType = gst_rtp::RTPHeaderExtension;
+}
+
+impl ObjectImpl for RtpHdrExtClientAudioLevel {}
+
+impl GstObjectImpl for RtpHdrExtClientAudioLevel {}
+
+impl ElementImpl for RtpHdrExtClientAudioLevel {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Client-to-Mixer Audio Level Indication (RFC 6464)",
+                "Network/Extension/RTPHeader",
+                "Writes and reads the client-to-mixer audio level RTP header extension",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+}
+
+impl RTPHeaderExtensionImpl for RtpHdrExtClientAudioLevel {
+    const URI: &'static str = URI;
+
+    fn supported_flags(&self) -> gst_rtp::RTPHeaderExtensionFlags {
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE | gst_rtp::RTPHeaderExtensionFlags::TWO_BYTE
+    }
+
+    fn max_size(&self, _input: &gst::BufferRef) -> usize {
+        1
+    }
+
+    fn write(
+        &self,
+        input: &gst::BufferRef,
+        _write_flags: gst_rtp::RTPHeaderExtensionFlags,
+        _output: &mut gst::BufferRef,
+        output_data: &mut [u8],
+    ) -> Result<usize, gst::LoggableError> {
+        let state = self.state.lock().unwrap();
+
+        let (level, voice_activity) = if let Some(meta) = input.meta::<gst_audio::AudioLevelMeta>()
+        {
+            (meta.level(), meta.voice_activity())
+        } else if let Some(level) = state.input_format.level(input) {
+            (level, false)
+        } else {
+            gst::trace!(CAT, imp = self, "No audio level available");
+            return Ok(0);
+        };
+
+        let Some(data) = output_data.first_mut() else {
+            return Err(gst::loggable_error!(CAT, "No space for audio level"));
+        };
+
+        *data = level.min(LEVEL_MASK);
+        if state.vad && voice_activity {
+            *data |= VOICE_ACTIVITY;
+        }
+
+        Ok(1)
+    }
+
+    fn read(
+        &self,
+        _read_flags: gst_rtp::RTPHeaderExtensionFlags,
+        input_data: &[u8],
+        output: &mut gst::BufferRef,
+    ) -> Result<(), gst::LoggableError> {
+        let Some(&data) = input_data.first() else {
+            return Err(gst::loggable_error!(CAT, "Empty audio level"));
+        };
+
+        let vad = self.state.lock().unwrap().vad;
+        gst_audio::AudioLevelMeta::add(
+            output,
+            data & LEVEL_MASK,
+            vad && data & VOICE_ACTIVITY != 0,
+        );
+
+        Ok(())
+    }
+
+    fn set_non_rtp_sink_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let input_format = InputFormat::from_non_rtp_caps(caps);
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Input format {input_format:?} for caps {caps:?}"
+        );
+        self.state.lock().unwrap().input_format = input_format;
+
+        Ok(())
+    }
+
+    fn set_attributes(
+        &self,
+        _direction: gst_rtp::RTPHeaderExtensionDirection,
+        attributes: &str,
+    ) -> Result<(), gst::LoggableError> {
+        let vad = match attributes {
+            "" | "vad=on" => true,
+            "vad=off" => false,
+            _ => {
+                return Err(gst::loggable_error!(
+                    CAT,
+                    "Invalid attributes '{attributes}'"
+                ))
+            }
+        };
+
+        self.state.lock().unwrap().vad = vad;
+
+        Ok(())
+    }
+
+    fn set_caps_from_attributes(&self, caps: &mut gst::CapsRef) -> Result<(), gst::LoggableError> {
+        let attributes = if self.state.lock().unwrap().vad {
+            "vad=on"
+        } else {
+            "vad=off"
+        };
+
+        if self.obj().set_caps_from_attributes_helper(caps, attributes) {
+            Ok(())
+        } else {
+            Err(gst::loggable_error!(
+                CAT,
+                "Failed to set caps from attributes"
+            ))
+        }
+    }
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtphdrextmixeraudiolevel2
+ * @see_also: rtphdrextclientaudiolevel2, rtpsend, rtprecv
+ *
+ * RTP header extension for the mixer-to-client audio level indication as defined in RFC 6465.
+ *
+ * When writing the header extension, the levels of the `GstAudioLevelMeta`s on the input buffer
+ * are written in order, one for each CSRC of the packet.
+ *
+ * When reading the header extension, a `GstAudioLevelMeta` is added to the buffer for each
+ * level, in the order of the CSRCs of the packet.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::sync::{LazyLock, Mutex};
+
+use gst::{glib, subclass::prelude::*};
+use gst_rtp::subclass::prelude::*;
+
+use super::super::{InputFormat, MAX_CSRCS};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtphdrextmixeraudiolevel2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP Mixer-to-Client Audio Level Header Extension"),
+    )
+});
+
+const URI: &str = "urn:ietf:params:rtp-hdrext:csrc-audio-level";
+
+const LEVEL_MASK: u8 = 0x7f;
+
+#[derive(Default)]
+pub struct RtpHdrExtMixerAudioLevel {
+    input_format: Mutex<InputFormat>,
+}
+
+impl RtpHdrExtMixerAudioLevel {
+    /// Number of levels to write for `input`
+    fn n_levels(&self, input: &gst::BufferRef) -> usize {
+        let input_format = *self.input_format.lock().unwrap();
+        let n_metas = input.iter_meta::<gst_audio::AudioLevelMeta>().count();
+
+        // Only write levels for the CSRCs of the packet if they are known
+        n_metas
+            .min(input_format.n_csrcs(input).unwrap_or(MAX_CSRCS))
+            .min(MAX_CSRCS)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpHdrExtMixerAudioLevel {
+    const NAME: &'static str = "GstRtpHdrExtMixerAudioLevel2";
+    type Type = super::RtpHdrExtMixerAudioLevel;
+    type ParentType = gst_rtp::RTPHeaderExtension;
+}
+
+impl ObjectImpl for RtpHdrExtMixerAudioLevel {}
+
+impl GstObjectImpl for RtpHdrExtMixerAudioLevel {}
+
+impl ElementImpl for RtpHdrExtMixerAudioLevel {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Mixer-to-Client Audio Level Indication (RFC 6465)",
+                "Network/Extension/RTPHeader",
+                "Writes and reads the mixer-to-client audio level RTP header extension",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+}
+
+impl RTPHeaderExtensionImpl for RtpHdrExtMixerAudioLevel {
+    const URI: &'static str = URI;
+
+    fn supported_flags(&self) -> gst_rtp::RTPHeaderExtensionFlags {
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE | gst_rtp::RTPHeaderExtensionFlags::TWO_BYTE
+    }
+
+    fn max_size(&self, input: &gst::BufferRef) -> usize {
+        self.n_levels(input)
+    }
+
+    fn write(
+        &self,
+        input: &gst::BufferRef,
+        _write_flags: gst_rtp::RTPHeaderExtensionFlags,
+        _output: &mut gst::BufferRef,
+        output_data: &mut [u8],
+    ) -> Result<usize, gst::LoggableError> {
+        let n_levels = self.n_levels(input);
+        if n_levels == 0 {
+            gst::trace!(CAT, imp = self, "No audio levels available");
+            return Ok(0);
+        }
+
+        if output_data.len() < n_levels {
+            return Err(gst::loggable_error!(
+                CAT,
+                "No space for {n_levels} audio levels"
+            ));
+        }
+
+        for (data, meta) in output_data
+            .iter_mut()
+            .zip(input.iter_meta::<gst_audio::AudioLevelMeta>())
+            .take(n_levels)
+        {
+            *data = meta.level().min(LEVEL_MASK);
+        }
+
+        Ok(n_levels)
+    }
+
+    fn read(
+        &self,
+        _read_flags: gst_rtp::RTPHeaderExtensionFlags,
+        input_data: &[u8],
+        output: &mut gst::BufferRef,
+    ) -> Result<(), gst::LoggableError> {
+        for &data in input_data.iter().take(MAX_CSRCS) {
+            gst_audio::AudioLevelMeta::add(output, data & LEVEL_MASK, false);
+        }
+
+        Ok(())
+    }
+
+    fn set_non_rtp_sink_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        *self.input_format.lock().unwrap() = InputFormat::from_non_rtp_caps(caps);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct RtpHdrExtMixerAudioLevel(ObjectSubclass<imp::RtpHdrExtMixerAudioLevel>)
+        @extends gst_rtp::RTPHeaderExtension, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "rtphdrextmixeraudiolevel2",
+        gst::Rank::MARGINAL,
+        RtpHdrExtMixerAudioLevel::static_type(),
+    )
+}
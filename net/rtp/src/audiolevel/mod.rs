@@ -0,0 +1,158 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+
+pub mod client;
+pub mod mixer;
+
+#[cfg(test)]
+mod tests;
+
+/// Maximum number of CSRCs in an RTP packet, and thus of mixer-to-client audio levels
+const MAX_CSRCS: usize = 15;
+
+/// Audio encodings for which the audio level can be computed from the samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AudioEncoding {
+    S16Be,
+    S16Le,
+    Pcmu,
+    Pcma,
+}
+
+impl AudioEncoding {
+    /// Encoding of static RTP payload types
+    fn from_payload_type(pt: u8) -> Option<Self> {
+        match pt {
+            0 => Some(AudioEncoding::Pcmu),
+            8 => Some(AudioEncoding::Pcma),
+            10 | 11 => Some(AudioEncoding::S16Be),
+            _ => None,
+        }
+    }
+
+    fn from_caps(caps: &gst::CapsRef) -> Option<Self> {
+        let s = caps.structure(0)?;
+
+        match s.name().as_str() {
+            "audio/x-raw" => match s.get::<&str>("format").ok()? {
+                "S16BE" => Some(AudioEncoding::S16Be),
+                "S16LE" => Some(AudioEncoding::S16Le),
+                _ => None,
+            },
+            "audio/x-mulaw" => Some(AudioEncoding::Pcmu),
+            "audio/x-alaw" => Some(AudioEncoding::Pcma),
+            _ => None,
+        }
+    }
+
+    /// Audio level in -dBov of `data`, or `None` if `data` contains no samples
+    fn level(self, data: &[u8]) -> Option<u8> {
+        match self {
+            AudioEncoding::S16Be => level_from_samples(
+                data.chunks_exact(2)
+                    .map(|s| i16::from_be_bytes([s[0], s[1]])),
+            ),
+            AudioEncoding::S16Le => level_from_samples(
+                data.chunks_exact(2)
+                    .map(|s| i16::from_le_bytes([s[0], s[1]])),
+            ),
+            AudioEncoding::Pcmu => level_from_samples(data.iter().map(|&u| ulaw_to_linear(u))),
+            AudioEncoding::Pcma => level_from_samples(data.iter().map(|&a| alaw_to_linear(a))),
+        }
+    }
+}
+
+/// Format of the buffers passed to the header extensions for writing
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum InputFormat {
+    /// Complete RTP packets, e.g. when used by `rtpsend`
+    #[default]
+    Rtp,
+    /// Input buffers of a payloader with the given encoding, if it is supported
+    Raw(Option<AudioEncoding>),
+}
+
+impl InputFormat {
+    fn from_non_rtp_caps(caps: &gst::CapsRef) -> Self {
+        InputFormat::Raw(AudioEncoding::from_caps(caps))
+    }
+
+    /// Compute the audio level of the samples in `input`
+    fn level(self, input: &gst::BufferRef) -> Option<u8> {
+        let map = input.map_readable().ok()?;
+
+        match self {
+            InputFormat::Rtp => {
+                let packet = rtp_types::RtpPacket::parse(&map).ok()?;
+                AudioEncoding::from_payload_type(packet.payload_type())?.level(packet.payload())
+            }
+            InputFormat::Raw(encoding) => encoding?.level(&map),
+        }
+    }
+
+    /// Number of CSRCs of `input`, if known
+    fn n_csrcs(self, input: &gst::BufferRef) -> Option<usize> {
+        if self != InputFormat::Rtp {
+            return None;
+        }
+
+        let map = input.map_readable().ok()?;
+        let packet = rtp_types::RtpPacket::parse(&map).ok()?;
+
+        Some(packet.csrc().count())
+    }
+}
+
+/// Audio level in -dBov as defined in RFC 6464, i.e. the RMS of the samples relative to the
+/// overload point, clamped to 0 to 127.
+fn level_from_samples(samples: impl Iterator<Item = i16>) -> Option<u8> {
+    let (sum, n_samples) = samples.fold((0.0f64, 0usize), |(sum, n_samples), sample| {
+        (sum + (sample as f64).powi(2), n_samples + 1)
+    });
+    if n_samples == 0 {
+        return None;
+    }
+
+    let rms = (sum / n_samples as f64).sqrt() / 32768.0;
+
+    // Digital silence results in infinity, which is clamped to the lowest level
+    Some((-20.0 * rms.log10()).round().clamp(0.0, 127.0) as u8)
+}
+
+/// Decode a G.711 µ-law sample
+fn ulaw_to_linear(u: u8) -> i16 {
+    let u = !u;
+    let exponent = (u >> 4) & 0x07;
+    let mantissa = (u & 0x0f) as i16;
+    let magnitude = (((mantissa << 3) + 0x84) << exponent) - 0x84;
+
+    if u & 0x80 != 0 {
+        -magnitude
+    } else {
+        magnitude
+    }
+}
+
+/// Decode a G.711 A-law sample
+fn alaw_to_linear(a: u8) -> i16 {
+    let a = a ^ 0x55;
+    let exponent = (a >> 4) & 0x07;
+    let mantissa = (a & 0x0f) as i16;
+    let magnitude = if exponent == 0 {
+        (mantissa << 4) + 0x08
+    } else {
+        ((mantissa << 4) + 0x108) << (exponent - 1)
+    };
+
+    if a & 0x80 != 0 {
+        magnitude
+    } else {
+        -magnitude
+    }
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    client::register(plugin)?;
+    mixer::register(plugin)
+}
@@ -0,0 +1,188 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst_rtp::prelude::*;
+
+use super::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        crate::plugin_register_static().expect("rtp audiolevel test");
+    });
+}
+
+fn make_extension(name: &str) -> gst_rtp::RTPHeaderExtension {
+    gst::ElementFactory::make(name)
+        .build()
+        .unwrap()
+        .downcast::<gst_rtp::RTPHeaderExtension>()
+        .unwrap()
+}
+
+fn make_rtp_buffer(pt: u8, csrcs: &[u32], payload: &[u8]) -> gst::Buffer {
+    let mut packet = rtp_types::RtpPacketBuilder::new()
+        .ssrc(1234)
+        .payload_type(pt)
+        .sequence_number(1)
+        .timestamp(0)
+        .payload(payload);
+    for csrc in csrcs {
+        packet = packet.add_csrc(*csrc);
+    }
+    gst::Buffer::from_mut_slice(packet.write_vec().unwrap())
+}
+
+#[test]
+fn test_level_from_samples() {
+    assert_eq!(level_from_samples(std::iter::empty()), None);
+    assert_eq!(level_from_samples([0i16; 160].into_iter()), Some(127));
+    assert_eq!(
+        level_from_samples([i16::MAX, i16::MIN].into_iter().cycle().take(160)),
+        Some(0)
+    );
+    assert_eq!(
+        level_from_samples([16384i16, -16384].into_iter().cycle().take(160)),
+        Some(6)
+    );
+    assert_eq!(
+        level_from_samples([328i16, -328].into_iter().cycle().take(160)),
+        Some(40)
+    );
+}
+
+#[test]
+fn test_g711() {
+    // Positive and negative zero
+    assert_eq!(ulaw_to_linear(0xff), 0);
+    assert_eq!(ulaw_to_linear(0x7f), 0);
+    assert_eq!(ulaw_to_linear(0x80), 32124);
+    assert_eq!(ulaw_to_linear(0x00), -32124);
+
+    assert_eq!(alaw_to_linear(0xd5), 8);
+    assert_eq!(alaw_to_linear(0x55), -8);
+    assert_eq!(alaw_to_linear(0xaa), 32256);
+    assert_eq!(alaw_to_linear(0x2a), -32256);
+
+    assert_eq!(AudioEncoding::Pcmu.level(&[0xff; 160]), Some(127));
+    assert_eq!(AudioEncoding::Pcma.level(&[0xaa, 0x2a].repeat(80)), Some(0));
+}
+
+#[test]
+fn test_client_write_read() {
+    init();
+
+    let ext = make_extension("rtphdrextclientaudiolevel2");
+    ext.set_id(1);
+
+    let mut output = gst::Buffer::new();
+    let mut data = [0u8; 1];
+
+    // Level computed from a full-scale PCMU packet
+    let input = make_rtp_buffer(0, &[], &[0x80, 0x00].repeat(80));
+    let written = ext.write(
+        &input,
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE,
+        output.get_mut().unwrap(),
+        &mut data,
+    );
+    assert_eq!(written.unwrap(), 1);
+    assert_eq!(data[0], 0);
+
+    // Level and voice activity from the meta
+    let mut input = make_rtp_buffer(96, &[], &[0; 160]);
+    gst_audio::AudioLevelMeta::add(input.get_mut().unwrap(), 42, true);
+    let written = ext.write(
+        &input,
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE,
+        output.get_mut().unwrap(),
+        &mut data,
+    );
+    assert_eq!(written.unwrap(), 1);
+    assert_eq!(data[0], 0x80 | 42);
+
+    // Unknown encoding and no meta
+    let input = make_rtp_buffer(96, &[], &[0; 160]);
+    let written = ext.write(
+        &input,
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE,
+        output.get_mut().unwrap(),
+        &mut data,
+    );
+    assert_eq!(written.unwrap(), 0);
+
+    assert!(ext.read(
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE,
+        &[0x80 | 42],
+        output.get_mut().unwrap(),
+    ));
+    let meta = output.meta::<gst_audio::AudioLevelMeta>().unwrap();
+    assert_eq!(meta.level(), 42);
+    assert!(meta.voice_activity());
+}
+
+#[test]
+fn test_client_vad_off() {
+    init();
+
+    let ext = make_extension("rtphdrextclientaudiolevel2");
+    ext.set_id(1);
+
+    let caps = gst::Caps::builder("application/x-rtp")
+        .field(
+            "extmap-1",
+            gst::Array::new(["", "urn:ietf:params:rtp-hdrext:ssrc-audio-level", "vad=off"]),
+        )
+        .build();
+    assert!(ext.set_attributes_from_caps(&caps));
+
+    let mut output = gst::Buffer::new();
+    assert!(ext.read(
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE,
+        &[0x80 | 42],
+        output.get_mut().unwrap(),
+    ));
+    let meta = output.meta::<gst_audio::AudioLevelMeta>().unwrap();
+    assert_eq!(meta.level(), 42);
+    assert!(!meta.voice_activity());
+}
+
+#[test]
+fn test_mixer_write_read() {
+    init();
+
+    let ext = make_extension("rtphdrextmixeraudiolevel2");
+    ext.set_id(2);
+
+    // Three levels but only two CSRCs
+    let mut input = make_rtp_buffer(96, &[1, 2], &[0; 160]);
+    for level in [10, 20, 30] {
+        gst_audio::AudioLevelMeta::add(input.get_mut().unwrap(), level, true);
+    }
+    assert_eq!(ext.max_size(&input), 2);
+
+    let mut output = gst::Buffer::new();
+    let mut data = [0u8; 2];
+    let written = ext.write(
+        &input,
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE,
+        output.get_mut().unwrap(),
+        &mut data,
+    );
+    assert_eq!(written.unwrap(), 2);
+    assert_eq!(data, [10, 20]);
+
+    assert!(ext.read(
+        gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE,
+        &data,
+        output.get_mut().unwrap(),
+    ));
+    let levels = output
+        .iter_meta::<gst_audio::AudioLevelMeta>()
+        .map(|meta| (meta.level(), meta.voice_activity()))
+        .collect::<Vec<_>>();
+    assert_eq!(levels, [(10, false), (20, false)]);
+}
@@ -498,6 +498,19 @@ impl RtpBasePay2 {
 
                     packet_rtptime = base_rtptime + (rtp_diff & 0xffff_ffff) as u32;
                 }
+                crate::basepay::TimestampOffset::PtsOnly(pts_diff) => {
+                    packet_pts = match segment.stop() {
+                        Some(stop) if pts + pts_diff > stop => stop,
+                        _ => pts + pts_diff,
+                    };
+
+                    packet_rtptime = (*rtptime_base
+                        .mul_div_ceil(clock_rate as u64, *gst::ClockTime::SECOND)
+                        .unwrap()
+                        & 0xffff_ffff) as u32;
+
+                    state.last_pts_rtp_mapping = Some((pts, packet_rtptime));
+                }
             }
         } else {
             packet_pts = pts;
@@ -479,6 +479,9 @@ pub enum TimestampOffset {
     /// Offset in RTP clock-time units relative to the last packet that had offset given in RTP
     /// clock-rate units.
     Rtp(u64),
+    /// Offset in nanoseconds relative to the first buffer id this packet belongs to, applied only
+    /// to the PTS. The RTP timestamp stays the one of that buffer.
+    PtsOnly(gst::ClockTime),
 }
 
 /// Relation between queued packet and input buffer ids.
@@ -0,0 +1,639 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::sync::{Arc, LazyLock, Mutex, OnceLock};
+
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::{PKey, Private};
+use openssl::srtp::SrtpProfileId;
+use openssl::ssl::{ErrorCode, Ssl, SslContext, SslMethod, SslStream, SslVerifyMode};
+use openssl::x509::{X509NameBuilder, X509Ref, X509};
+
+use super::Role;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpdtlssrtp",
+        gst::DebugColorFlags::empty(),
+        Some("RTP DTLS-SRTP connection"),
+    )
+});
+
+static CONNECTIONS: OnceLock<Mutex<HashMap<String, Connection>>> = OnceLock::new();
+
+/// SRTP protection profiles offered in the `use_srtp` extension, in order of preference
+const SRTP_PROFILES: &str = "SRTP_AES128_CM_SHA1_80:SRTP_AES128_CM_SHA1_32";
+const SRTP_KEY_LEN: usize = 16;
+const SRTP_SALT_LEN: usize = 14;
+/// Label for exporting the SRTP keying material, see RFC 5764 section 4.2
+const SRTP_EXPORTER_LABEL: &str = "EXTRACTOR-dtls_srtp";
+const DTLS_MTU: u32 = 1200;
+/// Maximum number of datagrams kept around when received before the handshake started
+const MAX_PENDING_DATAGRAMS: usize = 16;
+
+/// Whether a datagram received on an RTP port is a DTLS record, see RFC 7983
+pub fn is_dtls(data: &[u8]) -> bool {
+    matches!(data.first(), Some(20..=63))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrtpProfile {
+    Aes128CmHmacSha1_80,
+    Aes128CmHmacSha1_32,
+}
+
+impl SrtpProfile {
+    pub fn cipher(self) -> &'static str {
+        "aes-128-icm"
+    }
+
+    pub fn rtp_auth(self) -> &'static str {
+        match self {
+            SrtpProfile::Aes128CmHmacSha1_80 => "hmac-sha1-80",
+            SrtpProfile::Aes128CmHmacSha1_32 => "hmac-sha1-32",
+        }
+    }
+
+    /// SRTCP always uses the 80 bits authentication tag, see RFC 5764 section 4.1.2
+    pub fn rtcp_auth(self) -> &'static str {
+        "hmac-sha1-80"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrtpKeys {
+    pub profile: SrtpProfile,
+    /// Master key followed by the master salt protecting the packets we send
+    pub local: Vec<u8>,
+    /// Master key followed by the master salt protecting the packets the peer sends
+    pub remote: Vec<u8>,
+}
+
+#[derive(Clone)]
+pub struct Certificate {
+    cert: X509,
+    key: PKey<Private>,
+}
+
+impl Certificate {
+    /// Generates a self-signed ECDSA P-256 certificate
+    pub fn generate() -> Result<Self, ErrorStack> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let key = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, "gstreamer")?;
+        let name = name.build();
+
+        let mut serial = BigNum::new()?;
+        serial.rand(64, MsbOption::MAYBE_ZERO, false)?;
+
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        builder.set_serial_number(&serial.to_asn1_integer()?)?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&key)?;
+        builder.set_not_before(&Asn1Time::days_from_now(0)?)?;
+        builder.set_not_after(&Asn1Time::days_from_now(365)?)?;
+        builder.sign(&key, MessageDigest::sha256())?;
+
+        Ok(Certificate {
+            cert: builder.build(),
+            key,
+        })
+    }
+
+    /// Parses a PEM string containing both the certificate and its private key
+    pub fn from_pem(pem: &str) -> Result<Self, ErrorStack> {
+        Ok(Certificate {
+            cert: X509::from_pem(pem.as_bytes())?,
+            key: PKey::private_key_from_pem(pem.as_bytes())?,
+        })
+    }
+
+    pub fn fingerprint(&self) -> Result<String, ErrorStack> {
+        fingerprint(&self.cert)
+    }
+}
+
+/// Fingerprint in the format of the SDP `a=fingerprint` attribute, see RFC 8122
+fn fingerprint(cert: &X509Ref) -> Result<String, ErrorStack> {
+    let digest = cert.digest(MessageDigest::sha256())?;
+    let hex = digest
+        .iter()
+        .map(|b| format!("{b:02X}"))
+        .collect::<Vec<_>>()
+        .join(":");
+
+    Ok(format!("sha-256 {hex}"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    New,
+    Connecting,
+    Connected,
+    Failed,
+    Closed,
+}
+
+/// In-memory datagram transport for the DTLS stream
+#[derive(Debug, Default)]
+struct Transport {
+    incoming: VecDeque<Vec<u8>>,
+    outgoing: Vec<Vec<u8>>,
+}
+
+impl io::Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(datagram) = self.incoming.pop_front() else {
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+
+        let len = datagram.len().min(buf.len());
+        buf[..len].copy_from_slice(&datagram[..len]);
+
+        Ok(len)
+    }
+}
+
+impl io::Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.outgoing.push(buf.to_vec());
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+pub type DatagramSender = Arc<dyn Fn(Vec<u8>) + Send + Sync>;
+
+/// DTLS connection shared between a `rtpdtlssrtpsend` and a `rtpdtlssrtprecv` with the same
+/// `dtls-id`.
+///
+/// The sender pushes the DTLS records towards the peer while the receiver feeds the records
+/// demultiplexed from the incoming SRTP stream.
+#[derive(Clone)]
+pub struct Connection {
+    name: String,
+    inner: Arc<Mutex<ConnectionInner>>,
+}
+
+struct ConnectionInner {
+    role: Role,
+    peer_fingerprint: Option<String>,
+    stream: Option<SslStream<Transport>>,
+    state: ConnectionState,
+    keys: Option<SrtpKeys>,
+    pending: Vec<Vec<u8>>,
+    sender: Option<DatagramSender>,
+    send_outstanding: bool,
+    recv_outstanding: bool,
+}
+
+impl ConnectionInner {
+    fn new(send_outstanding: bool, recv_outstanding: bool) -> Self {
+        ConnectionInner {
+            role: Role::default(),
+            peer_fingerprint: None,
+            stream: None,
+            state: ConnectionState::New,
+            keys: None,
+            pending: Vec::new(),
+            sender: None,
+            send_outstanding,
+            recv_outstanding,
+        }
+    }
+
+    /// Advances the handshake, or processes alerts once connected, with the datagrams
+    /// received so far
+    fn drive(&mut self) -> Result<(), gst::ErrorMessage> {
+        let Some(stream) = self.stream.as_mut() else {
+            return Ok(());
+        };
+
+        match self.state {
+            ConnectionState::Connecting => match stream.do_handshake() {
+                Ok(()) => {
+                    let keys = self.handshake_done().inspect_err(|_| {
+                        self.state = ConnectionState::Failed;
+                    })?;
+                    gst::info!(CAT, "DTLS handshake done, using {:?}", keys.profile);
+                    self.keys = Some(keys);
+                    self.state = ConnectionState::Connected;
+                }
+                Err(err) if [ErrorCode::WANT_READ, ErrorCode::WANT_WRITE].contains(&err.code()) => {
+                }
+                Err(err) => {
+                    self.state = ConnectionState::Failed;
+                    return Err(gst::error_msg!(
+                        gst::ResourceError::Failed,
+                        ["DTLS handshake failed: {err}"]
+                    ));
+                }
+            },
+            ConnectionState::Connected => {
+                // No application data is expected, this only handles alerts and
+                // retransmissions of the peer's last handshake flight
+                let mut buf = [0u8; 1500];
+                loop {
+                    match stream.ssl_read(&mut buf) {
+                        Ok(len) => {
+                            gst::trace!(CAT, "Ignoring {len} bytes of DTLS application data");
+                        }
+                        Err(err) if err.code() == ErrorCode::ZERO_RETURN => {
+                            gst::info!(CAT, "DTLS connection closed by peer");
+                            self.state = ConnectionState::Closed;
+                            break;
+                        }
+                        Err(err) if err.code() == ErrorCode::WANT_READ => break,
+                        Err(err) => {
+                            gst::warning!(CAT, "Failed to read from DTLS connection: {err}");
+                            break;
+                        }
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        Ok(())
+    }
+
+    fn handshake_done(&self) -> Result<SrtpKeys, gst::ErrorMessage> {
+        let ssl = self.stream.as_ref().unwrap().ssl();
+
+        // The certificate itself is not verified, so the fingerprint is what authenticates the
+        // peer and without it anybody could be in the middle
+        let Some(ref expected) = self.peer_fingerprint else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::NotAuthorized,
+                ["No peer fingerprint to authenticate the peer"]
+            ));
+        };
+        let Some(cert) = ssl.peer_certificate() else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::NotAuthorized,
+                ["Peer did not provide a certificate"]
+            ));
+        };
+        let actual = fingerprint(&cert).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::Failed,
+                ["Failed to compute peer fingerprint: {err}"]
+            )
+        })?;
+        if !actual.eq_ignore_ascii_case(expected.trim()) {
+            return Err(gst::error_msg!(
+                gst::ResourceError::NotAuthorized,
+                ["Peer fingerprint {actual} does not match {expected}"]
+            ));
+        }
+
+        let profile = match ssl.selected_srtp_profile().map(|profile| profile.id()) {
+            Some(SrtpProfileId::SRTP_AES128_CM_SHA1_80) => SrtpProfile::Aes128CmHmacSha1_80,
+            Some(SrtpProfileId::SRTP_AES128_CM_SHA1_32) => SrtpProfile::Aes128CmHmacSha1_32,
+            _ => {
+                return Err(gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["No supported SRTP protection profile negotiated"]
+                ));
+            }
+        };
+
+        // client key | server key | client salt | server salt, see RFC 5764 section 4.2
+        let mut material = [0u8; 2 * (SRTP_KEY_LEN + SRTP_SALT_LEN)];
+        ssl.export_keying_material(&mut material, SRTP_EXPORTER_LABEL, None)
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Failed,
+                    ["Failed to export SRTP keying material: {err}"]
+                )
+            })?;
+        let (client_key, rest) = material.split_at(SRTP_KEY_LEN);
+        let (server_key, rest) = rest.split_at(SRTP_KEY_LEN);
+        let (client_salt, server_salt) = rest.split_at(SRTP_SALT_LEN);
+        let client = [client_key, client_salt].concat();
+        let server = [server_key, server_salt].concat();
+
+        let (local, remote) = match self.role {
+            Role::Client => (client, server),
+            Role::Server => (server, client),
+        };
+
+        Ok(SrtpKeys {
+            profile,
+            local,
+            remote,
+        })
+    }
+}
+
+impl Connection {
+    pub fn recv_get_or_init(name: String) -> Self {
+        CONNECTIONS
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .unwrap()
+            .entry(name)
+            .and_modify(|v| {
+                v.inner.lock().unwrap().recv_outstanding = true;
+            })
+            .or_insert_with_key(|name| Connection {
+                name: name.to_owned(),
+                inner: Arc::new(Mutex::new(ConnectionInner::new(false, true))),
+            })
+            .clone()
+    }
+
+    pub fn send_get_or_init(name: String) -> Self {
+        CONNECTIONS
+            .get_or_init(|| Mutex::new(HashMap::new()))
+            .lock()
+            .unwrap()
+            .entry(name)
+            .and_modify(|v| {
+                v.inner.lock().unwrap().send_outstanding = true;
+            })
+            .or_insert_with_key(|name| Connection {
+                name: name.to_owned(),
+                inner: Arc::new(Mutex::new(ConnectionInner::new(true, false))),
+            })
+            .clone()
+    }
+
+    pub fn unmark_send_outstanding(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.send_outstanding = false;
+        if !inner.recv_outstanding {
+            drop(inner);
+            Self::remove_from_global(&self.name);
+        }
+    }
+
+    pub fn unmark_recv_outstanding(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.recv_outstanding = false;
+        if !inner.send_outstanding {
+            drop(inner);
+            Self::remove_from_global(&self.name);
+        }
+    }
+
+    fn remove_from_global(name: &str) {
+        let _connection = CONNECTIONS.get().unwrap().lock().unwrap().remove(name);
+    }
+
+    pub fn set_sender(&self, sender: Option<DatagramSender>) {
+        self.inner.lock().unwrap().sender = sender;
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        self.inner.lock().unwrap().state
+    }
+
+    pub fn keys(&self) -> Option<SrtpKeys> {
+        self.inner.lock().unwrap().keys.clone()
+    }
+
+    /// Starts the handshake. As a client this immediately sends the `ClientHello`.
+    ///
+    /// The handshake fails unless the peer certificate matches `peer_fingerprint`.
+    pub fn start(
+        &self,
+        role: Role,
+        certificate: &Certificate,
+        peer_fingerprint: String,
+    ) -> Result<(), gst::ErrorMessage> {
+        let to_error = |err: ErrorStack| {
+            gst::error_msg!(gst::LibraryError::Init, ["Failed to set up DTLS: {err}"])
+        };
+
+        let mut ctx = SslContext::builder(SslMethod::dtls()).map_err(to_error)?;
+        ctx.set_tlsext_use_srtp(SRTP_PROFILES).map_err(to_error)?;
+        ctx.set_certificate(&certificate.cert).map_err(to_error)?;
+        ctx.set_private_key(&certificate.key).map_err(to_error)?;
+        ctx.check_private_key().map_err(to_error)?;
+        // Certificates are usually self-signed and authenticated by their fingerprint instead
+        // once the handshake is done
+        ctx.set_verify_callback(
+            SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+            |_, _| true,
+        );
+
+        let mut ssl = Ssl::new(&ctx.build()).map_err(to_error)?;
+        ssl.set_mtu(DTLS_MTU).map_err(to_error)?;
+        match role {
+            Role::Client => ssl.set_connect_state(),
+            Role::Server => ssl.set_accept_state(),
+        }
+        let mut stream = SslStream::new(ssl, Transport::default()).map_err(to_error)?;
+
+        self.process(|inner| {
+            gst::debug!(CAT, "Starting DTLS handshake as {role:?}");
+
+            stream
+                .get_mut()
+                .incoming
+                .extend(std::mem::take(&mut inner.pending));
+            inner.role = role;
+            inner.peer_fingerprint = Some(peer_fingerprint);
+            inner.stream = Some(stream);
+            inner.state = ConnectionState::Connecting;
+            inner.keys = None;
+        })
+    }
+
+    /// Stops the connection, notifying the peer if the handshake was done
+    pub fn stop(&self) {
+        let _ = self.process(|inner| {
+            if let Some(ref mut stream) = inner.stream {
+                if inner.state == ConnectionState::Connected {
+                    let _ = stream.shutdown();
+                }
+            }
+            inner.state = ConnectionState::New;
+            inner.keys = None;
+        });
+        self.inner.lock().unwrap().stream = None;
+    }
+
+    /// Handles a DTLS record received from the peer
+    pub fn handle_datagram(&self, data: &[u8]) -> Result<(), gst::ErrorMessage> {
+        self.process(|inner| match inner.stream {
+            Some(ref mut stream) => stream.get_mut().incoming.push_back(data.to_vec()),
+            None if inner.pending.len() < MAX_PENDING_DATAGRAMS => {
+                inner.pending.push(data.to_vec())
+            }
+            None => gst::debug!(CAT, "Dropping DTLS record received before starting"),
+        })
+    }
+
+    /// Retransmits the last handshake flight if the DTLS timer expired
+    pub fn handle_timeout(&self) -> Result<(), gst::ErrorMessage> {
+        self.process(|_| ())
+    }
+
+    fn process(&self, func: impl FnOnce(&mut ConnectionInner)) -> Result<(), gst::ErrorMessage> {
+        let mut inner = self.inner.lock().unwrap();
+        func(&mut inner);
+        let res = inner.drive();
+        let outgoing = inner
+            .stream
+            .as_mut()
+            .map(|stream| std::mem::take(&mut stream.get_mut().outgoing))
+            .unwrap_or_default();
+        let sender = inner.sender.clone();
+        drop(inner);
+
+        if let Some(sender) = sender {
+            for datagram in outgoing {
+                sender(datagram);
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Queue = Arc<Mutex<VecDeque<Vec<u8>>>>;
+
+    fn init() {
+        use std::sync::Once;
+        static INIT: Once = Once::new();
+
+        INIT.call_once(|| {
+            gst::init().unwrap();
+        });
+    }
+
+    fn connection(name: &str) -> (Connection, Queue) {
+        let connection = Connection::send_get_or_init(name.to_owned());
+        let queue = Queue::default();
+        connection.set_sender(Some(Arc::new({
+            let queue = queue.clone();
+            move |datagram| queue.lock().unwrap().push_back(datagram)
+        })));
+
+        (connection, queue)
+    }
+
+    /// Forwards the datagrams between both connections until neither has anything left to send
+    fn exchange(
+        (client, client_queue): &(Connection, Queue),
+        (server, server_queue): &(Connection, Queue),
+    ) -> Result<(), gst::ErrorMessage> {
+        loop {
+            let to_server = std::mem::take(&mut *client_queue.lock().unwrap());
+            let to_client = std::mem::take(&mut *server_queue.lock().unwrap());
+            if to_server.is_empty() && to_client.is_empty() {
+                return Ok(());
+            }
+
+            for datagram in to_server {
+                assert!(is_dtls(&datagram));
+                server.handle_datagram(&datagram)?;
+            }
+            for datagram in to_client {
+                assert!(is_dtls(&datagram));
+                client.handle_datagram(&datagram)?;
+            }
+        }
+    }
+
+    #[test]
+    fn handshake() {
+        init();
+
+        let client_cert = Certificate::generate().unwrap();
+        let server_cert = Certificate::generate().unwrap();
+        let client = connection("test-handshake-client");
+        let server = connection("test-handshake-server");
+
+        server
+            .0
+            .start(
+                Role::Server,
+                &server_cert,
+                client_cert.fingerprint().unwrap(),
+            )
+            .unwrap();
+        client
+            .0
+            .start(
+                Role::Client,
+                &client_cert,
+                server_cert.fingerprint().unwrap(),
+            )
+            .unwrap();
+        exchange(&client, &server).unwrap();
+
+        assert_eq!(client.0.state(), ConnectionState::Connected);
+        assert_eq!(server.0.state(), ConnectionState::Connected);
+
+        // Both sides derive the same keys for either direction
+        let client_keys = client.0.keys().unwrap();
+        let server_keys = server.0.keys().unwrap();
+        assert_eq!(client_keys.profile, SrtpProfile::Aes128CmHmacSha1_80);
+        assert_eq!(client_keys.profile, server_keys.profile);
+        assert_eq!(client_keys.local, server_keys.remote);
+        assert_eq!(client_keys.remote, server_keys.local);
+        assert_eq!(client_keys.local.len(), SRTP_KEY_LEN + SRTP_SALT_LEN);
+        assert_ne!(client_keys.local, client_keys.remote);
+
+        client.0.unmark_send_outstanding();
+        server.0.unmark_send_outstanding();
+    }
+
+    #[test]
+    fn fingerprint_mismatch() {
+        init();
+
+        let client_cert = Certificate::generate().unwrap();
+        let server_cert = Certificate::generate().unwrap();
+        // Somebody in the middle presenting a different certificate than the expected one
+        let other_cert = Certificate::generate().unwrap();
+        let client = connection("test-mismatch-client");
+        let server = connection("test-mismatch-server");
+
+        server
+            .0
+            .start(
+                Role::Server,
+                &server_cert,
+                client_cert.fingerprint().unwrap(),
+            )
+            .unwrap();
+        client
+            .0
+            .start(
+                Role::Client,
+                &client_cert,
+                other_cert.fingerprint().unwrap(),
+            )
+            .unwrap();
+        let err = exchange(&client, &server).unwrap_err();
+
+        assert!(err.to_string().contains("does not match"), "{err}");
+        assert_eq!(client.0.state(), ConnectionState::Failed);
+        assert!(client.0.keys().is_none());
+
+        client.0.unmark_send_outstanding();
+        server.0.unmark_send_outstanding();
+    }
+}
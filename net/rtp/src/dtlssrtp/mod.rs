@@ -0,0 +1,47 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod connection;
+mod recv;
+mod send;
+
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRtpDtlsSrtpRole")]
+pub enum Role {
+    #[default]
+    #[enum_value(name = "Client: Initiates the DTLS handshake", nick = "client")]
+    Client,
+    #[enum_value(
+        name = "Server: Waits for the peer to initiate the DTLS handshake",
+        nick = "server"
+    )]
+    Server,
+}
+
+glib::wrapper! {
+    pub struct DtlsSrtpSend(ObjectSubclass<send::DtlsSrtpSend>) @extends gst::Bin, gst::Element, gst::Object, @implements gst::ChildProxy;
+}
+glib::wrapper! {
+    pub struct DtlsSrtpRecv(ObjectSubclass<recv::DtlsSrtpRecv>) @extends gst::Bin, gst::Element, gst::Object, @implements gst::ChildProxy;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    Role::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "rtpdtlssrtpsend",
+        gst::Rank::NONE,
+        DtlsSrtpSend::static_type(),
+    )?;
+    gst::Element::register(
+        Some(plugin),
+        "rtpdtlssrtprecv",
+        gst::Rank::NONE,
+        DtlsSrtpRecv::static_type(),
+    )
+}
@@ -0,0 +1,302 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpdtlssrtprecv
+ * @see_also: rtpdtlssrtpsend, rtpsend, rtprecv, srtpdec
+ *
+ * Unprotects SRTP and SRTCP with keys negotiated by a DTLS handshake (RFC 5764), without
+ * requiring the full WebRTC stack.
+ *
+ * DTLS records received on the `sink` pad are demultiplexed from the SRTP and SRTCP packets
+ * (RFC 7983) and handed to the `rtpdtlssrtpsend` element sharing the same `dtls-id`, which
+ * drives the handshake. Packets received before the handshake is done are dropped.
+ *
+ * The decrypted RTP and RTCP packets are output on the `rtp_src` and `rtcp_src` pads
+ * respectively, for example to be linked to a `rtprecv` element.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::ops::ControlFlow;
+use std::sync::{LazyLock, Mutex};
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+use super::connection::{self, Connection};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpdtlssrtprecv",
+        gst::DebugColorFlags::empty(),
+        Some("RTP DTLS-SRTP receiver"),
+    )
+});
+
+const DEFAULT_DTLS_ID: &str = "dtls-id";
+
+#[derive(Debug, Clone)]
+struct Settings {
+    dtls_id: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            dtls_id: DEFAULT_DTLS_ID.to_owned(),
+        }
+    }
+}
+
+pub struct DtlsSrtpRecv {
+    settings: Mutex<Settings>,
+    connection: Mutex<Option<Connection>>,
+    srtpdec: gst::Element,
+}
+
+impl DtlsSrtpRecv {
+    /// Whether a received packet is to be forwarded to `srtpdec`. DTLS records are passed to the
+    /// connection instead.
+    fn handle_packet(&self, connection: &Connection, buffer: &gst::BufferRef) -> bool {
+        let Ok(map) = buffer.map_readable() else {
+            return false;
+        };
+
+        if connection::is_dtls(&map) {
+            gst::trace!(CAT, imp = self, "Received {} bytes DTLS record", map.len());
+            if let Err(err) = connection.handle_datagram(&map) {
+                self.post_error_message(err);
+            }
+            return false;
+        }
+
+        if connection.keys().is_none() {
+            gst::trace!(
+                CAT,
+                imp = self,
+                "Dropping packet before DTLS handshake is done"
+            );
+            return false;
+        }
+
+        true
+    }
+
+    fn sink_probe(&self, info: &mut gst::PadProbeInfo) -> gst::PadProbeReturn {
+        let Some(connection) = self.connection.lock().unwrap().clone() else {
+            return gst::PadProbeReturn::Drop;
+        };
+
+        match info.data {
+            Some(gst::PadProbeData::Buffer(ref buffer)) => {
+                if self.handle_packet(&connection, buffer) {
+                    gst::PadProbeReturn::Ok
+                } else {
+                    gst::PadProbeReturn::Drop
+                }
+            }
+            Some(gst::PadProbeData::BufferList(ref mut list)) => {
+                list.make_mut().foreach_mut(|buffer, _i| {
+                    if self.handle_packet(&connection, &buffer) {
+                        ControlFlow::Continue(Some(buffer))
+                    } else {
+                        ControlFlow::Continue(None)
+                    }
+                });
+                if list.is_empty() {
+                    gst::PadProbeReturn::Drop
+                } else {
+                    gst::PadProbeReturn::Ok
+                }
+            }
+            _ => gst::PadProbeReturn::Ok,
+        }
+    }
+
+    fn request_key(&self, ssrc: u32) -> Option<gst::Caps> {
+        let connection = self.connection.lock().unwrap().clone()?;
+        let Some(keys) = connection.keys() else {
+            gst::warning!(CAT, imp = self, "No SRTP keys for SSRC {ssrc:#010x} yet");
+            return None;
+        };
+
+        gst::debug!(CAT, imp = self, "Providing SRTP keys for SSRC {ssrc:#010x}");
+
+        Some(
+            gst::Caps::builder("application/x-srtp")
+                .field("srtp-key", gst::Buffer::from_slice(keys.remote))
+                .field("srtp-cipher", keys.profile.cipher())
+                .field("srtp-auth", keys.profile.rtp_auth())
+                .field("srtcp-cipher", keys.profile.cipher())
+                .field("srtcp-auth", keys.profile.rtcp_auth())
+                .build(),
+        )
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for DtlsSrtpRecv {
+    const NAME: &'static str = "GstRtpDtlsSrtpRecv";
+    type Type = super::DtlsSrtpRecv;
+    type ParentType = gst::Bin;
+
+    fn new() -> Self {
+        Self {
+            settings: Default::default(),
+            connection: Default::default(),
+            srtpdec: gst::ElementFactory::make("srtpdec").build().unwrap(),
+        }
+    }
+}
+
+impl ObjectImpl for DtlsSrtpRecv {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![glib::ParamSpecString::builder("dtls-id")
+                .nick("DTLS ID")
+                .blurb("The DTLS connection ID shared with a rtpdtlssrtpsend element")
+                .default_value(Some(DEFAULT_DTLS_ID))
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "dtls-id" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.dtls_id = value.get::<String>().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "dtls-id" => {
+                let settings = self.settings.lock().unwrap();
+                settings.dtls_id.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add(&self.srtpdec).unwrap();
+
+        let element = obj.downgrade();
+        self.srtpdec.connect("request-key", false, move |args| {
+            let ssrc = args[1].get::<u32>().unwrap();
+            let caps = element
+                .upgrade()
+                .and_then(|element| element.imp().request_key(ssrc));
+            Some(caps.to_value())
+        });
+
+        let templ = obj.pad_template("sink").unwrap();
+        let sink_pad = gst::GhostPad::from_template_with_target(
+            &templ,
+            &self.srtpdec.static_pad("rtp_sink").unwrap(),
+        )
+        .unwrap();
+        let element = obj.downgrade();
+        sink_pad.add_probe(
+            gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+            move |_pad, info| {
+                let Some(element) = element.upgrade() else {
+                    return gst::PadProbeReturn::Drop;
+                };
+                element.imp().sink_probe(info)
+            },
+        );
+        obj.add_pad(&sink_pad).unwrap();
+
+        for name in ["rtp_src", "rtcp_src"] {
+            let templ = obj.pad_template(name).unwrap();
+            let pad = gst::GhostPad::from_template_with_target(
+                &templ,
+                &self.srtpdec.static_pad(name).unwrap(),
+            )
+            .unwrap();
+            obj.add_pad(&pad).unwrap();
+        }
+    }
+}
+
+impl GstObjectImpl for DtlsSrtpRecv {}
+
+impl ElementImpl for DtlsSrtpRecv {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP DTLS-SRTP Receiver",
+                "Network/RTP/Decoder",
+                "Unprotects SRTP and SRTCP with keys negotiated over DTLS",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            let rtp_src_pad_template = gst::PadTemplate::new(
+                "rtp_src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_empty_simple("application/x-rtp"),
+            )
+            .unwrap();
+
+            let rtcp_src_pad_template = gst::PadTemplate::new(
+                "rtcp_src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_empty_simple("application/x-rtcp"),
+            )
+            .unwrap();
+
+            vec![
+                sink_pad_template,
+                rtp_src_pad_template,
+                rtcp_src_pad_template,
+            ]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        if transition == gst::StateChange::NullToReady {
+            let dtls_id = self.settings.lock().unwrap().dtls_id.clone();
+            *self.connection.lock().unwrap() = Some(Connection::recv_get_or_init(dtls_id));
+        }
+
+        let ret = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::ReadyToNull {
+            if let Some(connection) = self.connection.lock().unwrap().take() {
+                connection.unmark_recv_outstanding();
+            }
+        }
+
+        Ok(ret)
+    }
+}
+
+impl BinImpl for DtlsSrtpRecv {}
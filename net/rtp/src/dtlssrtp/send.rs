@@ -0,0 +1,485 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpdtlssrtpsend
+ * @see_also: rtpdtlssrtprecv, rtpsend, rtprecv, srtpenc
+ *
+ * Protects RTP and RTCP with SRTP keys negotiated by a DTLS handshake (RFC 5764), without
+ * requiring the full WebRTC stack.
+ *
+ * The element performs the handshake together with a `rtpdtlssrtprecv` element that shares the
+ * same `dtls-id`. DTLS records for the peer are sent from the `src` pad, multiplexed with the
+ * SRTP and SRTCP packets. RTP and RTCP packets are dropped until the handshake is done.
+ *
+ * The peer is authenticated by the fingerprint of its certificate, which has to be configured
+ * with `peer-fingerprint` as exchanged e.g. via SDP. The handshake fails if it is not set or
+ * does not match.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 videotestsrc ! vp8enc ! rtpvp8pay2 ! rtpsend name=rtpsend \
+ *     rtpsend.rtp_src_0 ! dtls.rtp_sink rtpsend.rtcp_src_0 ! dtls.rtcp_sink \
+ *     rtpdtlssrtpsend name=dtls dtls-id=dtls0 role=client peer-fingerprint="sha-256 AB:CD:..." \
+ *     ! udpsink host=127.0.0.1 port=5004 \
+ *     udpsrc port=5004 bind-port=5004 ! rtpdtlssrtprecv dtls-id=dtls0 name=dtlsrecv \
+ *     dtlsrecv.rtcp_src ! rtprecv name=rtprecv rtp-id=rtpsend ...
+ * ]|
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::sync::{Arc, LazyLock, Mutex};
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+use super::connection::{Certificate, Connection, SrtpKeys};
+use super::Role;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpdtlssrtpsend",
+        gst::DebugColorFlags::empty(),
+        Some("RTP DTLS-SRTP sender"),
+    )
+});
+
+const DEFAULT_DTLS_ID: &str = "dtls-id";
+const DEFAULT_ROLE: Role = Role::Client;
+/// Interval at which the DTLS timers are checked for handshake retransmissions
+const TIMEOUT_INTERVAL: gst::ClockTime = gst::ClockTime::from_mseconds(100);
+
+#[derive(Debug, Clone)]
+struct Settings {
+    dtls_id: String,
+    role: Role,
+    pem: Option<String>,
+    peer_fingerprint: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            dtls_id: DEFAULT_DTLS_ID.to_owned(),
+            role: DEFAULT_ROLE,
+            pem: None,
+            peer_fingerprint: None,
+        }
+    }
+}
+
+struct State {
+    connection: Connection,
+    timeout: Option<gst::PeriodicClockId>,
+    keys: Option<SrtpKeys>,
+}
+
+pub struct DtlsSrtpSend {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+    certificate: Mutex<Option<Certificate>>,
+    srtpenc: gst::Element,
+    dtlssrc: gst::Element,
+    funnel: gst::Element,
+}
+
+impl DtlsSrtpSend {
+    fn certificate(&self) -> Result<Certificate, gst::ErrorMessage> {
+        let mut certificate = self.certificate.lock().unwrap();
+        if let Some(ref certificate) = *certificate {
+            return Ok(certificate.clone());
+        }
+
+        let pem = self.settings.lock().unwrap().pem.clone();
+        let new_certificate = match pem {
+            Some(pem) => Certificate::from_pem(&pem).map_err(|err| {
+                gst::error_msg!(
+                    gst::LibraryError::Settings,
+                    ["Failed to parse certificate: {err}"]
+                )
+            })?,
+            None => Certificate::generate().map_err(|err| {
+                gst::error_msg!(
+                    gst::LibraryError::Init,
+                    ["Failed to generate certificate: {err}"]
+                )
+            })?,
+        };
+        *certificate = Some(new_certificate.clone());
+
+        Ok(new_certificate)
+    }
+
+    /// Drops RTP and RTCP until the handshake is done and configures `srtpenc` once the keys
+    /// are known
+    fn sink_probe(&self, pad: &gst::Pad, info: &gst::PadProbeInfo) -> gst::PadProbeReturn {
+        if !matches!(
+            info.data,
+            Some(gst::PadProbeData::Buffer(_)) | Some(gst::PadProbeData::BufferList(_))
+        ) {
+            return gst::PadProbeReturn::Ok;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let Some(ref mut state) = *state else {
+            return gst::PadProbeReturn::Drop;
+        };
+
+        let Some(keys) = state.connection.keys() else {
+            gst::trace!(
+                CAT,
+                obj = pad,
+                "Dropping packet before DTLS handshake is done"
+            );
+            return gst::PadProbeReturn::Drop;
+        };
+
+        if state.keys.as_ref() != Some(&keys) {
+            gst::debug!(CAT, imp = self, "Configuring SRTP keys");
+            self.srtpenc
+                .set_property("key", gst::Buffer::from_slice(keys.local.clone()));
+            self.srtpenc
+                .set_property_from_str("rtp-cipher", keys.profile.cipher());
+            self.srtpenc
+                .set_property_from_str("rtp-auth", keys.profile.rtp_auth());
+            self.srtpenc
+                .set_property_from_str("rtcp-cipher", keys.profile.cipher());
+            self.srtpenc
+                .set_property_from_str("rtcp-auth", keys.profile.rtcp_auth());
+            state.keys = Some(keys);
+        }
+
+        gst::PadProbeReturn::Ok
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let certificate = self.certificate()?;
+        let settings = self.settings.lock().unwrap().clone();
+        let connection = self
+            .state
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .connection
+            .clone();
+
+        let Some(peer_fingerprint) = settings.peer_fingerprint else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No peer-fingerprint configured to authenticate the peer"]
+            ));
+        };
+
+        connection.start(settings.role, &certificate, peer_fingerprint)?;
+
+        let clock = gst::SystemClock::obtain();
+        let timeout =
+            clock.new_periodic_id(clock.time().unwrap() + TIMEOUT_INTERVAL, TIMEOUT_INTERVAL);
+        let element = self.obj().downgrade();
+        timeout
+            .wait_async(move |_clock, _time, _id| {
+                if let Err(err) = connection.handle_timeout() {
+                    if let Some(element) = element.upgrade() {
+                        element.post_error_message(err);
+                    }
+                }
+            })
+            .map_err(|err| {
+                gst::error_msg!(
+                    gst::CoreError::Clock,
+                    ["Failed to schedule DTLS timeout: {err:?}"]
+                )
+            })?;
+        self.state.lock().unwrap().as_mut().unwrap().timeout = Some(timeout);
+
+        Ok(())
+    }
+
+    fn stop(&self) {
+        let mut state = self.state.lock().unwrap();
+        let Some(ref mut state) = *state else {
+            return;
+        };
+
+        if let Some(timeout) = state.timeout.take() {
+            timeout.unschedule();
+        }
+        state.keys = None;
+        state.connection.stop();
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for DtlsSrtpSend {
+    const NAME: &'static str = "GstRtpDtlsSrtpSend";
+    type Type = super::DtlsSrtpSend;
+    type ParentType = gst::Bin;
+
+    fn new() -> Self {
+        Self {
+            settings: Default::default(),
+            state: Default::default(),
+            certificate: Default::default(),
+            srtpenc: gst::ElementFactory::make("srtpenc").build().unwrap(),
+            dtlssrc: gst::ElementFactory::make("appsrc")
+                .property("is-live", true)
+                .property("caps", gst::Caps::new_empty_simple("application/x-dtls"))
+                .property_from_str("format", "time")
+                .property("do-timestamp", true)
+                .build()
+                .unwrap(),
+            funnel: gst::ElementFactory::make("funnel").build().unwrap(),
+        }
+    }
+}
+
+impl ObjectImpl for DtlsSrtpSend {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecString::builder("dtls-id")
+                    .nick("DTLS ID")
+                    .blurb("The DTLS connection ID shared with a rtpdtlssrtprecv element")
+                    .default_value(Some(DEFAULT_DTLS_ID))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("role", DEFAULT_ROLE)
+                    .nick("Role")
+                    .blurb("Whether to act as DTLS client or server")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("pem")
+                    .nick("PEM")
+                    .blurb(
+                        "PEM encoded certificate and private key, a self-signed certificate \
+                        is generated if unset",
+                    )
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("fingerprint")
+                    .nick("Fingerprint")
+                    .blurb("SHA-256 fingerprint of the certificate, as used in SDP")
+                    .read_only()
+                    .build(),
+                glib::ParamSpecString::builder("peer-fingerprint")
+                    .nick("Peer Fingerprint")
+                    .blurb(
+                        "Expected SHA-256 fingerprint of the peer certificate as used in SDP \
+                        (e.g. \"sha-256 AB:CD:...\"), required to authenticate the peer",
+                    )
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "dtls-id" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.dtls_id = value.get::<String>().expect("type checked upstream");
+            }
+            "role" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.role = value.get::<Role>().expect("type checked upstream");
+            }
+            "pem" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.pem = value.get().expect("type checked upstream");
+                *self.certificate.lock().unwrap() = None;
+            }
+            "peer-fingerprint" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.peer_fingerprint = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "dtls-id" => {
+                let settings = self.settings.lock().unwrap();
+                settings.dtls_id.to_value()
+            }
+            "role" => {
+                let settings = self.settings.lock().unwrap();
+                settings.role.to_value()
+            }
+            "pem" => {
+                let settings = self.settings.lock().unwrap();
+                settings.pem.to_value()
+            }
+            "fingerprint" => match self
+                .certificate()
+                .ok()
+                .and_then(|certificate| certificate.fingerprint().ok())
+            {
+                Some(fingerprint) => fingerprint.to_value(),
+                None => {
+                    gst::warning!(CAT, imp = self, "Failed to get certificate fingerprint");
+                    None::<String>.to_value()
+                }
+            },
+            "peer-fingerprint" => {
+                let settings = self.settings.lock().unwrap();
+                settings.peer_fingerprint.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_many([&self.srtpenc, &self.dtlssrc, &self.funnel])
+            .unwrap();
+
+        for (name, srtp_sink, srtp_src) in [
+            ("rtp_sink", "rtp_sink_0", "rtp_src_0"),
+            ("rtcp_sink", "rtcp_sink_0", "rtcp_src_0"),
+        ] {
+            let target = self.srtpenc.request_pad_simple(srtp_sink).unwrap();
+            let templ = obj.pad_template(name).unwrap();
+            let pad = gst::GhostPad::from_template_with_target(&templ, &target).unwrap();
+
+            let element = obj.downgrade();
+            pad.add_probe(
+                gst::PadProbeType::BUFFER | gst::PadProbeType::BUFFER_LIST,
+                move |pad, info| {
+                    let Some(element) = element.upgrade() else {
+                        return gst::PadProbeReturn::Drop;
+                    };
+                    element.imp().sink_probe(pad, info)
+                },
+            );
+            obj.add_pad(&pad).unwrap();
+
+            self.srtpenc
+                .static_pad(srtp_src)
+                .unwrap()
+                .link(&self.funnel.request_pad_simple("sink_%u").unwrap())
+                .unwrap();
+        }
+
+        self.dtlssrc.link(&self.funnel).unwrap();
+
+        let templ = obj.pad_template("src").unwrap();
+        let src_pad = gst::GhostPad::from_template_with_target(
+            &templ,
+            &self.funnel.static_pad("src").unwrap(),
+        )
+        .unwrap();
+        obj.add_pad(&src_pad).unwrap();
+    }
+}
+
+impl GstObjectImpl for DtlsSrtpSend {}
+
+impl ElementImpl for DtlsSrtpSend {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP DTLS-SRTP Sender",
+                "Network/RTP/Encoder",
+                "Protects RTP and RTCP with SRTP keys negotiated over DTLS",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let rtp_sink_pad_template = gst::PadTemplate::new(
+                "rtp_sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_empty_simple("application/x-rtp"),
+            )
+            .unwrap();
+
+            let rtcp_sink_pad_template = gst::PadTemplate::new(
+                "rtcp_sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_empty_simple("application/x-rtcp"),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![
+                rtp_sink_pad_template,
+                rtcp_sink_pad_template,
+                src_pad_template,
+            ]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        match transition {
+            gst::StateChange::NullToReady => {
+                let dtls_id = self.settings.lock().unwrap().dtls_id.clone();
+                let connection = Connection::send_get_or_init(dtls_id);
+
+                let dtlssrc = self.dtlssrc.downgrade();
+                connection.set_sender(Some(Arc::new(move |datagram| {
+                    if let Some(dtlssrc) = dtlssrc.upgrade() {
+                        let _ = dtlssrc.emit_by_name::<gst::FlowReturn>(
+                            "push-buffer",
+                            &[&gst::Buffer::from_mut_slice(datagram)],
+                        );
+                    }
+                })));
+
+                *self.state.lock().unwrap() = Some(State {
+                    connection,
+                    timeout: None,
+                    keys: None,
+                });
+            }
+            gst::StateChange::ReadyToPaused => {
+                if let Err(err) = self.start() {
+                    self.post_error_message(err);
+                    return Err(gst::StateChangeError);
+                }
+            }
+            _ => (),
+        }
+
+        let ret = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                self.stop();
+            }
+            gst::StateChange::ReadyToNull => {
+                if let Some(state) = self.state.lock().unwrap().take() {
+                    state.connection.set_sender(None);
+                    state.connection.unmark_send_outstanding();
+                }
+            }
+            _ => (),
+        }
+
+        Ok(ret)
+    }
+}
+
+impl BinImpl for DtlsSrtpSend {}
@@ -0,0 +1,244 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpdtmfdepay2
+ * @see_also: rtpdtmfpay2, rtpdtmfdepay
+ *
+ * Depayload telephone events, e.g. DTMF digits, from RTP packets as per [RFC 4733][rfc-4733].
+ *
+ * One buffer is output per completed event, with the event code in the first byte, the volume
+ * in -dBm0 in the second byte and the start and duration of the event as buffer PTS and
+ * duration. Retransmissions of the final packet of an event are discarded.
+ *
+ * When an event starts, a `dtmf-event` element message is posted like `rtpdtmfdepay` does.
+ *
+ * [rfc-4733]: https://www.rfc-editor.org/rfc/rfc4733.html
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use atomic_refcell::AtomicRefCell;
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+use std::sync::LazyLock;
+
+use crate::basedepay::{Packet, PacketToBufferRelation, RtpBaseDepay2Ext, RtpBaseDepay2Impl};
+use crate::dtmf::event::{DtmfEvent, TelephoneEvent, MAX_DTMF_EVENT};
+
+#[derive(Default)]
+pub struct RtpDtmfDepay {
+    state: AtomicRefCell<State>,
+}
+
+#[derive(Debug)]
+struct CurrentEvent {
+    event: TelephoneEvent,
+    ext_timestamp: u64,
+    first_ext_seqnum: u64,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    clock_rate: Option<u32>,
+    current: Option<CurrentEvent>,
+    /// RTP timestamp of the last completed event, to discard its repeated end packets
+    last_ext_timestamp: Option<u64>,
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpdtmfdepay2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP Telephone Event Depayloader"),
+    )
+});
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpDtmfDepay {
+    const NAME: &'static str = "GstRtpDtmfDepay2";
+    type Type = super::RtpDtmfDepay;
+    type ParentType = crate::basedepay::RtpBaseDepay2;
+}
+
+impl ObjectImpl for RtpDtmfDepay {}
+
+impl GstObjectImpl for RtpDtmfDepay {}
+
+impl ElementImpl for RtpDtmfDepay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP Telephone Event Depayloader",
+                "Codec/Depayloader/Network/RTP",
+                "Depayload telephone events from RTP packets (RFC 4733)",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("application/x-rtp")
+                    .field("media", "audio")
+                    .field("encoding-name", "TELEPHONE-EVENT")
+                    .field("clock-rate", gst::IntRange::new(1, i32::MAX))
+                    .build(),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::new_empty_simple("application/x-telephone-event"),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl RtpBaseDepay2Impl for RtpDtmfDepay {
+    const ALLOWED_META_TAGS: &'static [&'static str] = &[];
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn flush(&self) {
+        let mut state = self.state.borrow_mut();
+        state.current = None;
+        state.last_ext_timestamp = None;
+    }
+
+    fn set_sink_caps(&self, caps: &gst::Caps) -> bool {
+        let s = caps.structure(0).unwrap();
+
+        let Some(clock_rate) = s.get::<i32>("clock-rate").ok().filter(|rate| *rate > 0) else {
+            gst::error!(CAT, imp = self, "No valid clock-rate in caps {caps}");
+            return false;
+        };
+        self.state.borrow_mut().clock_rate = Some(clock_rate as u32);
+
+        self.obj().set_src_caps(&gst::Caps::new_empty_simple(
+            "application/x-telephone-event",
+        ));
+
+        true
+    }
+
+    // https://www.rfc-editor.org/rfc/rfc4733.html#section-2.5.2
+    //
+    // All packets of an event share the same timestamp and carry the duration of the event so
+    // far. The event is output once its end packet was received, or once a packet of a new event
+    // shows up if all end packets were lost.
+    fn handle_packet(&self, packet: &Packet) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.borrow_mut();
+
+        let Some(event) = TelephoneEvent::parse(packet.payload()) else {
+            gst::warning!(CAT, imp = self, "Invalid telephone-event payload");
+            self.obj().drop_packet(packet);
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        if state.last_ext_timestamp == Some(packet.ext_timestamp()) {
+            gst::trace!(CAT, imp = self, "Dropping packet of already finished event");
+            self.obj().drop_packet(packet);
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        if state
+            .current
+            .as_ref()
+            .is_some_and(|current| current.ext_timestamp != packet.ext_timestamp())
+        {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "New event before the end of the previous one"
+            );
+            let current = state.current.take().unwrap();
+            state.last_ext_timestamp = Some(current.ext_timestamp);
+            self.finish_event(&state, current, packet.ext_seqnum() - 1)?;
+        }
+
+        let started = state.current.is_none();
+        let current = state.current.get_or_insert(CurrentEvent {
+            event,
+            ext_timestamp: packet.ext_timestamp(),
+            first_ext_seqnum: packet.ext_seqnum(),
+        });
+        current.event.duration = current.event.duration.max(event.duration);
+        current.event.end = event.end;
+
+        if event.end {
+            let current = state.current.take().unwrap();
+            state.last_ext_timestamp = Some(current.ext_timestamp);
+            self.finish_event(&state, current, packet.ext_seqnum())?;
+        }
+        drop(state);
+
+        if started && event.event <= MAX_DTMF_EVENT {
+            let dtmf_event = DtmfEvent {
+                number: event.event,
+                volume: event.volume,
+                start: true,
+            };
+            let _ = self.obj().post_message(
+                gst::message::Element::builder(dtmf_event.to_structure())
+                    .src(&*self.obj())
+                    .build(),
+            );
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
+
+impl RtpDtmfDepay {
+    fn finish_event(
+        &self,
+        state: &State,
+        current: CurrentEvent,
+        last_ext_seqnum: u64,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let clock_rate = state.clock_rate.unwrap() as u64;
+        let duration = (current.event.duration as u64)
+            .mul_div_floor(*gst::ClockTime::SECOND, clock_rate)
+            .map(gst::ClockTime::from_nseconds);
+
+        gst::trace!(
+            CAT,
+            imp = self,
+            "Finished event {} with volume {} and duration {}",
+            current.event.event,
+            current.event.volume,
+            duration.display(),
+        );
+
+        let mut buffer = gst::Buffer::from_mut_slice([current.event.event, current.event.volume]);
+        buffer.get_mut().unwrap().set_duration(duration);
+
+        self.obj().queue_buffer(
+            PacketToBufferRelation::Seqnums(current.first_ext_seqnum..=last_ext_seqnum),
+            buffer,
+        )
+    }
+}
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! RFC 4733 telephone-event payload and the `dtmf-event` structures used by the GStreamer DTMF
+//! elements.
+
+/// Size of a single telephone-event payload
+pub const PAYLOAD_SIZE: usize = 4;
+
+/// Highest event code of the DTMF digits 0-9, `*`, `#` and A-D
+pub const MAX_DTMF_EVENT: u8 = 15;
+
+/// Highest volume (power level in -dBm0) of an event
+pub const MAX_VOLUME: u8 = 63;
+
+/// Number of times the final packet of an event is sent
+///
+/// See https://www.rfc-editor.org/rfc/rfc4733.html#section-2.5.1.4
+pub const END_PACKET_REPETITIONS: usize = 3;
+
+/// A single telephone-event payload as per
+/// https://www.rfc-editor.org/rfc/rfc4733.html#section-2.3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TelephoneEvent {
+    pub event: u8,
+    pub end: bool,
+    pub volume: u8,
+    /// Duration of the event so far in RTP clock units
+    pub duration: u16,
+}
+
+impl TelephoneEvent {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < PAYLOAD_SIZE {
+            return None;
+        }
+
+        Some(TelephoneEvent {
+            event: data[0],
+            end: data[1] & 0x80 != 0,
+            volume: data[1] & 0x3f,
+            duration: u16::from_be_bytes([data[2], data[3]]),
+        })
+    }
+
+    pub fn write(&self) -> [u8; PAYLOAD_SIZE] {
+        let duration = self.duration.to_be_bytes();
+        [
+            self.event,
+            if self.end { 0x80 } else { 0x00 } | (self.volume & 0x3f),
+            duration[0],
+            duration[1],
+        ]
+    }
+}
+
+/// A DTMF event as carried by the `dtmf-event` events and messages, e.g. of `rtpdtmfsrc` and
+/// `rtpdtmfdepay`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DtmfEvent {
+    pub number: u8,
+    pub volume: u8,
+    /// `true` if the event starts, `false` if the currently running event stops
+    pub start: bool,
+}
+
+impl DtmfEvent {
+    /// Parse a `dtmf-event` structure of type 1 (RTP)
+    pub fn from_structure(s: &gst::StructureRef) -> Option<Self> {
+        if s.name() != "dtmf-event" || s.get::<i32>("type").ok()? != 1 {
+            return None;
+        }
+
+        let start = s.get::<bool>("start").ok()?;
+        if !start {
+            return Some(DtmfEvent {
+                number: 0,
+                volume: 0,
+                start,
+            });
+        }
+
+        let number = s.get::<i32>("number").ok()?;
+        let volume = s.get::<i32>("volume").ok()?;
+        if !(0..=MAX_DTMF_EVENT as i32).contains(&number)
+            || !(0..=MAX_VOLUME as i32).contains(&volume)
+        {
+            return None;
+        }
+
+        Some(DtmfEvent {
+            number: number as u8,
+            volume: volume as u8,
+            start,
+        })
+    }
+
+    pub fn to_structure(self) -> gst::Structure {
+        gst::Structure::builder("dtmf-event")
+            .field("type", 1i32)
+            .field("method", 1i32)
+            .field("number", self.number as i32)
+            .field("volume", self.volume as i32)
+            .field("start", self.start)
+            .build()
+    }
+}
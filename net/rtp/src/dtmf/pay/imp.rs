@@ -0,0 +1,273 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpdtmfpay2
+ * @see_also: rtpdtmfdepay2, rtpdtmfsrc, rtpsend
+ *
+ * Payload telephone events, e.g. DTMF digits, into RTP packets as per [RFC 4733][rfc-4733].
+ *
+ * Each input buffer describes one complete event: the first byte is the event code, the second
+ * byte the volume in -dBm0 and the buffer PTS and duration give the start and duration of the
+ * event. The event is sent as a sequence of packets carrying the duration so far, one per
+ * packet interval, followed by three packets marking the end of the event.
+ *
+ * For sending events as part of an audio stream with the same SSRC, see the `dtmf-event` event
+ * handling of `rtpsend`.
+ *
+ * [rfc-4733]: https://www.rfc-editor.org/rfc/rfc4733.html
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use atomic_refcell::AtomicRefCell;
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+use std::sync::LazyLock;
+
+use crate::basepay::{
+    PacketToBufferRelation, RtpBasePay2Ext, RtpBasePay2Impl, RtpBasePay2ImplExt, TimestampOffset,
+};
+use crate::dtmf::event::{TelephoneEvent, END_PACKET_REPETITIONS, MAX_VOLUME};
+
+const DEFAULT_CLOCK_RATE: i32 = 8000;
+const DEFAULT_PACKET_INTERVAL: gst::ClockTime = gst::ClockTime::from_mseconds(50);
+
+#[derive(Default)]
+pub struct RtpDtmfPay {
+    state: AtomicRefCell<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    clock_rate: u32,
+    packet_interval: gst::ClockTime,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            clock_rate: DEFAULT_CLOCK_RATE as u32,
+            packet_interval: DEFAULT_PACKET_INTERVAL,
+        }
+    }
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpdtmfpay2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP Telephone Event Payloader"),
+    )
+});
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpDtmfPay {
+    const NAME: &'static str = "GstRtpDtmfPay2";
+    type Type = super::RtpDtmfPay;
+    type ParentType = crate::basepay::RtpBasePay2;
+}
+
+impl ObjectImpl for RtpDtmfPay {}
+
+impl GstObjectImpl for RtpDtmfPay {}
+
+impl ElementImpl for RtpDtmfPay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP Telephone Event Payloader",
+                "Codec/Payloader/Network/RTP",
+                "Payload telephone events into RTP packets (RFC 4733)",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_empty_simple("application/x-telephone-event"),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("application/x-rtp")
+                    .field("media", "audio")
+                    .field("encoding-name", "TELEPHONE-EVENT")
+                    .field("clock-rate", gst::IntRange::new(1, i32::MAX))
+                    .build(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl RtpBasePay2Impl for RtpDtmfPay {
+    const ALLOWED_META_TAGS: &'static [&'static str] = &[];
+    const DEFAULT_PT: u8 = 101;
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        self.parent_start()
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        self.parent_stop()
+    }
+
+    fn set_sink_caps(&self, _caps: &gst::Caps) -> bool {
+        let src_caps = gst::Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("encoding-name", "TELEPHONE-EVENT")
+            .field("clock-rate", gst::IntRange::new(1, i32::MAX))
+            .build();
+
+        self.obj().set_src_caps(&src_caps);
+
+        true
+    }
+
+    fn negotiate(&self, mut src_caps: gst::Caps) {
+        // The clock rate must match the one of the audio stream the events belong to, so
+        // prefer whatever downstream wants and otherwise fall back to 8kHz
+        src_caps.truncate();
+        {
+            let src_caps = src_caps.get_mut().unwrap();
+            let s = src_caps.structure_mut(0).unwrap();
+            s.fixate_field_nearest_int("clock-rate", DEFAULT_CLOCK_RATE);
+        }
+        src_caps.fixate();
+
+        let s = src_caps.structure(0).unwrap();
+        let clock_rate = s.get::<i32>("clock-rate").unwrap() as u32;
+        let ptime = s
+            .get::<u32>("ptime")
+            .ok()
+            .filter(|ptime| *ptime > 0)
+            .map(u64::from)
+            .map(gst::ClockTime::from_mseconds);
+
+        self.parent_negotiate(src_caps);
+
+        let mut state = self.state.borrow_mut();
+        state.clock_rate = clock_rate;
+        state.packet_interval = ptime.unwrap_or(DEFAULT_PACKET_INTERVAL);
+    }
+
+    fn handle_buffer(
+        &self,
+        buffer: &gst::Buffer,
+        id: u64,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let (clock_rate, packet_interval) = {
+            let state = self.state.borrow();
+            (state.clock_rate, state.packet_interval)
+        };
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::error!(CAT, imp = self, "Can't map buffer readable");
+            gst::FlowError::Error
+        })?;
+
+        let [event, volume, ..] = map.as_slice() else {
+            gst::warning!(CAT, imp = self, "Too short event buffer, skipping");
+            self.obj().drop_buffers(id..=id);
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let Some(duration) = buffer.duration() else {
+            gst::warning!(CAT, imp = self, "Event without duration, skipping");
+            self.obj().drop_buffers(id..=id);
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let to_time = |clock_units: u16| {
+            gst::ClockTime::from_nseconds(
+                (clock_units as u64)
+                    .mul_div_floor(gst::ClockTime::SECOND.nseconds(), clock_rate as u64)
+                    .unwrap_or(u64::MAX),
+            )
+        };
+        // Each packet is sent once the duration it carries has elapsed (RFC 4733 2.5.1.2)
+        let relation = |duration: u16| PacketToBufferRelation::IdsWithOffset {
+            ids: id..=id,
+            timestamp_offset: TimestampOffset::PtsOnly(to_time(duration)),
+        };
+
+        let to_clock_units = |time: gst::ClockTime| {
+            time.nseconds()
+                .mul_div_ceil(clock_rate as u64, gst::ClockTime::SECOND.nseconds())
+                .unwrap_or(u64::MAX)
+        };
+
+        let mut total_duration = to_clock_units(duration);
+        if total_duration > u16::MAX as u64 {
+            gst::warning!(
+                CAT,
+                imp = self,
+                "Event duration {duration} too long, truncating"
+            );
+            total_duration = u16::MAX as u64;
+        }
+        let total_duration = total_duration as u16;
+        let interval = to_clock_units(packet_interval).clamp(1, u16::MAX as u64) as u16;
+
+        let mut event = TelephoneEvent {
+            event: *event,
+            end: false,
+            volume: (*volume).min(MAX_VOLUME),
+            duration: 0,
+        };
+
+        gst::trace!(
+            CAT,
+            imp = self,
+            "Payloading event {} with volume {} for {total_duration} clock units",
+            event.event,
+            event.volume,
+        );
+
+        // All packets of an event share the RTP timestamp of the start of the event, only the
+        // first one has the marker bit set
+        let mut first = true;
+        while event.duration.saturating_add(interval) < total_duration {
+            event.duration += interval;
+            self.obj().queue_packet(
+                relation(event.duration),
+                rtp_types::RtpPacketBuilder::new()
+                    .payload(event.write().as_slice())
+                    .marker_bit(first),
+            )?;
+            first = false;
+        }
+
+        event.duration = total_duration;
+        event.end = true;
+        for _ in 0..END_PACKET_REPETITIONS {
+            self.obj().queue_packet(
+                relation(event.duration),
+                rtp_types::RtpPacketBuilder::new()
+                    .payload(event.write().as_slice())
+                    .marker_bit(first),
+            )?;
+            first = false;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
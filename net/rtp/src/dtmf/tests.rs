@@ -0,0 +1,147 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::dtmf::event::{DtmfEvent, TelephoneEvent};
+use crate::tests::{run_test_pipeline_full, ExpectedBuffer, ExpectedPacket, Liveness, Source};
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        crate::plugin_register_static().expect("rtp dtmf test");
+    });
+}
+
+fn make_event(
+    event: u8,
+    volume: u8,
+    pts: gst::ClockTime,
+    duration: gst::ClockTime,
+    flags: gst::BufferFlags,
+) -> gst::Buffer {
+    let mut buf = gst::Buffer::from_mut_slice([event, volume]);
+
+    let buf_ref = buf.get_mut().unwrap();
+    buf_ref.set_pts(pts);
+    buf_ref.set_duration(duration);
+    buf_ref.set_flags(flags);
+
+    buf
+}
+
+#[test]
+fn test_telephone_event() {
+    let event = TelephoneEvent {
+        event: 11,
+        end: true,
+        volume: 10,
+        duration: 1600,
+    };
+    let data = event.write();
+    assert_eq!(data, [0x0b, 0x8a, 0x06, 0x40]);
+    assert_eq!(TelephoneEvent::parse(&data), Some(event));
+    assert_eq!(TelephoneEvent::parse(&data[..3]), None);
+
+    let s = gst::Structure::builder("dtmf-event")
+        .field("type", 1i32)
+        .field("number", 5i32)
+        .field("volume", 25i32)
+        .field("start", true)
+        .build();
+    let dtmf_event = DtmfEvent::from_structure(&s).unwrap();
+    assert_eq!(
+        dtmf_event,
+        DtmfEvent {
+            number: 5,
+            volume: 25,
+            start: true,
+        }
+    );
+    assert_eq!(
+        DtmfEvent::from_structure(&dtmf_event.to_structure()),
+        Some(dtmf_event)
+    );
+
+    // Only RTP events and valid DTMF digits
+    let mut s = s;
+    s.set("number", 16i32);
+    assert_eq!(DtmfEvent::from_structure(&s), None);
+    s.set("type", 0i32);
+    assert_eq!(DtmfEvent::from_structure(&s), None);
+}
+
+#[test]
+fn test_dtmf_pay_depay() {
+    init();
+
+    let input_caps = gst::Caps::new_empty_simple("application/x-telephone-event");
+
+    let input_buffers = vec![
+        make_event(
+            1,
+            10,
+            gst::ClockTime::ZERO,
+            gst::ClockTime::from_mseconds(200),
+            gst::BufferFlags::DISCONT,
+        ),
+        make_event(
+            11,
+            20,
+            gst::ClockTime::SECOND,
+            gst::ClockTime::from_mseconds(100),
+            gst::BufferFlags::empty(),
+        ),
+    ];
+
+    // 200ms at 8kHz are sent as three intermediate packets every 50ms plus three end packets,
+    // 100ms as one intermediate packet plus three end packets. Each packet is timestamped with
+    // the time it covers while the RTP timestamp stays the one of the start of the event.
+    let mut expected_pay = vec![];
+    for (i, n_packets) in [(0, 6), (1, 4)] {
+        let mut packets = vec![];
+        for j in 0..n_packets {
+            let offset = gst::ClockTime::from_mseconds(50 * (j.min(n_packets - 3) + 1));
+            packets.push(
+                ExpectedPacket::builder()
+                    .pts(gst::ClockTime::from_seconds(i) + offset)
+                    .flags(match (i, j) {
+                        (0, 0) => gst::BufferFlags::DISCONT | gst::BufferFlags::MARKER,
+                        (_, 0) => gst::BufferFlags::MARKER,
+                        _ => gst::BufferFlags::empty(),
+                    })
+                    .pt(101)
+                    .rtp_time(i as u32 * 8000)
+                    .marker_bit(j == 0)
+                    .size(16)
+                    .build(),
+            );
+        }
+        expected_pay.push(packets);
+    }
+
+    let expected_depay = vec![
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::ZERO)
+            .duration(gst::ClockTime::from_mseconds(200))
+            .size(2)
+            .flags(gst::BufferFlags::DISCONT)
+            .build()],
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::SECOND)
+            .duration(gst::ClockTime::from_mseconds(100))
+            .size(2)
+            .flags(gst::BufferFlags::empty())
+            .build()],
+    ];
+
+    run_test_pipeline_full(
+        Source::Buffers(input_caps.clone(), input_buffers),
+        "rtpdtmfpay2",
+        "rtpdtmfdepay2",
+        expected_pay,
+        expected_depay,
+        Some(input_caps),
+        Liveness::NonLive,
+    );
+}
@@ -0,0 +1,542 @@
+// GStreamer RTP H.265 Depayloader
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtph265depay2
+ * @see_also: rtph265pay2, h265parse, x265enc
+ *
+ * Depayload an H.265 video stream from RTP packets as per [RFC 7798][rfc-7798].
+ *
+ * Single NAL unit packets, aggregation packets and fragmentation units are supported and the
+ * output is an Annex B byte-stream with one access unit per buffer. Parameter sets from the
+ * `sprop-vps`, `sprop-sps` and `sprop-pps` caps fields are inserted before IRAP pictures that
+ * don't carry their own.
+ *
+ * If the caps contain a `sprop-max-don-diff` or `sprop-depack-buf-nalus` greater than zero then
+ * the packets contain decoding order numbers, which are used to restore the decoding order of the
+ * NAL units inside each access unit.
+ *
+ * [rfc-7798]: https://www.rfc-editor.org/rfc/rfc7798.html
+ *
+ * ## Example pipeline
+ *
+ * ```shell
+ * gst-launch-1.0 udpsrc address=127.0.0.1 port=5004 caps='application/x-rtp,media=video,clock-rate=90000,encoding-name=H265' ! rtpjitterbuffer latency=100 ! rtph265depay2 ! decodebin3 ! videoconvertscale ! autovideosink
+ * ```
+ *
+ * This will depayload and decode an incoming RTP H.265 video stream. You can use the
+ * #rtph265pay2 and #x265enc elements to create such an RTP stream.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::sync::{LazyLock, Mutex};
+
+use atomic_refcell::AtomicRefCell;
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+use crate::basedepay::{PacketToBufferRelation, RtpBaseDepay2Ext};
+use crate::h265::nal::{self, ParameterSets, NAL_HEADER_SIZE};
+
+const START_CODE: [u8; 4] = [0, 0, 0, 1];
+
+#[derive(Clone, Default)]
+struct Settings {
+    request_keyframe: bool,
+    wait_for_keyframe: bool,
+}
+
+/// NAL unit of the current access unit together with its decoding order number, if any.
+struct PendingNal {
+    don: Option<u16>,
+    data: Vec<u8>,
+}
+
+struct State {
+    /// Parameter sets from the caps.
+    parameter_sets: ParameterSets,
+    /// Whether the packets contain decoding order numbers.
+    use_don: bool,
+
+    /// Extended RTP timestamp of the current access unit.
+    pending_au_ext_timestamp: Option<u64>,
+    /// First and last extended seqnum of the current access unit.
+    pending_au_ext_seqnums: Option<(u64, u64)>,
+    pending_au: Vec<PendingNal>,
+
+    /// NAL unit that is currently reassembled from fragmentation units.
+    fragment: Option<PendingNal>,
+
+    /// Set once the first IRAP picture was output.
+    seen_keyframe: bool,
+
+    /// Set to `true` if the next outgoing buffer should have the `DISCONT` flag set.
+    needs_discont: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            parameter_sets: ParameterSets::default(),
+            use_don: false,
+            pending_au_ext_timestamp: None,
+            pending_au_ext_seqnums: None,
+            pending_au: Vec::new(),
+            fragment: None,
+            seen_keyframe: false,
+            needs_discont: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RtpH265Depay {
+    state: AtomicRefCell<State>,
+    settings: Mutex<Settings>,
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtph265depay2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP H.265 Depayloader"),
+    )
+});
+
+impl RtpH265Depay {
+    /// Resets the streaming state but keeps the configuration from the caps.
+    fn reset(&self, state: &mut State) {
+        gst::debug!(CAT, imp = self, "resetting state");
+
+        *state = State {
+            parameter_sets: std::mem::take(&mut state.parameter_sets),
+            use_don: state.use_don,
+            ..State::default()
+        };
+    }
+
+    fn drop_pending_au(&self, state: &mut State) {
+        if let Some((_, last)) = state.pending_au_ext_seqnums.take() {
+            self.obj().drop_packets(..=last);
+        }
+        state.pending_au_ext_timestamp = None;
+        state.pending_au.clear();
+        state.fragment = None;
+    }
+
+    fn finish_pending_au(&self, state: &mut State) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        if state.fragment.take().is_some() {
+            gst::warning!(CAT, imp = self, "Dropping incomplete fragmented NAL unit");
+        }
+
+        let Some((first_seqnum, last_seqnum)) = state.pending_au_ext_seqnums.take() else {
+            return Ok(gst::FlowSuccess::Ok);
+        };
+        state.pending_au_ext_timestamp = None;
+        let mut nals = std::mem::take(&mut state.pending_au);
+
+        if nals.is_empty() {
+            self.obj().drop_packets(..=last_seqnum);
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        // Restore the decoding order, relative to the first NAL unit to handle wraparounds
+        if state.use_don {
+            let first_don = nals[0].don.unwrap_or(0);
+            nals.sort_by_key(|nal| nal.don.unwrap_or(first_don).wrapping_sub(first_don));
+        }
+
+        let is_keyframe = nals
+            .iter()
+            .any(|nal| nal::is_irap(nal::nal_type(&nal.data)));
+
+        // If necessary wait for a key frame if we never saw one so far and/or request one
+        // from upstream.
+        if !is_keyframe && !state.seen_keyframe {
+            if settings.request_keyframe {
+                gst::debug!(CAT, imp = self, "Requesting keyframe from upstream");
+                let event = gst_video::UpstreamForceKeyUnitEvent::builder()
+                    .all_headers(true)
+                    .build();
+                let _ = self.obj().sink_pad().push_event(event);
+            }
+
+            if settings.wait_for_keyframe {
+                gst::trace!(CAT, imp = self, "Waiting for keyframe");
+                self.obj().drop_packets(..=last_seqnum);
+                return Ok(gst::FlowSuccess::Ok);
+            }
+        }
+
+        let mut data = Vec::with_capacity(
+            nals.iter()
+                .map(|nal| START_CODE.len() + nal.data.len())
+                .sum(),
+        );
+
+        // Insert the parameter sets from the caps if the access unit doesn't carry any itself
+        if is_keyframe
+            && !nals
+                .iter()
+                .any(|nal| nal::is_parameter_set(nal::nal_type(&nal.data)))
+        {
+            for nal in state.parameter_sets.iter() {
+                data.extend_from_slice(&START_CODE);
+                data.extend_from_slice(nal);
+            }
+        }
+
+        for nal in &nals {
+            data.extend_from_slice(&START_CODE);
+            data.extend_from_slice(&nal.data);
+        }
+
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().unwrap();
+
+            if is_keyframe {
+                gst::trace!(CAT, imp = self, "Finishing keyframe");
+                state.seen_keyframe = true;
+            } else {
+                gst::trace!(CAT, imp = self, "Finishing delta-frame");
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+
+            if state.needs_discont {
+                gst::trace!(CAT, imp = self, "Setting DISCONT");
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+                state.needs_discont = false;
+            }
+
+            // Each buffer contains a complete access unit
+            buffer.set_flags(gst::BufferFlags::MARKER);
+        }
+
+        self.obj().queue_buffer(
+            PacketToBufferRelation::Seqnums(first_seqnum..=last_seqnum),
+            buffer,
+        )
+    }
+
+    /// Reads the decoding order number at the start of `data` if they're in use.
+    fn read_don<'a>(&self, state: &State, data: &'a [u8]) -> Option<(Option<u16>, &'a [u8])> {
+        if !state.use_don {
+            return Some((None, data));
+        }
+
+        if data.len() < 2 {
+            return None;
+        }
+
+        Some((Some(u16::from_be_bytes([data[0], data[1]])), &data[2..]))
+    }
+
+    /// Parses the payload into complete NAL units and adds them to the current access unit.
+    ///
+    /// Returns `None` if the payload is invalid.
+    fn parse_payload(&self, state: &mut State, payload: &[u8], discont: bool) -> Option<()> {
+        let header = &payload[..NAL_HEADER_SIZE];
+        let data = &payload[NAL_HEADER_SIZE..];
+
+        match nal::nal_type(header) {
+            nal::NAL_TYPE_AP => {
+                let (mut don, mut data) = self.read_don(state, data)?;
+                let mut first = true;
+
+                while !data.is_empty() {
+                    // All but the first NAL unit have a DON difference
+                    if state.use_don && !first {
+                        let dond = *data.first()?;
+                        don = don.map(|don| don.wrapping_add(dond as u16 + 1));
+                        data = &data[1..];
+                    }
+                    first = false;
+
+                    if data.len() < 2 {
+                        return None;
+                    }
+                    let len = u16::from_be_bytes([data[0], data[1]]) as usize;
+                    data = &data[2..];
+                    if len < NAL_HEADER_SIZE || data.len() < len {
+                        return None;
+                    }
+
+                    state.pending_au.push(PendingNal {
+                        don,
+                        data: data[..len].to_vec(),
+                    });
+                    data = &data[len..];
+                }
+            }
+            nal::NAL_TYPE_FU => {
+                let (&fu_header, data) = data.split_first()?;
+                let start = fu_header & 0x80 != 0;
+                let end = fu_header & 0x40 != 0;
+                let fu_type = fu_header & 0x3f;
+
+                if start {
+                    if state.fragment.is_some() {
+                        gst::warning!(CAT, imp = self, "Dropping incomplete fragmented NAL unit");
+                    }
+
+                    let (don, data) = self.read_don(state, data)?;
+                    let mut nal = Vec::with_capacity(NAL_HEADER_SIZE + data.len());
+                    nal.extend_from_slice(&nal::header(
+                        header[0] & 0x80 != 0,
+                        fu_type,
+                        nal::layer_id(header),
+                        nal::temporal_id_plus1(header),
+                    ));
+                    nal.extend_from_slice(data);
+                    state.fragment = Some(PendingNal { don, data: nal });
+                } else {
+                    // Fragments after a packet loss can't be used
+                    if discont {
+                        state.fragment = None;
+                    }
+
+                    let Some(ref mut fragment) = state.fragment else {
+                        gst::trace!(CAT, imp = self, "Waiting for start of fragmented NAL unit");
+                        return Some(());
+                    };
+                    fragment.data.extend_from_slice(data);
+                }
+
+                if end {
+                    if let Some(fragment) = state.fragment.take() {
+                        state.pending_au.push(fragment);
+                    }
+                }
+            }
+            nal::NAL_TYPE_PACI => {
+                gst::debug!(CAT, imp = self, "Ignoring unsupported PACI packet");
+            }
+            nal_type if nal_type > nal::NAL_TYPE_PACI => {
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Ignoring packet with NAL unit type {nal_type}"
+                );
+            }
+            _ => {
+                let (don, data) = self.read_don(state, data)?;
+                let mut nal = Vec::with_capacity(NAL_HEADER_SIZE + data.len());
+                nal.extend_from_slice(header);
+                nal.extend_from_slice(data);
+                state.pending_au.push(PendingNal { don, data: nal });
+            }
+        }
+
+        Some(())
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpH265Depay {
+    const NAME: &'static str = "GstRtpH265Depay2";
+    type Type = super::RtpH265Depay;
+    type ParentType = crate::basedepay::RtpBaseDepay2;
+}
+
+impl ObjectImpl for RtpH265Depay {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecBoolean::builder("request-keyframe")
+                    .nick("Request Keyframe")
+                    .blurb("Request new keyframe when packet loss is detected")
+                    .default_value(Settings::default().request_keyframe)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("wait-for-keyframe")
+                    .nick("Wait For Keyframe")
+                    .blurb("Wait for the next keyframe after packet loss")
+                    .default_value(Settings::default().wait_for_keyframe)
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "request-keyframe" => {
+                self.settings.lock().unwrap().request_keyframe = value.get().unwrap();
+            }
+            "wait-for-keyframe" => {
+                self.settings.lock().unwrap().wait_for_keyframe = value.get().unwrap();
+            }
+            _ => unimplemented!(),
+        };
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "request-keyframe" => self.settings.lock().unwrap().request_keyframe.to_value(),
+            "wait-for-keyframe" => self.settings.lock().unwrap().wait_for_keyframe.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for RtpH265Depay {}
+
+impl ElementImpl for RtpH265Depay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP H.265 Depayloader",
+                "Codec/Depayloader/Network/RTP",
+                "Depayload H.265 from RTP packets (RFC 7798)",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("application/x-rtp")
+                    .field("media", "video")
+                    .field("clock-rate", 90_000i32)
+                    .field("encoding-name", "H265")
+                    .build(),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("video/x-h265")
+                    .field("stream-format", "byte-stream")
+                    .field("alignment", "au")
+                    .build(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl crate::basedepay::RtpBaseDepay2Impl for RtpH265Depay {
+    const ALLOWED_META_TAGS: &'static [&'static str] = &["video"];
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn flush(&self) {
+        let mut state = self.state.borrow_mut();
+        self.reset(&mut state);
+    }
+
+    fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.borrow_mut();
+        self.finish_pending_au(&mut state)
+    }
+
+    fn set_sink_caps(&self, caps: &gst::Caps) -> bool {
+        let s = caps.structure(0).unwrap();
+        let mut state = self.state.borrow_mut();
+
+        match ParameterSets::from_caps(s) {
+            Ok(parameter_sets) => {
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Parameter sets from caps {parameter_sets:?}"
+                );
+                state.parameter_sets = parameter_sets;
+            }
+            Err(err) => {
+                gst::error!(CAT, imp = self, "Failed to parse parameter sets: {err:#}");
+                return false;
+            }
+        }
+
+        // Usually strings from the SDP but also accept integers
+        let get_u32 = |field: &str| {
+            s.get::<&str>(field)
+                .ok()
+                .and_then(|v| v.parse::<u32>().ok())
+                .or_else(|| s.get::<i32>(field).ok().map(|v| v as u32))
+                .unwrap_or(0)
+        };
+        // Decoding order numbers are present if either of them is greater than zero
+        state.use_don = get_u32("sprop-max-don-diff") > 0 || get_u32("sprop-depack-buf-nalus") > 0;
+
+        self.obj()
+            .set_src_caps(&self.obj().src_pad().pad_template_caps());
+
+        true
+    }
+
+    fn handle_packet(
+        &self,
+        packet: &crate::basedepay::Packet,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, imp = self, "Handling RTP packet {packet:?}");
+        let mut state = self.state.borrow_mut();
+
+        // A new timestamp starts a new access unit even if the marker bit was lost
+        if state
+            .pending_au_ext_timestamp
+            .is_some_and(|ts| ts != packet.ext_timestamp())
+        {
+            gst::debug!(CAT, imp = self, "Timestamp changed without marker bit");
+            self.finish_pending_au(&mut state)?;
+        }
+
+        let payload = packet.payload();
+        if payload.len() <= NAL_HEADER_SIZE
+            || self
+                .parse_payload(&mut state, payload, packet.discont())
+                .is_none()
+        {
+            gst::warning!(CAT, imp = self, "Invalid H.265 RTP packet");
+            self.drop_pending_au(&mut state);
+            self.obj().drop_packet(packet);
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        state.pending_au_ext_timestamp = Some(packet.ext_timestamp());
+        state.pending_au_ext_seqnums = Some(match state.pending_au_ext_seqnums {
+            Some((first, _)) => (first, packet.ext_seqnum()),
+            None => (packet.ext_seqnum(), packet.ext_seqnum()),
+        });
+
+        // The marker bit is set for the last packet of an access unit.
+        if packet.marker_bit() {
+            self.finish_pending_au(&mut state)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
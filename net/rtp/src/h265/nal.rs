@@ -0,0 +1,252 @@
+// GStreamer RTP H.265 NAL unit helpers
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use anyhow::Context;
+use gst::glib;
+
+pub const NAL_TYPE_VPS: u8 = 32;
+pub const NAL_TYPE_SPS: u8 = 33;
+pub const NAL_TYPE_PPS: u8 = 34;
+pub const NAL_TYPE_AUD: u8 = 35;
+
+/// Aggregation packet as per RFC 7798 section 4.4.2
+pub const NAL_TYPE_AP: u8 = 48;
+/// Fragmentation unit as per RFC 7798 section 4.4.3
+pub const NAL_TYPE_FU: u8 = 49;
+/// PACI packet as per RFC 7798 section 4.4.4
+pub const NAL_TYPE_PACI: u8 = 50;
+
+/// Size of the NAL unit header and the RTP payload header.
+pub const NAL_HEADER_SIZE: usize = 2;
+
+pub fn nal_type(nal: &[u8]) -> u8 {
+    (nal[0] >> 1) & 0x3f
+}
+
+pub fn layer_id(nal: &[u8]) -> u8 {
+    ((nal[0] & 0x01) << 5) | (nal[1] >> 3)
+}
+
+pub fn temporal_id_plus1(nal: &[u8]) -> u8 {
+    nal[1] & 0x07
+}
+
+/// Returns `true` for BLA, IDR and CRA pictures and the reserved IRAP types.
+pub fn is_irap(nal_type: u8) -> bool {
+    (16..=23).contains(&nal_type)
+}
+
+pub fn is_parameter_set(nal_type: u8) -> bool {
+    (NAL_TYPE_VPS..=NAL_TYPE_PPS).contains(&nal_type)
+}
+
+/// Builds a two byte NAL unit header.
+pub fn header(forbidden_zero: bool, nal_type: u8, layer_id: u8, temporal_id_plus1: u8) -> [u8; 2] {
+    [
+        ((forbidden_zero as u8) << 7) | (nal_type << 1) | (layer_id >> 5),
+        ((layer_id & 0x1f) << 3) | (temporal_id_plus1 & 0x07),
+    ]
+}
+
+/// Splits an Annex B byte-stream into its NAL units, without start codes.
+pub fn split_byte_stream(data: &[u8]) -> Vec<&[u8]> {
+    // NAL units always end with a non-zero byte so trailing zeroes belong to the next start code
+    fn trim(nal: &[u8]) -> &[u8] {
+        let end = nal.iter().rposition(|&b| b != 0).map_or(0, |pos| pos + 1);
+        &nal[..end]
+    }
+
+    let mut nals = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+    while i + 3 <= data.len() {
+        if data[i..i + 3] == [0, 0, 1] {
+            if let Some(start) = start {
+                nals.push(trim(&data[start..i]));
+            }
+            i += 3;
+            start = Some(i);
+        } else {
+            i += 1;
+        }
+    }
+    if let Some(start) = start {
+        nals.push(trim(&data[start..]));
+    }
+
+    nals.retain(|nal| nal.len() >= NAL_HEADER_SIZE);
+    nals
+}
+
+/// Splits NAL units prefixed with their size in `length_size` bytes.
+pub fn split_length_prefixed(mut data: &[u8], length_size: usize) -> anyhow::Result<Vec<&[u8]>> {
+    let mut nals = Vec::new();
+    while !data.is_empty() {
+        if data.len() < length_size {
+            anyhow::bail!("Truncated NAL unit length");
+        }
+        let len = data[..length_size]
+            .iter()
+            .fold(0usize, |len, &b| (len << 8) | b as usize);
+        data = &data[length_size..];
+        if data.len() < len {
+            anyhow::bail!("Truncated NAL unit of size {len}");
+        }
+        if len >= NAL_HEADER_SIZE {
+            nals.push(&data[..len]);
+        }
+        data = &data[len..];
+    }
+
+    Ok(nals)
+}
+
+/// Parameter sets of a stream, in the order VPS, SPS, PPS.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ParameterSets {
+    pub vps: Vec<Vec<u8>>,
+    pub sps: Vec<Vec<u8>>,
+    pub pps: Vec<Vec<u8>>,
+}
+
+impl ParameterSets {
+    pub fn is_empty(&self) -> bool {
+        self.vps.is_empty() && self.sps.is_empty() && self.pps.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &[u8]> {
+        self.vps
+            .iter()
+            .chain(self.sps.iter())
+            .chain(self.pps.iter())
+            .map(Vec::as_slice)
+    }
+
+    fn list_mut(&mut self, nal_type: u8) -> Option<&mut Vec<Vec<u8>>> {
+        match nal_type {
+            NAL_TYPE_VPS => Some(&mut self.vps),
+            NAL_TYPE_SPS => Some(&mut self.sps),
+            NAL_TYPE_PPS => Some(&mut self.pps),
+            _ => None,
+        }
+    }
+
+    /// Collects the parameter sets from a list of NAL units.
+    pub fn from_nals<'a>(nals: impl IntoIterator<Item = &'a [u8]>) -> Self {
+        let mut sets = ParameterSets::default();
+        for nal in nals {
+            if let Some(list) = sets.list_mut(nal_type(nal)) {
+                list.push(nal.to_vec());
+            }
+        }
+
+        sets
+    }
+
+    /// Replaces the parameter sets of each type that is present in `other`.
+    ///
+    /// Returns `true` if anything changed.
+    pub fn update(&mut self, other: ParameterSets) -> bool {
+        let mut changed = false;
+        for (list, new) in [
+            (&mut self.vps, other.vps),
+            (&mut self.sps, other.sps),
+            (&mut self.pps, other.pps),
+        ] {
+            if !new.is_empty() && *list != new {
+                *list = new;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+
+    /// Parses the `sprop-vps`, `sprop-sps` and `sprop-pps` caps fields.
+    pub fn from_caps(s: &gst::StructureRef) -> anyhow::Result<Self> {
+        let mut sets = ParameterSets::default();
+        for (field, list) in [
+            ("sprop-vps", &mut sets.vps),
+            ("sprop-sps", &mut sets.sps),
+            ("sprop-pps", &mut sets.pps),
+        ] {
+            let Ok(value) = s.get::<&str>(field) else {
+                continue;
+            };
+
+            for part in value.split(',').filter(|part| !part.is_empty()) {
+                let nal = glib::base64_decode(part);
+                if nal.len() < NAL_HEADER_SIZE {
+                    anyhow::bail!("Invalid NAL unit in {field}");
+                }
+                list.push(nal);
+            }
+        }
+
+        Ok(sets)
+    }
+
+    /// Adds the `sprop-vps`, `sprop-sps` and `sprop-pps` caps fields.
+    pub fn to_caps(&self, s: &mut gst::StructureRef) {
+        for (field, list) in [
+            ("sprop-vps", &self.vps),
+            ("sprop-sps", &self.sps),
+            ("sprop-pps", &self.pps),
+        ] {
+            if list.is_empty() {
+                s.remove_field(field);
+                continue;
+            }
+
+            let value = list
+                .iter()
+                .map(|nal| glib::base64_encode(nal).to_string())
+                .collect::<Vec<_>>()
+                .join(",");
+            s.set(field, value);
+        }
+    }
+}
+
+/// Parses a `HEVCDecoderConfigurationRecord` as stored in `hvcC` boxes.
+///
+/// Returns the size of the NAL unit length prefix and the contained parameter sets.
+pub fn parse_hvcc(data: &[u8]) -> anyhow::Result<(usize, ParameterSets)> {
+    use bitstream_io::{BigEndian, ByteRead as _, ByteReader};
+
+    let mut r = ByteReader::endian(data, BigEndian);
+
+    let version = r.read::<u8>().context("version")?;
+    if version != 1 {
+        anyhow::bail!("Unsupported hvcC version {version}");
+    }
+    r.skip(20).context("header")?;
+    let length_size = (r.read::<u8>().context("length size")? & 0x03) as usize + 1;
+    if length_size == 3 {
+        anyhow::bail!("Invalid NAL unit length size {length_size}");
+    }
+
+    let mut nals = Vec::new();
+    let num_arrays = r.read::<u8>().context("number of arrays")?;
+    for _ in 0..num_arrays {
+        r.skip(1).context("array header")?;
+        let num_nals = r.read::<u16>().context("number of NAL units")?;
+        for _ in 0..num_nals {
+            let len = r.read::<u16>().context("NAL unit length")?;
+            let nal = r.read_to_vec(len as usize).context("NAL unit")?;
+            if nal.len() >= NAL_HEADER_SIZE {
+                nals.push(nal);
+            }
+        }
+    }
+
+    Ok((
+        length_size,
+        ParameterSets::from_nals(nals.iter().map(Vec::as_slice)),
+    ))
+}
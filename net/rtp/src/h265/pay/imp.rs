@@ -0,0 +1,498 @@
+// GStreamer RTP H.265 Payloader
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtph265pay2
+ * @see_also: rtph265depay2, x265enc, h265parse
+ *
+ * Payload an H.265 video stream into RTP packets as per [RFC 7798][rfc-7798].
+ *
+ * NAL units that don't fit into a single packet are split into fragmentation units, and with
+ * the default `aggregate-mode` small NAL units of the same access unit are combined into
+ * aggregation packets.
+ *
+ * Parameter sets from the `codec_data` or the stream are signalled via the `sprop-vps`,
+ * `sprop-sps` and `sprop-pps` caps fields. The `config-interval` property allows to also
+ * repeat them in-band before IRAP pictures.
+ *
+ * Packets are always sent in decoding order so no decoding order numbers are included.
+ *
+ * [rfc-7798]: https://www.rfc-editor.org/rfc/rfc7798.html
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 videotestsrc ! video/x-raw,width=1280,height=720,format=I420 ! timeoverlay font-desc=Sans,22 ! x265enc tune=zerolatency ! h265parse ! rtph265pay2 ! udpsink host=127.0.0.1 port=5004
+ * ]| This will create and payload an H.265 video stream with a test pattern and
+ * send it out via UDP to localhost port 5004.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use atomic_refcell::AtomicRefCell;
+use gst::{glib, prelude::*, subclass::prelude::*};
+use std::sync::{LazyLock, Mutex};
+
+use crate::{
+    basepay::RtpBasePay2Ext,
+    h265::nal::{self, ParameterSets, NAL_HEADER_SIZE},
+};
+
+use super::RtpH265PayAggregateMode;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtph265pay2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP H.265 Payloader"),
+    )
+});
+
+const DEFAULT_AGGREGATE_MODE: RtpH265PayAggregateMode = RtpH265PayAggregateMode::ZeroLatency;
+const DEFAULT_CONFIG_INTERVAL: i32 = 0;
+
+#[derive(Clone)]
+struct Settings {
+    aggregate_mode: RtpH265PayAggregateMode,
+    config_interval: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            aggregate_mode: DEFAULT_AGGREGATE_MODE,
+            config_interval: DEFAULT_CONFIG_INTERVAL,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StreamFormat {
+    ByteStream,
+    /// hvc1 / hev1 with the given NAL unit length size
+    LengthPrefixed(usize),
+}
+
+#[derive(Default)]
+struct State {
+    stream_format: Option<StreamFormat>,
+    /// Latest parameter sets from the caps or the stream.
+    parameter_sets: ParameterSets,
+    /// PTS at which the parameter sets were last inserted into the stream.
+    last_config_pts: Option<gst::ClockTime>,
+}
+
+#[derive(Default)]
+pub struct RtpH265Pay {
+    settings: Mutex<Settings>,
+    state: AtomicRefCell<State>,
+}
+
+/// Payload of a single RTP packet.
+enum Payload<'a> {
+    Single(&'a [u8]),
+    Aggregation(Vec<u8>),
+    Fragment([u8; 3], &'a [u8]),
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpH265Pay {
+    const NAME: &'static str = "GstRtpH265Pay2";
+    type Type = super::RtpH265Pay;
+    type ParentType = crate::basepay::RtpBasePay2;
+}
+
+impl ObjectImpl for RtpH265Pay {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("aggregate-mode", DEFAULT_AGGREGATE_MODE)
+                    .nick("Aggregate Mode")
+                    .blurb("Whether to aggregate NAL units of an access unit into one packet")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecInt::builder("config-interval")
+                    .nick("Config Interval")
+                    .blurb(
+                        "Send VPS, SPS and PPS in-band before IRAP pictures at this interval in \
+                         seconds (0 = disabled, -1 = before every IRAP picture)",
+                    )
+                    .default_value(DEFAULT_CONFIG_INTERVAL)
+                    .minimum(-1)
+                    .maximum(3600)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "aggregate-mode" => {
+                self.settings.lock().unwrap().aggregate_mode = value.get().unwrap();
+            }
+            "config-interval" => {
+                self.settings.lock().unwrap().config_interval = value.get().unwrap();
+            }
+            _ => unimplemented!(),
+        };
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "aggregate-mode" => self.settings.lock().unwrap().aggregate_mode.to_value(),
+            "config-interval" => self.settings.lock().unwrap().config_interval.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for RtpH265Pay {}
+
+impl ElementImpl for RtpH265Pay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP H.265 payloader",
+                "Codec/Payloader/Network/RTP",
+                "Payload H.265 as RTP packets (RFC 7798)",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("video/x-h265")
+                    .field(
+                        "stream-format",
+                        gst::List::new(["byte-stream", "hvc1", "hev1"]),
+                    )
+                    .field("alignment", "au")
+                    .build(),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("application/x-rtp")
+                    .field("media", "video")
+                    .field("clock-rate", 90_000i32)
+                    .field("encoding-name", "H265")
+                    .build(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl RtpH265Pay {
+    fn update_src_caps(&self, state: &State) {
+        let mut caps = gst::Caps::builder("application/x-rtp")
+            .field("media", "video")
+            .field("clock-rate", 90_000i32)
+            .field("encoding-name", "H265")
+            .build();
+        state
+            .parameter_sets
+            .to_caps(caps.get_mut().unwrap().structure_mut(0).unwrap());
+
+        self.obj().set_src_caps(&caps);
+    }
+
+    /// Returns `true` if the parameter sets have to be inserted before the access unit.
+    fn needs_config(
+        &self,
+        settings: &Settings,
+        state: &State,
+        nals: &[&[u8]],
+        pts: Option<gst::ClockTime>,
+    ) -> bool {
+        if settings.config_interval == 0 || state.parameter_sets.is_empty() {
+            return false;
+        }
+
+        if !nals.iter().any(|nal| nal::is_irap(nal::nal_type(nal))) {
+            return false;
+        }
+
+        // Nothing to do if the access unit already carries all parameter sets
+        let au_sets = ParameterSets::from_nals(nals.iter().copied());
+        if !au_sets.vps.is_empty() && !au_sets.sps.is_empty() && !au_sets.pps.is_empty() {
+            return false;
+        }
+
+        if settings.config_interval < 0 {
+            return true;
+        }
+
+        match (state.last_config_pts, pts) {
+            (Some(last), Some(pts)) => {
+                pts.saturating_sub(last)
+                    >= gst::ClockTime::from_seconds(settings.config_interval as u64)
+            }
+            _ => true,
+        }
+    }
+
+    /// Splits the NAL units into packet payloads, fragmenting and aggregating as needed.
+    fn packetize<'a>(
+        &self,
+        nals: &[&'a [u8]],
+        max_payload_size: usize,
+        aggregate: bool,
+    ) -> Vec<Payload<'a>> {
+        let mut payloads = Vec::new();
+        let mut aggregated = Vec::<&[u8]>::new();
+        let mut aggregated_size = NAL_HEADER_SIZE;
+
+        fn finish_aggregation<'a>(
+            aggregated: &mut Vec<&'a [u8]>,
+            aggregated_size: &mut usize,
+            payloads: &mut Vec<Payload<'a>>,
+        ) {
+            match aggregated.len() {
+                0 => (),
+                1 => payloads.push(Payload::Single(aggregated[0])),
+                _ => {
+                    // The payload header has the F bit set if any NAL unit has it set and the
+                    // lowest layer ID and temporal ID of all NAL units
+                    let forbidden_zero = aggregated.iter().any(|nal| nal[0] & 0x80 != 0);
+                    let layer_id = aggregated.iter().map(|nal| nal::layer_id(nal)).min();
+                    let tid = aggregated
+                        .iter()
+                        .map(|nal| nal::temporal_id_plus1(nal))
+                        .min();
+
+                    let mut payload = Vec::with_capacity(*aggregated_size);
+                    payload.extend_from_slice(&nal::header(
+                        forbidden_zero,
+                        nal::NAL_TYPE_AP,
+                        layer_id.unwrap(),
+                        tid.unwrap(),
+                    ));
+                    for nal in aggregated.iter() {
+                        payload.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+                        payload.extend_from_slice(nal);
+                    }
+                    payloads.push(Payload::Aggregation(payload));
+                }
+            }
+
+            aggregated.clear();
+            *aggregated_size = NAL_HEADER_SIZE;
+        }
+
+        for &nal in nals {
+            if aggregate && aggregated_size + 2 + nal.len() <= max_payload_size {
+                aggregated.push(nal);
+                aggregated_size += 2 + nal.len();
+                continue;
+            }
+
+            finish_aggregation(&mut aggregated, &mut aggregated_size, &mut payloads);
+
+            if aggregate && NAL_HEADER_SIZE + 2 + nal.len() <= max_payload_size {
+                aggregated.push(nal);
+                aggregated_size += 2 + nal.len();
+            } else if nal.len() <= max_payload_size {
+                payloads.push(Payload::Single(nal));
+            } else {
+                let nal_type = nal::nal_type(nal);
+                let header = nal::header(
+                    nal[0] & 0x80 != 0,
+                    nal::NAL_TYPE_FU,
+                    nal::layer_id(nal),
+                    nal::temporal_id_plus1(nal),
+                );
+
+                let chunk_size = max_payload_size - NAL_HEADER_SIZE - 1;
+                let data = &nal[NAL_HEADER_SIZE..];
+                let num_chunks = data.len().div_ceil(chunk_size);
+                for (idx, chunk) in data.chunks(chunk_size).enumerate() {
+                    let start = (idx == 0) as u8;
+                    let end = (idx + 1 == num_chunks) as u8;
+                    let fu_header = (start << 7) | (end << 6) | nal_type;
+                    payloads.push(Payload::Fragment([header[0], header[1], fu_header], chunk));
+                }
+            }
+        }
+
+        finish_aggregation(&mut aggregated, &mut aggregated_size, &mut payloads);
+
+        payloads
+    }
+}
+
+impl crate::basepay::RtpBasePay2Impl for RtpH265Pay {
+    const ALLOWED_META_TAGS: &'static [&'static str] = &["video"];
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn set_sink_caps(&self, caps: &gst::Caps) -> bool {
+        gst::debug!(CAT, imp = self, "received caps {caps:?}");
+
+        let s = caps.structure(0).unwrap();
+        let mut state = self.state.borrow_mut();
+
+        let stream_format = s.get::<&str>("stream-format").unwrap_or("byte-stream");
+        if stream_format == "byte-stream" {
+            state.stream_format = Some(StreamFormat::ByteStream);
+        } else {
+            let Ok(codec_data) = s.get::<gst::Buffer>("codec_data") else {
+                gst::error!(CAT, imp = self, "No codec_data for {stream_format} stream");
+                return false;
+            };
+            let map = codec_data.map_readable().unwrap();
+
+            match nal::parse_hvcc(&map) {
+                Ok((length_size, parameter_sets)) => {
+                    gst::debug!(
+                        CAT,
+                        imp = self,
+                        "Parsed codec_data with NAL unit length size {length_size} and parameter sets {parameter_sets:?}",
+                    );
+                    state.stream_format = Some(StreamFormat::LengthPrefixed(length_size));
+                    state.parameter_sets.update(parameter_sets);
+                }
+                Err(err) => {
+                    gst::error!(CAT, imp = self, "Failed to parse codec_data: {err:#}");
+                    return false;
+                }
+            }
+        }
+
+        self.update_src_caps(&state);
+
+        true
+    }
+
+    fn handle_buffer(
+        &self,
+        buffer: &gst::Buffer,
+        id: u64,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.borrow_mut();
+        let settings = self.settings.lock().unwrap().clone();
+        let max_payload_size = self.obj().max_payload_size() as usize;
+
+        gst::trace!(CAT, imp = self, "received buffer of size {}", buffer.size());
+
+        if max_payload_size <= NAL_HEADER_SIZE + 1 {
+            gst::element_imp_error!(
+                self,
+                gst::LibraryError::Settings,
+                ["Too small MTU configured for stream"]
+            );
+            return Err(gst::FlowError::Error);
+        }
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Read,
+                ["Failed to map buffer readable"]
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        let mut nals = match state.stream_format {
+            Some(StreamFormat::ByteStream) => nal::split_byte_stream(&map),
+            Some(StreamFormat::LengthPrefixed(length_size)) => {
+                match nal::split_length_prefixed(&map, length_size) {
+                    Ok(nals) => nals,
+                    Err(err) => {
+                        gst::warning!(CAT, imp = self, "Failed to parse access unit: {err:#}");
+                        self.obj().drop_buffers(..=id);
+                        return Ok(gst::FlowSuccess::Ok);
+                    }
+                }
+            }
+            None => return Err(gst::FlowError::NotNegotiated),
+        };
+
+        if nals.is_empty() {
+            gst::warning!(CAT, imp = self, "Access unit without NAL units");
+            self.obj().drop_buffers(..=id);
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        // Keep track of in-band parameter sets for the caps and for re-sending them
+        if state
+            .parameter_sets
+            .update(ParameterSets::from_nals(nals.iter().copied()))
+        {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Updated parameter sets {:?}",
+                state.parameter_sets
+            );
+            self.update_src_caps(&state);
+        }
+
+        let parameter_sets = state.parameter_sets.clone();
+        if self.needs_config(&settings, &state, &nals, buffer.pts()) {
+            gst::trace!(CAT, imp = self, "Inserting parameter sets");
+
+            let pos = nals
+                .iter()
+                .position(|nal| nal::nal_type(nal) != nal::NAL_TYPE_AUD)
+                .unwrap_or(nals.len());
+            nals.splice(pos..pos, parameter_sets.iter());
+            state.last_config_pts = buffer.pts();
+        }
+
+        // Access unit delimiters are not useful in RTP as the marker bit signals the end of an
+        // access unit
+        nals.retain(|nal| nal::nal_type(nal) != nal::NAL_TYPE_AUD);
+
+        let aggregate = settings.aggregate_mode != RtpH265PayAggregateMode::None;
+        let payloads = self.packetize(&nals, max_payload_size, aggregate);
+
+        let num_payloads = payloads.len();
+        for (idx, payload) in payloads.into_iter().enumerate() {
+            let builder = rtp_types::RtpPacketBuilder::new().marker_bit(idx + 1 == num_payloads);
+            let builder = match payload {
+                Payload::Single(nal) => builder.payload(nal),
+                Payload::Aggregation(ref data) => builder.payload(data.as_slice()),
+                Payload::Fragment(ref header, data) => {
+                    builder.payload(header.as_slice()).payload(data)
+                }
+            };
+
+            self.obj().queue_packet(id.into(), builder)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,49 @@
+// GStreamer RTP H.265 Payloader
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+pub mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(i32)]
+#[enum_type(name = "GstRtpH265Pay2AggregateMode")]
+#[non_exhaustive]
+pub(crate) enum RtpH265PayAggregateMode {
+    #[enum_value(
+        name = "None: send every NAL unit in its own packet or fragments thereof.",
+        nick = "none"
+    )]
+    None = 0,
+
+    #[enum_value(
+        name = "Zero Latency: aggregate small NAL units of the same access unit into aggregation packets.",
+        nick = "zero-latency"
+    )]
+    ZeroLatency = 1,
+}
+
+glib::wrapper! {
+    pub struct RtpH265Pay(ObjectSubclass<imp::RtpH265Pay>)
+        @extends crate::basepay::RtpBasePay2, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    {
+        RtpH265PayAggregateMode::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
+
+    gst::Element::register(
+        Some(plugin),
+        "rtph265pay2",
+        gst::Rank::MARGINAL,
+        RtpH265Pay::static_type(),
+    )
+}
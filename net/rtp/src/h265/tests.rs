@@ -0,0 +1,451 @@
+// GStreamer RTP H.265 Payloader / Depayloader Tests
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::h265::nal;
+use crate::tests::{run_test_pipeline, ExpectedBuffer, ExpectedPacket, Source};
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        crate::plugin_register_static().expect("rtph265 test");
+    });
+}
+
+fn nal_unit(nal_type: u8, size: usize) -> Vec<u8> {
+    let mut nal = vec![0xaa; size];
+    nal[..2].copy_from_slice(&nal::header(false, nal_type, 0, 1));
+    nal
+}
+
+fn byte_stream(nals: &[Vec<u8>]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for nal in nals {
+        data.extend_from_slice(&[0, 0, 0, 1]);
+        data.extend_from_slice(nal);
+    }
+    data
+}
+
+/// One IRAP access unit with in-band parameter sets and a 3000 byte IDR slice, followed by a
+/// 100 byte trailing picture.
+fn input() -> Source<'static> {
+    let keyframe = byte_stream(&[
+        nal_unit(nal::NAL_TYPE_VPS, 10),
+        nal_unit(nal::NAL_TYPE_SPS, 10),
+        nal_unit(nal::NAL_TYPE_PPS, 10),
+        nal_unit(19, 3000),
+    ]);
+    let delta = byte_stream(&[nal_unit(1, 100)]);
+
+    let mut buffers = Vec::new();
+    for (i, data) in [keyframe, delta].into_iter().enumerate() {
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(i as u64 * 40));
+            if i > 0 {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        buffers.push(buffer);
+    }
+
+    let caps = gst::Caps::builder("video/x-h265")
+        .field("stream-format", "byte-stream")
+        .field("alignment", "au")
+        .build();
+
+    Source::Buffers(caps, buffers)
+}
+
+fn expected_depay() -> Vec<Vec<ExpectedBuffer>> {
+    vec![
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::ZERO)
+            .size(4 * 4 + 3 * 10 + 3000)
+            .flags(gst::BufferFlags::DISCONT | gst::BufferFlags::MARKER)
+            .build()],
+        vec![ExpectedBuffer::builder()
+            .pts(gst::ClockTime::from_mseconds(40))
+            .size(4 + 100)
+            .flags(gst::BufferFlags::MARKER | gst::BufferFlags::DELTA_UNIT)
+            .build()],
+    ]
+}
+
+/// The same access units as [`input`] in hvc1 format with the parameter sets only in the
+/// `codec_data`.
+fn input_hvc1() -> Source<'static> {
+    fn length_prefixed(nals: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for nal in nals {
+            data.extend_from_slice(&(nal.len() as u32).to_be_bytes());
+            data.extend_from_slice(nal);
+        }
+        data
+    }
+
+    let keyframe = length_prefixed(&[nal_unit(19, 3000)]);
+    let delta = length_prefixed(&[nal_unit(1, 100)]);
+
+    let mut buffers = Vec::new();
+    for (i, data) in [keyframe, delta].into_iter().enumerate() {
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(i as u64 * 40));
+            if i > 0 {
+                buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+            }
+        }
+        buffers.push(buffer);
+    }
+
+    // Version 1, general profile / level information and a 4 byte NAL unit length size
+    let mut hvcc = vec![1];
+    hvcc.extend_from_slice(&[0; 20]);
+    hvcc.push(0xfc | 3);
+    hvcc.push(3);
+    for nal_type in [nal::NAL_TYPE_VPS, nal::NAL_TYPE_SPS, nal::NAL_TYPE_PPS] {
+        hvcc.push(0x80 | nal_type);
+        hvcc.extend_from_slice(&1u16.to_be_bytes());
+        hvcc.extend_from_slice(&10u16.to_be_bytes());
+        hvcc.extend_from_slice(&nal_unit(nal_type, 10));
+    }
+
+    let caps = gst::Caps::builder("video/x-h265")
+        .field("stream-format", "hvc1")
+        .field("alignment", "au")
+        .field("codec_data", gst::Buffer::from_mut_slice(hvcc))
+        .build();
+
+    Source::Buffers(caps, buffers)
+}
+
+/// Packets for the fragmented IDR slice and the trailing picture.
+fn expected_fragments_and_delta(first_discont: bool) -> (Vec<ExpectedPacket>, Vec<ExpectedPacket>) {
+    // 2998 bytes after the NAL unit header, 1385 bytes per fragment
+    let fragments = [1400, 1400, 12 + 3 + 228]
+        .into_iter()
+        .enumerate()
+        .map(|(i, size)| {
+            ExpectedPacket::builder()
+                .pts(gst::ClockTime::ZERO)
+                .flags(match i {
+                    0 if first_discont => gst::BufferFlags::DISCONT,
+                    2 => gst::BufferFlags::MARKER,
+                    _ => gst::BufferFlags::empty(),
+                })
+                .rtp_time(0)
+                .marker_bit(i == 2)
+                .size(size)
+                .build()
+        })
+        .collect();
+
+    let delta = vec![ExpectedPacket::builder()
+        .pts(gst::ClockTime::from_mseconds(40))
+        .flags(gst::BufferFlags::MARKER)
+        .rtp_time(3_600)
+        .marker_bit(true)
+        .size(12 + 100)
+        .build()];
+
+    (fragments, delta)
+}
+
+#[test]
+fn test_h265_aggregate() {
+    init();
+
+    let pay = "rtph265pay2";
+    let depay = "rtph265depay2";
+
+    let (fragments, delta) = expected_fragments_and_delta(false);
+
+    // Parameter sets are aggregated into one packet
+    let mut keyframe = vec![ExpectedPacket::builder()
+        .pts(gst::ClockTime::ZERO)
+        .flags(gst::BufferFlags::DISCONT)
+        .rtp_time(0)
+        .marker_bit(false)
+        .size(12 + 2 + 3 * (2 + 10))
+        .build()];
+    keyframe.extend(fragments);
+
+    run_test_pipeline(input(), pay, depay, vec![keyframe, delta], expected_depay());
+}
+
+#[test]
+fn test_h265_no_aggregate() {
+    init();
+
+    let pay = "rtph265pay2 aggregate-mode=none";
+    let depay = "rtph265depay2";
+
+    let (fragments, delta) = expected_fragments_and_delta(false);
+
+    // One packet per parameter set
+    let mut keyframe = (0..3)
+        .map(|i| {
+            ExpectedPacket::builder()
+                .pts(gst::ClockTime::ZERO)
+                .flags(if i == 0 {
+                    gst::BufferFlags::DISCONT
+                } else {
+                    gst::BufferFlags::empty()
+                })
+                .rtp_time(0)
+                .marker_bit(false)
+                .size(12 + 10)
+                .build()
+        })
+        .collect::<Vec<_>>();
+    keyframe.extend(fragments);
+
+    run_test_pipeline(input(), pay, depay, vec![keyframe, delta], expected_depay());
+}
+
+#[test]
+fn test_h265_hvc1() {
+    init();
+
+    let pay = "rtph265pay2 config-interval=-1";
+    let depay = "rtph265depay2";
+
+    let (fragments, delta) = expected_fragments_and_delta(false);
+
+    // Parameter sets from the codec_data are inserted in-band before the IDR slice
+    let mut keyframe = vec![ExpectedPacket::builder()
+        .pts(gst::ClockTime::ZERO)
+        .flags(gst::BufferFlags::DISCONT)
+        .rtp_time(0)
+        .marker_bit(false)
+        .size(12 + 2 + 3 * (2 + 10))
+        .build()];
+    keyframe.extend(fragments);
+
+    run_test_pipeline(
+        input_hvc1(),
+        pay,
+        depay,
+        vec![keyframe, delta],
+        expected_depay(),
+    );
+}
+
+#[test]
+fn test_h265_hvc1_sprop() {
+    init();
+
+    let pay = "rtph265pay2";
+    let depay = "rtph265depay2";
+
+    // Parameter sets are only signalled in the caps and inserted again by the depayloader
+    let (keyframe, delta) = expected_fragments_and_delta(true);
+
+    run_test_pipeline(
+        input_hvc1(),
+        pay,
+        depay,
+        vec![keyframe, delta],
+        expected_depay(),
+    );
+}
+
+fn test_h265_depay_don(don_field: &str) {
+    init();
+
+    let idr = |size| nal_unit(19, size);
+    let header = |nal_type| nal::header(false, nal_type, 0, 1);
+
+    // An aggregation packet with DONs 5 and 7 and a single NAL unit packet with DON 6 for the
+    // first access unit, and a fragmented NAL unit with DON 8 for the second access unit
+    let mut aggregation = header(nal::NAL_TYPE_AP).to_vec();
+    aggregation.extend_from_slice(&[0, 5, 0, 4]);
+    aggregation.extend_from_slice(&idr(4));
+    aggregation.extend_from_slice(&[1, 0, 6]);
+    aggregation.extend_from_slice(&idr(6));
+
+    let mut single = header(19).to_vec();
+    single.extend_from_slice(&[0, 6]);
+    single.extend_from_slice(&idr(5)[nal::NAL_HEADER_SIZE..]);
+
+    let mut fragment_start = header(nal::NAL_TYPE_FU).to_vec();
+    fragment_start.extend_from_slice(&[0x80 | 1, 0, 8, 1, 2, 3]);
+    let mut fragment_end = header(nal::NAL_TYPE_FU).to_vec();
+    fragment_end.extend_from_slice(&[0x40 | 1, 4, 5]);
+
+    let packets = [
+        (aggregation, false, 0),
+        (single, true, 0),
+        (fragment_start, false, 3_600),
+        (fragment_end, true, 3_600),
+    ];
+
+    let mut h = gst_check::Harness::new("rtph265depay2");
+    h.play();
+    h.set_src_caps(
+        gst::Caps::builder("application/x-rtp")
+            .field("media", "video")
+            .field("payload", 96)
+            .field("clock-rate", 90_000)
+            .field("encoding-name", "H265")
+            .field(don_field, "1")
+            .build(),
+    );
+
+    for (idx, (payload, marker, timestamp)) in packets.iter().enumerate() {
+        let buf = rtp_types::RtpPacketBuilder::new()
+            .marker_bit(*marker)
+            .timestamp(*timestamp)
+            .payload_type(96)
+            .sequence_number(idx as u16)
+            .payload(payload.as_slice())
+            .write_vec()
+            .unwrap();
+        let mut buf = gst::Buffer::from_mut_slice(buf);
+        buf.get_mut()
+            .unwrap()
+            .set_pts(gst::ClockTime::from_mseconds(*timestamp as u64 / 90));
+
+        h.push(buf).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // NAL units are output in decoding order
+    let buffer = h.pull().unwrap();
+    assert_eq!(
+        buffer.map_readable().unwrap().as_slice(),
+        byte_stream(&[idr(4), idr(5), idr(6)]).as_slice()
+    );
+
+    let mut trailing = header(1).to_vec();
+    trailing.extend_from_slice(&[1, 2, 3, 4, 5]);
+    let buffer = h.pull().unwrap();
+    assert!(buffer.flags().contains(gst::BufferFlags::DELTA_UNIT));
+    assert_eq!(
+        buffer.map_readable().unwrap().as_slice(),
+        byte_stream(&[trailing]).as_slice()
+    );
+}
+
+#[test]
+fn test_h265_depay_donl_max_don_diff() {
+    test_h265_depay_don("sprop-max-don-diff");
+}
+
+#[test]
+fn test_h265_depay_donl_depack_buf_nalus() {
+    test_h265_depay_don("sprop-depack-buf-nalus");
+}
+
+#[test]
+fn test_h265_depay_sprop() {
+    init();
+
+    let sets = nal::ParameterSets::from_nals([
+        nal_unit(nal::NAL_TYPE_VPS, 10).as_slice(),
+        nal_unit(nal::NAL_TYPE_SPS, 12).as_slice(),
+        nal_unit(nal::NAL_TYPE_PPS, 4).as_slice(),
+    ]);
+
+    let mut caps = gst::Caps::builder("application/x-rtp")
+        .field("media", "video")
+        .field("payload", 96)
+        .field("clock-rate", 90_000)
+        .field("encoding-name", "H265")
+        .build();
+    sets.to_caps(caps.get_mut().unwrap().structure_mut(0).unwrap());
+
+    let mut h = gst_check::Harness::new("rtph265depay2");
+    h.play();
+    h.set_src_caps(caps);
+
+    // An IRAP picture with and one without in-band parameter sets, and a trailing picture
+    let mut aggregation = nal::header(false, nal::NAL_TYPE_AP, 0, 1).to_vec();
+    for nal in sets.iter().map(<[u8]>::to_vec).chain([nal_unit(19, 8)]) {
+        aggregation.extend_from_slice(&(nal.len() as u16).to_be_bytes());
+        aggregation.extend_from_slice(&nal);
+    }
+    let packets = [aggregation, nal_unit(19, 8), nal_unit(1, 8)];
+
+    for (idx, payload) in packets.iter().enumerate() {
+        let buf = rtp_types::RtpPacketBuilder::new()
+            .marker_bit(true)
+            .timestamp(idx as u32 * 3_600)
+            .payload_type(96)
+            .sequence_number(idx as u16)
+            .payload(payload.as_slice())
+            .write_vec()
+            .unwrap();
+        let mut buf = gst::Buffer::from_mut_slice(buf);
+        buf.get_mut()
+            .unwrap()
+            .set_pts(gst::ClockTime::from_mseconds(idx as u64 * 40));
+
+        h.push(buf).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // Parameter sets are only inserted before IRAP pictures that don't carry them
+    let with_sets = byte_stream(
+        &sets
+            .iter()
+            .map(<[u8]>::to_vec)
+            .chain([nal_unit(19, 8)])
+            .collect::<Vec<_>>(),
+    );
+    for expected in [with_sets.clone(), with_sets, byte_stream(&[nal_unit(1, 8)])] {
+        let buffer = h.pull().unwrap();
+        assert_eq!(
+            buffer.map_readable().unwrap().as_slice(),
+            expected.as_slice()
+        );
+    }
+}
+
+#[test]
+fn test_split_byte_stream() {
+    let vps = nal_unit(nal::NAL_TYPE_VPS, 4);
+    let idr = nal_unit(19, 6);
+
+    // 3 and 4 byte start codes and trailing zero bytes
+    let mut data = vec![0, 0, 1];
+    data.extend_from_slice(&vps);
+    data.extend_from_slice(&[0, 0, 0, 0, 1]);
+    data.extend_from_slice(&idr);
+    data.push(0);
+
+    assert_eq!(
+        nal::split_byte_stream(&data),
+        vec![vps.as_slice(), idr.as_slice()]
+    );
+}
+
+#[test]
+fn test_sprop_roundtrip() {
+    init();
+
+    let sets = nal::ParameterSets::from_nals([
+        nal_unit(nal::NAL_TYPE_VPS, 10).as_slice(),
+        nal_unit(nal::NAL_TYPE_SPS, 12).as_slice(),
+        nal_unit(nal::NAL_TYPE_PPS, 4).as_slice(),
+        nal_unit(nal::NAL_TYPE_PPS, 5).as_slice(),
+    ]);
+
+    let mut caps = gst::Caps::new_empty_simple("application/x-rtp");
+    sets.to_caps(caps.get_mut().unwrap().structure_mut(0).unwrap());
+
+    let s = caps.structure(0).unwrap();
+    assert_eq!(s.get::<&str>("sprop-pps").unwrap().split(',').count(), 2);
+    assert_eq!(nal::ParameterSets::from_caps(s).unwrap(), sets);
+}
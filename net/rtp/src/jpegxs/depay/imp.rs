@@ -0,0 +1,403 @@
+// GStreamer RTP JPEG XS Depayloader
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpjpegxsdepay2
+ * @see_also: rtpjpegxspay2, svtjpegxsdec, svtjpegxsenc
+ *
+ * Depayload a JPEG XS video stream from RTP packets as per [RFC 9134][rfc-9134].
+ *
+ * Both the codestream and the slice packetization mode are supported. Packets of a frame are
+ * ordered by their packet counter so that out-of-order transmission is handled too, and frames
+ * with missing packets are dropped.
+ *
+ * Interlaced streams are output with the codestreams of both fields of a frame in a single
+ * buffer.
+ *
+ * [rfc-9134]: https://www.rfc-editor.org/rfc/rfc9134.html
+ *
+ * ## Example pipeline
+ *
+ * ```shell
+ * gst-launch-1.0 udpsrc address=127.0.0.1 port=5004 caps='application/x-rtp,media=video,clock-rate=90000,encoding-name=JXSV,width=(string)1920,height=(string)1080' ! rtpjitterbuffer latency=50 ! rtpjpegxsdepay2 ! svtjpegxsdec ! videoconvertscale ! autovideosink
+ * ```
+ *
+ * This will depayload and decode an incoming RTP JPEG XS video stream. You can use the
+ * #rtpjpegxspay2 and #svtjpegxsenc elements to create such an RTP stream.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+use atomic_refcell::AtomicRefCell;
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+use crate::basedepay::{PacketToBufferRelation, RtpBaseDepay2Ext};
+use crate::jpegxs::header::{self, PayloadHeader, PAYLOAD_HEADER_SIZE};
+
+/// Frame or field that is currently received.
+struct PendingField {
+    frame_counter: u8,
+    interlace: u8,
+    ext_timestamp: u64,
+    /// Payloads by packet counter.
+    packets: BTreeMap<u32, Vec<u8>>,
+    /// Packet counter of the packet with the marker bit.
+    last_packet_counter: Option<u32>,
+    /// First and last extended seqnum.
+    ext_seqnums: (u64, u64),
+}
+
+/// First field of an interlaced frame that waits for the second field.
+struct FirstField {
+    frame_counter: u8,
+    data: Vec<u8>,
+    ext_seqnums: (u64, u64),
+}
+
+struct State {
+    /// Resolution from the caps
+    dimensions: Option<(i32, i32)>,
+    /// Framerate from the `exactframerate` attribute
+    framerate: Option<gst::Fraction>,
+    /// Whether the last configured caps were interlaced
+    interlaced: Option<bool>,
+
+    pending_field: Option<PendingField>,
+    first_field: Option<FirstField>,
+
+    /// Set to `true` if the next outgoing buffer should have the `DISCONT` flag set.
+    needs_discont: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            dimensions: None,
+            framerate: None,
+            interlaced: None,
+            pending_field: None,
+            first_field: None,
+            needs_discont: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct RtpJpegXsDepay {
+    state: AtomicRefCell<State>,
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpjpegxsdepay2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP JPEG XS Depayloader"),
+    )
+});
+
+/// Parses an SDP attribute that is usually a string but might also be an integer.
+fn parse_int_field(s: &gst::StructureRef, name: &str) -> Option<i32> {
+    s.get::<&str>(name)
+        .ok()
+        .and_then(|v| v.trim().parse::<i32>().ok())
+        .or_else(|| s.get::<i32>(name).ok())
+}
+
+impl RtpJpegXsDepay {
+    /// Resets the streaming state but keeps the configuration from the caps.
+    fn reset(&self, state: &mut State) {
+        gst::debug!(CAT, imp = self, "resetting state");
+
+        *state = State {
+            dimensions: state.dimensions,
+            framerate: state.framerate,
+            ..State::default()
+        };
+    }
+
+    fn drop_pending(&self, state: &mut State) {
+        let last = state
+            .pending_field
+            .take()
+            .map(|field| field.ext_seqnums.1)
+            .or_else(|| state.first_field.as_ref().map(|field| field.ext_seqnums.1));
+        state.first_field = None;
+
+        if let Some(last) = last {
+            self.obj().drop_packets(..=last);
+        }
+        state.needs_discont = true;
+    }
+
+    fn finish_field(
+        &self,
+        state: &mut State,
+        field: PendingField,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let data = field
+            .packets
+            .into_values()
+            .reduce(|mut data, payload| {
+                data.extend_from_slice(&payload);
+                data
+            })
+            .unwrap_or_default();
+        let (first_seqnum, last_seqnum) = field.ext_seqnums;
+
+        let (data, first_seqnum, interlaced) = match field.interlace {
+            header::INTERLACE_FIRST_FIELD => {
+                if state.first_field.is_some() {
+                    gst::warning!(CAT, imp = self, "Dropping first field without second field");
+                    self.obj().drop_packets(..first_seqnum);
+                    state.needs_discont = true;
+                }
+
+                state.first_field = Some(FirstField {
+                    frame_counter: field.frame_counter,
+                    data,
+                    ext_seqnums: field.ext_seqnums,
+                });
+                return Ok(gst::FlowSuccess::Ok);
+            }
+            header::INTERLACE_SECOND_FIELD => match state.first_field.take() {
+                Some(first_field) if first_field.frame_counter == field.frame_counter => {
+                    let mut frame = first_field.data;
+                    frame.extend_from_slice(&data);
+                    (frame, first_field.ext_seqnums.0, true)
+                }
+                _ => {
+                    gst::warning!(CAT, imp = self, "Dropping second field without first field");
+                    self.obj().drop_packets(..=last_seqnum);
+                    state.needs_discont = true;
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+            },
+            _ => (data, first_seqnum, false),
+        };
+
+        if state.interlaced != Some(interlaced) || !self.obj().src_pad().has_current_caps() {
+            let Some((width, height)) = state.dimensions else {
+                gst::warning!(CAT, imp = self, "Can't determine resolution of the stream");
+                self.obj().drop_packets(..=last_seqnum);
+                return Ok(gst::FlowSuccess::Ok);
+            };
+
+            let mut caps_builder = gst::Caps::builder("image/x-jxsc")
+                .field("alignment", "frame")
+                .field("width", width)
+                .field("height", height)
+                .field(
+                    "interlace-mode",
+                    if interlaced {
+                        "interleaved"
+                    } else {
+                        "progressive"
+                    },
+                );
+            if let Some(framerate) = state.framerate {
+                caps_builder = caps_builder.field("framerate", framerate);
+            }
+
+            let caps = caps_builder.build();
+            gst::debug!(CAT, imp = self, "Setting caps {caps:?}");
+            self.obj().set_src_caps(&caps);
+            state.interlaced = Some(interlaced);
+        }
+
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        if state.needs_discont {
+            gst::trace!(CAT, imp = self, "Setting DISCONT");
+            buffer
+                .get_mut()
+                .unwrap()
+                .set_flags(gst::BufferFlags::DISCONT);
+            state.needs_discont = false;
+        }
+
+        self.obj().queue_buffer(
+            PacketToBufferRelation::Seqnums(first_seqnum..=last_seqnum),
+            buffer,
+        )
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpJpegXsDepay {
+    const NAME: &'static str = "GstRtpJpegXsDepay2";
+    type Type = super::RtpJpegXsDepay;
+    type ParentType = crate::basedepay::RtpBaseDepay2;
+}
+
+impl ObjectImpl for RtpJpegXsDepay {}
+
+impl GstObjectImpl for RtpJpegXsDepay {}
+
+impl ElementImpl for RtpJpegXsDepay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP JPEG XS Depayloader",
+                "Codec/Depayloader/Network/RTP",
+                "Depayload JPEG XS from RTP packets (RFC 9134)",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("application/x-rtp")
+                    .field("media", "video")
+                    .field("clock-rate", 90_000i32)
+                    .field("encoding-name", "JXSV")
+                    .build(),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("image/x-jxsc")
+                    .field("alignment", "frame")
+                    .build(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl crate::basedepay::RtpBaseDepay2Impl for RtpJpegXsDepay {
+    const ALLOWED_META_TAGS: &'static [&'static str] = &["video"];
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn flush(&self) {
+        let mut state = self.state.borrow_mut();
+        self.reset(&mut state);
+    }
+
+    fn drain(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.borrow_mut();
+        // Incomplete frames can't be decoded
+        self.drop_pending(&mut state);
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn set_sink_caps(&self, caps: &gst::Caps) -> bool {
+        let s = caps.structure(0).unwrap();
+        let mut state = self.state.borrow_mut();
+
+        state.dimensions = parse_int_field(s, "width")
+            .zip(parse_int_field(s, "height"))
+            .filter(|(width, height)| *width > 0 && *height > 0);
+        if state.dimensions.is_none() {
+            gst::warning!(CAT, imp = self, "No valid width and height in caps");
+        }
+
+        state.framerate = s.get::<&str>("exactframerate").ok().and_then(|v| {
+            let (numer, denom) = v.split_once('/').unwrap_or((v, "1"));
+            let numer = numer.trim().parse::<i32>().ok()?;
+            let denom = denom.trim().parse::<i32>().ok()?;
+            (numer > 0 && denom > 0).then(|| gst::Fraction::new(numer, denom))
+        });
+
+        // Renegotiate with the next frame
+        state.interlaced = None;
+
+        true
+    }
+
+    fn handle_packet(
+        &self,
+        packet: &crate::basedepay::Packet,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, imp = self, "Handling RTP packet {packet:?}");
+        let mut state = self.state.borrow_mut();
+
+        let payload = packet.payload();
+        let Some(payload_header) = PayloadHeader::parse(payload)
+            .filter(|_| payload.len() > PAYLOAD_HEADER_SIZE)
+            .filter(|h| h.interlace != 0b01)
+        else {
+            gst::warning!(CAT, imp = self, "Invalid JPEG XS RTP packet");
+            self.drop_pending(&mut state);
+            self.obj().drop_packet(packet);
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        gst::trace!(CAT, imp = self, "Parsed payload header {payload_header:?}");
+
+        // A packet of another frame or field means that the pending one is incomplete
+        if state.pending_field.as_ref().is_some_and(|field| {
+            field.frame_counter != payload_header.frame_counter
+                || field.interlace != payload_header.interlace
+                || field.ext_timestamp != packet.ext_timestamp()
+        }) {
+            gst::warning!(CAT, imp = self, "Dropping incomplete frame");
+            self.drop_pending(&mut state);
+        }
+
+        let field = state.pending_field.get_or_insert_with(|| PendingField {
+            frame_counter: payload_header.frame_counter,
+            interlace: payload_header.interlace,
+            ext_timestamp: packet.ext_timestamp(),
+            packets: BTreeMap::new(),
+            last_packet_counter: None,
+            ext_seqnums: (packet.ext_seqnum(), packet.ext_seqnum()),
+        });
+
+        field.ext_seqnums = (
+            field.ext_seqnums.0.min(packet.ext_seqnum()),
+            field.ext_seqnums.1.max(packet.ext_seqnum()),
+        );
+        field.packets.insert(
+            payload_header.packet_counter,
+            payload[PAYLOAD_HEADER_SIZE..].to_vec(),
+        );
+        if packet.marker_bit() {
+            field.last_packet_counter = Some(payload_header.packet_counter);
+        }
+
+        // Complete once all packets up to the one with the marker bit arrived
+        let complete = field.last_packet_counter.is_some_and(|last| {
+            field.packets.len() as u64 == last as u64 + 1
+                && field.packets.last_key_value().map(|(k, _)| *k) == Some(last)
+        });
+        if !complete {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let field = state.pending_field.take().unwrap();
+        self.finish_field(&mut state, field)
+    }
+}
@@ -0,0 +1,127 @@
+// GStreamer RTP JPEG XS Payload Header
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+pub const PAYLOAD_HEADER_SIZE: usize = 4;
+
+/// Interlace field value for progressive frames.
+pub const INTERLACE_PROGRESSIVE: u8 = 0b00;
+/// Interlace field value for the first field of an interlaced frame.
+pub const INTERLACE_FIRST_FIELD: u8 = 0b10;
+/// Interlace field value for the second field of an interlaced frame.
+pub const INTERLACE_SECOND_FIELD: u8 = 0b11;
+
+/// Maximum value of the combined SEP and P counters.
+pub const PACKET_COUNTER_MASK: u32 = (1 << 22) - 1;
+
+/// End of codestream marker, followed by the start of codestream and capabilities markers of
+/// the second field in interlaced mode.
+const FIELD_BOUNDARY: [u8; 6] = [0xff, 0x11, 0xff, 0x10, 0xff, 0x50];
+
+/// Payload header as per RFC 9134 section 4.3.
+///
+/// ```text
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |T|K|L| I |F counter|     SEP counter     |      P counter      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayloadHeader {
+    /// Packets are sent in sequential order (T).
+    pub sequential: bool,
+    /// Slice packetization mode (K), otherwise codestream packetization mode.
+    pub slice_mode: bool,
+    /// Last packet of a packetization unit (L).
+    pub last: bool,
+    /// Interlace information (I).
+    pub interlace: u8,
+    /// Frame counter modulo 32 (F).
+    pub frame_counter: u8,
+    /// SEP and P counters combined into the packet index inside the frame or field.
+    pub packet_counter: u32,
+}
+
+impl PayloadHeader {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let v = u32::from_be_bytes(data.get(..PAYLOAD_HEADER_SIZE)?.try_into().unwrap());
+
+        Some(PayloadHeader {
+            sequential: v & (1 << 31) != 0,
+            slice_mode: v & (1 << 30) != 0,
+            last: v & (1 << 29) != 0,
+            interlace: ((v >> 27) & 0b11) as u8,
+            frame_counter: ((v >> 22) & 0x1f) as u8,
+            packet_counter: v & PACKET_COUNTER_MASK,
+        })
+    }
+
+    pub fn to_bytes(self) -> [u8; PAYLOAD_HEADER_SIZE] {
+        let v = (self.sequential as u32) << 31
+            | (self.slice_mode as u32) << 30
+            | (self.last as u32) << 29
+            | ((self.interlace & 0b11) as u32) << 27
+            | ((self.frame_counter & 0x1f) as u32) << 22
+            | (self.packet_counter & PACKET_COUNTER_MASK);
+
+        v.to_be_bytes()
+    }
+}
+
+/// Splits a buffer with two interlaced fields into the codestreams of both fields.
+pub fn split_fields(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let pos = data
+        .windows(FIELD_BOUNDARY.len())
+        .position(|w| w == FIELD_BOUNDARY)?;
+
+    Some(data.split_at(pos + 2))
+}
+
+/// Splits a codestream into the packetization units of the slice packetization mode.
+///
+/// The first unit contains the codestream header up to the first slice, every following unit
+/// contains one slice starting with its slice header marker segment. Slice data is not byte
+/// stuffed so slice boundaries are found by looking for the complete slice header of the next
+/// slice, i.e. the SLH marker, its length and the expected slice index.
+pub fn split_slices(codestream: &[u8]) -> Vec<&[u8]> {
+    fn slice_header(index: u16) -> [u8; 6] {
+        let [hi, lo] = index.to_be_bytes();
+        [0xff, 0x20, 0x00, 0x04, hi, lo]
+    }
+
+    fn find(data: &[u8], pattern: &[u8; 6]) -> Option<usize> {
+        data.windows(pattern.len()).position(|w| w == pattern)
+    }
+
+    let Some(first) = find(codestream, &slice_header(0)) else {
+        return vec![codestream];
+    };
+
+    let mut units = Vec::new();
+    if first > 0 {
+        units.push(&codestream[..first]);
+    }
+    let mut remaining = &codestream[first..];
+    let mut index = 1u16;
+    loop {
+        match find(&remaining[6..], &slice_header(index)) {
+            Some(pos) => {
+                let (slice, rest) = remaining.split_at(6 + pos);
+                units.push(slice);
+                remaining = rest;
+                index = index.wrapping_add(1);
+            }
+            None => {
+                units.push(remaining);
+                break;
+            }
+        }
+    }
+
+    units
+}
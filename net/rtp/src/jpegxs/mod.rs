@@ -0,0 +1,14 @@
+// GStreamer RTP JPEG XS Payloader / Depayloader
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+pub mod depay;
+mod header;
+pub mod pay;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,354 @@
+// GStreamer RTP JPEG XS Payloader
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpjpegxspay2
+ * @see_also: rtpjpegxsdepay2, svtjpegxsenc, svtjpegxsdec
+ *
+ * Payload a JPEG XS video stream into RTP packets as per [RFC 9134][rfc-9134].
+ *
+ * In the default codestream packetization mode the codestream of every frame is split into
+ * packets of the maximum payload size. In slice packetization mode every slice of the codestream
+ * starts a new packet so that receivers can start decoding before the whole frame arrived.
+ *
+ * Interlaced input has to contain the codestreams of both fields of a frame in a single buffer.
+ * Each field is sent as a separate sequence of packets with the marker bit set on the last
+ * packet of each field.
+ *
+ * Packets are always sent in sequential order.
+ *
+ * [rfc-9134]: https://www.rfc-editor.org/rfc/rfc9134.html
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 videotestsrc ! video/x-raw,width=1920,height=1080,format=I422_10LE ! svtjpegxsenc ! rtpjpegxspay2 packetization-mode=slice ! udpsink host=127.0.0.1 port=5004
+ * ]| This will create and payload a JPEG XS video stream with a test pattern and
+ * send it out via UDP to localhost port 5004.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use atomic_refcell::AtomicRefCell;
+use gst::{glib, prelude::*, subclass::prelude::*};
+use std::sync::{LazyLock, Mutex};
+
+use crate::{
+    basepay::RtpBasePay2Ext,
+    jpegxs::header::{self, PayloadHeader, PACKET_COUNTER_MASK, PAYLOAD_HEADER_SIZE},
+};
+
+use super::RtpJpegXsPayPacketizationMode;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpjpegxspay2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP JPEG XS Payloader"),
+    )
+});
+
+const DEFAULT_PACKETIZATION_MODE: RtpJpegXsPayPacketizationMode =
+    RtpJpegXsPayPacketizationMode::Codestream;
+
+/// Caps fields that are passed through to the RTP caps as strings.
+const FORWARDED_FIELDS: &[&str] = &["profile", "level", "sublevel", "sampling", "depth"];
+
+#[derive(Clone)]
+struct Settings {
+    packetization_mode: RtpJpegXsPayPacketizationMode,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            packetization_mode: DEFAULT_PACKETIZATION_MODE,
+        }
+    }
+}
+
+#[derive(Default)]
+struct State {
+    /// Packetization mode configured together with the caps.
+    slice_mode: bool,
+    interlaced: bool,
+    /// Counter of the next frame modulo 32.
+    frame_counter: u8,
+}
+
+#[derive(Default)]
+pub struct RtpJpegXsPay {
+    settings: Mutex<Settings>,
+    state: AtomicRefCell<State>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpJpegXsPay {
+    const NAME: &'static str = "GstRtpJpegXsPay2";
+    type Type = super::RtpJpegXsPay;
+    type ParentType = crate::basepay::RtpBasePay2;
+}
+
+impl ObjectImpl for RtpJpegXsPay {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![glib::ParamSpecEnum::builder_with_default(
+                "packetization-mode",
+                DEFAULT_PACKETIZATION_MODE,
+            )
+            .nick("Packetization Mode")
+            .blurb("Whether to split the codestream at slice boundaries")
+            .mutable_ready()
+            .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "packetization-mode" => {
+                self.settings.lock().unwrap().packetization_mode = value.get().unwrap();
+            }
+            _ => unimplemented!(),
+        };
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "packetization-mode" => self.settings.lock().unwrap().packetization_mode.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for RtpJpegXsPay {}
+
+impl ElementImpl for RtpJpegXsPay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP JPEG XS payloader",
+                "Codec/Payloader/Network/RTP",
+                "Payload JPEG XS as RTP packets (RFC 9134)",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("image/x-jxsc")
+                    .field("alignment", "frame")
+                    .field("width", gst::IntRange::new(1i32, u16::MAX as i32))
+                    .field("height", gst::IntRange::new(1i32, u16::MAX as i32))
+                    .field(
+                        "interlace-mode",
+                        gst::List::new(["progressive", "interleaved"]),
+                    )
+                    .build(),
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("application/x-rtp")
+                    .field("media", "video")
+                    .field("clock-rate", 90_000i32)
+                    .field("encoding-name", "JXSV")
+                    .build(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl crate::basepay::RtpBasePay2Impl for RtpJpegXsPay {
+    const ALLOWED_META_TAGS: &'static [&'static str] = &["video"];
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.borrow_mut() = State::default();
+
+        Ok(())
+    }
+
+    fn set_sink_caps(&self, caps: &gst::Caps) -> bool {
+        gst::debug!(CAT, imp = self, "received caps {caps:?}");
+
+        let s = caps.structure(0).unwrap();
+        let settings = self.settings.lock().unwrap().clone();
+
+        let slice_mode = settings.packetization_mode == RtpJpegXsPayPacketizationMode::Slice;
+        let interlaced = s
+            .get::<&str>("interlace-mode")
+            .is_ok_and(|mode| mode == "interleaved");
+
+        let width = s.get::<i32>("width").unwrap();
+        let height = s.get::<i32>("height").unwrap();
+
+        let mut caps_builder = gst::Caps::builder("application/x-rtp")
+            .field("media", "video")
+            .field("clock-rate", 90_000i32)
+            .field("encoding-name", "JXSV")
+            .field("packetmode", if slice_mode { "1" } else { "0" })
+            .field("transmode", "1")
+            .field("width", width.to_string())
+            .field("height", height.to_string());
+
+        if let Some(framerate) = s
+            .get::<gst::Fraction>("framerate")
+            .ok()
+            .filter(|fps| *fps > gst::Fraction::new(0, 1))
+        {
+            let framerate = if framerate.denom() == 1 {
+                framerate.numer().to_string()
+            } else {
+                format!("{}/{}", framerate.numer(), framerate.denom())
+            };
+            caps_builder = caps_builder.field("exactframerate", framerate);
+        }
+
+        if interlaced {
+            caps_builder = caps_builder.field("interlace", "1");
+        }
+
+        for field in FORWARDED_FIELDS {
+            if let Ok(value) = s.get::<&str>(field) {
+                caps_builder = caps_builder.field(*field, value);
+            } else if let Ok(value) = s.get::<i32>(field) {
+                caps_builder = caps_builder.field(*field, value.to_string());
+            }
+        }
+
+        self.obj().set_src_caps(&caps_builder.build());
+
+        let mut state = self.state.borrow_mut();
+        state.slice_mode = slice_mode;
+        state.interlaced = interlaced;
+
+        true
+    }
+
+    fn handle_buffer(
+        &self,
+        buffer: &gst::Buffer,
+        id: u64,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.borrow_mut();
+
+        gst::trace!(CAT, imp = self, "received buffer of size {}", buffer.size());
+
+        let map = buffer.map_readable().map_err(|_| {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Read,
+                ["Failed to map buffer readable"]
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        let max_payload_size = (self.obj().max_payload_size() as usize)
+            .checked_sub(PAYLOAD_HEADER_SIZE)
+            .filter(|size| *size > 0)
+            .ok_or_else(|| {
+                gst::error!(CAT, imp = self, "Too small MTU configured for stream");
+                gst::element_imp_error!(
+                    self,
+                    gst::LibraryError::Settings,
+                    ["Too small MTU configured for stream"]
+                );
+                gst::FlowError::Error
+            })?;
+
+        let fields = if state.interlaced {
+            let Some((first, second)) = header::split_fields(&map) else {
+                gst::error!(CAT, imp = self, "Failed to find second field in buffer");
+                gst::element_imp_error!(
+                    self,
+                    gst::StreamError::Format,
+                    ["Interlaced buffer without second field"]
+                );
+                return Err(gst::FlowError::Error);
+            };
+            vec![
+                (header::INTERLACE_FIRST_FIELD, first),
+                (header::INTERLACE_SECOND_FIELD, second),
+            ]
+        } else {
+            vec![(header::INTERLACE_PROGRESSIVE, map.as_slice())]
+        };
+
+        for (interlace, codestream) in fields {
+            let units = if state.slice_mode {
+                header::split_slices(codestream)
+            } else {
+                vec![codestream]
+            };
+
+            gst::trace!(
+                CAT,
+                imp = self,
+                "Sending frame {} with interlace {interlace:#04b} in {} packetization units",
+                state.frame_counter,
+                units.len(),
+            );
+
+            // The packet counter starts at zero for every frame or field
+            let mut packet_counter = 0;
+            let n_units = units.len();
+            for (i, unit) in units.into_iter().enumerate() {
+                let mut chunks = unit.chunks(max_payload_size).peekable();
+                while let Some(chunk) = chunks.next() {
+                    let last = chunks.peek().is_none();
+
+                    let payload_header = PayloadHeader {
+                        sequential: true,
+                        slice_mode: state.slice_mode,
+                        last,
+                        interlace,
+                        frame_counter: state.frame_counter,
+                        packet_counter,
+                    }
+                    .to_bytes();
+
+                    self.obj().queue_packet(
+                        id.into(),
+                        rtp_types::RtpPacketBuilder::new()
+                            .marker_bit(last && i + 1 == n_units)
+                            .payload(payload_header.as_slice())
+                            .payload(chunk),
+                    )?;
+
+                    packet_counter = (packet_counter + 1) & PACKET_COUNTER_MASK;
+                }
+            }
+        }
+
+        state.frame_counter = (state.frame_counter + 1) & 0x1f;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
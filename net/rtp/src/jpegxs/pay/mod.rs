@@ -0,0 +1,50 @@
+// GStreamer RTP JPEG XS Payloader
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+pub mod imp;
+
+#[derive(Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(i32)]
+#[enum_type(name = "GstRtpJpegXsPay2PacketizationMode")]
+#[non_exhaustive]
+pub(crate) enum RtpJpegXsPayPacketizationMode {
+    #[enum_value(
+        name = "Codestream: split the whole codestream into packets.",
+        nick = "codestream"
+    )]
+    Codestream = 0,
+
+    #[enum_value(
+        name = "Slice: start a new packet for every slice of the codestream.",
+        nick = "slice"
+    )]
+    Slice = 1,
+}
+
+glib::wrapper! {
+    pub struct RtpJpegXsPay(ObjectSubclass<imp::RtpJpegXsPay>)
+        @extends crate::basepay::RtpBasePay2, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    {
+        RtpJpegXsPayPacketizationMode::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
+
+    gst::Element::register(
+        Some(plugin),
+        "rtpjpegxspay2",
+        gst::Rank::MARGINAL,
+        RtpJpegXsPay::static_type(),
+    )
+}
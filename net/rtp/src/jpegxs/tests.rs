@@ -0,0 +1,235 @@
+// GStreamer RTP JPEG XS Payloader / Depayloader Tests
+//
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use crate::jpegxs::header::{self, PayloadHeader};
+use crate::tests::{run_test_pipeline, ExpectedBuffer, ExpectedPacket, Source};
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        crate::plugin_register_static().expect("rtpjpegxs test");
+    });
+}
+
+const HEADER_SIZE: usize = 12;
+const SLICE_SIZE: usize = 6 + 500;
+const CODESTREAM_SIZE: usize = HEADER_SIZE + 2 * SLICE_SIZE + 2;
+
+/// Codestream with a minimal header and two slices of 500 bytes each.
+fn codestream() -> Vec<u8> {
+    // SOC, CAP and a dummy PIH marker segment
+    let mut data = vec![
+        0xff, 0x10, 0xff, 0x50, 0x00, 0x02, 0xff, 0x12, 0x00, 0x04, 0xaa, 0xaa,
+    ];
+    for i in 0..2 {
+        data.extend_from_slice(&[0xff, 0x20, 0x00, 0x04, 0x00, i]);
+        data.extend_from_slice(&[0x55; 500]);
+    }
+    // EOC
+    data.extend_from_slice(&[0xff, 0x11]);
+    assert_eq!(data.len(), CODESTREAM_SIZE);
+
+    data
+}
+
+/// Two frames, 40ms apart.
+fn input(interlaced: bool) -> Source<'static> {
+    let buffers = (0..2)
+        .map(|i| {
+            let mut data = codestream();
+            if interlaced {
+                data.extend(codestream());
+            }
+            let mut buffer = gst::Buffer::from_mut_slice(data);
+            buffer
+                .get_mut()
+                .unwrap()
+                .set_pts(gst::ClockTime::from_mseconds(i * 40));
+            buffer
+        })
+        .collect();
+
+    let caps = gst::Caps::builder("image/x-jxsc")
+        .field("alignment", "frame")
+        .field("width", 64i32)
+        .field("height", 32i32)
+        .field("framerate", gst::Fraction::new(25, 1))
+        .field(
+            "interlace-mode",
+            if interlaced {
+                "interleaved"
+            } else {
+                "progressive"
+            },
+        )
+        .build();
+
+    Source::Buffers(caps, buffers)
+}
+
+/// Packets of one frame with the given payload sizes, the marker bit is set on the packets at
+/// the given indices.
+fn expected_packets(frame: u64, sizes: &[usize], markers: &[usize]) -> Vec<ExpectedPacket> {
+    sizes
+        .iter()
+        .enumerate()
+        .map(|(i, size)| {
+            let marker = markers.contains(&i);
+            let mut flags = if marker {
+                gst::BufferFlags::MARKER
+            } else {
+                gst::BufferFlags::empty()
+            };
+            if frame == 0 && i == 0 {
+                flags |= gst::BufferFlags::DISCONT;
+            }
+
+            ExpectedPacket::builder()
+                .pts(gst::ClockTime::from_mseconds(frame * 40))
+                .flags(flags)
+                .rtp_time((frame * 3600) as u32)
+                .marker_bit(marker)
+                .size(12 + 4 + size)
+                .build()
+        })
+        .collect()
+}
+
+fn expected_depay(size: usize) -> Vec<Vec<ExpectedBuffer>> {
+    (0..2)
+        .map(|i| {
+            vec![ExpectedBuffer::builder()
+                .pts(gst::ClockTime::from_mseconds(i * 40))
+                .size(size)
+                .flags(if i == 0 {
+                    gst::BufferFlags::DISCONT
+                } else {
+                    gst::BufferFlags::empty()
+                })
+                .build()]
+        })
+        .collect()
+}
+
+#[test]
+fn test_jpegxs_codestream() {
+    init();
+
+    let pay = "rtpjpegxspay2";
+    let depay = "rtpjpegxsdepay2";
+
+    let expected_pay = (0..2)
+        .map(|frame| expected_packets(frame, &[CODESTREAM_SIZE], &[0]))
+        .collect();
+
+    run_test_pipeline(
+        input(false),
+        pay,
+        depay,
+        expected_pay,
+        expected_depay(CODESTREAM_SIZE),
+    );
+}
+
+#[test]
+fn test_jpegxs_codestream_fragmented() {
+    init();
+
+    let pay = "rtpjpegxspay2 mtu=516";
+    let depay = "rtpjpegxsdepay2";
+
+    // 500 bytes payload per packet
+    let expected_pay = (0..2)
+        .map(|frame| expected_packets(frame, &[500, 500, CODESTREAM_SIZE - 1000], &[2]))
+        .collect();
+
+    run_test_pipeline(
+        input(false),
+        pay,
+        depay,
+        expected_pay,
+        expected_depay(CODESTREAM_SIZE),
+    );
+}
+
+#[test]
+fn test_jpegxs_slice() {
+    init();
+
+    let pay = "rtpjpegxspay2 packetization-mode=slice";
+    let depay = "rtpjpegxsdepay2";
+
+    let expected_pay = (0..2)
+        .map(|frame| expected_packets(frame, &[HEADER_SIZE, SLICE_SIZE, SLICE_SIZE + 2], &[2]))
+        .collect();
+
+    run_test_pipeline(
+        input(false),
+        pay,
+        depay,
+        expected_pay,
+        expected_depay(CODESTREAM_SIZE),
+    );
+}
+
+#[test]
+fn test_jpegxs_slice_interlaced() {
+    init();
+
+    let pay = "rtpjpegxspay2 packetization-mode=slice";
+    let depay = "rtpjpegxsdepay2";
+
+    let field = [HEADER_SIZE, SLICE_SIZE, SLICE_SIZE + 2];
+    let expected_pay = (0..2)
+        .map(|frame| expected_packets(frame, &[field, field].concat(), &[2, 5]))
+        .collect();
+
+    run_test_pipeline(
+        input(true),
+        pay,
+        depay,
+        expected_pay,
+        expected_depay(2 * CODESTREAM_SIZE),
+    );
+}
+
+#[test]
+fn test_payload_header() {
+    let payload_header = PayloadHeader {
+        sequential: true,
+        slice_mode: false,
+        last: true,
+        interlace: header::INTERLACE_SECOND_FIELD,
+        frame_counter: 17,
+        packet_counter: 0x2abcd,
+    };
+
+    let bytes = payload_header.to_bytes();
+    assert_eq!(bytes, [0xbc, 0x42, 0xab, 0xcd]);
+    assert_eq!(PayloadHeader::parse(&bytes), Some(payload_header));
+}
+
+#[test]
+fn test_split() {
+    let data = codestream();
+
+    let units = header::split_slices(&data);
+    assert_eq!(
+        units.iter().map(|unit| unit.len()).collect::<Vec<_>>(),
+        vec![HEADER_SIZE, SLICE_SIZE, SLICE_SIZE + 2]
+    );
+    assert_eq!(units.concat(), data);
+
+    let interlaced = [codestream(), codestream()].concat();
+    let (first, second) = header::split_fields(&interlaced).unwrap();
+    assert_eq!(first, data.as_slice());
+    assert_eq!(second, data.as_slice());
+}
@@ -23,8 +23,13 @@ use gst::glib;
 #[macro_use]
 mod utils;
 
+mod audiolevel;
+#[cfg(feature = "dtls-srtp")]
+mod dtlssrtp;
 mod gcc;
+mod ptdemux;
 mod rtpbin2;
+mod ssrcdemux;
 
 mod audio_discont;
 mod baseaudiopay;
@@ -33,13 +38,17 @@ mod basepay;
 
 mod ac3;
 mod av1;
+mod dtmf;
+mod h265;
 mod jpeg;
+mod jpegxs;
 mod klv;
 mod mp2t;
 mod mp4a;
 mod mp4g;
 mod opus;
 mod pcmau;
+mod red;
 mod vp8;
 mod vp9;
 
@@ -49,6 +58,11 @@ mod tests;
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     gcc::register(plugin)?;
     rtpbin2::register(plugin)?;
+    ptdemux::register(plugin)?;
+    ssrcdemux::register(plugin)?;
+    audiolevel::register(plugin)?;
+    #[cfg(feature = "dtls-srtp")]
+    dtlssrtp::register(plugin)?;
 
     #[cfg(feature = "doc")]
     {
@@ -68,9 +82,18 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     av1::depay::register(plugin)?;
     av1::pay::register(plugin)?;
 
+    dtmf::depay::register(plugin)?;
+    dtmf::pay::register(plugin)?;
+
+    h265::depay::register(plugin)?;
+    h265::pay::register(plugin)?;
+
     jpeg::depay::register(plugin)?;
     jpeg::pay::register(plugin)?;
 
+    jpegxs::depay::register(plugin)?;
+    jpegxs::pay::register(plugin)?;
+
     klv::depay::register(plugin)?;
     klv::pay::register(plugin)?;
 
@@ -89,6 +112,9 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     pcmau::depay::register(plugin)?;
     pcmau::pay::register(plugin)?;
 
+    red::dec::register(plugin)?;
+    red::enc::register(plugin)?;
+
     vp8::depay::register(plugin)?;
     vp8::pay::register(plugin)?;
 
@@ -287,7 +287,7 @@ impl RtpOpusDepay {
 
         let n_frames = match toc & 0b11 {
             0 => 1,
-            1 => 2,
+            1 | 2 => 2,
             3 => {
                 if data.len() < 2 {
                     return gst::ClockTime::NONE;
@@ -23,6 +23,11 @@
  * ]|
  * for 5.1 surround sound audio.
  *
+ * Discontinuous transmission (DTX) and in-band forward error correction (FEC) are signalled
+ * via the `usedtx` and `useinbandfec` fields of the output caps. Like libwebrtc, DTX is also
+ * enabled if downstream requires `usedtx=1`, in which case the encoder needs to be configured
+ * accordingly too. The first packet after a period of DTX silence has the marker bit set.
+ *
  * [rfc-7587]: https://www.rfc-editor.org/rfc/rfc7587.html
  * [libwebrtc-multiopus]: https://webrtc-review.googlesource.com/c/src/+/129768
  *
@@ -47,12 +52,15 @@ use std::sync::atomic::AtomicBool;
 
 struct State {
     marker_pending: bool,
+    // Downstream requested discontinuous transmission
+    peer_dtx: bool,
 }
 
 impl Default for State {
     fn default() -> Self {
         State {
             marker_pending: true,
+            peer_dtx: false,
         }
     }
 }
@@ -64,10 +72,12 @@ impl State {
 }
 
 const DEFAULT_DTX: bool = false;
+const DEFAULT_INBAND_FEC: bool = false;
 
 #[derive(Default)]
 struct Settings {
     dtx: AtomicBool,
+    inband_fec: AtomicBool,
 }
 
 #[derive(Default)]
@@ -97,12 +107,27 @@ impl ObjectSubclass for RtpOpusPay {
 impl ObjectImpl for RtpOpusPay {
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
-            vec![glib::ParamSpecBoolean::builder("dtx")
-                .nick("Discontinuous Transmission")
-                .blurb("Do not send out empty packets for transmission (requires opusenc dtx=true)")
-                .default_value(DEFAULT_DTX)
-                .mutable_playing()
-                .build()]
+            vec![
+                glib::ParamSpecBoolean::builder("dtx")
+                    .nick("Discontinuous Transmission")
+                    .blurb("Do not send out empty packets for transmission (requires opusenc dtx=true)")
+                    .default_value(DEFAULT_DTX)
+                    .mutable_playing()
+                    .build(),
+                /**
+                 * GstRtpOpusPay2:inband-fec:
+                 *
+                 * Signal in the output caps that the stream contains in-band FEC data.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecBoolean::builder("inband-fec")
+                    .nick("In-band FEC")
+                    .blurb("Signal in-band forward error correction data (requires opusenc inband-fec=true)")
+                    .default_value(DEFAULT_INBAND_FEC)
+                    .mutable_ready()
+                    .build(),
+            ]
         });
 
         PROPERTIES.as_ref()
@@ -113,6 +138,10 @@ impl ObjectImpl for RtpOpusPay {
                 value.get().expect("type checked upstream"),
                 std::sync::atomic::Ordering::Relaxed,
             ),
+            "inband-fec" => self.settings.inband_fec.store(
+                value.get().expect("type checked upstream"),
+                std::sync::atomic::Ordering::Relaxed,
+            ),
             name => unimplemented!("Property '{name}'"),
         };
     }
@@ -124,6 +153,11 @@ impl ObjectImpl for RtpOpusPay {
                 .dtx
                 .load(std::sync::atomic::Ordering::Relaxed)
                 .to_value(),
+            "inband-fec" => self
+                .settings
+                .inband_fec
+                .load(std::sync::atomic::Ordering::Relaxed)
+                .to_value(),
             name => unimplemented!("Property '{name}'"),
         }
     }
@@ -275,6 +309,31 @@ impl RtpBasePay2Impl for RtpOpusPay {
             src_caps = src_caps.field("sprop-maxcapturerate", rate.to_string());
         }
 
+        // https://www.rfc-editor.org/rfc/rfc7587.html#section-6.1
+        //
+        // Like libwebrtc, enable DTX if the receiver asks for it
+        let peer_caps = self.obj().src_pad().peer_query_caps(None);
+        let peer_dtx = peer_caps
+            .structure(0)
+            .and_then(|s| s.get::<&str>("usedtx").ok())
+            .is_some_and(|v| v.trim() == "1");
+        if peer_dtx {
+            gst::debug!(CAT, imp = self, "Downstream requested DTX");
+        }
+        self.state.borrow_mut().peer_dtx = peer_dtx;
+
+        if self.settings.dtx.load(std::sync::atomic::Ordering::Relaxed) {
+            src_caps = src_caps.field("usedtx", "1");
+        }
+
+        if self
+            .settings
+            .inband_fec
+            .load(std::sync::atomic::Ordering::Relaxed)
+        {
+            src_caps = src_caps.field("useinbandfec", "1");
+        }
+
         self.obj().set_src_caps(&src_caps.build());
 
         true
@@ -299,14 +358,16 @@ impl RtpBasePay2Impl for RtpOpusPay {
 
         let data = map.as_slice();
 
-        let dtx = self.settings.dtx.load(std::sync::atomic::Ordering::Relaxed);
+        let dtx = self.settings.dtx.load(std::sync::atomic::Ordering::Relaxed) || state.peer_dtx;
 
         // Don't output DTX packets if discontinuous transmission was enabled (in encoder and here)
         // (Although seeing that it's opt-in in the encoder already one wonders whether we
         // shouldn't just do it automatically here)
         //
         // Even in DTX mode there will still be a non-DTX packet going through every 400ms.
-        if dtx && data.len() <= 2 {
+        let is_dtx = data.len() <= 2;
+
+        if dtx && is_dtx {
             gst::log!(
                 CAT,
                 imp = self,
@@ -321,6 +382,12 @@ impl RtpBasePay2Impl for RtpOpusPay {
 
         let marker_pending = state.marker_pending();
 
+        // DTX packets are still sent out if DTX was not enabled here, but the
+        // first non-DTX packet after them is still the start of a talkspurt
+        if is_dtx {
+            state.marker_pending = true;
+        }
+
         self.obj().queue_packet(
             id.into(),
             rtp_types::RtpPacketBuilder::new()
@@ -144,6 +144,91 @@ fn test_opus_pay_dtx() {
     }
 }
 
+// test_opus_pay_dtx_fec_signalling
+//
+// Make sure DTX and FEC are signalled in the caps, DTX is enabled if requested by downstream,
+// and the first packet after DTX silence has the marker bit set
+//
+#[test]
+fn test_opus_pay_dtx_fec_signalling() {
+    const OPUS_BUFFER_SILENCE: &[u8] = &[0xf8, 0xff, 0xfe];
+    const OPUS_BUFFER_SILENCE_DTX: &[u8] = &[0xf8];
+
+    init();
+
+    for (pay_props, peer_caps, expect_dtx_dropped) in [
+        ("", None, false),
+        ("dtx=true inband-fec=true", None, true),
+        ("", Some("application/x-rtp, usedtx=(string)1"), true),
+    ] {
+        eprintln!("Testing rtpopuspay2 {pay_props} with peer caps {peer_caps:?} ..");
+
+        let mut h = Harness::new_parse(&format!("rtpopuspay2 {pay_props}"));
+
+        if let Some(peer_caps) = peer_caps {
+            h.set_sink_caps_str(peer_caps);
+        }
+
+        h.set_src_caps(
+            gst::Caps::builder("audio/x-opus")
+                .field("rate", 48000i32)
+                .field("channels", 1i32)
+                .field("channel-mapping-family", 0i32)
+                .build(),
+        );
+
+        for (i, data) in [
+            OPUS_BUFFER_SILENCE,
+            OPUS_BUFFER_SILENCE_DTX,
+            OPUS_BUFFER_SILENCE,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            h.push(make_buffer(
+                data,
+                gst::ClockTime::from_mseconds(20 * i as u64),
+                gst::ClockTime::from_mseconds(20),
+                gst::BufferFlags::empty(),
+            ))
+            .expect("Got error flow when pushing buffer");
+        }
+
+        let output_caps = h
+            .sinkpad()
+            .expect("harness sinkpad")
+            .current_caps()
+            .expect("output caps");
+        let s = output_caps.structure(0).unwrap();
+
+        assert_eq!(
+            s.get::<&str>("usedtx").ok(),
+            expect_dtx_dropped.then_some("1")
+        );
+        assert_eq!(
+            s.get::<&str>("useinbandfec").ok(),
+            pay_props.contains("inband-fec=true").then_some("1")
+        );
+
+        let mut expected_packets = vec![(0, true), (960, false), (1920, true)];
+        if expect_dtx_dropped {
+            expected_packets.remove(1);
+        }
+
+        let mut first_rtp_time = None;
+        for (rtp_time, marker_bit) in expected_packets {
+            let buffer = h.pull().expect("Didn't get output buffer");
+            let map = buffer.map_readable().unwrap();
+            let packet = rtp_types::RtpPacket::parse(&map).unwrap();
+            let first_rtp_time = *first_rtp_time.get_or_insert(packet.timestamp());
+            assert_eq!(packet.timestamp().wrapping_sub(first_rtp_time), rtp_time);
+            assert_eq!(packet.marker_bit(), marker_bit);
+        }
+
+        assert_eq!(h.buffers_in_queue(), 0);
+    }
+}
+
 // test_opus_pay_depay
 //
 // Check basic payloading/depayloading
@@ -280,6 +365,50 @@ fn test_opus_depay_pay() {
     let _output_buffer = h.pull().expect("Didn't get output buffer");
 }
 
+// test_opus_depay_two_frames
+//
+// Check that the duration of packets with two frames of different size is parsed correctly
+//
+#[test]
+fn test_opus_depay_two_frames() {
+    init();
+
+    let mut h = Harness::new("rtpopusdepay2");
+
+    h.set_src_caps(
+        gst::Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("encoding-name", "OPUS")
+            .field("clock-rate", 48000i32)
+            .field("payload", 96i32)
+            .build(),
+    );
+
+    // SILK NB 20ms, code 2: two frames of 1 and 2 bytes
+    let data = rtp_types::RtpPacketBuilder::new()
+        .payload_type(96)
+        .sequence_number(1)
+        .timestamp(0)
+        .payload([0x0a, 0x01, 0xaa, 0xbb, 0xcc].as_slice())
+        .write_vec()
+        .unwrap();
+
+    let mut input_buffer = gst::Buffer::from_mut_slice(data);
+    input_buffer
+        .get_mut()
+        .unwrap()
+        .set_pts(gst::ClockTime::ZERO);
+
+    h.push(input_buffer)
+        .expect("Got error flow when pushing buffer");
+
+    let output_buffer = h.pull().expect("Didn't get output buffer");
+    assert_eq!(
+        output_buffer.duration(),
+        Some(gst::ClockTime::from_mseconds(40))
+    );
+}
+
 // test_opus_payloader_get_caps
 //
 // Check that a caps query on payloader sink pad reflects downstream RTP caps requirements.
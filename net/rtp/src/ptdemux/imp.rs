@@ -0,0 +1,429 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpptdemux2
+ * @see_also: rtprecv, rtpssrcdemux2
+ *
+ * Demultiplexes an RTP stream by payload type, without requiring a full `rtprecv`.
+ *
+ * A new source pad named `src_<pt>` is added for every payload type the first time a packet with
+ * that payload type arrives. The caps of each source pad are determined from, in this order:
+ *
+ *  * the `pt-map` property, in the same format as the `pt-map` property of `rtprecv` sessions,
+ *  * the caps returned from the `request-pt-map` signal, which are then added to the `pt-map`,
+ *  * the caps of the sink pad if they don't contain a different payload type,
+ *  * otherwise only the media type and payload type.
+ *
+ * Emitting the `clear-pt-map` action signal or changing the `pt-map` property causes the caps
+ * to be determined again for every payload type with the next packet.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 udpsrc port=5004 caps='application/x-rtp' ! rtpptdemux2 name=d \
+ *   d.src_96 ! queue ! rtpvp8depay2 ! vp8dec ! videoconvert ! autovideosink \
+ *   d.src_111 ! queue ! rtpopusdepay2 ! opusdec ! audioconvert ! autoaudiosink
+ * ]|
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpptdemux2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP payload type demuxer"),
+    )
+});
+
+struct PtPad {
+    pad: gst::Pad,
+    /// Caps that were configured on the pad, `None` if they have to be determined again.
+    caps: Option<gst::Caps>,
+}
+
+#[derive(Default)]
+struct State {
+    pads: HashMap<u8, PtPad>,
+}
+
+pub struct RtpPtDemux {
+    sinkpad: gst::Pad,
+    pt_map: Mutex<HashMap<u8, gst::Caps>>,
+    state: Mutex<State>,
+    flow_combiner: Mutex<gst_base::UniqueFlowCombiner>,
+}
+
+impl RtpPtDemux {
+    fn invalidate_caps(&self) {
+        let mut state = self.state.lock().unwrap();
+        for pt_pad in state.pads.values_mut() {
+            pt_pad.caps = None;
+        }
+    }
+
+    fn set_pt_map(&self, pt_map: Option<gst::Structure>) {
+        let mut map = HashMap::new();
+
+        for (key, value) in pt_map.iter().flat_map(|pt_map| pt_map.iter()) {
+            let Some(pt) = key.parse::<u8>().ok().filter(|pt| *pt < 128) else {
+                gst::warning!(CAT, imp = self, "failed to parse key {key} as a pt");
+                continue;
+            };
+            if let Ok(caps) = value.get::<gst::Caps>() {
+                map.insert(pt, caps);
+            } else {
+                gst::warning!(CAT, imp = self, "{pt} does not contain a caps value");
+            }
+        }
+
+        *self.pt_map.lock().unwrap() = map;
+        self.invalidate_caps();
+    }
+
+    fn pt_map(&self) -> gst::Structure {
+        let mut ret = gst::Structure::builder("application/x-rtp2-pt-map");
+
+        let pt_map = self.pt_map.lock().unwrap();
+        for (pt, caps) in pt_map.iter() {
+            ret = ret.field(pt.to_string(), caps);
+        }
+
+        ret.build()
+    }
+
+    fn caps_for_pt(&self, pt: u8) -> gst::Caps {
+        if let Some(caps) = self.pt_map.lock().unwrap().get(&pt) {
+            return caps.clone();
+        }
+
+        if let Some(caps) = self
+            .obj()
+            .emit_by_name::<Option<gst::Caps>>("request-pt-map", &[&(pt as u32)])
+        {
+            gst::debug!(CAT, imp = self, "Got caps {caps:?} for pt {pt} from signal");
+            self.pt_map.lock().unwrap().insert(pt, caps.clone());
+            return caps;
+        }
+
+        if let Some(mut caps) = self.sinkpad.current_caps().filter(|caps| {
+            caps.structure(0)
+                .is_some_and(|s| s.get::<i32>("payload").map_or(true, |p| p == pt as i32))
+        }) {
+            caps.make_mut().set("payload", pt as i32);
+            return caps;
+        }
+
+        gst::debug!(CAT, imp = self, "No caps known for pt {pt}");
+
+        gst::Caps::builder("application/x-rtp")
+            .field("payload", pt as i32)
+            .build()
+    }
+
+    fn create_pad(&self, pt: u8, caps: &gst::Caps) -> gst::Pad {
+        gst::debug!(CAT, imp = self, "Adding pad for pt {pt} with caps {caps:?}");
+
+        let templ = self.obj().pad_template("src_%u").unwrap();
+        let pad = gst::Pad::builder_from_template(&templ)
+            .name(format!("src_{pt}"))
+            .build();
+
+        pad.set_active(true).unwrap();
+
+        let stream_id = pad.create_stream_id(&*self.obj(), Some(&pt.to_string()));
+        let mut stored_caps = false;
+        let mut stored_stream_start = false;
+        self.sinkpad.sticky_events_foreach(|ev| {
+            if let gst::EventView::StreamStart(ev) = ev.view() {
+                let mut builder = gst::event::StreamStart::builder(&stream_id)
+                    .seqnum(ev.seqnum())
+                    .flags(ev.stream_flags());
+                if let Some(group_id) = ev.group_id() {
+                    builder = builder.group_id(group_id);
+                }
+                let _ = pad.store_sticky_event(&builder.build());
+                stored_stream_start = true;
+            } else if ev.type_() < gst::EventType::Caps {
+                let _ = pad.store_sticky_event(ev);
+            } else if ev.type_() > gst::EventType::Caps {
+                if !stored_caps {
+                    let _ = pad.store_sticky_event(&gst::event::Caps::new(caps));
+                    stored_caps = true;
+                }
+                let _ = pad.store_sticky_event(ev);
+            }
+
+            std::ops::ControlFlow::Continue(gst::EventForeachAction::Keep)
+        });
+
+        if !stored_stream_start {
+            let _ = pad.store_sticky_event(&gst::event::StreamStart::new(&stream_id));
+        }
+        if !stored_caps {
+            let _ = pad.store_sticky_event(&gst::event::Caps::new(caps));
+        }
+
+        pad
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let pt = {
+            let map = buffer.map_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+                gst::FlowError::Error
+            })?;
+
+            match rtp_types::RtpPacket::parse(&map) {
+                Ok(rtp) => rtp.payload_type(),
+                Err(err) => {
+                    gst::warning!(CAT, obj = pad, "Dropping invalid RTP packet: {err:?}");
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+            }
+        };
+
+        let existing = {
+            let state = self.state.lock().unwrap();
+            state
+                .pads
+                .get(&pt)
+                .map(|pt_pad| (pt_pad.pad.clone(), pt_pad.caps.is_none()))
+        };
+
+        let srcpad = match existing {
+            Some((srcpad, false)) => srcpad,
+            Some((srcpad, true)) => {
+                let caps = self.caps_for_pt(pt);
+                if srcpad.current_caps().as_ref() != Some(&caps) {
+                    gst::debug!(CAT, obj = srcpad, "Updating caps to {caps:?}");
+                    srcpad.push_event(gst::event::Caps::new(&caps));
+                }
+                if let Some(pt_pad) = self.state.lock().unwrap().pads.get_mut(&pt) {
+                    pt_pad.caps = Some(caps);
+                }
+                srcpad
+            }
+            None => {
+                let caps = self.caps_for_pt(pt);
+                let srcpad = self.create_pad(pt, &caps);
+
+                self.state.lock().unwrap().pads.insert(
+                    pt,
+                    PtPad {
+                        pad: srcpad.clone(),
+                        caps: Some(caps),
+                    },
+                );
+                self.flow_combiner.lock().unwrap().add_pad(&srcpad);
+                self.obj().add_pad(&srcpad).unwrap();
+
+                srcpad
+            }
+        };
+
+        let res = srcpad.push(buffer);
+        self.flow_combiner
+            .lock()
+            .unwrap()
+            .update_pad_flow(&srcpad, res)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        gst::log!(CAT, obj = pad, "Handling event {event:?}");
+
+        match event.view() {
+            // Every source pad has its own caps and stream id
+            gst::EventView::Caps(_) => {
+                self.invalidate_caps();
+                true
+            }
+            gst::EventView::StreamStart(_) => true,
+            gst::EventView::FlushStop(_) => {
+                self.flow_combiner.lock().unwrap().reset();
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+
+    fn remove_pads(&self) {
+        let pads = std::mem::take(&mut self.state.lock().unwrap().pads);
+        let mut flow_combiner = self.flow_combiner.lock().unwrap();
+        for pt_pad in pads.into_values() {
+            flow_combiner.remove_pad(&pt_pad.pad);
+            let _ = self.obj().remove_pad(&pt_pad.pad);
+        }
+        flow_combiner.reset();
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpPtDemux {
+    const NAME: &'static str = "GstRtpPtDemux2";
+    type Type = super::RtpPtDemux;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                RtpPtDemux::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                RtpPtDemux::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        Self {
+            sinkpad,
+            pt_map: Mutex::new(HashMap::new()),
+            state: Mutex::new(State::default()),
+            flow_combiner: Mutex::new(gst_base::UniqueFlowCombiner::new()),
+        }
+    }
+}
+
+impl ObjectImpl for RtpPtDemux {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![glib::ParamSpecBoxed::builder::<gst::Structure>("pt-map")
+                .nick("RTP Payload Type Map")
+                .blurb("Mapping of RTP payload type to caps")
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "pt-map" => self.set_pt_map(
+                value
+                    .get::<Option<gst::Structure>>()
+                    .expect("Type checked upstream"),
+            ),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "pt-map" => self.pt_map().to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: LazyLock<Vec<glib::subclass::Signal>> = LazyLock::new(|| {
+            vec![
+                glib::subclass::Signal::builder("request-pt-map")
+                    .param_types([u32::static_type()])
+                    .return_type::<gst::Caps>()
+                    .accumulator(|_hint, acc, val| {
+                        if matches!(val.get::<Option<gst::Caps>>(), Ok(Some(_))) {
+                            *acc = val.clone();
+                            false
+                        } else {
+                            true
+                        }
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("clear-pt-map")
+                    .action()
+                    .class_handler(|_token, args| {
+                        let demux = args[0].get::<super::RtpPtDemux>().unwrap();
+                        demux.imp().pt_map.lock().unwrap().clear();
+                        demux.imp().invalidate_caps();
+
+                        None
+                    })
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        self.obj().add_pad(&self.sinkpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for RtpPtDemux {}
+
+impl ElementImpl for RtpPtDemux {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP Payload Type Demuxer",
+                "Demux/Network/RTP",
+                "Demultiplexes RTP packets by their payload type",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst::Caps::builder("application/x-rtp").build();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src_%u",
+                gst::PadDirection::Src,
+                gst::PadPresence::Sometimes,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let res = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            self.remove_pads();
+        }
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,389 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpreddec2
+ * @see_also: rtpredenc2, rtprecv
+ *
+ * Decapsulates RED packets (RFC 2198). The primary block of each RED packet is output as a
+ * normal RTP packet, and the redundant blocks are output as the packets they were copied from
+ * unless these were already received.
+ *
+ * The sequence numbers of the redundant blocks are derived by assuming they protect the
+ * packets directly preceding the RED packet, as done by `rtpredenc2`. Packets with a payload
+ * type other than `pt` are forwarded unchanged.
+ *
+ * The element is meant to be placed before `rtprecv`, whose jitterbuffer orders the recovered
+ * packets and discards any remaining duplicates. Once the payload type of the primary blocks is
+ * known, caps for it are sent downstream so that `rtprecv` can look up its clock rate.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 udpsrc port=5004 caps="application/x-rtp,media=audio,clock-rate=48000,encoding-name=RED,payload=63" ! \
+ *     rtpreddec2 pt=63 ! rtprecv name=rtprecv rtp-id=example rtprecv.rtp_src_0_96_1234 ! ...
+ * ]|
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+use smallvec::SmallVec;
+
+use crate::red;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpreddec2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP RED Decoder"),
+    )
+});
+
+const DEFAULT_PT: u32 = 63;
+/// Number of received sequence numbers per SSRC remembered for detecting already received packets
+const RECEIVED_HISTORY_SIZE: usize = 64;
+/// Set on recovered packets so that the jitterbuffer counts them as recovered
+const RETRANSMISSION_FLAG: gst::BufferFlags =
+    gst::BufferFlags::from_bits_retain(gst_rtp::ffi::GST_RTP_BUFFER_FLAG_RETRANSMISSION);
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    pt: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { pt: DEFAULT_PT }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Recently received sequence numbers per SSRC
+    received: HashMap<u32, VecDeque<u16>>,
+    sink_caps: Option<gst::Caps>,
+    /// Payload type of the primary blocks the caps were last configured for
+    caps_pt: Option<u8>,
+}
+
+impl State {
+    /// Remembers `seqnum` of `ssrc` as received, returns `false` if it already was
+    fn mark_received(&mut self, ssrc: u32, seqnum: u16) -> bool {
+        let received = self.received.entry(ssrc).or_default();
+        if received.contains(&seqnum) {
+            return false;
+        }
+
+        if received.len() == RECEIVED_HISTORY_SIZE {
+            received.pop_front();
+        }
+        received.push_back(seqnum);
+
+        true
+    }
+}
+
+pub struct RtpRedDec {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl RtpRedDec {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let red_pt = self.settings.lock().unwrap().pt as u8;
+
+        let mut state = self.state.lock().unwrap();
+
+        let (caps, outbufs) = {
+            let map = buffer.map_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+                gst::FlowError::Error
+            })?;
+
+            let packet = match rtp_types::RtpPacket::parse(&map) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    gst::warning!(CAT, obj = pad, "Dropping invalid RTP packet: {err:?}");
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+            };
+
+            let ssrc = packet.ssrc();
+            let seqnum = packet.sequence_number();
+
+            if packet.payload_type() != red_pt {
+                state.mark_received(ssrc, seqnum);
+                (None, None)
+            } else {
+                let Some(blocks) = red::parse_payload(packet.payload()) else {
+                    gst::warning!(CAT, obj = pad, "Dropping invalid RED packet {seqnum}");
+                    return Ok(gst::FlowSuccess::Ok);
+                };
+
+                let primary_pt = blocks.last().unwrap().pt;
+                let caps = match state.sink_caps {
+                    Some(ref sink_caps) if state.caps_pt != Some(primary_pt) => {
+                        state.caps_pt = Some(primary_pt);
+
+                        let mut caps = sink_caps.clone();
+                        if let Some(s) = caps.make_mut().structure_mut(0) {
+                            s.set("payload", primary_pt as i32);
+                            s.remove_field("encoding-name");
+                        }
+                        Some(caps)
+                    }
+                    _ => None,
+                };
+
+                let header_len = packet.payload_offset();
+                // Header extensions describe the primary packet only
+                let recovered_header_len = 12 + 4 * packet.csrc().count();
+                let n_redundant = blocks.len() - 1;
+                let mut outbufs = SmallVec::<[gst::Buffer; 4]>::new();
+                for (i, block) in blocks.iter().enumerate() {
+                    let block_seqnum = seqnum.wrapping_sub((n_redundant - i) as u16);
+                    let is_primary = i == n_redundant;
+
+                    if !state.mark_received(ssrc, block_seqnum) && !is_primary {
+                        gst::trace!(
+                            CAT,
+                            obj = pad,
+                            "Skipping redundant block for already received packet {block_seqnum}"
+                        );
+                        continue;
+                    }
+                    if !is_primary {
+                        gst::debug!(
+                            CAT,
+                            obj = pad,
+                            "Recovered packet {block_seqnum} from RED packet {seqnum}"
+                        );
+                    }
+
+                    let header_len = if is_primary {
+                        header_len
+                    } else {
+                        recovered_header_len
+                    };
+                    let mut data = Vec::with_capacity(header_len + block.data.len());
+                    data.extend_from_slice(&map[..header_len]);
+                    data[0] &= !0x20;
+                    data[1] = (data[1] & 0x80) | block.pt;
+                    if !is_primary {
+                        // The extension and marker bits belong to the primary packet only
+                        data[0] &= !0x10;
+                        data[1] &= !0x80;
+                    }
+                    data[2..4].copy_from_slice(&block_seqnum.to_be_bytes());
+                    data[4..8].copy_from_slice(
+                        &packet
+                            .timestamp()
+                            .wrapping_sub(block.timestamp_offset)
+                            .to_be_bytes(),
+                    );
+                    data.extend_from_slice(block.data);
+
+                    let mut outbuf = gst::Buffer::from_mut_slice(data);
+                    {
+                        let outbuf = outbuf.get_mut().unwrap();
+                        let _ = buffer.copy_into(outbuf, gst::BufferCopyFlags::METADATA, ..);
+                        if !is_primary {
+                            outbuf.set_flags(RETRANSMISSION_FLAG);
+                        }
+                    }
+                    outbufs.push(outbuf);
+                }
+
+                (caps, Some(outbufs))
+            }
+        };
+        drop(state);
+
+        if let Some(caps) = caps {
+            gst::debug!(CAT, obj = pad, "Configuring caps {caps:?}");
+            self.srcpad.push_event(gst::event::Caps::new(&caps));
+        }
+
+        let Some(outbufs) = outbufs else {
+            return self.srcpad.push(buffer);
+        };
+
+        for outbuf in outbufs {
+            self.srcpad.push(outbuf)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        gst::log!(CAT, obj = pad, "Handling event {event:?}");
+
+        match event.view() {
+            gst::EventView::Caps(caps) => {
+                let mut state = self.state.lock().unwrap();
+                state.sink_caps = Some(caps.caps_owned());
+                state.caps_pt = None;
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            gst::EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.received.clear();
+                drop(state);
+
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpRedDec {
+    const NAME: &'static str = "GstRtpRedDec2";
+    type Type = super::RtpRedDec;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                RtpRedDec::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                RtpRedDec::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ).build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Default::default(),
+            state: Default::default(),
+        }
+    }
+}
+
+impl ObjectImpl for RtpRedDec {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![glib::ParamSpecUInt::builder("pt")
+                .nick("Payload Type")
+                .blurb("Payload type of the RED packets")
+                .maximum(127)
+                .default_value(DEFAULT_PT)
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "pt" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.pt = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "pt" => {
+                let settings = self.settings.lock().unwrap();
+                settings.pt.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for RtpRedDec {}
+
+impl ElementImpl for RtpRedDec {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP RED Decoder",
+                "Codec/Depayloader/Network/RTP",
+                "Decapsulates RED packets (RFC 2198) and recovers lost packets from redundant blocks",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst::Caps::new_empty_simple("application/x-rtp");
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let ret = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        Ok(ret)
+    }
+}
@@ -0,0 +1,414 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpredenc2
+ * @see_also: rtpreddec2, rtpsend
+ *
+ * Encapsulates RTP packets into RED packets (RFC 2198), adding the payloads of up to `distance`
+ * previous packets as redundant blocks so that the receiver can recover lost packets without
+ * retransmissions.
+ *
+ * The RED packets keep the SSRC, sequence number and timestamp of the original packet and use
+ * the payload type configured with the `pt` property. The output caps are updated accordingly,
+ * which allows `rtpsend` to look up the clock rate of the RED payload type.
+ *
+ * Only the payloads of the directly preceding packets are added as redundant blocks, so that
+ * `rtpreddec2` can derive their sequence numbers. If `distance` is 0, packets are forwarded
+ * unchanged.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 audiotestsrc ! opusenc ! rtpopuspay2 pt=96 ! rtpredenc2 pt=63 distance=2 ! \
+ *     rtpsend name=rtpsend rtp-id=example rtpsend.rtp_src_0 ! udpsink port=5004
+ * ]|
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::collections::VecDeque;
+use std::sync::{LazyLock, Mutex};
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+use smallvec::SmallVec;
+
+use crate::red::{self, Block, MAX_BLOCK_LENGTH, MAX_TIMESTAMP_OFFSET};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpredenc2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP RED Encoder"),
+    )
+});
+
+const DEFAULT_PT: u32 = 63;
+const DEFAULT_DISTANCE: u32 = 0;
+const MAX_DISTANCE: u32 = 10;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    pt: u32,
+    distance: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            pt: DEFAULT_PT,
+            distance: DEFAULT_DISTANCE,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HistoryEntry {
+    seqnum: u16,
+    timestamp: u32,
+    pt: u8,
+    payload: Vec<u8>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    history: VecDeque<HistoryEntry>,
+    sink_caps: Option<gst::Caps>,
+    /// Whether the caps configured downstream are for RED packets
+    red_caps: Option<bool>,
+}
+
+pub struct RtpRedEnc {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl RtpRedEnc {
+    fn src_caps(sink_caps: &gst::Caps, settings: &Settings) -> gst::Caps {
+        if settings.distance == 0 {
+            return sink_caps.clone();
+        }
+
+        let mut caps = sink_caps.clone();
+        if let Some(s) = caps.make_mut().structure_mut(0) {
+            s.set("payload", settings.pt as i32);
+            s.set("encoding-name", "RED");
+        }
+
+        caps
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+        let red = settings.distance > 0;
+
+        let mut state = self.state.lock().unwrap();
+
+        // The distance changed between 0 and non-0 since the caps were configured
+        if state.red_caps.is_some_and(|red_caps| red_caps != red) {
+            let caps = Self::src_caps(state.sink_caps.as_ref().unwrap(), &settings);
+            state.red_caps = Some(red);
+            drop(state);
+
+            gst::debug!(CAT, obj = pad, "Updating caps to {caps:?}");
+            self.srcpad.push_event(gst::event::Caps::new(&caps));
+
+            state = self.state.lock().unwrap();
+        }
+
+        let data = {
+            let map = buffer.map_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+                gst::FlowError::Error
+            })?;
+
+            let packet = match rtp_types::RtpPacket::parse(&map) {
+                Ok(packet) => packet,
+                Err(err) => {
+                    gst::warning!(CAT, obj = pad, "Dropping invalid RTP packet: {err:?}");
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+            };
+
+            if red {
+                let seqnum = packet.sequence_number();
+                let timestamp = packet.timestamp();
+
+                // Stop at the first packet that is not directly preceding or that can't be
+                // described by a block header
+                let n_redundant = state
+                    .history
+                    .iter()
+                    .rev()
+                    .enumerate()
+                    .take_while(|(i, entry)| {
+                        entry.seqnum == seqnum.wrapping_sub(*i as u16 + 1)
+                            && timestamp.wrapping_sub(entry.timestamp) <= MAX_TIMESTAMP_OFFSET
+                            && entry.payload.len() <= MAX_BLOCK_LENGTH
+                    })
+                    .count();
+
+                let redundant = state
+                    .history
+                    .iter()
+                    .skip(state.history.len() - n_redundant)
+                    .map(|entry| Block {
+                        pt: entry.pt,
+                        timestamp_offset: timestamp.wrapping_sub(entry.timestamp),
+                        data: &entry.payload,
+                    })
+                    .collect::<SmallVec<[Block; 4]>>();
+                let primary = Block {
+                    pt: packet.payload_type(),
+                    timestamp_offset: 0,
+                    data: packet.payload(),
+                };
+
+                gst::trace!(
+                    CAT,
+                    obj = pad,
+                    "Encapsulating packet {seqnum} with {n_redundant} redundant blocks"
+                );
+
+                let header_len = packet.payload_offset();
+                let mut data = Vec::with_capacity(
+                    header_len
+                        + 1
+                        + primary.data.len()
+                        + redundant
+                            .iter()
+                            .map(|block| 4 + block.data.len())
+                            .sum::<usize>(),
+                );
+                data.extend_from_slice(&map[..header_len]);
+                // Any padding is removed together with the original payload
+                data[0] &= !0x20;
+                data[1] = (data[1] & 0x80) | settings.pt as u8;
+                red::write_payload(&redundant, &primary, &mut data);
+
+                state.history.push_back(HistoryEntry {
+                    seqnum,
+                    timestamp,
+                    pt: primary.pt,
+                    payload: primary.data.to_vec(),
+                });
+                while state.history.len() > settings.distance as usize {
+                    state.history.pop_front();
+                }
+
+                Some(data)
+            } else {
+                state.history.clear();
+
+                None
+            }
+        };
+        drop(state);
+
+        let Some(data) = data else {
+            return self.srcpad.push(buffer);
+        };
+
+        let mut outbuf = gst::Buffer::from_mut_slice(data);
+        let _ = buffer.copy_into(
+            outbuf.get_mut().unwrap(),
+            gst::BufferCopyFlags::METADATA,
+            ..,
+        );
+
+        self.srcpad.push(outbuf)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        gst::log!(CAT, obj = pad, "Handling event {event:?}");
+
+        match event.view() {
+            gst::EventView::Caps(caps) => {
+                let settings = *self.settings.lock().unwrap();
+                let sink_caps = caps.caps_owned();
+                let src_caps = Self::src_caps(&sink_caps, &settings);
+
+                let mut state = self.state.lock().unwrap();
+                state.sink_caps = Some(sink_caps);
+                state.red_caps = Some(settings.distance > 0);
+                drop(state);
+
+                self.srcpad.push_event(
+                    gst::event::Caps::builder(&src_caps)
+                        .seqnum(event.seqnum())
+                        .build(),
+                )
+            }
+            gst::EventView::FlushStop(_) => {
+                self.state.lock().unwrap().history.clear();
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpRedEnc {
+    const NAME: &'static str = "GstRtpRedEnc2";
+    type Type = super::RtpRedEnc;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                RtpRedEnc::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                RtpRedEnc::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ).build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Default::default(),
+            state: Default::default(),
+        }
+    }
+}
+
+impl ObjectImpl for RtpRedEnc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecUInt::builder("pt")
+                    .nick("Payload Type")
+                    .blurb("Payload type of the RED packets")
+                    .maximum(127)
+                    .default_value(DEFAULT_PT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("distance")
+                    .nick("Distance")
+                    .blurb(
+                        "Number of previous packets added as redundant blocks \
+                        (0 = forward packets unchanged)",
+                    )
+                    .maximum(MAX_DISTANCE)
+                    .default_value(DEFAULT_DISTANCE)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "pt" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.pt = value.get().expect("type checked upstream");
+            }
+            "distance" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.distance = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "pt" => {
+                let settings = self.settings.lock().unwrap();
+                settings.pt.to_value()
+            }
+            "distance" => {
+                let settings = self.settings.lock().unwrap();
+                settings.distance.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for RtpRedEnc {}
+
+impl ElementImpl for RtpRedEnc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP RED Encoder",
+                "Codec/Payloader/Network/RTP",
+                "Encapsulates RTP packets into RED packets (RFC 2198) with redundant blocks",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst::Caps::new_empty_simple("application/x-rtp");
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let ret = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        Ok(ret)
+    }
+}
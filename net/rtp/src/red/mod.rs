@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use smallvec::SmallVec;
+
+pub mod dec;
+pub mod enc;
+
+#[cfg(test)]
+mod tests;
+
+/// Largest timestamp offset a redundant block header can signal (14 bits)
+pub(crate) const MAX_TIMESTAMP_OFFSET: u32 = (1 << 14) - 1;
+/// Largest length a redundant block header can signal (10 bits)
+pub(crate) const MAX_BLOCK_LENGTH: usize = (1 << 10) - 1;
+
+/// A block of a RED payload, see RFC 2198 section 3
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Block<'a> {
+    pub pt: u8,
+    /// Offset of the block's timestamp before the timestamp of the RTP packet, always 0 for
+    /// the primary block
+    pub timestamp_offset: u32,
+    pub data: &'a [u8],
+}
+
+/// Splits a RED payload into its redundant blocks followed by the primary block.
+///
+/// Returns `None` if the payload is truncated.
+pub(crate) fn parse_payload(payload: &[u8]) -> Option<SmallVec<[Block<'_>; 4]>> {
+    let mut headers = SmallVec::<[(u8, u32, usize); 4]>::new();
+    let mut offset = 0;
+
+    loop {
+        let first = *payload.get(offset)?;
+        let pt = first & 0x7f;
+
+        if first & 0x80 == 0 {
+            offset += 1;
+            break;
+        }
+
+        let header = payload.get(offset..offset + 4)?;
+        let timestamp_offset = (u32::from(header[1]) << 6) | (u32::from(header[2]) >> 2);
+        let length = (usize::from(header[2] & 0x03) << 8) | usize::from(header[3]);
+        headers.push((pt, timestamp_offset, length));
+        offset += 4;
+    }
+    let primary_pt = payload[offset - 1] & 0x7f;
+
+    let mut blocks = SmallVec::new();
+    for (pt, timestamp_offset, length) in headers {
+        blocks.push(Block {
+            pt,
+            timestamp_offset,
+            data: payload.get(offset..offset + length)?,
+        });
+        offset += length;
+    }
+    blocks.push(Block {
+        pt: primary_pt,
+        timestamp_offset: 0,
+        data: &payload[offset..],
+    });
+
+    Some(blocks)
+}
+
+/// Writes a RED payload containing the redundant blocks followed by the primary block.
+///
+/// The redundant blocks must fit into the header fields, see [`MAX_TIMESTAMP_OFFSET`] and
+/// [`MAX_BLOCK_LENGTH`].
+pub(crate) fn write_payload(redundant: &[Block], primary: &Block, out: &mut Vec<u8>) {
+    for block in redundant {
+        debug_assert!(block.timestamp_offset <= MAX_TIMESTAMP_OFFSET);
+        debug_assert!(block.data.len() <= MAX_BLOCK_LENGTH);
+
+        let length = block.data.len();
+        out.extend_from_slice(&[
+            0x80 | block.pt,
+            (block.timestamp_offset >> 6) as u8,
+            ((block.timestamp_offset & 0x3f) << 2) as u8 | (length >> 8) as u8,
+            (length & 0xff) as u8,
+        ]);
+    }
+    out.push(primary.pt & 0x7f);
+
+    for block in redundant {
+        out.extend_from_slice(block.data);
+    }
+    out.extend_from_slice(primary.data);
+}
@@ -0,0 +1,255 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use gst_check::Harness;
+
+use super::*;
+
+fn init() {
+    crate::test_init();
+}
+
+fn rtp_packet(seqnum: u16, timestamp: u32, payload: &[u8]) -> gst::Buffer {
+    let data = rtp_types::RtpPacketBuilder::new()
+        .payload_type(96)
+        .ssrc(0x1234)
+        .sequence_number(seqnum)
+        .timestamp(timestamp)
+        .payload(payload)
+        .write_vec()
+        .unwrap();
+
+    gst::Buffer::from_mut_slice(data)
+}
+
+fn caps(pt: i32, encoding_name: &str) -> gst::Caps {
+    gst::Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("clock-rate", 48000i32)
+        .field("payload", pt)
+        .field("encoding-name", encoding_name)
+        .build()
+}
+
+#[test]
+fn payload_roundtrip() {
+    let redundant = [
+        Block {
+            pt: 96,
+            timestamp_offset: 1920,
+            data: &[1; 10],
+        },
+        Block {
+            pt: 97,
+            timestamp_offset: MAX_TIMESTAMP_OFFSET,
+            data: &[2; MAX_BLOCK_LENGTH],
+        },
+    ];
+    let primary = Block {
+        pt: 96,
+        timestamp_offset: 0,
+        data: &[3; 20],
+    };
+
+    let mut payload = Vec::new();
+    write_payload(&redundant, &primary, &mut payload);
+    assert_eq!(payload.len(), 2 * 4 + 1 + 10 + MAX_BLOCK_LENGTH + 20);
+
+    let blocks = parse_payload(&payload).unwrap();
+    assert_eq!(blocks.as_slice(), &[redundant[0], redundant[1], primary]);
+
+    // Only the primary block header
+    let blocks = parse_payload(&[96, 3, 3]).unwrap();
+    assert_eq!(
+        blocks.as_slice(),
+        &[Block {
+            pt: 96,
+            timestamp_offset: 0,
+            data: &[3, 3],
+        }]
+    );
+}
+
+#[test]
+fn payload_truncated() {
+    assert!(parse_payload(&[]).is_none());
+    // Incomplete redundant block header
+    assert!(parse_payload(&[0x80 | 96, 0, 0]).is_none());
+    // Redundant block longer than the payload
+    assert!(parse_payload(&[0x80 | 96, 0, 0, 10, 96, 1, 2, 3]).is_none());
+}
+
+#[test]
+fn encode_decode() {
+    init();
+
+    let mut enc = Harness::new("rtpredenc2");
+    enc.element().unwrap().set_property("distance", 2u32);
+    enc.play();
+    enc.set_src_caps(caps(96, "OPUS"));
+
+    let sink_caps = enc.sinkpad().unwrap().current_caps().unwrap();
+    let s = sink_caps.structure(0).unwrap();
+    assert_eq!(s.get::<i32>("payload").unwrap(), 63);
+    assert_eq!(s.get::<&str>("encoding-name").unwrap(), "RED");
+    assert_eq!(s.get::<i32>("clock-rate").unwrap(), 48000);
+
+    let mut red_packets = Vec::new();
+    for seqnum in 0..4u16 {
+        enc.push(rtp_packet(seqnum, seqnum as u32 * 960, &[seqnum as u8; 10]))
+            .unwrap();
+
+        let buffer = enc.pull().unwrap();
+        {
+            let map = buffer.map_readable().unwrap();
+            let packet = rtp_types::RtpPacket::parse(&map).unwrap();
+            assert_eq!(packet.payload_type(), 63);
+            assert_eq!(packet.sequence_number(), seqnum);
+            assert_eq!(packet.timestamp(), seqnum as u32 * 960);
+
+            let blocks = parse_payload(packet.payload()).unwrap();
+            assert_eq!(blocks.len(), 1 + seqnum.min(2) as usize);
+            for (i, block) in blocks.iter().rev().enumerate() {
+                assert_eq!(block.pt, 96);
+                assert_eq!(block.timestamp_offset, i as u32 * 960);
+                assert_eq!(block.data, &[seqnum as u8 - i as u8; 10]);
+            }
+        }
+        red_packets.push(buffer);
+    }
+
+    let mut dec = Harness::new("rtpreddec2");
+    dec.play();
+    dec.set_src_caps(caps(63, "RED"));
+
+    // Lose packets 1 and 2, which are recovered from packet 3
+    dec.push(red_packets[0].clone()).unwrap();
+    dec.push(red_packets[3].clone()).unwrap();
+
+    let src_caps = dec.sinkpad().unwrap().current_caps().unwrap();
+    let s = src_caps.structure(0).unwrap();
+    assert_eq!(s.get::<i32>("payload").unwrap(), 96);
+    assert!(!s.has_field("encoding-name"));
+
+    for seqnum in 0..4u16 {
+        let buffer = dec.pull().unwrap();
+        let map = buffer.map_readable().unwrap();
+        let packet = rtp_types::RtpPacket::parse(&map).unwrap();
+        assert_eq!(packet.payload_type(), 96);
+        assert_eq!(packet.ssrc(), 0x1234);
+        assert_eq!(packet.sequence_number(), seqnum);
+        assert_eq!(packet.timestamp(), seqnum as u32 * 960);
+        assert_eq!(packet.payload(), &[seqnum as u8; 10]);
+        // Recovered packets are flagged like retransmissions
+        assert_eq!(
+            buffer.flags().bits() & gst_rtp::ffi::GST_RTP_BUFFER_FLAG_RETRANSMISSION != 0,
+            seqnum == 1 || seqnum == 2
+        );
+    }
+
+    // Redundant blocks of already received packets are not output again
+    dec.push(red_packets[2].clone()).unwrap();
+    let buffer = dec.pull().unwrap();
+    let map = buffer.map_readable().unwrap();
+    let packet = rtp_types::RtpPacket::parse(&map).unwrap();
+    assert_eq!(packet.sequence_number(), 2);
+    assert!(dec.try_pull().is_none());
+}
+
+/// RED packet with sequence number `seqnum` that carries the payload of the previous packet
+/// as redundant block.
+fn red_packet(ssrc: u32, seqnum: u16, extension: Option<&[u8]>) -> gst::Buffer {
+    let mut payload = Vec::new();
+    write_payload(
+        &[Block {
+            pt: 96,
+            timestamp_offset: 960,
+            data: &[seqnum as u8 - 1; 10],
+        }],
+        &Block {
+            pt: 96,
+            timestamp_offset: 0,
+            data: &[seqnum as u8; 10],
+        },
+        &mut payload,
+    );
+
+    let mut builder = rtp_types::RtpPacketBuilder::new()
+        .payload_type(63)
+        .ssrc(ssrc)
+        .sequence_number(seqnum)
+        .timestamp(seqnum as u32 * 960)
+        .marker_bit(true)
+        .payload(payload.as_slice());
+    if let Some(extension) = extension {
+        builder = builder.extension(0xbede, extension);
+    }
+
+    gst::Buffer::from_mut_slice(builder.write_vec().unwrap())
+}
+
+#[test]
+fn decode_strips_extensions() {
+    init();
+
+    let mut dec = Harness::new("rtpreddec2");
+    dec.play();
+    dec.set_src_caps(caps(63, "RED"));
+
+    dec.push(red_packet(0x1234, 1, Some(&[0x10, 0xaa, 0, 0])))
+        .unwrap();
+
+    // The recovered packet has neither the header extension nor the marker bit of the primary
+    // packet
+    let buffer = dec.pull().unwrap();
+    let map = buffer.map_readable().unwrap();
+    let packet = rtp_types::RtpPacket::parse(&map).unwrap();
+    assert_eq!(packet.sequence_number(), 0);
+    assert!(packet.extension().is_none());
+    assert!(!packet.marker_bit());
+    assert_eq!(packet.payload(), &[0; 10]);
+
+    let buffer = dec.pull().unwrap();
+    let map = buffer.map_readable().unwrap();
+    let packet = rtp_types::RtpPacket::parse(&map).unwrap();
+    assert_eq!(packet.sequence_number(), 1);
+    assert_eq!(
+        packet.extension(),
+        Some((0xbede, [0x10, 0xaa, 0, 0].as_slice()))
+    );
+    assert!(packet.marker_bit());
+    assert_eq!(packet.payload(), &[1; 10]);
+}
+
+#[test]
+fn decode_multiple_ssrcs() {
+    init();
+
+    let mut dec = Harness::new("rtpreddec2");
+    dec.play();
+    dec.set_src_caps(caps(63, "RED"));
+
+    // Packets of both SSRCs are interleaved
+    dec.push(red_packet(0x1234, 1, None)).unwrap();
+    dec.push(red_packet(0x5678, 1, None)).unwrap();
+    dec.push(red_packet(0x1234, 2, None)).unwrap();
+    dec.push(red_packet(0x5678, 2, None)).unwrap();
+
+    // Only the first packet of each SSRC recovers its previous packet
+    let mut outputs = Vec::new();
+    while let Some(buffer) = dec.try_pull() {
+        let map = buffer.map_readable().unwrap();
+        let packet = rtp_types::RtpPacket::parse(&map).unwrap();
+        outputs.push((packet.ssrc(), packet.sequence_number()));
+    }
+    assert_eq!(
+        outputs,
+        [
+            (0x1234, 0),
+            (0x1234, 1),
+            (0x5678, 0),
+            (0x5678, 1),
+            (0x1234, 2),
+            (0x5678, 2)
+        ]
+    );
+}
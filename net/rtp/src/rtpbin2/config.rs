@@ -3,10 +3,22 @@
 use gst::glib;
 use gst::prelude::*;
 use gst::subclass::prelude::*;
+use rtcp_types::SdesItem;
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
 use std::sync::LazyLock;
 use std::sync::{Mutex, Weak};
+use std::time::{Duration, Instant};
 
+use crate::rtpbin2::hdrext;
 use crate::rtpbin2::internal::SharedSessionInner;
+use crate::rtpbin2::rtpsend::Profile;
+use crate::rtpbin2::session::{
+    KeyUnitRequestType, RequestRemoteKeyUnitReply, RtcpBandwidth, RtpProfile, SourceTimeout,
+    DEFAULT_BYE_REASON, DEFAULT_RTCP_FRACTION, RTCP_MIN_REPORT_INTERVAL,
+    RTCP_SENDER_TIMEOUT_N_INTERVALS, RTCP_SOURCE_TIMEOUT_N_INTERVALS,
+};
+use crate::rtpbin2::twcc;
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -27,6 +39,47 @@ impl Rtp2Session {
         imp.set_session(weak_session);
         ret
     }
+
+    /// Enable or disable RTP header extensions according to the `extmap-N` fields of the caps in
+    /// the payload type map of the session
+    pub(crate) fn update_extensions(&self) {
+        self.imp().update_extensions();
+    }
+}
+
+/// Field names of the SDES items in the `sdes` structure
+const SDES_FIELDS: [(&str, u8); 7] = [
+    ("cname", SdesItem::CNAME),
+    ("name", SdesItem::NAME),
+    ("email", SdesItem::EMAIL),
+    ("phone", SdesItem::PHONE),
+    ("location", SdesItem::LOC),
+    ("tool", SdesItem::TOOL),
+    ("note", SdesItem::NOTE),
+];
+
+/// Convert an `application/x-rtp-source-sdes` structure to SDES items
+pub(crate) fn sdes_from_structure(s: &gst::StructureRef) -> HashMap<u8, String> {
+    SDES_FIELDS
+        .iter()
+        .filter_map(|(field, item)| {
+            s.get_optional::<String>(field)
+                .ok()
+                .flatten()
+                .map(|value| (*item, value))
+        })
+        .collect()
+}
+
+/// Convert SDES items to an `application/x-rtp-source-sdes` structure
+pub(crate) fn sdes_to_structure(sdes: &HashMap<u8, String>) -> gst::Structure {
+    let mut ret = gst::Structure::builder("application/x-rtp-source-sdes");
+    for (field, item) in SDES_FIELDS {
+        if let Some(value) = sdes.get(&item) {
+            ret = ret.field(field, value);
+        }
+    }
+    ret.build()
 }
 
 mod imp {
@@ -34,13 +87,61 @@ mod imp {
 
     use super::*;
 
+    const DEFAULT_AUTO_HEADER_EXTENSIONS: bool = true;
+    const DEFAULT_SENDER_TIMEOUT_INTERVALS: u32 = RTCP_SENDER_TIMEOUT_N_INTERVALS;
+    const DEFAULT_MEMBER_TIMEOUT_INTERVALS: u32 = RTCP_SOURCE_TIMEOUT_N_INTERVALS;
+
+    /// Source timeout as configured through the properties. An absolute timeout takes
+    /// precedence over the number of RTCP intervals.
+    #[derive(Debug, Clone, Copy)]
+    struct TimeoutSettings {
+        intervals: u32,
+        duration: Option<Duration>,
+    }
+
+    impl TimeoutSettings {
+        fn new(intervals: u32) -> Self {
+            Self {
+                intervals,
+                duration: None,
+            }
+        }
+
+        fn source_timeout(&self) -> SourceTimeout {
+            match self.duration {
+                Some(duration) => SourceTimeout::Duration(duration),
+                None => SourceTimeout::Intervals(self.intervals),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    struct Settings {
+        auto_header_extensions: bool,
+        sender_timeout: TimeoutSettings,
+        member_timeout: TimeoutSettings,
+    }
+
+    impl Default for Settings {
+        fn default() -> Self {
+            Self {
+                auto_header_extensions: DEFAULT_AUTO_HEADER_EXTENSIONS,
+                sender_timeout: TimeoutSettings::new(DEFAULT_SENDER_TIMEOUT_INTERVALS),
+                member_timeout: TimeoutSettings::new(DEFAULT_MEMBER_TIMEOUT_INTERVALS),
+            }
+        }
+    }
+
     #[derive(Debug, Default)]
     struct State {
         pub(super) weak_session: Option<Weak<Mutex<SharedSessionInner>>>,
+        // Count of the FIRs requested with the request-key-unit signal
+        fir_count: u32,
     }
 
     #[derive(Debug, Default)]
     pub struct Rtp2Session {
+        settings: Mutex<Settings>,
         state: Mutex<State>,
     }
 
@@ -65,11 +166,8 @@ mod imp {
             };
             let mut session = session.lock().unwrap();
             session.clear_pt_map();
-            let Some(pt_map) = pt_map else {
-                return;
-            };
 
-            for (key, value) in pt_map.iter() {
+            for (key, value) in pt_map.iter().flat_map(|pt_map| pt_map.iter()) {
                 let Ok(pt) = key.parse::<u8>() else {
                     gst::warning!(CAT, "failed to parse key as a pt");
                     continue;
@@ -81,6 +179,9 @@ mod imp {
                     continue;
                 }
             }
+            drop(session);
+
+            self.update_extensions();
         }
 
         pub fn pt_map(&self) -> gst::Structure {
@@ -102,6 +203,563 @@ mod imp {
             let session = session.lock().unwrap();
             Some(session.stats())
         }
+
+        fn twcc_stats(&self) -> Option<gst::Structure> {
+            let session = self.session()?;
+            let session = session.lock().unwrap();
+            session.session.twcc_stats().map(gst::Structure::from)
+        }
+
+        fn extensions(&self) -> gst::Array {
+            let Some(session) = self.session() else {
+                return gst::Array::new(Vec::<gst_rtp::RTPHeaderExtension>::new());
+            };
+            let session = session.lock().unwrap();
+            gst::Array::new(session.extensions.values())
+        }
+
+        fn add_extension(&self, ext: &gst_rtp::RTPHeaderExtension) {
+            if ext.id() == 0 || ext.id() > 255 {
+                gst::warning!(CAT, imp = self, "Invalid extension ID {}", ext.id());
+                return;
+            }
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.extensions.insert(ext.id() as u8, ext.clone());
+            drop(session);
+
+            self.obj().notify("extensions");
+        }
+
+        fn min_rtcp_interval(&self) -> Duration {
+            let Some(session) = self.session() else {
+                return RTCP_MIN_REPORT_INTERVAL;
+            };
+            let session = session.lock().unwrap();
+            session.session.min_rtcp_interval()
+        }
+
+        fn set_min_rtcp_interval(&self, min_rtcp_interval: Duration) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.session.set_min_rtcp_interval(min_rtcp_interval);
+        }
+
+        fn rtcp_bandwidth(&self) -> RtcpBandwidth {
+            let Some(session) = self.session() else {
+                return RtcpBandwidth::default();
+            };
+            let session = session.lock().unwrap();
+            session.session.rtcp_bandwidth()
+        }
+
+        fn update_rtcp_bandwidth(&self, f: impl FnOnce(&mut RtcpBandwidth)) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            let mut bandwidth = session.session.rtcp_bandwidth();
+            f(&mut bandwidth);
+            session.session.set_rtcp_bandwidth(bandwidth);
+        }
+
+        fn profile(&self) -> Profile {
+            let Some(session) = self.session() else {
+                return Profile::default();
+            };
+            let session = session.lock().unwrap();
+            session.session.profile().into()
+        }
+
+        fn set_profile(&self, profile: Profile) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.session.set_profile(profile.into());
+        }
+
+        fn rtcp_xr_rrtr(&self) -> bool {
+            let Some(session) = self.session() else {
+                return false;
+            };
+            let session = session.lock().unwrap();
+            session.session.rtcp_xr_rrtr()
+        }
+
+        fn set_rtcp_xr_rrtr(&self, rtcp_xr_rrtr: bool) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.session.set_rtcp_xr_rrtr(rtcp_xr_rrtr);
+        }
+
+        fn separate_sources_by_address(&self) -> bool {
+            let Some(session) = self.session() else {
+                return false;
+            };
+            let session = session.lock().unwrap();
+            session.session.separate_sources_by_address()
+        }
+
+        fn set_separate_sources_by_address(&self, separate_sources_by_address: bool) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session
+                .session
+                .set_separate_sources_by_address(separate_sources_by_address);
+        }
+
+        fn feedback_target(&self) -> Option<String> {
+            let session = self.session()?;
+            let session = session.lock().unwrap();
+            session
+                .session
+                .feedback_target()
+                .map(|addr| addr.to_string())
+        }
+
+        fn set_feedback_target(&self, feedback_target: Option<&str>) {
+            let feedback_target = match feedback_target.filter(|s| !s.is_empty()) {
+                Some(s) => match s.parse::<SocketAddr>() {
+                    Ok(addr) => Some(addr),
+                    Err(err) => {
+                        gst::warning!(CAT, imp = self, "Invalid feedback target {s}: {err}");
+                        return;
+                    }
+                },
+                None => None,
+            };
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.session.set_feedback_target(feedback_target);
+        }
+
+        fn ts_offset(&self) -> i64 {
+            let Some(session) = self.session() else {
+                return 0;
+            };
+            let session = session.lock().unwrap();
+            session.ts_offset
+        }
+
+        fn set_ts_offset(&self, ts_offset: i64) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.ts_offset = ts_offset;
+        }
+
+        fn forward(&self) -> bool {
+            let Some(session) = self.session() else {
+                return false;
+            };
+            let session = session.lock().unwrap();
+            session.forward
+        }
+
+        fn set_forward(&self, forward: bool) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.forward = forward;
+        }
+
+        fn sdes(&self) -> Option<gst::Structure> {
+            let session = self.session()?;
+            let session = session.lock().unwrap();
+            Some(sdes_to_structure(session.session.sdes()))
+        }
+
+        fn set_sdes(&self, sdes: Option<gst::Structure>) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session
+                .session
+                .set_sdes(sdes.as_deref().map(sdes_from_structure).unwrap_or_default());
+        }
+
+        fn bye_reason(&self) -> String {
+            let Some(session) = self.session() else {
+                return String::from(DEFAULT_BYE_REASON);
+            };
+            let session = session.lock().unwrap();
+            session.session.bye_reason().to_string()
+        }
+
+        fn set_bye_reason(&self, bye_reason: &str) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.session.set_bye_reason(bye_reason);
+        }
+
+        fn send_bye(&self, ssrc: u32, reason: Option<&str>) -> bool {
+            let Some(session) = self.session() else {
+                return false;
+            };
+            let mut session = session.lock().unwrap();
+
+            let reason = reason
+                .map(String::from)
+                .unwrap_or_else(|| session.session.bye_reason().to_string());
+            let Some(source) = session.session.mut_local_send_source_by_ssrc(ssrc) else {
+                gst::debug!(CAT, imp = self, "No local sender with ssrc {ssrc:#08x}");
+                return false;
+            };
+
+            gst::debug!(CAT, imp = self, "Sending BYE for {ssrc:#08x}: {reason}");
+            source.mark_bye(&reason);
+
+            if let Some(waker) = session.rtcp_waker.take() {
+                waker.wake();
+            }
+
+            true
+        }
+
+        fn update_timeouts(&self) {
+            let settings = self.settings.lock().unwrap();
+            let sender_timeout = settings.sender_timeout.source_timeout();
+            let member_timeout = settings.member_timeout.source_timeout();
+            drop(settings);
+
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.session.set_sender_timeout(sender_timeout);
+            session.session.set_member_timeout(member_timeout);
+        }
+
+        fn clear_pt_map(&self) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            gst::debug!(CAT, imp = self, "Clearing pt map");
+            session.clear_pt_map();
+            session.session.clear_pt_clock_rates();
+        }
+
+        fn set_pt_clock_rate(&self, pt: u32, clock_rate: u32, caps: Option<gst::Caps>) -> bool {
+            if pt > 127 || clock_rate == 0 || clock_rate > i32::MAX as u32 {
+                gst::warning!(
+                    CAT,
+                    imp = self,
+                    "Invalid pt {pt} or clock-rate {clock_rate}"
+                );
+                return false;
+            }
+            let Some(session) = self.session() else {
+                return false;
+            };
+
+            let mut caps = caps
+                .filter(|caps| caps.size() == 1)
+                .unwrap_or_else(|| gst::Caps::new_empty_simple("application/x-rtp"));
+            {
+                let caps = caps.make_mut();
+                caps.set("payload", pt as i32);
+                caps.set("clock-rate", clock_rate as i32);
+            }
+            gst::debug!(CAT, imp = self, "Setting caps {caps} for pt {pt}");
+
+            let mut session = session.lock().unwrap();
+            session.add_caps(caps);
+            drop(session);
+
+            self.update_extensions();
+
+            true
+        }
+
+        fn set_ssrc_clock_rate(&self, ssrc: u32, clock_rate: u32) -> bool {
+            if clock_rate == 0 {
+                gst::warning!(CAT, imp = self, "Invalid clock-rate {clock_rate}");
+                return false;
+            }
+            let Some(session) = self.session() else {
+                return false;
+            };
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Setting clock-rate {clock_rate} for ssrc {ssrc:#08x}"
+            );
+            let mut session = session.lock().unwrap();
+            session.session.set_ssrc_clock_rate(ssrc, clock_rate);
+
+            true
+        }
+
+        fn request_key_unit(&self, ssrc: u32, fir: bool) -> bool {
+            let Some(session) = self.session() else {
+                return false;
+            };
+            let mut session = session.lock().unwrap();
+
+            if session.session.profile() != RtpProfile::Avpf {
+                gst::warning!(
+                    CAT,
+                    imp = self,
+                    "Key units can only be requested with the AVPF profile"
+                );
+                return false;
+            }
+            if session.session.remote_send_source_by_ssrc(ssrc).is_none() {
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Can't request key unit from unknown sender {ssrc:#08x}"
+                );
+                return false;
+            }
+
+            let typ = if fir {
+                let mut state = self.state.lock().unwrap();
+                state.fir_count = state.fir_count.wrapping_add(1);
+                KeyUnitRequestType::Fir(state.fir_count)
+            } else {
+                KeyUnitRequestType::Pli
+            };
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Requesting key unit {typ:?} from {ssrc:#08x}"
+            );
+
+            let replies = session
+                .session
+                .request_remote_key_unit(Instant::now(), typ, ssrc);
+            for reply in replies {
+                match reply {
+                    RequestRemoteKeyUnitReply::TimerReconsideration => {
+                        if let Some(waker) = session.rtcp_waker.take() {
+                            // reconsider timers means that we wake the rtcp task to get a new timeout
+                            waker.wake();
+                        }
+                    }
+                }
+            }
+
+            true
+        }
+
+        fn clear_extensions(&self) {
+            let Some(session) = self.session() else {
+                return;
+            };
+            let mut session = session.lock().unwrap();
+            session.extensions.clear();
+            drop(session);
+
+            self.obj().notify("extensions");
+        }
+
+        fn request_extension(&self, ext_id: u32, uri: &str) -> Option<gst_rtp::RTPHeaderExtension> {
+            if !self.settings.lock().unwrap().auto_header_extensions {
+                return None;
+            }
+
+            let Some(ext) = gst_rtp::RTPHeaderExtension::create_from_uri(uri) else {
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Didn't find any extension implementing URI {uri}",
+                );
+                return None;
+            };
+
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Automatically enabling extension {} for URI {uri}",
+                ext.name(),
+            );
+
+            ext.set_id(ext_id);
+
+            Some(ext)
+        }
+
+        pub(super) fn update_extensions(&self) {
+            let Some(session) = self.session() else {
+                return;
+            };
+
+            // Collect the extensions from all caps of the session
+            let session_guard = session.lock().unwrap();
+            let mut caps_extensions = BTreeMap::new();
+            for (pt, caps) in session_guard.pt_map() {
+                let Some(s) = caps.structure(0) else {
+                    continue;
+                };
+                for (k, v) in s.iter() {
+                    let Some(ext_id) = k.strip_prefix("extmap-") else {
+                        continue;
+                    };
+                    let Ok(ext_id) = ext_id.parse::<u8>() else {
+                        gst::warning!(
+                            CAT,
+                            imp = self,
+                            "Can't parse RTP header extension id from caps {caps:?}"
+                        );
+                        continue;
+                    };
+
+                    let uri = if let Ok(uri) = v.get::<String>() {
+                        uri
+                    } else if let Some(uri) = v
+                        .get::<gst::ArrayRef>()
+                        .ok()
+                        .and_then(|arr| arr.get(1).and_then(|v| v.get::<String>().ok()))
+                    {
+                        uri
+                    } else {
+                        gst::warning!(
+                            CAT,
+                            imp = self,
+                            "Couldn't get URI for RTP header extension id {ext_id} from caps {caps:?}"
+                        );
+                        continue;
+                    };
+
+                    if let Some((other_pt, other_uri, _)) = caps_extensions.get(&ext_id) {
+                        if other_uri != &uri {
+                            gst::warning!(
+                                CAT,
+                                imp = self,
+                                "Extension ID {ext_id} used for {other_uri} by pt {other_pt} and {uri} by pt {pt}"
+                            );
+                        }
+                        continue;
+                    }
+
+                    caps_extensions.insert(ext_id, (pt, uri, caps.clone()));
+                }
+            }
+            let mut extensions = session_guard.extensions.clone();
+            drop(session_guard);
+
+            // Signal handlers must not be called with the session lock held
+            let mut extensions_changed = false;
+            for (ext_id, (_pt, uri, caps)) in &caps_extensions {
+                if let Some(extension) = extensions.get(ext_id) {
+                    if extension.uri().as_deref() == Some(uri) {
+                        // Same extension, update it with the new caps in case the attributes
+                        // changed
+                        if extension.set_attributes_from_caps(caps) {
+                            continue;
+                        }
+
+                        // Try to get a new one for this extension ID instead
+                        gst::warning!(
+                            CAT,
+                            imp = self,
+                            "Failed to configure extension {ext_id} from caps {caps}"
+                        );
+                    } else {
+                        gst::debug!(
+                            CAT,
+                            imp = self,
+                            "Extension ID {ext_id} changed from {:?} to {uri}",
+                            extension.uri(),
+                        );
+                    }
+                    extensions_changed = true;
+                    extensions.remove(ext_id);
+                }
+
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Requesting extension {uri} for ID {ext_id}"
+                );
+                let ext = self
+                    .obj()
+                    .emit_by_name::<Option<gst_rtp::RTPHeaderExtension>>(
+                        "request-extension",
+                        &[&(*ext_id as u32), &uri],
+                    );
+
+                let Some(ext) = ext else {
+                    gst::debug!(
+                        CAT,
+                        imp = self,
+                        "Couldn't create extension for {uri} with ID {ext_id}"
+                    );
+                    continue;
+                };
+
+                if ext.id() != *ext_id as u32 {
+                    gst::warning!(CAT, imp = self, "Created extension has wrong ID");
+                    continue;
+                }
+
+                if !ext.set_attributes_from_caps(caps) {
+                    gst::warning!(
+                        CAT,
+                        imp = self,
+                        "Failed to configure extension {ext_id} from caps {caps}"
+                    );
+                    continue;
+                }
+
+                extensions.insert(*ext_id, ext);
+                extensions_changed = true;
+            }
+
+            // Remove all extensions that are not in the caps
+            extensions.retain(|ext_id, _| {
+                if !caps_extensions.contains_key(ext_id) {
+                    extensions_changed = true;
+                    false
+                } else {
+                    true
+                }
+            });
+
+            // MID and RtpStreamId are needed for BUNDLE demuxing, abs-send-time is written when
+            // sending and the transport-wide sequence number of sent packets is needed for
+            // congestion control, independent of any extension implementation being available
+            let ext_id_for_uri = |wanted: &str| {
+                caps_extensions
+                    .iter()
+                    .find(|(_, (_, uri, _))| uri == wanted)
+                    .map(|(ext_id, _)| *ext_id)
+            };
+            let mid_ext_id = ext_id_for_uri(hdrext::MID_URI);
+            let rid_ext_id = ext_id_for_uri(hdrext::RTP_STREAM_ID_URI);
+            let abs_send_time_ext_id = ext_id_for_uri(hdrext::ABS_SEND_TIME_URI);
+            let twcc_ext_id = ext_id_for_uri(twcc::TWCC_URI);
+
+            let mut session = session.lock().unwrap();
+            session.mid_ext_id = mid_ext_id;
+            session.rid_ext_id = rid_ext_id;
+            session.abs_send_time_ext_id = abs_send_time_ext_id;
+            session.twcc_ext_id = twcc_ext_id;
+            if extensions_changed {
+                session.extensions = extensions;
+                drop(session);
+
+                self.obj().notify("extensions");
+            }
+        }
     }
 
     #[glib::object_subclass]
@@ -114,10 +772,127 @@ mod imp {
     impl ObjectImpl for Rtp2Session {
         fn properties() -> &'static [glib::ParamSpec] {
             static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
-                vec![glib::ParamSpecBoxed::builder::<gst::Structure>("pt-map")
-                    .nick("RTP Payload Type Map")
-                    .blurb("Mapping of RTP payload type to caps")
-                    .build()]
+                vec![
+                    glib::ParamSpecBoxed::builder::<gst::Structure>("pt-map")
+                        .nick("RTP Payload Type Map")
+                        .blurb("Mapping of RTP payload type to caps")
+                        .build(),
+                    glib::ParamSpecBoolean::builder("auto-header-extension")
+                        .nick("Automatic RTP Header Extensions")
+                        .blurb("Whether RTP header extensions should be automatically enabled, if an implementation is available")
+                        .default_value(DEFAULT_AUTO_HEADER_EXTENSIONS)
+                        .build(),
+                    gst::ParamSpecArray::builder("extensions")
+                        .nick("RTP Header Extensions")
+                        .blurb("List of enabled RTP header extensions")
+                        .element_spec(
+                            &glib::ParamSpecObject::builder::<gst_rtp::RTPHeaderExtension>(
+                                "extension",
+                            )
+                            .nick("RTP Header Extension")
+                            .blurb("Enabled RTP header extension")
+                            .read_only()
+                            .build(),
+                        )
+                        .read_only()
+                        .build(),
+                    glib::ParamSpecBoxed::builder::<gst::Structure>("twcc-stats")
+                        .nick("TWCC Statistics")
+                        .blurb("Transport-wide congestion control statistics of the sent packets as of the last received feedback, notified on updates")
+                        .read_only()
+                        .build(),
+                    glib::ParamSpecUInt::builder("min-rtcp-interval")
+                        .nick("Minimum RTCP interval in ms")
+                        .blurb("Minimum time (in ms) between RTCP reports of this session")
+                        .default_value(RTCP_MIN_REPORT_INTERVAL.as_millis() as u32)
+                        .build(),
+                    glib::ParamSpecUInt64::builder("bandwidth")
+                        .nick("Bandwidth")
+                        .blurb("Session bandwidth in bits per second used for the RTCP bandwidth calculation (0 = estimate from the senders)")
+                        .default_value(0)
+                        .build(),
+                    glib::ParamSpecDouble::builder("rtcp-fraction")
+                        .nick("RTCP Fraction")
+                        .blurb("Fraction of the session bandwidth to use for RTCP")
+                        .minimum(0.0)
+                        .maximum(1.0)
+                        .default_value(DEFAULT_RTCP_FRACTION)
+                        .build(),
+                    glib::ParamSpecInt64::builder("rtcp-rs-bandwidth")
+                        .nick("RTCP RS Bandwidth")
+                        .blurb("RTCP bandwidth in bits per second for the senders (RFC 3556) (-1 = derive from rtcp-fraction, 0 for senders and receivers disables RTCP)")
+                        .minimum(-1)
+                        .default_value(-1)
+                        .build(),
+                    glib::ParamSpecInt64::builder("rtcp-rr-bandwidth")
+                        .nick("RTCP RR Bandwidth")
+                        .blurb("RTCP bandwidth in bits per second for the receivers (RFC 3556) (-1 = derive from rtcp-fraction, 0 for senders and receivers disables RTCP)")
+                        .minimum(-1)
+                        .default_value(-1)
+                        .build(),
+                    glib::ParamSpecEnum::builder::<Profile>("rtp-profile")
+                        .nick("RTP Profile")
+                        .blurb("RTP Profile to use for this session")
+                        .default_value(Profile::default())
+                        .build(),
+                    glib::ParamSpecBoolean::builder("rtcp-xr-rrtr")
+                        .nick("RTCP XR RRTR")
+                        .blurb("Send RTCP XR Receiver Reference Time blocks to allow measuring the round-trip time on receive-only sessions")
+                        .default_value(false)
+                        .build(),
+                    glib::ParamSpecBoolean::builder("separate-sources-by-address")
+                        .nick("Separate Sources by Address")
+                        .blurb("Handle packets with the same SSRC from different source addresses as separate sources instead of ignoring the ones from the later address")
+                        .default_value(false)
+                        .build(),
+                    glib::ParamSpecString::builder("feedback-target")
+                        .nick("Feedback Target")
+                        .blurb("Unicast address (IP:port) RTCP is sent to in a source-specific multicast session (RFC 5760)")
+                        .build(),
+                    glib::ParamSpecString::builder("bye-reason")
+                        .nick("BYE reason")
+                        .blurb("Reason sent in the RTCP BYE packets when the session ends")
+                        .default_value(Some(DEFAULT_BYE_REASON))
+                        .build(),
+                    glib::ParamSpecInt64::builder("ts-offset")
+                        .nick("Timestamp Offset")
+                        .blurb("Offset in ns to add to the timestamps of packets received in this session, e.g. for manually correcting lip-sync")
+                        .default_value(0)
+                        .build(),
+                    glib::ParamSpecBoolean::builder("forward")
+                        .nick("Forward")
+                        .blurb("Bypass the jitterbuffer for packets received in this session and output them in arrival order without added latency. Only applies to pads created afterwards")
+                        .default_value(false)
+                        .build(),
+                    glib::ParamSpecBoxed::builder::<gst::Structure>("sdes")
+                        .nick("SDES")
+                        .blurb("The SDES items of this session. A CNAME is generated if none is set")
+                        .build(),
+                    glib::ParamSpecUInt::builder("sender-timeout-intervals")
+                        .nick("Sender Timeout Intervals")
+                        .blurb("Number of RTCP intervals without sending RTP after which a local sender becomes a receiver")
+                        .minimum(1)
+                        .default_value(DEFAULT_SENDER_TIMEOUT_INTERVALS)
+                        .build(),
+                    glib::ParamSpecUInt64::builder("sender-timeout")
+                        .nick("Sender Timeout")
+                        .blurb("Time in ns without sending RTP after which a local sender becomes a receiver. Overrides sender-timeout-intervals if not 0")
+                        .maximum(u64::MAX - 1)
+                        .default_value(0)
+                        .build(),
+                    glib::ParamSpecUInt::builder("member-timeout-intervals")
+                        .nick("Member Timeout Intervals")
+                        .blurb("Number of RTCP intervals without any activity after which a source is removed from the session")
+                        .minimum(1)
+                        .default_value(DEFAULT_MEMBER_TIMEOUT_INTERVALS)
+                        .build(),
+                    glib::ParamSpecUInt64::builder("member-timeout")
+                        .nick("Member Timeout")
+                        .blurb("Time in ns without any activity after which a source is removed from the session. Overrides member-timeout-intervals if not 0")
+                        .maximum(u64::MAX - 1)
+                        .default_value(0)
+                        .build(),
+                ]
             });
 
             PROPERTIES.as_ref()
@@ -127,6 +902,65 @@ mod imp {
             match pspec.name() {
                 "pt-map" => self.pt_map().to_value(),
                 "stats" => self.stats().to_value(),
+                "twcc-stats" => self.twcc_stats().to_value(),
+                "auto-header-extension" => self
+                    .settings
+                    .lock()
+                    .unwrap()
+                    .auto_header_extensions
+                    .to_value(),
+                "extensions" => self.extensions().to_value(),
+                "min-rtcp-interval" => (self.min_rtcp_interval().as_millis() as u32).to_value(),
+                "bandwidth" => self
+                    .rtcp_bandwidth()
+                    .session
+                    .map_or(0, |bw| bw as u64 * 8)
+                    .to_value(),
+                "rtcp-fraction" => self.rtcp_bandwidth().fraction.to_value(),
+                "rtcp-rs-bandwidth" => self
+                    .rtcp_bandwidth()
+                    .senders
+                    .map_or(-1, |bw| bw as i64 * 8)
+                    .to_value(),
+                "rtcp-rr-bandwidth" => self
+                    .rtcp_bandwidth()
+                    .receivers
+                    .map_or(-1, |bw| bw as i64 * 8)
+                    .to_value(),
+                "rtp-profile" => self.profile().to_value(),
+                "rtcp-xr-rrtr" => self.rtcp_xr_rrtr().to_value(),
+                "separate-sources-by-address" => self.separate_sources_by_address().to_value(),
+                "feedback-target" => self.feedback_target().to_value(),
+                "bye-reason" => self.bye_reason().to_value(),
+                "ts-offset" => self.ts_offset().to_value(),
+                "forward" => self.forward().to_value(),
+                "sdes" => self.sdes().to_value(),
+                "sender-timeout-intervals" => {
+                    let settings = self.settings.lock().unwrap();
+                    settings.sender_timeout.intervals.to_value()
+                }
+                "sender-timeout" => {
+                    let settings = self.settings.lock().unwrap();
+                    (settings
+                        .sender_timeout
+                        .duration
+                        .unwrap_or_default()
+                        .as_nanos() as u64)
+                        .to_value()
+                }
+                "member-timeout-intervals" => {
+                    let settings = self.settings.lock().unwrap();
+                    settings.member_timeout.intervals.to_value()
+                }
+                "member-timeout" => {
+                    let settings = self.settings.lock().unwrap();
+                    (settings
+                        .member_timeout
+                        .duration
+                        .unwrap_or_default()
+                        .as_nanos() as u64)
+                        .to_value()
+                }
                 _ => unreachable!(),
             }
         }
@@ -138,18 +972,216 @@ mod imp {
                         .get::<Option<gst::Structure>>()
                         .expect("Type checked upstream"),
                 ),
+                "auto-header-extension" => {
+                    self.settings.lock().unwrap().auto_header_extensions =
+                        value.get().expect("Type checked upstream");
+                }
+                "min-rtcp-interval" => self.set_min_rtcp_interval(Duration::from_millis(
+                    value.get::<u32>().expect("Type checked upstream").into(),
+                )),
+                "bandwidth" => {
+                    let bandwidth = value.get::<u64>().expect("Type checked upstream");
+                    self.update_rtcp_bandwidth(|bw| {
+                        bw.session = (bandwidth > 0).then_some((bandwidth / 8) as usize)
+                    });
+                }
+                "rtcp-fraction" => {
+                    let fraction = value.get::<f64>().expect("Type checked upstream");
+                    self.update_rtcp_bandwidth(|bw| bw.fraction = fraction);
+                }
+                "rtcp-rs-bandwidth" => {
+                    let bandwidth = value.get::<i64>().expect("Type checked upstream");
+                    self.update_rtcp_bandwidth(|bw| {
+                        bw.senders = (bandwidth >= 0).then_some((bandwidth / 8) as usize)
+                    });
+                }
+                "rtcp-rr-bandwidth" => {
+                    let bandwidth = value.get::<i64>().expect("Type checked upstream");
+                    self.update_rtcp_bandwidth(|bw| {
+                        bw.receivers = (bandwidth >= 0).then_some((bandwidth / 8) as usize)
+                    });
+                }
+                "rtp-profile" => {
+                    self.set_profile(value.get::<Profile>().expect("Type checked upstream"))
+                }
+                "rtcp-xr-rrtr" => {
+                    self.set_rtcp_xr_rrtr(value.get::<bool>().expect("Type checked upstream"))
+                }
+                "separate-sources-by-address" => self.set_separate_sources_by_address(
+                    value.get::<bool>().expect("Type checked upstream"),
+                ),
+                "feedback-target" => self.set_feedback_target(
+                    value.get::<Option<&str>>().expect("Type checked upstream"),
+                ),
+                "bye-reason" => self.set_bye_reason(
+                    value
+                        .get::<Option<&str>>()
+                        .expect("Type checked upstream")
+                        .unwrap_or(DEFAULT_BYE_REASON),
+                ),
+                "ts-offset" => {
+                    self.set_ts_offset(value.get::<i64>().expect("Type checked upstream"))
+                }
+                "forward" => self.set_forward(value.get::<bool>().expect("Type checked upstream")),
+                "sdes" => self.set_sdes(
+                    value
+                        .get::<Option<gst::Structure>>()
+                        .expect("Type checked upstream"),
+                ),
+                "sender-timeout-intervals" => {
+                    self.settings.lock().unwrap().sender_timeout.intervals =
+                        value.get().expect("Type checked upstream");
+                    self.update_timeouts();
+                }
+                "sender-timeout" => {
+                    let duration = value.get::<u64>().expect("Type checked upstream");
+                    self.settings.lock().unwrap().sender_timeout.duration =
+                        (duration > 0).then(|| Duration::from_nanos(duration));
+                    self.update_timeouts();
+                }
+                "member-timeout-intervals" => {
+                    self.settings.lock().unwrap().member_timeout.intervals =
+                        value.get().expect("Type checked upstream");
+                    self.update_timeouts();
+                }
+                "member-timeout" => {
+                    let duration = value.get::<u64>().expect("Type checked upstream");
+                    self.settings.lock().unwrap().member_timeout.duration =
+                        (duration > 0).then(|| Duration::from_nanos(duration));
+                    self.update_timeouts();
+                }
                 _ => unreachable!(),
             }
         }
 
-        fn signals() -> &'static [glib::subclass::Signal] {
-            static SIGNALS: LazyLock<Vec<glib::subclass::Signal>> = LazyLock::new(|| {
-                vec![
-                    glib::subclass::Signal::builder("new-ssrc")
-                        .param_types([u32::static_type()])
+        fn signals() -> &'static [glib::subclass::Signal] {
+            static SIGNALS: LazyLock<Vec<glib::subclass::Signal>> = LazyLock::new(|| {
+                vec![
+                    glib::subclass::Signal::builder("new-ssrc")
+                        .param_types([u32::static_type()])
+                        .build(),
+                    glib::subclass::Signal::builder("new-sender-ssrc")
+                        .param_types([u32::static_type()])
+                        .build(),
+                    glib::subclass::Signal::builder("bye-ssrc")
+                        .param_types([u32::static_type()])
+                        .build(),
+                    glib::subclass::Signal::builder("on-timeout")
+                        .param_types([u32::static_type()])
+                        .build(),
+                    glib::subclass::Signal::builder("on-ssrc-collision")
+                        .param_types([u32::static_type(), u32::static_type()])
+                        .build(),
+                    glib::subclass::Signal::builder("add-extension")
+                        .action()
+                        .param_types([gst_rtp::RTPHeaderExtension::static_type()])
+                        .class_handler(|_token, args| {
+                            let s = args[0].get::<super::Rtp2Session>().unwrap();
+                            let ext = args[1].get::<&gst_rtp::RTPHeaderExtension>().unwrap();
+                            s.imp().add_extension(ext);
+
+                            None
+                        })
+                        .build(),
+                    glib::subclass::Signal::builder("request-extension")
+                        .param_types([u32::static_type(), String::static_type()])
+                        .return_type::<gst_rtp::RTPHeaderExtension>()
+                        .accumulator(|_hint, acc, val| {
+                            if matches!(val.get::<Option<glib::Object>>(), Ok(Some(_))) {
+                                *acc = val.clone();
+                                false
+                            } else {
+                                true
+                            }
+                        })
+                        .class_handler(|_token, args| {
+                            let s = args[0].get::<super::Rtp2Session>().unwrap();
+                            let ext_id = args[1].get::<u32>().unwrap();
+                            let uri = args[2].get::<&str>().unwrap();
+                            let ext = s.imp().request_extension(ext_id, uri);
+
+                            Some(ext.to_value())
+                        })
+                        .build(),
+                    glib::subclass::Signal::builder("clear-pt-map")
+                        .action()
+                        .class_handler(|_token, args| {
+                            let s = args[0].get::<super::Rtp2Session>().unwrap();
+                            s.imp().clear_pt_map();
+
+                            None
+                        })
+                        .build(),
+                    // Set the clock-rate of a payload type, e.g. if the input caps don't provide
+                    // it. The optional caps are used as caps of the payload type together with
+                    // the payload and clock-rate fields.
+                    glib::subclass::Signal::builder("set-pt-clock-rate")
+                        .action()
+                        .param_types([
+                            u32::static_type(),
+                            u32::static_type(),
+                            gst::Caps::static_type(),
+                        ])
+                        .return_type::<bool>()
+                        .class_handler(|_token, args| {
+                            let s = args[0].get::<super::Rtp2Session>().unwrap();
+                            let pt = args[1].get::<u32>().unwrap();
+                            let clock_rate = args[2].get::<u32>().unwrap();
+                            let caps = args[3].get::<Option<gst::Caps>>().unwrap();
+
+                            Some(s.imp().set_pt_clock_rate(pt, clock_rate, caps).to_value())
+                        })
+                        .build(),
+                    // Set the clock-rate of a remote SSRC independent of its payload type. Only
+                    // has an effect on sources that did not receive any packets yet.
+                    glib::subclass::Signal::builder("set-ssrc-clock-rate")
+                        .action()
+                        .param_types([u32::static_type(), u32::static_type()])
+                        .return_type::<bool>()
+                        .class_handler(|_token, args| {
+                            let s = args[0].get::<super::Rtp2Session>().unwrap();
+                            let ssrc = args[1].get::<u32>().unwrap();
+                            let clock_rate = args[2].get::<u32>().unwrap();
+
+                            Some(s.imp().set_ssrc_clock_rate(ssrc, clock_rate).to_value())
+                        })
                         .build(),
-                    glib::subclass::Signal::builder("bye-ssrc")
-                        .param_types([u32::static_type()])
+                    glib::subclass::Signal::builder("clear-extensions")
+                        .action()
+                        .class_handler(|_token, args| {
+                            let s = args[0].get::<super::Rtp2Session>().unwrap();
+                            s.imp().clear_extensions();
+
+                            None
+                        })
+                        .build(),
+                    // Request a key unit from a remote sender with a PLI or, if the second
+                    // argument is true, a FIR. Only possible with the AVPF profile.
+                    glib::subclass::Signal::builder("request-key-unit")
+                        .action()
+                        .param_types([u32::static_type(), bool::static_type()])
+                        .return_type::<bool>()
+                        .class_handler(|_token, args| {
+                            let s = args[0].get::<super::Rtp2Session>().unwrap();
+                            let ssrc = args[1].get::<u32>().unwrap();
+                            let fir = args[2].get::<bool>().unwrap();
+
+                            Some(s.imp().request_key_unit(ssrc, fir).to_value())
+                        })
+                        .build(),
+                    // Send a BYE for a local sender, e.g. when a track is permanently muted.
+                    // The reason defaults to the bye-reason property if NULL.
+                    glib::subclass::Signal::builder("send-bye")
+                        .action()
+                        .param_types([u32::static_type(), String::static_type()])
+                        .return_type::<bool>()
+                        .class_handler(|_token, args| {
+                            let s = args[0].get::<super::Rtp2Session>().unwrap();
+                            let ssrc = args[1].get::<u32>().unwrap();
+                            let reason = args[2].get::<Option<&str>>().unwrap();
+
+                            Some(s.imp().send_bye(ssrc, reason).to_value())
+                        })
                         .build(),
                 ]
             });
@@ -217,6 +1249,95 @@ mod tests {
         assert_eq!(pt_caps, caps);
     }
 
+    #[test]
+    fn sdes() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .property(
+                "sdes",
+                gst::Structure::builder("application/x-rtp-source-sdes")
+                    .field("tool", "test")
+                    .build(),
+            )
+            .build()
+            .unwrap();
+        let _pad = rtpbin2.request_pad_simple("rtp_sink_0").unwrap();
+        let session = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+
+        // A CNAME is generated if none is configured
+        let sdes = session.property::<gst::Structure>("sdes");
+        assert!(sdes.has_name("application/x-rtp-source-sdes"));
+        assert_eq!(sdes.get::<&str>("tool").unwrap(), "test");
+        let cname = sdes.get::<String>("cname").unwrap();
+        assert!(!cname.is_empty());
+
+        rtpbin2.set_property(
+            "sdes",
+            gst::Structure::builder("application/x-rtp-source-sdes")
+                .field("cname", "user@example.com")
+                .field("name", "User")
+                .build(),
+        );
+        let sdes = session.property::<gst::Structure>("sdes");
+        assert_eq!(sdes.get::<&str>("cname").unwrap(), "user@example.com");
+        assert_eq!(sdes.get::<&str>("name").unwrap(), "User");
+        assert!(!sdes.has_field("tool"));
+
+        session.set_property(
+            "sdes",
+            gst::Structure::builder("application/x-rtp-source-sdes")
+                .field("note", "away")
+                .build(),
+        );
+        let sdes = session.property::<gst::Structure>("sdes");
+        assert_eq!(sdes.get::<&str>("cname").unwrap(), "user@example.com");
+        assert_eq!(sdes.get::<&str>("note").unwrap(), "away");
+        assert_eq!(sdes.fields().len(), 2);
+    }
+
+    #[test]
+    fn source_timeouts() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtprecv")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let _pad = rtpbin2.request_pad_simple("rtp_sink_0").unwrap();
+        let session = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+
+        assert_eq!(session.property::<u32>("sender-timeout-intervals"), 2);
+        assert_eq!(session.property::<u64>("sender-timeout"), 0);
+        assert_eq!(session.property::<u32>("member-timeout-intervals"), 5);
+        assert_eq!(session.property::<u64>("member-timeout"), 0);
+
+        session.set_property("member-timeout-intervals", 3u32);
+        session.set_property("sender-timeout", gst::ClockTime::SECOND.nseconds());
+        assert_eq!(session.property::<u32>("member-timeout-intervals"), 3);
+        assert_eq!(
+            session.property::<u64>("sender-timeout"),
+            gst::ClockTime::SECOND.nseconds()
+        );
+    }
+
+    #[test]
+    fn ts_offset() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtprecv")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let _pad = rtpbin2.request_pad_simple("rtp_sink_0").unwrap();
+        let session = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+
+        assert_eq!(session.property::<i64>("ts-offset"), 0);
+        session.set_property("ts-offset", -20_000_000i64);
+        assert_eq!(session.property::<i64>("ts-offset"), -20_000_000);
+    }
+
     #[test]
     fn pt_map_set_none() {
         test_init();
@@ -232,6 +1353,208 @@ mod tests {
         assert!(prop.has_name("application/x-rtp2-pt-map"));
     }
 
+    #[test]
+    fn pt_map_clear() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let _pad = rtpbin2.request_pad_simple("rtp_sink_0").unwrap();
+        let session = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        let pt_map = gst::Structure::builder("application/x-rtp2-pt-map")
+            .field(
+                "96",
+                gst::Caps::builder("application/x-rtp")
+                    .field("payload", 96i32)
+                    .field("clock-rate", 90000i32)
+                    .build(),
+            )
+            .build();
+        session.set_property("pt-map", pt_map);
+        assert_eq!(
+            session.property::<gst::Structure>("pt-map").fields().len(),
+            1
+        );
+
+        session.emit_by_name::<()>("clear-pt-map", &[]);
+        let prop = session.property::<gst::Structure>("pt-map");
+        assert!(prop.has_name("application/x-rtp2-pt-map"));
+        assert_eq!(prop.fields().len(), 0);
+    }
+
+    #[test]
+    fn child_proxy_sessions() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let child_proxy = rtpbin2.dynamic_cast_ref::<gst::ChildProxy>().unwrap();
+
+        let children = Arc::new(Mutex::new(Vec::new()));
+        child_proxy.connect_child_added({
+            let children = children.clone();
+            move |_, _, name| children.lock().unwrap().push(format!("+{name}"))
+        });
+        child_proxy.connect_child_removed({
+            let children = children.clone();
+            move |_, _, name| children.lock().unwrap().push(format!("-{name}"))
+        });
+
+        let pad0 = rtpbin2.request_pad_simple("rtp_sink_0").unwrap();
+        let _pad1 = rtpbin2.request_pad_simple("rtp_sink_1").unwrap();
+        assert_eq!(child_proxy.children_count(), 2);
+        assert_eq!(*children.lock().unwrap(), ["+session_0", "+session_1"]);
+
+        let session0 = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        let session1 = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&1u32]);
+        assert_eq!(child_proxy.child_by_name("session_0").unwrap(), session0);
+        assert_eq!(child_proxy.child_by_name("session_1").unwrap(), session1);
+        assert!(child_proxy.child_by_name("session_2").is_none());
+
+        child_proxy.set_child_property("session_1::min-rtcp-interval", 1000u32);
+        child_proxy.set_child_property("session_1::rtp-profile", Profile::Avpf);
+        assert_eq!(session1.property::<u32>("min-rtcp-interval"), 1000);
+        assert_eq!(session1.property::<Profile>("rtp-profile"), Profile::Avpf);
+        assert_eq!(
+            session0.property::<u32>("min-rtcp-interval"),
+            RTCP_MIN_REPORT_INTERVAL.as_millis() as u32
+        );
+        assert_eq!(session0.property::<Profile>("rtp-profile"), Profile::Avp);
+
+        // Releasing the last pad of a session removes it
+        rtpbin2.release_request_pad(&pad0);
+        assert_eq!(child_proxy.children_count(), 1);
+        assert!(child_proxy.child_by_name("session_0").is_none());
+        assert_eq!(
+            *children.lock().unwrap(),
+            ["+session_0", "+session_1", "-session_0"]
+        );
+    }
+
+    #[test]
+    fn rtcp_bandwidth() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let _pad = rtpbin2.request_pad_simple("rtp_sink_0").unwrap();
+        let session = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+
+        assert_eq!(session.property::<u64>("bandwidth"), 0);
+        assert_eq!(
+            session.property::<f64>("rtcp-fraction"),
+            DEFAULT_RTCP_FRACTION
+        );
+        assert_eq!(session.property::<i64>("rtcp-rs-bandwidth"), -1);
+        assert_eq!(session.property::<i64>("rtcp-rr-bandwidth"), -1);
+
+        session.set_property("bandwidth", 1_000_000u64);
+        session.set_property("rtcp-fraction", 0.1f64);
+        session.set_property("rtcp-rs-bandwidth", 0i64);
+        session.set_property("rtcp-rr-bandwidth", 64_000i64);
+        assert_eq!(session.property::<u64>("bandwidth"), 1_000_000);
+        assert_eq!(session.property::<f64>("rtcp-fraction"), 0.1);
+        assert_eq!(session.property::<i64>("rtcp-rs-bandwidth"), 0);
+        assert_eq!(session.property::<i64>("rtcp-rr-bandwidth"), 64_000);
+
+        session.set_property("bandwidth", 0u64);
+        session.set_property("rtcp-rr-bandwidth", -1i64);
+        assert_eq!(session.property::<u64>("bandwidth"), 0);
+        assert_eq!(session.property::<i64>("rtcp-rr-bandwidth"), -1);
+    }
+
+    #[test]
+    fn create_destroy_session() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtprecv")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let child_proxy = rtpbin2.dynamic_cast_ref::<gst::ChildProxy>().unwrap();
+
+        let session_id = rtpbin2.emit_by_name::<u32>("create-session", &[]);
+        assert_eq!(session_id, 0);
+        assert_eq!(rtpbin2.emit_by_name::<u32>("create-session", &[]), 1);
+        assert_eq!(child_proxy.children_count(), 2);
+
+        let pad = rtpbin2
+            .emit_by_name::<Option<gst::Pad>>("request-session-pad", &[&session_id, &"rtp_sink"])
+            .unwrap();
+        assert_eq!(pad.name(), "rtp_sink_0");
+        assert!(rtpbin2
+            .emit_by_name::<Option<gst::Pad>>("request-session-pad", &[&5u32, &"rtp_sink"])
+            .is_none());
+
+        // Sessions are kept after their pads are released
+        rtpbin2.release_request_pad(&pad);
+        assert_eq!(child_proxy.children_count(), 2);
+
+        let _pad = rtpbin2
+            .emit_by_name::<Option<gst::Pad>>("request-session-pad", &[&session_id, &"rtcp_sink"])
+            .unwrap();
+        assert!(rtpbin2.emit_by_name::<bool>("destroy-session", &[&session_id]));
+        assert!(rtpbin2.static_pad("rtcp_sink_0").is_none());
+        assert_eq!(child_proxy.children_count(), 1);
+        assert!(!rtpbin2.emit_by_name::<bool>("destroy-session", &[&session_id]));
+    }
+
+    #[test]
+    fn extensions_get_empty() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let _pad = rtpbin2.request_pad_simple("rtp_sink_0").unwrap();
+        let session = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        let extensions = session.property::<gst::Array>("extensions");
+        assert!(extensions.is_empty());
+    }
+
+    #[test]
+    fn pt_map_request_extension() {
+        test_init();
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let _pad = rtpbin2.request_pad_simple("rtp_sink_0").unwrap();
+        let session = rtpbin2.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        let request_hit = Arc::new(AtomicBool::new(false));
+        session.connect("request-extension", false, {
+            let request_hit = request_hit.clone();
+            move |args| {
+                let ext_id = args[1].get::<u32>().unwrap();
+                let uri = args[2].get::<&str>().unwrap();
+                assert_eq!(ext_id, 3);
+                assert_eq!(uri, "urn:example:custom-extension");
+                request_hit.store(true, std::sync::atomic::Ordering::SeqCst);
+                None
+            }
+        });
+        let pt_caps = gst::Caps::builder("application/x-rtp")
+            .field("payload", 96i32)
+            .field("clock-rate", 90000i32)
+            .field("extmap-3", "urn:example:custom-extension")
+            .build();
+        let pt_map = gst::Structure::builder("application/x-rtp2-pt-map")
+            .field("96", pt_caps)
+            .build();
+        session.set_property("pt-map", pt_map);
+        assert!(request_hit.load(std::sync::atomic::Ordering::SeqCst));
+        let extensions = session.property::<gst::Array>("extensions");
+        assert!(extensions.is_empty());
+    }
+
     #[test]
     fn new_send_ssrc() {
         test_init();
@@ -271,6 +1594,168 @@ mod tests {
         assert_eq!(buf4, buf2);
     }
 
+    #[test]
+    fn new_sender_ssrc() {
+        test_init();
+        let ssrc = 0x12345678;
+        let new_sender_ssrc_hit = Arc::new(AtomicBool::new(false));
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        // Only used for collecting the messages
+        let pipeline = gst::Pipeline::new();
+        pipeline.add(&rtpbin2).unwrap();
+        let bus = pipeline.bus().unwrap();
+        let mut h =
+            gst_check::Harness::with_element(&rtpbin2, Some("rtp_sink_0"), Some("rtp_src_0"));
+        let session = h
+            .element()
+            .unwrap()
+            .emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        let ssrc_hit = new_sender_ssrc_hit.clone();
+        session.connect("new-sender-ssrc", false, move |args| {
+            let new_ssrc = args[1].get::<u32>().unwrap();
+            ssrc_hit.store(true, std::sync::atomic::Ordering::SeqCst);
+            assert_eq!(new_ssrc, ssrc);
+            None
+        });
+        h.set_src_caps_str("application/x-rtp,payload=96,clock-rate=90000");
+        let mut segment = gst::Segment::new();
+        segment.set_format(gst::Format::Time);
+        h.push_event(gst::event::Segment::builder(&segment).build());
+        let buf1 = gst::Buffer::from_mut_slice(generate_rtp_packet(ssrc, 0x34, 0x10, 16));
+        h.push(buf1).unwrap();
+        assert!(new_sender_ssrc_hit.load(std::sync::atomic::Ordering::SeqCst));
+
+        let msg = bus
+            .iter()
+            .find(|msg| msg.structure().is_some_and(|s| s.has_name("rtp-new-ssrc")))
+            .unwrap();
+        let s = msg.structure().unwrap();
+        assert_eq!(s.get::<u32>("session").unwrap(), 0);
+        assert_eq!(s.get::<u32>("ssrc").unwrap(), ssrc);
+        assert!(s.get::<bool>("local").unwrap());
+    }
+
+    #[test]
+    fn set_pt_clock_rate() {
+        test_init();
+        let ssrc = 0x12345678;
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtprecv")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let mut h = gst_check::Harness::with_element(&rtpbin2, Some("rtp_sink_0"), None);
+        let session = h
+            .element()
+            .unwrap()
+            .emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        h.play();
+        // No clock-rate in the caps
+        h.set_src_caps_str("application/x-rtp");
+        let mut segment = gst::Segment::new();
+        segment.set_format(gst::Format::Time);
+        h.push_event(gst::event::Segment::builder(&segment).build());
+
+        // Packets without clock-rate are dropped
+        let buf = gst::Buffer::from_mut_slice(generate_rtp_packet(ssrc, 0x34, 0x10, 16));
+        assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+        assert!(!session.property::<gst::Structure>("pt-map").has_field("96"));
+
+        assert!(!session.emit_by_name::<bool>(
+            "set-pt-clock-rate",
+            &[&128u32, &90000u32, &None::<gst::Caps>]
+        ));
+        assert!(!session
+            .emit_by_name::<bool>("set-pt-clock-rate", &[&96u32, &0u32, &None::<gst::Caps>]));
+
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("media", "video")
+            .field("encoding-name", "VP8")
+            .build();
+        assert!(session.emit_by_name::<bool>("set-pt-clock-rate", &[&96u32, &90000u32, &caps]));
+
+        let pt_caps = session
+            .property::<gst::Structure>("pt-map")
+            .get::<gst::Caps>("96")
+            .unwrap();
+        let s = pt_caps.structure(0).unwrap();
+        assert_eq!(s.get::<i32>("payload"), Ok(96));
+        assert_eq!(s.get::<i32>("clock-rate"), Ok(90000));
+        assert_eq!(s.get::<&str>("encoding-name"), Ok("VP8"));
+
+        // The clock-rate of the SSRC overrides the one of the payload type
+        assert!(session.emit_by_name::<bool>("set-ssrc-clock-rate", &[&ssrc, &48000u32]));
+        assert!(!session.emit_by_name::<bool>("set-ssrc-clock-rate", &[&ssrc, &0u32]));
+
+        let srcpad = Arc::new(Mutex::new(None));
+        rtpbin2.connect_pad_added({
+            let srcpad = srcpad.clone();
+            move |_, pad| {
+                if pad.direction() == gst::PadDirection::Src {
+                    *srcpad.lock().unwrap() = Some(pad.clone());
+                }
+            }
+        });
+
+        // Two packets to get the source out of probation
+        for seqnum in [0x35, 0x36] {
+            let buf = gst::Buffer::from_mut_slice(generate_rtp_packet(ssrc, seqnum, 0x10, 16));
+            assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+        }
+
+        let srcpad = srcpad.lock().unwrap().clone().expect("no srcpad added");
+        let caps = srcpad
+            .sticky_event::<gst::event::Caps>(0)
+            .expect("no caps on the srcpad");
+        let s = caps.caps().structure(0).unwrap();
+        assert_eq!(s.get::<i32>("payload"), Ok(96));
+        assert_eq!(s.get::<i32>("clock-rate"), Ok(48000));
+        assert_eq!(s.get::<&str>("encoding-name"), Ok("VP8"));
+
+        let stats = session.property::<gst::Structure>("stats");
+        let source_stats = stats.get::<gst::Structure>(ssrc.to_string()).unwrap();
+        assert_eq!(source_stats.get::<u32>("clock-rate"), Ok(48000));
+    }
+
+    #[test]
+    fn request_key_unit() {
+        test_init();
+        let ssrc = 0x12345678;
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtprecv")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let mut h = gst_check::Harness::with_element(&rtpbin2, Some("rtp_sink_0"), None);
+        let session = h
+            .element()
+            .unwrap()
+            .emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        h.play();
+        h.set_src_caps_str("application/x-rtp,payload=96,clock-rate=90000");
+        let mut segment = gst::Segment::new();
+        segment.set_format(gst::Format::Time);
+        h.push_event(gst::event::Segment::builder(&segment).build());
+
+        // Unknown sender
+        session.set_property("rtp-profile", Profile::Avpf);
+        assert!(!session.emit_by_name::<bool>("request-key-unit", &[&ssrc, &false]));
+
+        let buf = gst::Buffer::from_mut_slice(generate_rtp_packet(ssrc, 0x34, 0x10, 16));
+        let _ = h.push(buf);
+
+        assert!(session.emit_by_name::<bool>("request-key-unit", &[&ssrc, &false]));
+        assert!(session.emit_by_name::<bool>("request-key-unit", &[&ssrc, &true]));
+
+        // Not possible without feedback messages
+        session.set_property("rtp-profile", Profile::Avp);
+        assert!(!session.emit_by_name::<bool>("request-key-unit", &[&ssrc, &false]));
+    }
+
     #[test]
     fn bye_send_ssrc() {
         test_init();
@@ -312,4 +1797,63 @@ mod tests {
         let _rtcp = h_rtcp.pull().unwrap();
         assert_eq!(bye_ssrc_receiver.recv().unwrap(), ssrc);
     }
+
+    #[test]
+    fn send_bye() {
+        test_init();
+        let ssrc = 0x12345678;
+        let (bye_ssrc_sender, bye_ssrc_receiver) = std::sync::mpsc::sync_channel(16);
+        let id = next_element_counter();
+        let rtpbin2 = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .build()
+            .unwrap();
+        let mut h =
+            gst_check::Harness::with_element(&rtpbin2, Some("rtp_sink_0"), Some("rtp_src_0"));
+        let mut h_rtcp = gst_check::Harness::with_element(&rtpbin2, None, Some("rtcp_src_0"));
+        let session = h
+            .element()
+            .unwrap()
+            .emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        assert_eq!(session.property::<String>("bye-reason"), DEFAULT_BYE_REASON);
+        session.set_property("bye-reason", "Muted");
+        assert_eq!(session.property::<String>("bye-reason"), "Muted");
+        session.connect("bye-ssrc", false, move |args| {
+            let bye_ssrc = args[1].get::<u32>().unwrap();
+            bye_ssrc_sender.send(bye_ssrc).unwrap();
+            None
+        });
+        h.set_src_caps_str("application/x-rtp,payload=96,clock-rate=90000");
+        let mut segment = gst::Segment::new();
+        segment.set_format(gst::Format::Time);
+        h.push_event(gst::event::Segment::builder(&segment).build());
+
+        // Unknown sender
+        assert!(!session.emit_by_name::<bool>("send-bye", &[&ssrc, &None::<String>]));
+
+        let buf = gst::Buffer::from_mut_slice(generate_rtp_packet(ssrc, 0x34, 0x10, 16));
+        h.push(buf.clone()).unwrap();
+        assert_eq!(h.pull().unwrap(), buf);
+
+        assert!(session.emit_by_name::<bool>("send-bye", &[&ssrc, &None::<String>]));
+
+        // A report may have been scheduled before the BYE
+        let mut received_bye = false;
+        for _ in 0..2 {
+            let rtcp = h_rtcp.pull().unwrap();
+            let rtcp = rtcp.map_readable().unwrap();
+            for p in rtcp_types::Compound::parse(&rtcp).unwrap() {
+                if let Ok(rtcp_types::Packet::Bye(bye)) = p {
+                    assert_eq!(bye.ssrcs().next(), Some(ssrc));
+                    assert_eq!(bye.reason(), Some(b"Muted".as_ref()));
+                    received_bye = true;
+                }
+            }
+            if received_bye {
+                break;
+            }
+        }
+        assert!(received_bye);
+        assert_eq!(bye_ssrc_receiver.recv().unwrap(), ssrc);
+    }
 }
@@ -0,0 +1,217 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Injection of RFC 4733 telephone events into an outgoing audio stream. The events share the
+//! SSRC, sequence number space and RTP clock of the audio stream, so all following audio packets
+//! have their sequence number shifted by the number of injected packets.
+
+use std::collections::HashMap;
+
+use crate::dtmf::event::{TelephoneEvent, END_PACKET_REPETITIONS};
+
+#[derive(Debug)]
+struct PendingEvent {
+    pt: u8,
+    ssrc: Option<u32>,
+    event: u8,
+    volume: u8,
+}
+
+#[derive(Debug)]
+struct ActiveEvent {
+    pt: u8,
+    ssrc: u32,
+    start_timestamp: u32,
+    event: TelephoneEvent,
+    sent_first: bool,
+    stopping: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct DtmfInjector {
+    /// Event to start with the next outgoing packet of a matching SSRC
+    pending: Option<PendingEvent>,
+    /// Whether the pending event was already stopped before it was started
+    pending_stop: bool,
+    active: Option<ActiveEvent>,
+    /// Number of packets injected per SSRC so far
+    seqnum_offsets: HashMap<u32, u16>,
+}
+
+impl DtmfInjector {
+    /// Start a new event with the next outgoing packet of `ssrc`, or of any SSRC if `None`.
+    ///
+    /// Returns `false` if another event is still running.
+    pub fn start(&mut self, pt: u8, ssrc: Option<u32>, event: u8, volume: u8) -> bool {
+        if self.pending.is_some() || self.active.is_some() {
+            return false;
+        }
+
+        self.pending = Some(PendingEvent {
+            pt,
+            ssrc,
+            event,
+            volume,
+        });
+        self.pending_stop = false;
+
+        true
+    }
+
+    /// Stop the current event with the next outgoing packet of its SSRC.
+    ///
+    /// Returns `false` if there is no event to stop.
+    pub fn stop(&mut self) -> bool {
+        if let Some(active) = self.active.as_mut() {
+            active.stopping = true;
+            true
+        } else if self.pending.is_some() {
+            self.pending_stop = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Whether outgoing packets need to be passed through [`Self::process`]
+    pub fn is_idle(&self) -> bool {
+        self.pending.is_none() && self.active.is_none() && self.seqnum_offsets.is_empty()
+    }
+
+    /// Handle an outgoing packet. Returns the new sequence number of the packet and the
+    /// telephone-event packets to send before it.
+    pub fn process(&mut self, ssrc: u32, seqnum: u16, timestamp: u32) -> (u16, Vec<Vec<u8>>) {
+        let mut payloads = vec![];
+
+        if self.pending.as_ref().is_some_and(|pending| {
+            pending
+                .ssrc
+                .map_or(true, |pending_ssrc| pending_ssrc == ssrc)
+        }) {
+            // The event starts with the timestamp of this packet and the first packet is sent
+            // once some duration has passed, i.e. with the following audio packet
+            let pending = self.pending.take().unwrap();
+            self.active = Some(ActiveEvent {
+                pt: pending.pt,
+                ssrc,
+                start_timestamp: timestamp,
+                event: TelephoneEvent {
+                    event: pending.event,
+                    end: false,
+                    volume: pending.volume,
+                    duration: 0,
+                },
+                sent_first: false,
+                stopping: std::mem::take(&mut self.pending_stop),
+            });
+        } else if let Some(active) = self.active.as_mut().filter(|active| active.ssrc == ssrc) {
+            let duration = timestamp.wrapping_sub(active.start_timestamp);
+            active.event.duration = duration.min(u16::MAX as u32) as u16;
+
+            if active.stopping || duration >= u16::MAX as u32 {
+                active.event.end = true;
+                for _ in 0..END_PACKET_REPETITIONS {
+                    payloads.push((!active.sent_first, active.event.write()));
+                    active.sent_first = true;
+                }
+            } else {
+                payloads.push((!active.sent_first, active.event.write()));
+                active.sent_first = true;
+            }
+        }
+
+        let Some(active) = self.active.as_ref().filter(|active| active.ssrc == ssrc) else {
+            let offset = self.seqnum_offsets.get(&ssrc).copied().unwrap_or(0);
+            return (seqnum.wrapping_add(offset), vec![]);
+        };
+
+        let offset = self.seqnum_offsets.entry(ssrc).or_default();
+        let packets = payloads
+            .into_iter()
+            .map(|(marker, payload)| {
+                let packet = rtp_types::RtpPacketBuilder::new()
+                    .payload_type(active.pt)
+                    .sequence_number(seqnum.wrapping_add(*offset))
+                    .timestamp(active.start_timestamp)
+                    .ssrc(ssrc)
+                    .marker_bit(marker)
+                    .payload(payload.as_slice())
+                    .write_vec()
+                    .unwrap();
+                *offset = offset.wrapping_add(1);
+                packet
+            })
+            .collect();
+
+        if active.event.end {
+            self.active = None;
+        }
+
+        (seqnum.wrapping_add(*offset), packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(packet: &[u8]) -> (u16, u32, bool, TelephoneEvent) {
+        let rtp = rtp_types::RtpPacket::parse(packet).unwrap();
+        assert_eq!(rtp.payload_type(), 101);
+        assert_eq!(rtp.ssrc(), 0x1234);
+        (
+            rtp.sequence_number(),
+            rtp.timestamp(),
+            rtp.marker_bit(),
+            TelephoneEvent::parse(rtp.payload()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn inject_event() {
+        let mut injector = DtmfInjector::default();
+        assert!(injector.is_idle());
+        assert_eq!(injector.process(0x1234, 10, 1000), (10, vec![]));
+
+        assert!(injector.start(101, None, 5, 10));
+        assert!(!injector.start(101, None, 6, 10));
+
+        // The event starts with this packet
+        assert_eq!(injector.process(0x1234, 11, 1160), (11, vec![]));
+
+        let (seqnum, packets) = injector.process(0x1234, 12, 1320);
+        assert_eq!(seqnum, 13);
+        assert_eq!(packets.len(), 1);
+        let (event_seqnum, timestamp, marker, event) = parse(&packets[0]);
+        assert_eq!((event_seqnum, timestamp, marker), (12, 1160, true));
+        assert_eq!(
+            event,
+            TelephoneEvent {
+                event: 5,
+                end: false,
+                volume: 10,
+                duration: 160,
+            }
+        );
+
+        // Other SSRCs are not affected
+        assert_eq!(injector.process(0x5678, 100, 0), (100, vec![]));
+
+        assert!(injector.stop());
+        let (seqnum, packets) = injector.process(0x1234, 13, 1480);
+        assert_eq!(seqnum, 17);
+        assert_eq!(packets.len(), END_PACKET_REPETITIONS);
+        for (i, packet) in packets.iter().enumerate() {
+            let (event_seqnum, timestamp, marker, event) = parse(packet);
+            assert_eq!(
+                (event_seqnum, timestamp, marker),
+                (14 + i as u16, 1160, false)
+            );
+            assert!(event.end);
+            assert_eq!(event.duration, 320);
+        }
+
+        // All following packets are shifted
+        assert!(!injector.stop());
+        assert_eq!(injector.process(0x1234, 14, 1640), (18, vec![]));
+    }
+}
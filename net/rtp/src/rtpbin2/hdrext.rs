@@ -0,0 +1,249 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Parsing and writing of RTP header extensions as specified in RFC 8285.
+
+pub const ONE_BYTE_PATTERN: u16 = 0xBEDE;
+pub const TWO_BYTE_PATTERN: u16 = 0x1000;
+
+/// URI of the MID header extension (RFC 8843)
+pub const MID_URI: &str = "urn:ietf:params:rtp-hdrext:sdes:mid";
+/// URI of the RtpStreamId header extension (RFC 8852)
+pub const RTP_STREAM_ID_URI: &str = "urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id";
+/// URI of the abs-send-time header extension
+pub const ABS_SEND_TIME_URI: &str = "http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time";
+/// Size of the abs-send-time header extension element
+pub const ABS_SEND_TIME_LEN: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtensionFormat {
+    OneByte,
+    TwoByte,
+}
+
+impl ExtensionFormat {
+    /// The format of the extension elements for the provided extension profile, if supported
+    pub fn from_pattern(pattern: u16) -> Option<Self> {
+        match pattern {
+            ONE_BYTE_PATTERN => Some(Self::OneByte),
+            x if x >> 4 == TWO_BYTE_PATTERN >> 4 => Some(Self::TwoByte),
+            _ => None,
+        }
+    }
+
+    /// The extension profile to put into the RTP header for this format
+    pub fn pattern(self) -> u16 {
+        match self {
+            Self::OneByte => ONE_BYTE_PATTERN,
+            Self::TwoByte => TWO_BYTE_PATTERN,
+        }
+    }
+
+    /// The size of the header in front of each extension element
+    pub fn header_len(self) -> usize {
+        match self {
+            Self::OneByte => 1,
+            Self::TwoByte => 2,
+        }
+    }
+
+    /// Whether an extension element with the provided id and length can be represented
+    pub fn can_represent(self, id: u8, len: usize) -> bool {
+        match self {
+            Self::OneByte => (1..=14).contains(&id) && (1..=16).contains(&len),
+            Self::TwoByte => id >= 1 && len <= 255,
+        }
+    }
+
+    /// Write the header of an extension element into `data`. `data` must have at least
+    /// `header_len()` bytes available.
+    pub fn write_header(self, data: &mut [u8], id: u8, len: usize) {
+        match self {
+            Self::OneByte => {
+                assert!(self.can_represent(id, len));
+                data[0] = (id << 4) | (len as u8 - 1);
+            }
+            Self::TwoByte => {
+                assert!(self.can_represent(id, len));
+                data[0] = id;
+                data[1] = len as u8;
+            }
+        }
+    }
+}
+
+/// Iterator over the extension elements contained in the extension data of an RTP packet
+#[derive(Debug, Clone)]
+pub struct ExtensionIter<'a> {
+    format: ExtensionFormat,
+    data: &'a [u8],
+}
+
+impl<'a> ExtensionIter<'a> {
+    /// Returns `None` if the extension profile is not one of the RFC 8285 formats
+    pub fn new(pattern: u16, data: &'a [u8]) -> Option<Self> {
+        let format = ExtensionFormat::from_pattern(pattern)?;
+        Some(Self { format, data })
+    }
+
+    pub fn format(&self) -> ExtensionFormat {
+        self.format
+    }
+}
+
+/// Parse the value of a SDES header extension element like the MID or RtpStreamId
+pub fn parse_sdes_value(element: &[u8]) -> Option<&str> {
+    // Some implementations pad the value with zeroes
+    let end = element
+        .iter()
+        .position(|&b| b == 0)
+        .unwrap_or(element.len());
+    let value = std::str::from_utf8(&element[..end]).ok()?;
+    (!value.is_empty()).then_some(value)
+}
+
+impl<'a> Iterator for ExtensionIter<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (&first, rest) = self.data.split_first()?;
+
+            let (id, len, rest) = match self.format {
+                ExtensionFormat::OneByte => {
+                    let id = first >> 4;
+                    // Padding
+                    if id == 0 {
+                        self.data = rest;
+                        continue;
+                    }
+                    // Reserved for future extensions, stop processing
+                    if id == 15 {
+                        self.data = &[];
+                        return None;
+                    }
+                    (id, (first & 0x0f) as usize + 1, rest)
+                }
+                ExtensionFormat::TwoByte => {
+                    // Padding
+                    if first == 0 {
+                        self.data = rest;
+                        continue;
+                    }
+                    let Some((&len, rest)) = rest.split_first() else {
+                        self.data = &[];
+                        return None;
+                    };
+                    (first, len as usize, rest)
+                }
+            };
+
+            if rest.len() < len {
+                trace!("Truncated header extension element with id {id} and length {len}");
+                self.data = &[];
+                return None;
+            }
+
+            let (element, rest) = rest.split_at(len);
+            self.data = rest;
+
+            return Some((id, element));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_pattern() {
+        assert_eq!(
+            ExtensionFormat::from_pattern(0xBEDE),
+            Some(ExtensionFormat::OneByte)
+        );
+        assert_eq!(
+            ExtensionFormat::from_pattern(0x1000),
+            Some(ExtensionFormat::TwoByte)
+        );
+        // The lower 4 bits are application specific
+        assert_eq!(
+            ExtensionFormat::from_pattern(0x100f),
+            Some(ExtensionFormat::TwoByte)
+        );
+        assert_eq!(ExtensionFormat::from_pattern(0x1234), None);
+    }
+
+    #[test]
+    fn can_represent() {
+        assert!(ExtensionFormat::OneByte.can_represent(1, 1));
+        assert!(ExtensionFormat::OneByte.can_represent(14, 16));
+        assert!(!ExtensionFormat::OneByte.can_represent(0, 1));
+        assert!(!ExtensionFormat::OneByte.can_represent(15, 1));
+        assert!(!ExtensionFormat::OneByte.can_represent(1, 0));
+        assert!(!ExtensionFormat::OneByte.can_represent(1, 17));
+        assert!(ExtensionFormat::TwoByte.can_represent(1, 0));
+        assert!(ExtensionFormat::TwoByte.can_represent(255, 255));
+        assert!(!ExtensionFormat::TwoByte.can_represent(0, 1));
+        assert!(!ExtensionFormat::TwoByte.can_represent(1, 256));
+    }
+
+    #[test]
+    fn parse_one_byte() {
+        let data = [0x10, 0xaa, 0x00, 0x21, 0xbb, 0xcc, 0x00, 0x00];
+        let iter = ExtensionIter::new(0xBEDE, &data).unwrap();
+        assert_eq!(iter.format(), ExtensionFormat::OneByte);
+        let elements = iter.collect::<Vec<_>>();
+        assert_eq!(elements, vec![(1, &[0xaa][..]), (2, &[0xbb, 0xcc][..])]);
+    }
+
+    #[test]
+    fn parse_one_byte_reserved_id() {
+        let data = [0x10, 0xaa, 0xf0, 0x21, 0xbb, 0xcc, 0x00, 0x00];
+        let elements = ExtensionIter::new(0xBEDE, &data)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert_eq!(elements, vec![(1, &[0xaa][..])]);
+    }
+
+    #[test]
+    fn parse_two_byte() {
+        let data = [0x01, 0x00, 0x00, 0x20, 0x02, 0xbb, 0xcc, 0x00];
+        let iter = ExtensionIter::new(0x1000, &data).unwrap();
+        assert_eq!(iter.format(), ExtensionFormat::TwoByte);
+        let elements = iter.collect::<Vec<_>>();
+        assert_eq!(elements, vec![(1, &[][..]), (0x20, &[0xbb, 0xcc][..])]);
+    }
+
+    #[test]
+    fn parse_truncated() {
+        let data = [0x13, 0xaa, 0xbb];
+        let elements = ExtensionIter::new(0xBEDE, &data)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert!(elements.is_empty());
+
+        let data = [0x01];
+        let elements = ExtensionIter::new(0x1000, &data)
+            .unwrap()
+            .collect::<Vec<_>>();
+        assert!(elements.is_empty());
+    }
+
+    #[test]
+    fn write_header() {
+        let mut data = [0u8; 2];
+        ExtensionFormat::OneByte.write_header(&mut data, 3, 4);
+        assert_eq!(data[0], 0x33);
+
+        ExtensionFormat::TwoByte.write_header(&mut data, 3, 4);
+        assert_eq!(data, [0x03, 0x04]);
+    }
+
+    #[test]
+    fn sdes_value() {
+        assert_eq!(parse_sdes_value(b"audio"), Some("audio"));
+        assert_eq!(parse_sdes_value(b"v\0\0"), Some("v"));
+        assert_eq!(parse_sdes_value(b"\0"), None);
+        assert_eq!(parse_sdes_value(&[0xff, 0xfe]), None);
+    }
+}
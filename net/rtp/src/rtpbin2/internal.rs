@@ -1,18 +1,23 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     sync::{Arc, Mutex},
     task::Waker,
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
-use gst::glib;
+use futures::future::{AbortHandle, Abortable};
+use gst::{glib, prelude::*};
 use std::sync::{LazyLock, OnceLock};
 
 use super::config::Rtp2Session;
+use super::hdrext::{parse_sdes_value, ExtensionFormat, ExtensionIter, ABS_SEND_TIME_LEN};
 use super::session::{RtpProfile, Session};
-use super::source::ReceivedRb;
+use super::source::{ReceivedDlrr, ReceivedRb};
+use super::time::system_time_to_ntp_time_u64;
+use super::twcc::TWCC_EXT_LEN;
+use super::RUNTIME;
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -142,6 +147,38 @@ impl SharedSession {
             config: Rtp2Session::new(weak_inner),
         }
     }
+
+    /// Resolve a collision of the local `ssrc` with another participant by sending with a new SSRC
+    /// from now on, and notify the application about it. Returns the new SSRC unless it was
+    /// already changed before.
+    ///
+    /// Must not be called with the session lock held.
+    pub(crate) fn handle_ssrc_collision(&self, ssrc: u32) -> Option<u32> {
+        let new_ssrc = self.inner.lock().unwrap().session.change_local_ssrc(ssrc);
+        if let Some(new_ssrc) = new_ssrc {
+            self.config
+                .emit_by_name::<()>("on-ssrc-collision", &[&ssrc, &new_ssrc]);
+        }
+        new_ssrc
+    }
+
+    /// Notify the application about a new SSRC in this session, both via signals on the session
+    /// object and via an element message from `element`.
+    ///
+    /// Must not be called with the session lock held.
+    pub(crate) fn notify_new_ssrc(&self, element: &gst::Element, ssrc: u32, local: bool) {
+        self.config.emit_by_name::<()>("new-ssrc", &[&ssrc]);
+        if local {
+            self.config.emit_by_name::<()>("new-sender-ssrc", &[&ssrc]);
+        }
+
+        let s = gst::Structure::builder("rtp-new-ssrc")
+            .field("session", self.id as u32)
+            .field("ssrc", ssrc)
+            .field("local", local)
+            .build();
+        let _ = element.post_message(gst::message::Element::builder(s).src(element).build());
+    }
 }
 
 #[derive(Debug)]
@@ -151,6 +188,21 @@ pub(crate) struct SharedSessionInner {
     pub(crate) session: Session,
 
     pub(crate) pt_map: HashMap<u8, gst::Caps>,
+    // Currently enabled RTP header extensions by ID
+    pub(crate) extensions: BTreeMap<u8, gst_rtp::RTPHeaderExtension>,
+    // IDs of the header extensions used for BUNDLE demuxing
+    pub(crate) mid_ext_id: Option<u8>,
+    pub(crate) rid_ext_id: Option<u8>,
+    // ID of the abs-send-time header extension to write into sent packets
+    pub(crate) abs_send_time_ext_id: Option<u8>,
+    // ID of the transport-wide sequence number header extension of sent packets
+    pub(crate) twcc_ext_id: Option<u8>,
+    // Next transport-wide sequence number, shared by all sent SSRCs of the session
+    pub(crate) twcc_seqnum: u16,
+    // Offset in ns added to the PTS of received packets
+    pub(crate) ts_offset: i64,
+    // Whether received packets bypass the jitterbuffer
+    pub(crate) forward: bool,
 
     pub(crate) rtcp_waker: Option<Waker>,
     pub(crate) rtp_send_sinkpad: Option<gst::Pad>,
@@ -164,6 +216,14 @@ impl SharedSessionInner {
             session: Session::new(),
 
             pt_map: HashMap::default(),
+            extensions: BTreeMap::new(),
+            mid_ext_id: None,
+            rid_ext_id: None,
+            abs_send_time_ext_id: None,
+            twcc_ext_id: None,
+            twcc_seqnum: 0,
+            ts_offset: 0,
+            forward: false,
             rtcp_waker: None,
             rtp_send_sinkpad: None,
         }
@@ -193,153 +253,585 @@ impl SharedSessionInner {
         )
     }
 
+    /// Caps for the payload type `pt` of `ssrc`, with the clock-rate set for that SSRC if any
+    pub(crate) fn caps_from_ssrc_pt(&self, ssrc: u32, pt: u8) -> gst::Caps {
+        let mut caps = self.caps_from_pt(pt);
+        if let Some(clock_rate) = self.session.clock_rate_from_ssrc(ssrc) {
+            caps.make_mut().set("clock-rate", clock_rate as i32);
+        }
+        caps
+    }
+
     pub fn pt_map(&self) -> impl Iterator<Item = (u8, &gst::Caps)> + '_ {
         self.pt_map.iter().map(|(&k, v)| (k, v))
     }
 
-    pub fn stats(&self) -> gst::Structure {
-        let mut session_stats = gst::Structure::builder("application/x-rtpbin2-session-stats")
-            .field("id", self.id as u64);
-        for ssrc in self.session.ssrcs() {
-            if let Some(ls) = self.session.local_send_source_by_ssrc(ssrc) {
-                let mut source_stats =
-                    gst::Structure::builder("application/x-rtpbin2-source-stats")
-                        .field("ssrc", ls.ssrc())
-                        .field("sender", true)
-                        .field("local", true)
-                        .field("packets-sent", ls.packet_count())
-                        .field("octets-sent", ls.octet_count())
-                        .field("bitrate", ls.bitrate() as u64);
-                if let Some(pt) = ls.payload_type() {
-                    if let Some(clock_rate) = self.session.clock_rate_from_pt(pt) {
-                        source_stats = source_stats.field("clock-rate", clock_rate);
-                    }
+    /// Read the header extension elements of a received packet with all enabled extensions,
+    /// e.g. adding metas to `buffer`
+    pub(crate) fn read_header_extensions(
+        &self,
+        pattern: u16,
+        data: &[u8],
+        buffer: &mut gst::BufferRef,
+    ) {
+        let Some(elements) = ExtensionIter::new(pattern, data) else {
+            gst::trace!(CAT, "Unknown extension pattern {pattern:04X}");
+            return;
+        };
+        let flags = extension_flags(elements.format());
+
+        for (id, element) in elements {
+            let Some(extension) = self.extensions.get(&id) else {
+                continue;
+            };
+
+            if !extension.read(flags, element, buffer) {
+                gst::warning!(
+                    CAT,
+                    "Failed reading RTP header extension with id {id} and length {}",
+                    element.len()
+                );
+            }
+        }
+    }
+
+    /// Whether received packets need to be checked for the MID or RtpStreamId header extensions
+    pub(crate) fn has_bundle_extensions(&self) -> bool {
+        self.mid_ext_id.is_some() || self.rid_ext_id.is_some()
+    }
+
+    /// Update the MID and RtpStreamId associations of `ssrc` from the header extension elements of
+    /// a received packet
+    pub(crate) fn read_bundle_ids(&mut self, ssrc: u32, pattern: u16, data: &[u8]) {
+        if !self.has_bundle_extensions() {
+            return;
+        }
+        let Some(elements) = ExtensionIter::new(pattern, data) else {
+            return;
+        };
+
+        for (id, element) in elements {
+            if Some(id) == self.mid_ext_id {
+                if let Some(mid) = parse_sdes_value(element) {
+                    self.session.set_ssrc_mid(ssrc, mid);
                 }
-                if let Some(sr) = ls.last_sent_sr() {
-                    source_stats = source_stats
-                        .field("sr-ntptime", sr.ntp_timestamp().as_u64())
-                        .field("sr-rtptime", sr.rtp_timestamp())
-                        .field("sr-octet-count", sr.octet_count())
-                        .field("sr-packet-count", sr.packet_count());
+            } else if Some(id) == self.rid_ext_id {
+                if let Some(rid) = parse_sdes_value(element) {
+                    self.session.set_ssrc_rid(ssrc, rid);
                 }
-                let rbs = gst::List::new(ls.received_report_blocks().map(
-                    |(sender_ssrc, ReceivedRb { rb, .. })| {
-                        gst::Structure::builder("application/x-rtcp-report-block")
-                            .field("sender-ssrc", sender_ssrc)
-                            .field("rb-fraction-lost", rb.fraction_lost())
-                            .field("rb-packets-lost", rb.cumulative_lost())
-                            .field("rb-extended_sequence_number", rb.extended_sequence_number())
-                            .field("rb-jitter", rb.jitter())
-                            .field("rb-last-sr-ntp-time", rb.last_sr_ntp_time())
-                            .field("rb-delay_since_last-sr-ntp-time", rb.delay_since_last_sr())
-                            .build()
-                    },
-                ));
-                match rbs.len() {
-                    0 => (),
-                    1 => {
-                        source_stats =
-                            source_stats.field("report-blocks", rbs.first().unwrap().clone());
+            }
+        }
+    }
+
+    /// Write all enabled header extensions into an outgoing packet. Header extension elements
+    /// already present in the packet are kept unless their ID is used by an enabled extension.
+    ///
+    /// If the abs-send-time header extension is configured and not provided by an enabled
+    /// extension, it is written with the current time.
+    ///
+    /// If the transport-wide sequence number header extension is configured, it is always
+    /// written by the session so that the sequence numbers are consecutive over all SSRCs,
+    /// and the packet is tracked for transport-wide congestion control.
+    pub(crate) fn write_header_extensions(
+        &mut self,
+        buffer: gst::Buffer,
+        now: Instant,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let twcc_ext_id = self.twcc_ext_id;
+        let abs_send_time_ext_id = self
+            .abs_send_time_ext_id
+            .filter(|id| !self.extensions.contains_key(id) && Some(*id) != twcc_ext_id);
+        // The transport-wide sequence number is not written by extension implementations
+        let extensions = self
+            .extensions
+            .iter()
+            .filter(|(id, _)| Some(**id) != twcc_ext_id)
+            .collect::<smallvec::SmallVec<[_; 8]>>();
+        if extensions.is_empty() && abs_send_time_ext_id.is_none() && twcc_ext_id.is_none() {
+            return Ok(buffer);
+        }
+
+        let mapped = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
+        let rtp = rtp_types::RtpPacket::parse(&mapped).map_err(|err| {
+            gst::error!(CAT, "Failed to parse RTP packet: {err:?}");
+            gst::FlowError::Error
+        })?;
+
+        // Keep any extension elements that were added upstream
+        let existing = rtp
+            .extension()
+            .and_then(|(pattern, data)| ExtensionIter::new(pattern, data))
+            .map(|elements| {
+                elements
+                    .filter(|(id, _)| {
+                        !self.extensions.contains_key(id)
+                            && Some(*id) != abs_send_time_ext_id
+                            && Some(*id) != twcc_ext_id
+                    })
+                    .collect::<smallvec::SmallVec<[_; 4]>>()
+            })
+            .unwrap_or_default();
+
+        let mut format = Some(ExtensionFormat::OneByte);
+        let mut extension_size = 0;
+        for &(id, element) in existing.iter() {
+            if format == Some(ExtensionFormat::OneByte)
+                && !ExtensionFormat::OneByte.can_represent(id, element.len())
+            {
+                format = Some(ExtensionFormat::TwoByte);
+            }
+            extension_size += element.len();
+        }
+        if let Some(id) = abs_send_time_ext_id {
+            if !ExtensionFormat::OneByte.can_represent(id, ABS_SEND_TIME_LEN) {
+                format = Some(ExtensionFormat::TwoByte);
+            }
+            extension_size += ABS_SEND_TIME_LEN;
+        }
+        if let Some(id) = twcc_ext_id {
+            if !ExtensionFormat::OneByte.can_represent(id, TWCC_EXT_LEN) {
+                format = Some(ExtensionFormat::TwoByte);
+            }
+            extension_size += TWCC_EXT_LEN;
+        }
+        for &(&id, extension) in extensions.iter() {
+            let max_size = extension.max_size(&buffer);
+            let supported = extension.supported_flags();
+            if format == Some(ExtensionFormat::OneByte)
+                && (!supported.contains(gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE)
+                    || !ExtensionFormat::OneByte.can_represent(id, max_size.max(1)))
+            {
+                format = Some(ExtensionFormat::TwoByte);
+            }
+            if format == Some(ExtensionFormat::TwoByte)
+                && (!supported.contains(gst_rtp::RTPHeaderExtensionFlags::TWO_BYTE)
+                    || !ExtensionFormat::TwoByte.can_represent(id, max_size))
+            {
+                format = None;
+            }
+            extension_size += max_size;
+        }
+
+        let Some(format) = format else {
+            gst::warning!(
+                CAT,
+                "Enabled RTP header extensions can't be written in a common format"
+            );
+            drop(existing);
+            drop(mapped);
+            return Ok(buffer);
+        };
+
+        let n_elements = existing.len()
+            + extensions.len()
+            + abs_send_time_ext_id.iter().count()
+            + twcc_ext_id.iter().count();
+        extension_size += n_elements * format.header_len();
+        // Round up to a multiple of 4 bytes
+        extension_size = extension_size.div_ceil(4) * 4;
+
+        // Zeroes are padding inside the extension data so unused space can simply stay empty
+        let mut extension_data = smallvec::SmallVec::<[u8; 256]>::from_elem(0, extension_size);
+        let mut offset = 0;
+        for &(id, element) in existing.iter() {
+            format.write_header(&mut extension_data[offset..], id, element.len());
+            offset += format.header_len();
+            extension_data[offset..][..element.len()].copy_from_slice(element);
+            offset += element.len();
+        }
+        if let Some(id) = abs_send_time_ext_id {
+            let abs_send_time = system_time_to_ntp_time_u64(SystemTime::now()).as_abs_send_time();
+            format.write_header(&mut extension_data[offset..], id, ABS_SEND_TIME_LEN);
+            offset += format.header_len();
+            extension_data[offset..][..ABS_SEND_TIME_LEN]
+                .copy_from_slice(&abs_send_time.to_be_bytes()[1..]);
+            offset += ABS_SEND_TIME_LEN;
+        }
+        let mut twcc_seqnum = None;
+        if let Some(id) = twcc_ext_id {
+            let seqnum = self.twcc_seqnum;
+            self.twcc_seqnum = seqnum.wrapping_add(1);
+            format.write_header(&mut extension_data[offset..], id, TWCC_EXT_LEN);
+            offset += format.header_len();
+            extension_data[offset..][..TWCC_EXT_LEN].copy_from_slice(&seqnum.to_be_bytes());
+            offset += TWCC_EXT_LEN;
+            twcc_seqnum = Some(seqnum);
+        }
+
+        let mut builder = rtp_types::RtpPacketBuilder::new()
+            .marker_bit(rtp.marker_bit())
+            .payload_type(rtp.payload_type())
+            .sequence_number(rtp.sequence_number())
+            .timestamp(rtp.timestamp())
+            .ssrc(rtp.ssrc())
+            .extension(format.pattern(), extension_data.as_slice())
+            .payload(rtp.payload());
+        for csrc in rtp.csrc() {
+            builder = builder.add_csrc(csrc);
+        }
+        if let Some(padding) = rtp.padding() {
+            builder = builder.padding(padding);
+        }
+        let packet = builder.write_vec().map_err(|err| {
+            gst::error!(CAT, "Can't write packet: {err}");
+            gst::FlowError::Error
+        })?;
+
+        let mut output = gst::Buffer::from_mut_slice(packet);
+        {
+            let output = output.get_mut().unwrap();
+            buffer
+                .copy_into(output, gst::BufferCopyFlags::METADATA, ..)
+                .map_err(|_| gst::FlowError::Error)?;
+
+            // FIXME Get a mutable reference to the output buffer via a pointer to
+            // work around bug in the C API.
+            // See https://gitlab.freedesktop.org/gstreamer/gstreamer-rs/-/issues/375
+            let output_ptr = output.as_mut_ptr();
+
+            let mut map = output.map_writable().unwrap();
+            let mut packet = rtp_types::RtpPacketMut::parse(&mut map).unwrap();
+            let mut extension_data = &mut packet.extension_mut().unwrap()[offset..];
+
+            let flags = extension_flags(format);
+            for &(&id, extension) in extensions.iter() {
+                if extension_data.len() < format.header_len() {
+                    gst::error!(CAT, "No space left for writing RTP header extension {id}");
+                    break;
+                }
+
+                match extension.write(
+                    &buffer,
+                    flags,
+                    unsafe { gst::BufferRef::from_mut_ptr(output_ptr) },
+                    &mut extension_data[format.header_len()..],
+                ) {
+                    // Nothing written, can just continue
+                    Ok(0) => continue,
+                    Ok(written) => {
+                        format.write_header(extension_data, id, written);
+                        extension_data = &mut extension_data[format.header_len() + written..];
                     }
-                    _ => {
-                        source_stats = source_stats.field("report-blocks", rbs);
+                    Err(_) => {
+                        gst::error!(CAT, "Writing RTP header extension {id} failed");
                     }
                 }
+            }
+        }
 
-                // TODO: add jitter, packets-lost
-                session_stats = session_stats.field(ls.ssrc().to_string(), source_stats.build());
-            } else if let Some(lr) = self.session.local_receive_source_by_ssrc(ssrc) {
-                let mut source_stats =
-                    gst::Structure::builder("application/x-rtpbin2-source-stats")
-                        .field("ssrc", lr.ssrc())
-                        .field("sender", false)
-                        .field("local", true);
-                if let Some(pt) = lr.payload_type() {
-                    if let Some(clock_rate) = self.session.clock_rate_from_pt(pt) {
-                        source_stats = source_stats.field("clock-rate", clock_rate);
-                    }
+        if let Some(seqnum) = twcc_seqnum {
+            self.session.twcc_packet_sent(seqnum, output.size(), now);
+        }
+
+        Ok(output)
+    }
+
+    pub fn stats(&self) -> gst::Structure {
+        let mut session_stats = gst::Structure::builder("application/x-rtpbin2-session-stats")
+            .field("id", self.id as u64);
+        if let Some(group_size) = self.session.rsi_group_size() {
+            session_stats = session_stats.field("rsi-group-size", group_size as u64);
+        }
+        for ssrc in self.session.ssrcs() {
+            if let Some(source_stats) = self.source_stats(ssrc) {
+                session_stats = session_stats.field(ssrc.to_string(), source_stats);
+            }
+        }
+
+        session_stats.build()
+    }
+
+    /// Statistics of a single source of this session, if known
+    pub fn source_stats(&self, ssrc: u32) -> Option<gst::Structure> {
+        if let Some(ls) = self.session.local_send_source_by_ssrc(ssrc) {
+            let mut source_stats = gst::Structure::builder("application/x-rtpbin2-source-stats")
+                .field("ssrc", ls.ssrc())
+                .field("sender", true)
+                .field("local", true)
+                .field("packets-sent", ls.packet_count())
+                .field("octets-sent", ls.octet_count())
+                .field("bitrate", ls.bitrate() as u64);
+            if let Some(pt) = ls.payload_type() {
+                if let Some(clock_rate) = self.session.clock_rate_from_ssrc_pt(ssrc, pt) {
+                    source_stats = source_stats.field("clock-rate", clock_rate);
                 }
-                // TODO: add rb stats
-                session_stats = session_stats.field(lr.ssrc().to_string(), source_stats.build());
-            } else if let Some(rs) = self.session.remote_send_source_by_ssrc(ssrc) {
-                let mut source_stats =
-                    gst::Structure::builder("application/x-rtpbin2-source-stats")
-                        .field("ssrc", rs.ssrc())
-                        .field("sender", true)
-                        .field("local", false)
-                        .field("octets-received", rs.octet_count())
-                        .field("packets-received", rs.packet_count())
-                        .field("bitrate", rs.bitrate() as u64)
-                        .field("jitter", rs.jitter())
-                        .field("packets-lost", rs.packets_lost());
-                if let Some(pt) = rs.payload_type() {
-                    if let Some(clock_rate) = self.session.clock_rate_from_pt(pt) {
-                        source_stats = source_stats.field("clock-rate", clock_rate);
-                    }
+            }
+            if let Some(sr) = ls.last_sent_sr() {
+                source_stats = source_stats
+                    .field("sr-ntptime", sr.ntp_timestamp().as_u64())
+                    .field("sr-rtptime", sr.rtp_timestamp())
+                    .field("sr-octet-count", sr.octet_count())
+                    .field("sr-packet-count", sr.packet_count());
+            }
+            let rbs = gst::List::new(ls.received_report_blocks().map(
+                |(sender_ssrc, received_rb)| {
+                    let rb = &received_rb.rb;
+                    gst::Structure::builder("application/x-rtcp-report-block")
+                        .field("sender-ssrc", sender_ssrc)
+                        .field("rb-fraction-lost", rb.fraction_lost())
+                        .field("rb-packets-lost", rb.cumulative_lost())
+                        .field("rb-extended_sequence_number", rb.extended_sequence_number())
+                        .field("rb-jitter", rb.jitter())
+                        .field("rb-last-sr-ntp-time", rb.last_sr_ntp_time())
+                        .field("rb-delay_since_last-sr-ntp-time", rb.delay_since_last_sr())
+                        .field(
+                            "rb-round-trip-time",
+                            received_rb.round_trip_time().as_nanos() as u64,
+                        )
+                        .build()
+                },
+            ));
+            match rbs.len() {
+                0 => (),
+                1 => {
+                    source_stats =
+                        source_stats.field("report-blocks", rbs.first().unwrap().clone());
                 }
-                if let Some(rtp_from) = rs.rtp_from() {
-                    source_stats = source_stats.field("rtp-from", rtp_from.to_string());
+                _ => {
+                    source_stats = source_stats.field("report-blocks", rbs);
                 }
-                if let Some(rtcp_from) = rs.rtcp_from() {
-                    source_stats = source_stats.field("rtcp-from", rtcp_from.to_string());
+            }
+
+            let dlrrs = dlrr_stats(ls.received_dlrr());
+            if !dlrrs.is_empty() {
+                source_stats = source_stats.field("xr-dlrr-blocks", dlrrs);
+            }
+
+            // TODO: add jitter, packets-lost
+            Some(source_stats.build())
+        } else if let Some(lr) = self.session.local_receive_source_by_ssrc(ssrc) {
+            let mut source_stats = gst::Structure::builder("application/x-rtpbin2-source-stats")
+                .field("ssrc", lr.ssrc())
+                .field("sender", false)
+                .field("local", true);
+            if let Some(pt) = lr.payload_type() {
+                if let Some(clock_rate) = self.session.clock_rate_from_ssrc_pt(ssrc, pt) {
+                    source_stats = source_stats.field("clock-rate", clock_rate);
                 }
-                if let Some(sr) = rs.last_received_sr() {
-                    source_stats = source_stats
-                        .field("sr-ntptime", sr.ntp_timestamp().as_u64())
-                        .field("sr-rtptime", sr.rtp_timestamp())
-                        .field("sr-octet-count", sr.octet_count())
-                        .field("sr-packet-count", sr.packet_count());
+            }
+            let dlrrs = dlrr_stats(lr.received_dlrr());
+            if !dlrrs.is_empty() {
+                source_stats = source_stats.field("xr-dlrr-blocks", dlrrs);
+            }
+            // TODO: add rb stats
+            Some(source_stats.build())
+        } else if let Some(rs) = self.session.remote_send_source_by_ssrc(ssrc) {
+            let mut source_stats = gst::Structure::builder("application/x-rtpbin2-source-stats")
+                .field("ssrc", rs.ssrc())
+                .field("sender", true)
+                .field("local", false)
+                .field("octets-received", rs.octet_count())
+                .field("packets-received", rs.packet_count())
+                .field("bitrate", rs.bitrate() as u64)
+                .field("jitter", rs.jitter())
+                .field("packets-lost", rs.packets_lost());
+            if let Some(pt) = rs.payload_type() {
+                if let Some(clock_rate) = self.session.clock_rate_from_ssrc_pt(ssrc, pt) {
+                    source_stats = source_stats.field("clock-rate", clock_rate);
                 }
-                if let Some(rb) = rs.last_sent_rb() {
-                    source_stats = source_stats
-                        .field("sent-rb-fraction-lost", rb.fraction_lost())
-                        .field("sent-rb-packets-lost", rb.cumulative_lost())
-                        .field(
-                            "sent-rb-extended-sequence-number",
-                            rb.extended_sequence_number(),
-                        )
-                        .field("sent-rb-jitter", rb.jitter())
-                        .field("sent-rb-last-sr-ntp-time", rb.last_sr_ntp_time())
-                        .field(
-                            "sent-rb-delay-since-last-sr-ntp-time",
-                            rb.delay_since_last_sr(),
-                        );
+            }
+            if let Some(rtp_from) = rs.rtp_from() {
+                source_stats = source_stats.field("rtp-from", rtp_from.to_string());
+            }
+            if let Some(rtcp_from) = rs.rtcp_from() {
+                source_stats = source_stats.field("rtcp-from", rtcp_from.to_string());
+            }
+            if let Some(rtt) = self.session.round_trip_time(rs.ssrc()) {
+                source_stats = source_stats.field("round-trip-time", rtt.as_nanos() as u64);
+            }
+            if let Some(sr) = rs.last_received_sr() {
+                source_stats = source_stats
+                    .field("sr-ntptime", sr.ntp_timestamp().as_u64())
+                    .field("sr-rtptime", sr.rtp_timestamp())
+                    .field("sr-octet-count", sr.octet_count())
+                    .field("sr-packet-count", sr.packet_count());
+            }
+            if let Some(rrtr) = rs.last_received_rrtr() {
+                source_stats = source_stats.field("xr-rrtr-ntptime", rrtr.ntp_timestamp.as_u64());
+            }
+            if let Some(rb) = rs.last_sent_rb() {
+                source_stats = source_stats
+                    .field("sent-rb-fraction-lost", rb.fraction_lost())
+                    .field("sent-rb-packets-lost", rb.cumulative_lost())
+                    .field(
+                        "sent-rb-extended-sequence-number",
+                        rb.extended_sequence_number(),
+                    )
+                    .field("sent-rb-jitter", rb.jitter())
+                    .field("sent-rb-last-sr-ntp-time", rb.last_sr_ntp_time())
+                    .field(
+                        "sent-rb-delay-since-last-sr-ntp-time",
+                        rb.delay_since_last_sr(),
+                    );
+            }
+            let rbs = gst::List::new(rs.received_report_blocks().map(
+                |(sender_ssrc, ReceivedRb { rb, .. })| {
+                    gst::Structure::builder("application/x-rtcp-report-block")
+                        .field("sender-ssrc", sender_ssrc)
+                        .field("rb-fraction-lost", rb.fraction_lost())
+                        .field("rb-packets-lost", rb.cumulative_lost())
+                        .field("rb-extended_sequence_number", rb.extended_sequence_number())
+                        .field("rb-jitter", rb.jitter())
+                        .field("rb-last-sr-ntp-time", rb.last_sr_ntp_time())
+                        .field("rb-delay_since_last-sr-ntp-time", rb.delay_since_last_sr())
+                        .build()
+                },
+            ));
+            match rbs.len() {
+                0 => (),
+                1 => {
+                    source_stats =
+                        source_stats.field("report-blocks", rbs.first().unwrap().clone());
                 }
-                let rbs = gst::List::new(rs.received_report_blocks().map(
-                    |(sender_ssrc, ReceivedRb { rb, .. })| {
-                        gst::Structure::builder("application/x-rtcp-report-block")
-                            .field("sender-ssrc", sender_ssrc)
-                            .field("rb-fraction-lost", rb.fraction_lost())
-                            .field("rb-packets-lost", rb.cumulative_lost())
-                            .field("rb-extended_sequence_number", rb.extended_sequence_number())
-                            .field("rb-jitter", rb.jitter())
-                            .field("rb-last-sr-ntp-time", rb.last_sr_ntp_time())
-                            .field("rb-delay_since_last-sr-ntp-time", rb.delay_since_last_sr())
-                            .build()
-                    },
-                ));
-                match rbs.len() {
-                    0 => (),
-                    1 => {
-                        source_stats =
-                            source_stats.field("report-blocks", rbs.first().unwrap().clone());
-                    }
-                    _ => {
-                        source_stats = source_stats.field("report-blocks", rbs);
-                    }
+                _ => {
+                    source_stats = source_stats.field("report-blocks", rbs);
                 }
-                session_stats = session_stats.field(rs.ssrc().to_string(), source_stats.build());
-            } else if let Some(rr) = self.session.remote_receive_source_by_ssrc(ssrc) {
-                let source_stats = gst::Structure::builder("application/x-rtpbin2-source-stats")
-                    .field("ssrc", rr.ssrc())
-                    .field("sender", false)
-                    .field("local", false)
-                    .build();
-                session_stats = session_stats.field(rr.ssrc().to_string(), source_stats);
             }
+            Some(source_stats.build())
+        } else if let Some(rr) = self.session.remote_receive_source_by_ssrc(ssrc) {
+            let mut source_stats = gst::Structure::builder("application/x-rtpbin2-source-stats")
+                .field("ssrc", rr.ssrc())
+                .field("sender", false)
+                .field("local", false);
+            if let Some(rtt) = self.session.round_trip_time(rr.ssrc()) {
+                source_stats = source_stats.field("round-trip-time", rtt.as_nanos() as u64);
+            }
+            if let Some(rrtr) = rr.last_received_rrtr() {
+                source_stats = source_stats.field("xr-rrtr-ntptime", rrtr.ntp_timestamp.as_u64());
+            }
+            Some(source_stats.build())
+        } else {
+            None
         }
+    }
+}
 
-        session_stats.build()
+fn dlrr_stats<'a>(dlrrs: impl Iterator<Item = (u32, &'a ReceivedDlrr)>) -> gst::List {
+    gst::List::new(dlrrs.map(|(sender_ssrc, dlrr)| {
+        gst::Structure::builder("application/x-rtcp-xr-dlrr")
+            .field("sender-ssrc", sender_ssrc)
+            .field("last-rr", dlrr.dlrr.last_rr)
+            .field("delay-since-last-rr", dlrr.dlrr.delay_since_last_rr)
+            .field("round-trip-time", dlrr.round_trip_time().as_nanos() as u64)
+            .build()
+    }))
+}
+
+/// Task periodically posting statistics as element messages until dropped
+#[derive(Debug)]
+pub(crate) struct StatsTask {
+    abort_handle: AbortHandle,
+}
+
+impl StatsTask {
+    pub fn start<F>(element: &gst::Element, interval: Duration, stats: F) -> Self
+    where
+        F: Fn() -> gst::Structure + Send + 'static,
+    {
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let element = element.downgrade();
+
+        RUNTIME.spawn(Abortable::new(
+            async move {
+                let mut interval = tokio::time::interval(interval);
+                // The first tick completes immediately
+                interval.tick().await;
+
+                loop {
+                    interval.tick().await;
+
+                    let Some(element) = element.upgrade() else {
+                        break;
+                    };
+                    let _ = element.post_message(
+                        gst::message::Element::builder(stats())
+                            .src(&element)
+                            .build(),
+                    );
+                }
+            },
+            abort_registration,
+        ));
+
+        Self { abort_handle }
+    }
+}
+
+impl Drop for StatsTask {
+    fn drop(&mut self) {
+        self.abort_handle.abort();
+    }
+}
+
+fn extension_flags(format: ExtensionFormat) -> gst_rtp::RTPHeaderExtensionFlags {
+    match format {
+        ExtensionFormat::OneByte => gst_rtp::RTPHeaderExtensionFlags::ONE_BYTE,
+        ExtensionFormat::TwoByte => gst_rtp::RTPHeaderExtensionFlags::TWO_BYTE,
+    }
+}
+
+/// Stream positioning fields of the caps, as provided by e.g. rtspsrc from the RTSP `PLAY`
+/// response
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CapsTiming {
+    pub seqnum_base: Option<u16>,
+    pub clock_base: Option<u32>,
+    pub npt_start: Option<gst::ClockTime>,
+    pub play_speed: f64,
+}
+
+impl CapsTiming {
+    /// The timing for packets with the given payload type and ssrc, if the caps contain any
+    pub fn from_caps(caps: &gst::CapsRef, pt: u8, ssrc: u32) -> Option<Self> {
+        let s = caps.structure(0)?;
+        if s.get::<i32>("payload").ok() != Some(pt as i32)
+            || s.get::<u32>("ssrc")
+                .is_ok_and(|caps_ssrc| caps_ssrc != ssrc)
+        {
+            return None;
+        }
+
+        let timing = Self {
+            seqnum_base: s.get::<u32>("seqnum-base").ok().map(|base| base as u16),
+            clock_base: s.get::<u32>("clock-base").ok(),
+            npt_start: s
+                .get::<u64>("npt-start")
+                .ok()
+                .map(gst::ClockTime::from_nseconds),
+            play_speed: s
+                .get::<f64>("play-speed")
+                .ok()
+                .filter(|speed| *speed != 0.0)
+                .unwrap_or(1.0),
+        };
+
+        (timing.seqnum_base.is_some() || timing.clock_base.is_some() || timing.npt_start.is_some())
+            .then_some(timing)
+    }
+
+    /// The segment for a stream whose first packet has the given `pts` and is
+    /// `clock_base_offset` nanoseconds after the clock-base. The stream time of the packet at
+    /// the clock-base is npt-start.
+    pub fn segment(
+        &self,
+        segment: &gst::FormattedSegment<gst::ClockTime>,
+        pts: gst::ClockTime,
+        clock_base_offset: Option<i64>,
+    ) -> Option<gst::FormattedSegment<gst::ClockTime>> {
+        let npt_start = self.npt_start?;
+        let running_time = segment.to_running_time(pts)?;
+        let time = npt_start
+            .nseconds()
+            .saturating_add_signed(clock_base_offset.unwrap_or(0));
+
+        let mut segment = segment.clone();
+        segment.set_start(pts);
+        segment.set_position(pts);
+        segment.set_base(running_time);
+        segment.set_time(gst::ClockTime::from_nseconds(time));
+        segment.set_applied_rate(self.play_speed);
+
+        Some(segment)
     }
 }
 
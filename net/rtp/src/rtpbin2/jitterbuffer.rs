@@ -10,6 +10,8 @@ struct Stats {
     num_lost: u64,
     num_duplicates: u64,
     num_pushed: u64,
+    // Packets recovered by retransmission or FEC that were queued
+    num_recovered: u64,
 }
 
 impl From<Stats> for gst::Structure {
@@ -19,10 +21,20 @@ impl From<Stats> for gst::Structure {
             .field("num-duplicates", stats.num_duplicates)
             .field("num-lost", stats.num_lost)
             .field("num-pushed", stats.num_pushed)
+            .field("num-recovered", stats.num_recovered)
             .build()
     }
 }
 
+/// Limits for the packets stored in the jitterbuffer, `None` meaning unlimited
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimits {
+    pub max_packets: Option<usize>,
+    pub max_bytes: Option<usize>,
+    /// Maximum PTS difference between the oldest and the newest stored packet
+    pub max_time: Option<Duration>,
+}
+
 #[derive(Debug)]
 pub struct JitterBuffer {
     packet_counter: usize,
@@ -36,15 +48,51 @@ pub struct JitterBuffer {
     // Arrival time, PTS
     base_times: Option<(Instant, u64)>,
     last_output_seqnum: Option<u64>,
+    last_output_pts: Option<u64>,
     extended_seqnum: ExtendedSeqnum,
+    // Extended seqnum of the first packet to accept, from the seqnum-base of the caps
+    seqnum_base: Option<u64>,
     last_input_ts: Option<u64>,
     stats: Stats,
     flushing: bool,
+    // Forward packets immediately if they are the next in sequence
+    passthrough: bool,
+    // Forward all packets immediately in arrival order without reordering
+    bypass: bool,
+    // Report gaps in the sequence as lost packets before forwarding the next packet
+    do_lost: bool,
+    // Whether the next forwarded packet follows a reported gap
+    pending_discont: bool,
+    size_limits: SizeLimits,
+    // Drop the oldest packets when the size limits are reached instead of refusing new packets
+    drop_oldest: bool,
+    queued_packets: usize,
+    queued_bytes: usize,
+    // Packets dropped because of the size limits that were not returned yet
+    evicted: Vec<usize>,
+    // Offset from the extended seqnums of the current sequence to the seqnums used for ordering,
+    // so that a new sequence continues after the previous one
+    seqnum_offset: i64,
+    // Whether the next queued packet starts a new sequence
+    pending_sequence_reset: bool,
+    // Seqnum of the first packet of a new sequence, which is forwarded as discont
+    sequence_start: Option<u64>,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum PollResult {
-    Forward { id: usize, discont: bool },
+    Forward {
+        id: usize,
+        discont: bool,
+    },
+    Lost {
+        // Sequence number of the first lost packet
+        seqnum: u16,
+        num_packets: u64,
+        // Estimated PTS and duration of the lost packets
+        pts: u64,
+        duration: u64,
+    },
     Drop(usize),
     Timeout(Instant),
     Empty,
@@ -57,14 +105,21 @@ pub enum QueueResult {
     Late,
     Duplicate,
     Flushing,
+    // The size limits are reached and old packets are not dropped
+    Overflow,
 }
 
-#[derive(Eq, Debug)]
+#[derive(Eq, Debug, Clone)]
 struct Item {
     id: usize,
     // If not set, this is an event / query
     pts: Option<u64>,
+    // Used for ordering, the arrival order in bypass mode
     seqnum: u64,
+    // Extended seqnum of the packet
+    ext_seqnum: u64,
+    // Size of the packet in bytes
+    size: usize,
 }
 
 impl Ord for Item {
@@ -91,6 +146,13 @@ impl PartialEq for Item {
     }
 }
 
+fn packet_size(rtp: &RtpPacket) -> usize {
+    12 + rtp.csrc().count() * 4
+        + rtp.extension().map_or(0, |(_, data)| 4 + data.len())
+        + rtp.payload().len()
+        + rtp.padding().unwrap_or(0) as usize
+}
+
 impl JitterBuffer {
     pub fn new(latency: Duration) -> Self {
         Self {
@@ -101,24 +163,162 @@ impl JitterBuffer {
             base_times: None,
             last_input_ts: None,
             last_output_seqnum: None,
+            last_output_pts: None,
             extended_seqnum: ExtendedSeqnum::default(),
+            seqnum_base: None,
             stats: Stats {
                 num_late: 0,
                 num_lost: 0,
                 num_duplicates: 0,
                 num_pushed: 0,
+                num_recovered: 0,
             },
             flushing: true,
+            passthrough: false,
+            bypass: false,
+            do_lost: false,
+            pending_discont: false,
+            size_limits: SizeLimits::default(),
+            drop_oldest: true,
+            queued_packets: 0,
+            queued_bytes: 0,
+            evicted: vec![],
+            seqnum_offset: 0,
+            pending_sequence_reset: false,
+            sequence_start: None,
         }
     }
 
+    /// Limit the packets stored in the jitterbuffer. Once a limit is reached the oldest packets
+    /// are dropped if `drop_oldest` is set, otherwise queueing new packets fails with
+    /// [`QueueResult::Overflow`].
+    pub fn set_size_limits(&mut self, size_limits: SizeLimits, drop_oldest: bool) {
+        self.size_limits = size_limits;
+        self.drop_oldest = drop_oldest;
+    }
+
+    /// How late a packet with the given PTS is compared to its deadline, if known
+    pub fn lateness(&self, pts: u64, now: Instant) -> Option<Duration> {
+        let (base_instant, base_ts) = self.base_times?;
+        let deadline =
+            base_instant + Duration::from_nanos(pts.checked_sub(base_ts)?) + self.latency;
+
+        Some(now.saturating_duration_since(deadline))
+    }
+
+    /// Ids of the packets that were dropped because of the size limits since the last call
+    pub fn take_evicted(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.evicted)
+    }
+
+    fn exceeds_size_limits(&self, size: usize, pts: u64) -> bool {
+        if self
+            .size_limits
+            .max_packets
+            .is_some_and(|max_packets| self.queued_packets + 1 > max_packets)
+        {
+            return true;
+        }
+
+        if self
+            .size_limits
+            .max_bytes
+            .is_some_and(|max_bytes| self.queued_bytes + size > max_bytes)
+        {
+            return true;
+        }
+
+        if let Some(max_time) = self.size_limits.max_time {
+            if let Some(oldest_pts) = self.items.iter().find_map(|item| item.pts) {
+                return Duration::from_nanos(pts.saturating_sub(oldest_pts)) > max_time;
+            }
+        }
+
+        false
+    }
+
+    fn remove_item(&mut self, item: &Item) {
+        if item.pts.is_some() {
+            self.queued_packets -= 1;
+            self.queued_bytes -= item.size;
+        }
+    }
+
+    /// Change the latency, applying to the deadlines of the queued packets as well.
+    /// Callers should poll again as packets may now be ready.
+    pub fn set_latency(&mut self, latency: Duration) {
+        self.latency = latency;
+    }
+
+    /// Whether gaps in the sequence are reported with [`PollResult::Lost`] once the packet
+    /// following the gap is ready to be forwarded.
+    pub fn set_do_lost(&mut self, do_lost: bool) {
+        self.do_lost = do_lost;
+    }
+
+    /// Whether packets that are the next in sequence are forwarded immediately instead of
+    /// waiting for their deadline. Packets after a gap still wait for their deadline.
+    pub fn set_passthrough(&mut self, passthrough: bool) {
+        self.passthrough = passthrough;
+    }
+
+    /// Whether all packets are forwarded immediately in the order they were queued, without
+    /// waiting for missing packets or their deadline. Out of order packets are not dropped as
+    /// late and no packets are reported as lost. Must be set before the first item is queued.
+    pub fn set_bypass(&mut self, bypass: bool) {
+        self.bypass = bypass;
+    }
+
+    /// Whether packets are forwarded in arrival order without waiting
+    pub fn bypass(&self) -> bool {
+        self.bypass
+    }
+
+    /// Drop packets preceding `seqnum_base`, e.g. packets that were sent before the RTSP `PLAY`
+    /// request that started the stream. Only has an effect before the first packet is queued.
+    pub fn set_seqnum_base(&mut self, seqnum_base: u16) {
+        if self.extended_seqnum.current().is_some() {
+            debug!("Ignoring seqnum-base {seqnum_base} after the first packet");
+            return;
+        }
+
+        self.seqnum_base = Some(self.extended_seqnum.next(seqnum_base));
+    }
+
+    /// Sequence number of the last forwarded packet
+    pub fn last_output_seqnum(&self) -> Option<u16> {
+        self.last_output_seqnum
+            .map(|seqnum| self.rtp_seqnum(seqnum))
+    }
+
+    /// Start a new, unrelated sequence of packets with the next queued packet, e.g. after the
+    /// SSRC of the stream changed. Packets of the new sequence are forwarded after all packets
+    /// of the previous sequence.
+    pub fn reset_sequence(&mut self) {
+        debug!("Resetting sequence");
+        self.extended_seqnum = ExtendedSeqnum::default();
+        self.pending_sequence_reset = true;
+    }
+
+    /// RTP sequence number of a seqnum used for ordering
+    fn rtp_seqnum(&self, seqnum: u64) -> u16 {
+        (seqnum as i64 - self.seqnum_offset) as u16
+    }
+
     pub fn queue_serialized_item(&mut self) -> QueueResult {
         let id = self.packet_counter;
         self.packet_counter += 1;
+        let seqnum = if self.bypass {
+            id as u64
+        } else {
+            *self.seqnums.last().unwrap_or(&0)
+        };
         let item = Item {
             id,
             pts: None,
-            seqnum: (*self.seqnums.last().unwrap_or(&0)),
+            seqnum,
+            ext_seqnum: seqnum,
+            size: 0,
         };
         self.items.insert(item);
         trace!("Queued serialized item and assigned ID {id}");
@@ -130,6 +330,8 @@ impl JitterBuffer {
         trace!("Flush changed from {} to {flushing}", self.flushing);
         self.flushing = flushing;
         self.last_output_seqnum = None;
+        self.last_output_pts = None;
+        self.pending_discont = false;
     }
 
     pub fn queue_packet(&mut self, rtp: &RtpPacket, mut pts: u64, now: Instant) -> QueueResult {
@@ -138,7 +340,31 @@ impl JitterBuffer {
         }
 
         // From this point on we always work with extended sequence numbers
-        let seqnum = self.extended_seqnum.next(rtp.sequence_number());
+        let mut seqnum = self.extended_seqnum.next(rtp.sequence_number());
+        if std::mem::take(&mut self.pending_sequence_reset) {
+            // Continue after all packets seen of the previous sequence
+            let sequence_start = self.seqnums.last().map_or(seqnum, |last| last + 1);
+            debug!(
+                "Starting new sequence with packet {} at {sequence_start}",
+                rtp.sequence_number()
+            );
+            self.seqnum_offset = sequence_start as i64 - seqnum as i64;
+            self.sequence_start = Some(sequence_start);
+        }
+        seqnum = (seqnum as i64 + self.seqnum_offset) as u64;
+
+        if self
+            .seqnum_base
+            .is_some_and(|seqnum_base| seqnum < seqnum_base)
+        {
+            debug!(
+                "Packet {} (extended {}) precedes seqnum-base",
+                rtp.sequence_number(),
+                seqnum
+            );
+            self.stats.num_late += 1;
+            return QueueResult::Late;
+        }
 
         if let Some(ts) = self.last_input_ts {
             pts = pts.max(ts);
@@ -170,7 +396,7 @@ impl JitterBuffer {
 
         self.seqnums.insert(seqnum);
 
-        if let Some(last_output_seqnum) = self.last_output_seqnum {
+        if let Some(last_output_seqnum) = self.last_output_seqnum.filter(|_| !self.bypass) {
             if last_output_seqnum >= seqnum {
                 debug!(
                     "Late packet {} (extended {})",
@@ -182,17 +408,46 @@ impl JitterBuffer {
             }
         }
 
+        let size = packet_size(rtp);
+        while self.exceeds_size_limits(size, pts) {
+            if !self.drop_oldest {
+                debug!(
+                    "Size limits reached, not queueing packet {} (extended {})",
+                    rtp.sequence_number(),
+                    seqnum
+                );
+                // Not queued, so a retransmission is no duplicate
+                self.seqnums.remove(&seqnum);
+                return QueueResult::Overflow;
+            }
+
+            let Some(oldest) = self.items.iter().find(|item| item.pts.is_some()).cloned() else {
+                break;
+            };
+            debug!(
+                "Size limits reached, dropping oldest packet with id {}",
+                oldest.id
+            );
+            self.items.remove(&oldest);
+            self.remove_item(&oldest);
+            self.evicted.push(oldest.id);
+        }
+
         let id = self.packet_counter;
         self.packet_counter += 1;
         let item = Item {
             id,
             pts: Some(pts),
-            seqnum,
+            seqnum: if self.bypass { id as u64 } else { seqnum },
+            ext_seqnum: seqnum,
+            size,
         };
 
         if !self.items.insert(item) {
             unreachable!()
         }
+        self.queued_packets += 1;
+        self.queued_bytes += size;
 
         trace!("Queued RTP packet with ts {pts}, assigned ID {id}");
 
@@ -202,6 +457,7 @@ impl JitterBuffer {
     pub fn poll(&mut self, now: Instant) -> PollResult {
         if self.flushing {
             if let Some(item) = self.items.pop_first() {
+                self.remove_item(&item);
                 return PollResult::Drop(item.id);
             } else {
                 return PollResult::Flushing;
@@ -223,6 +479,28 @@ impl JitterBuffer {
             };
         };
 
+        if self.bypass {
+            let packet = self.items.pop_first().unwrap();
+            self.remove_item(&packet);
+
+            let sequence_start = self.sequence_start == Some(packet.ext_seqnum);
+            if sequence_start {
+                self.sequence_start = None;
+            }
+            let discont = self.last_output_seqnum.is_none() || sequence_start;
+
+            trace!("Forwarding packet with id {} in bypass mode", packet.id);
+
+            self.last_output_seqnum = Some(packet.ext_seqnum);
+            self.last_output_pts = Some(pts);
+            self.stats.num_pushed += 1;
+
+            return PollResult::Forward {
+                id: packet.id,
+                discont,
+            };
+        }
+
         let Some((base_instant, base_ts)) = self.base_times else {
             return PollResult::Empty;
         };
@@ -242,7 +520,12 @@ impl JitterBuffer {
             item.id
         );
 
-        if deadline <= duration_since_base_instant {
+        let in_sequence = self.passthrough
+            && self.last_output_seqnum.map_or(true, |last_output_seq_ext| {
+                item.seqnum == last_output_seq_ext + 1
+            });
+
+        if in_sequence || deadline <= duration_since_base_instant {
             debug!("Packet with id {} is ready", item.id);
 
             let discont = match self.last_output_seqnum {
@@ -250,15 +533,60 @@ impl JitterBuffer {
                 Some(last_output_seq_ext) => {
                     let gap = item.seqnum - last_output_seq_ext;
 
+                    if gap > 1 && self.do_lost {
+                        if let Some(last_output_pts) = self.last_output_pts {
+                            // Spread the lost packets evenly between the surrounding packets
+                            let packet_duration = pts.saturating_sub(last_output_pts) / gap;
+                            let lost_pts = last_output_pts + packet_duration;
+
+                            // Like rtpjitterbuffer, report each lost packet separately so
+                            // that downstream can conceal them one by one, unless the gap
+                            // is larger than the latency in which case a single event
+                            // covers all of them.
+                            let num_packets = if Duration::from_nanos(packet_duration * (gap - 1))
+                                > self.latency
+                            {
+                                gap - 1
+                            } else {
+                                1
+                            };
+
+                            debug!(
+                                "Lost {num_packets} packets after {last_output_seq_ext} before packet with id {}",
+                                item.id
+                            );
+
+                            self.stats.num_lost += num_packets;
+                            self.last_output_seqnum = Some(last_output_seq_ext + num_packets);
+                            self.last_output_pts =
+                                Some(last_output_pts + packet_duration * num_packets);
+                            self.pending_discont = true;
+
+                            return PollResult::Lost {
+                                seqnum: self.rtp_seqnum(last_output_seq_ext + 1),
+                                num_packets,
+                                pts: lost_pts,
+                                duration: packet_duration * num_packets,
+                            };
+                        }
+                    }
+
                     self.stats.num_lost += gap - 1;
 
                     gap != 1
                 }
             };
+            let sequence_start = self.sequence_start == Some(item.seqnum);
+            if sequence_start {
+                self.sequence_start = None;
+            }
+            let discont = std::mem::take(&mut self.pending_discont) || sequence_start || discont;
 
             self.last_output_seqnum = Some(item.seqnum);
+            self.last_output_pts = Some(pts);
             // Safe unwrap, we know the queue isn't empty at this point
             let packet = self.items.pop_first().unwrap();
+            self.remove_item(&packet);
 
             self.stats.num_pushed += 1;
 
@@ -272,8 +600,51 @@ impl JitterBuffer {
         }
     }
 
+    /// Remove all queued packets, keeping serialized items, and return their ids
+    pub fn drain_packets(&mut self) -> Vec<usize> {
+        let mut ids = vec![];
+        self.items.retain(|item| {
+            if item.pts.is_some() {
+                ids.push(item.id);
+                false
+            } else {
+                true
+            }
+        });
+        self.queued_packets = 0;
+        self.queued_bytes = 0;
+        trace!("Drained {} packets", ids.len());
+
+        ids
+    }
+
+    /// Count a queued packet as recovered by retransmission or FEC
+    pub fn packet_recovered(&mut self) {
+        self.stats.num_recovered += 1;
+    }
+
+    /// PTS difference between the oldest and the newest stored packet
+    fn queued_time(&self) -> Duration {
+        let (min, max) =
+            self.items
+                .iter()
+                .filter_map(|item| item.pts)
+                .fold((None, None), |(min, max), pts| {
+                    (
+                        Some(min.map_or(pts, |min: u64| min.min(pts))),
+                        Some(max.map_or(pts, |max: u64| max.max(pts))),
+                    )
+                });
+
+        Duration::from_nanos(Option::zip(min, max).map_or(0, |(min, max)| max - min))
+    }
+
     pub fn stats(&self) -> gst::Structure {
-        self.stats.into()
+        let mut stats = gst::Structure::from(self.stats);
+        stats.set("queued-packets", self.queued_packets as u64);
+        stats.set("queued-bytes", self.queued_bytes as u64);
+        stats.set("queued-time", self.queued_time().as_nanos() as u64);
+        stats
     }
 }
 
@@ -377,6 +748,49 @@ mod tests {
         );
     }
 
+    #[test]
+    fn seqnum_base() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(0));
+        jb.set_seqnum_base(65535);
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x12345678, 65534, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(jb.queue_packet(&packet, 0, now), QueueResult::Late);
+
+        let rtp_data = generate_rtp_packet(0x12345678, 0, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        let rtp_data = generate_rtp_packet(0x12345678, 65535, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_base) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_base,
+                discont: true
+            }
+        );
+        assert_eq!(jb.poll(now), PollResult::Forward { id, discont: false });
+
+        // Only applies before the first packet
+        jb.set_seqnum_base(10);
+        let rtp_data = generate_rtp_packet(0x12345678, 1, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert!(matches!(
+            jb.queue_packet(&packet, 0, now),
+            QueueResult::Queued(_)
+        ));
+    }
+
     #[test]
     fn ordered_packets_no_latency_with_gap() {
         let mut jb = JitterBuffer::new(Duration::from_secs(0));
@@ -506,6 +920,259 @@ mod tests {
         );
     }
 
+    #[test]
+    fn passthrough_packets_with_latency() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(1));
+        jb.set_passthrough(true);
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x12345678, 0, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_first) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        // Packets in sequence are forwarded immediately
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_first,
+                discont: true
+            }
+        );
+
+        // Packets after a gap wait for their deadline
+        let rtp_data = generate_rtp_packet(0x12345678, 2, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_third) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Timeout(now + Duration::from_secs(1))
+        );
+
+        let rtp_data = generate_rtp_packet(0x12345678, 1, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_second) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_second,
+                discont: false
+            }
+        );
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_third,
+                discont: false
+            }
+        );
+    }
+
+    #[test]
+    fn bypass_arrival_order() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(1));
+        jb.set_bypass(true);
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let mut ids = vec![];
+        for seqnum in [0, 2, 1, 2] {
+            let rtp_data = generate_rtp_packet(0x12345678, seqnum, 0, 4);
+            let packet = RtpPacket::parse(&rtp_data).unwrap();
+            match jb.queue_packet(&packet, 0, now) {
+                QueueResult::Queued(id) => ids.push(id),
+                ret => {
+                    // Only the duplicate is dropped
+                    assert_eq!(seqnum, 2);
+                    assert_eq!(ret, QueueResult::Duplicate);
+                }
+            }
+        }
+        assert_eq!(ids.len(), 3);
+
+        // Packets are forwarded in arrival order without waiting for the latency
+        for (i, id) in ids.into_iter().enumerate() {
+            assert_eq!(
+                jb.poll(now),
+                PollResult::Forward {
+                    id,
+                    discont: i == 0
+                }
+            );
+        }
+        assert_eq!(jb.poll(now), PollResult::Empty);
+
+        // Packets older than the last forwarded one are not late
+        for seqnum in [4, 3] {
+            let rtp_data = generate_rtp_packet(0x12345678, seqnum, 0, 4);
+            let packet = RtpPacket::parse(&rtp_data).unwrap();
+            let QueueResult::Queued(id) = jb.queue_packet(&packet, 0, now) else {
+                unreachable!()
+            };
+            assert_eq!(jb.poll(now), PollResult::Forward { id, discont: false });
+        }
+        assert_eq!(jb.stats().get::<u64>("num-lost").unwrap(), 0);
+    }
+
+    #[test]
+    fn latency_change() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(1));
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x12345678, 0, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        let now = now + Duration::from_millis(100);
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Timeout(now + Duration::from_millis(900))
+        );
+
+        // Lowering the latency makes the packet ready right away
+        jb.set_latency(Duration::from_millis(50));
+        assert_eq!(jb.poll(now), PollResult::Forward { id, discont: true });
+
+        let rtp_data = generate_rtp_packet(0x12345678, 1, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(_) = jb.queue_packet(&packet, 100_000_000, now) else {
+            unreachable!()
+        };
+
+        // Raising it postpones the deadline of queued packets
+        jb.set_latency(Duration::from_millis(200));
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Timeout(now + Duration::from_millis(200))
+        );
+    }
+
+    #[test]
+    fn lost_packets_reported() {
+        let mut jb = JitterBuffer::new(Duration::ZERO);
+        jb.set_do_lost(true);
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x12345678, 0, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_first) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_first,
+                discont: true
+            }
+        );
+
+        // Packets 1 and 2 never arrive
+        let rtp_data = generate_rtp_packet(0x12345678, 3, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_fourth) = jb.queue_packet(&packet, 30_000_000, now) else {
+            unreachable!()
+        };
+
+        let now = now + Duration::from_millis(30);
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Lost {
+                seqnum: 1,
+                num_packets: 2,
+                pts: 10_000_000,
+                duration: 20_000_000,
+            }
+        );
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_fourth,
+                discont: true
+            }
+        );
+        assert_eq!(jb.poll(now), PollResult::Empty);
+
+        assert_stats(&jb, 0, 2, 0, 2);
+    }
+
+    #[test]
+    fn lost_packets_reported_individually() {
+        let mut jb = JitterBuffer::new(Duration::from_millis(100));
+        jb.set_do_lost(true);
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x12345678, 0, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_first) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        let now = now + Duration::from_millis(100);
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_first,
+                discont: true
+            }
+        );
+
+        // Packets 1 and 2 never arrive
+        let rtp_data = generate_rtp_packet(0x12345678, 3, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_fourth) = jb.queue_packet(&packet, 30_000_000, now) else {
+            unreachable!()
+        };
+
+        let now = now + Duration::from_millis(30);
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Lost {
+                seqnum: 1,
+                num_packets: 1,
+                pts: 10_000_000,
+                duration: 10_000_000,
+            }
+        );
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Lost {
+                seqnum: 2,
+                num_packets: 1,
+                pts: 20_000_000,
+                duration: 10_000_000,
+            }
+        );
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_fourth,
+                discont: true
+            }
+        );
+        assert_eq!(jb.poll(now), PollResult::Empty);
+
+        assert_stats(&jb, 0, 2, 0, 2);
+    }
+
     fn assert_stats(
         jb: &JitterBuffer,
         num_late: u64,
@@ -577,6 +1244,35 @@ mod tests {
         assert_stats(&jb, 1, 1, 3, 2);
     }
 
+    #[test]
+    fn queued_stats() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(1));
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let stats = jb.stats();
+        assert_eq!(stats.get::<u64>("queued-packets").unwrap(), 0);
+        assert_eq!(stats.get::<u64>("queued-bytes").unwrap(), 0);
+        assert_eq!(stats.get::<u64>("queued-time").unwrap(), 0);
+
+        for (seqnum, pts) in [(0, 0), (1, 20_000_000)] {
+            let rtp_data = generate_rtp_packet(0x12345678, seqnum, seqnum as u32 * 1800, 4);
+            let packet = RtpPacket::parse(&rtp_data).unwrap();
+            assert!(matches!(
+                jb.queue_packet(&packet, pts, now),
+                QueueResult::Queued(_)
+            ));
+        }
+        jb.packet_recovered();
+
+        let stats = jb.stats();
+        assert_eq!(stats.get::<u64>("queued-packets").unwrap(), 2);
+        assert_eq!(stats.get::<u64>("queued-bytes").unwrap(), 32);
+        assert_eq!(stats.get::<u64>("queued-time").unwrap(), 20_000_000);
+        assert_eq!(stats.get::<u64>("num-recovered").unwrap(), 1);
+    }
+
     #[test]
     fn serialized_items() {
         let mut jb = JitterBuffer::new(Duration::from_secs(0));
@@ -667,4 +1363,212 @@ mod tests {
         jb.set_flushing(false);
         assert_eq!(jb.poll(now), PollResult::Empty);
     }
+
+    #[test]
+    fn lateness() {
+        let mut jb = JitterBuffer::new(Duration::from_millis(100));
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+        assert_eq!(jb.lateness(0, now), None);
+
+        let rtp_data = generate_rtp_packet(0x12345678, 0, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(_) = jb.queue_packet(&packet, 1_000_000_000, now) else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            jb.lateness(1_000_000_000, now + Duration::from_millis(150)),
+            Some(Duration::from_millis(50))
+        );
+        assert_eq!(
+            jb.lateness(1_100_000_000, now + Duration::from_millis(150)),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(jb.lateness(0, now), None);
+    }
+
+    #[test]
+    fn size_limits_drop_oldest() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(1));
+        jb.set_flushing(false);
+        jb.set_size_limits(
+            SizeLimits {
+                max_packets: Some(2),
+                ..Default::default()
+            },
+            true,
+        );
+
+        let now = Instant::now();
+
+        let mut ids = vec![];
+        for seqnum in 0..3 {
+            let rtp_data = generate_rtp_packet(0x12345678, seqnum, 0, 4);
+            let packet = RtpPacket::parse(&rtp_data).unwrap();
+            let QueueResult::Queued(id) = jb.queue_packet(&packet, 0, now) else {
+                unreachable!()
+            };
+            ids.push(id);
+        }
+
+        // The first packet was dropped to make room for the third one
+        assert_eq!(jb.take_evicted(), vec![ids[0]]);
+        assert!(jb.take_evicted().is_empty());
+
+        let now = now + Duration::from_secs(1);
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: ids[1],
+                discont: true
+            }
+        );
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: ids[2],
+                discont: false
+            }
+        );
+        assert_eq!(jb.poll(now), PollResult::Empty);
+    }
+
+    #[test]
+    fn size_limits_overflow() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(1));
+        jb.set_flushing(false);
+        jb.set_size_limits(
+            SizeLimits {
+                max_time: Some(Duration::from_millis(100)),
+                ..Default::default()
+            },
+            false,
+        );
+
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x12345678, 0, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_first) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        let rtp_data = generate_rtp_packet(0x12345678, 1, 18000, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(
+            jb.queue_packet(&packet, 200_000_000, now),
+            QueueResult::Overflow
+        );
+        assert!(jb.take_evicted().is_empty());
+
+        // Once the first packet is forwarded there is room again
+        let now = now + Duration::from_secs(1);
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_first,
+                discont: true
+            }
+        );
+        let QueueResult::Queued(id_second) = jb.queue_packet(&packet, 200_000_000, now) else {
+            unreachable!()
+        };
+        assert_eq!(
+            jb.poll(now + Duration::from_millis(200)),
+            PollResult::Forward {
+                id: id_second,
+                discont: false
+            }
+        );
+    }
+
+    #[test]
+    fn reset_sequence() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(0));
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x12345678, 1000, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_first) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        // The new sequence starts with a lower seqnum but is still forwarded after the previous
+        // one, without any lost packets
+        jb.reset_sequence();
+        let rtp_data = generate_rtp_packet(0x87654321, 10, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_second) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        let rtp_data = generate_rtp_packet(0x87654321, 11, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_third) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_first,
+                discont: true
+            }
+        );
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_second,
+                discont: true
+            }
+        );
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_third,
+                discont: false
+            }
+        );
+        assert_eq!(jb.last_output_seqnum(), Some(11));
+        assert_eq!(jb.stats.num_lost, 0);
+    }
+
+    #[test]
+    fn drain_packets() {
+        let mut jb = JitterBuffer::new(Duration::from_secs(1));
+        jb.set_flushing(false);
+
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x12345678, 0, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_first) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        let rtp_data = generate_rtp_packet(0x12345678, 1, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        let QueueResult::Queued(id_second) = jb.queue_packet(&packet, 0, now) else {
+            unreachable!()
+        };
+
+        let QueueResult::Queued(id_serialized_item) = jb.queue_serialized_item() else {
+            unreachable!()
+        };
+
+        // Packets are still waiting for their deadline, draining only leaves the serialized item
+        assert_eq!(jb.drain_packets(), vec![id_first, id_second]);
+        assert_eq!(
+            jb.poll(now),
+            PollResult::Forward {
+                id: id_serialized_item,
+                discont: false
+            }
+        );
+        assert_eq!(jb.poll(now), PollResult::Empty);
+    }
 }
@@ -4,20 +4,27 @@ use gst::glib;
 use gst::prelude::*;
 use std::sync::LazyLock;
 mod config;
+mod dtmf;
+mod hdrext;
 mod internal;
 mod jitterbuffer;
+mod pacing;
+mod rsi;
 mod rtprecv;
 mod rtpsend;
 mod session;
+mod snapshot;
 mod source;
 mod sync;
 mod time;
+mod twcc;
+mod xr;
 
 glib::wrapper! {
-    pub struct RtpSend(ObjectSubclass<rtpsend::RtpSend>) @extends gst::Element, gst::Object;
+    pub struct RtpSend(ObjectSubclass<rtpsend::RtpSend>) @extends gst::Element, gst::Object, @implements gst::ChildProxy;
 }
 glib::wrapper! {
-    pub struct RtpRecv(ObjectSubclass<rtprecv::RtpRecv>) @extends gst::Element, gst::Object;
+    pub struct RtpRecv(ObjectSubclass<rtprecv::RtpRecv>) @extends gst::Element, gst::Object, @implements gst::ChildProxy;
 }
 
 pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
@@ -29,6 +36,14 @@ pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
             .mark_as_plugin_api(gst::PluginAPIFlags::empty());
         crate::rtpbin2::rtpsend::Profile::static_type()
             .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        crate::rtpbin2::rtpsend::KeepaliveMethod::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        crate::rtpbin2::rtprecv::EosHandling::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        crate::rtpbin2::rtprecv::BufferMode::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        crate::rtpbin2::rtprecv::OverflowPolicy::static_type()
+            .mark_as_plugin_api(gst::PluginAPIFlags::empty());
     }
     gst::Element::register(
         Some(plugin),
@@ -51,3 +66,26 @@ pub static RUNTIME: LazyLock<tokio::runtime::Runtime> = LazyLock::new(|| {
         .build()
         .unwrap()
 });
+
+/// Runtime on which all `rtprecv` source pads push their data downstream.
+///
+/// Pushing blocks, so each push is moved off the worker thread with
+/// `tokio::task::block_in_place()`. Threads are only occupied by pushes that are
+/// currently in progress and are reused afterwards, instead of having one thread per
+/// received SSRC and payload type.
+///
+/// A push can block until data arrives on another source pad, e.g. in an aggregator
+/// downstream or while prerolling, so the number of threads must not be capped below
+/// the number of source pads: every blocked push gets its own thread and the worker
+/// keeps serving the remaining pads. Idle threads exit after the default keep-alive.
+pub static PUSH_RUNTIME: LazyLock<tokio::runtime::Runtime> = LazyLock::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_time()
+        .worker_threads(1)
+        // Bounded by the number of jitterbuffers as each one has at most one push in
+        // progress
+        .max_blocking_threads(usize::MAX)
+        .thread_name("rtprecv-push")
+        .build()
+        .unwrap()
+});
@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::time::{Duration, Instant};
+
+/// Upper bounds of the buckets of the histogram of the time between two sent packets. The last
+/// bucket of the histogram counts all larger deltas.
+const SEND_DELTA_BOUNDS: [Duration; 8] = [
+    Duration::from_micros(100),
+    Duration::from_micros(250),
+    Duration::from_micros(500),
+    Duration::from_millis(1),
+    Duration::from_millis(2),
+    Duration::from_millis(5),
+    Duration::from_millis(10),
+    Duration::from_millis(50),
+];
+
+/// Upper bounds of the buckets of the histogram of the number of packets per burst. The last
+/// bucket of the histogram counts all larger bursts.
+const BURST_SIZE_BOUNDS: [u64; 7] = [1, 2, 4, 8, 16, 32, 64];
+
+/// Packets sent less than this apart are considered part of the same burst
+const BURST_THRESHOLD: Duration = Duration::from_micros(500);
+
+/// Send time deltas and burst sizes of the packets sent in a session, for checking whether the
+/// packets are paced adequately upstream of the session.
+#[derive(Debug, Default)]
+pub struct PacingStats {
+    last_sent: Option<Instant>,
+    current_burst: u64,
+    max_send_delta: Duration,
+    max_burst_size: u64,
+    send_deltas: [u64; SEND_DELTA_BOUNDS.len() + 1],
+    burst_sizes: [u64; BURST_SIZE_BOUNDS.len() + 1],
+}
+
+impl PacingStats {
+    /// Record a packet sent at `now`
+    pub fn packet_sent(&mut self, now: Instant) {
+        let Some(last_sent) = self.last_sent.replace(now) else {
+            self.current_burst = 1;
+            return;
+        };
+
+        let delta = now.saturating_duration_since(last_sent);
+        let idx = SEND_DELTA_BOUNDS
+            .iter()
+            .position(|bound| delta <= *bound)
+            .unwrap_or(SEND_DELTA_BOUNDS.len());
+        self.send_deltas[idx] += 1;
+        self.max_send_delta = self.max_send_delta.max(delta);
+
+        if delta < BURST_THRESHOLD {
+            self.current_burst += 1;
+        } else {
+            self.finish_burst();
+            self.current_burst = 1;
+        }
+    }
+
+    fn finish_burst(&mut self) {
+        if self.current_burst == 0 {
+            return;
+        }
+
+        let idx = BURST_SIZE_BOUNDS
+            .iter()
+            .position(|bound| self.current_burst <= *bound)
+            .unwrap_or(BURST_SIZE_BOUNDS.len());
+        self.burst_sizes[idx] += 1;
+        self.max_burst_size = self.max_burst_size.max(self.current_burst);
+    }
+
+    pub fn stats(&self) -> gst::Structure {
+        gst::Structure::builder("application/x-rtpbin2-pacing-stats")
+            .field(
+                "send-delta-bounds",
+                gst::Array::new(SEND_DELTA_BOUNDS.iter().map(|d| d.as_nanos() as u64)),
+            )
+            .field("send-delta-histogram", gst::Array::new(self.send_deltas))
+            .field("max-send-delta", self.max_send_delta.as_nanos() as u64)
+            .field("burst-threshold", BURST_THRESHOLD.as_nanos() as u64)
+            .field("burst-size-bounds", gst::Array::new(BURST_SIZE_BOUNDS))
+            .field("burst-size-histogram", gst::Array::new(self.burst_sizes))
+            .field("max-burst-size", self.max_burst_size)
+            .field("current-burst-size", self.current_burst)
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn send_deltas() {
+        let mut stats = PacingStats::default();
+        let start = Instant::now();
+
+        for delta in [0, 100, 1_000, 20_000, 100_000] {
+            stats.packet_sent(start + Duration::from_micros(delta));
+        }
+
+        // The first packet has no delta
+        assert_eq!(stats.send_deltas, [1, 0, 0, 1, 0, 0, 0, 1, 1]);
+        assert_eq!(stats.max_send_delta, Duration::from_millis(80));
+    }
+
+    #[test]
+    fn burst_sizes() {
+        let mut stats = PacingStats::default();
+        let mut now = Instant::now();
+
+        for burst in [3, 1, 10] {
+            for _ in 0..burst {
+                stats.packet_sent(now);
+            }
+            now += Duration::from_millis(5);
+        }
+
+        // The last burst is still running
+        assert_eq!(stats.burst_sizes, [1, 0, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(stats.current_burst, 10);
+        assert_eq!(stats.max_burst_size, 3);
+
+        stats.packet_sent(now);
+        assert_eq!(stats.burst_sizes, [1, 0, 1, 0, 1, 0, 0, 0]);
+        assert_eq!(stats.max_burst_size, 10);
+
+        let s = stats.stats();
+        assert_eq!(s.get::<u64>("max-burst-size").unwrap(), 10);
+        assert_eq!(
+            s.get::<gst::Array>("burst-size-histogram").unwrap().len(),
+            8
+        );
+    }
+}
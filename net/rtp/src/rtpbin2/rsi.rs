@@ -0,0 +1,111 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Minimal RTCP Receiver Summary Information (RFC 5760) support for the Group and Average Packet
+//! Size sub-report block, as sent by the distribution source of source-specific multicast
+//! sessions.
+
+/// RTCP packet type of an RSI packet
+pub const RSI_PACKET_TYPE: u8 = 209;
+
+const GROUP_AND_AVERAGE_PACKET_SIZE_BLOCK_TYPE: u8 = 4;
+
+const RSI_HEADER_LEN: usize = 20;
+const SUB_REPORT_HEADER_LEN: usize = 4;
+
+/// A parsed RSI packet. Unsupported sub-report blocks are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rsi {
+    /// SSRC of the distribution source
+    pub ssrc: u32,
+    /// SSRC of the media sender the report is about
+    pub summarized_ssrc: u32,
+    pub ntp_timestamp: u64,
+    /// Number of receivers of the session
+    pub group_size: Option<u32>,
+    /// Average RTCP packet size of the session in bytes
+    pub average_packet_size: Option<u16>,
+}
+
+impl Rsi {
+    /// Parse a complete RSI packet including the RTCP header
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < RSI_HEADER_LEN || data[0] >> 6 != 2 || data[1] != RSI_PACKET_TYPE {
+            return None;
+        }
+
+        let len = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+        if data.len() < len {
+            return None;
+        }
+        let mut payload_len = len;
+        if data[0] & 0x20 != 0 {
+            let padding = data[len - 1] as usize;
+            payload_len = payload_len.checked_sub(padding)?;
+        }
+        if payload_len < RSI_HEADER_LEN {
+            return None;
+        }
+
+        let mut rsi = Rsi {
+            ssrc: u32::from_be_bytes(data[4..8].try_into().unwrap()),
+            summarized_ssrc: u32::from_be_bytes(data[8..12].try_into().unwrap()),
+            ntp_timestamp: u64::from_be_bytes(data[12..20].try_into().unwrap()),
+            group_size: None,
+            average_packet_size: None,
+        };
+
+        let mut data = &data[RSI_HEADER_LEN..payload_len];
+        while data.len() >= SUB_REPORT_HEADER_LEN {
+            let block_type = data[0];
+            let block_len = data[1] as usize * 4;
+            if block_len < SUB_REPORT_HEADER_LEN || data.len() < block_len {
+                return None;
+            }
+
+            if block_type == GROUP_AND_AVERAGE_PACKET_SIZE_BLOCK_TYPE && block_len == 8 {
+                rsi.average_packet_size = Some(u16::from_be_bytes([data[2], data[3]]));
+                rsi.group_size = Some(u32::from_be_bytes(data[4..8].try_into().unwrap()));
+            }
+
+            data = &data[block_len..];
+        }
+
+        Some(rsi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rsi_group_and_average_packet_size() {
+        let data = [
+            0x80, 0xd1, 0x00, 0x07, // header
+            0x00, 0x00, 0x00, 0x01, // ssrc
+            0x00, 0x00, 0x00, 0x02, // summarized ssrc
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, // ntp timestamp
+            0x06, 0x01, 0xff, 0xff, // unknown sub-report block type 6
+            0x04, 0x02, 0x00, 0x64, 0x00, 0x01, 0x00, 0x00, // group and average packet size
+        ];
+
+        let parsed = Rsi::parse(&data).unwrap();
+        assert_eq!(
+            parsed,
+            Rsi {
+                ssrc: 1,
+                summarized_ssrc: 2,
+                ntp_timestamp: 0x0102_0304_0506_0708,
+                group_size: Some(65536),
+                average_packet_size: Some(100),
+            }
+        );
+
+        // truncated sub-report block
+        assert!(Rsi::parse(&data[..28]).is_none());
+        // zero length sub-report block
+        let mut invalid = data;
+        invalid[21] = 0;
+        assert!(Rsi::parse(&invalid).is_none());
+    }
+}
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::net::SocketAddr;
 use std::ops::ControlFlow;
 use std::pin::Pin;
@@ -12,18 +12,45 @@ use futures::StreamExt;
 use gst::{glib, prelude::*, subclass::prelude::*};
 use std::sync::LazyLock;
 
-use super::internal::{pt_clock_rate_from_caps, GstRustLogger, SharedRtpState, SharedSession};
+use super::internal::{
+    pt_clock_rate_from_caps, CapsTiming, GstRustLogger, SharedRtpState, SharedSession,
+    SharedSessionInner, StatsTask,
+};
 use super::jitterbuffer::{self, JitterBuffer};
 use super::session::{
-    KeyUnitRequestType, RecvReply, RequestRemoteKeyUnitReply, RtcpRecvReply, RtpProfile,
+    BundleIds, KeyUnitRequestType, RecvReply, RequestRemoteKeyUnitReply, RtcpRecvReply, RtpProfile,
     RTCP_MIN_REPORT_INTERVAL,
 };
+use super::snapshot::{StateSnapshot, StreamSnapshot};
 use super::source::SourceState;
 use super::sync;
 
-use crate::rtpbin2::RUNTIME;
+use crate::rtpbin2::{PUSH_RUNTIME, RUNTIME};
 
 const DEFAULT_LATENCY: gst::ClockTime = gst::ClockTime::from_mseconds(200);
+// How often remote sources are checked for timeouts
+const SOURCE_TIMEOUT_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+const DEFAULT_ADD_REFERENCE_TIMESTAMP_META: bool = false;
+const DEFAULT_RFC7273_SYNC: bool = false;
+const DEFAULT_DO_LOST: bool = false;
+const DEFAULT_IGNORE_PT: bool = false;
+const DEFAULT_AUTO_REMOVE: bool = false;
+const DEFAULT_MAX_DROPOUT_TIME: u32 = 60000;
+const DEFAULT_MAX_MISORDER_TIME: u32 = 2000;
+const DEFAULT_PROBATION: u32 = 2;
+const DEFAULT_STATS_INTERVAL: u32 = 0;
+const DEFAULT_POST_DROP_MESSAGES: bool = false;
+const DEFAULT_DROP_MESSAGES_INTERVAL: u32 = 200;
+const DEFAULT_MAX_SIZE_PACKETS: u32 = 0;
+const DEFAULT_MAX_SIZE_BYTES: u32 = 0;
+const DEFAULT_MAX_SIZE_TIME: gst::ClockTime = gst::ClockTime::ZERO;
+
+/// Set by RTX and FEC receivers on the packets they recovered
+const RETRANSMISSION_FLAG: gst::BufferFlags =
+    gst::BufferFlags::from_bits_retain(gst_rtp::ffi::GST_RTP_BUFFER_FLAG_RETRANSMISSION);
+
+static NTP_CAPS: LazyLock<gst::Caps> =
+    LazyLock::new(|| gst::Caps::builder("timestamp/x-ntp").build());
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -33,11 +60,144 @@ static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     )
 });
 
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRtpRecvEosHandling")]
+pub enum EosHandling {
+    #[enum_value(
+        name = "Forward EOS immediately, discarding packets waiting in the jitterbuffers",
+        nick = "immediate"
+    )]
+    Immediate,
+    #[default]
+    #[enum_value(
+        name = "Forward EOS once the jitterbuffers are drained",
+        nick = "drain"
+    )]
+    Drain,
+    #[enum_value(
+        name = "Forward EOS once all remote senders sent a RTCP BYE or timed out",
+        nick = "wait-bye"
+    )]
+    WaitBye,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRtpRecvBufferMode")]
+pub enum BufferMode {
+    #[enum_value(
+        name = "Only use RTP timestamps and output packets once their deadline is reached",
+        nick = "none"
+    )]
+    None,
+    #[enum_value(
+        name = "Slave receiver to sender clock and output packets once their deadline is reached",
+        nick = "slave"
+    )]
+    Slave,
+    #[enum_value(
+        name = "Synchronized sender and receiver clocks, output packets in sequence immediately",
+        nick = "synced"
+    )]
+    Synced,
+    #[default]
+    #[enum_value(
+        name = "Use the timestamping-mode property and output packets once their deadline is reached",
+        nick = "auto"
+    )]
+    Auto,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRtpRecvOverflowPolicy")]
+pub enum OverflowPolicy {
+    #[default]
+    #[enum_value(
+        name = "Drop the oldest packets of the jitterbuffer to make room for new packets",
+        nick = "drop-oldest"
+    )]
+    DropOldest,
+    #[enum_value(name = "Fail with an error", nick = "error")]
+    Error,
+}
+
+/// Why the jitterbuffer dropped a packet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DropReason {
+    Late,
+    Duplicate,
+}
+
+impl DropReason {
+    fn as_str(self) -> &'static str {
+        match self {
+            DropReason::Late => "late",
+            DropReason::Duplicate => "duplicate",
+        }
+    }
+}
+
+impl BufferMode {
+    fn timestamping_mode(
+        self,
+        timestamping_mode: sync::TimestampingMode,
+    ) -> sync::TimestampingMode {
+        match self {
+            BufferMode::None | BufferMode::Synced => sync::TimestampingMode::Rtp,
+            BufferMode::Slave => sync::TimestampingMode::Skew,
+            BufferMode::Auto => timestamping_mode,
+        }
+    }
+
+    fn passthrough(self) -> bool {
+        self == BufferMode::Synced
+    }
+}
+
 #[derive(Debug, Clone)]
 struct Settings {
     rtp_id: String,
     latency: gst::ClockTime,
     timestamping_mode: sync::TimestampingMode,
+    buffer_mode: BufferMode,
+    eos_handling: EosHandling,
+    add_reference_timestamp_meta: bool,
+    rfc7273_sync: bool,
+    do_lost: bool,
+    ignore_pt: bool,
+    sparse_pts: Vec<u8>,
+    auto_remove: bool,
+    max_dropout_time: u32,
+    max_misorder_time: u32,
+    probation: u32,
+    stats_interval: u32,
+    sync_group: Option<String>,
+    max_size_packets: u32,
+    max_size_bytes: u32,
+    max_size_time: gst::ClockTime,
+    overflow_policy: OverflowPolicy,
+    post_drop_messages: bool,
+    drop_messages_interval: u32,
+}
+
+impl Settings {
+    fn configure_session(&self, session: &mut super::session::Session) {
+        let to_duration = |ms| (ms > 0).then(|| Duration::from_millis(ms as u64));
+
+        session.set_max_dropout_time(to_duration(self.max_dropout_time));
+        session.set_max_misorder_time(to_duration(self.max_misorder_time));
+        session.set_probation(self.probation as usize);
+    }
+
+    fn size_limits(&self) -> jitterbuffer::SizeLimits {
+        jitterbuffer::SizeLimits {
+            max_packets: (self.max_size_packets > 0).then_some(self.max_size_packets as usize),
+            max_bytes: (self.max_size_bytes > 0).then_some(self.max_size_bytes as usize),
+            max_time: (!self.max_size_time.is_zero()).then(|| self.max_size_time.into()),
+        }
+    }
 }
 
 impl Default for Settings {
@@ -46,6 +206,25 @@ impl Default for Settings {
             rtp_id: String::from("rtp-id"),
             latency: DEFAULT_LATENCY,
             timestamping_mode: sync::TimestampingMode::default(),
+            buffer_mode: BufferMode::default(),
+            eos_handling: EosHandling::default(),
+            add_reference_timestamp_meta: DEFAULT_ADD_REFERENCE_TIMESTAMP_META,
+            rfc7273_sync: DEFAULT_RFC7273_SYNC,
+            do_lost: DEFAULT_DO_LOST,
+            ignore_pt: DEFAULT_IGNORE_PT,
+            sparse_pts: vec![],
+            auto_remove: DEFAULT_AUTO_REMOVE,
+            max_dropout_time: DEFAULT_MAX_DROPOUT_TIME,
+            max_misorder_time: DEFAULT_MAX_MISORDER_TIME,
+            probation: DEFAULT_PROBATION,
+            stats_interval: DEFAULT_STATS_INTERVAL,
+            sync_group: None,
+            max_size_packets: DEFAULT_MAX_SIZE_PACKETS,
+            max_size_bytes: DEFAULT_MAX_SIZE_BYTES,
+            max_size_time: DEFAULT_MAX_SIZE_TIME,
+            overflow_policy: OverflowPolicy::default(),
+            post_drop_messages: DEFAULT_POST_DROP_MESSAGES,
+            drop_messages_interval: DEFAULT_DROP_MESSAGES_INTERVAL,
         }
     }
 }
@@ -148,6 +327,36 @@ impl futures::stream::Stream for JitterBufferStream {
                     }
                     return Poll::Ready(Some(item));
                 }
+                jitterbuffer::PollResult::Lost {
+                    seqnum,
+                    num_packets,
+                    pts,
+                    duration,
+                } => {
+                    gst::debug!(
+                        CAT,
+                        "Forwarding packet-lost event for {num_packets} packets from seqnum {seqnum}"
+                    );
+
+                    let item = JitterBufferItem::Event(
+                        gst::event::CustomDownstream::builder(
+                            gst::Structure::builder("GstRTPPacketLost")
+                                .field("seqnum", seqnum as u32)
+                                .field("timestamp", pts)
+                                .field("duration", duration)
+                                .field("retry", 0u32)
+                                .field("might-have-been-fec", false)
+                                .build(),
+                        )
+                        .build(),
+                    );
+
+                    if pending_item.is_some() {
+                        next_pending_item = Some(item);
+                        break;
+                    }
+                    return Poll::Ready(Some(item));
+                }
                 jitterbuffer::PollResult::Timeout(timeout) => {
                     if lowest_wait.map_or(true, |lowest_wait| timeout < lowest_wait) {
                         lowest_wait = Some(timeout);
@@ -201,42 +410,99 @@ struct JitterBufferStore {
     store: BTreeMap<usize, JitterBufferItem>,
     waker: Option<Waker>,
     jitterbuffer: JitterBuffer,
+    // Remove the pad once an EOS was pushed, used when the remote sender left
+    remove_on_eos: bool,
+    // Task pushing the items of this jitterbuffer downstream
+    push_task: Option<tokio::task::JoinHandle<()>>,
+    session_id: usize,
+    // When the last message about a dropped packet was posted, for rate limiting
+    last_drop_message: Option<Instant>,
+}
+
+impl JitterBufferStore {
+    /// Queue a serialized event, forwarded in order with the packets
+    fn queue_serialized_event(&mut self, event: gst::Event) {
+        let jitterbuffer::QueueResult::Queued(id) = self.jitterbuffer.queue_serialized_item()
+        else {
+            unreachable!()
+        };
+
+        self.store.insert(id, JitterBufferItem::Event(event));
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    /// Drop the packets the jitterbuffer evicted because of its size limits
+    fn remove_evicted(&mut self) {
+        for id in self.jitterbuffer.take_evicted() {
+            self.store.remove(&id);
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 struct RtpRecvSrcPad {
+    // pt and ssrc of the most recent packet for pads of a BUNDLE MID and RtpStreamId
     pt: u8,
     ssrc: u32,
+    // Previous ssrc of a BUNDLE MID and the PTS of the switch to the current one
+    previous_ssrc: Option<(u32, gst::ClockTime)>,
+    mid: Option<String>,
+    rid: Option<String>,
     pad: gst::Pad,
     jitter_buffer_store: Arc<Mutex<JitterBufferStore>>,
+    // Segment to start with instead of the one of the sink pad, from the timing in the caps
+    segment: Option<gst::FormattedSegment<gst::ClockTime>>,
 }
 
 impl PartialEq for RtpRecvSrcPad {
     fn eq(&self, other: &Self) -> bool {
-        self.pt == other.pt && self.ssrc == other.ssrc && self.pad == other.pad
+        self.pt == other.pt
+            && self.ssrc == other.ssrc
+            && self.mid == other.mid
+            && self.rid == other.rid
+            && self.pad == other.pad
     }
 }
 
 impl Eq for RtpRecvSrcPad {}
 
 impl RtpRecvSrcPad {
+    /// Caps for the payload type `pt` of the current SSRC, annotated with the RtpStreamId of
+    /// simulcast layers
+    fn caps(&self, session_inner: &SharedSessionInner, pt: u8) -> gst::Caps {
+        let mut caps = session_inner.caps_from_ssrc_pt(self.ssrc, pt);
+        if let Some(ref rid) = self.rid {
+            caps.make_mut().set("rid", rid);
+        }
+        caps
+    }
+
     fn activate(&mut self, state: MutexGuard<State>, session_id: usize) {
         let session = state.session_by_id(session_id).unwrap();
         let seqnum = session.rtp_recv_sink_seqnum.unwrap();
-        let stream_id = format!("{}/{}", self.pt, self.ssrc);
+        let stream_id = match (&self.mid, &self.rid) {
+            (Some(mid), Some(rid)) => format!("{mid}/{rid}"),
+            (Some(mid), None) => mid.clone(),
+            _ => format!("{}/{}", self.pt, self.ssrc),
+        };
         let stream_start = gst::event::StreamStart::builder(&stream_id)
             .group_id(session.rtp_recv_sink_group_id.unwrap())
             .seqnum(seqnum)
             .build();
 
         let session_inner = session.internal_session.inner.lock().unwrap();
-        let caps = session_inner.caps_from_pt(self.pt);
+        let caps = self.caps(&session_inner, self.pt);
         let caps = gst::event::Caps::builder(&caps).seqnum(seqnum).build();
         drop(session_inner);
 
-        let segment = gst::event::Segment::builder(session.rtp_recv_sink_segment.as_ref().unwrap())
-            .seqnum(seqnum)
-            .build();
+        let segment = self
+            .segment
+            .as_ref()
+            .or(session.rtp_recv_sink_segment.as_ref())
+            .unwrap();
+        let segment = gst::event::Segment::builder(segment).seqnum(seqnum).build();
         drop(state);
 
         self.pad.set_active(true).unwrap();
@@ -253,25 +519,21 @@ struct HeldRecvBuffer {
     jb: Arc<Mutex<JitterBufferStore>>,
 }
 
-#[derive(Debug)]
-struct HeldRecvBufferList {
-    list: gst::BufferList,
-    jb: Arc<Mutex<JitterBufferStore>>,
-}
-
 #[derive(Debug)]
 enum HeldRecvItem {
     NewPad(RtpRecvSrcPad),
+    // Serialized event to queue in a jitterbuffer, e.g. caps after a payload type change
+    Event(Arc<Mutex<JitterBufferStore>>, gst::Event),
+    // Start of a new sequence in a jitterbuffer after the ssrc of a MID changed
+    ResetSequence(Arc<Mutex<JitterBufferStore>>),
     Buffer(HeldRecvBuffer),
-    BufferList(HeldRecvBufferList),
 }
 
 impl HeldRecvItem {
     fn hold_id(&self) -> Option<usize> {
         match self {
-            Self::NewPad(_) => None,
+            Self::NewPad(_) | Self::Event(..) | Self::ResetSequence(_) => None,
             Self::Buffer(buf) => buf.hold_id,
-            Self::BufferList(_list) => None,
         }
     }
 }
@@ -286,6 +548,9 @@ struct RecvSession {
     rtp_recv_sink_caps: Option<gst::Caps>,
     rtp_recv_sink_segment: Option<gst::FormattedSegment<gst::ClockTime>>,
     rtp_recv_sink_seqnum: Option<gst::Seqnum>,
+    // Added to the running times of incoming packets so that they stay positive when the
+    // first packet has a negative running time. Fixed by the first packet.
+    running_time_offset: Option<gst::ClockTime>,
 
     recv_store: Vec<HeldRecvItem>,
 
@@ -293,19 +558,51 @@ struct RecvSession {
     recv_flow_combiner: Arc<Mutex<gst_base::UniqueFlowCombiner>>,
 
     rtcp_recv_sinkpad: Option<gst::Pad>,
+
+    // EOS held back until all remote senders are gone with EosHandling::WaitBye
+    pending_eos: Option<gst::Event>,
+
+    // Created with the create-session signal, kept until destroy-session
+    // even if all its pads are released
+    created: bool,
+
+    timeout_task: tokio::task::JoinHandle<()>,
 }
 
 impl RecvSession {
-    fn new(shared_state: &SharedRtpState, id: usize) -> Self {
+    fn new(
+        rtpbin: &RtpRecv,
+        shared_state: &SharedRtpState,
+        id: usize,
+        settings: &Settings,
+    ) -> Self {
         let internal_session = shared_state.session_get_or_init(id, || {
             SharedSession::new(id, RtpProfile::Avp, RTCP_MIN_REPORT_INTERVAL, false)
         });
+        settings.configure_session(&mut internal_session.inner.lock().unwrap().session);
+
+        // RTCP is only produced if there is also a rtpsend for the session, remote sources
+        // have to time out without it
+        let timeout_task = RUNTIME.spawn({
+            let this = rtpbin.downgrade();
+            async move {
+                loop {
+                    tokio::time::sleep(SOURCE_TIMEOUT_CHECK_INTERVAL).await;
+                    let Some(this) = this.upgrade() else {
+                        break;
+                    };
+                    this.handle_source_timeouts(id);
+                }
+            }
+        });
+
         Self {
             internal_session,
             rtp_recv_sinkpad: None,
             rtp_recv_sink_group_id: None,
             rtp_recv_sink_caps: None,
             rtp_recv_sink_segment: None,
+            running_time_offset: None,
             rtp_recv_sink_seqnum: None,
 
             recv_store: vec![],
@@ -314,110 +611,262 @@ impl RecvSession {
             recv_flow_combiner: Arc::new(Mutex::new(gst_base::UniqueFlowCombiner::new())),
 
             rtcp_recv_sinkpad: None,
+
+            pending_eos: None,
+
+            created: false,
+
+            timeout_task,
         }
     }
 
-    fn start_rtp_task(&mut self, pad: &gst::Pad) -> Result<(), glib::BoolError> {
+    fn start_rtp_task(&mut self, pad: &gst::Pad) {
         gst::debug!(CAT, obj = pad, "Starting rtp recv src task");
 
-        let recv_pad = self
-            .rtp_recv_srcpads
-            .iter_mut()
-            .find(|recv| &recv.pad == pad)
-            .unwrap();
-
-        let pad_weak = pad.downgrade();
-        let recv_flow_combiner = self.recv_flow_combiner.clone();
-        let store = recv_pad.jitter_buffer_store.clone();
+        // A pad of a BUNDLE MID without RtpStreamId has one jitterbuffer per ssrc
+        for recv_pad in self.rtp_recv_srcpads.iter().filter(|recv| &recv.pad == pad) {
+            Self::start_jitterbuffer_task(
+                pad,
+                &recv_pad.jitter_buffer_store,
+                self.recv_flow_combiner.clone(),
+                self.internal_session.id,
+            );
+        }
+    }
 
+    fn start_jitterbuffer_task(
+        pad: &gst::Pad,
+        store: &Arc<Mutex<JitterBufferStore>>,
+        recv_flow_combiner: Arc<Mutex<gst_base::UniqueFlowCombiner>>,
+        session_id: usize,
+    ) {
+        let mut store_guard = store.lock().unwrap();
+        store_guard.jitterbuffer.set_flushing(false);
+        store_guard.waker.take();
+
+        if store_guard
+            .push_task
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
         {
-            let mut store = store.lock().unwrap();
-            store.jitterbuffer.set_flushing(false);
-            store.waker.take();
-        }
-
-        // A task per received ssrc may be a bit excessive.
-        // Other options are:
-        // - Single task per received input stream rather than per output ssrc/pt
-        // - somehow pool multiple recv tasks together (thread pool)
-        pad.start_task(move || {
-            let Some(pad) = pad_weak.upgrade() else {
-                return;
-            };
+            gst::debug!(CAT, obj = pad, "Task already running");
+            return;
+        }
 
-            let recv_flow_combiner = recv_flow_combiner.clone();
+        // All pads share the same runtime, the task only occupies a thread while
+        // it is actually pushing something downstream.
+        store_guard.push_task = Some(PUSH_RUNTIME.spawn({
+            let pad = pad.clone();
             let store = store.clone();
-
-            RUNTIME.block_on(async move {
-                let mut stream = JitterBufferStream::new(store);
+            async move {
+                let mut stream = JitterBufferStream::new(store.clone());
                 while let Some(item) = stream.next().await {
-                    match item {
-                        JitterBufferItem::PacketList(list) => {
-                            let flow = pad.push_list(list);
-                            gst::trace!(CAT, obj = pad, "Pushed buffer list, flow ret {:?}", flow);
-                            let mut recv_flow_combiner = recv_flow_combiner.lock().unwrap();
-                            let _combined_flow = recv_flow_combiner.update_pad_flow(&pad, flow);
-                            // TODO: store flow, return only on session pads?
-                        }
-                        JitterBufferItem::Packet(buffer) => {
-                            let flow = pad.push(buffer);
-                            gst::trace!(CAT, obj = pad, "Pushed buffer, flow ret {:?}", flow);
-                            let mut recv_flow_combiner = recv_flow_combiner.lock().unwrap();
-                            let _combined_flow = recv_flow_combiner.update_pad_flow(&pad, flow);
-                            // TODO: store flow, return only on session pads?
-                        }
-                        JitterBufferItem::Event(event) => {
-                            let res = pad.push_event(event);
-                            gst::trace!(CAT, obj = pad, "Pushed serialized event, result: {}", res);
-                        }
-                        JitterBufferItem::Query(mut query, tx) => {
-                            // This is safe because the thread holding the original reference is waiting
-                            // for us exclusively
-                            let res = pad.peer_query(unsafe { query.as_mut() });
-                            let _ = tx.send(res);
-                        }
-                    }
+                    tokio::task::block_in_place(|| {
+                        Self::push_item(&pad, &store, &recv_flow_combiner, session_id, item)
+                    });
                 }
-            })
-        })?;
+                gst::debug!(CAT, obj = pad, "Task stopped");
+            }
+        }));
 
         gst::debug!(CAT, obj = pad, "Task started");
+    }
 
-        Ok(())
+    fn push_item(
+        pad: &gst::Pad,
+        store: &Mutex<JitterBufferStore>,
+        recv_flow_combiner: &Mutex<gst_base::UniqueFlowCombiner>,
+        session_id: usize,
+        item: JitterBufferItem,
+    ) {
+        match item {
+            JitterBufferItem::PacketList(list) => {
+                let flow = pad.push_list(list);
+                gst::trace!(CAT, obj = pad, "Pushed buffer list, flow ret {:?}", flow);
+                let mut recv_flow_combiner = recv_flow_combiner.lock().unwrap();
+                let _combined_flow = recv_flow_combiner.update_pad_flow(pad, flow);
+                // TODO: store flow, return only on session pads?
+            }
+            JitterBufferItem::Packet(buffer) => {
+                let flow = pad.push(buffer);
+                gst::trace!(CAT, obj = pad, "Pushed buffer, flow ret {:?}", flow);
+                let mut recv_flow_combiner = recv_flow_combiner.lock().unwrap();
+                let _combined_flow = recv_flow_combiner.update_pad_flow(pad, flow);
+                // TODO: store flow, return only on session pads?
+            }
+            JitterBufferItem::Event(event) => {
+                let is_eos = event.type_() == gst::EventType::Eos;
+                let res = pad.push_event(event);
+                gst::trace!(CAT, obj = pad, "Pushed serialized event, result: {}", res);
+
+                if is_eos && store.lock().unwrap().remove_on_eos {
+                    // The pad can't be deactivated from its own task
+                    if let Some(element) = pad.parent_element() {
+                        let pad = pad.clone();
+                        element.call_async(move |element| {
+                            let element = element.downcast_ref::<super::RtpRecv>().unwrap();
+                            element.imp().remove_rtp_src_pad(&pad, session_id);
+                        });
+                    }
+                }
+            }
+            JitterBufferItem::Query(mut query, tx) => {
+                // This is safe because the thread holding the original reference is waiting
+                // for us exclusively
+                let res = pad.peer_query(unsafe { query.as_mut() });
+                let _ = tx.send(res);
+            }
+        }
     }
 
-    fn stop_rtp_task(&mut self, pad: &gst::Pad) -> Result<(), glib::BoolError> {
+    /// Stops the tasks of the pad. The returned tasks have to be waited for once no locks are
+    /// held anymore.
+    #[must_use]
+    fn stop_rtp_task(&mut self, pad: &gst::Pad) -> Vec<tokio::task::JoinHandle<()>> {
         gst::debug!(CAT, obj = pad, "Stopping rtp recv src task");
-        let recv_pad = self
-            .rtp_recv_srcpads
-            .iter_mut()
-            .find(|recv| &recv.pad == pad)
-            .unwrap();
 
-        let mut store = recv_pad.jitter_buffer_store.lock().unwrap();
-        store.jitterbuffer.set_flushing(true);
-        if let Some(waker) = store.waker.take() {
-            waker.wake();
-        }
+        self.rtp_recv_srcpads
+            .iter()
+            .filter(|recv| &recv.pad == pad)
+            .filter_map(|recv_pad| {
+                let mut store = recv_pad.jitter_buffer_store.lock().unwrap();
+                store.jitterbuffer.set_flushing(true);
+                if let Some(waker) = store.waker.take() {
+                    waker.wake();
+                }
 
-        Ok(())
+                store.push_task.take()
+            })
+            .collect()
+    }
+
+    /// Whether a packet is a straggler of the previous ssrc of a BUNDLE MID, i.e. it arrived
+    /// within `window` after the switch to the current ssrc. Such packets are dropped instead
+    /// of switching back and forth between both ssrcs.
+    fn is_previous_ssrc_straggler(
+        &self,
+        bundle_ids: &BundleIds,
+        ssrc: u32,
+        pts: gst::ClockTime,
+        window: gst::ClockTime,
+    ) -> bool {
+        bundle_ids.mid.is_some()
+            && self.rtp_recv_srcpads.iter().any(|r| {
+                r.mid == bundle_ids.mid
+                    && r.rid == bundle_ids.rid
+                    && r.ssrc != ssrc
+                    && r.previous_ssrc.is_some_and(|(previous_ssrc, switch_pts)| {
+                        previous_ssrc == ssrc && pts <= switch_pts + window
+                    })
+            })
+    }
+
+    /// The first entry of each source pad, serialized items are only passed through one of
+    /// the jitterbuffers of a pad
+    fn first_recv_srcpads(&self) -> impl Iterator<Item = &RtpRecvSrcPad> {
+        self.rtp_recv_srcpads
+            .iter()
+            .enumerate()
+            .filter(|(i, r)| {
+                !self.rtp_recv_srcpads[..*i]
+                    .iter()
+                    .any(|other| other.pad == r.pad)
+            })
+            .map(|(_, r)| r)
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn get_or_create_rtp_src(
         &mut self,
         rtpbin: &RtpRecv,
+        session_inner: &SharedSessionInner,
         pt: u8,
         ssrc: u32,
-    ) -> (RtpRecvSrcPad, bool) {
+        bundle_ids: Option<BundleIds>,
+        pts: gst::ClockTime,
+        clock_base_offset: Option<i64>,
+    ) -> (RtpRecvSrcPad, bool, bool, bool) {
+        let BundleIds { mid, rid } = bundle_ids.unwrap_or_default();
+        let ignore_pt = rtpbin.settings.lock().unwrap().ignore_pt;
+
         if let Some(pad) = self
+            .rtp_recv_srcpads
+            .iter_mut()
+            .find(|r| match (&mid, &rid) {
+                (Some(_), Some(_)) => r.mid == mid && r.rid == rid,
+                // All ssrcs of a MID without RtpStreamId share the pad, each with its own
+                // jitterbuffer
+                (Some(_), None) => r.mid == mid && r.rid.is_none() && r.ssrc == ssrc,
+                (None, _) => r.mid.is_none() && r.ssrc == ssrc && (ignore_pt || r.pt == pt),
+            })
+        {
+            // Packets of a MID, or of a SSRC when ignoring the pt, can change their ssrc or
+            // pt at any time, e.g. when a simulcast layer is paused and resumed
+            let pt_changed = pad.pt != pt;
+            let ssrc_changed = pad.ssrc != ssrc;
+            if ssrc_changed {
+                pad.previous_ssrc = Some((pad.ssrc, pts));
+            }
+            pad.pt = pt;
+            pad.ssrc = ssrc;
+            (pad.clone(), false, pt_changed, ssrc_changed)
+        } else if let Some(srcpad) = self
             .rtp_recv_srcpads
             .iter()
-            .find(|&r| r.ssrc == ssrc && r.pt == pt)
+            .find(|r| mid.is_some() && r.mid == mid && r.rid.is_none() && rid.is_none())
+            .map(|r| r.pad.clone())
         {
-            (pad.clone(), false)
+            gst::debug!(CAT, obj = srcpad, "New ssrc {ssrc:#08x} for mid {mid:?}");
+
+            let (jitter_buffer_store, _segment) = self.new_jitter_buffer_store(
+                rtpbin,
+                session_inner,
+                &srcpad,
+                pt,
+                ssrc,
+                mid.as_deref(),
+                rid.as_deref(),
+                pts,
+                clock_base_offset,
+            );
+            let recv_pad = RtpRecvSrcPad {
+                pt,
+                ssrc,
+                previous_ssrc: None,
+                mid,
+                rid,
+                pad: srcpad,
+                jitter_buffer_store,
+                segment: None,
+            };
+            // Otherwise started together with the other ssrcs once the pad is activated
+            if recv_pad.pad.is_active() {
+                Self::start_jitterbuffer_task(
+                    &recv_pad.pad,
+                    &recv_pad.jitter_buffer_store,
+                    self.recv_flow_combiner.clone(),
+                    self.internal_session.id,
+                );
+            }
+            self.rtp_recv_srcpads.push(recv_pad.clone());
+            // The pad already exists but the caps of the new ssrc still have to be sent
+            (recv_pad, false, true, false)
         } else {
-            let src_templ = rtpbin.obj().pad_template("rtp_src_%u_%u_%u").unwrap();
             let id = self.internal_session.id;
+            let (src_templ, name) = match (&mid, &rid) {
+                (Some(mid), Some(rid)) => (
+                    rtpbin.obj().pad_template("rtp_mid_src_%u_%s").unwrap(),
+                    format!("rtp_mid_src_{id}_{mid}_{rid}"),
+                ),
+                (Some(mid), None) => (
+                    rtpbin.obj().pad_template("rtp_mid_src_%u_%s").unwrap(),
+                    format!("rtp_mid_src_{id}_{mid}"),
+                ),
+                _ => (
+                    rtpbin.obj().pad_template("rtp_src_%u_%u_%u").unwrap(),
+                    format!("rtp_src_{id}_{pt}_{ssrc}"),
+                ),
+            };
             let srcpad = gst::Pad::builder_from_template(&src_templ)
                 .iterate_internal_links_function(|pad, parent| {
                     RtpRecv::catch_panic_pad_function(
@@ -437,7 +886,7 @@ impl RecvSession {
                     RtpRecv::catch_panic_pad_function(
                         parent,
                         || false,
-                        |this| this.rtp_src_event(pad, event, id, pt, ssrc),
+                        |this| this.rtp_src_event(pad, event, id),
                     )
                 })
                 .activatemode_function({
@@ -452,22 +901,35 @@ impl RecvSession {
                         this.rtp_src_activatemode(pad, mode, active, id)
                     }
                 })
-                .name(format!("rtp_src_{}_{}_{}", id, pt, ssrc))
+                .name(name)
                 .build();
 
             srcpad.use_fixed_caps();
 
-            let settings = rtpbin.settings.lock().unwrap();
+            let (jitter_buffer_store, segment) = self.new_jitter_buffer_store(
+                rtpbin,
+                session_inner,
+                &srcpad,
+                pt,
+                ssrc,
+                mid.as_deref(),
+                rid.as_deref(),
+                pts,
+                clock_base_offset,
+            );
+            if let Some(ref segment) = segment {
+                gst::debug!(CAT, obj = srcpad, "Starting with segment {segment:?}");
+            }
 
             let recv_pad = RtpRecvSrcPad {
                 pt,
                 ssrc,
+                previous_ssrc: None,
+                mid,
+                rid,
                 pad: srcpad.clone(),
-                jitter_buffer_store: Arc::new(Mutex::new(JitterBufferStore {
-                    waker: None,
-                    store: BTreeMap::new(),
-                    jitterbuffer: JitterBuffer::new(settings.latency.into()),
-                })),
+                jitter_buffer_store,
+                segment,
             };
 
             self.recv_flow_combiner
@@ -475,8 +937,91 @@ impl RecvSession {
                 .unwrap()
                 .add_pad(&recv_pad.pad);
             self.rtp_recv_srcpads.push(recv_pad.clone());
-            (recv_pad, true)
+            (recv_pad, true, false, false)
+        }
+    }
+
+    /// Creates the jitterbuffer for the packets of `ssrc`, together with the segment to start
+    /// a new pad with if the caps contain timing information
+    #[allow(clippy::too_many_arguments)]
+    fn new_jitter_buffer_store(
+        &self,
+        rtpbin: &RtpRecv,
+        session_inner: &SharedSessionInner,
+        srcpad: &gst::Pad,
+        pt: u8,
+        ssrc: u32,
+        mid: Option<&str>,
+        rid: Option<&str>,
+        pts: gst::ClockTime,
+        clock_base_offset: Option<i64>,
+    ) -> (
+        Arc<Mutex<JitterBufferStore>>,
+        Option<gst::FormattedSegment<gst::ClockTime>>,
+    ) {
+        let id = self.internal_session.id;
+        let settings = rtpbin.settings.lock().unwrap();
+
+        let mut jitterbuffer = JitterBuffer::new(settings.latency.into());
+        jitterbuffer.set_passthrough(settings.buffer_mode.passthrough());
+        // Sparse streams are forwarded right away instead of waiting for packets that may
+        // only arrive much later
+        let sparse = settings.sparse_pts.contains(&pt);
+        if sparse {
+            gst::debug!(
+                CAT,
+                obj = srcpad,
+                "Bypassing jitterbuffer for sparse pt {pt}"
+            );
+        }
+        jitterbuffer.set_bypass(session_inner.forward || sparse);
+        jitterbuffer.set_do_lost(settings.do_lost);
+        jitterbuffer.set_size_limits(
+            settings.size_limits(),
+            settings.overflow_policy == OverflowPolicy::DropOldest,
+        );
+
+        let timing = CapsTiming::from_caps(&session_inner.caps_from_ssrc_pt(ssrc, pt), pt, ssrc);
+        if let Some(seqnum_base) = timing.and_then(|timing| timing.seqnum_base) {
+            gst::debug!(CAT, obj = srcpad, "Using seqnum-base {seqnum_base}");
+            jitterbuffer.set_seqnum_base(seqnum_base);
+        }
+        let restored = rtpbin
+            .restored_snapshot
+            .lock()
+            .unwrap()
+            .as_mut()
+            .and_then(|snapshot| snapshot.take_stream(id, pt, ssrc, mid, rid));
+        if let Some(restored) = restored {
+            gst::debug!(
+                CAT,
+                obj = srcpad,
+                "Continuing restored stream at seqnum {}",
+                restored.next_seqnum
+            );
+            jitterbuffer.set_seqnum_base(restored.next_seqnum);
         }
+        let segment = timing.and_then(|timing| {
+            timing.segment(self.rtp_recv_sink_segment.as_ref()?, pts, clock_base_offset)
+        });
+
+        let store = Arc::new(Mutex::new(JitterBufferStore {
+            waker: None,
+            store: BTreeMap::new(),
+            jitterbuffer,
+            remove_on_eos: false,
+            push_task: None,
+            session_id: id,
+            last_drop_message: None,
+        }));
+
+        (store, segment)
+    }
+}
+
+impl Drop for RecvSession {
+    fn drop(&mut self) {
+        self.timeout_task.abort();
     }
 }
 
@@ -508,83 +1053,483 @@ impl State {
             .find(|session| session.internal_session.id == id)
     }
 
+    fn stats_snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            sessions: self
+                .sessions
+                .iter()
+                .map(|session| {
+                    (
+                        session.internal_session.clone(),
+                        session.rtp_recv_srcpads.clone(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// References to everything the statistics are collected from, so that the state lock
+/// does not have to be held while locking each session and jitterbuffer.
+struct StatsSnapshot {
+    sessions: Vec<(SharedSession, Vec<RtpRecvSrcPad>)>,
+}
+
+/// Replaces the sender SSRCs in a compound RTCP packet, i.e. the SSRC of all packets that start
+/// with the SSRC of the sender, the chunks of SDES packets and the sources of BYE packets.
+fn rewrite_rtcp_ssrcs(data: &mut [u8], map: impl Fn(u32) -> u32) {
+    let rewrite = |data: &mut [u8]| {
+        let ssrc = u32::from_be_bytes(data[..4].try_into().unwrap());
+        data[..4].copy_from_slice(&map(ssrc).to_be_bytes());
+    };
+
+    let mut offset = 0;
+    while offset + 4 <= data.len() {
+        let count = (data[offset] & 0x1f) as usize;
+        let pt = data[offset + 1];
+        let len = (u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize + 1) * 4;
+        let Some(packet) = data.get_mut(offset..offset + len) else {
+            break;
+        };
+        offset += len;
+
+        if packet.len() < 8 {
+            continue;
+        }
+
+        match pt {
+            // SDES chunks are an SSRC followed by items, a null item and padding to 32 bits
+            202 => {
+                let mut pos = 4;
+                for _ in 0..count {
+                    if pos + 4 > packet.len() {
+                        break;
+                    }
+                    rewrite(&mut packet[pos..]);
+                    pos += 4;
+                    while pos < packet.len() && packet[pos] != 0 {
+                        pos += 2 + packet.get(pos + 1).copied().unwrap_or(0) as usize;
+                    }
+                    pos = (pos + 4) & !3;
+                }
+            }
+            // BYE
+            203 => {
+                for source in packet[4..].chunks_exact_mut(4).take(count) {
+                    rewrite(source);
+                }
+            }
+            _ => rewrite(&mut packet[4..]),
+        }
+    }
+}
+
+fn jitterbuffer_stats(pad: &RtpRecvSrcPad) -> gst::Structure {
+    let mut jb_stats = pad.jitter_buffer_store.lock().unwrap().jitterbuffer.stats();
+    jb_stats.set_value("ssrc", (pad.ssrc as i32).to_send_value());
+    jb_stats.set_value("pt", (pad.pt as i32).to_send_value());
+    if let Some(ref mid) = pad.mid {
+        jb_stats.set_value("mid", mid.to_send_value());
+    }
+    if let Some(ref rid) = pad.rid {
+        jb_stats.set_value("rid", rid.to_send_value());
+    }
+    jb_stats
+}
+
+/// Adds the counters and fill levels of the jitterbuffers of a single source to its stats
+fn add_jitterbuffer_source_stats<'a>(
+    source_stats: &mut gst::StructureRef,
+    jb_stats: impl Iterator<Item = &'a gst::Structure>,
+) {
+    let mut duplicated = 0;
+    let mut late = 0;
+    let mut recovered = 0;
+    let mut queued_packets = 0;
+    let mut queued_bytes = 0;
+    let mut queued_time = 0;
+    for jb_stats in jb_stats {
+        duplicated += jb_stats.get::<u64>("num-duplicates").unwrap();
+        late += jb_stats.get::<u64>("num-late").unwrap();
+        recovered += jb_stats.get::<u64>("num-recovered").unwrap();
+        queued_packets += jb_stats.get::<u64>("queued-packets").unwrap();
+        queued_bytes += jb_stats.get::<u64>("queued-bytes").unwrap();
+        queued_time = queued_time.max(jb_stats.get::<u64>("queued-time").unwrap());
+    }
+
+    source_stats.set("packets-duplicated", duplicated);
+    source_stats.set("packets-late", late);
+    source_stats.set("packets-recovered", recovered);
+    source_stats.set("jitterbuffer-packets", queued_packets);
+    source_stats.set("jitterbuffer-bytes", queued_bytes);
+    source_stats.set("jitterbuffer-fill-level", queued_time);
+}
+
+impl StatsSnapshot {
     fn stats(&self) -> gst::Structure {
         let mut ret = gst::Structure::builder("application/x-rtp2-stats");
-        for session in self.sessions.iter() {
-            let sess_id = session.internal_session.id;
-            let session_inner = session.internal_session.inner.lock().unwrap();
+        for (internal_session, rtp_recv_srcpads) in self.sessions.iter() {
+            let sess_id = internal_session.id;
+            let session_inner = internal_session.inner.lock().unwrap();
 
             let mut session_stats = session_inner.stats();
-            let jb_stats = gst::List::new(session.rtp_recv_srcpads.iter().map(|pad| {
-                let mut jb_stats = pad.jitter_buffer_store.lock().unwrap().jitterbuffer.stats();
-                jb_stats.set_value("ssrc", (pad.ssrc as i32).to_send_value());
-                jb_stats.set_value("pt", (pad.pt as i32).to_send_value());
-                jb_stats
-            }));
-
-            session_stats.set("jitterbuffer-stats", jb_stats);
+            drop(session_inner);
+            let jb_stats = rtp_recv_srcpads
+                .iter()
+                .map(|pad| (pad.ssrc, jitterbuffer_stats(pad)))
+                .collect::<Vec<_>>();
+
+            let ssrcs = rtp_recv_srcpads
+                .iter()
+                .map(|pad| pad.ssrc)
+                .collect::<HashSet<_>>();
+            for ssrc in ssrcs {
+                let Ok(mut source_stats) = session_stats.get::<gst::Structure>(ssrc.to_string())
+                else {
+                    continue;
+                };
+                add_jitterbuffer_source_stats(
+                    &mut source_stats,
+                    jb_stats
+                        .iter()
+                        .filter(|(pad_ssrc, _)| *pad_ssrc == ssrc)
+                        .map(|(_, s)| s),
+                );
+                session_stats.set(ssrc.to_string(), source_stats);
+            }
+
+            session_stats.set(
+                "jitterbuffer-stats",
+                gst::List::new(jb_stats.into_iter().map(|(_, s)| s)),
+            );
             ret = ret.field(sess_id.to_string(), session_stats);
         }
         ret.build()
     }
 }
 
+/// The sync context used by this element, possibly shared with other elements of the
+/// same sync group, and the ssrcs this element added to it
+#[derive(Debug)]
+struct SyncState {
+    context: Arc<Mutex<sync::Context>>,
+    ssrcs: HashSet<u32>,
+}
+
+impl SyncState {
+    fn context(&mut self, ssrc: u32) -> MutexGuard<'_, sync::Context> {
+        self.ssrcs.insert(ssrc);
+        self.context.lock().unwrap()
+    }
+}
+
+impl Drop for SyncState {
+    fn drop(&mut self) {
+        // Other elements of the sync group may keep using the context
+        let mut context = self.context.lock().unwrap();
+        for ssrc in self.ssrcs.drain() {
+            context.remove_ssrc(ssrc);
+        }
+    }
+}
+
 pub struct RtpRecv {
     settings: Mutex<Settings>,
     state: Arc<Mutex<State>>,
-    sync_context: Arc<Mutex<Option<sync::Context>>>,
+    sync_context: Mutex<Option<SyncState>>,
+    stats_task: Mutex<Option<StatsTask>>,
+    // State restored with the restore-state-snapshot signal that was not applied yet
+    restored_snapshot: Mutex<Option<StateSnapshot>>,
 }
 
 impl RtpRecv {
-    fn rtp_src_activatemode(
-        &self,
-        pad: &gst::Pad,
-        mode: gst::PadMode,
-        active: bool,
-        id: usize,
-    ) -> Result<(), gst::LoggableError> {
-        if let gst::PadMode::Push = mode {
-            let mut state = self.state.lock().unwrap();
-            let Some(session) = state.mut_session_by_id(id) else {
-                if active {
-                    return Err(gst::LoggableError::new(
-                        *CAT,
-                        glib::bool_error!("Can't activate pad of unknown session {id}"),
-                    ));
-                } else {
-                    return Ok(());
-                }
-            };
-
-            if active {
-                session.start_rtp_task(pad)?;
-            } else {
-                session.stop_rtp_task(pad)?;
+    fn start_stats_task(&self) {
+        let stats_interval = self.settings.lock().unwrap().stats_interval;
+        if stats_interval == 0 {
+            return;
+        }
 
-                gst::debug!(CAT, obj = pad, "Stopping task");
+        let state = self.state.clone();
+        let task = StatsTask::start(
+            self.obj().upcast_ref(),
+            Duration::from_millis(stats_interval.into()),
+            move || {
+                let snapshot = state.lock().unwrap().stats_snapshot();
+                snapshot.stats()
+            },
+        );
+        self.stats_task.lock().unwrap().replace(task);
+    }
 
-                let _ = pad.stop_task();
-            }
+    /// Statistics of a single source, including the jitterbuffers of its source pads
+    fn source_stats(&self, id: usize, ssrc: u32) -> Option<gst::Structure> {
+        let (internal_session, rtp_recv_srcpads) = {
+            let state = self.state.lock().unwrap();
+            let session = state.session_by_id(id)?;
+            (
+                session.internal_session.clone(),
+                session
+                    .rtp_recv_srcpads
+                    .iter()
+                    .filter(|pad| pad.ssrc == ssrc)
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            )
+        };
 
-            Ok(())
-        } else {
-            Err(gst::LoggableError::new(
-                *CAT,
-                glib::bool_error!("Unsupported pad mode {mode:?}"),
-            ))
+        let mut source_stats = internal_session.inner.lock().unwrap().source_stats(ssrc)?;
+        if !rtp_recv_srcpads.is_empty() {
+            let jb_stats = rtp_recv_srcpads
+                .iter()
+                .map(jitterbuffer_stats)
+                .collect::<Vec<_>>();
+            add_jitterbuffer_source_stats(&mut source_stats, jb_stats.iter());
+            source_stats.set("jitterbuffer-stats", gst::List::new(jb_stats));
         }
+
+        Some(source_stats)
     }
 
-    pub fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
-        gst::log!(CAT, obj = pad, "Handling query {query:?}");
+    fn sync_offsets(&self) -> gst::Structure {
+        let offsets = self
+            .sync_context
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|sync_state| sync_state.context.lock().unwrap().offsets())
+            .unwrap_or_default();
+
+        gst::Structure::builder("application/x-rtp2-sync-offsets")
+            .field(
+                "offsets",
+                gst::List::new(offsets.into_iter().map(|offset| {
+                    gst::Structure::builder("application/x-rtp2-sync-offset")
+                        .field("ssrc", offset.ssrc)
+                        .field_if_some("cname", offset.cname.as_deref())
+                        .field_if_some("delay", offset.delay)
+                        .field_if_some("offset", offset.offset)
+                        .build()
+                })),
+            )
+            .build()
+    }
 
-        use gst::QueryViewMut::*;
-        match query.view_mut() {
-            Latency(q) => {
+    fn state_snapshot(&self) -> gst::Structure {
+        let stats_snapshot = self.state.lock().unwrap().stats_snapshot();
+
+        let mut snapshot = StateSnapshot::default();
+        for (internal_session, rtp_recv_srcpads) in stats_snapshot.sessions.iter() {
+            for pad in rtp_recv_srcpads {
+                let Some(last_output_seqnum) = pad
+                    .jitter_buffer_store
+                    .lock()
+                    .unwrap()
+                    .jitterbuffer
+                    .last_output_seqnum()
+                else {
+                    continue;
+                };
+
+                snapshot.streams.push(StreamSnapshot {
+                    session: internal_session.id,
+                    pt: pad.pt,
+                    ssrc: pad.ssrc,
+                    mid: pad.mid.clone(),
+                    rid: pad.rid.clone(),
+                    next_seqnum: last_output_seqnum.wrapping_add(1),
+                });
+            }
+        }
+
+        if let Some(ref sync_state) = *self.sync_context.lock().unwrap() {
+            snapshot.ssrcs = sync_state
+                .context
+                .lock()
+                .unwrap()
+                .snapshot(sync_state.ssrcs.iter().copied());
+        }
+
+        gst::debug!(CAT, imp = self, "Created snapshot {snapshot:?}");
+
+        snapshot.to_structure()
+    }
+
+    fn restore_state_snapshot(&self, s: &gst::StructureRef) -> bool {
+        let mut snapshot = match StateSnapshot::from_structure(s) {
+            Ok(snapshot) => snapshot,
+            Err(err) => {
+                gst::warning!(CAT, imp = self, "Invalid state snapshot {s}: {err}");
+                return false;
+            }
+        };
+
+        gst::debug!(CAT, imp = self, "Restoring snapshot {snapshot:?}");
+
+        // Applied once the sync context is created otherwise
+        if let Some(ref mut sync_state) = *self.sync_context.lock().unwrap() {
+            for ssrc in std::mem::take(&mut snapshot.ssrcs) {
+                sync_state.context(ssrc.ssrc).restore(&ssrc);
+            }
+        }
+
+        *self.restored_snapshot.lock().unwrap() = Some(snapshot);
+
+        true
+    }
+
+    fn create_session(&self) -> u32 {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+        let id = state.max_session_id;
+
+        let shared_state = state
+            .shared_state
+            .get_or_insert_with(|| SharedRtpState::recv_get_or_init(settings.rtp_id.clone()));
+        let mut session = RecvSession::new(self, shared_state, id, &settings);
+        session.created = true;
+        let config = session.internal_session.config.clone();
+        state.sessions.push(session);
+        state.max_session_id = id + 1;
+        drop(state);
+
+        gst::debug!(CAT, imp = self, "Created session {id}");
+        self.obj().child_added(&config, &format!("session_{id}"));
+
+        id as u32
+    }
+
+    fn destroy_session(&self, id: usize) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(session) = state.mut_session_by_id(id) else {
+            gst::warning!(CAT, imp = self, "Can't destroy unknown session {id}");
+            return false;
+        };
+        session.created = false;
+        let pads = [
+            session.rtp_recv_sinkpad.clone(),
+            session.rtcp_recv_sinkpad.clone(),
+        ];
+        drop(state);
+
+        gst::debug!(CAT, imp = self, "Destroying session {id}");
+
+        // Releasing the last pad of the session also removes the session
+        for pad in pads.into_iter().flatten() {
+            self.obj().release_request_pad(&pad);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(session) = state.session_by_id(id) {
+            let config = session.internal_session.config.clone();
+            state.sessions.retain(|s| s.internal_session.id != id);
+            drop(state);
+
+            self.obj().child_removed(&config, &format!("session_{id}"));
+        }
+
+        true
+    }
+
+    fn request_session_pad(&self, id: usize, kind: &str) -> Option<gst::Pad> {
+        if self.state.lock().unwrap().session_by_id(id).is_none() {
+            gst::warning!(
+                CAT,
+                imp = self,
+                "Can't request pad for unknown session {id}"
+            );
+            return None;
+        }
+
+        let obj = self.obj();
+        let templ = obj.pad_template(&format!("{kind}_%u"))?;
+        if templ.presence() != gst::PadPresence::Request {
+            return None;
+        }
+
+        obj.request_pad(&templ, Some(&format!("{kind}_{id}")), None)
+    }
+
+    fn rtp_src_activatemode(
+        &self,
+        pad: &gst::Pad,
+        mode: gst::PadMode,
+        active: bool,
+        id: usize,
+    ) -> Result<(), gst::LoggableError> {
+        if let gst::PadMode::Push = mode {
+            let mut state = self.state.lock().unwrap();
+            let Some(session) = state.mut_session_by_id(id) else {
+                if active {
+                    return Err(gst::LoggableError::new(
+                        *CAT,
+                        glib::bool_error!("Can't activate pad of unknown session {id}"),
+                    ));
+                } else {
+                    return Ok(());
+                }
+            };
+
+            if active {
+                session.start_rtp_task(pad);
+            } else {
+                let tasks = session.stop_rtp_task(pad);
+                drop(state);
+
+                for task in tasks {
+                    gst::debug!(CAT, obj = pad, "Waiting for task to stop");
+                    let _ = futures::executor::block_on(task);
+                }
+            }
+
+            Ok(())
+        } else {
+            Err(gst::LoggableError::new(
+                *CAT,
+                glib::bool_error!("Unsupported pad mode {mode:?}"),
+            ))
+        }
+    }
+
+    /// Whether the packets of a source pad are forwarded without waiting in the jitterbuffer,
+    /// either because its session is in forward mode or because its payload type is sparse
+    fn pad_bypasses_jitterbuffer(&self, pad: &gst::Pad) -> bool {
+        let state = self.state.lock().unwrap();
+        let Some(session) = state
+            .pads_session_id_map
+            .get(pad)
+            .and_then(|id| state.session_by_id(*id))
+        else {
+            return false;
+        };
+
+        session
+            .rtp_recv_srcpads
+            .iter()
+            .find(|recv_pad| &recv_pad.pad == pad)
+            .is_some_and(|recv_pad| {
+                recv_pad
+                    .jitter_buffer_store
+                    .lock()
+                    .unwrap()
+                    .jitterbuffer
+                    .bypass()
+            })
+    }
+
+    pub fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        gst::log!(CAT, obj = pad, "Handling query {query:?}");
+
+        use gst::QueryViewMut::*;
+        match query.view_mut() {
+            Latency(q) => {
                 let mut peer_query = gst::query::Latency::new();
 
                 let ret = gst::Pad::query_default(pad, Some(&*self.obj()), &mut peer_query);
-                let our_latency = self.settings.lock().unwrap().latency;
+                let our_latency = if self.pad_bypasses_jitterbuffer(pad) {
+                    gst::ClockTime::ZERO
+                } else {
+                    self.settings.lock().unwrap().latency
+                };
 
                 let min = if ret {
                     let (_, min, _) = peer_query.result();
@@ -613,13 +1558,18 @@ impl RtpRecv {
             if let Some(session) = state.session_by_id(id) {
                 if let Some(ref sinkpad) = session.rtp_recv_sinkpad {
                     if sinkpad == pad {
-                        let pads = session
+                        let mut pads = Vec::<gst::Pad>::new();
+                        for r in session
                             .rtp_recv_srcpads
                             .iter()
                             // Only include pads that are already part of the element
                             .filter(|r| state.pads_session_id_map.contains_key(&r.pad))
-                            .map(|r| r.pad.clone())
-                            .collect();
+                        {
+                            // Pads of a BUNDLE MID can have multiple jitterbuffers
+                            if !pads.contains(&r.pad) {
+                                pads.push(r.pad.clone());
+                            }
+                        }
                         return gst::Iterator::from_vec(pads);
                     } else if session.rtp_recv_srcpads.iter().any(|r| &r.pad == pad) {
                         return gst::Iterator::from_vec(vec![sinkpad.clone()]);
@@ -631,6 +1581,49 @@ impl RtpRecv {
         gst::Iterator::from_vec(vec![])
     }
 
+    /// Rewrites the SSRC of an RTP packet if the session handles packets from `addr` with a
+    /// different SSRC because another address already uses the same one.
+    fn rewrite_recv_ssrc(
+        &self,
+        session: &RecvSession,
+        buffer: &mut gst::Buffer,
+        addr: SocketAddr,
+    ) -> Result<(), gst::FlowError> {
+        let mut session_inner = session.internal_session.inner.lock().unwrap();
+        if !session_inner.session.separate_sources_by_address() {
+            return Ok(());
+        }
+
+        let ssrc = {
+            let mapped = buffer.map_readable().map_err(|e| {
+                gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                gst::FlowError::Error
+            })?;
+            match rtp_types::RtpPacket::parse(&mapped) {
+                Ok(rtp) => rtp.ssrc(),
+                // Handled later
+                Err(_) => return Ok(()),
+            }
+        };
+
+        let recv_ssrc = session_inner.session.recv_ssrc(ssrc, addr);
+        drop(session_inner);
+        if recv_ssrc != ssrc {
+            gst::trace!(
+                CAT,
+                imp = self,
+                "Rewriting ssrc {ssrc:#08x} from {addr:?} to {recv_ssrc:#08x}"
+            );
+            let mut mapped = buffer.make_mut().map_writable().map_err(|e| {
+                gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                gst::FlowError::Error
+            })?;
+            mapped[8..12].copy_from_slice(&recv_ssrc.to_be_bytes());
+        }
+
+        Ok(())
+    }
+
     fn handle_buffer_locked<const H: usize, const P: usize>(
         &self,
         pad: &gst::Pad,
@@ -646,26 +1639,52 @@ impl RtpRecv {
         //
         // Check if this makes sense or if this leads to issue with eg interleaved
         // TCP.
-        let arrival_time = match buffer.dts() {
+        let running_time = match buffer.dts() {
             Some(dts) => {
                 let segment = session.rtp_recv_sink_segment.as_ref().unwrap();
-                // TODO: use running_time_full if we care to support that
-                match segment.to_running_time(dts) {
-                    Some(time) => time,
+                match segment.to_running_time_full(dts) {
+                    Some(running_time) => running_time,
                     None => {
-                        gst::error!(CAT, obj = pad, "out of segment DTS are not supported");
+                        gst::error!(
+                            CAT,
+                            obj = pad,
+                            "Failed to convert DTS {dts} to running time"
+                        );
                         return Err(gst::FlowError::Error);
                     }
                 }
             }
             None => match self.obj().current_running_time() {
-                Some(time) => time,
+                Some(time) => gst::Signed::Positive(time),
                 None => {
                     gst::error!(CAT, obj = pad, "Failed to get current time");
                     return Err(gst::FlowError::Error);
                 }
             },
         };
+        // Negative running times happen e.g. with interleaved TCP or trick modes. The
+        // synchronization code only deals with positive times, so shift all running times
+        // by the amount the first packet is negative to keep the relative timing.
+        let running_time_offset = *session
+            .running_time_offset
+            .get_or_insert(match running_time {
+                gst::Signed::Negative(time) => time,
+                gst::Signed::Positive(_) => gst::ClockTime::ZERO,
+            });
+        let arrival_time = match running_time {
+            gst::Signed::Positive(time) => time + running_time_offset,
+            gst::Signed::Negative(time) => match running_time_offset.checked_sub(time) {
+                Some(time) => time,
+                None => {
+                    gst::warning!(
+                        CAT,
+                        obj = pad,
+                        "Dropping packet with running time -{time} before the first packet"
+                    );
+                    return Ok(RecvRtpBuffer::Drop);
+                }
+            },
+        };
 
         let addr: Option<SocketAddr> =
             buffer
@@ -677,6 +1696,11 @@ impl RtpRecv {
                         .map(|a| a.into())
                         .ok()
                 });
+
+        if let Some(addr) = addr {
+            self.rewrite_recv_ssrc(session, &mut buffer, addr)?;
+        }
+
         let mapped = buffer.map_readable().map_err(|e| {
             gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
             gst::FlowError::Error
@@ -708,29 +1732,128 @@ impl RtpRecv {
         let internal_session = session.internal_session.clone();
         let mut session_inner = internal_session.inner.lock().unwrap();
 
-        let pts = {
-            let mut sync_context = self.sync_context.lock().unwrap();
-            let sync_context = sync_context.as_mut().unwrap();
+        // Only copy the header extension data if there is anything to read it
+        let hdrext = if session_inner.extensions.is_empty()
+            && !session_inner.has_bundle_extensions()
+        {
+            None
+        } else {
+            rtp.extension()
+                .map(|(pattern, data)| (pattern, smallvec::SmallVec::<[u8; 64]>::from_slice(data)))
+        };
+        if let Some((pattern, ref data)) = hdrext {
+            session_inner.read_bundle_ids(rtp.ssrc(), pattern, data);
+        }
+
+        let (pts, ntp_time, clock_base_offset) = {
+            let mut sync_state = self.sync_context.lock().unwrap();
+            let mut sync_context = sync_state.as_mut().unwrap().context(rtp.ssrc());
             if !sync_context.has_clock_rate(rtp.ssrc()) {
-                let clock_rate = session_inner
+                let Some(clock_rate) = session_inner
                     .session
-                    .clock_rate_from_pt(rtp.payload_type())
-                    .unwrap();
+                    .clock_rate_from_ssrc_pt(rtp.ssrc(), rtp.payload_type())
+                else {
+                    gst::warning!(
+                        CAT,
+                        obj = pad,
+                        "No clock-rate for pt {} of ssrc {:#08x}, dropping packet",
+                        rtp.payload_type(),
+                        rtp.ssrc()
+                    );
+                    return Ok(RecvRtpBuffer::Drop);
+                };
                 sync_context.set_clock_rate(rtp.ssrc(), clock_rate);
+
+                let caps = session_inner.caps_from_ssrc_pt(rtp.ssrc(), rtp.payload_type());
+                if let Some(clock_base) =
+                    CapsTiming::from_caps(&caps, rtp.payload_type(), rtp.ssrc())
+                        .and_then(|timing| timing.clock_base)
+                {
+                    gst::debug!(CAT, obj = pad, "Using clock-base {clock_base}");
+                    sync_context.set_clock_base(rtp.ssrc(), clock_base);
+                }
+                if let Some(media_clock) = sync::MediaClock::from_caps(&caps) {
+                    gst::debug!(CAT, obj = pad, "Using RFC 7273 media clock {media_clock:?}");
+                    sync_context.set_media_clock(rtp.ssrc(), media_clock);
+                }
             }
 
-            // TODO: Put NTP time as `gst::ReferenceTimeStampMeta` on the buffers if selected via property
-            let (pts, _ntp_time) =
+            let (mut pts, ntp_time) =
                 sync_context.calculate_pts(rtp.ssrc(), rtp.timestamp(), arrival_time.nseconds());
-            pts
+            if self.settings.lock().unwrap().rfc7273_sync {
+                if let Some(media_clock_pts) = self.media_clock_pts(
+                    &sync_context,
+                    rtp.ssrc(),
+                    rtp.timestamp(),
+                    arrival_time,
+                    running_time_offset,
+                ) {
+                    gst::trace!(CAT, obj = pad, "Using media clock PTS {media_clock_pts}");
+                    pts = media_clock_pts;
+                }
+            }
+            (pts, ntp_time, sync_context.clock_base_offset(rtp.ssrc()))
+        };
+        let pts = pts.saturating_add_signed(session_inner.ts_offset);
+
+        let ntp_time = if self.settings.lock().unwrap().add_reference_timestamp_meta {
+            ntp_time
+                .and_then(|ntp_time| ntp_time.as_duration().ok())
+                .and_then(|ntp_time| gst::ClockTime::try_from(ntp_time).ok())
+        } else {
+            None
         };
 
         let segment = session.rtp_recv_sink_segment.as_ref().unwrap();
-        let pts = segment
-            .position_from_running_time(gst::ClockTime::from_nseconds(pts))
-            .unwrap();
+        let pts = gst::ClockTime::from_nseconds(pts);
+        let position = match pts.checked_sub(running_time_offset) {
+            Some(running_time) => segment.position_from_running_time_full(running_time),
+            // Negative running time: position before the segment start
+            None if segment.rate() > 0.0 => {
+                let running_time = running_time_offset - pts;
+                let duration = (running_time + segment.base().unwrap_or(gst::ClockTime::ZERO))
+                    .nseconds() as f64
+                    * segment.rate();
+                segment
+                    .start()
+                    .and_then(|start| {
+                        start.checked_sub(gst::ClockTime::from_nseconds(duration as u64))
+                    })
+                    .map(gst::Signed::Positive)
+            }
+            None => None,
+        };
+        let pts = match position {
+            Some(gst::Signed::Positive(position)) => position,
+            _ => {
+                gst::warning!(
+                    CAT,
+                    obj = pad,
+                    "Dropping packet at running time {pts} - {running_time_offset} without positive position in segment"
+                );
+                return Ok(RecvRtpBuffer::Drop);
+            }
+        };
         gst::debug!(CAT, obj = pad, "Calculated PTS: {}", pts);
 
+        if let Some(bundle_ids) = session_inner.session.bundle_ids(rtp.ssrc()) {
+            // Packets can be misordered by up to max-misorder-time on top of the latency
+            let window = {
+                let settings = self.settings.lock().unwrap();
+                settings.latency + gst::ClockTime::from_mseconds(settings.max_misorder_time as u64)
+            };
+            if session.is_previous_ssrc_straggler(bundle_ids, rtp.ssrc(), pts, window) {
+                gst::debug!(
+                    CAT,
+                    obj = pad,
+                    "Dropping straggler packet {} of previous ssrc {:#08x}",
+                    rtp.sequence_number(),
+                    rtp.ssrc()
+                );
+                return Ok(RecvRtpBuffer::Drop);
+            }
+        }
+
         loop {
             let recv_ret = session_inner.session.handle_recv(&rtp, addr, now);
             gst::trace!(CAT, obj = pad, "session handle_recv ret: {recv_ret:?}");
@@ -738,9 +1861,7 @@ impl RtpRecv {
                 RecvReply::SsrcCollision(ssrc) => return Ok(RecvRtpBuffer::SsrcCollision(ssrc)),
                 RecvReply::NewSsrc(ssrc, _pt) => {
                     drop(session_inner);
-                    internal_session
-                        .config
-                        .emit_by_name::<()>("new-ssrc", &[&ssrc]);
+                    internal_session.notify_new_ssrc(self.obj().upcast_ref(), ssrc, false);
                     session_inner = internal_session.inner.lock().unwrap();
                 }
                 RecvReply::Hold(hold_id) => {
@@ -750,11 +1871,42 @@ impl RtpRecv {
                     {
                         let buf_mut = buffer.make_mut();
                         buf_mut.set_pts(pts);
+                        if let Some(ntp_time) = ntp_time {
+                            gst::ReferenceTimestampMeta::add(buf_mut, &NTP_CAPS, ntp_time, None);
+                        }
+                        if let Some((pattern, ref data)) = hdrext {
+                            session_inner.read_header_extensions(pattern, data, buf_mut);
+                        }
                     }
-                    let (pad, new_pad) = session.get_or_create_rtp_src(self, pt, ssrc);
+                    let bundle_ids = session_inner.session.bundle_ids(ssrc).cloned();
+                    let (pad, new_pad, pt_changed, ssrc_changed) = session.get_or_create_rtp_src(
+                        self,
+                        &session_inner,
+                        pt,
+                        ssrc,
+                        bundle_ids,
+                        pts,
+                        clock_base_offset,
+                    );
                     let jb = pad.jitter_buffer_store.clone();
                     if new_pad {
                         items_to_pre_push.push(HeldRecvItem::NewPad(pad));
+                    } else {
+                        if ssrc_changed {
+                            gst::debug!(CAT, obj = pad.pad, "SSRC changed to {ssrc:#08x}");
+                            items_to_pre_push.push(HeldRecvItem::ResetSequence(jb.clone()));
+                        }
+                        if pt_changed {
+                            gst::debug!(CAT, obj = pad.pad, "Payload type changed to {pt}");
+                        }
+                        // The clock-rate can be configured per SSRC
+                        if pt_changed || ssrc_changed {
+                            let caps = pad.caps(&session_inner, pt);
+                            items_to_pre_push.push(HeldRecvItem::Event(
+                                jb.clone(),
+                                gst::event::Caps::new(&caps),
+                            ));
+                        }
                     }
                     held_buffers.push(HeldRecvBuffer {
                         hold_id: Some(hold_id),
@@ -797,11 +1949,42 @@ impl RtpRecv {
                     {
                         let buf_mut = buffer.make_mut();
                         buf_mut.set_pts(pts);
+                        if let Some(ntp_time) = ntp_time {
+                            gst::ReferenceTimestampMeta::add(buf_mut, &NTP_CAPS, ntp_time, None);
+                        }
+                        if let Some((pattern, ref data)) = hdrext {
+                            session_inner.read_header_extensions(pattern, data, buf_mut);
+                        }
                     }
-                    let (pad, new_pad) = session.get_or_create_rtp_src(self, pt, ssrc);
+                    let bundle_ids = session_inner.session.bundle_ids(ssrc).cloned();
+                    let (pad, new_pad, pt_changed, ssrc_changed) = session.get_or_create_rtp_src(
+                        self,
+                        &session_inner,
+                        pt,
+                        ssrc,
+                        bundle_ids,
+                        pts,
+                        clock_base_offset,
+                    );
                     let jb = pad.jitter_buffer_store.clone();
                     if new_pad {
                         items_to_pre_push.push(HeldRecvItem::NewPad(pad));
+                    } else {
+                        if ssrc_changed {
+                            gst::debug!(CAT, obj = pad.pad, "SSRC changed to {ssrc:#08x}");
+                            items_to_pre_push.push(HeldRecvItem::ResetSequence(jb.clone()));
+                        }
+                        if pt_changed {
+                            gst::debug!(CAT, obj = pad.pad, "Payload type changed to {pt}");
+                        }
+                        // The clock-rate can be configured per SSRC
+                        if pt_changed || ssrc_changed {
+                            let caps = pad.caps(&session_inner, pt);
+                            items_to_pre_push.push(HeldRecvItem::Event(
+                                jb.clone(),
+                                gst::event::Caps::new(&caps),
+                            ));
+                        }
                     }
                     return Ok(RecvRtpBuffer::Forward((buffer, jb)));
                 }
@@ -816,23 +1999,9 @@ impl RtpRecv {
         session: &mut RecvSession,
         ssrc_collision: impl IntoIterator<Item = u32>,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
-        let session_inner = session.internal_session.inner.lock().unwrap();
-        let send_rtp_sink = session_inner.rtp_send_sinkpad.clone();
-        drop(session_inner);
-
-        if let Some(pad) = send_rtp_sink {
-            // XXX: Another option is to have us rewrite ssrc's instead of asking upstream to do
-            // so.
-            for ssrc in ssrc_collision {
-                pad.send_event(
-                    gst::event::CustomUpstream::builder(
-                        gst::Structure::builder("GstRTPCollision")
-                            .field("ssrc", ssrc)
-                            .build(),
-                    )
-                    .build(),
-                );
-            }
+        for ssrc in ssrc_collision {
+            gst::debug!(CAT, imp = self, "SSRC collision for local ssrc {ssrc:#08x}");
+            session.internal_session.handle_ssrc_collision(ssrc);
         }
 
         Ok(gst::FlowSuccess::Ok)
@@ -855,111 +2024,284 @@ impl RtpRecv {
                     self.obj().add_pad(&pad.pad).unwrap();
                     state = self.state.lock().unwrap();
                 }
+                HeldRecvItem::Event(jb, event) => {
+                    jb.lock().unwrap().queue_serialized_event(event);
+                }
+                HeldRecvItem::ResetSequence(jb) => {
+                    jb.lock().unwrap().jitterbuffer.reset_sequence();
+                }
                 HeldRecvItem::Buffer(buffer) => {
-                    let mapped = buffer.buffer.map_readable().map_err(|e| {
-                        gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
-                        gst::FlowError::Error
-                    })?;
-                    let rtp = match rtp_types::RtpPacket::parse(&mapped) {
-                        Ok(rtp) => rtp,
-                        Err(e) => {
-                            gst::error!(
-                                CAT,
-                                imp = self,
-                                "Failed to parse input as valid rtp packet: {e:?}"
-                            );
-                            return Ok(state);
-                        }
-                    };
+                    self.queue_buffer(&buffer.jb, buffer.buffer, now)?;
+                }
+            }
+        }
 
-                    // FIXME: Should block if too many packets are stored here because the source pad task
-                    // is blocked
-                    let mut jitterbuffer_store = buffer.jb.lock().unwrap();
+        Ok(state)
+    }
 
-                    let ret = jitterbuffer_store.jitterbuffer.queue_packet(
-                        &rtp,
-                        buffer.buffer.pts().unwrap().nseconds(),
-                        now,
+    /// Queues a buffer into the jitterbuffer of a source pad. The jitterbuffer has its own lock,
+    /// the state lock does not need to be held for this.
+    fn queue_buffer(
+        &self,
+        jb: &Mutex<JitterBufferStore>,
+        buffer: gst::Buffer,
+        now: Instant,
+    ) -> Result<(), gst::FlowError> {
+        let mapped = buffer.map_readable().map_err(|e| {
+            gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+            gst::FlowError::Error
+        })?;
+        let rtp = match rtp_types::RtpPacket::parse(&mapped) {
+            Ok(rtp) => rtp,
+            Err(e) => {
+                gst::error!(
+                    CAT,
+                    imp = self,
+                    "Failed to parse input as valid rtp packet: {e:?}"
+                );
+                return Ok(());
+            }
+        };
+
+        // The size limits of the jitterbuffer bound the packets stored here while the source
+        // pad task is blocked
+        let mut jitterbuffer_store = jb.lock().unwrap();
+
+        let pts = buffer.pts().unwrap();
+        let ret = jitterbuffer_store
+            .jitterbuffer
+            .queue_packet(&rtp, pts.nseconds(), now);
+        gst::trace!(CAT, "jb queue buffer: {ret:?}");
+        jitterbuffer_store.remove_evicted();
+        let mut drop_message = None;
+        match ret {
+            jitterbuffer::QueueResult::Flushing => {
+                // TODO: return flushing result upstream
+            }
+            jitterbuffer::QueueResult::Queued(id) => {
+                drop(mapped);
+
+                if buffer.flags().contains(RETRANSMISSION_FLAG) {
+                    jitterbuffer_store.jitterbuffer.packet_recovered();
+                }
+                jitterbuffer_store
+                    .store
+                    .insert(id, JitterBufferItem::Packet(buffer));
+                if let Some(waker) = jitterbuffer_store.waker.take() {
+                    waker.wake()
+                }
+            }
+            jitterbuffer::QueueResult::Late => {
+                gst::warning!(CAT, "Late buffer was dropped");
+                drop_message =
+                    self.drop_message(&mut jitterbuffer_store, &rtp, pts, DropReason::Late, now);
+            }
+            jitterbuffer::QueueResult::Duplicate => {
+                gst::warning!(CAT, "Duplicate buffer was dropped");
+                drop_message = self.drop_message(
+                    &mut jitterbuffer_store,
+                    &rtp,
+                    pts,
+                    DropReason::Duplicate,
+                    now,
+                );
+            }
+            jitterbuffer::QueueResult::Overflow => {
+                drop(jitterbuffer_store);
+                return Err(self.jitterbuffer_overflow());
+            }
+        }
+        drop(jitterbuffer_store);
+
+        if let Some(msg) = drop_message {
+            let _ = self.obj().post_message(msg);
+        }
+
+        Ok(())
+    }
+
+    /// Create a message about a packet dropped by the jitterbuffer, unless drop messages are
+    /// disabled or the last one was posted less than `drop-messages-interval` ago
+    fn drop_message(
+        &self,
+        jitterbuffer_store: &mut JitterBufferStore,
+        rtp: &rtp_types::RtpPacket,
+        pts: gst::ClockTime,
+        reason: DropReason,
+        now: Instant,
+    ) -> Option<gst::Message> {
+        let settings = self.settings.lock().unwrap();
+        if !settings.post_drop_messages {
+            return None;
+        }
+        let interval = Duration::from_millis(settings.drop_messages_interval.into());
+        drop(settings);
+
+        if jitterbuffer_store
+            .last_drop_message
+            .is_some_and(|last| now < last + interval)
+        {
+            return None;
+        }
+        jitterbuffer_store.last_drop_message = Some(now);
+
+        let lateness = match reason {
+            DropReason::Late => jitterbuffer_store
+                .jitterbuffer
+                .lateness(pts.nseconds(), now)
+                .map(|lateness| gst::ClockTime::from_nseconds(lateness.as_nanos() as u64)),
+            DropReason::Duplicate => None,
+        };
+        let stats = jitterbuffer_store.jitterbuffer.stats();
+
+        let s = gst::Structure::builder("rtp-packet-dropped")
+            .field("session", jitterbuffer_store.session_id as u32)
+            .field("ssrc", rtp.ssrc())
+            .field("pt", rtp.payload_type() as u32)
+            .field("seqnum", rtp.sequence_number() as u32)
+            .field("timestamp", pts)
+            .field("reason", reason.as_str())
+            .field_if_some("lateness", lateness)
+            .field("num-late", stats.get::<u64>("num-late").unwrap())
+            .field(
+                "num-duplicates",
+                stats.get::<u64>("num-duplicates").unwrap(),
+            )
+            .build();
+
+        Some(gst::message::Element::builder(s).src(&*self.obj()).build())
+    }
+
+    /// Running time of a packet according to the RFC 7273 media clock of its stream, if the
+    /// pipeline clock is the reference clock of the media clock
+    fn media_clock_pts(
+        &self,
+        sync_context: &sync::Context,
+        ssrc: u32,
+        timestamp: u32,
+        arrival_time: gst::ClockTime,
+        running_time_offset: gst::ClockTime,
+    ) -> Option<u64> {
+        let media_clock = sync_context.media_clock(ssrc)?;
+        let obj = self.obj();
+        let clock = obj.clock()?;
+        if !media_clock.ref_clock.is_clock(&clock) {
+            gst::trace!(
+                CAT,
+                imp = self,
+                "Pipeline clock {clock:?} is not the reference clock {:?}",
+                media_clock.ref_clock
+            );
+            return None;
+        }
+        // Arrival times and the resulting PTS are shifted by the running time offset
+        let base_time = obj.base_time()?.checked_sub(running_time_offset)?;
+
+        sync_context.calculate_media_clock_pts(
+            ssrc,
+            timestamp,
+            (arrival_time + base_time).nseconds(),
+            base_time.nseconds(),
+        )
+    }
+
+    fn jitterbuffer_overflow(&self) -> gst::FlowError {
+        gst::element_imp_error!(
+            self,
+            gst::StreamError::Failed,
+            ["Jitterbuffer size limits exceeded"]
+        );
+
+        gst::FlowError::Error
+    }
+
+    /// Queues a buffer list into the jitterbuffer of a source pad, see `queue_buffer()`
+    fn queue_buffer_list(
+        &self,
+        jb: &Mutex<JitterBufferStore>,
+        list: gst::BufferList,
+        now: Instant,
+    ) -> Result<(), gst::FlowError> {
+        // The size limits of the jitterbuffer bound the packets stored here while the source
+        // pad task is blocked
+        let mut jitterbuffer_store = jb.lock().unwrap();
+        let mut drop_messages = vec![];
+
+        for buffer in list.iter_owned() {
+            let mapped = buffer.map_readable().map_err(|e| {
+                gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                gst::FlowError::Error
+            })?;
+            let rtp = match rtp_types::RtpPacket::parse(&mapped) {
+                Ok(rtp) => rtp,
+                Err(e) => {
+                    gst::error!(
+                        CAT,
+                        imp = self,
+                        "Failed to parse input as valid rtp packet: {e:?}"
                     );
-                    gst::trace!(CAT, "jb queue buffer: {ret:?}");
-                    match ret {
-                        jitterbuffer::QueueResult::Flushing => {
-                            // TODO: return flushing result upstream
-                        }
-                        jitterbuffer::QueueResult::Queued(id) => {
-                            drop(mapped);
-
-                            jitterbuffer_store
-                                .store
-                                .insert(id, JitterBufferItem::Packet(buffer.buffer));
-                            if let Some(waker) = jitterbuffer_store.waker.take() {
-                                waker.wake()
-                            }
-                        }
-                        jitterbuffer::QueueResult::Late => {
-                            gst::warning!(CAT, "Late buffer was dropped");
-                        }
-                        jitterbuffer::QueueResult::Duplicate => {
-                            gst::warning!(CAT, "Duplicate buffer was dropped");
-                        }
-                    }
+                    return Ok(());
                 }
-                HeldRecvItem::BufferList(list) => {
-                    // FIXME: Should block if too many packets are stored here because the source pad task
-                    // is blocked
-                    let mut jitterbuffer_store = list.jb.lock().unwrap();
-
-                    for buffer in list.list.iter_owned() {
-                        let mapped = buffer.map_readable().map_err(|e| {
-                            gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
-                            gst::FlowError::Error
-                        })?;
-                        let rtp = match rtp_types::RtpPacket::parse(&mapped) {
-                            Ok(rtp) => rtp,
-                            Err(e) => {
-                                gst::error!(
-                                    CAT,
-                                    imp = self,
-                                    "Failed to parse input as valid rtp packet: {e:?}"
-                                );
-                                return Ok(state);
-                            }
-                        };
+            };
 
-                        let ret = jitterbuffer_store.jitterbuffer.queue_packet(
-                            &rtp,
-                            buffer.pts().unwrap().nseconds(),
-                            now,
-                        );
-                        gst::trace!(CAT, "jb queue buffer in list: {ret:?}");
-                        match ret {
-                            jitterbuffer::QueueResult::Flushing => {
-                                return Err(gst::FlowError::Flushing);
-                            }
-                            jitterbuffer::QueueResult::Queued(id) => {
-                                drop(mapped);
+            let pts = buffer.pts().unwrap();
+            let ret = jitterbuffer_store
+                .jitterbuffer
+                .queue_packet(&rtp, pts.nseconds(), now);
+            gst::trace!(CAT, "jb queue buffer in list: {ret:?}");
+            jitterbuffer_store.remove_evicted();
+            match ret {
+                jitterbuffer::QueueResult::Flushing => {
+                    return Err(gst::FlowError::Flushing);
+                }
+                jitterbuffer::QueueResult::Queued(id) => {
+                    drop(mapped);
 
-                                jitterbuffer_store
-                                    .store
-                                    .insert(id, JitterBufferItem::Packet(buffer));
+                    if buffer.flags().contains(RETRANSMISSION_FLAG) {
+                        jitterbuffer_store.jitterbuffer.packet_recovered();
+                    }
+                    jitterbuffer_store
+                        .store
+                        .insert(id, JitterBufferItem::Packet(buffer));
 
-                                if let Some(waker) = jitterbuffer_store.waker.take() {
-                                    waker.wake()
-                                }
-                            }
-                            jitterbuffer::QueueResult::Late => {
-                                gst::warning!(CAT, "Late buffer was dropped");
-                            }
-                            jitterbuffer::QueueResult::Duplicate => {
-                                gst::warning!(CAT, "Duplicate buffer was dropped");
-                            }
-                        }
+                    if let Some(waker) = jitterbuffer_store.waker.take() {
+                        waker.wake()
                     }
                 }
+                jitterbuffer::QueueResult::Late => {
+                    gst::warning!(CAT, "Late buffer was dropped");
+                    drop_messages.extend(self.drop_message(
+                        &mut jitterbuffer_store,
+                        &rtp,
+                        pts,
+                        DropReason::Late,
+                        now,
+                    ));
+                }
+                jitterbuffer::QueueResult::Duplicate => {
+                    gst::warning!(CAT, "Duplicate buffer was dropped");
+                    drop_messages.extend(self.drop_message(
+                        &mut jitterbuffer_store,
+                        &rtp,
+                        pts,
+                        DropReason::Duplicate,
+                        now,
+                    ));
+                }
+                jitterbuffer::QueueResult::Overflow => {
+                    drop(mapped);
+                    drop(jitterbuffer_store);
+                    return Err(self.jitterbuffer_overflow());
+                }
             }
         }
+        drop(jitterbuffer_store);
 
-        Ok(state)
+        for msg in drop_messages {
+            let _ = self.obj().post_message(msg);
+        }
+
+        Ok(())
     }
 
     fn rtp_sink_chain_list(
@@ -975,6 +2317,7 @@ impl RtpRecv {
 
         let now = Instant::now();
         let mut ssrc_collision: smallvec::SmallVec<[u32; 4]> = Default::default();
+        let mut rtcp_buffers: smallvec::SmallVec<[gst::Buffer; 2]> = Default::default();
         let mut items_to_pre_push: smallvec::SmallVec<[HeldRecvItem; 4]> =
             smallvec::SmallVec::with_capacity(list.len() + 2);
         let mut held_buffers: smallvec::SmallVec<[HeldRecvBuffer; 4]> = Default::default();
@@ -996,13 +2339,9 @@ impl RtpRecv {
                     ControlFlow::Continue(None)
                 }
                 Ok(RecvRtpBuffer::IsRtcp(buffer)) => {
-                    match Self::rtcp_sink_chain(self, id, buffer) {
-                        Ok(_buf) => ControlFlow::Continue(None),
-                        Err(e) => {
-                            ret = Err(e);
-                            ControlFlow::Break(None)
-                        }
-                    }
+                    // Handled once the state lock is released
+                    rtcp_buffers.push(buffer);
+                    ControlFlow::Continue(None)
                 }
                 Ok(RecvRtpBuffer::Drop) => ControlFlow::Continue(None),
                 Ok(RecvRtpBuffer::Forward((buffer, jb))) => {
@@ -1030,47 +2369,28 @@ impl RtpRecv {
             .extend(held_buffers.into_iter().map(HeldRecvItem::Buffer));
 
         self.handle_ssrc_collision(session, ssrc_collision)?;
+        let internal_session = session.internal_session.clone();
         state = self.handle_push_jitterbuffer(state, id, items_to_pre_push, now)?;
-        if split_bufferlist {
-            // this abomination is to work around passing state through handle_push_jitterbuffer
-            // inside a closure
-            let mut maybe_state = Some(state);
-            list_mut.foreach_mut({
-                let maybe_state = &mut maybe_state;
-                |buffer, _i| match self.handle_push_jitterbuffer(
-                    maybe_state.take().unwrap(),
-                    id,
-                    [HeldRecvItem::Buffer(HeldRecvBuffer {
-                        hold_id: None,
-                        buffer,
-                        jb: previous_jb.clone().unwrap(),
-                    })],
-                    now,
-                ) {
-                    Ok(state) => {
-                        *maybe_state = Some(state);
-                        ControlFlow::Continue(None)
-                    }
+        drop(state);
+
+        if let Some(jb) = previous_jb {
+            if split_bufferlist {
+                list_mut.foreach_mut(|buffer, _i| match self.queue_buffer(&jb, buffer, now) {
+                    Ok(()) => ControlFlow::Continue(None),
                     Err(e) => {
                         ret = Err(e);
                         ControlFlow::Break(None)
                     }
-                }
-            });
-            state = maybe_state.unwrap();
-            ret?;
-        } else {
-            state = self.handle_push_jitterbuffer(
-                state,
-                id,
-                [HeldRecvItem::BufferList(HeldRecvBufferList {
-                    list,
-                    jb: previous_jb.unwrap(),
-                })],
-                now,
-            )?;
+                });
+                ret?;
+            } else {
+                self.queue_buffer_list(&jb, list, now)?;
+            }
+        }
+
+        for buffer in rtcp_buffers {
+            self.handle_rtcp(&internal_session, buffer)?;
         }
-        drop(state);
 
         Ok(gst::FlowSuccess::Ok)
     }
@@ -1100,7 +2420,11 @@ impl RtpRecv {
             RecvRtpBuffer::SsrcCollision(ssrc) => {
                 return self.handle_ssrc_collision(session, [ssrc])
             }
-            RecvRtpBuffer::IsRtcp(buffer) => return Self::rtcp_sink_chain(self, id, buffer),
+            RecvRtpBuffer::IsRtcp(buffer) => {
+                let internal_session = session.internal_session.clone();
+                drop(state);
+                return self.handle_rtcp(&internal_session, buffer);
+            }
             RecvRtpBuffer::Drop => None,
             RecvRtpBuffer::Forward((buffer, jb)) => Some((buffer, jb)),
         };
@@ -1109,19 +2433,11 @@ impl RtpRecv {
             .extend(held_buffers.into_iter().map(HeldRecvItem::Buffer));
 
         state = self.handle_push_jitterbuffer(state, id, items_to_pre_push, now)?;
+        drop(state);
+
         if let Some((buffer, jb)) = forward {
-            state = self.handle_push_jitterbuffer(
-                state,
-                id,
-                [HeldRecvItem::Buffer(HeldRecvBuffer {
-                    hold_id: None,
-                    buffer,
-                    jb,
-                })],
-                now,
-            )?;
+            self.queue_buffer(&jb, buffer, now)?;
         }
-        drop(state);
 
         Ok(gst::FlowSuccess::Ok)
     }
@@ -1131,11 +2447,26 @@ impl RtpRecv {
         id: usize,
         buffer: gst::Buffer,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
-        let state = self.state.lock().unwrap();
-        let Some(session) = state.session_by_id(id) else {
+        let Some(internal_session) = self
+            .state
+            .lock()
+            .unwrap()
+            .session_by_id(id)
+            .map(|session| session.internal_session.clone())
+        else {
             return Err(gst::FlowError::Error);
         };
 
+        self.handle_rtcp(&internal_session, buffer)
+    }
+
+    /// Must be called without the state lock held
+    fn handle_rtcp(
+        &self,
+        internal_session: &SharedSession,
+        mut buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let id = internal_session.id;
         let addr: Option<SocketAddr> =
             buffer
                 .meta::<gst_net::NetAddressMeta>()
@@ -1146,6 +2477,20 @@ impl RtpRecv {
                         .map(|a| a.into())
                         .ok()
                 });
+
+        if let Some(addr) = addr {
+            let session_inner = internal_session.inner.lock().unwrap();
+            if session_inner.session.separate_sources_by_address() {
+                let mut mapped = buffer.make_mut().map_writable().map_err(|e| {
+                    gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                    gst::FlowError::Error
+                })?;
+                rewrite_rtcp_ssrcs(&mut mapped, |ssrc| {
+                    session_inner.session.recv_rtcp_ssrc(ssrc, addr)
+                });
+            }
+        }
+
         let mapped = buffer.map_readable().map_err(|e| {
             gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
             gst::FlowError::Error
@@ -1162,7 +2507,6 @@ impl RtpRecv {
             }
         };
 
-        let internal_session = session.internal_session.clone();
         let mut session_inner = internal_session.inner.lock().unwrap();
 
         let now = Instant::now();
@@ -1173,33 +2517,18 @@ impl RtpRecv {
                 .handle_rtcp_recv(rtcp, mapped.len(), addr, now, ntp_now);
         let rtp_send_sinkpad = session_inner.rtp_send_sinkpad.clone();
         drop(session_inner);
-        drop(state);
 
         for reply in replies {
             match reply {
                 RtcpRecvReply::NewSsrc(ssrc) => {
-                    internal_session
-                        .config
-                        .emit_by_name::<()>("new-ssrc", &[&ssrc]);
+                    internal_session.notify_new_ssrc(self.obj().upcast_ref(), ssrc, false);
                 }
                 RtcpRecvReply::SsrcCollision(ssrc) => {
-                    if let Some(pad) = rtp_send_sinkpad.as_ref() {
-                        // XXX: Another option is to have us rewrite ssrc's instead of asking
-                        // upstream to do so.
-                        pad.send_event(
-                            gst::event::CustomUpstream::builder(
-                                gst::Structure::builder("GstRTPCollision")
-                                    .field("ssrc", ssrc)
-                                    .build(),
-                            )
-                            .build(),
-                        );
-                    }
+                    gst::debug!(CAT, imp = self, "SSRC collision for local ssrc {ssrc:#08x}");
+                    internal_session.handle_ssrc_collision(ssrc);
                 }
                 RtcpRecvReply::TimerReconsideration => {
-                    let state = self.state.lock().unwrap();
-                    let session = state.session_by_id(id).unwrap();
-                    let mut session_inner = session.internal_session.inner.lock().unwrap();
+                    let mut session_inner = internal_session.inner.lock().unwrap();
                     if let Some(waker) = session_inner.rtcp_waker.take() {
                         // reconsider timers means that we wake the rtcp task to get a new timeout
                         waker.wake();
@@ -1228,25 +2557,38 @@ impl RtpRecv {
                     }
                 }
                 RtcpRecvReply::NewCName((cname, ssrc)) => {
-                    let mut sync_context = self.sync_context.lock().unwrap();
+                    let mut sync_state = self.sync_context.lock().unwrap();
 
-                    sync_context.as_mut().unwrap().associate(ssrc, &cname);
+                    sync_state
+                        .as_mut()
+                        .unwrap()
+                        .context(ssrc)
+                        .associate(ssrc, &cname);
                 }
                 RtcpRecvReply::NewRtpNtp((ssrc, rtp, ntp)) => {
-                    let mut sync_context = self.sync_context.lock().unwrap();
+                    let mut sync_state = self.sync_context.lock().unwrap();
 
-                    sync_context
+                    sync_state
                         .as_mut()
                         .unwrap()
+                        .context(ssrc)
                         .add_sender_report(ssrc, rtp, ntp);
                 }
-                RtcpRecvReply::SsrcBye(ssrc) => internal_session
-                    .config
-                    .emit_by_name::<()>("bye-ssrc", &[&ssrc]),
+                RtcpRecvReply::SsrcBye(ssrc) => {
+                    internal_session
+                        .config
+                        .emit_by_name::<()>("bye-ssrc", &[&ssrc]);
+                    self.remove_ssrc_pads(id, ssrc);
+                }
+                RtcpRecvReply::TwccStats => {
+                    internal_session.config.notify("twcc-stats");
+                }
             }
         }
         drop(mapped);
 
+        self.maybe_forward_pending_eos(id);
+
         Ok(gst::FlowSuccess::Ok)
     }
 
@@ -1259,8 +2601,7 @@ impl RtpRecv {
 
             if let Some(session) = state.session_by_id(id) {
                 let jb_stores: Vec<Arc<Mutex<JitterBufferStore>>> = session
-                    .rtp_recv_srcpads
-                    .iter()
+                    .first_recv_srcpads()
                     .filter(|r| state.pads_session_id_map.contains_key(&r.pad))
                     .map(|p| p.jitter_buffer_store.clone())
                     .collect();
@@ -1320,38 +2661,202 @@ impl RtpRecv {
         } else {
             gst::Pad::query_default(pad, Some(pad), query)
         }
-    }
+    }
+
+    // Serialized events received on our sink pads have to navigate
+    // through the relevant jitterbuffers in order to remain (reasonably)
+    // consistently ordered with the RTP packets once output on our source
+    // pads
+    fn rtp_sink_queue_serialized_event(&self, id: usize, event: gst::Event) -> bool {
+        let state = self.state.lock().unwrap();
+        if let Some(session) = state.session_by_id(id) {
+            for srcpad in session
+                .first_recv_srcpads()
+                .filter(|r| state.pads_session_id_map.contains_key(&r.pad))
+            {
+                let mut jitterbuffer_store = srcpad.jitter_buffer_store.lock().unwrap();
+                jitterbuffer_store.queue_serialized_event(event.clone());
+            }
+        }
+
+        true
+    }
+
+    // Drops all packets currently waiting in the jitterbuffers of a session so that
+    // serialized items queued afterwards are forwarded right away
+    fn rtp_sink_drain_jitterbuffers(&self, id: usize) {
+        let state = self.state.lock().unwrap();
+        if let Some(session) = state.session_by_id(id) {
+            for srcpad in session.rtp_recv_srcpads.iter() {
+                let mut jitterbuffer_store = srcpad.jitter_buffer_store.lock().unwrap();
+                for id in jitterbuffer_store.jitterbuffer.drain_packets() {
+                    jitterbuffer_store.store.remove(&id);
+                }
+            }
+        }
+    }
+
+    // Signals the remote sources of a session that timed out and removes their pads
+    fn handle_source_timeouts(&self, id: usize) {
+        let state = self.state.lock().unwrap();
+        let Some(internal_session) = state
+            .session_by_id(id)
+            .map(|session| session.internal_session.clone())
+        else {
+            return;
+        };
+        drop(state);
+
+        let mut session_inner = internal_session.inner.lock().unwrap();
+        let (timed_out, reconsidered) = session_inner.session.poll_source_timeouts(Instant::now());
+        if reconsidered {
+            if let Some(waker) = session_inner.rtcp_waker.take() {
+                waker.wake();
+            }
+        }
+        drop(session_inner);
+
+        for ssrc in timed_out {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Remote ssrc {ssrc} of session {id} timed out"
+            );
+            internal_session
+                .config
+                .emit_by_name::<()>("on-timeout", &[&ssrc]);
+            self.remove_ssrc_pads(id, ssrc);
+        }
+
+        self.maybe_forward_pending_eos(id);
+    }
+
+    // Sends EOS on the pads of a remote sender that left the session, the pads are
+    // removed once the EOS went through their jitterbuffer. The jitterbuffer of a sender
+    // sharing a BUNDLE MID pad with other senders is removed right away instead.
+    fn remove_ssrc_pads(&self, id: usize, ssrc: u32) {
+        if !self.settings.lock().unwrap().auto_remove {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        let Some(session) = state.mut_session_by_id(id) else {
+            return;
+        };
+
+        let shared_pads = session
+            .rtp_recv_srcpads
+            .iter()
+            .filter(|r| {
+                r.ssrc == ssrc
+                    && session
+                        .rtp_recv_srcpads
+                        .iter()
+                        .any(|other| other.pad == r.pad && other.ssrc != ssrc)
+            })
+            .map(|r| r.pad.clone())
+            .collect::<Vec<_>>();
+        session.rtp_recv_srcpads.retain(|r| {
+            if r.ssrc != ssrc || !shared_pads.contains(&r.pad) {
+                return true;
+            }
+
+            gst::debug!(
+                CAT,
+                obj = r.pad,
+                "Remote ssrc {ssrc} left session {id}, removing its jitterbuffer"
+            );
+            // Ends the task of the jitterbuffer
+            let mut jitterbuffer_store = r.jitter_buffer_store.lock().unwrap();
+            jitterbuffer_store.jitterbuffer.set_flushing(true);
+            if let Some(waker) = jitterbuffer_store.waker.take() {
+                waker.wake();
+            }
+
+            false
+        });
+
+        let session = state.session_by_id(id).unwrap();
+        for srcpad in session
+            .rtp_recv_srcpads
+            .iter()
+            .filter(|r| r.ssrc == ssrc && state.pads_session_id_map.contains_key(&r.pad))
+        {
+            gst::debug!(
+                CAT,
+                obj = srcpad.pad,
+                "Remote ssrc {ssrc} left session {id}, removing pad"
+            );
+
+            let mut jitterbuffer_store = srcpad.jitter_buffer_store.lock().unwrap();
+            if jitterbuffer_store.remove_on_eos {
+                continue;
+            }
+            jitterbuffer_store.remove_on_eos = true;
+
+            let jitterbuffer::QueueResult::Queued(item_id) =
+                jitterbuffer_store.jitterbuffer.queue_serialized_item()
+            else {
+                unreachable!()
+            };
+
+            jitterbuffer_store.store.insert(
+                item_id,
+                JitterBufferItem::Event(gst::event::Eos::builder().build()),
+            );
+            if let Some(waker) = jitterbuffer_store.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    fn remove_rtp_src_pad(&self, pad: &gst::Pad, id: usize) {
+        let _ = pad.set_active(false);
+
+        let mut state = self.state.lock().unwrap();
+        if state.pads_session_id_map.remove(pad).is_none() {
+            // Already removed together with its session
+            return;
+        }
+        if let Some(session) = state.mut_session_by_id(id) {
+            session.rtp_recv_srcpads.retain(|r| &r.pad != pad);
+            session.recv_flow_combiner.lock().unwrap().remove_pad(pad);
+        }
+        drop(state);
+
+        if pad.has_as_parent(&*self.obj()) {
+            let _ = self.obj().remove_pad(pad);
+        }
+    }
+
+    // Forwards an EOS held back for EosHandling::WaitBye once no remote sender is
+    // active anymore. Returns whether an EOS is still pending.
+    fn maybe_forward_pending_eos(&self, id: usize) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(session) = state.mut_session_by_id(id) else {
+            return false;
+        };
+        if session.pending_eos.is_none() {
+            return false;
+        }
 
-    // Serialized events received on our sink pads have to navigate
-    // through the relevant jitterbuffers in order to remain (reasonably)
-    // consistently ordered with the RTP packets once output on our source
-    // pads
-    fn rtp_sink_queue_serialized_event(&self, id: usize, event: gst::Event) -> bool {
-        let state = self.state.lock().unwrap();
-        if let Some(session) = state.session_by_id(id) {
-            for srcpad in session
-                .rtp_recv_srcpads
-                .iter()
-                .filter(|r| state.pads_session_id_map.contains_key(&r.pad))
-            {
-                let mut jitterbuffer_store = srcpad.jitter_buffer_store.lock().unwrap();
+        let session_inner = session.internal_session.inner.lock().unwrap();
+        if session_inner.session.has_active_remote_senders() {
+            return true;
+        }
+        drop(session_inner);
 
-                let jitterbuffer::QueueResult::Queued(id) =
-                    jitterbuffer_store.jitterbuffer.queue_serialized_item()
-                else {
-                    unreachable!()
-                };
+        let event = session.pending_eos.take().unwrap();
+        drop(state);
 
-                jitterbuffer_store
-                    .store
-                    .insert(id, JitterBufferItem::Event(event.clone()));
-                if let Some(waker) = jitterbuffer_store.waker.take() {
-                    waker.wake();
-                }
-            }
-        }
+        gst::debug!(
+            CAT,
+            imp = self,
+            "All remote senders of session {id} left, forwarding EOS"
+        );
+        self.rtp_sink_queue_serialized_event(id, event);
 
-        true
+        false
     }
 
     fn rtp_sink_event(&self, pad: &gst::Pad, mut event: gst::Event, id: usize) -> bool {
@@ -1378,6 +2883,11 @@ impl RtpRecv {
                         let mut session_inner = session.internal_session.inner.lock().unwrap();
                         session_inner.session.set_pt_clock_rate(pt, clock_rate);
                         session_inner.add_caps(caps);
+                        drop(session_inner);
+
+                        let config = session.internal_session.config.clone();
+                        drop(state);
+                        config.update_extensions();
                     }
                 } else {
                     gst::warning!(
@@ -1419,8 +2929,30 @@ impl RtpRecv {
             }
             gst::EventView::Eos(_eos) => {
                 let now = Instant::now();
-                let state = self.state.lock().unwrap();
-                if let Some(session) = state.session_by_id(id) {
+                let eos_handling = self.settings.lock().unwrap().eos_handling;
+                let mut state = self.state.lock().unwrap();
+                let mut wait_bye = false;
+                if let Some(session) = state.mut_session_by_id(id) {
+                    if eos_handling == EosHandling::WaitBye {
+                        // Without RTCP we would never see a BYE from the remote senders
+                        wait_bye = session.rtcp_recv_sinkpad.is_some()
+                            && session
+                                .internal_session
+                                .inner
+                                .lock()
+                                .unwrap()
+                                .session
+                                .has_active_remote_senders();
+                        if wait_bye {
+                            gst::debug!(
+                                CAT,
+                                obj = pad,
+                                "Holding back EOS until all remote senders left"
+                            );
+                            session.pending_eos = Some(event.clone());
+                        }
+                    }
+
                     let mut session = session.internal_session.inner.lock().unwrap();
                     let ssrcs = session.session.ssrcs().collect::<Vec<_>>();
                     // we can only Bye the entire session if we do not have any local send sources
@@ -1444,45 +2976,58 @@ impl RtpRecv {
                         };
                     }
                     if all_remote {
-                        session.session.schedule_bye("End of stream", now);
+                        let bye_reason = session.session.bye_reason().to_string();
+                        session.session.schedule_bye(&bye_reason, now);
                     }
                     drop(session);
                 }
                 drop(state);
-                // FIXME: may need to delay sending eos under some circumstances
-                self.rtp_sink_queue_serialized_event(id, event);
+
+                match eos_handling {
+                    EosHandling::Immediate => {
+                        self.rtp_sink_drain_jitterbuffers(id);
+                        self.rtp_sink_queue_serialized_event(id, event);
+                    }
+                    EosHandling::WaitBye if wait_bye => {
+                        // Forwarded once the remaining remote senders sent a BYE or timed out
+                    }
+                    EosHandling::Drain | EosHandling::WaitBye => {
+                        self.rtp_sink_queue_serialized_event(id, event);
+                    }
+                }
                 true
             }
             gst::EventView::FlushStart(_fs) => {
-                let state = self.state.lock().unwrap();
-                let mut pause_tasks = vec![];
-                if let Some(session) = state.session_by_id(id) {
-                    for recv_pad in session.rtp_recv_srcpads.iter() {
-                        let mut store = recv_pad.jitter_buffer_store.lock().unwrap();
-                        store.jitterbuffer.set_flushing(true);
-                        if let Some(waker) = store.waker.take() {
-                            waker.wake();
-                        }
-                        pause_tasks.push(recv_pad.pad.clone());
+                let mut state = self.state.lock().unwrap();
+                let mut tasks = vec![];
+                if let Some(session) = state.mut_session_by_id(id) {
+                    let pads = session
+                        .rtp_recv_srcpads
+                        .iter()
+                        .map(|r| r.pad.clone())
+                        .collect::<Vec<_>>();
+                    for pad in pads {
+                        tasks.extend(session.stop_rtp_task(&pad));
                     }
                 }
                 drop(state);
-                for pad in pause_tasks {
-                    let _ = pad.pause_task();
+                for task in tasks {
+                    let _ = futures::executor::block_on(task);
                 }
                 gst::Pad::event_default(pad, Some(&*self.obj()), event)
             }
             gst::EventView::FlushStop(_fs) => {
                 let mut state = self.state.lock().unwrap();
                 if let Some(session) = state.mut_session_by_id(id) {
+                    session.pending_eos = None;
                     let pads = session
                         .rtp_recv_srcpads
                         .iter()
                         .map(|r| r.pad.clone())
                         .collect::<Vec<_>>();
                     for pad in pads {
-                        // Will reset flushing to false and ensure task is woken up
-                        let _ = session.start_rtp_task(&pad);
+                        // Will reset flushing to false and start the task again
+                        session.start_rtp_task(&pad);
                     }
                 }
                 drop(state);
@@ -1498,14 +3043,7 @@ impl RtpRecv {
         }
     }
 
-    fn rtp_src_event(
-        &self,
-        pad: &gst::Pad,
-        event: gst::Event,
-        id: usize,
-        pt: u8,
-        ssrc: u32,
-    ) -> bool {
+    fn rtp_src_event(&self, pad: &gst::Pad, event: gst::Event, id: usize) -> bool {
         match event.view() {
             gst::EventView::CustomUpstream(custom) => {
                 if let Ok(fku) = gst_video::UpstreamForceKeyUnitEvent::parse(custom) {
@@ -1514,6 +3052,14 @@ impl RtpRecv {
 
                     let state = self.state.lock().unwrap();
                     if let Some(session) = state.session_by_id(id) {
+                        let Some((pt, ssrc)) = session
+                            .rtp_recv_srcpads
+                            .iter()
+                            .find(|r| &r.pad == pad)
+                            .map(|r| (r.pt, r.ssrc))
+                        else {
+                            return true;
+                        };
                         let now = Instant::now();
                         let mut session = session.internal_session.inner.lock().unwrap();
                         let caps = session.caps_from_pt(pt);
@@ -1559,6 +3105,7 @@ impl ObjectSubclass for RtpRecv {
     const NAME: &'static str = "GstRtpRecv";
     type Type = super::RtpRecv;
     type ParentType = gst::Element;
+    type Interfaces = (gst::ChildProxy,);
 
     fn new() -> Self {
         GstRustLogger::install();
@@ -1566,6 +3113,8 @@ impl ObjectSubclass for RtpRecv {
             settings: Default::default(),
             state: Default::default(),
             sync_context: Default::default(),
+            stats_task: Default::default(),
+            restored_snapshot: Default::default(),
         }
     }
 }
@@ -1583,7 +3132,7 @@ impl ObjectImpl for RtpRecv {
                     .nick("Buffer latency in ms")
                     .blurb("Amount of ms to buffer")
                     .default_value(DEFAULT_LATENCY.mseconds() as u32)
-                    .mutable_ready()
+                    .mutable_playing()
                     .build(),
                 glib::ParamSpecUInt::builder("stats")
                     .nick("Statistics")
@@ -1596,6 +3145,126 @@ impl ObjectImpl for RtpRecv {
                     .default_value(sync::TimestampingMode::default())
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecEnum::builder::<BufferMode>("buffer-mode")
+                    .nick("Buffer Mode")
+                    .blurb("Govern how packets are timestamped and scheduled for output by the jitterbuffer")
+                    .default_value(BufferMode::default())
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder::<EosHandling>("eos-handling")
+                    .nick("EOS Handling")
+                    .blurb("Govern when EOS received on the RTP sink pads is forwarded to the source pads")
+                    .default_value(EosHandling::default())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("add-reference-timestamp-meta")
+                    .nick("Add Reference Timestamp Meta")
+                    .blurb("Add Reference Timestamp Meta with the NTP capture time from the RTCP sender reports to the buffers")
+                    .default_value(DEFAULT_ADD_REFERENCE_TIMESTAMP_META)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("rfc7273-sync")
+                    .nick("Sync on RFC7273 clock")
+                    .blurb("Timestamp packets of streams with a RFC 7273 media clock against the pipeline clock, if it is the reference clock")
+                    .default_value(DEFAULT_RFC7273_SYNC)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("do-lost")
+                    .nick("Do Lost")
+                    .blurb("Send an event downstream when a packet is considered lost")
+                    .default_value(DEFAULT_DO_LOST)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("ignore-pt")
+                    .nick("Ignore PT")
+                    .blurb("Create a single source pad per SSRC regardless of the payload type, payload type changes are signalled with caps events")
+                    .default_value(DEFAULT_IGNORE_PT)
+                    .mutable_ready()
+                    .build(),
+                gst::ParamSpecArray::builder("sparse-pts")
+                    .nick("Sparse Payload Types")
+                    .blurb("Payload types of sparse streams, e.g. metadata or DTMF, that are forwarded immediately without waiting in the jitterbuffer or adding latency")
+                    .element_spec(
+                        &glib::ParamSpecUInt::builder("pt")
+                            .nick("Payload Type")
+                            .blurb("Payload type of a sparse stream")
+                            .maximum(0x7f)
+                            .build(),
+                    )
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("auto-remove")
+                    .nick("Auto Remove")
+                    .blurb("Send EOS on and remove the source pads of remote senders that sent a BYE or timed out")
+                    .default_value(DEFAULT_AUTO_REMOVE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-dropout-time")
+                    .nick("Max dropout time")
+                    .blurb("The maximum time (milliseconds) of missing packets tolerated before considering the sequence restarted (0 = fixed number of packets)")
+                    .default_value(DEFAULT_MAX_DROPOUT_TIME)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-misorder-time")
+                    .nick("Max misorder time")
+                    .blurb("The maximum time (milliseconds) of misordered packets tolerated before considering the sequence restarted (0 = fixed number of packets)")
+                    .default_value(DEFAULT_MAX_MISORDER_TIME)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("probation")
+                    .nick("Number of probations")
+                    .blurb("Consecutive packets a new source has to send before its packets are forwarded (0 = disabled)")
+                    .default_value(DEFAULT_PROBATION)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("stats-interval")
+                    .nick("Statistics interval")
+                    .blurb("Interval (in ms) for posting the statistics as element message (0 = disabled)")
+                    .default_value(DEFAULT_STATS_INTERVAL)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecString::builder("sync-group")
+                    .nick("Sync Group")
+                    .blurb("Synchronize streams with the same CNAME with those of other elements using the same sync group")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("post-drop-messages")
+                    .nick("Post Drop Messages")
+                    .blurb("Post element messages about late and duplicate packets dropped by the jitterbuffers")
+                    .default_value(DEFAULT_POST_DROP_MESSAGES)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("drop-messages-interval")
+                    .nick("Drop Messages Interval")
+                    .blurb("Minimum interval (in ms) between drop messages of a source pad (0 = no limit)")
+                    .default_value(DEFAULT_DROP_MESSAGES_INTERVAL)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-size-packets")
+                    .nick("Max Size Packets")
+                    .blurb("Maximum number of packets stored per jitterbuffer (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_SIZE_PACKETS)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-size-bytes")
+                    .nick("Max Size Bytes")
+                    .blurb("Maximum number of bytes stored per jitterbuffer (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_SIZE_BYTES)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("max-size-time")
+                    .nick("Max Size Time")
+                    .blurb("Maximum duration (in ns) of the packets stored per jitterbuffer (0 = unlimited)")
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_MAX_SIZE_TIME.nseconds())
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder::<OverflowPolicy>("overflow-policy")
+                    .nick("Overflow Policy")
+                    .blurb("What to do when the size limits of a jitterbuffer are reached")
+                    .default_value(OverflowPolicy::default())
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -1609,7 +3278,7 @@ impl ObjectImpl for RtpRecv {
                 settings.rtp_id = value.get::<String>().expect("type checked upstream");
             }
             "latency" => {
-                let _latency = {
+                let latency = {
                     let mut settings = self.settings.lock().unwrap();
                     settings.latency = gst::ClockTime::from_mseconds(
                         value.get::<u32>().expect("type checked upstream").into(),
@@ -1617,6 +3286,25 @@ impl ObjectImpl for RtpRecv {
                     settings.latency
                 };
 
+                // Apply to the existing jitterbuffers without flushing them, they are woken
+                // up as packets might be ready already if the latency was lowered
+                let stores = {
+                    let state = self.state.lock().unwrap();
+                    state
+                        .sessions
+                        .iter()
+                        .flat_map(|session| session.rtp_recv_srcpads.iter())
+                        .map(|pad| pad.jitter_buffer_store.clone())
+                        .collect::<Vec<_>>()
+                };
+                for store in stores {
+                    let mut store = store.lock().unwrap();
+                    store.jitterbuffer.set_latency(latency.into());
+                    if let Some(waker) = store.waker.take() {
+                        waker.wake();
+                    }
+                }
+
                 let _ = self
                     .obj()
                     .post_message(gst::message::Latency::builder().src(&*self.obj()).build());
@@ -1627,6 +3315,115 @@ impl ObjectImpl for RtpRecv {
                     .get::<sync::TimestampingMode>()
                     .expect("Type checked upstream");
             }
+            "buffer-mode" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.buffer_mode = value.get::<BufferMode>().expect("Type checked upstream");
+            }
+            "eos-handling" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.eos_handling = value.get::<EosHandling>().expect("Type checked upstream");
+            }
+            "add-reference-timestamp-meta" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.add_reference_timestamp_meta =
+                    value.get::<bool>().expect("Type checked upstream");
+            }
+            "rfc7273-sync" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.rfc7273_sync = value.get::<bool>().expect("Type checked upstream");
+            }
+            "do-lost" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.do_lost = value.get::<bool>().expect("Type checked upstream");
+            }
+            "ignore-pt" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.ignore_pt = value.get::<bool>().expect("Type checked upstream");
+            }
+            "sparse-pts" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.sparse_pts = value
+                    .get::<gst::ArrayRef>()
+                    .expect("Type checked upstream")
+                    .as_slice()
+                    .iter()
+                    .map(|pt| pt.get::<u32>().expect("Type checked upstream") as u8)
+                    .collect();
+            }
+            "auto-remove" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.auto_remove = value.get::<bool>().expect("Type checked upstream");
+            }
+            "max-dropout-time" | "max-misorder-time" => {
+                let mut settings = self.settings.lock().unwrap();
+                let time = value.get::<u32>().expect("Type checked upstream");
+                if pspec.name() == "max-dropout-time" {
+                    settings.max_dropout_time = time;
+                } else {
+                    settings.max_misorder_time = time;
+                }
+                let settings = settings.clone();
+
+                let state = self.state.lock().unwrap();
+                for session in state.sessions.iter() {
+                    settings.configure_session(
+                        &mut session.internal_session.inner.lock().unwrap().session,
+                    );
+                }
+            }
+            "probation" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.probation = value.get::<u32>().expect("Type checked upstream");
+                let settings = settings.clone();
+
+                let state = self.state.lock().unwrap();
+                for session in state.sessions.iter() {
+                    settings.configure_session(
+                        &mut session.internal_session.inner.lock().unwrap().session,
+                    );
+                }
+            }
+            "stats-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.stats_interval = value.get::<u32>().expect("Type checked upstream");
+            }
+            "sync-group" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.sync_group = value
+                    .get::<Option<String>>()
+                    .expect("Type checked upstream")
+                    .filter(|sync_group| !sync_group.is_empty());
+            }
+            "post-drop-messages" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.post_drop_messages = value.get::<bool>().expect("Type checked upstream");
+            }
+            "drop-messages-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.drop_messages_interval =
+                    value.get::<u32>().expect("Type checked upstream");
+            }
+            "max-size-packets" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_size_packets = value.get::<u32>().expect("Type checked upstream");
+            }
+            "max-size-bytes" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_size_bytes = value.get::<u32>().expect("Type checked upstream");
+            }
+            "max-size-time" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_size_time = value
+                    .get::<u64>()
+                    .expect("Type checked upstream")
+                    .nseconds();
+            }
+            "overflow-policy" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.overflow_policy = value
+                    .get::<OverflowPolicy>()
+                    .expect("Type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -1642,33 +3439,182 @@ impl ObjectImpl for RtpRecv {
                 (settings.latency.mseconds() as u32).to_value()
             }
             "stats" => {
-                let state = self.state.lock().unwrap();
-                state.stats().to_value()
+                let snapshot = self.state.lock().unwrap().stats_snapshot();
+                snapshot.stats().to_value()
             }
             "timestamping-mode" => {
                 let settings = self.settings.lock().unwrap();
                 settings.timestamping_mode.to_value()
             }
+            "buffer-mode" => {
+                let settings = self.settings.lock().unwrap();
+                settings.buffer_mode.to_value()
+            }
+            "eos-handling" => {
+                let settings = self.settings.lock().unwrap();
+                settings.eos_handling.to_value()
+            }
+            "add-reference-timestamp-meta" => {
+                let settings = self.settings.lock().unwrap();
+                settings.add_reference_timestamp_meta.to_value()
+            }
+            "rfc7273-sync" => {
+                let settings = self.settings.lock().unwrap();
+                settings.rfc7273_sync.to_value()
+            }
+            "do-lost" => {
+                let settings = self.settings.lock().unwrap();
+                settings.do_lost.to_value()
+            }
+            "ignore-pt" => {
+                let settings = self.settings.lock().unwrap();
+                settings.ignore_pt.to_value()
+            }
+            "sparse-pts" => {
+                let settings = self.settings.lock().unwrap();
+                gst::Array::new(settings.sparse_pts.iter().map(|pt| *pt as u32)).to_value()
+            }
+            "auto-remove" => {
+                let settings = self.settings.lock().unwrap();
+                settings.auto_remove.to_value()
+            }
+            "max-dropout-time" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_dropout_time.to_value()
+            }
+            "max-misorder-time" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_misorder_time.to_value()
+            }
+            "probation" => {
+                let settings = self.settings.lock().unwrap();
+                settings.probation.to_value()
+            }
+            "stats-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.stats_interval.to_value()
+            }
+            "sync-group" => {
+                let settings = self.settings.lock().unwrap();
+                settings.sync_group.to_value()
+            }
+            "post-drop-messages" => {
+                let settings = self.settings.lock().unwrap();
+                settings.post_drop_messages.to_value()
+            }
+            "drop-messages-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.drop_messages_interval.to_value()
+            }
+            "max-size-packets" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_size_packets.to_value()
+            }
+            "max-size-bytes" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_size_bytes.to_value()
+            }
+            "max-size-time" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_size_time.nseconds().to_value()
+            }
+            "overflow-policy" => {
+                let settings = self.settings.lock().unwrap();
+                settings.overflow_policy.to_value()
+            }
             _ => unimplemented!(),
         }
     }
 
     fn signals() -> &'static [glib::subclass::Signal] {
         static SIGNALS: LazyLock<Vec<glib::subclass::Signal>> = LazyLock::new(|| {
-            vec![glib::subclass::Signal::builder("get-session")
-                .param_types([u32::static_type()])
-                .return_type::<crate::rtpbin2::config::Rtp2Session>()
-                .action()
-                .class_handler(|_token, args| {
-                    let element = args[0].get::<super::RtpRecv>().expect("signal arg");
-                    let id = args[1].get::<u32>().expect("signal arg");
-                    let bin = element.imp();
-                    let state = bin.state.lock().unwrap();
-                    state
-                        .session_by_id(id as usize)
-                        .map(|sess| sess.internal_session.config.to_value())
-                })
-                .build()]
+            vec![
+                glib::subclass::Signal::builder("get-session")
+                    .param_types([u32::static_type()])
+                    .return_type::<crate::rtpbin2::config::Rtp2Session>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpRecv>().expect("signal arg");
+                        let id = args[1].get::<u32>().expect("signal arg");
+                        let bin = element.imp();
+                        let state = bin.state.lock().unwrap();
+                        state
+                            .session_by_id(id as usize)
+                            .map(|sess| sess.internal_session.config.to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("create-session")
+                    .return_type::<u32>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpRecv>().expect("signal arg");
+                        Some(element.imp().create_session().to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("destroy-session")
+                    .param_types([u32::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpRecv>().expect("signal arg");
+                        let id = args[1].get::<u32>().expect("signal arg");
+                        Some(element.imp().destroy_session(id as usize).to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("request-session-pad")
+                    .param_types([u32::static_type(), String::static_type()])
+                    .return_type::<gst::Pad>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpRecv>().expect("signal arg");
+                        let id = args[1].get::<u32>().expect("signal arg");
+                        let kind = args[2].get::<&str>().expect("signal arg");
+                        Some(
+                            element
+                                .imp()
+                                .request_session_pad(id as usize, kind)
+                                .to_value(),
+                        )
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("get-source-stats")
+                    .param_types([u32::static_type(), u32::static_type()])
+                    .return_type::<gst::Structure>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpRecv>().expect("signal arg");
+                        let id = args[1].get::<u32>().expect("signal arg");
+                        let ssrc = args[2].get::<u32>().expect("signal arg");
+                        Some(element.imp().source_stats(id as usize, ssrc).to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("get-state-snapshot")
+                    .return_type::<gst::Structure>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpRecv>().expect("signal arg");
+                        Some(element.imp().state_snapshot().to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("restore-state-snapshot")
+                    .param_types([gst::Structure::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpRecv>().expect("signal arg");
+                        let snapshot = args[1].get::<gst::Structure>().expect("signal arg");
+                        Some(element.imp().restore_state_snapshot(&snapshot).to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("get-sync-offsets")
+                    .return_type::<gst::Structure>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpRecv>().expect("signal arg");
+                        Some(element.imp().sync_offsets().to_value())
+                    })
+                    .build(),
+            ]
         });
 
         SIGNALS.as_ref()
@@ -1677,6 +3623,29 @@ impl ObjectImpl for RtpRecv {
 
 impl GstObjectImpl for RtpRecv {}
 
+impl ChildProxyImpl for RtpRecv {
+    fn children_count(&self) -> u32 {
+        let state = self.state.lock().unwrap();
+        state.sessions.len() as u32
+    }
+
+    fn child_by_name(&self, name: &str) -> Option<glib::Object> {
+        let id = name.strip_prefix("session_")?.parse::<usize>().ok()?;
+        let state = self.state.lock().unwrap();
+        state
+            .session_by_id(id)
+            .map(|sess| sess.internal_session.config.clone().upcast())
+    }
+
+    fn child_by_index(&self, index: u32) -> Option<glib::Object> {
+        let state = self.state.lock().unwrap();
+        state
+            .sessions
+            .get(index as usize)
+            .map(|sess| sess.internal_session.config.clone().upcast())
+    }
+}
+
 impl ElementImpl for RtpRecv {
     fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
         static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
@@ -1722,6 +3691,13 @@ impl ElementImpl for RtpRecv {
                     &rtp_caps,
                 )
                 .unwrap(),
+                gst::PadTemplate::new(
+                    "rtp_mid_src_%u_%s",
+                    gst::PadDirection::Src,
+                    gst::PadPresence::Sometimes,
+                    &rtp_caps,
+                )
+                .unwrap(),
             ]
         });
 
@@ -1738,6 +3714,7 @@ impl ElementImpl for RtpRecv {
         let rtp_id = settings.rtp_id.clone();
         let mut state = self.state.lock().unwrap();
         let max_session_id = state.max_session_id;
+        let mut new_session = None;
 
         // parse the possibly provided name into a session id or use the default
         let sess_parse = move |name: Option<&str>, prefix, default_id| -> Option<usize> {
@@ -1815,8 +3792,9 @@ impl ElementImpl for RtpRecv {
                     let shared_state = state
                         .shared_state
                         .get_or_insert_with(|| SharedRtpState::recv_get_or_init(rtp_id));
-                    let mut session = RecvSession::new(shared_state, id);
+                    let mut session = RecvSession::new(self, shared_state, id, &settings);
                     let ret = new_pad(&mut session);
+                    new_session = Some(session.internal_session.config.clone());
                     state.sessions.push(session);
                     ret
                 }
@@ -1860,8 +3838,9 @@ impl ElementImpl for RtpRecv {
                     let shared_state = state
                         .shared_state
                         .get_or_insert_with(|| SharedRtpState::recv_get_or_init(rtp_id));
-                    let mut session = RecvSession::new(shared_state, id);
+                    let mut session = RecvSession::new(self, shared_state, id, &settings);
                     let ret = new_pad(&mut session);
+                    new_session = Some(session.internal_session.config.clone());
                     state.sessions.push(session);
                     ret
                 }
@@ -1888,6 +3867,10 @@ impl ElementImpl for RtpRecv {
                 self.obj().add_pad(&pad).unwrap();
             }
 
+            if let Some(config) = new_session {
+                self.obj().child_added(&config, &format!("session_{id}"));
+            }
+
             pad
         })
     }
@@ -1912,7 +3895,10 @@ impl ElementImpl for RtpRecv {
                     session.rtcp_recv_sinkpad = None;
                 }
 
-                if session.rtp_recv_sinkpad.is_none() && session.rtcp_recv_sinkpad.is_none() {
+                if !session.created
+                    && session.rtp_recv_sinkpad.is_none()
+                    && session.rtcp_recv_sinkpad.is_none()
+                {
                     removed_session_ids.push(session.internal_session.id);
                 }
             }
@@ -1931,6 +3917,7 @@ impl ElementImpl for RtpRecv {
             }
         }
 
+        let mut removed_sessions = vec![];
         {
             let mut state = self.state.lock().unwrap();
             for id in removed_srcpads_session_ids {
@@ -1940,14 +3927,22 @@ impl ElementImpl for RtpRecv {
             }
             for id in removed_session_ids {
                 if let Some(session) = state.mut_session_by_id(id) {
-                    if session.rtp_recv_sinkpad.is_none() && session.rtcp_recv_sinkpad.is_none() {
+                    if !session.created
+                        && session.rtp_recv_sinkpad.is_none()
+                        && session.rtcp_recv_sinkpad.is_none()
+                    {
                         let id = session.internal_session.id;
+                        removed_sessions.push((id, session.internal_session.config.clone()));
                         state.sessions.retain(|s| s.internal_session.id != id);
                     }
                 }
             }
         }
 
+        for (id, config) in removed_sessions {
+            self.obj().child_removed(&config, &format!("session_{id}"));
+        }
+
         self.parent_release_pad(pad)
     }
 
@@ -1980,7 +3975,21 @@ impl ElementImpl for RtpRecv {
                 let settings = self.settings.lock().unwrap();
                 let mut sync_context = self.sync_context.lock().unwrap();
 
-                *sync_context = Some(sync::Context::new(settings.timestamping_mode));
+                let sync_state = sync_context.insert(SyncState {
+                    context: sync::context_get_or_init(
+                        settings.sync_group.as_deref(),
+                        settings
+                            .buffer_mode
+                            .timestamping_mode(settings.timestamping_mode),
+                    ),
+                    ssrcs: HashSet::new(),
+                });
+
+                if let Some(ref mut snapshot) = *self.restored_snapshot.lock().unwrap() {
+                    for ssrc in std::mem::take(&mut snapshot.ssrcs) {
+                        sync_state.context(ssrc.ssrc).restore(&ssrc);
+                    }
+                }
             }
             _ => (),
         }
@@ -1990,8 +3999,13 @@ impl ElementImpl for RtpRecv {
         match transition {
             gst::StateChange::ReadyToPaused | gst::StateChange::PlayingToPaused => {
                 success = gst::StateChangeSuccess::NoPreroll;
+                if transition == gst::StateChange::ReadyToPaused {
+                    self.start_stats_task();
+                }
             }
             gst::StateChange::PausedToReady => {
+                self.stats_task.lock().unwrap().take();
+
                 let mut state = self.state.lock().unwrap();
                 let mut removed_pads = vec![];
                 for session in &mut state.sessions {
@@ -2004,7 +4018,9 @@ impl ElementImpl for RtpRecv {
                     session.rtp_recv_sink_caps = None;
                     session.rtp_recv_sink_segment = None;
                     session.rtp_recv_sink_seqnum = None;
+                    session.running_time_offset = None;
                     session.rtp_recv_sink_group_id = None;
+                    session.pending_eos = None;
                 }
                 let mut sync_context = self.sync_context.lock().unwrap();
                 *sync_context = None;
@@ -2039,3 +4055,95 @@ impl Drop for RtpRecv {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(ssrc: u32) -> u32 {
+        match ssrc {
+            0x11111111 => 0xaaaaaaaa,
+            0x22222222 => 0xbbbbbbbb,
+            other => other,
+        }
+    }
+
+    #[test]
+    fn rewrite_rtcp_ssrcs_compound() {
+        let mut data = vec![0; 256];
+        let len = rtcp_types::Compound::builder()
+            .add_packet(
+                rtcp_types::SenderReport::builder(0x11111111)
+                    .add_report_block(rtcp_types::ReportBlock::builder(0x33333333)),
+            )
+            .add_packet(
+                rtcp_types::Sdes::builder()
+                    .add_chunk(rtcp_types::SdesChunk::builder(0x11111111).add_item(
+                        rtcp_types::SdesItem::builder(rtcp_types::SdesItem::CNAME, "a"),
+                    ))
+                    .add_chunk(rtcp_types::SdesChunk::builder(0x22222222).add_item(
+                        rtcp_types::SdesItem::builder(rtcp_types::SdesItem::CNAME, "longer"),
+                    )),
+            )
+            .add_packet(
+                rtcp_types::Bye::builder()
+                    .add_source(0x22222222)
+                    .add_source(0x44444444),
+            )
+            .write_into(&mut data)
+            .unwrap();
+        data.truncate(len);
+
+        rewrite_rtcp_ssrcs(&mut data, rewrite);
+
+        let compound = rtcp_types::Compound::parse(&data).unwrap();
+        let packets = compound.map(Result::unwrap).collect::<Vec<_>>();
+        assert_eq!(packets.len(), 3);
+
+        let rtcp_types::Packet::Sr(sr) = &packets[0] else {
+            panic!("not a sender report");
+        };
+        assert_eq!(sr.ssrc(), 0xaaaaaaaa);
+        // Report blocks refer to sources of the receiver and are kept
+        assert_eq!(sr.report_blocks().next().unwrap().ssrc(), 0x33333333);
+
+        let rtcp_types::Packet::Sdes(sdes) = &packets[1] else {
+            panic!("not a SDES packet");
+        };
+        let chunks = sdes
+            .chunks()
+            .map(|chunk| {
+                let cname = chunk.items().next().unwrap().value().to_vec();
+                (chunk.ssrc(), cname)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chunks,
+            [
+                (0xaaaaaaaa, b"a".to_vec()),
+                (0xbbbbbbbb, b"longer".to_vec())
+            ]
+        );
+
+        let rtcp_types::Packet::Bye(bye) = &packets[2] else {
+            panic!("not a BYE packet");
+        };
+        assert_eq!(bye.ssrcs().collect::<Vec<_>>(), [0xbbbbbbbb, 0x44444444]);
+    }
+
+    #[test]
+    fn rewrite_rtcp_ssrcs_truncated() {
+        let mut data = vec![0; 128];
+        let len = rtcp_types::Compound::builder()
+            .add_packet(rtcp_types::ReceiverReport::builder(0x11111111))
+            .add_packet(rtcp_types::Bye::builder().add_source(0x22222222))
+            .write_into(&mut data)
+            .unwrap();
+
+        // The incomplete BYE packet is left alone
+        let mut truncated = data[..len - 2].to_vec();
+        rewrite_rtcp_ssrcs(&mut truncated, rewrite);
+        assert_eq!(&truncated[4..8], &0xaaaaaaaau32.to_be_bytes());
+        assert_eq!(&truncated[8..], &data[8..len - 2]);
+    }
+}
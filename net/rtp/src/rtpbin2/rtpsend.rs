@@ -11,14 +11,26 @@ use futures::StreamExt;
 use gst::{glib, prelude::*, subclass::prelude::*};
 use std::sync::LazyLock;
 
-use super::internal::{pt_clock_rate_from_caps, GstRustLogger, SharedRtpState, SharedSession};
+use super::config::sdes_from_structure;
+use super::dtmf::DtmfInjector;
+use super::internal::{
+    pt_clock_rate_from_caps, GstRustLogger, SharedRtpState, SharedSession, StatsTask,
+};
+use super::pacing::PacingStats;
 use super::session::{RtcpSendReply, RtpProfile, SendReply, RTCP_MIN_REPORT_INTERVAL};
 use super::source::SourceState;
 
+use crate::dtmf::event::DtmfEvent;
 use crate::rtpbin2::RUNTIME;
 
 const DEFAULT_MIN_RTCP_INTERVAL: Duration = RTCP_MIN_REPORT_INTERVAL;
 const DEFAULT_REDUCED_SIZE_RTCP: bool = false;
+const DEFAULT_STATS_INTERVAL: u32 = 0;
+const DEFAULT_KEEPALIVE_INTERVAL: u32 = 0;
+const DEFAULT_KEEPALIVE_METHOD: KeepaliveMethod = KeepaliveMethod::Padding;
+
+const RTCP_APP_PACKET_TYPE: u8 = 204;
+const KEEPALIVE_APP_NAME: &[u8; 4] = b"KEEP";
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -57,12 +69,34 @@ impl From<Profile> for RtpProfile {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstRtpSendKeepaliveMethod")]
+pub enum KeepaliveMethod {
+    #[enum_value(
+        name = "RTCP APP packet without data, multiplexed with RTP as in RFC 5761",
+        nick = "rtcp-app"
+    )]
+    RtcpApp,
+    #[enum_value(name = "Resend the last RTP packet", nick = "last-packet")]
+    LastPacket,
+    #[enum_value(
+        name = "Padding-only RTP packet with the sequence number of the last RTP packet",
+        nick = "padding"
+    )]
+    Padding,
+}
+
 #[derive(Debug, Clone)]
 struct Settings {
     rtp_id: String,
     min_rtcp_interval: Duration,
     profile: Profile,
     reduced_size_rtcp: bool,
+    sdes: Option<gst::Structure>,
+    stats_interval: u32,
+    keepalive_interval: u32,
+    keepalive_method: KeepaliveMethod,
 }
 
 impl Default for Settings {
@@ -72,6 +106,10 @@ impl Default for Settings {
             min_rtcp_interval: DEFAULT_MIN_RTCP_INTERVAL,
             profile: Profile::default(),
             reduced_size_rtcp: DEFAULT_REDUCED_SIZE_RTCP,
+            sdes: None,
+            stats_interval: DEFAULT_STATS_INTERVAL,
+            keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+            keepalive_method: DEFAULT_KEEPALIVE_METHOD,
         }
     }
 }
@@ -134,10 +172,53 @@ impl futures::stream::Stream for RtcpSendStream {
     }
 }
 
+/// The last RTP packet pushed downstream, used for generating keepalive packets
+#[derive(Debug)]
+struct LastPacket {
+    buffer: gst::Buffer,
+    /// When the last RTP or keepalive packet was pushed
+    sent: Instant,
+}
+
+/// Create a keepalive packet for the stream of the last RTP packet
+fn keepalive_packet(method: KeepaliveMethod, last: &gst::Buffer) -> Option<gst::Buffer> {
+    if method == KeepaliveMethod::LastPacket {
+        return Some(last.clone());
+    }
+
+    let mapped = last.map_readable().ok()?;
+    let rtp = rtp_types::RtpPacket::parse(&mapped).ok()?;
+
+    let mut data = Vec::with_capacity(16);
+    match method {
+        KeepaliveMethod::RtcpApp => {
+            data.extend_from_slice(&[0x80, RTCP_APP_PACKET_TYPE, 0x00, 0x02]);
+            data.extend_from_slice(&rtp.ssrc().to_be_bytes());
+            data.extend_from_slice(KEEPALIVE_APP_NAME);
+        }
+        KeepaliveMethod::Padding => {
+            // Reusing the sequence number of the last packet makes receivers discard it as a
+            // duplicate instead of seeing a gap in the sequence numbers of the stream
+            data.extend_from_slice(&[0xa0, rtp.payload_type()]);
+            data.extend_from_slice(&rtp.sequence_number().to_be_bytes());
+            data.extend_from_slice(&rtp.timestamp().to_be_bytes());
+            data.extend_from_slice(&rtp.ssrc().to_be_bytes());
+            data.extend_from_slice(&[0x00, 0x00, 0x00, 0x04]);
+        }
+        KeepaliveMethod::LastPacket => unreachable!(),
+    }
+
+    Some(gst::Buffer::from_mut_slice(data))
+}
+
 #[derive(Debug)]
 struct SendSession {
     internal_session: SharedSession,
 
+    last_packet: Arc<Mutex<Option<LastPacket>>>,
+    pacing_stats: Arc<Mutex<PacingStats>>,
+    dtmf: Arc<Mutex<DtmfInjector>>,
+
     rtcp_task: Mutex<Option<RtcpTask>>,
 
     // State for sending RTP streams
@@ -145,6 +226,10 @@ struct SendSession {
     rtp_send_srcpad: Option<gst::Pad>,
 
     rtcp_send_srcpad: Option<gst::Pad>,
+
+    // Created with the create-session signal, kept until destroy-session
+    // even if all its pads are released
+    created: bool,
 }
 
 impl SendSession {
@@ -165,15 +250,22 @@ impl SendSession {
         inner
             .session
             .set_reduced_size_rtcp(settings.reduced_size_rtcp);
+        if let Some(ref sdes) = settings.sdes {
+            inner.session.set_sdes(sdes_from_structure(sdes));
+        }
         drop(inner);
 
         Self {
             internal_session,
 
+            last_packet: Arc::new(Mutex::new(None)),
+            pacing_stats: Arc::new(Mutex::new(PacingStats::default())),
+            dtmf: Arc::new(Mutex::new(DtmfInjector::default())),
             rtcp_task: Mutex::new(None),
             rtp_send_sinkpad: None,
             rtp_send_srcpad: None,
             rtcp_send_srcpad: None,
+            created: false,
         }
     }
 
@@ -210,7 +302,17 @@ impl SendSession {
                 };
                 match reply {
                     RtcpSendReply::Data(data) => {
-                        session.rtcp_send_srcpad.clone().map(|pad| (pad, data))
+                        let feedback_target = session
+                            .internal_session
+                            .inner
+                            .lock()
+                            .unwrap()
+                            .session
+                            .feedback_target();
+                        session
+                            .rtcp_send_srcpad
+                            .clone()
+                            .map(|pad| (pad, data, feedback_target))
                     }
                     RtcpSendReply::SsrcBye(ssrc) => {
                         session
@@ -222,10 +324,17 @@ impl SendSession {
                 }
             };
 
-            if let Some((rtcp_srcpad, data)) = send {
+            if let Some((rtcp_srcpad, data, feedback_target)) = send {
                 let acquired = sem.clone().acquire_owned().await;
                 RUNTIME.spawn_blocking(move || {
-                    let buffer = gst::Buffer::from_mut_slice(data);
+                    let mut buffer = gst::Buffer::from_mut_slice(data);
+                    if let Some(addr) = feedback_target {
+                        // Sent to the unicast feedback target by e.g. dynudpsink
+                        gst_net::NetAddressMeta::add(
+                            buffer.get_mut().unwrap(),
+                            &gio::InetSocketAddress::from(addr),
+                        );
+                    }
                     if let Err(e) = rtcp_srcpad.push(buffer) {
                         gst::warning!(
                             CAT,
@@ -273,9 +382,12 @@ impl State {
         let mut ret = gst::Structure::builder("application/x-rtp2-stats");
         for session in self.sessions.iter() {
             let sess_id = session.internal_session.id;
-            let session = session.internal_session.inner.lock().unwrap();
+            let session_inner = session.internal_session.inner.lock().unwrap();
 
-            ret = ret.field(sess_id.to_string(), session.stats());
+            let mut session_stats = session_inner.stats();
+            drop(session_inner);
+            session_stats.set("pacing-stats", session.pacing_stats.lock().unwrap().stats());
+            ret = ret.field(sess_id.to_string(), session_stats);
         }
         ret.build()
     }
@@ -284,6 +396,8 @@ impl State {
 pub struct RtpSend {
     settings: Mutex<Settings>,
     state: Arc<Mutex<State>>,
+    stats_task: Mutex<Option<StatsTask>>,
+    keepalive_task: Mutex<Option<AbortHandle>>,
 }
 
 #[derive(Debug)]
@@ -292,6 +406,210 @@ struct RtcpTask {
 }
 
 impl RtpSend {
+    fn start_stats_task(&self) {
+        let stats_interval = self.settings.lock().unwrap().stats_interval;
+        if stats_interval == 0 {
+            return;
+        }
+
+        let state = self.state.clone();
+        let task = StatsTask::start(
+            self.obj().upcast_ref(),
+            Duration::from_millis(stats_interval.into()),
+            move || state.lock().unwrap().stats(),
+        );
+        self.stats_task.lock().unwrap().replace(task);
+    }
+
+    fn start_keepalive_task(&self) {
+        let settings = self.settings.lock().unwrap();
+        if settings.keepalive_interval == 0 {
+            return;
+        }
+        let interval = Duration::from_millis(settings.keepalive_interval.into());
+        let method = settings.keepalive_method;
+        drop(settings);
+
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Sending {method:?} keepalive packets after {interval:?} without RTP packets"
+        );
+
+        let (abort_handle, abort_registration) = AbortHandle::new_pair();
+        let state = self.state.clone();
+        RUNTIME.spawn(Abortable::new(
+            Self::keepalive_task(state, interval, method),
+            abort_registration,
+        ));
+        self.keepalive_task.lock().unwrap().replace(abort_handle);
+    }
+
+    fn stop_keepalive_task(&self) {
+        if let Some(abort_handle) = self.keepalive_task.lock().unwrap().take() {
+            abort_handle.abort();
+        }
+    }
+
+    async fn keepalive_task(state: Arc<Mutex<State>>, interval: Duration, method: KeepaliveMethod) {
+        loop {
+            let now = Instant::now();
+            let mut next_wakeup = now + interval;
+            let mut sends = vec![];
+
+            {
+                let state = state.lock().unwrap();
+                for session in &state.sessions {
+                    let (Some(ref sinkpad), Some(ref srcpad)) =
+                        (&session.rtp_send_sinkpad, &session.rtp_send_srcpad)
+                    else {
+                        continue;
+                    };
+                    let last_packet = session.last_packet.lock().unwrap();
+                    // Nothing to keep alive before the first RTP packet or after EOS
+                    let Some(ref last_packet) = *last_packet else {
+                        continue;
+                    };
+
+                    let deadline = last_packet.sent + interval;
+                    if deadline > now {
+                        next_wakeup = next_wakeup.min(deadline);
+                        continue;
+                    }
+
+                    sends.push((sinkpad.clone(), srcpad.clone(), session.last_packet.clone()));
+                }
+            }
+
+            let waits = sends
+                .into_iter()
+                .map(|(sinkpad, srcpad, last_packet)| {
+                    RUNTIME.spawn_blocking(move || {
+                        Self::send_keepalive(&sinkpad, &srcpad, &last_packet, interval, method)
+                    })
+                })
+                .collect::<Vec<_>>();
+            // Don't look at the sessions again before the keepalives are sent
+            for wait in waits {
+                let _ = wait.await;
+            }
+
+            tokio::time::sleep_until(next_wakeup.into()).await;
+        }
+    }
+
+    /// Pushes a keepalive packet under the stream lock of the RTP sink pad, so that it is
+    /// serialized with the RTP packets and can't be sent once the stream has ended
+    fn send_keepalive(
+        sinkpad: &gst::Pad,
+        srcpad: &gst::Pad,
+        last_packet: &Mutex<Option<LastPacket>>,
+        interval: Duration,
+        method: KeepaliveMethod,
+    ) {
+        let _stream_lock = sinkpad.stream_lock();
+
+        let buffer = {
+            let now = Instant::now();
+            let mut last_packet = last_packet.lock().unwrap();
+            // RTP packets may have been sent, or the stream ended, while waiting
+            let Some(ref mut last_packet) = *last_packet else {
+                return;
+            };
+            if last_packet.sent + interval > now {
+                return;
+            }
+            last_packet.sent = now;
+
+            let Some(buffer) = keepalive_packet(method, &last_packet.buffer) else {
+                return;
+            };
+            buffer
+        };
+
+        gst::trace!(CAT, obj = srcpad, "Sending keepalive packet");
+        if let Err(e) = srcpad.push(buffer) {
+            gst::warning!(
+                CAT,
+                obj = srcpad,
+                "Failed to send keepalive packet: flow return {e:?}"
+            );
+        }
+    }
+
+    fn create_session(&self) -> u32 {
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+        let id = state.max_session_id;
+
+        let shared_state = state
+            .shared_state
+            .get_or_insert_with(|| SharedRtpState::send_get_or_init(settings.rtp_id.clone()));
+        let mut session = SendSession::new(shared_state, id, &settings);
+        session.created = true;
+        let config = session.internal_session.config.clone();
+        state.sessions.push(session);
+        state.max_session_id = id + 1;
+        drop(state);
+
+        gst::debug!(CAT, imp = self, "Created session {id}");
+        self.obj().child_added(&config, &format!("session_{id}"));
+
+        id as u32
+    }
+
+    fn destroy_session(&self, id: usize) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let Some(session) = state.mut_session_by_id(id) else {
+            gst::warning!(CAT, imp = self, "Can't destroy unknown session {id}");
+            return false;
+        };
+        session.created = false;
+        let pads = [
+            session.rtp_send_sinkpad.clone(),
+            session.rtcp_send_srcpad.clone(),
+        ];
+        drop(state);
+
+        gst::debug!(CAT, imp = self, "Destroying session {id}");
+
+        // Releasing the last pad of the session also removes the session
+        for pad in pads.into_iter().flatten() {
+            self.obj().release_request_pad(&pad);
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(session) = state.session_by_id(id) {
+            session.stop_rtcp_task();
+            let config = session.internal_session.config.clone();
+            state.sessions.retain(|s| s.internal_session.id != id);
+            drop(state);
+
+            self.obj().child_removed(&config, &format!("session_{id}"));
+        }
+
+        true
+    }
+
+    fn request_session_pad(&self, id: usize, kind: &str) -> Option<gst::Pad> {
+        if self.state.lock().unwrap().session_by_id(id).is_none() {
+            gst::warning!(
+                CAT,
+                imp = self,
+                "Can't request pad for unknown session {id}"
+            );
+            return None;
+        }
+
+        let obj = self.obj();
+        let templ = obj.pad_template(&format!("{kind}_%u"))?;
+        if templ.presence() != gst::PadPresence::Request {
+            return None;
+        }
+
+        obj.request_pad(&templ, Some(&format!("{kind}_{id}")), None)
+    }
+
     fn iterate_internal_links(&self, pad: &gst::Pad) -> gst::Iterator<gst::Pad> {
         let state = self.state.lock().unwrap();
         if let Some(&id) = state.pads_session_id_map.get(pad) {
@@ -313,72 +631,218 @@ impl RtpSend {
 
     fn handle_buffer(
         &self,
-        sinkpad: &gst::Pad,
         srcpad: &gst::Pad,
         internal_session: &SharedSession,
-        buffer: gst::Buffer,
+        last_packet: Option<&Mutex<Option<LastPacket>>>,
+        pacing_stats: &Mutex<PacingStats>,
+        mut buffer: gst::Buffer,
         now: Instant,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
-        let mapped = buffer.map_readable().map_err(|e| {
-            gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
-            gst::FlowError::Error
-        })?;
-        let rtp = match rtp_types::RtpPacket::parse(&mapped) {
-            Ok(rtp) => rtp,
-            Err(e) => {
-                gst::error!(
-                    CAT,
-                    imp = self,
-                    "Failed to parse input as valid rtp packet: {e:?}"
-                );
-                return Ok(gst::FlowSuccess::Ok);
-            }
+        let mut session_inner = internal_session.inner.lock().unwrap();
+
+        let ssrc = {
+            let mapped = buffer.map_readable().map_err(|e| {
+                gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                gst::FlowError::Error
+            })?;
+            mapped
+                .get(8..12)
+                .map(|ssrc| u32::from_be_bytes(ssrc.try_into().unwrap()))
         };
 
-        let mut session_inner = internal_session.inner.lock().unwrap();
+        // A packet colliding with another participant is sent with the new SSRC instead
+        'send: loop {
+            // Rewrite the SSRC if it had to be changed because of a collision
+            if let Some(ssrc) = ssrc {
+                let send_ssrc = session_inner.session.send_ssrc(ssrc);
+                if send_ssrc != ssrc {
+                    gst::trace!(
+                        CAT,
+                        imp = self,
+                        "Rewriting ssrc {ssrc:#08x} to {send_ssrc:#08x}"
+                    );
+                    let mut mapped = buffer.make_mut().map_writable().map_err(|e| {
+                        gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                        gst::FlowError::Error
+                    })?;
+                    mapped[8..12].copy_from_slice(&send_ssrc.to_be_bytes());
+                }
+            }
 
-        let mut ssrc_collision: smallvec::SmallVec<[u32; 4]> = smallvec::SmallVec::new();
-        loop {
-            match session_inner.session.handle_send(&rtp, now) {
-                SendReply::SsrcCollision(ssrc) => {
-                    if !ssrc_collision.iter().any(|&needle| needle == ssrc) {
-                        ssrc_collision.push(ssrc);
-                    }
+            let mapped = buffer.map_readable().map_err(|e| {
+                gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                gst::FlowError::Error
+            })?;
+            let rtp = match rtp_types::RtpPacket::parse(&mapped) {
+                Ok(rtp) => rtp,
+                Err(e) => {
+                    gst::error!(
+                        CAT,
+                        imp = self,
+                        "Failed to parse input as valid rtp packet: {e:?}"
+                    );
+                    return Ok(gst::FlowSuccess::Ok);
                 }
-                SendReply::NewSsrc(ssrc, _pt) => {
-                    drop(session_inner);
-                    internal_session
-                        .config
-                        .emit_by_name::<()>("new-ssrc", &[&ssrc]);
-                    session_inner = internal_session.inner.lock().unwrap();
+            };
+
+            loop {
+                match session_inner.session.handle_send(&rtp, now) {
+                    SendReply::SsrcCollision(ssrc) => {
+                        drop(session_inner);
+                        let new_ssrc = internal_session.handle_ssrc_collision(ssrc);
+                        session_inner = internal_session.inner.lock().unwrap();
+                        if new_ssrc.is_none() {
+                            return Ok(gst::FlowSuccess::Ok);
+                        }
+                        continue 'send;
+                    }
+                    SendReply::NewSsrc(ssrc, _pt) => {
+                        drop(session_inner);
+                        internal_session.notify_new_ssrc(self.obj().upcast_ref(), ssrc, true);
+                        session_inner = internal_session.inner.lock().unwrap();
+                    }
+                    SendReply::Passthrough => break 'send,
+                    SendReply::Drop => return Ok(gst::FlowSuccess::Ok),
                 }
-                SendReply::Passthrough => break,
-                SendReply::Drop => return Ok(gst::FlowSuccess::Ok),
             }
         }
         // TODO: handle other processing
-        drop(mapped);
+        let buffer = session_inner.write_header_extensions(buffer, now)?;
         drop(session_inner);
 
-        for ssrc in ssrc_collision {
-            // XXX: Another option is to have us rewrite ssrc's instead of asking upstream to do
-            // so.
-            sinkpad.send_event(
-                gst::event::CustomUpstream::builder(
-                    gst::Structure::builder("GstRTPCollision")
-                        .field("ssrc", ssrc)
-                        .build(),
-                )
-                .build(),
-            );
+        pacing_stats.lock().unwrap().packet_sent(now);
+
+        if let Some(last_packet) = last_packet {
+            *last_packet.lock().unwrap() = Some(LastPacket {
+                buffer: buffer.clone(),
+                sent: now,
+            });
         }
 
         srcpad.push(buffer)
     }
 
+    /// Returns the telephone-event packets to send before `buffer` and rewrites its sequence
+    /// number to make room for them
+    fn inject_dtmf(
+        &self,
+        dtmf: &Mutex<DtmfInjector>,
+        buffer: &mut gst::Buffer,
+    ) -> Result<Vec<gst::Buffer>, gst::FlowError> {
+        let mut dtmf = dtmf.lock().unwrap();
+        if dtmf.is_idle() {
+            return Ok(vec![]);
+        }
+
+        let (ssrc, seqnum, timestamp) = {
+            let mapped = buffer.map_readable().map_err(|e| {
+                gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                gst::FlowError::Error
+            })?;
+            match rtp_types::RtpPacket::parse(&mapped) {
+                Ok(rtp) => (rtp.ssrc(), rtp.sequence_number(), rtp.timestamp()),
+                // Invalid packets are dropped later
+                Err(_) => return Ok(vec![]),
+            }
+        };
+
+        let (new_seqnum, packets) = dtmf.process(ssrc, seqnum, timestamp);
+        drop(dtmf);
+
+        if new_seqnum != seqnum {
+            gst::trace!(
+                CAT,
+                imp = self,
+                "Rewriting seqnum {seqnum} to {new_seqnum} for ssrc {ssrc:#08x}"
+            );
+            let mut mapped = buffer.make_mut().map_writable().map_err(|e| {
+                gst::error!(CAT, imp = self, "Failed to map input buffer {e:?}");
+                gst::FlowError::Error
+            })?;
+            mapped[2..4].copy_from_slice(&new_seqnum.to_be_bytes());
+        }
+
+        let pts = buffer.pts();
+        Ok(packets
+            .into_iter()
+            .map(|packet| {
+                let mut packet = gst::Buffer::from_mut_slice(packet);
+                packet.get_mut().unwrap().set_pts(pts);
+                packet
+            })
+            .collect())
+    }
+
+    fn handle_dtmf_event(&self, id: usize, s: &gst::StructureRef) -> bool {
+        let Some(event) = DtmfEvent::from_structure(s) else {
+            gst::warning!(CAT, imp = self, "Invalid DTMF event {s:?}");
+            return false;
+        };
+
+        let state = self.state.lock().unwrap();
+        let Some(session) = state.session_by_id(id) else {
+            return false;
+        };
+
+        if !event.start {
+            gst::debug!(CAT, imp = self, "Stopping DTMF event in session {id}");
+            return session.dtmf.lock().unwrap().stop();
+        }
+
+        // Without explicit payload type use the telephone-event payload type of the session
+        let pt = s
+            .get::<i32>("pt")
+            .ok()
+            .filter(|pt| (0..=127).contains(pt))
+            .map(|pt| pt as u8)
+            .or_else(|| {
+                let session_inner = session.internal_session.inner.lock().unwrap();
+                session_inner
+                    .pt_map()
+                    .filter(|(_pt, caps)| {
+                        caps.structure(0)
+                            .and_then(|s| s.get::<&str>("encoding-name").ok())
+                            .is_some_and(|name| name.eq_ignore_ascii_case("TELEPHONE-EVENT"))
+                    })
+                    .map(|(pt, _caps)| pt)
+                    .min()
+            });
+        let Some(pt) = pt else {
+            gst::warning!(
+                CAT,
+                imp = self,
+                "No telephone-event payload type for session {id}"
+            );
+            return false;
+        };
+        let ssrc = s.get::<u32>("ssrc").ok();
+
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Starting DTMF event {} with volume {} and pt {pt} in session {id}",
+            event.number,
+            event.volume,
+        );
+        session
+            .dtmf
+            .lock()
+            .unwrap()
+            .start(pt, ssrc, event.number, event.volume)
+    }
+
+    fn rtp_src_event(&self, pad: &gst::Pad, event: gst::Event, id: usize) -> bool {
+        if let gst::EventView::CustomUpstream(custom) = event.view() {
+            if let Some(s) = custom.structure().filter(|s| s.has_name("dtmf-event")) {
+                return self.handle_dtmf_event(id, s);
+            }
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
     fn rtp_sink_chain_list(
         &self,
-        pad: &gst::Pad,
         id: usize,
         list: gst::BufferList,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
@@ -390,20 +854,41 @@ impl RtpSend {
 
         let srcpad = session.rtp_send_srcpad.clone().unwrap();
         let internal_session = session.internal_session.clone();
+        // Only keep the last packet around if it's needed for keepalives
+        let last_packet = (self.settings.lock().unwrap().keepalive_interval > 0)
+            .then(|| session.last_packet.clone());
+        let pacing_stats = session.pacing_stats.clone();
+        let dtmf = session.dtmf.clone();
         drop(state);
 
         let now = Instant::now();
-        for buffer in list.iter_owned() {
-            self.handle_buffer(pad, &srcpad, &internal_session, buffer, now)?;
+        for mut buffer in list.iter_owned() {
+            for packet in self.inject_dtmf(&dtmf, &mut buffer)? {
+                self.handle_buffer(
+                    &srcpad,
+                    &internal_session,
+                    last_packet.as_deref(),
+                    &pacing_stats,
+                    packet,
+                    now,
+                )?;
+            }
+            self.handle_buffer(
+                &srcpad,
+                &internal_session,
+                last_packet.as_deref(),
+                &pacing_stats,
+                buffer,
+                now,
+            )?;
         }
         Ok(gst::FlowSuccess::Ok)
     }
 
     fn rtp_sink_chain(
         &self,
-        pad: &gst::Pad,
         id: usize,
-        buffer: gst::Buffer,
+        mut buffer: gst::Buffer,
     ) -> Result<gst::FlowSuccess, gst::FlowError> {
         let state = self.state.lock().unwrap();
         let Some(session) = state.session_by_id(id) else {
@@ -413,10 +898,32 @@ impl RtpSend {
 
         let srcpad = session.rtp_send_srcpad.clone().unwrap();
         let internal_session = session.internal_session.clone();
+        // Only keep the last packet around if it's needed for keepalives
+        let last_packet = (self.settings.lock().unwrap().keepalive_interval > 0)
+            .then(|| session.last_packet.clone());
+        let pacing_stats = session.pacing_stats.clone();
+        let dtmf = session.dtmf.clone();
         drop(state);
 
         let now = Instant::now();
-        self.handle_buffer(pad, &srcpad, &internal_session, buffer, now)
+        for packet in self.inject_dtmf(&dtmf, &mut buffer)? {
+            self.handle_buffer(
+                &srcpad,
+                &internal_session,
+                last_packet.as_deref(),
+                &pacing_stats,
+                packet,
+                now,
+            )?;
+        }
+        self.handle_buffer(
+            &srcpad,
+            &internal_session,
+            last_packet.as_deref(),
+            &pacing_stats,
+            buffer,
+            now,
+        )
     }
 
     fn rtp_sink_event(&self, pad: &gst::Pad, event: gst::Event, id: usize) -> bool {
@@ -425,9 +932,14 @@ impl RtpSend {
                 if let Some((pt, clock_rate)) = pt_clock_rate_from_caps(caps.caps()) {
                     let state = self.state.lock().unwrap();
                     if let Some(session) = state.session_by_id(id) {
+                        let config = session.internal_session.config.clone();
                         let mut session = session.internal_session.inner.lock().unwrap();
                         session.session.set_pt_clock_rate(pt, clock_rate);
                         session.add_caps(caps.caps_owned());
+                        drop(session);
+                        drop(state);
+
+                        config.update_extensions();
                     }
                 } else {
                     gst::warning!(
@@ -438,12 +950,24 @@ impl RtpSend {
                 }
                 gst::Pad::event_default(pad, Some(&*self.obj()), event)
             }
+            gst::EventView::FlushStart(_) | gst::EventView::FlushStop(_) => {
+                // No keepalives until the next RTP packet
+                let state = self.state.lock().unwrap();
+                if let Some(session) = state.session_by_id(id) {
+                    *session.last_packet.lock().unwrap() = None;
+                }
+                drop(state);
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
             gst::EventView::Eos(_eos) => {
                 let now = Instant::now();
                 let state = self.state.lock().unwrap();
                 if let Some(session) = state.session_by_id(id) {
+                    // The stream is done, so no more keepalives either
+                    *session.last_packet.lock().unwrap() = None;
                     let mut session = session.internal_session.inner.lock().unwrap();
                     let ssrcs = session.session.ssrcs().collect::<Vec<_>>();
+                    let bye_reason = session.session.bye_reason().to_string();
                     // We want to bye all relevant ssrc's here.
                     // Relevant means they will not be used by something else which means that any
                     // local send ssrc that is not being used for Sr/Rr reports (internal_ssrc) can
@@ -465,13 +989,13 @@ impl RtpSend {
                             continue;
                         };
                         if Some(ssrc) != internal_ssrc {
-                            local_send.mark_bye("End of Stream")
+                            local_send.mark_bye(&bye_reason)
                         }
                     }
                     if all_local {
                         // if there are no non-local send ssrc's, then we can Bye the entire
                         // session.
-                        session.session.schedule_bye("End of Stream", now);
+                        session.session.schedule_bye(&bye_reason, now);
                     }
                     if let Some(waker) = session.rtcp_waker.take() {
                         waker.wake();
@@ -491,12 +1015,15 @@ impl ObjectSubclass for RtpSend {
     const NAME: &'static str = "GstRtpSend";
     type Type = super::RtpSend;
     type ParentType = gst::Element;
+    type Interfaces = (gst::ChildProxy,);
 
     fn new() -> Self {
         GstRustLogger::install();
         Self {
             settings: Default::default(),
             state: Default::default(),
+            stats_task: Default::default(),
+            keepalive_task: Default::default(),
         }
     }
 }
@@ -533,6 +1060,28 @@ impl ObjectImpl for RtpSend {
                     .default_value(DEFAULT_REDUCED_SIZE_RTCP)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("sdes")
+                    .nick("SDES")
+                    .blurb("The SDES items to use for all sessions. A CNAME is generated per session if none is set")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("stats-interval")
+                    .nick("Statistics interval")
+                    .blurb("Interval (in ms) for posting the statistics as element message (0 = disabled)")
+                    .default_value(DEFAULT_STATS_INTERVAL)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("keepalive-interval")
+                    .nick("Keepalive interval")
+                    .blurb("Interval (in ms) without RTP packets after which a keepalive packet is sent to keep NAT bindings open (0 = disabled)")
+                    .default_value(DEFAULT_KEEPALIVE_INTERVAL)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("keepalive-method", DEFAULT_KEEPALIVE_METHOD)
+                    .nick("Keepalive method")
+                    .blurb("Kind of packet to send as keepalive")
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -559,6 +1108,38 @@ impl ObjectImpl for RtpSend {
                 let mut settings = self.settings.lock().unwrap();
                 settings.reduced_size_rtcp = value.get::<bool>().expect("Type checked upstream");
             }
+            "sdes" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.sdes = value
+                    .get::<Option<gst::Structure>>()
+                    .expect("Type checked upstream");
+                let sdes = settings
+                    .sdes
+                    .as_deref()
+                    .map(sdes_from_structure)
+                    .unwrap_or_default();
+                drop(settings);
+
+                let state = self.state.lock().unwrap();
+                for session in &state.sessions {
+                    let mut inner = session.internal_session.inner.lock().unwrap();
+                    inner.session.set_sdes(sdes.clone());
+                }
+            }
+            "stats-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.stats_interval = value.get::<u32>().expect("Type checked upstream");
+            }
+            "keepalive-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.keepalive_interval = value.get::<u32>().expect("Type checked upstream");
+            }
+            "keepalive-method" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.keepalive_method = value
+                    .get::<KeepaliveMethod>()
+                    .expect("Type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -585,26 +1166,98 @@ impl ObjectImpl for RtpSend {
                 let settings = self.settings.lock().unwrap();
                 settings.reduced_size_rtcp.to_value()
             }
+            "sdes" => {
+                let settings = self.settings.lock().unwrap();
+                settings.sdes.to_value()
+            }
+            "stats-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.stats_interval.to_value()
+            }
+            "keepalive-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.keepalive_interval.to_value()
+            }
+            "keepalive-method" => {
+                let settings = self.settings.lock().unwrap();
+                settings.keepalive_method.to_value()
+            }
             _ => unimplemented!(),
         }
     }
 
     fn signals() -> &'static [glib::subclass::Signal] {
         static SIGNALS: LazyLock<Vec<glib::subclass::Signal>> = LazyLock::new(|| {
-            vec![glib::subclass::Signal::builder("get-session")
-                .param_types([u32::static_type()])
-                .return_type::<crate::rtpbin2::config::Rtp2Session>()
-                .action()
-                .class_handler(|_token, args| {
-                    let element = args[0].get::<super::RtpSend>().expect("signal arg");
-                    let id = args[1].get::<u32>().expect("signal arg");
-                    let send = element.imp();
-                    let state = send.state.lock().unwrap();
-                    state
-                        .session_by_id(id as usize)
-                        .map(|sess| sess.internal_session.config.to_value())
-                })
-                .build()]
+            vec![
+                glib::subclass::Signal::builder("get-session")
+                    .param_types([u32::static_type()])
+                    .return_type::<crate::rtpbin2::config::Rtp2Session>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpSend>().expect("signal arg");
+                        let id = args[1].get::<u32>().expect("signal arg");
+                        let send = element.imp();
+                        let state = send.state.lock().unwrap();
+                        state
+                            .session_by_id(id as usize)
+                            .map(|sess| sess.internal_session.config.to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("create-session")
+                    .return_type::<u32>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpSend>().expect("signal arg");
+                        Some(element.imp().create_session().to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("destroy-session")
+                    .param_types([u32::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpSend>().expect("signal arg");
+                        let id = args[1].get::<u32>().expect("signal arg");
+                        Some(element.imp().destroy_session(id as usize).to_value())
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("request-session-pad")
+                    .param_types([u32::static_type(), String::static_type()])
+                    .return_type::<gst::Pad>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpSend>().expect("signal arg");
+                        let id = args[1].get::<u32>().expect("signal arg");
+                        let kind = args[2].get::<&str>().expect("signal arg");
+                        Some(
+                            element
+                                .imp()
+                                .request_session_pad(id as usize, kind)
+                                .to_value(),
+                        )
+                    })
+                    .build(),
+                glib::subclass::Signal::builder("get-source-stats")
+                    .param_types([u32::static_type(), u32::static_type()])
+                    .return_type::<gst::Structure>()
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::RtpSend>().expect("signal arg");
+                        let id = args[1].get::<u32>().expect("signal arg");
+                        let ssrc = args[2].get::<u32>().expect("signal arg");
+                        let state = element.imp().state.lock().unwrap();
+                        let source_stats = state.session_by_id(id as usize).and_then(|session| {
+                            session
+                                .internal_session
+                                .inner
+                                .lock()
+                                .unwrap()
+                                .source_stats(ssrc)
+                        });
+                        Some(source_stats.to_value())
+                    })
+                    .build(),
+            ]
         });
 
         SIGNALS.as_ref()
@@ -613,6 +1266,29 @@ impl ObjectImpl for RtpSend {
 
 impl GstObjectImpl for RtpSend {}
 
+impl ChildProxyImpl for RtpSend {
+    fn children_count(&self) -> u32 {
+        let state = self.state.lock().unwrap();
+        state.sessions.len() as u32
+    }
+
+    fn child_by_name(&self, name: &str) -> Option<glib::Object> {
+        let id = name.strip_prefix("session_")?.parse::<usize>().ok()?;
+        let state = self.state.lock().unwrap();
+        state
+            .session_by_id(id)
+            .map(|sess| sess.internal_session.config.clone().upcast())
+    }
+
+    fn child_by_index(&self, index: u32) -> Option<glib::Object> {
+        let state = self.state.lock().unwrap();
+        state
+            .sessions
+            .get(index as usize)
+            .map(|sess| sess.internal_session.config.clone().upcast())
+    }
+}
+
 impl ElementImpl for RtpSend {
     fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
         static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
@@ -675,6 +1351,7 @@ impl ElementImpl for RtpSend {
         let mut state = self.state.lock().unwrap();
         let max_session_id = state.max_session_id;
         let rtp_id = settings.rtp_id.clone();
+        let mut new_session = None;
 
         // parse the possibly provided name into a session id or use the default
         let sess_parse = move |name: Option<&str>, prefix, default_id| -> Option<usize> {
@@ -700,18 +1377,18 @@ impl ElementImpl for RtpSend {
                     Vec<gst::Event>,
                 )> {
                     let sinkpad = gst::Pad::builder_from_template(templ)
-                        .chain_function(move |pad, parent, buffer| {
+                        .chain_function(move |_pad, parent, buffer| {
                             RtpSend::catch_panic_pad_function(
                                 parent,
                                 || Err(gst::FlowError::Error),
-                                |this| this.rtp_sink_chain(pad, id, buffer),
+                                |this| this.rtp_sink_chain(id, buffer),
                             )
                         })
-                        .chain_list_function(move |pad, parent, list| {
+                        .chain_list_function(move |_pad, parent, list| {
                             RtpSend::catch_panic_pad_function(
                                 parent,
                                 || Err(gst::FlowError::Error),
-                                |this| this.rtp_sink_chain_list(pad, id, list),
+                                |this| this.rtp_sink_chain_list(id, list),
                             )
                         })
                         .iterate_internal_links_function(|pad, parent| {
@@ -740,6 +1417,13 @@ impl ElementImpl for RtpSend {
                                 |this| this.iterate_internal_links(pad),
                             )
                         })
+                        .event_function(move |pad, parent, event| {
+                            RtpSend::catch_panic_pad_function(
+                                parent,
+                                || false,
+                                |this| this.rtp_src_event(pad, event, id),
+                            )
+                        })
                         .name(format!("rtp_src_{}", id))
                         .build();
                     session.rtp_send_sinkpad = Some(sinkpad.clone());
@@ -766,6 +1450,7 @@ impl ElementImpl for RtpSend {
                         .get_or_insert_with(|| SharedRtpState::send_get_or_init(rtp_id));
                     let mut session = SendSession::new(shared_state, id, &settings);
                     let ret = new_pad(&mut session);
+                    new_session = Some(session.internal_session.config.clone());
                     state.sessions.push(session);
                     ret
                 }
@@ -816,6 +1501,7 @@ impl ElementImpl for RtpSend {
                         .get_or_insert_with(|| SharedRtpState::send_get_or_init(rtp_id));
                     let mut session = SendSession::new(shared_state, id, &settings);
                     let ret = new_pad(&mut session);
+                    new_session = Some(session.internal_session.config.clone());
                     state.sessions.push(session);
                     ret
                 }
@@ -842,6 +1528,10 @@ impl ElementImpl for RtpSend {
                 self.obj().add_pad(&pad).unwrap();
             }
 
+            if let Some(config) = new_session {
+                self.obj().child_added(&config, &format!("session_{id}"));
+            }
+
             pad
         })
     }
@@ -871,7 +1561,10 @@ impl ElementImpl for RtpSend {
                     session.rtcp_send_srcpad = None;
                 }
 
-                if session.rtp_send_sinkpad.is_none() && session.rtcp_send_srcpad.is_none() {
+                if !session.created
+                    && session.rtp_send_sinkpad.is_none()
+                    && session.rtcp_send_srcpad.is_none()
+                {
                     removed_session_ids.push(session.internal_session.id);
                 }
             }
@@ -886,6 +1579,7 @@ impl ElementImpl for RtpSend {
             }
         }
 
+        let mut removed_sessions = vec![];
         {
             let mut state = self.state.lock().unwrap();
 
@@ -894,14 +1588,22 @@ impl ElementImpl for RtpSend {
             }
             for id in removed_session_ids {
                 if let Some(session) = state.session_by_id(id) {
-                    if session.rtp_send_sinkpad.is_none() && session.rtcp_send_srcpad.is_none() {
+                    if !session.created
+                        && session.rtp_send_sinkpad.is_none()
+                        && session.rtcp_send_srcpad.is_none()
+                    {
                         session.stop_rtcp_task();
+                        removed_sessions.push((id, session.internal_session.config.clone()));
                         state.sessions.retain(|s| s.internal_session.id != id);
                     }
                 }
             }
         }
 
+        for (id, config) in removed_sessions {
+            self.obj().child_removed(&config, &format!("session_{id}"));
+        }
+
         self.parent_release_pad(pad)
     }
 
@@ -943,6 +1645,12 @@ impl ElementImpl for RtpSend {
         let success = self.parent_change_state(transition)?;
 
         match transition {
+            gst::StateChange::ReadyToPaused => self.start_stats_task(),
+            gst::StateChange::PausedToPlaying => self.start_keepalive_task(),
+            gst::StateChange::PlayingToPaused => self.stop_keepalive_task(),
+            gst::StateChange::PausedToReady => {
+                self.stats_task.lock().unwrap().take();
+            }
             gst::StateChange::ReadyToNull => {
                 let mut state = self.state.lock().unwrap();
                 for session in state.sessions.iter_mut() {
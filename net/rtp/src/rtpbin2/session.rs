@@ -11,24 +11,79 @@ use rand::prelude::*;
 
 use crate::rtpbin2::source::SourceRecvReply;
 
+use super::rsi::{Rsi, RSI_PACKET_TYPE};
 use super::source::{
-    LocalReceiveSource, LocalSendSource, RemoteReceiveSource, RemoteSendSource, SourceState,
+    LocalReceiveSource, LocalSendSource, ReceivedRb, RemoteReceiveSource, RemoteSendSource,
+    SourceState, DEFAULT_PROBATION_N_PACKETS,
 };
 use super::time::system_time_to_ntp_time_u64;
+use super::twcc::{Twcc, TwccFeedback, TwccStats};
+use super::xr::{Xr, XrBlock, XrBuilder, XR_PACKET_TYPE};
 
 use gst::prelude::MulDiv;
 
 // TODO: make configurable
 pub const RTCP_MIN_REPORT_INTERVAL: Duration = Duration::from_secs(5);
 
-const RTCP_SOURCE_TIMEOUT_N_INTERVALS: u32 = 5;
+// RFC 3550 6.3.5
+pub const RTCP_SENDER_TIMEOUT_N_INTERVALS: u32 = 2;
+pub const RTCP_SOURCE_TIMEOUT_N_INTERVALS: u32 = 5;
+
+pub const DEFAULT_BYE_REASON: &str = "End of Stream";
 const RTCP_ADDRESS_CONFLICT_TIMEOUT: Duration = RTCP_MIN_REPORT_INTERVAL.saturating_mul(12);
 // 5% of 8kB/s
 const RTCP_MIN_BANDWIDTH: usize = 400;
+pub const DEFAULT_RTCP_FRACTION: f64 = 0.05;
 const RTCP_MTU: usize = 1200;
 
 const UDP_IP_OVERHEAD_BYTES: usize = 28;
 
+/// SDES item type of the RtpStreamId (RFC 8852)
+const SDES_ITEM_RTP_STREAM_ID: u8 = 12;
+/// SDES item type of the MID (RFC 8843)
+const SDES_ITEM_MID: u8 = 15;
+
+/// Bandwidth configuration the RTCP interval is derived from. All bandwidths are in bytes per
+/// second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RtcpBandwidth {
+    /// Session bandwidth, estimated from the bitrates of the active senders if `None`
+    pub session: Option<usize>,
+    /// Fraction of the session bandwidth used for RTCP
+    pub fraction: f64,
+    /// RTCP bandwidth of the senders (RFC 3556), derived from `fraction` if `None`
+    pub senders: Option<usize>,
+    /// RTCP bandwidth of the receivers (RFC 3556), derived from `fraction` if `None`
+    pub receivers: Option<usize>,
+}
+
+impl RtcpBandwidth {
+    /// RFC 3556: RTCP is disabled if both the sender and receiver bandwidths are zero
+    pub fn is_disabled(&self) -> bool {
+        self.senders == Some(0) && self.receivers == Some(0)
+    }
+}
+
+impl Default for RtcpBandwidth {
+    fn default() -> Self {
+        Self {
+            session: None,
+            fraction: DEFAULT_RTCP_FRACTION,
+            senders: None,
+            receivers: None,
+        }
+    }
+}
+
+/// Inactivity period after which a source is timed out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceTimeout {
+    /// A multiple of the deterministic RTCP interval
+    Intervals(u32),
+    /// A fixed duration
+    Duration(Duration),
+}
+
 #[derive(Debug, Default)]
 struct RtcpTimeMembers {
     time: Option<Instant>,
@@ -67,12 +122,31 @@ enum RequestEarlyRtcpResult {
     TimerReconsideration,
 }
 
+/// Identification of a remote SSRC inside a BUNDLE group
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BundleIds {
+    /// The media identification (RFC 8843)
+    pub mid: Option<String>,
+    /// The RtpStreamId (RFC 8852)
+    pub rid: Option<String>,
+}
+
 #[derive(Debug)]
 pub struct Session {
     // settings
     min_rtcp_interval: Duration,
+    rtcp_bandwidth: RtcpBandwidth,
     profile: RtpProfile,
     reduced_size_rtcp: bool,
+    rtcp_xr_rrtr: bool,
+    separate_sources_by_address: bool,
+    feedback_target: Option<SocketAddr>,
+    max_dropout_time: Option<Duration>,
+    max_misorder_time: Option<Duration>,
+    probation: usize,
+    sender_timeout: SourceTimeout,
+    member_timeout: SourceTimeout,
+    bye_reason: String,
     // state
     local_senders: HashMap<u32, LocalSendSource>,
     local_receivers: HashMap<u32, LocalReceiveSource>,
@@ -83,11 +157,23 @@ pub struct Session {
     hold_buffer_counter: usize,
     sdes: HashMap<u8, String>,
     pt_map: HashMap<u8, u32>,
+    // clock rates of SSRCs that override the clock rate of their payload type
+    ssrc_clock_rates: HashMap<u32, u32>,
+    bundle_ids: HashMap<u32, BundleIds>,
+    // SSRCs of sent packets that have to be rewritten because of a collision
+    ssrc_rewrites: HashMap<u32, u32>,
     conflicting_addresses: HashMap<SocketAddr, Instant>,
+    // SSRCs used for remote sources that reuse the SSRC of a source from another address
+    address_ssrcs: HashMap<(SocketAddr, u32), u32>,
     // used when we have not sent anything but need a ssrc for Rr
     internal_rtcp_sender_src: Option<u32>,
     bye_state: Option<ByeState>,
     is_point_to_point: bool,
+    // Number of receivers of a source-specific multicast session as reported by the
+    // distribution source, and when it was last reported
+    rsi_group_size: Option<(usize, Instant)>,
+    // Transport-wide congestion control state of sent packets
+    twcc: Twcc,
 
     // rtcp scheduling state
     // T_rr: holds the interval used to calculate the current `next_rtcp_send`
@@ -97,11 +183,13 @@ pub struct Session {
     // tn: holds the next regular rtcp send time and the number of members at the
     // time when the time was calculated
     next_rtcp_send: RtcpTimeMembers,
-    // T_rr_last: last two times a regular rtcp packet was sent
+    // T_rr_last: last times a regular rtcp packet was sent, at least the last two
     last_rtcp_sent_times: VecDeque<Instant>,
     // time for the next early rtcp to be sent
     next_early_rtcp_time: Option<Instant>,
     pending_rtcp_send: VecDeque<RtcpSendReply>,
+    // remote ssrcs that timed out, until returned by poll_source_timeouts()
+    timed_out_ssrcs: Vec<u32>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -154,6 +242,8 @@ pub enum RtcpRecvReply {
     NewRtpNtp((u32, u32, u64)),
     /// A ssrc has byed
     SsrcBye(u32),
+    /// New transport-wide congestion control statistics are available
+    TwccStats,
 }
 
 #[derive(Debug)]
@@ -177,8 +267,18 @@ impl Session {
         sdes.insert(SdesItem::CNAME, cname);
         Self {
             min_rtcp_interval: RTCP_MIN_REPORT_INTERVAL,
+            rtcp_bandwidth: RtcpBandwidth::default(),
             profile: RtpProfile::default(),
             reduced_size_rtcp: false,
+            rtcp_xr_rrtr: false,
+            separate_sources_by_address: false,
+            feedback_target: None,
+            max_dropout_time: None,
+            max_misorder_time: None,
+            probation: DEFAULT_PROBATION_N_PACKETS,
+            sender_timeout: SourceTimeout::Intervals(RTCP_SENDER_TIMEOUT_N_INTERVALS),
+            member_timeout: SourceTimeout::Intervals(RTCP_SOURCE_TIMEOUT_N_INTERVALS),
+            bye_reason: String::from(DEFAULT_BYE_REASON),
             local_senders: HashMap::new(),
             // also known as remote_senders
             local_receivers: HashMap::new(),
@@ -195,13 +295,20 @@ impl Session {
             hold_buffer_counter: 0,
             sdes,
             pt_map: HashMap::new(),
+            ssrc_clock_rates: HashMap::new(),
+            bundle_ids: HashMap::new(),
+            ssrc_rewrites: HashMap::new(),
             conflicting_addresses: HashMap::new(),
+            address_ssrcs: HashMap::new(),
             internal_rtcp_sender_src: None,
             bye_state: None,
             next_early_rtcp_time: None,
             last_rtcp_handle_time: None,
             is_point_to_point: true,
+            rsi_group_size: None,
+            twcc: Twcc::default(),
             pending_rtcp_send: VecDeque::new(),
+            timed_out_ssrcs: vec![],
         }
     }
 
@@ -210,22 +317,160 @@ impl Session {
         self.min_rtcp_interval = min_rtcp_interval;
     }
 
+    /// Set the bandwidths the RTCP interval is derived from
+    pub fn set_rtcp_bandwidth(&mut self, rtcp_bandwidth: RtcpBandwidth) {
+        self.rtcp_bandwidth = rtcp_bandwidth;
+    }
+
+    /// The bandwidths the RTCP interval is derived from
+    pub fn rtcp_bandwidth(&self) -> RtcpBandwidth {
+        self.rtcp_bandwidth
+    }
+
     /// Set the RTP profile to use.
     pub fn set_profile(&mut self, profile: RtpProfile) {
         self.profile = profile;
     }
 
+    /// The minimum (regular) RTCP interval used for this session
+    pub fn min_rtcp_interval(&self) -> Duration {
+        self.min_rtcp_interval
+    }
+
+    /// The RTP profile in use.
+    pub fn profile(&self) -> RtpProfile {
+        self.profile
+    }
+
     /// Set usage of reduced size RTCP
     pub fn set_reduced_size_rtcp(&mut self, reduced_size_rtcp: bool) {
         self.reduced_size_rtcp = reduced_size_rtcp;
     }
 
+    /// Set the reason sent in BYE packets when the session ends
+    pub fn set_bye_reason(&mut self, bye_reason: &str) {
+        self.bye_reason = bye_reason.to_string();
+    }
+
+    /// The reason sent in BYE packets when the session ends
+    pub fn bye_reason(&self) -> &str {
+        &self.bye_reason
+    }
+
+    /// Set whether to include an XR Receiver Reference Time block (RFC 3611) in generated RTCP
+    pub fn set_rtcp_xr_rrtr(&mut self, rtcp_xr_rrtr: bool) {
+        self.rtcp_xr_rrtr = rtcp_xr_rrtr;
+    }
+
+    /// Whether XR Receiver Reference Time blocks are included in generated RTCP
+    pub fn rtcp_xr_rrtr(&self) -> bool {
+        self.rtcp_xr_rrtr
+    }
+
+    /// Set whether remote sources with the same SSRC but different source addresses are handled
+    /// as separate sources instead of ignoring the packets of the later one
+    pub fn set_separate_sources_by_address(&mut self, separate_sources_by_address: bool) {
+        self.separate_sources_by_address = separate_sources_by_address;
+        if !separate_sources_by_address {
+            self.address_ssrcs.clear();
+        }
+    }
+
+    /// Whether remote sources are separated by their source address
+    pub fn separate_sources_by_address(&self) -> bool {
+        self.separate_sources_by_address
+    }
+
+    /// Set the unicast address RTCP is sent to in a source-specific multicast session
+    /// (RFC 5760), instead of the destination configured downstream
+    pub fn set_feedback_target(&mut self, feedback_target: Option<SocketAddr>) {
+        self.feedback_target = feedback_target;
+    }
+
+    /// The unicast feedback target of a source-specific multicast session
+    pub fn feedback_target(&self) -> Option<SocketAddr> {
+        self.feedback_target
+    }
+
+    /// Number of receivers of the session as reported by the distribution source of a
+    /// source-specific multicast session
+    pub fn rsi_group_size(&self) -> Option<usize> {
+        self.rsi_group_size.map(|(group_size, _)| group_size)
+    }
+
+    /// Set the time after which a jump forward in sequence numbers of a remote sender is
+    /// considered a restart of the sequence. `None` uses a fixed number of packets.
+    pub fn set_max_dropout_time(&mut self, max_dropout_time: Option<Duration>) {
+        self.max_dropout_time = max_dropout_time;
+    }
+
+    /// Set the time after which a jump backwards in sequence numbers of a remote sender is
+    /// considered a restart of the sequence. `None` uses a fixed number of packets.
+    pub fn set_max_misorder_time(&mut self, max_misorder_time: Option<Duration>) {
+        self.max_misorder_time = max_misorder_time;
+    }
+
+    /// Set the number of consecutive packets a new remote sender has to send before its packets
+    /// are forwarded. Applies to senders that are detected afterwards.
+    pub fn set_probation(&mut self, probation: usize) {
+        self.probation = probation;
+    }
+
+    /// Set the time after which a local sender that did not send any RTP packets is considered a
+    /// receiver again.
+    pub fn set_sender_timeout(&mut self, sender_timeout: SourceTimeout) {
+        self.sender_timeout = sender_timeout;
+    }
+
+    /// The time after which a local sender that did not send any RTP packets is considered a
+    /// receiver again.
+    pub fn sender_timeout(&self) -> SourceTimeout {
+        self.sender_timeout
+    }
+
+    /// Set the time after which a source without any activity is removed from the session.
+    pub fn set_member_timeout(&mut self, member_timeout: SourceTimeout) {
+        self.member_timeout = member_timeout;
+    }
+
+    /// The time after which a source without any activity is removed from the session.
+    pub fn member_timeout(&self) -> SourceTimeout {
+        self.member_timeout
+    }
+
+    /// Set the SDES items to send for all local sources. The current CNAME is kept if `sdes`
+    /// does not contain one.
+    pub fn set_sdes(&mut self, mut sdes: HashMap<u8, String>) {
+        if !sdes.contains_key(&SdesItem::CNAME) {
+            sdes.insert(SdesItem::CNAME, self.cname().to_owned());
+        }
+        self.sdes = sdes;
+
+        for source in self.local_senders.values_mut() {
+            source.set_sdes(&self.sdes);
+        }
+        for source in self.local_receivers.values_mut() {
+            source.set_sdes(&self.sdes);
+        }
+    }
+
+    /// The SDES items sent for local sources
+    pub fn sdes(&self) -> &HashMap<u8, String> {
+        &self.sdes
+    }
+
+    /// The CNAME sent for local sources
+    pub fn cname(&self) -> &str {
+        &self.sdes[&SdesItem::CNAME]
+    }
+
     fn n_members(&self) -> usize {
         self.bye_state
             .as_ref()
             .map(|state| state.members)
             .unwrap_or_else(|| {
-                self.local_senders
+                let n_members = self
+                    .local_senders
                     .values()
                     .filter(|source| source.state() == SourceState::Normal)
                     .count()
@@ -243,7 +488,11 @@ impl Session {
                         .remote_receivers
                         .values()
                         .filter(|source| source.state() == SourceState::Normal)
-                        .count()
+                        .count();
+
+                // In source-specific multicast sessions most members are only known through
+                // the group size reported by the distribution source
+                n_members.max(self.rsi_group_size().unwrap_or(0))
             })
     }
 
@@ -295,11 +544,32 @@ impl Session {
         self.pt_map.insert(pt, clock_rate);
     }
 
+    /// Forget the RTP clock rates of all payload types
+    pub fn clear_pt_clock_rates(&mut self) {
+        self.pt_map.clear();
+    }
+
     /// Retrieve the RTP clock rate for a particular payload type
     pub fn clock_rate_from_pt(&self, pt: u8) -> Option<u32> {
         self.pt_map.get(&pt).copied()
     }
 
+    /// Set the RTP clock rate for a particular SSRC, independent of its payload type
+    pub fn set_ssrc_clock_rate(&mut self, ssrc: u32, clock_rate: u32) {
+        self.ssrc_clock_rates.insert(ssrc, clock_rate);
+    }
+
+    /// Retrieve the RTP clock rate set for a particular SSRC
+    pub fn clock_rate_from_ssrc(&self, ssrc: u32) -> Option<u32> {
+        self.ssrc_clock_rates.get(&ssrc).copied()
+    }
+
+    /// Retrieve the RTP clock rate for a packet of a particular SSRC and payload type
+    pub fn clock_rate_from_ssrc_pt(&self, ssrc: u32, pt: u8) -> Option<u32> {
+        self.clock_rate_from_ssrc(ssrc)
+            .or_else(|| self.clock_rate_from_pt(pt))
+    }
+
     fn handle_ssrc_conflict(&mut self, addr: SocketAddr, now: Instant) -> bool {
         if let Some(time) = self.conflicting_addresses.get_mut(&addr) {
             trace!("ignoring looped packet from known collision address {addr:?}");
@@ -339,6 +609,7 @@ impl Session {
             } else if let Some(recv) = self.remote_receivers.remove(&rtp.ssrc()) {
                 let mut sender = recv.into_send();
                 sender.set_rtp_from(from);
+                sender.set_probation_packets(self.probation);
                 self.remote_senders.insert(rtp.ssrc(), sender);
             } else if let Some(recv) = self.remote_senders.get_mut(&rtp.ssrc()) {
                 if let Some(from_addr) = recv.rtp_from() {
@@ -354,9 +625,12 @@ impl Session {
 
         // TODO: handle CSRCs
 
-        let clock_rate = self.clock_rate_from_pt(rtp.payload_type());
+        let clock_rate = self.clock_rate_from_ssrc_pt(rtp.ssrc(), rtp.payload_type());
 
         if let Some(source) = self.remote_senders.get_mut(&rtp.ssrc()) {
+            source.set_last_activity(now);
+            source.set_max_dropout_time(self.max_dropout_time);
+            source.set_max_misorder_time(self.max_misorder_time);
             match source.recv_packet(
                 rtp.payload().len() as u32,
                 now,
@@ -378,6 +652,7 @@ impl Session {
         } else {
             let mut source = RemoteSendSource::new(rtp.ssrc());
             source.set_rtp_from(from);
+            source.set_probation_packets(self.probation);
             self.remote_senders.insert(rtp.ssrc(), source);
             trace!("new receive ssrc:{}, pt:{}", rtp.ssrc(), rtp.payload_type());
             RecvReply::NewSsrc(rtp.ssrc(), rtp.payload_type())
@@ -557,6 +832,7 @@ impl Session {
                     let n_members = self.n_members();
                     let mut check_reconsideration = false;
                     for ssrc in bye.ssrcs() {
+                        self.bundle_ids.remove(&ssrc);
                         if let Some(source) = self.remote_senders.get_mut(&ssrc) {
                             source.set_rtcp_from(from);
                             source.set_last_activity(now);
@@ -677,6 +953,8 @@ impl Session {
                                     | SdesItem::LOC
                                     | SdesItem::TOOL
                                     | SdesItem::NOTE
+                                    | SDES_ITEM_RTP_STREAM_ID
+                                    | SDES_ITEM_MID
                             ) {
                                 // FIXME: handle unknown sdes items
                                 continue;
@@ -700,13 +978,18 @@ impl Session {
                                 source.set_last_activity(now);
                             }
 
-                            if item.type_() == SdesItem::CNAME {
-                                if let Ok(s) = std::str::from_utf8(item.value()) {
+                            match (item.type_(), std::str::from_utf8(item.value())) {
+                                (SdesItem::CNAME, Ok(s)) => {
                                     replies.push(RtcpRecvReply::NewCName((
                                         s.to_owned(),
                                         chunk.ssrc(),
                                     )));
                                 }
+                                (SDES_ITEM_MID, Ok(mid)) => self.set_ssrc_mid(chunk.ssrc(), mid),
+                                (SDES_ITEM_RTP_STREAM_ID, Ok(rid)) => {
+                                    self.set_ssrc_rid(chunk.ssrc(), rid)
+                                }
+                                _ => (),
                             }
                         }
                     }
@@ -731,7 +1014,30 @@ impl Session {
                         );
                     }
                 }
-                Ok(Packet::TransportFeedback(_)) | Ok(Packet::Unknown(_)) => (),
+                Ok(Packet::Unknown(unknown)) if unknown.type_() == XR_PACKET_TYPE => {
+                    if let Some(xr) = Xr::parse(unknown.data()) {
+                        self.handle_xr(xr, from, now, ntp_time, &mut replies);
+                    }
+                }
+                Ok(Packet::Unknown(unknown)) if unknown.type_() == RSI_PACKET_TYPE => {
+                    if let Some(rsi) = Rsi::parse(unknown.data()) {
+                        self.handle_rsi(rsi, now);
+                    }
+                }
+                Ok(Packet::TransportFeedback(tf)) => {
+                    if let Ok(feedback) = tf.parse_fci::<TwccFeedback>() {
+                        trace!(
+                            "Received TWCC feedback {} from {:#010x} about {} packets",
+                            feedback.feedback_count,
+                            tf.sender_ssrc(),
+                            feedback.packets.len()
+                        );
+                        if self.twcc.handle_feedback(&feedback) {
+                            replies.push(RtcpRecvReply::TwccStats);
+                        }
+                    }
+                }
+                Ok(Packet::Unknown(_)) => (),
                 // TODO: in RFC4585 profile, need to listen for feedback messages and remove any
                 // that we would have sent
                 Err(_) => (),
@@ -741,6 +1047,71 @@ impl Session {
         replies
     }
 
+    fn handle_rsi(&mut self, rsi: Rsi, now: Instant) {
+        trace!(
+            "Received RSI from {:#010x} about {:#010x} with NTP time {:#018x}",
+            rsi.ssrc,
+            rsi.summarized_ssrc,
+            rsi.ntp_timestamp
+        );
+
+        // RFC 5760: Receivers of a source-specific multicast session don't see each other's
+        // RTCP, so the group size and average RTCP packet size reported by the distribution
+        // source are used for the RTCP interval calculation instead
+        if let Some(group_size) = rsi.group_size {
+            if self.rsi_group_size() != Some(group_size as usize) {
+                debug!("Group size of source-specific multicast session changed to {group_size}");
+            }
+            self.rsi_group_size = Some((group_size as usize, now));
+        }
+        if let Some(average_packet_size) = rsi.average_packet_size.filter(|size| *size > 0) {
+            self.average_rtcp_size = average_packet_size as usize;
+        }
+    }
+
+    fn handle_xr(
+        &mut self,
+        xr: Xr,
+        from: Option<SocketAddr>,
+        now: Instant,
+        ntp_time: SystemTime,
+        replies: &mut Vec<RtcpRecvReply>,
+    ) {
+        if self.local_senders.contains_key(&xr.ssrc) || self.local_receivers.contains_key(&xr.ssrc)
+        {
+            return;
+        }
+
+        for block in xr.blocks {
+            match block {
+                XrBlock::Rrtr(ntp_timestamp) => {
+                    if let Some(source) = self.remote_senders.get_mut(&xr.ssrc) {
+                        source.set_rtcp_from(from);
+                        source.set_last_activity(now);
+                        source.set_last_received_rrtr(ntp_timestamp, ntp_time);
+                    } else {
+                        let source = self.remote_receivers.entry(xr.ssrc).or_insert_with(|| {
+                            replies.push(RtcpRecvReply::NewSsrc(xr.ssrc));
+                            RemoteReceiveSource::new(xr.ssrc)
+                        });
+                        source.set_rtcp_from(from);
+                        source.set_last_activity(now);
+                        source.set_last_received_rrtr(ntp_timestamp, ntp_time);
+                    }
+                }
+                XrBlock::Dlrr(sub_blocks) => {
+                    for sub_block in sub_blocks {
+                        if let Some(source) = self.local_senders.get_mut(&sub_block.ssrc) {
+                            source.add_received_dlrr(xr.ssrc, sub_block, ntp_time);
+                        } else if let Some(source) = self.local_receivers.get_mut(&sub_block.ssrc) {
+                            source.add_received_dlrr(xr.ssrc, sub_block, ntp_time);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
     fn handle_remote_request_key_unit(
         &mut self,
         now: Instant,
@@ -835,7 +1206,7 @@ impl Session {
                         // get the clock-rate for this source
                         last_rtp_ts + sender
                             .payload_type()
-                            .and_then(|pt| self.clock_rate_from_pt(pt))
+                            .and_then(|pt| self.clock_rate_from_ssrc_pt(sender.ssrc(), pt))
                             .and_then(|clock_rate| {
                                 // assume that the rtp times and clock times advance at a rate
                                 // close to 1.0 and do a direct linear extrapolation to get the rtp
@@ -973,6 +1344,52 @@ impl Session {
         rtcp
     }
 
+    fn generate_xr<'a>(
+        &self,
+        rtcp: CompoundBuilder<'a>,
+        ntp_now: SystemTime,
+        minimum: bool, // RFC 4585
+    ) -> CompoundBuilder<'a> {
+        // Don't include in an early reduced-size RTCP packet
+        if minimum && self.reduced_size_rtcp_allowed() {
+            return rtcp;
+        }
+
+        let Some(ssrc) = self.internal_rtcp_sender_src else {
+            return rtcp;
+        };
+
+        let ntp_time = system_time_to_ntp_time_u64(ntp_now);
+        let dlrr = self
+            .remote_senders
+            .values()
+            .filter_map(|source| {
+                source
+                    .last_received_rrtr()
+                    .map(|rrtr| rrtr.dlrr_sub_block(source.ssrc(), ntp_time))
+            })
+            .chain(self.remote_receivers.values().filter_map(|source| {
+                source
+                    .last_received_rrtr()
+                    .map(|rrtr| rrtr.dlrr_sub_block(source.ssrc(), ntp_time))
+            }))
+            .collect::<Vec<_>>();
+
+        if !self.rtcp_xr_rrtr && dlrr.is_empty() {
+            return rtcp;
+        }
+
+        let mut xr = XrBuilder::new(ssrc);
+        if self.rtcp_xr_rrtr {
+            xr = xr.add_block(XrBlock::Rrtr(ntp_time.as_u64()));
+        }
+        if !dlrr.is_empty() {
+            xr = xr.add_block(XrBlock::Dlrr(dlrr));
+        }
+
+        rtcp.add_packet(xr)
+    }
+
     fn generate_sdes<'a>(
         &self,
         rtcp: CompoundBuilder<'a>,
@@ -1120,43 +1537,102 @@ impl Session {
         rtcp
     }
 
-    // RFC 3550 6.3.5
-    // FIXME: we should surface this information to the element in order
-    // to perform clean up of the sync context
-    fn handle_timeouts(&mut self, now: Instant) {
-        trace!("handling rtcp timeouts");
-        let td = RTCP_SOURCE_TIMEOUT_N_INTERVALS
-            * self
-                .deterministic_rtcp_duration(false)
-                .max(Duration::from_secs(5));
+    // Deletes all sources that are too old, returns whether any remote source was removed
+    fn timeout_members(&mut self, now: Instant) -> bool {
+        let td = match self.member_timeout {
+            SourceTimeout::Intervals(n) => {
+                n * self
+                    .deterministic_rtcp_duration(false)
+                    .max(Duration::from_secs(5))
+            }
+            SourceTimeout::Duration(duration) => duration,
+        };
 
-        // delete all sources that are too old
         self.local_receivers
             .retain(|_ssrc, source| now - source.last_activity() < td);
-        self.remote_senders
-            .retain(|_ssrc, source| now - source.last_activity() < td);
-        self.remote_receivers
-            .retain(|_ssrc, source| now - source.last_activity() < td);
+        let mut timed_out = vec![];
+        self.remote_senders.retain(|&ssrc, source| {
+            let keep = now - source.last_activity() < td;
+            if !keep {
+                timed_out.push(ssrc);
+            }
+            keep
+        });
+        self.remote_receivers.retain(|&ssrc, source| {
+            let keep = now - source.last_activity() < td;
+            if !keep && !timed_out.contains(&ssrc) {
+                timed_out.push(ssrc);
+            }
+            keep
+        });
+        for &ssrc in &timed_out {
+            debug!("remote ssrc {ssrc} timed out");
+            self.bundle_ids.remove(&ssrc);
+        }
+        self.address_ssrcs
+            .retain(|_, mapped| !timed_out.contains(mapped));
+        // The group size is only valid as long as the distribution source keeps reporting it
+        if self
+            .rsi_group_size
+            .is_some_and(|(_, last_rsi)| now - last_rsi >= td)
+        {
+            debug!("Group size of source-specific multicast session timed out");
+            self.rsi_group_size = None;
+        }
+        let any_timed_out = !timed_out.is_empty();
+        self.timed_out_ssrcs.extend(timed_out);
+
+        any_timed_out
+    }
+
+    /// Remote ssrcs that timed out since the last call, either while producing RTCP or now.
+    /// Has to be called regularly by the receiver as RTCP is not necessarily produced for the
+    /// session.
+    ///
+    /// Also returns whether the RTCP timer was reconsidered, in which case
+    /// `poll_rtcp_send_timeout()` has to be called again.
+    pub fn poll_source_timeouts(&mut self, now: Instant) -> (Vec<u32>, bool) {
+        let n_members = self.n_members();
+        let mut reconsidered = false;
+        // Outside of RTCP handling, the timer has to be reconsidered for the changed number
+        // of members
+        if self.timeout_members(now) {
+            reconsidered = self.rtcp_reverse_consideration(n_members, now);
+        }
+
+        (std::mem::take(&mut self.timed_out_ssrcs), reconsidered)
+    }
+
+    // RFC 3550 6.3.5
+    // The timed out remote sources are reported by poll_source_timeouts()
+    fn handle_timeouts(&mut self, now: Instant) {
+        trace!("handling rtcp timeouts");
+        self.timeout_members(now);
 
         // There is a SHOULD about performing RTCP reverse timer consideration here if any sources
         // were timed out, however we are here before calculating the next rtcp timeout so are
         // covered already with a changing number of members.
-        // If we call this outside of rtcp handling, then rtcp_reverse_consideration() would need to
-        // be called.
+        // Outside of rtcp handling, poll_source_timeouts() calls rtcp_reverse_consideration().
 
         // switch senders that haven't sent in a while to receivers
-        if self.last_rtcp_sent_times.len() >= 2 {
-            let two_rtcp_ago = *self.last_rtcp_sent_times.back().unwrap();
+        let sender_cutoff = match self.sender_timeout {
+            SourceTimeout::Intervals(n) => self
+                .last_rtcp_sent_times
+                .get(n.max(1) as usize - 1)
+                .copied(),
+            SourceTimeout::Duration(duration) => now.checked_sub(duration),
+        };
+        if let Some(sender_cutoff) = sender_cutoff {
             let removed_senders = self
                 .local_senders
                 .iter()
                 .filter_map(|(&ssrc, source)| {
                     trace!(
-                        "now: {now:?}, last activity: {:?} two_rtcp_ago: {:?}",
+                        "now: {now:?}, last activity: {:?} sender cutoff: {:?}",
                         source.last_activity(),
-                        two_rtcp_ago
+                        sender_cutoff
                     );
-                    if source.last_activity() < two_rtcp_ago {
+                    if source.last_activity() < sender_cutoff {
                         Some(ssrc)
                     } else {
                         None
@@ -1190,6 +1666,19 @@ impl Session {
             trace!("no next check time yet");
             return None;
         };
+
+        if self.rtcp_bandwidth.is_disabled() {
+            // No RTCP is sent at all but sources still need to time out
+            self.next_early_rtcp_time = None;
+            if now < next_rtcp_send {
+                return None;
+            }
+            trace!("RTCP disabled, only handling timeouts");
+            self.handle_timeouts(now);
+            self.last_rtcp_handle_time = Some(now);
+            self.next_rtcp_send.time = Some(now + RTCP_MIN_REPORT_INTERVAL);
+            return self.pending_rtcp_send.pop_back();
+        }
         let is_early = self.next_early_rtcp_time.is_some() && !self.last_rtcp_sent_times.is_empty();
 
         if is_early {
@@ -1233,6 +1722,7 @@ impl Session {
             rtcp = self.generate_sr(rtcp, now, ntp_now, is_early, &mut ssrcs_reported);
             rtcp = self.generate_rr(rtcp, now, ntp_now, is_early, &mut ssrcs_reported);
             rtcp = self.generate_sdes(rtcp, is_early);
+            rtcp = self.generate_xr(rtcp, ntp_now, is_early);
             rtcp = self.generate_pli(rtcp, now);
             rtcp = self.generate_fir(rtcp, now);
             rtcp = self.generate_bye(rtcp, now);
@@ -1271,7 +1761,11 @@ impl Session {
             };
             trace!("next rtcp time {:?}", self.next_rtcp_send.time);
             self.last_rtcp_sent_times.push_front(now);
-            while self.last_rtcp_sent_times.len() > 2 {
+            let max_rtcp_sent_times = match self.sender_timeout {
+                SourceTimeout::Intervals(n) => (n as usize).max(2),
+                SourceTimeout::Duration(_) => 2,
+            };
+            while self.last_rtcp_sent_times.len() > max_rtcp_sent_times {
                 self.last_rtcp_sent_times.pop_back();
             }
             self.bye_state = None;
@@ -1288,6 +1782,15 @@ impl Session {
             self.next_early_rtcp_time,
             self.next_rtcp_send.time
         );
+        if self.rtcp_bandwidth.is_disabled() {
+            // Only wake up for handling timeouts
+            return Some(
+                *self
+                    .next_rtcp_send
+                    .time
+                    .get_or_insert(now + RTCP_MIN_REPORT_INTERVAL),
+            );
+        }
         if let Some(early_time) = self.next_early_rtcp_time {
             return Some(early_time);
         }
@@ -1311,19 +1814,45 @@ impl Session {
     fn deterministic_rtcp_duration(&self, we_sent: bool) -> Duration {
         let n_senders = self.n_senders() as u64;
         let n_members = self.n_members() as u64;
-        let session_bandwidth = self.session_bandwidth();
-        // 5% of the session bandwidth, or the minimum of 400B/s
-        let rtcp_bw = (session_bandwidth / 20).max(RTCP_MIN_BANDWIDTH);
-
-        let (n, rtcp_bw) = if n_senders * 4 <= n_members {
-            if we_sent {
-                (n_senders, rtcp_bw / 4)
+        let session_bandwidth = self
+            .rtcp_bandwidth
+            .session
+            .unwrap_or_else(|| self.session_bandwidth());
+        // 5% of the session bandwidth by default, or the minimum of 400B/s
+        let fraction_bw = ((session_bandwidth as f64 * self.rtcp_bandwidth.fraction) as usize)
+            .max(RTCP_MIN_BANDWIDTH);
+
+        let (n, rtcp_bw) =
+            if self.rtcp_bandwidth.senders.is_none() && self.rtcp_bandwidth.receivers.is_none() {
+                // A quarter of the RTCP bandwidth is reserved for the senders if they are less
+                // than a quarter of the members.
+                let rtcp_bw = fraction_bw;
+                if n_senders * 4 <= n_members {
+                    if we_sent {
+                        (n_senders, rtcp_bw / 4)
+                    } else {
+                        (n_members - n_senders, rtcp_bw / 4 * 3)
+                    }
+                } else {
+                    (n_members, rtcp_bw)
+                }
             } else {
-                (n_members - n_senders, rtcp_bw / 4 * 3)
-            }
-        } else {
-            (n_members, rtcp_bw)
-        };
+                // RFC 3556: separately configured sender and receiver bandwidths, the senders get
+                // their share if they are at most the same fraction of the members. A bandwidth
+                // that is not configured is derived from the fraction like above.
+                let senders_bw = self.rtcp_bandwidth.senders.unwrap_or(fraction_bw / 4);
+                let receivers_bw = self.rtcp_bandwidth.receivers.unwrap_or(fraction_bw / 4 * 3);
+                let rtcp_bw = senders_bw + receivers_bw;
+                if n_senders as u128 * rtcp_bw as u128 <= n_members as u128 * senders_bw as u128 {
+                    if we_sent {
+                        (n_senders, senders_bw)
+                    } else {
+                        (n_members - n_senders, receivers_bw)
+                    }
+                } else {
+                    (n_members, rtcp_bw)
+                }
+            };
 
         let min_rtcp_interval = if we_sent {
             Duration::ZERO
@@ -1363,7 +1892,6 @@ impl Session {
     }
 
     fn session_bandwidth(&self) -> usize {
-        // TODO: allow to be externally provided
         self.local_senders
             .values()
             .filter(|source| source.state() == SourceState::Normal)
@@ -1518,6 +2046,104 @@ impl Session {
         interval
     }
 
+    /// Change the SSRC used for sending after a collision of the local `ssrc` with another
+    /// participant, and schedule a BYE for `ssrc`.
+    ///
+    /// Returns the new SSRC, or `None` if `ssrc` is not in use for sending anymore.
+    pub fn change_local_ssrc(&mut self, ssrc: u32) -> Option<u32> {
+        let upstream_ssrc = self
+            .ssrc_rewrites
+            .iter()
+            .find_map(|(&upstream_ssrc, &rewritten)| (rewritten == ssrc).then_some(upstream_ssrc))
+            .unwrap_or(ssrc);
+        if upstream_ssrc == ssrc && self.ssrc_rewrites.contains_key(&ssrc) {
+            debug!("ssrc {ssrc:#08x} was already changed");
+            return None;
+        }
+
+        let new_ssrc = loop {
+            let new_ssrc = generate_ssrc();
+            if !self.ssrcs().any(|ssrc| ssrc == new_ssrc)
+                && !self.ssrc_rewrites.contains_key(&new_ssrc)
+            {
+                break new_ssrc;
+            }
+        };
+
+        if let Some(source) = self.local_senders.get_mut(&ssrc) {
+            source.mark_bye("SSRC collision");
+        }
+        self.ssrc_rewrites.insert(upstream_ssrc, new_ssrc);
+        info!("changing send ssrc {ssrc:#08x} to {new_ssrc:#08x} after collision");
+
+        Some(new_ssrc)
+    }
+
+    /// The SSRC to use for sending packets that were produced with `ssrc`
+    pub fn send_ssrc(&self, ssrc: u32) -> u32 {
+        self.ssrc_rewrites.get(&ssrc).copied().unwrap_or(ssrc)
+    }
+
+    /// The SSRC under which RTP packets with `ssrc` received from `from` are handled.
+    ///
+    /// If sources are separated by address and another address already sends with `ssrc`, a new
+    /// SSRC is allocated for this address and the packets have to be rewritten to use it.
+    pub fn recv_ssrc(&mut self, ssrc: u32, from: SocketAddr) -> u32 {
+        if !self.separate_sources_by_address || self.local_senders.contains_key(&ssrc) {
+            return ssrc;
+        }
+
+        if let Some(&mapped) = self.address_ssrcs.get(&(from, ssrc)) {
+            if self.ssrcs().any(|ssrc| ssrc == mapped) {
+                return mapped;
+            }
+            // The source timed out in the meantime
+            self.address_ssrcs.remove(&(from, ssrc));
+        }
+
+        let Some(source_from) = self
+            .remote_senders
+            .get(&ssrc)
+            .and_then(|source| source.rtp_from())
+        else {
+            return ssrc;
+        };
+        if source_from == from {
+            return ssrc;
+        }
+
+        let new_ssrc = loop {
+            let new_ssrc = generate_ssrc();
+            if !self.ssrcs().any(|ssrc| ssrc == new_ssrc)
+                && !self.address_ssrcs.values().any(|&ssrc| ssrc == new_ssrc)
+            {
+                break new_ssrc;
+            }
+        };
+        info!(
+            "ssrc {ssrc:#08x} from {from:?} already used by {source_from:?}, handling as ssrc {new_ssrc:#08x}"
+        );
+        self.address_ssrcs.insert((from, ssrc), new_ssrc);
+
+        new_ssrc
+    }
+
+    /// The SSRC under which RTCP packets referring to `ssrc` received from `from` are handled.
+    ///
+    /// RTCP usually arrives from a different port than RTP so only the IP address is compared.
+    pub fn recv_rtcp_ssrc(&self, ssrc: u32, from: SocketAddr) -> u32 {
+        if !self.separate_sources_by_address {
+            return ssrc;
+        }
+
+        self.address_ssrcs
+            .iter()
+            .find_map(|(&(addr, orig_ssrc), &mapped)| {
+                (orig_ssrc == ssrc && addr.ip() == from.ip()).then_some(mapped)
+            })
+            .unwrap_or(ssrc)
+    }
+
     /// Retrieve a list of all ssrc's currently handled by this session
     pub fn ssrcs(&self) -> impl Iterator<Item = u32> + '_ {
         self.local_senders
@@ -1548,6 +2174,62 @@ impl Session {
         self.remote_receivers.get(&ssrc)
     }
 
+    /// Round trip time to the remote `ssrc`, calculated from the most recent report block it sent
+    /// about one of our local senders
+    pub fn round_trip_time(&self, ssrc: u32) -> Option<Duration> {
+        self.local_senders
+            .values()
+            .filter_map(|source| {
+                source
+                    .received_report_blocks()
+                    .find_map(|(sender_ssrc, rb)| (sender_ssrc == ssrc).then_some(rb))
+            })
+            .filter(|rb| rb.rb.last_sr_ntp_time() != 0)
+            .max_by_key(|rb| rb.receive_time)
+            .map(ReceivedRb::round_trip_time)
+    }
+
+    /// Track a sent packet with the provided transport-wide sequence number for transport-wide
+    /// congestion control
+    pub fn twcc_packet_sent(&mut self, seqnum: u16, size: usize, now: Instant) {
+        self.twcc.packet_sent(seqnum, size, now);
+    }
+
+    /// Transport-wide congestion control statistics as of the last received feedback
+    pub fn twcc_stats(&self) -> Option<TwccStats> {
+        self.twcc.stats()
+    }
+
+    /// Whether any remote sender has neither sent a BYE nor timed out yet
+    pub fn has_active_remote_senders(&self) -> bool {
+        self.remote_senders
+            .values()
+            .any(|source| source.state() != SourceState::Bye)
+    }
+
+    /// Associate a remote ssrc with a MID, e.g. from the MID RTP header extension
+    pub fn set_ssrc_mid(&mut self, ssrc: u32, mid: &str) {
+        let ids = self.bundle_ids.entry(ssrc).or_default();
+        if ids.mid.as_deref() != Some(mid) {
+            debug!("ssrc {ssrc:#08x} is associated with mid {mid}");
+            ids.mid = Some(mid.to_owned());
+        }
+    }
+
+    /// Associate a remote ssrc with a RtpStreamId, e.g. from the RtpStreamId RTP header extension
+    pub fn set_ssrc_rid(&mut self, ssrc: u32, rid: &str) {
+        let ids = self.bundle_ids.entry(ssrc).or_default();
+        if ids.rid.as_deref() != Some(rid) {
+            debug!("ssrc {ssrc:#08x} is associated with rid {rid}");
+            ids.rid = Some(rid.to_owned());
+        }
+    }
+
+    /// The MID and RtpStreamId a remote ssrc is associated with, if any
+    pub fn bundle_ids(&self, ssrc: u32) -> Option<&BundleIds> {
+        self.bundle_ids.get(&ssrc)
+    }
+
     pub fn mut_local_send_source_by_ssrc(&mut self, ssrc: u32) -> Option<&mut LocalSendSource> {
         self.local_senders.get_mut(&ssrc)
     }
@@ -1673,12 +2355,61 @@ pub(crate) mod tests {
         }
     }
 
-    pub fn generate_rtp_packet(ssrc: u32, seq_no: u16, rtp_ts: u32, payload_len: usize) -> Vec<u8> {
+    #[test]
+    fn receive_configured_probation() {
         init_logs();
-        let mut rtp_data = [0; 1200];
-        let payload = vec![1; payload_len];
-        let len = RtpPacketBuilder::new()
-            .payload_type(TEST_PT)
+        let mut session = Session::new();
+        session.set_probation(3);
+        let now = Instant::now();
+        let mut held = vec![];
+        for seq_no in 0..3 {
+            let rtp_data = generate_rtp_packet(0x12345678, seq_no, 0, 4);
+            let packet = RtpPacket::parse(&rtp_data).unwrap();
+            let mut ret = session.handle_recv(&packet, None, now);
+            if seq_no == 0 {
+                assert_eq!(ret, RecvReply::NewSsrc(0x12345678, TEST_PT));
+                ret = session.handle_recv(&packet, None, now);
+            }
+            match seq_no {
+                0 | 1 => {
+                    let RecvReply::Hold(id) = ret else {
+                        unreachable!();
+                    };
+                    held.push(id);
+                }
+                2 => {
+                    while let RecvReply::Forward(id) = ret {
+                        let pos = held.iter().position(|&held_id| held_id == id).unwrap();
+                        held.remove(pos);
+                        ret = session.handle_recv(&packet, None, now);
+                    }
+                    assert!(held.is_empty());
+                    assert_eq!(ret, RecvReply::Passthrough);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        // New sources are forwarded immediately without probation
+        session.set_probation(0);
+        let rtp_data = generate_rtp_packet(0x87654321, 100, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(
+            session.handle_recv(&packet, None, now),
+            RecvReply::NewSsrc(0x87654321, TEST_PT)
+        );
+        assert_eq!(
+            session.handle_recv(&packet, None, now),
+            RecvReply::Passthrough
+        );
+    }
+
+    pub fn generate_rtp_packet(ssrc: u32, seq_no: u16, rtp_ts: u32, payload_len: usize) -> Vec<u8> {
+        init_logs();
+        let mut rtp_data = [0; 1200];
+        let payload = vec![1; payload_len];
+        let len = RtpPacketBuilder::new()
+            .payload_type(TEST_PT)
             .ssrc(ssrc)
             .sequence_number(seq_no)
             .timestamp(rtp_ts)
@@ -1913,6 +2644,68 @@ pub(crate) mod tests {
         assert_eq!(n_rb_ssrcs, ssrcs.len());
     }
 
+    #[test]
+    fn send_configured_sdes() {
+        init_logs();
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        let generated_cname = session.cname().to_owned();
+
+        let now = Instant::now();
+        let ntp_now = SystemTime::now();
+        let ssrc = 0x12345678;
+
+        let (now, ntp_now) =
+            increment_rtcp_times(now, session.poll_rtcp_send_timeout(now).unwrap(), ntp_now);
+        let (now, ntp_now) = increment_rtcp_times(now, now + RTCP_MIN_REPORT_INTERVAL, ntp_now);
+
+        let rtp_data = generate_rtp_packet(ssrc, 100, 4, 1024);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(
+            session.handle_send(&packet, now),
+            SendReply::NewSsrc(ssrc, TEST_PT)
+        );
+        assert_eq!(session.handle_send(&packet, now), SendReply::Passthrough);
+
+        // Without a CNAME the generated one is kept
+        session.set_sdes(HashMap::from([(SdesItem::TOOL, String::from("test"))]));
+        assert_eq!(session.cname(), generated_cname);
+
+        session.set_sdes(HashMap::from([
+            (SdesItem::CNAME, String::from("user@example.com")),
+            (SdesItem::NAME, String::from("User")),
+        ]));
+        assert_eq!(session.cname(), "user@example.com");
+
+        let rtcp_data = session.poll_rtcp_send(now, ntp_now).unwrap();
+        let RtcpSendReply::Data(rtcp_data) = rtcp_data else {
+            unreachable!();
+        };
+        let rtcp = Compound::parse(&rtcp_data).unwrap();
+        let mut items = HashMap::new();
+        for p in rtcp {
+            match p {
+                Ok(Packet::Sr(_)) => (),
+                Ok(Packet::Sdes(sdes)) => {
+                    for chunk in sdes.chunks() {
+                        assert_eq!(chunk.ssrc(), ssrc);
+                        for item in chunk.items() {
+                            items.insert(item.type_(), item.value().to_vec());
+                        }
+                    }
+                }
+                _ => unreachable!(),
+            }
+        }
+        assert_eq!(
+            items,
+            HashMap::from([
+                (SdesItem::CNAME, b"user@example.com".to_vec()),
+                (SdesItem::NAME, b"User".to_vec()),
+            ])
+        );
+    }
+
     #[test]
     fn receive_two_ssrc_sr() {
         init_logs();
@@ -2361,6 +3154,42 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn ssrc_collision_change_ssrc() {
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        let now = Instant::now();
+        let ssrc = 0x11223344;
+
+        let rtp_data = generate_rtp_packet(ssrc, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(
+            session.handle_send(&packet, now),
+            SendReply::NewSsrc(ssrc, TEST_PT)
+        );
+        assert_eq!(session.handle_send(&packet, now), SendReply::Passthrough);
+
+        let new_ssrc = session.change_local_ssrc(ssrc).unwrap();
+        assert_ne!(new_ssrc, ssrc);
+        assert_eq!(session.send_ssrc(ssrc), new_ssrc);
+        let source = session.local_send_source_by_ssrc(ssrc).unwrap();
+        assert_eq!(source.state(), SourceState::Bye);
+        // already changed
+        assert_eq!(session.change_local_ssrc(ssrc), None);
+
+        let rtp_data = generate_rtp_packet(new_ssrc, 501, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(
+            session.handle_send(&packet, now),
+            SendReply::NewSsrc(new_ssrc, TEST_PT)
+        );
+
+        // another collision on the new ssrc changes the ssrc again
+        let newer_ssrc = session.change_local_ssrc(new_ssrc).unwrap();
+        assert_ne!(newer_ssrc, new_ssrc);
+        assert_eq!(session.send_ssrc(ssrc), newer_ssrc);
+    }
+
     #[test]
     fn ssrc_collision_third_party() {
         let mut session = Session::new();
@@ -2391,6 +3220,71 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn separate_sources_by_address() {
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        session.set_separate_sources_by_address(true);
+        let now = Instant::now();
+        let ssrc = 0x11223344;
+        let from1 = "127.0.0.1:8080".parse().unwrap();
+        let from2 = "127.0.0.2:8080".parse().unwrap();
+
+        let rtp_data = generate_rtp_packet(ssrc, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        session_recv_first_packet_disable_probation(&mut session, &packet, now);
+        assert_eq!(session.recv_ssrc(ssrc, from1), ssrc);
+        assert_eq!(
+            session.handle_recv(&packet, Some(from1), now),
+            RecvReply::Passthrough
+        );
+
+        // the same ssrc from a different address gets its own ssrc
+        let new_ssrc = session.recv_ssrc(ssrc, from2);
+        assert_ne!(new_ssrc, ssrc);
+        let rtp_data = generate_rtp_packet(new_ssrc, 700, 0, 4);
+        let packet2 = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(
+            session.handle_recv(&packet2, Some(from2), now),
+            RecvReply::NewSsrc(new_ssrc, TEST_PT)
+        );
+        assert_eq!(session.recv_ssrc(ssrc, from2), new_ssrc);
+        assert_eq!(session.recv_ssrc(ssrc, from1), ssrc);
+
+        // RTCP from the second host on another port maps to the same ssrc
+        assert_eq!(
+            session.recv_rtcp_ssrc(ssrc, "127.0.0.2:8081".parse().unwrap()),
+            new_ssrc
+        );
+        assert_eq!(
+            session.recv_rtcp_ssrc(ssrc, "127.0.0.1:8081".parse().unwrap()),
+            ssrc
+        );
+
+        // packets from the original address are unaffected
+        assert_eq!(
+            session.handle_recv(&packet, Some(from1), now),
+            RecvReply::Passthrough
+        );
+
+        // the mapping is forgotten once the separated source timed out
+        session.set_member_timeout(SourceTimeout::Duration(Duration::from_secs(1)));
+        let later = now + Duration::from_millis(500);
+        let rtp_data = generate_rtp_packet(ssrc, 501, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(
+            session.handle_recv(&packet, Some(from1), later),
+            RecvReply::Passthrough
+        );
+        let (timed_out, _) = session.poll_source_timeouts(now + Duration::from_millis(1200));
+        assert_eq!(timed_out, [new_ssrc]);
+        assert!(session.address_ssrcs.is_empty());
+        assert_eq!(
+            session.recv_rtcp_ssrc(ssrc, "127.0.0.2:8081".parse().unwrap()),
+            ssrc
+        );
+    }
+
     #[test]
     fn bye_remote_sender() {
         let mut session = Session::new();
@@ -2430,6 +3324,418 @@ pub(crate) mod tests {
         assert_eq!(source.state(), SourceState::Bye);
     }
 
+    #[test]
+    fn remote_sender_timeout() {
+        init_logs();
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        let mut now = Instant::now();
+        let mut ntp_now = SystemTime::now();
+        let ssrc = 0x11223344;
+
+        let rtp_data = generate_rtp_packet(ssrc, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        session_recv_first_packet_disable_probation(&mut session, &packet, now);
+        assert_eq!(
+            session.handle_recv(&packet, None, now),
+            RecvReply::Passthrough
+        );
+
+        // Without any further packets the remote sender times out after a few RTCP intervals
+        let mut timed_out = false;
+        for _ in 0..20 {
+            let rtcp_data;
+            (rtcp_data, now, ntp_now) = next_rtcp_packet(&mut session, now, ntp_now);
+            assert!(matches!(rtcp_data, RtcpSendReply::Data(_)));
+            let (timed_out_ssrcs, _) = session.poll_source_timeouts(now);
+            if !timed_out_ssrcs.is_empty() {
+                assert_eq!(timed_out_ssrcs, [ssrc]);
+                timed_out = true;
+                break;
+            }
+        }
+        assert!(timed_out);
+        assert!(session.remote_send_source_by_ssrc(ssrc).is_none());
+    }
+
+    #[test]
+    fn remote_sender_member_timeout_duration() {
+        init_logs();
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        session.set_member_timeout(SourceTimeout::Duration(Duration::from_secs(1)));
+        let mut now = Instant::now();
+        let mut ntp_now = SystemTime::now();
+        let ssrc = 0x11223344;
+
+        let rtp_data = generate_rtp_packet(ssrc, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        session_recv_first_packet_disable_probation(&mut session, &packet, now);
+        assert_eq!(
+            session.handle_recv(&packet, None, now),
+            RecvReply::Passthrough
+        );
+        session.set_ssrc_mid(ssrc, "audio");
+
+        // The first RTCP interval is already longer than the configured timeout
+        let mut timed_out = false;
+        for _ in 0..2 {
+            let rtcp_data;
+            (rtcp_data, now, ntp_now) = next_rtcp_packet(&mut session, now, ntp_now);
+            assert!(matches!(rtcp_data, RtcpSendReply::Data(_)));
+            let (timed_out_ssrcs, _) = session.poll_source_timeouts(now);
+            if !timed_out_ssrcs.is_empty() {
+                assert_eq!(timed_out_ssrcs, [ssrc]);
+                timed_out = true;
+                break;
+            }
+        }
+        assert!(timed_out);
+        assert!(session.remote_send_source_by_ssrc(ssrc).is_none());
+        assert_eq!(session.bundle_ids(ssrc), None);
+    }
+
+    #[test]
+    fn remote_sender_timeout_without_rtcp() {
+        init_logs();
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        session.set_member_timeout(SourceTimeout::Duration(Duration::from_secs(1)));
+        let now = Instant::now();
+        let ssrc = 0x11223344;
+
+        let rtp_data = generate_rtp_packet(ssrc, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        session_recv_first_packet_disable_probation(&mut session, &packet, now);
+        assert_eq!(
+            session.handle_recv(&packet, None, now),
+            RecvReply::Passthrough
+        );
+
+        // A receive-only session never produces RTCP but its sources still time out
+        let (timed_out_ssrcs, _) = session.poll_source_timeouts(now + Duration::from_millis(500));
+        assert!(timed_out_ssrcs.is_empty());
+        let (timed_out_ssrcs, _) = session.poll_source_timeouts(now + Duration::from_secs(2));
+        assert_eq!(timed_out_ssrcs, [ssrc]);
+        assert!(session.remote_send_source_by_ssrc(ssrc).is_none());
+
+        // Only reported once
+        let (timed_out_ssrcs, _) = session.poll_source_timeouts(now + Duration::from_secs(3));
+        assert!(timed_out_ssrcs.is_empty());
+    }
+
+    #[test]
+    fn active_remote_sender_no_timeout() {
+        init_logs();
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        let mut now = Instant::now();
+        let mut ntp_now = SystemTime::now();
+        let ssrc = 0x11223344;
+
+        let rtp_data = generate_rtp_packet(ssrc, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        session_recv_first_packet_disable_probation(&mut session, &packet, now);
+
+        // A remote sender that keeps sending RTP packets without any RTCP is not timed out
+        for i in 0..20 {
+            let rtp_data = generate_rtp_packet(ssrc, 501 + i, 0, 4);
+            let packet = RtpPacket::parse(&rtp_data).unwrap();
+            assert_eq!(
+                session.handle_recv(&packet, None, now),
+                RecvReply::Passthrough
+            );
+
+            let rtcp_data;
+            (rtcp_data, now, ntp_now) = next_rtcp_packet(&mut session, now, ntp_now);
+            assert!(matches!(rtcp_data, RtcpSendReply::Data(_)));
+        }
+        assert!(session.remote_send_source_by_ssrc(ssrc).is_some());
+    }
+
+    #[test]
+    fn rtcp_xr_rrtr_dlrr() {
+        init_logs();
+        let mut receiver = Session::new();
+        receiver.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        receiver.set_rtcp_xr_rrtr(true);
+        let mut peer = Session::new();
+        let now = Instant::now();
+        let ntp_now = SystemTime::now();
+        let delay = Duration::from_millis(100);
+
+        let rtp_data = generate_rtp_packet(0x11223344, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        session_recv_first_packet_disable_probation(&mut receiver, &packet, now);
+
+        // The receive-only session includes its reference time in an XR packet
+        let (rtcp_data, now, ntp_now) = next_rtcp_packet(&mut receiver, now, ntp_now);
+        let RtcpSendReply::Data(rtcp_data) = rtcp_data else {
+            unreachable!();
+        };
+        let receiver_ssrc = receiver.internal_ssrc().unwrap();
+        let rtcp = Compound::parse(&rtcp_data).unwrap();
+        let mut found_rrtr = false;
+        for p in rtcp {
+            if let Ok(Packet::Unknown(unknown)) = p {
+                let xr = Xr::parse(unknown.data()).unwrap();
+                assert_eq!(xr.ssrc, receiver_ssrc);
+                assert_eq!(
+                    xr.blocks,
+                    vec![XrBlock::Rrtr(system_time_to_ntp_time_u64(ntp_now).as_u64())]
+                );
+                found_rrtr = true;
+            }
+        }
+        assert!(found_rrtr);
+
+        let rtcp = Compound::parse(&rtcp_data).unwrap();
+        peer.handle_rtcp_recv(rtcp, rtcp_data.len(), None, now + delay, ntp_now + delay);
+        assert!(peer
+            .remote_receive_source_by_ssrc(receiver_ssrc)
+            .unwrap()
+            .last_received_rrtr()
+            .is_some());
+
+        // The peer replies with a DLRR sub-block for the receiver
+        let (rtcp_data, now, ntp_now) = next_rtcp_packet(&mut peer, now + delay, ntp_now + delay);
+        let RtcpSendReply::Data(rtcp_data) = rtcp_data else {
+            unreachable!();
+        };
+        let rtcp = Compound::parse(&rtcp_data).unwrap();
+        receiver.handle_rtcp_recv(rtcp, rtcp_data.len(), None, now + delay, ntp_now + delay);
+
+        let source = receiver
+            .local_receive_source_by_ssrc(receiver_ssrc)
+            .unwrap();
+        let dlrr = source.received_dlrr().collect::<Vec<_>>();
+        assert_eq!(dlrr.len(), 1);
+        assert_eq!(dlrr[0].0, peer.internal_ssrc().unwrap());
+        let rtt = dlrr[0].1.round_trip_time();
+        assert!(
+            rtt > 2 * delay - Duration::from_millis(1)
+                && rtt < 2 * delay + Duration::from_millis(1),
+            "{rtt:?}"
+        );
+    }
+
+    #[test]
+    fn rtcp_bandwidth() {
+        init_logs();
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        session.set_min_rtcp_interval(Duration::ZERO);
+        let now = Instant::now();
+
+        let rtp_data = generate_rtp_packet(0x11223344, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        session_recv_first_packet_disable_probation(&mut session, &packet, now);
+        // Based on the minimum RTCP bandwidth of 400B/s
+        let interval = session.deterministic_rtcp_duration(false);
+
+        // 5% of 80kB/s is 10 times the minimum RTCP bandwidth
+        session.set_rtcp_bandwidth(RtcpBandwidth {
+            session: Some(80_000),
+            ..Default::default()
+        });
+        let session_interval = session.deterministic_rtcp_duration(false);
+        assert!((interval.as_secs_f64() / session_interval.as_secs_f64() - 10.).abs() < 0.01);
+
+        // The same RTCP bandwidth through the fraction
+        session.set_rtcp_bandwidth(RtcpBandwidth {
+            session: Some(8_000),
+            fraction: 0.5,
+            ..Default::default()
+        });
+        assert_eq!(session.deterministic_rtcp_duration(false), session_interval);
+
+        // All of it for the receivers. The sender is more than 0% of the members so the
+        // bandwidth is shared by all members.
+        session.set_rtcp_bandwidth(RtcpBandwidth {
+            senders: Some(0),
+            receivers: Some(4_000),
+            ..Default::default()
+        });
+        assert_eq!(session.deterministic_rtcp_duration(false), session_interval);
+        assert_eq!(session.rtcp_bandwidth().receivers, Some(4_000));
+
+        // The derived sender bandwidth is based on the minimum RTCP bandwidth, a quarter of
+        // 400B/s
+        session.set_rtcp_bandwidth(RtcpBandwidth {
+            receivers: Some(300),
+            ..Default::default()
+        });
+        assert_eq!(session.deterministic_rtcp_duration(false), interval);
+
+        // No RTCP at all with both bandwidths set to zero, the session is only woken up for
+        // timeouts
+        session.set_rtcp_bandwidth(RtcpBandwidth {
+            senders: Some(0),
+            receivers: Some(0),
+            ..Default::default()
+        });
+        let wait = session.poll_rtcp_send_timeout(now).unwrap();
+        assert_eq!(wait, now + RTCP_MIN_REPORT_INTERVAL);
+        assert!(!matches!(
+            session.poll_rtcp_send(wait, SystemTime::now()),
+            Some(RtcpSendReply::Data(_))
+        ));
+        assert_eq!(
+            session.poll_rtcp_send_timeout(wait),
+            Some(wait + RTCP_MIN_REPORT_INTERVAL)
+        );
+    }
+
+    #[test]
+    fn rsi_group_size() {
+        init_logs();
+        let mut session = Session::new();
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        let now = Instant::now();
+        let ntp_now = SystemTime::now();
+
+        let rtp_data = generate_rtp_packet(0x11223344, 500, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        session_recv_first_packet_disable_probation(&mut session, &packet, now);
+        let interval = session.deterministic_rtcp_duration(false);
+
+        // RR of the distribution source followed by an RSI about the sender with a group size
+        // of 10000 and an average packet size of 100 bytes
+        let rtcp_data = [
+            0x80, 0xc9, 0x00, 0x01, 0x55, 0x66, 0x77, 0x88, // RR
+            0x80, 0xd1, 0x00, 0x06, 0x55, 0x66, 0x77, 0x88, 0x11, 0x22, 0x33, 0x44, // RSI
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // ntp timestamp
+            0x04, 0x02, 0x00, 0x64, 0x00, 0x00, 0x27, 0x10, // group and average packet size
+        ];
+        let rtcp = Compound::parse(&rtcp_data).unwrap();
+        session.handle_rtcp_recv(rtcp, rtcp_data.len(), None, now, ntp_now);
+        assert_eq!(session.rsi_group_size(), Some(10000));
+
+        // The RTCP interval scales with the size of the whole group
+        assert!(session.deterministic_rtcp_duration(false) > interval * 100);
+
+        // The group size times out like the sources once the distribution source stops
+        // reporting it
+        session.set_member_timeout(SourceTimeout::Duration(Duration::from_secs(30)));
+        session.poll_source_timeouts(now + Duration::from_secs(29));
+        assert_eq!(session.rsi_group_size(), Some(10000));
+        session.poll_source_timeouts(now + Duration::from_secs(30));
+        assert_eq!(session.rsi_group_size(), None);
+        assert!(session.deterministic_rtcp_duration(false) < interval * 100);
+    }
+
+    #[test]
+    fn ssrc_clock_rate() {
+        let mut session = Session::new();
+        assert_eq!(session.clock_rate_from_ssrc_pt(0x12345678, TEST_PT), None);
+
+        session.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        assert_eq!(
+            session.clock_rate_from_ssrc_pt(0x12345678, TEST_PT),
+            Some(TEST_CLOCK_RATE)
+        );
+
+        session.set_ssrc_clock_rate(0x12345678, 48000);
+        assert_eq!(
+            session.clock_rate_from_ssrc_pt(0x12345678, TEST_PT),
+            Some(48000)
+        );
+        assert_eq!(
+            session.clock_rate_from_ssrc_pt(0x11223344, TEST_PT),
+            Some(TEST_CLOCK_RATE)
+        );
+
+        // SSRC clock rates are kept when the payload types change
+        session.clear_pt_clock_rates();
+        assert_eq!(
+            session.clock_rate_from_ssrc_pt(0x12345678, TEST_PT),
+            Some(48000)
+        );
+        assert_eq!(session.clock_rate_from_ssrc_pt(0x11223344, TEST_PT), None);
+    }
+
+    #[test]
+    fn twcc_feedback() {
+        init_logs();
+        let mut session = Session::new();
+        let now = Instant::now();
+        let ntp_now = SystemTime::now();
+
+        for i in 0..4 {
+            session.twcc_packet_sent(i, 100, now + Duration::from_millis(10 * i as u64));
+        }
+        assert_eq!(session.twcc_stats(), None);
+
+        // RR followed by a transport-wide feedback about 4 packets received every 10ms
+        let rtcp_data = [
+            0x80, 0xc9, 0x00, 0x01, 0x55, 0x66, 0x77, 0x88, // RR
+            0x8f, 0xcd, 0x00, 0x06, 0x55, 0x66, 0x77, 0x88, 0x11, 0x22, 0x33, 0x44, // TWCC
+            0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00,
+            0x00, // base seqnum, count, reference time
+            0x20, 0x04, // run length chunk of 4 small deltas
+            0x00, 0x28, 0x28, 0x28, 0x00, 0x00, // deltas and padding
+        ];
+        let rtcp = Compound::parse(&rtcp_data).unwrap();
+        let replies = session.handle_rtcp_recv(rtcp, rtcp_data.len(), None, now, ntp_now);
+        assert!(replies.contains(&RtcpRecvReply::TwccStats));
+
+        let stats = session.twcc_stats().unwrap();
+        assert_eq!(stats.packets_sent, 4);
+        assert_eq!(stats.packets_recv, 4);
+        assert_eq!(stats.packet_loss_pct, 0.);
+        assert_eq!(stats.bitrate_sent, 80_000);
+        assert_eq!(stats.bitrate_recv, 80_000);
+        assert_eq!(stats.avg_delta_of_delta, 0);
+    }
+
+    #[test]
+    fn round_trip_time_from_rb() {
+        init_logs();
+        let mut sender = Session::new();
+        sender.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        let mut peer = Session::new();
+        peer.set_pt_clock_rate(TEST_PT, TEST_CLOCK_RATE);
+        let now = Instant::now();
+        let ntp_now = SystemTime::now();
+        let delay = Duration::from_millis(50);
+        let ssrc = 0x12345678;
+
+        let rtp_data = generate_rtp_packet(ssrc, 100, 0, 4);
+        let packet = RtpPacket::parse(&rtp_data).unwrap();
+        assert_eq!(
+            sender.handle_send(&packet, now),
+            SendReply::NewSsrc(ssrc, TEST_PT)
+        );
+        session_recv_first_packet_disable_probation(&mut peer, &packet, now);
+
+        // The sender report reaches the peer after `delay`
+        let (rtcp_data, now, ntp_now) = next_rtcp_packet(&mut sender, now, ntp_now);
+        let RtcpSendReply::Data(rtcp_data) = rtcp_data else {
+            unreachable!();
+        };
+        let rtcp = Compound::parse(&rtcp_data).unwrap();
+        peer.handle_rtcp_recv(rtcp, rtcp_data.len(), None, now + delay, ntp_now + delay);
+        assert_eq!(sender.round_trip_time(peer.internal_ssrc().unwrap()), None);
+
+        // And the peer's report block reaches the sender after `delay` again
+        let (rtcp_data, now, ntp_now) = next_rtcp_packet(&mut peer, now + delay, ntp_now + delay);
+        let RtcpSendReply::Data(rtcp_data) = rtcp_data else {
+            unreachable!();
+        };
+        let rtcp = Compound::parse(&rtcp_data).unwrap();
+        sender.handle_rtcp_recv(rtcp, rtcp_data.len(), None, now + delay, ntp_now + delay);
+
+        let rtt = sender
+            .round_trip_time(peer.internal_ssrc().unwrap())
+            .unwrap();
+        assert!(
+            rtt > 2 * delay - Duration::from_millis(1)
+                && rtt < 2 * delay + Duration::from_millis(1),
+            "{rtt:?}"
+        );
+        assert_eq!(sender.round_trip_time(ssrc), None);
+    }
+
     #[test]
     fn bye_local_sender() {
         let mut session = Session::new();
@@ -2656,4 +3962,54 @@ pub(crate) mod tests {
         );
         assert!(!session.is_point_to_point);
     }
+
+    #[test]
+    fn sdes_bundle_ids() {
+        init_logs();
+        let mut session = Session::new();
+        let now = Instant::now();
+        let ntp_now = SystemTime::now();
+        let recv_ssrc = 0x11223344;
+        let from = "127.0.0.1:8080".parse().unwrap();
+
+        assert_eq!(session.bundle_ids(recv_ssrc), None);
+
+        let mut data = vec![0; 128];
+        let len = Compound::builder()
+            .add_packet(
+                Sdes::builder().add_chunk(
+                    SdesChunk::builder(recv_ssrc)
+                        .add_item(SdesItem::builder(SdesItem::CNAME, "cname1"))
+                        .add_item(SdesItem::builder(SDES_ITEM_MID, "audio"))
+                        .add_item(SdesItem::builder(SDES_ITEM_RTP_STREAM_ID, "hi")),
+                ),
+            )
+            .write_into(&mut data)
+            .unwrap();
+        let rtcp = Compound::parse(&data[..len]).unwrap();
+        session.handle_rtcp_recv(rtcp, len, Some(from), now, ntp_now);
+        assert_eq!(
+            session.bundle_ids(recv_ssrc),
+            Some(&BundleIds {
+                mid: Some("audio".to_string()),
+                rid: Some("hi".to_string()),
+            })
+        );
+
+        // header extensions can change the association
+        session.set_ssrc_mid(recv_ssrc, "video");
+        assert_eq!(
+            session.bundle_ids(recv_ssrc).unwrap().mid.as_deref(),
+            Some("video")
+        );
+
+        // the association is forgotten once the ssrc leaves
+        let len = Compound::builder()
+            .add_packet(Bye::builder().add_source(recv_ssrc))
+            .write_into(&mut data)
+            .unwrap();
+        let rtcp = Compound::parse(&data[..len]).unwrap();
+        session.handle_rtcp_recv(rtcp, len, Some(from), now, ntp_now);
+        assert_eq!(session.bundle_ids(recv_ssrc), None);
+    }
 }
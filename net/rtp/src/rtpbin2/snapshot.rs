@@ -0,0 +1,264 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Snapshot of the receiver state that can be serialized and restored in another `rtprecv`,
+//! possibly in another process, to continue receiving the same streams.
+
+use gst::glib;
+
+use super::sync::SsrcSnapshot;
+
+const SNAPSHOT_NAME: &str = "application/x-rtp2-state-snapshot";
+const STREAM_NAME: &str = "application/x-rtp2-stream-state";
+const SSRC_NAME: &str = "application/x-rtp2-ssrc-state";
+
+/// State of the jitterbuffer of a single source pad
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamSnapshot {
+    pub session: usize,
+    pub pt: u8,
+    pub ssrc: u32,
+    pub mid: Option<String>,
+    pub rid: Option<String>,
+    /// Sequence number of the first packet that was not forwarded yet
+    pub next_seqnum: u16,
+}
+
+impl StreamSnapshot {
+    fn to_structure(&self) -> gst::Structure {
+        gst::Structure::builder(STREAM_NAME)
+            .field("session", self.session as u32)
+            .field("pt", self.pt as u32)
+            .field("ssrc", self.ssrc)
+            .field_if_some("mid", self.mid.as_deref())
+            .field_if_some("rid", self.rid.as_deref())
+            .field("next-seqnum", self.next_seqnum as u32)
+            .build()
+    }
+
+    fn from_structure(s: &gst::StructureRef) -> Result<Self, glib::BoolError> {
+        if s.name() != STREAM_NAME {
+            return Err(glib::bool_error!("Unexpected stream state {}", s.name()));
+        }
+
+        let pt = s
+            .get::<u32>("pt")
+            .map_err(|err| glib::bool_error!("{err}"))?;
+        let next_seqnum = s
+            .get::<u32>("next-seqnum")
+            .map_err(|err| glib::bool_error!("{err}"))?;
+
+        Ok(Self {
+            session: s
+                .get::<u32>("session")
+                .map_err(|err| glib::bool_error!("{err}"))? as usize,
+            pt: u8::try_from(pt).map_err(|_| glib::bool_error!("Invalid pt {pt}"))?,
+            ssrc: s
+                .get::<u32>("ssrc")
+                .map_err(|err| glib::bool_error!("{err}"))?,
+            mid: s.get_optional::<String>("mid").ok().flatten(),
+            rid: s.get_optional::<String>("rid").ok().flatten(),
+            next_seqnum: u16::try_from(next_seqnum)
+                .map_err(|_| glib::bool_error!("Invalid seqnum {next_seqnum}"))?,
+        })
+    }
+
+    /// Whether this is the state of the source pad for the given stream
+    pub fn matches(
+        &self,
+        session: usize,
+        pt: u8,
+        ssrc: u32,
+        mid: Option<&str>,
+        rid: Option<&str>,
+    ) -> bool {
+        if self.session != session {
+            return false;
+        }
+
+        match mid {
+            Some(_) => self.mid.as_deref() == mid && self.rid.as_deref() == rid,
+            None => self.mid.is_none() && self.ssrc == ssrc && self.pt == pt,
+        }
+    }
+}
+
+fn ssrc_to_structure(ssrc: &SsrcSnapshot) -> gst::Structure {
+    let mut s = gst::Structure::builder(SSRC_NAME)
+        .field("ssrc", ssrc.ssrc)
+        .field_if_some("cname", ssrc.cname.as_deref())
+        .field("clock-rate", ssrc.clock_rate);
+    if let Some((rtp_timestamp, ntp_timestamp)) = ssrc.sender_report {
+        s = s
+            .field("sr-rtp-timestamp", rtp_timestamp)
+            .field("sr-ntp-timestamp", ntp_timestamp);
+    }
+    s.build()
+}
+
+fn ssrc_from_structure(s: &gst::StructureRef) -> Result<SsrcSnapshot, glib::BoolError> {
+    if s.name() != SSRC_NAME {
+        return Err(glib::bool_error!("Unexpected ssrc state {}", s.name()));
+    }
+
+    let sr_rtp_timestamp = s
+        .get_optional::<u32>("sr-rtp-timestamp")
+        .map_err(|err| glib::bool_error!("{err}"))?;
+    let sr_ntp_timestamp = s
+        .get_optional::<u64>("sr-ntp-timestamp")
+        .map_err(|err| glib::bool_error!("{err}"))?;
+
+    Ok(SsrcSnapshot {
+        ssrc: s
+            .get::<u32>("ssrc")
+            .map_err(|err| glib::bool_error!("{err}"))?,
+        cname: s.get_optional::<String>("cname").ok().flatten(),
+        clock_rate: s
+            .get::<u32>("clock-rate")
+            .map_err(|err| glib::bool_error!("{err}"))?,
+        sender_report: sr_rtp_timestamp.zip(sr_ntp_timestamp),
+    })
+}
+
+fn list_from_structure<T>(
+    s: &gst::StructureRef,
+    field: &str,
+    f: impl Fn(&gst::StructureRef) -> Result<T, glib::BoolError>,
+) -> Result<Vec<T>, glib::BoolError> {
+    let Some(list) = s
+        .get_optional::<gst::List>(field)
+        .map_err(|err| glib::bool_error!("{err}"))?
+    else {
+        return Ok(vec![]);
+    };
+
+    list.iter()
+        .map(|value| {
+            let s = value
+                .get::<gst::Structure>()
+                .map_err(|err| glib::bool_error!("{err}"))?;
+            f(&s)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateSnapshot {
+    pub streams: Vec<StreamSnapshot>,
+    pub ssrcs: Vec<SsrcSnapshot>,
+}
+
+impl StateSnapshot {
+    pub fn to_structure(&self) -> gst::Structure {
+        gst::Structure::builder(SNAPSHOT_NAME)
+            .field(
+                "streams",
+                gst::List::new(self.streams.iter().map(StreamSnapshot::to_structure)),
+            )
+            .field(
+                "ssrcs",
+                gst::List::new(self.ssrcs.iter().map(ssrc_to_structure)),
+            )
+            .build()
+    }
+
+    pub fn from_structure(s: &gst::StructureRef) -> Result<Self, glib::BoolError> {
+        if s.name() != SNAPSHOT_NAME {
+            return Err(glib::bool_error!("Unexpected snapshot {}", s.name()));
+        }
+
+        Ok(Self {
+            streams: list_from_structure(s, "streams", StreamSnapshot::from_structure)?,
+            ssrcs: list_from_structure(s, "ssrcs", ssrc_from_structure)?,
+        })
+    }
+
+    /// Remove and return the state of the source pad for the given stream
+    pub fn take_stream(
+        &mut self,
+        session: usize,
+        pt: u8,
+        ssrc: u32,
+        mid: Option<&str>,
+        rid: Option<&str>,
+    ) -> Option<StreamSnapshot> {
+        let pos = self
+            .streams
+            .iter()
+            .position(|stream| stream.matches(session, pt, ssrc, mid, rid))?;
+
+        Some(self.streams.remove(pos))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn snapshot_roundtrip() {
+        gst::init().unwrap();
+
+        let snapshot = StateSnapshot {
+            streams: vec![
+                StreamSnapshot {
+                    session: 0,
+                    pt: 96,
+                    ssrc: 0x1234_5678,
+                    mid: None,
+                    rid: None,
+                    next_seqnum: 65535,
+                },
+                StreamSnapshot {
+                    session: 1,
+                    pt: 97,
+                    ssrc: 0x8765_4321,
+                    mid: Some(String::from("video")),
+                    rid: Some(String::from("hi")),
+                    next_seqnum: 0,
+                },
+            ],
+            ssrcs: vec![
+                SsrcSnapshot {
+                    ssrc: 0x1234_5678,
+                    cname: Some(String::from("foo@bar")),
+                    clock_rate: 90000,
+                    sender_report: Some((0xffff_ffff, 0x0102_0304_0506_0708)),
+                },
+                SsrcSnapshot {
+                    ssrc: 0x8765_4321,
+                    cname: None,
+                    clock_rate: 48000,
+                    sender_report: None,
+                },
+            ],
+        };
+
+        let serialized = snapshot.to_structure().to_string();
+        let s = gst::Structure::from_str(&serialized).unwrap();
+        assert_eq!(StateSnapshot::from_structure(&s).unwrap(), snapshot);
+
+        let mut snapshot = snapshot;
+        assert!(snapshot
+            .take_stream(0, 96, 0x1234_5678, Some("video"), None)
+            .is_none());
+        assert_eq!(
+            snapshot
+                .take_stream(1, 100, 0x1111, Some("video"), Some("hi"))
+                .unwrap()
+                .next_seqnum,
+            0
+        );
+        assert_eq!(
+            snapshot
+                .take_stream(0, 96, 0x1234_5678, None, None)
+                .unwrap()
+                .next_seqnum,
+            65535
+        );
+        assert!(snapshot.streams.is_empty());
+
+        let s = gst::Structure::new_empty("application/x-rtp2-stats");
+        assert!(StateSnapshot::from_structure(&s).is_err());
+    }
+}
@@ -13,6 +13,7 @@ use crate::utils::ExtendedSeqnum;
 use super::{
     session::KeyUnitRequestType,
     time::{system_time_to_ntp_time_u64, NtpTime},
+    xr::DlrrSubBlock,
 };
 
 use gst::prelude::MulDiv;
@@ -20,6 +21,8 @@ use gst::prelude::MulDiv;
 pub const DEFAULT_PROBATION_N_PACKETS: usize = 2;
 pub const DEFAULT_MAX_DROPOUT: u32 = 3000;
 pub const DEFAULT_MAX_MISORDER: u32 = 100;
+const MIN_MAX_DROPOUT: u64 = 30;
+const MIN_MAX_MISORDER: u64 = 10;
 
 const BITRATE_WINDOW: Duration = Duration::from_secs(3);
 
@@ -103,6 +106,8 @@ pub(crate) struct Source {
     sdes: HashMap<u8, String>,
     last_activity: Instant,
     payload_type: Option<u8>,
+    last_received_rrtr: Option<ReceivedRrtr>,
+    received_dlrr: HashMap<u32, ReceivedDlrr>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -120,25 +125,92 @@ impl Source {
             sdes: HashMap::new(),
             last_activity: Instant::now(),
             payload_type: None,
+            last_received_rrtr: None,
+            received_dlrr: HashMap::new(),
         }
     }
 
     fn set_state(&mut self, state: SourceState) {
         self.state = state;
     }
+
+    fn set_last_received_rrtr(&mut self, ntp_timestamp: u64, ntp_now: SystemTime) {
+        self.last_received_rrtr = Some(ReceivedRrtr {
+            ntp_timestamp: ntp_timestamp.into(),
+            receive_ntp_time: system_time_to_ntp_time_u64(ntp_now),
+        });
+    }
+
+    fn add_received_dlrr(&mut self, sender_ssrc: u32, dlrr: DlrrSubBlock, ntp_now: SystemTime) {
+        self.received_dlrr.insert(
+            sender_ssrc,
+            ReceivedDlrr {
+                dlrr,
+                receive_ntp_time: system_time_to_ntp_time_u64(ntp_now),
+            },
+        );
+    }
+}
+
+/// Receiver Reference Time report block (RFC 3611) received from a remote source
+#[derive(Debug, Clone, Copy)]
+pub struct ReceivedRrtr {
+    pub ntp_timestamp: NtpTime,
+    pub receive_ntp_time: NtpTime,
+}
+
+impl ReceivedRrtr {
+    /// Generate the DLRR sub-block for replying to this RRTR from `ssrc`
+    pub(crate) fn dlrr_sub_block(&self, ssrc: u32, ntp_now: NtpTime) -> DlrrSubBlock {
+        DlrrSubBlock {
+            ssrc,
+            last_rr: self.ntp_timestamp.as_u32(),
+            delay_since_last_rr: ntp_now
+                .as_u32()
+                .wrapping_sub(self.receive_ntp_time.as_u32()),
+        }
+    }
+}
+
+/// DLRR sub-block (RFC 3611) received for a local source
+#[derive(Debug, Clone, Copy)]
+pub struct ReceivedDlrr {
+    pub dlrr: DlrrSubBlock,
+    pub receive_ntp_time: NtpTime,
+}
+
+impl ReceivedDlrr {
+    pub fn round_trip_time(&self) -> Duration {
+        // Can't calculate any round trip time
+        if self.dlrr.last_rr == 0 {
+            return Duration::ZERO;
+        }
+
+        let rtt = self
+            .receive_ntp_time
+            .as_u32()
+            .wrapping_sub(self.dlrr.last_rr)
+            .wrapping_sub(self.dlrr.delay_since_last_rr);
+        // Negative or bogus RTT of more than 10 seconds
+        if (rtt >> 16) > 10 {
+            return Duration::ZERO;
+        }
+
+        Duration::from_nanos(rtt as u64 * 1_000_000_000 / 65_536)
+    }
 }
 
 #[derive(Debug)]
 pub struct ReceivedRb {
     pub rb: Rb,
-    #[allow(unused)]
     pub receive_time: Instant,
     pub receive_ntp_time: NtpTime,
 }
 
 impl ReceivedRb {
-    #[allow(unused)]
-    fn round_trip_time(&self) -> Duration {
+    /// Round trip time as described in RFC 3550 Section 6.4.1, or zero if the report block does
+    /// not refer to a sender report
+    pub fn round_trip_time(&self) -> Duration {
         let rb_send_ntp_time = self.rb.last_sr as u64 + self.rb.delay_since_last_sr as u64;
 
         // Can't calculate any round trip time
@@ -155,13 +227,12 @@ impl ReceivedRb {
             }
         }
 
-        let diff = rb_recv_ntp_time.saturating_sub(rb_send_ntp_time);
-        // Bogus RTT of more than 2*5 seconds, return 1s as a fallback
-        if (diff >> 16) > 5 {
+        let rtt = rb_recv_ntp_time.saturating_sub(rb_send_ntp_time);
+        // Bogus RTT of more than 10 seconds, return 1s as a fallback
+        if (rtt >> 16) > 10 {
             return Duration::from_secs(1);
         }
 
-        let rtt = 2 * diff;
         let rtt_ns = rtt * 1_000_000_000 / 65_536;
         Duration::from_nanos(rtt_ns)
     }
@@ -258,6 +329,11 @@ impl LocalSendSource {
         }
     }
 
+    /// Replace all sdes items of this source
+    pub fn set_sdes(&mut self, sdes: &HashMap<u8, String>) {
+        self.source.sdes.clone_from(sdes);
+    }
+
     /// Retrieve the sdes for this source
     pub fn sdes(&self) -> &HashMap<u8, String> {
         &self.source.sdes
@@ -348,6 +424,20 @@ impl LocalSendSource {
     pub fn received_report_blocks(&self) -> impl Iterator<Item = (u32, &ReceivedRb)> + '_ {
         self.last_received_rb.iter().map(|(&k, v)| (k, v))
     }
+
+    pub(crate) fn add_received_dlrr(
+        &mut self,
+        sender_ssrc: u32,
+        dlrr: DlrrSubBlock,
+        ntp_now: SystemTime,
+    ) {
+        self.source.add_received_dlrr(sender_ssrc, dlrr, ntp_now);
+    }
+
+    /// Retrieve the DLRR sub-blocks received for this source by sender ssrc
+    pub fn received_dlrr(&self) -> impl Iterator<Item = (u32, &ReceivedDlrr)> + '_ {
+        self.source.received_dlrr.iter().map(|(&k, v)| (k, v))
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -417,6 +507,12 @@ pub struct RemoteSendSource {
     transit: Option<u32>,
     // any held buffers. Used when source is on probation.
     held_buffers: VecDeque<HeldRecvBuffer>,
+    // Extended seqnum and RTP timestamp of the last packet used for the packet rate estimation
+    packet_rate_ref: Option<(u64, u32)>,
+    // Estimated packets per second
+    packet_rate: Option<u64>,
+    max_dropout_time: Option<Duration>,
+    max_misorder_time: Option<Duration>,
     bitrate: Bitrate,
     last_sent_rb: Option<Rb>,
     last_received_rb: HashMap<u32, ReceivedRb>,
@@ -450,6 +546,10 @@ impl RemoteSendSource {
             recv_packets_at_last_rtcp: 0,
             ext_seqnum_at_last_rtcp: 0,
             held_buffers: VecDeque::new(),
+            packet_rate_ref: None,
+            packet_rate: None,
+            max_dropout_time: None,
+            max_misorder_time: None,
             jitter: 0,
             transit: None,
             bitrate: Bitrate::new(BITRATE_WINDOW),
@@ -563,6 +663,12 @@ impl RemoteSendSource {
 
         trace!("source {} in state {:?} received seqnum {seqnum} with a difference of {diff} from the previous seqnum", self.ssrc(), self.state());
 
+        if let Some(clock_rate) = clock_rate {
+            self.update_packet_rate(ext_seqnum, rtp_timestamp, clock_rate);
+        }
+        let max_dropout = self.max_dropout() as i64;
+        let max_misorder = self.max_misorder() as i64;
+
         let ret = if let SourceState::Probation(n_probation) = self.state() {
             // consecutive packets are good
             if diff == 1 {
@@ -625,9 +731,9 @@ impl RemoteSendSource {
                 self.set_state(SourceState::Normal);
                 SourceRecvReply::Passthrough
             }
-        } else if diff >= 1 && diff < DEFAULT_MAX_DROPOUT as i64 {
+        } else if diff >= 1 && diff < max_dropout {
             SourceRecvReply::Passthrough
-        } else if diff < -(DEFAULT_MAX_MISORDER as i64) || diff >= DEFAULT_MAX_DROPOUT as i64 {
+        } else if diff < -max_misorder || diff >= max_dropout {
             debug!("non-consecutive packet outside of configured limits, dropping");
 
             // TODO: we will want to perform a few tasks here that the C jitterbuffer
@@ -684,6 +790,70 @@ impl RemoteSendSource {
         ret
     }
 
+    fn update_packet_rate(&mut self, ext_seqnum: u64, rtp_timestamp: u32, clock_rate: u32) {
+        let Some((ref_seqnum, ref_timestamp)) = self.packet_rate_ref else {
+            self.packet_rate_ref = Some((ext_seqnum, rtp_timestamp));
+            return;
+        };
+
+        // Only consider packets going forward, and skip packets of the same frame
+        if ext_seqnum <= ref_seqnum || rtp_timestamp == ref_timestamp {
+            return;
+        }
+
+        let ts_diff = rtp_timestamp.wrapping_sub(ref_timestamp);
+        self.packet_rate_ref = Some((ext_seqnum, rtp_timestamp));
+        // Backwards timestamps or more than a second between packets
+        if ts_diff > clock_rate {
+            return;
+        }
+
+        let Some(new_rate) =
+            (ext_seqnum - ref_seqnum).mul_div_round(clock_rate as u64, ts_diff as u64)
+        else {
+            return;
+        };
+
+        // Adapt quickly to higher packet rates and slowly to lower ones
+        let rate = match self.packet_rate {
+            None => new_rate,
+            Some(rate) if new_rate > rate => (rate + new_rate + 1) / 2,
+            Some(rate) => (7 * rate + new_rate + 4) / 8,
+        };
+        trace!("source {} packet rate {rate}", self.ssrc());
+        self.packet_rate = Some(rate);
+    }
+
+    fn max_dropout(&self) -> u64 {
+        match (self.packet_rate, self.max_dropout_time) {
+            (Some(rate), Some(time)) => {
+                u64::max(MIN_MAX_DROPOUT, rate * time.as_millis() as u64 / 1000)
+            }
+            _ => DEFAULT_MAX_DROPOUT as u64,
+        }
+    }
+
+    fn max_misorder(&self) -> u64 {
+        match (self.packet_rate, self.max_misorder_time) {
+            (Some(rate), Some(time)) => {
+                u64::max(MIN_MAX_MISORDER, rate * time.as_millis() as u64 / 1000)
+            }
+            _ => DEFAULT_MAX_MISORDER as u64,
+        }
+    }
+
+    /// Set the time after which a jump forward in sequence numbers is considered a restart of
+    /// the sequence instead of packet loss. `None` uses a fixed number of packets.
+    pub fn set_max_dropout_time(&mut self, max_dropout_time: Option<Duration>) {
+        self.max_dropout_time = max_dropout_time;
+    }
+
+    /// Set the time after which a jump backwards in sequence numbers is considered a restart of
+    /// the sequence instead of reordering. `None` uses a fixed number of packets.
+    pub fn set_max_misorder_time(&mut self, max_misorder_time: Option<Duration>) {
+        self.max_misorder_time = max_misorder_time;
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn recv_packet_add_to_stats(
         &mut self,
@@ -840,7 +1010,6 @@ impl RemoteSendSource {
         expected as i64 - self.recv_packets as i64
     }
 
-    #[cfg(test)]
     /// Set the number of probation packets before validating this source
     pub fn set_probation_packets(&mut self, n_packets: usize) {
         info!("source {} setting probation to {n_packets}", self.ssrc());
@@ -892,6 +1061,15 @@ impl RemoteSendSource {
         self.last_received_rb.iter().map(|(&k, v)| (k, v))
     }
 
+    pub(crate) fn set_last_received_rrtr(&mut self, ntp_timestamp: u64, ntp_now: SystemTime) {
+        self.source.set_last_received_rrtr(ntp_timestamp, ntp_now);
+    }
+
+    /// Retrieve the last RRTR report block received from this source
+    pub fn last_received_rrtr(&self) -> Option<ReceivedRrtr> {
+        self.source.last_received_rrtr
+    }
+
     pub(crate) fn into_receive(self) -> RemoteReceiveSource {
         RemoteReceiveSource {
             source: self.source,
@@ -1002,6 +1180,11 @@ impl LocalReceiveSource {
         }
     }
 
+    /// Replace all sdes items of this source
+    pub fn set_sdes(&mut self, sdes: &HashMap<u8, String>) {
+        self.source.sdes.clone_from(sdes);
+    }
+
     /// Retrieve the sdes for this source
     pub fn sdes(&self) -> &HashMap<u8, String> {
         &self.source.sdes
@@ -1036,6 +1219,20 @@ impl LocalReceiveSource {
     pub(crate) fn bye_reason(&self) -> Option<&String> {
         self.bye_reason.as_ref()
     }
+
+    pub(crate) fn add_received_dlrr(
+        &mut self,
+        sender_ssrc: u32,
+        dlrr: DlrrSubBlock,
+        ntp_now: SystemTime,
+    ) {
+        self.source.add_received_dlrr(sender_ssrc, dlrr, ntp_now);
+    }
+
+    /// Retrieve the DLRR sub-blocks received for this source by sender ssrc
+    pub fn received_dlrr(&self) -> impl Iterator<Item = (u32, &ReceivedDlrr)> + '_ {
+        self.source.received_dlrr.iter().map(|(&k, v)| (k, v))
+    }
 }
 
 #[derive(Debug)]
@@ -1094,6 +1291,15 @@ impl RemoteReceiveSource {
         self.source.last_activity
     }
 
+    pub(crate) fn set_last_received_rrtr(&mut self, ntp_timestamp: u64, ntp_now: SystemTime) {
+        self.source.set_last_received_rrtr(ntp_timestamp, ntp_now);
+    }
+
+    /// Retrieve the last RRTR report block received from this source
+    pub fn last_received_rrtr(&self) -> Option<ReceivedRrtr> {
+        self.source.last_received_rrtr
+    }
+
     pub(crate) fn into_send(self) -> RemoteSendSource {
         RemoteSendSource {
             source: self.source,
@@ -1108,6 +1314,10 @@ impl RemoteReceiveSource {
             recv_packets_at_last_rtcp: 0,
             ext_seqnum_at_last_rtcp: 0,
             held_buffers: VecDeque::new(),
+            packet_rate_ref: None,
+            packet_rate: None,
+            max_dropout_time: None,
+            max_misorder_time: None,
             jitter: 0,
             transit: None,
             bitrate: Bitrate::new(BITRATE_WINDOW),
@@ -1310,4 +1520,32 @@ mod tests {
             source.recv_packet(16, now, 0, 0, TEST_PT, None, 0)
         );
     }
+
+    #[test]
+    fn receive_max_dropout_time() {
+        init_logs();
+        let mut source = RemoteSendSource::new(100);
+        source.set_probation_packets(0);
+        source.set_max_dropout_time(Some(Duration::from_secs(1)));
+        let now = Instant::now();
+
+        // 50 packets per second
+        for i in 0..10u16 {
+            assert_eq!(
+                SourceRecvReply::Passthrough,
+                source.recv_packet(16, now, i, i as u32 * 160, TEST_PT, Some(8000), 0)
+            );
+        }
+        assert_eq!(source.max_dropout(), 50);
+
+        // A jump of more than a second of packets restarts the sequence
+        assert_eq!(
+            SourceRecvReply::Ignore,
+            source.recv_packet(16, now, 100, 100 * 160, TEST_PT, Some(8000), 0)
+        );
+        assert_eq!(
+            SourceRecvReply::Passthrough,
+            source.recv_packet(16, now, 101, 101 * 160, TEST_PT, Some(8000), 0)
+        );
+    }
 }
@@ -1,7 +1,7 @@
 use gst::glib;
 use gst::prelude::MulDiv;
 use std::collections::{HashMap, VecDeque};
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, Mutex, Weak};
 use std::time::Duration;
 
 use crate::utils::ExtendedTimestamp;
@@ -15,6 +15,10 @@ struct Ssrc {
     extended_timestamp: ExtendedTimestamp,
     last_sr_ntp_timestamp: Option<NtpTime>,
     last_sr_rtp_ext: Option<u64>,
+    // Extended RTP timestamp of the start of the stream, from the caps
+    clock_base: Option<u64>,
+    // RFC 7273 media clock the RTP timestamps are derived from, from the caps
+    media_clock: Option<MediaClock>,
     // Arrival, RTP timestamp (extended), PTS (potentially skew-corrected)
     base_times: Option<(u64, u64, u64)>,
     current_delay: Option<i64>,
@@ -33,6 +37,7 @@ impl Ssrc {
         self.extended_timestamp = ExtendedTimestamp::default();
         self.last_sr_ntp_timestamp = None;
         self.last_sr_rtp_ext = None;
+        self.clock_base = None;
         self.base_times = None;
         self.current_delay = None;
         self.observations = Observations::default();
@@ -87,6 +92,185 @@ pub enum TimestampingMode {
     Skew,
 }
 
+/// RFC 7273 reference clock of a media clock, from the `ts-refclk` attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RefClock {
+    /// IEEE 1588-2008 PTP clock of the given domain, or of any domain if traceable
+    Ptp { domain: Option<u32> },
+    /// NTP clock synchronized to the given server, or to any server if traceable
+    Ntp { server: Option<(String, u16)> },
+}
+
+impl RefClock {
+    fn parse(s: &str) -> Option<Self> {
+        if let Some(ptp) = s.strip_prefix("ptp=") {
+            let mut parts = ptp.split(':');
+            if parts.next() != Some("IEEE1588-2008") {
+                return None;
+            }
+            // Either traceable or the grandmaster identity, optionally followed by the domain
+            let domain = match parts.next()? {
+                "traceable" => None,
+                _ => Some(match parts.next() {
+                    Some(domain) => domain.parse().ok()?,
+                    None => 0,
+                }),
+            };
+
+            Some(RefClock::Ptp { domain })
+        } else if let Some(ntp) = s.strip_prefix("ntp=") {
+            if ntp == "/traceable/" {
+                return Some(RefClock::Ntp { server: None });
+            }
+
+            let (host, port) = match ntp.rsplit_once(':') {
+                Some((host, port)) if !host.contains(':') || host.ends_with(']') => {
+                    (host, port.parse().ok()?)
+                }
+                _ => (ntp, 123),
+            };
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+
+            Some(RefClock::Ntp {
+                server: Some((host.to_owned(), port)),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Whether `clock` is synchronized to this reference clock
+    pub fn is_clock(&self, clock: &gst::Clock) -> bool {
+        use gst::prelude::*;
+
+        match self {
+            RefClock::Ptp { domain } => {
+                clock
+                    .downcast_ref::<gst_net::PtpClock>()
+                    .is_some_and(|clock| {
+                        domain.map_or(true, |domain| clock.property::<u32>("domain") == domain)
+                    })
+            }
+            RefClock::Ntp { server } => {
+                clock
+                    .downcast_ref::<gst_net::NtpClock>()
+                    .is_some_and(|clock| {
+                        server.as_ref().map_or(true, |(host, port)| {
+                            clock.property::<Option<String>>("address").as_deref() == Some(host)
+                                && clock.property::<i32>("port") == *port as i32
+                        })
+                    })
+            }
+        }
+    }
+}
+
+/// RFC 7273 media clock of a stream whose RTP timestamps directly follow a reference clock
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaClock {
+    pub ref_clock: RefClock,
+    /// RTP timestamp at the epoch of the reference clock
+    pub offset: u32,
+    /// Rate of the media clock relative to the nominal RTP clock rate, as numerator and
+    /// denominator, e.g. 1000/1001 for NTSC-style rates
+    pub rate: Option<(u32, u32)>,
+}
+
+impl MediaClock {
+    /// The media clock signalled by the `a-ts-refclk` and `a-mediaclk` caps fields, as
+    /// created from the SDP. Only direct media clocks are supported.
+    pub fn from_caps(caps: &gst::CapsRef) -> Option<Self> {
+        let s = caps.structure(0)?;
+        let ref_clock = s
+            .get::<&str>("a-ts-refclk")
+            .ok()
+            .and_then(RefClock::parse)?;
+        let mediaclk = s.get::<&str>("a-mediaclk").ok()?;
+        let mut params = mediaclk
+            .strip_prefix("direct=")?
+            .split(|c: char| c == ';' || c.is_ascii_whitespace())
+            .filter(|p| !p.is_empty());
+        let offset = params.next()?.parse::<u64>().ok()?;
+
+        let mut rate = None;
+        for param in params {
+            let Some(value) = param.strip_prefix("rate=") else {
+                continue;
+            };
+            let (num, den) = value.split_once('/').unwrap_or((value, "1"));
+            let num = num.parse::<u32>().ok()?;
+            let den = den.parse::<u32>().ok()?;
+            if num == 0 || den == 0 {
+                return None;
+            }
+            if num != den {
+                rate = Some((num, den));
+            }
+        }
+
+        Some(MediaClock {
+            ref_clock,
+            offset: (offset & 0xffff_ffff) as u32,
+            rate,
+        })
+    }
+}
+
+/// Contexts shared between elements, by sync group name
+static SYNC_GROUPS: LazyLock<Mutex<HashMap<String, Weak<Mutex<Context>>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Get the context for the given sync group, creating it if no other element is
+/// currently using it. Without a sync group a new context is always created.
+pub fn context_get_or_init(
+    sync_group: Option<&str>,
+    mode: TimestampingMode,
+) -> Arc<Mutex<Context>> {
+    let Some(sync_group) = sync_group else {
+        return Arc::new(Mutex::new(Context::new(mode)));
+    };
+
+    let mut groups = SYNC_GROUPS.lock().unwrap();
+    groups.retain(|_, context| context.strong_count() > 0);
+
+    if let Some(context) = groups.get(sync_group).and_then(Weak::upgrade) {
+        let existing_mode = context.lock().unwrap().mode;
+        if existing_mode != mode {
+            warn!(
+                "Sync group {sync_group} already uses timestamping mode {existing_mode:?}, ignoring {mode:?}"
+            );
+        }
+        return context;
+    }
+
+    debug!("Creating sync group {sync_group}");
+    let context = Arc::new(Mutex::new(Context::new(mode)));
+    groups.insert(sync_group.to_owned(), Arc::downgrade(&context));
+
+    context
+}
+
+/// Inter-stream synchronization state of a single ssrc
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncOffset {
+    pub ssrc: u32,
+    pub cname: Option<Arc<str>>,
+    /// Difference between arrival and NTP times, once a sender report was received
+    pub delay: Option<i64>,
+    /// Offset added to the PTS, once all ssrcs with the same CNAME have a delay
+    pub offset: Option<u64>,
+}
+
+/// Synchronization state of a single ssrc that can be restored in another context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsrcSnapshot {
+    pub ssrc: u32,
+    pub cname: Option<String>,
+    pub clock_rate: u32,
+    /// RTP and NTP timestamps of the last sender report
+    pub sender_report: Option<(u32, u64)>,
+}
+
 #[derive(Debug)]
 pub struct Context {
     ssrcs: HashMap<u32, Ssrc>,
@@ -118,6 +302,79 @@ impl Context {
         }
     }
 
+    /// Use `clock_base` as the RTP timestamp of the start of the stream, e.g. from the
+    /// `clock-base` field of the caps. Only has an effect before the first packet.
+    pub fn set_clock_base(&mut self, ssrc_val: u32, clock_base: u32) {
+        let ssrc = self
+            .ssrcs
+            .entry(ssrc_val)
+            .or_insert_with(|| Ssrc::new(None));
+
+        if ssrc.extended_timestamp.current().is_some() {
+            debug!("{ssrc_val:#08x} ignoring clock-base {clock_base} after the first packet");
+            return;
+        }
+
+        ssrc.clock_base = Some(ssrc.extended_timestamp.next(clock_base));
+    }
+
+    /// Time in nanoseconds between the clock-base and the last RTP timestamp of `ssrc_val`
+    pub fn clock_base_offset(&self, ssrc_val: u32) -> Option<i64> {
+        let ssrc = self.ssrcs.get(&ssrc_val)?;
+        let clock_base = ssrc.clock_base?;
+        let clock_rate = ssrc.clock_rate?;
+        let rtp_ext = ssrc.extended_timestamp.current()?;
+
+        Some(((rtp_ext as i128 - clock_base as i128) * 1_000_000_000 / clock_rate as i128) as i64)
+    }
+
+    /// Use the RFC 7273 media clock of `ssrc_val` for `calculate_media_clock_pts()`
+    pub fn set_media_clock(&mut self, ssrc_val: u32, media_clock: MediaClock) {
+        let ssrc = self
+            .ssrcs
+            .entry(ssrc_val)
+            .or_insert_with(|| Ssrc::new(None));
+
+        ssrc.media_clock = Some(media_clock);
+    }
+
+    pub fn media_clock(&self, ssrc_val: u32) -> Option<&MediaClock> {
+        self.ssrcs.get(&ssrc_val)?.media_clock.as_ref()
+    }
+
+    /// Running time of a packet of `ssrc_val` derived from its RTP timestamp and the RFC 7273
+    /// media clock of the stream. `clock_time` and `base_time` are those of the pipeline clock,
+    /// which must be the reference clock of the media clock.
+    ///
+    /// The RTP timestamp is unwrapped to the media clock time closest to `clock_time`.
+    pub fn calculate_media_clock_pts(
+        &self,
+        ssrc_val: u32,
+        timestamp: u32,
+        clock_time: u64,
+        base_time: u64,
+    ) -> Option<u64> {
+        let ssrc = self.ssrcs.get(&ssrc_val)?;
+        let media_clock = ssrc.media_clock.as_ref()?;
+        let clock_rate = ssrc.clock_rate? as u64;
+        // Ticks per second of the media clock are clock_rate * num / den
+        let (num, den) = media_clock.rate.unwrap_or((1, 1));
+        let ticks_num = clock_rate * num as u64;
+        let ticks_den = 1_000_000_000 * den as u64;
+
+        let now_ticks = clock_time.mul_div_floor(ticks_num, ticks_den)?;
+        let now_rtp = (now_ticks as u32).wrapping_add(media_clock.offset);
+        let diff = timestamp.wrapping_sub(now_rtp) as i32;
+        let ticks = now_ticks.checked_add_signed(diff as i64)?;
+        let time = ticks.mul_div_round(ticks_den, ticks_num)?;
+
+        trace!(
+            "{ssrc_val:#08x} media clock time {time} for RTP timestamp {timestamp} at clock time {clock_time}"
+        );
+
+        Some(time.saturating_sub(base_time))
+    }
+
     pub fn has_clock_rate(&self, ssrc_val: u32) -> bool {
         self.ssrcs.contains_key(&ssrc_val)
     }
@@ -131,7 +388,6 @@ impl Context {
     }
 
     // FIXME: call this on timeouts / BYE (maybe collisions too?)
-    #[allow(dead_code)]
     pub fn remove_ssrc(&mut self, ssrc_val: u32) {
         if let Some(ssrc) = self.ssrcs.remove(&ssrc_val) {
             debug!("{ssrc_val:#08x} ssrc removed");
@@ -181,6 +437,72 @@ impl Context {
         ssrc.add_sender_report(rtp_timestamp, ntp_timestamp)
     }
 
+    /// Snapshot of the state of `ssrc_vals`, ignoring ssrcs for which no packet was received yet
+    pub fn snapshot(&self, ssrc_vals: impl IntoIterator<Item = u32>) -> Vec<SsrcSnapshot> {
+        let mut ret = ssrc_vals
+            .into_iter()
+            .filter_map(|ssrc_val| {
+                let ssrc = self.ssrcs.get(&ssrc_val)?;
+
+                Some(SsrcSnapshot {
+                    ssrc: ssrc_val,
+                    cname: ssrc.cname.as_deref().map(String::from),
+                    clock_rate: ssrc.clock_rate?,
+                    sender_report: ssrc
+                        .last_sr_rtp_ext
+                        .zip(ssrc.last_sr_ntp_timestamp)
+                        .map(|(rtp_ext, ntp)| (rtp_ext as u32, ntp.as_u64())),
+                })
+            })
+            .collect::<Vec<_>>();
+        ret.sort_by_key(|snapshot| snapshot.ssrc);
+
+        ret
+    }
+
+    /// Restore the state of an ssrc from a snapshot taken in another context, so that
+    /// inter-stream synchronization can be applied without waiting for new sender reports
+    pub fn restore(&mut self, snapshot: &SsrcSnapshot) {
+        debug!("{:#08x} restoring {snapshot:?}", snapshot.ssrc);
+
+        self.set_clock_rate(snapshot.ssrc, snapshot.clock_rate);
+        if let Some(ref cname) = snapshot.cname {
+            self.associate(snapshot.ssrc, cname);
+        }
+        if let Some((rtp_timestamp, ntp_timestamp)) = snapshot.sender_report {
+            self.add_sender_report(snapshot.ssrc, rtp_timestamp, ntp_timestamp);
+        }
+    }
+
+    /// The offsets currently applied to each ssrc, sorted by ssrc
+    pub fn offsets(&self) -> Vec<SyncOffset> {
+        let mut ret = self
+            .ssrcs
+            .iter()
+            .map(|(&ssrc_val, ssrc)| {
+                let offset = ssrc.cname.as_ref().and_then(|cname| {
+                    let delay = ssrc.current_delay?;
+                    let mut largest_delay = i64::MIN;
+                    for other in self.cnames_to_ssrcs.get(cname)? {
+                        largest_delay = largest_delay.max(self.ssrcs.get(other)?.current_delay?);
+                    }
+
+                    Some((largest_delay - delay) as u64)
+                });
+
+                SyncOffset {
+                    ssrc: ssrc_val,
+                    cname: ssrc.cname.clone(),
+                    delay: ssrc.current_delay,
+                    offset,
+                }
+            })
+            .collect::<Vec<_>>();
+        ret.sort_by_key(|offset| offset.ssrc);
+
+        ret
+    }
+
     pub fn calculate_pts(
         &mut self,
         ssrc_val: u32,
@@ -553,6 +875,166 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_media_clock_from_caps() {
+        init_logs();
+
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("a-ts-refclk", "ptp=IEEE1588-2008:39-A7-94-FF-FE-07-CB-D0:5")
+            .field("a-mediaclk", "direct=1266592257")
+            .build();
+        assert_eq!(
+            MediaClock::from_caps(&caps),
+            Some(MediaClock {
+                ref_clock: RefClock::Ptp { domain: Some(5) },
+                offset: 1266592257,
+                rate: None,
+            })
+        );
+
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("a-ts-refclk", "ptp=IEEE1588-2008:39-A7-94-FF-FE-07-CB-D0")
+            .field("a-mediaclk", "direct=0")
+            .build();
+        assert_eq!(
+            MediaClock::from_caps(&caps).unwrap().ref_clock,
+            RefClock::Ptp { domain: Some(0) }
+        );
+
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("a-ts-refclk", "ntp=203.0.113.10:1234")
+            .field("a-mediaclk", "direct=963214424;rate=1000/1001")
+            .build();
+        assert_eq!(
+            MediaClock::from_caps(&caps),
+            Some(MediaClock {
+                ref_clock: RefClock::Ntp {
+                    server: Some((String::from("203.0.113.10"), 1234))
+                },
+                offset: 963214424,
+                rate: Some((1000, 1001)),
+            })
+        );
+
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("a-ts-refclk", "ntp=/traceable/")
+            .field("a-mediaclk", "direct=0 rate=1/1")
+            .build();
+        assert_eq!(MediaClock::from_caps(&caps).unwrap().rate, None);
+
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("a-ts-refclk", "ntp=/traceable/")
+            .field("a-mediaclk", "direct=0 rate=1000/0")
+            .build();
+        assert_eq!(MediaClock::from_caps(&caps), None);
+
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("a-ts-refclk", "ntp=/traceable/")
+            .field("a-mediaclk", "direct=0")
+            .build();
+        assert_eq!(
+            MediaClock::from_caps(&caps).unwrap().ref_clock,
+            RefClock::Ntp { server: None }
+        );
+
+        // Sender media clocks are not supported
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("a-ts-refclk", "ntp=/traceable/")
+            .field("a-mediaclk", "sender")
+            .build();
+        assert_eq!(MediaClock::from_caps(&caps), None);
+
+        let caps = gst::Caps::builder("application/x-rtp")
+            .field("a-ts-refclk", "local")
+            .field("a-mediaclk", "direct=0")
+            .build();
+        assert_eq!(MediaClock::from_caps(&caps), None);
+    }
+
+    #[test]
+    fn test_media_clock_pts() {
+        init_logs();
+
+        let mut ctx = Context::new(TimestampingMode::Skew);
+        ctx.set_clock_rate(0x12345678, 90000);
+        ctx.set_media_clock(
+            0x12345678,
+            MediaClock {
+                ref_clock: RefClock::Ptp { domain: Some(0) },
+                offset: 1000,
+                rate: None,
+            },
+        );
+
+        // Clock time of 1000s, packet captured 100ms earlier
+        let clock_time = 1_000_000_000_000;
+        let base_time = 999_000_000_000;
+        let timestamp = ((1000 * 90000 - 9000 + 1000) & 0xffff_ffff) as u32;
+        assert_eq!(
+            ctx.calculate_media_clock_pts(0x12345678, timestamp, clock_time, base_time),
+            Some(900_000_000)
+        );
+
+        // Across a wraparound of the RTP timestamps
+        let clock_time = (1u64 << 32).mul_div_floor(1_000_000_000, 90000).unwrap();
+        let timestamp = 1000u32.wrapping_sub(9000);
+        assert_eq!(
+            ctx.calculate_media_clock_pts(0x12345678, timestamp, clock_time, 0),
+            Some(
+                ((1u64 << 32) - 9000)
+                    .mul_div_round(1_000_000_000, 90000)
+                    .unwrap()
+            )
+        );
+
+        // Media clock running at 1000/1001 of the RTP clock rate
+        ctx.set_clock_rate(0x11111111, 90000);
+        ctx.set_media_clock(
+            0x11111111,
+            MediaClock {
+                ref_clock: RefClock::Ptp { domain: Some(0) },
+                offset: 0,
+                rate: Some((1000, 1001)),
+            },
+        );
+        // 1001s of clock time are 1000 * 90000 media clock ticks, packet captured 1001ms earlier
+        let clock_time = 1_001_000_000_000;
+        let timestamp = (1000 * 90000 - 90000) as u32;
+        assert_eq!(
+            ctx.calculate_media_clock_pts(0x11111111, timestamp, clock_time, 0),
+            Some(999_999_000_000)
+        );
+
+        // No media clock
+        ctx.set_clock_rate(0x87654321, 90000);
+        assert_eq!(
+            ctx.calculate_media_clock_pts(0x87654321, timestamp, clock_time, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn test_single_stream_clock_base() {
+        init_logs();
+
+        let mut ctx = Context::new(TimestampingMode::Rtp);
+
+        ctx.set_clock_rate(0x12345678, 90000);
+        ctx.set_clock_base(0x12345678, 4_294_967_000);
+        assert_eq!(ctx.clock_base_offset(0x12345678), Some(0));
+
+        // Packets before and after a wraparound relative to the clock-base
+        ctx.calculate_pts(0x12345678, 4_294_966_100, 0);
+        assert_eq!(ctx.clock_base_offset(0x12345678), Some(-10_000_000));
+        ctx.calculate_pts(0x12345678, 89_704, 1_000_000_000);
+        assert_eq!(ctx.clock_base_offset(0x12345678), Some(1_000_000_000));
+
+        // Ignored once packets were received
+        ctx.set_clock_base(0x12345678, 0);
+        assert_eq!(ctx.clock_base_offset(0x12345678), Some(1_000_000_000));
+        assert_eq!(ctx.clock_base_offset(0x1234), None);
+    }
+
     #[test]
     fn test_single_stream_with_sr() {
         init_logs();
@@ -585,6 +1067,56 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_snapshot_restore() {
+        init_logs();
+
+        let mut ctx = Context::new(TimestampingMode::Rtp);
+
+        ctx.set_clock_rate(0x12345678, 90000);
+        ctx.associate(0x12345678, "foo@bar");
+        ctx.add_sender_report(
+            0x12345678,
+            0,
+            system_time_to_ntp_time_u64(std::time::UNIX_EPOCH).as_u64(),
+        );
+        ctx.calculate_pts(0x12345678, 0, 0);
+        // No packets received yet
+        ctx.associate(0x1234, "foo@bar");
+
+        let snapshot = ctx.snapshot([0x12345678, 0x1234, 0x5678]);
+        assert_eq!(
+            snapshot,
+            vec![SsrcSnapshot {
+                ssrc: 0x12345678,
+                cname: Some(String::from("foo@bar")),
+                clock_rate: 90000,
+                sender_report: Some((
+                    0,
+                    system_time_to_ntp_time_u64(std::time::UNIX_EPOCH).as_u64()
+                )),
+            }]
+        );
+
+        let mut restored = Context::new(TimestampingMode::Rtp);
+        for ssrc in &snapshot {
+            restored.restore(ssrc);
+        }
+        assert!(restored.has_clock_rate(0x12345678));
+
+        // The NTP time is known from the first packet on
+        assert_eq!(
+            restored.calculate_pts(0x12345678, 90000, 0),
+            (
+                0,
+                Some(system_time_to_ntp_time_u64(
+                    std::time::UNIX_EPOCH + Duration::from_millis(1000)
+                ))
+            )
+        );
+        assert_eq!(restored.snapshot([0x12345678]), snapshot);
+    }
+
     #[test]
     fn test_two_streams_with_sr() {
         init_logs();
@@ -761,6 +1293,93 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_shared_context_offsets() {
+        init_logs();
+
+        // Audio and video received by different elements, synchronized through the
+        // same sync group
+        let audio_ctx = context_get_or_init(Some("test-shared-offsets"), TimestampingMode::Rtp);
+        let video_ctx = context_get_or_init(Some("test-shared-offsets"), TimestampingMode::Rtp);
+        assert!(Arc::ptr_eq(&audio_ctx, &video_ctx));
+        let other_ctx = context_get_or_init(None, TimestampingMode::Rtp);
+        assert!(!Arc::ptr_eq(&audio_ctx, &other_ctx));
+
+        let mut now = 0;
+
+        {
+            let mut ctx = audio_ctx.lock().unwrap();
+            ctx.set_clock_rate(0x12345, 48000);
+            ctx.associate(0x12345, "foo@bar");
+            ctx.add_sender_report(
+                0x12345,
+                0,
+                system_time_to_ntp_time_u64(std::time::UNIX_EPOCH).as_u64(),
+            );
+        }
+        {
+            let mut ctx = video_ctx.lock().unwrap();
+            ctx.set_clock_rate(0x67890, 90000);
+            ctx.associate(0x67890, "foo@bar");
+            ctx.add_sender_report(
+                0x67890,
+                0,
+                system_time_to_ntp_time_u64(std::time::UNIX_EPOCH).as_u64(),
+            );
+        }
+
+        assert_eq!(
+            audio_ctx.lock().unwrap().calculate_pts(0x12345, 0, now),
+            (0, Some(system_time_to_ntp_time_u64(std::time::UNIX_EPOCH)))
+        );
+
+        // No offset until the delay of the video stream is known
+        let offsets = video_ctx.lock().unwrap().offsets();
+        assert_eq!(offsets.len(), 2);
+        assert_eq!(offsets[0].ssrc, 0x12345);
+        assert_eq!(offsets[0].cname.as_deref(), Some("foo@bar"));
+        assert!(offsets[0].delay.is_some());
+        assert_eq!(offsets[0].offset, None);
+        assert_eq!(offsets[1].ssrc, 0x67890);
+        assert_eq!(offsets[1].delay, None);
+        assert_eq!(offsets[1].offset, None);
+
+        now += 500_000_000;
+
+        // Video arrives 500ms later than audio
+        assert_eq!(
+            video_ctx.lock().unwrap().calculate_pts(0x67890, 0, now),
+            (
+                500_000_000,
+                Some(system_time_to_ntp_time_u64(std::time::UNIX_EPOCH))
+            )
+        );
+        assert_eq!(
+            audio_ctx.lock().unwrap().calculate_pts(0x12345, 24000, now),
+            (
+                1_000_000_000,
+                Some(system_time_to_ntp_time_u64(
+                    std::time::UNIX_EPOCH + Duration::from_millis(500)
+                ))
+            )
+        );
+
+        let offsets = audio_ctx.lock().unwrap().offsets();
+        assert_eq!(
+            offsets[1].delay.unwrap() - offsets[0].delay.unwrap(),
+            500_000_000
+        );
+        assert_eq!(offsets[0].offset, Some(500_000_000));
+        assert_eq!(offsets[1].offset, Some(0));
+
+        drop(audio_ctx);
+        drop(video_ctx);
+
+        // The group is gone once no element uses it anymore
+        let ctx = context_get_or_init(Some("test-shared-offsets"), TimestampingMode::Rtp);
+        assert!(ctx.lock().unwrap().offsets().is_empty());
+    }
+
     #[test]
     fn test_two_streams_with_sr_different_cnames() {
         init_logs();
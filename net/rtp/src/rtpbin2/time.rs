@@ -30,6 +30,11 @@ impl NtpTime {
     pub fn as_u64(self) -> u64 {
         self.0
     }
+
+    /// The 24 bit 6.18 fixed point representation used by the abs-send-time RTP header extension
+    pub fn as_abs_send_time(self) -> u32 {
+        ((self.0 >> 14) & 0xffffff) as u32
+    }
 }
 
 impl Sub for NtpTime {
@@ -60,3 +65,18 @@ impl From<u64> for NtpTime {
         NtpTime(value)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn abs_send_time() {
+        // 1.5s
+        let ntp = NtpTime::from(0x0000_0001_8000_0000);
+        assert_eq!(ntp.as_abs_send_time(), 0x06_0000);
+        // Only the lower 6 bits of the seconds are kept
+        let ntp = NtpTime::from(0x0000_0041_0000_0000);
+        assert_eq!(ntp.as_abs_send_time(), 0x04_0000);
+    }
+}
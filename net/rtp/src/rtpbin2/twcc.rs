@@ -0,0 +1,466 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Sender side of Transport-wide Congestion Control as specified in
+//! draft-holmer-rmcat-transport-wide-cc-extensions-01.
+//!
+//! The transport-wide sequence numbers of sent packets are tracked together with their send time
+//! and size, and combined with the arrival times reported in the RTCP transport feedback of the
+//! receiver to calculate the statistics consumed by congestion controllers.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use rtcp_types::{FciParser, RtcpParseError, TransportFeedback};
+
+/// URI of the transport-wide sequence number header extension
+pub const TWCC_URI: &str =
+    "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01";
+/// Size of the transport-wide sequence number header extension element
+pub const TWCC_EXT_LEN: usize = 2;
+
+/// Feedback message type of the transport-wide congestion control feedback
+const TWCC_FCI_FORMAT: u8 = 15;
+
+const FCI_HEADER_LEN: usize = 8;
+/// Unit of the reference time in microseconds
+const REFERENCE_TIME_UNIT_US: i64 = 64_000;
+/// Unit of the receive deltas in microseconds
+const DELTA_UNIT_US: i64 = 250;
+
+/// Sent packets for which no feedback arrived within this time are forgotten
+const SENT_PACKET_TIMEOUT: Duration = Duration::from_secs(2);
+/// Time window of sent packets the statistics are calculated over
+const STATS_WINDOW: Duration = Duration::from_millis(300);
+
+const SYMBOL_NOT_RECEIVED: u8 = 0;
+const SYMBOL_SMALL_DELTA: u8 = 1;
+const SYMBOL_LARGE_DELTA: u8 = 2;
+
+/// A parsed transport-wide congestion control feedback
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TwccFeedback {
+    pub feedback_count: u8,
+    /// Transport-wide sequence number of each reported packet together with its arrival time in
+    /// microseconds on the receiver's clock, or `None` if it was not received
+    pub packets: Vec<(u16, Option<i64>)>,
+}
+
+impl<'a> FciParser<'a> for TwccFeedback {
+    const PACKET_TYPE: u8 = TransportFeedback::PACKET_TYPE;
+    const FCI_FORMAT: u8 = TWCC_FCI_FORMAT;
+
+    fn parse(data: &'a [u8]) -> Result<Self, RtcpParseError> {
+        if data.len() < FCI_HEADER_LEN {
+            return Err(RtcpParseError::Truncated {
+                expected: FCI_HEADER_LEN,
+                actual: data.len(),
+            });
+        }
+
+        let base_seqnum = u16::from_be_bytes([data[0], data[1]]);
+        let status_count = u16::from_be_bytes([data[2], data[3]]) as usize;
+        // 24 bit signed integer
+        let reference_time = i32::from_be_bytes([data[4], data[5], data[6], 0]) >> 8;
+        let feedback_count = data[7];
+
+        let truncated = |expected: usize| RtcpParseError::Truncated {
+            expected,
+            actual: data.len(),
+        };
+
+        // Packet status chunks until all packets are covered
+        let mut symbols = Vec::with_capacity(status_count);
+        let mut offset = FCI_HEADER_LEN;
+        while symbols.len() < status_count {
+            let Some(chunk) = data.get(offset..offset + 2) else {
+                return Err(truncated(offset + 2));
+            };
+            let chunk = u16::from_be_bytes([chunk[0], chunk[1]]);
+            offset += 2;
+
+            let remaining = status_count - symbols.len();
+            if chunk & 0x8000 == 0 {
+                // Run length chunk
+                let symbol = ((chunk >> 13) & 0b11) as u8;
+                let run_length = (chunk & 0x1fff) as usize;
+                symbols.resize(symbols.len() + run_length.min(remaining), symbol);
+            } else if chunk & 0x4000 == 0 {
+                // Status vector chunk with 14 one-bit symbols
+                symbols.extend(
+                    (0..14)
+                        .map(|i| ((chunk >> (13 - i)) & 0b1) as u8)
+                        .take(remaining),
+                );
+            } else {
+                // Status vector chunk with 7 two-bit symbols
+                symbols.extend(
+                    (0..7)
+                        .map(|i| ((chunk >> (12 - 2 * i)) & 0b11) as u8)
+                        .take(remaining),
+                );
+            }
+        }
+
+        // Receive deltas of all received packets
+        let mut arrival_time = reference_time as i64 * REFERENCE_TIME_UNIT_US;
+        let mut packets = Vec::with_capacity(status_count);
+        for (i, symbol) in symbols.into_iter().enumerate() {
+            let seqnum = base_seqnum.wrapping_add(i as u16);
+            let delta = match symbol {
+                SYMBOL_NOT_RECEIVED => {
+                    packets.push((seqnum, None));
+                    continue;
+                }
+                SYMBOL_SMALL_DELTA => {
+                    let Some(delta) = data.get(offset) else {
+                        return Err(truncated(offset + 1));
+                    };
+                    offset += 1;
+                    *delta as i64
+                }
+                SYMBOL_LARGE_DELTA => {
+                    let Some(delta) = data.get(offset..offset + 2) else {
+                        return Err(truncated(offset + 2));
+                    };
+                    offset += 2;
+                    i16::from_be_bytes([delta[0], delta[1]]) as i64
+                }
+                // Reserved, treat like a packet without information
+                _ => {
+                    packets.push((seqnum, None));
+                    continue;
+                }
+            };
+
+            arrival_time += delta * DELTA_UNIT_US;
+            packets.push((seqnum, Some(arrival_time)));
+        }
+
+        Ok(TwccFeedback {
+            feedback_count,
+            packets,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SentPacket {
+    seqnum: u16,
+    sent: Instant,
+    size: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ReportedPacket {
+    sent: Instant,
+    size: usize,
+    /// Arrival time in microseconds on the receiver's clock
+    arrival: Option<i64>,
+}
+
+/// Statistics over the packets of the last feedback window, in the format of the `twcc-stats`
+/// of `rtpsession`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct TwccStats {
+    /// Bits per second sent
+    pub bitrate_sent: u32,
+    /// Bits per second received by the peer
+    pub bitrate_recv: u32,
+    pub packets_sent: u32,
+    pub packets_recv: u32,
+    pub packet_loss_pct: f64,
+    /// Average difference in ns between the inter-arrival and inter-departure time of
+    /// consecutive received packets
+    pub avg_delta_of_delta: i64,
+}
+
+impl From<TwccStats> for gst::Structure {
+    fn from(stats: TwccStats) -> gst::Structure {
+        gst::Structure::builder("RTPTWCCStats")
+            .field("bitrate-sent", stats.bitrate_sent)
+            .field("bitrate-recv", stats.bitrate_recv)
+            .field("packets-sent", stats.packets_sent)
+            .field("packets-recv", stats.packets_recv)
+            .field("packet-loss-pct", stats.packet_loss_pct)
+            .field("avg-delta-of-delta", stats.avg_delta_of_delta)
+            .build()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Twcc {
+    /// Sent packets without feedback yet, in send order
+    sent: VecDeque<SentPacket>,
+    /// Packets with feedback inside the statistics window, in send order
+    reported: VecDeque<ReportedPacket>,
+    last_feedback_count: Option<u8>,
+    stats: Option<TwccStats>,
+}
+
+impl Twcc {
+    /// Track a sent packet with its transport-wide sequence number and size in bytes
+    pub fn packet_sent(&mut self, seqnum: u16, size: usize, now: Instant) {
+        while self
+            .sent
+            .front()
+            .is_some_and(|packet| now.saturating_duration_since(packet.sent) > SENT_PACKET_TIMEOUT)
+        {
+            self.sent.pop_front();
+        }
+
+        self.sent.push_back(SentPacket {
+            seqnum,
+            sent: now,
+            size,
+        });
+    }
+
+    fn take_sent_packet(&mut self, seqnum: u16) -> Option<SentPacket> {
+        let first = self.sent.front()?.seqnum;
+        // Sequence numbers are usually consecutive so this is the position of the packet
+        let idx = seqnum.wrapping_sub(first) as usize;
+        let idx = if self.sent.get(idx).is_some_and(|p| p.seqnum == seqnum) {
+            idx
+        } else {
+            self.sent.iter().position(|p| p.seqnum == seqnum)?
+        };
+
+        // Earlier packets can still be reported by reordered feedback and are only forgotten
+        // after a timeout
+        self.sent.remove(idx)
+    }
+
+    /// Handle a received feedback and update the statistics.
+    ///
+    /// Returns `false` if the feedback was a duplicate or did not report any sent packet.
+    pub fn handle_feedback(&mut self, feedback: &TwccFeedback) -> bool {
+        if self.last_feedback_count == Some(feedback.feedback_count) {
+            return false;
+        }
+        self.last_feedback_count = Some(feedback.feedback_count);
+
+        let mut updated = false;
+        for &(seqnum, arrival) in &feedback.packets {
+            let Some(packet) = self.take_sent_packet(seqnum) else {
+                continue;
+            };
+            // Feedback can be reordered, keep the packets in send order
+            let idx = self
+                .reported
+                .partition_point(|reported| reported.sent <= packet.sent);
+            self.reported.insert(
+                idx,
+                ReportedPacket {
+                    sent: packet.sent,
+                    size: packet.size,
+                    arrival,
+                },
+            );
+            updated = true;
+        }
+        if !updated {
+            return false;
+        }
+
+        let newest = self.reported.back().unwrap().sent;
+        while self
+            .reported
+            .front()
+            .is_some_and(|packet| newest.saturating_duration_since(packet.sent) > STATS_WINDOW)
+        {
+            self.reported.pop_front();
+        }
+
+        self.stats = Some(self.calculate_stats());
+
+        true
+    }
+
+    fn calculate_stats(&self) -> TwccStats {
+        let packets_sent = self.reported.len();
+        let received = self
+            .reported
+            .iter()
+            .filter_map(|packet| packet.arrival.map(|arrival| (packet, arrival)))
+            .collect::<Vec<_>>();
+        let packets_recv = received.len();
+
+        let first = self.reported.front().unwrap();
+        let last = self.reported.back().unwrap();
+        // The size of the first packet was sent before the start of the window
+        let send_duration = last.sent.saturating_duration_since(first.sent);
+        let bits_sent = self
+            .reported
+            .iter()
+            .skip(1)
+            .map(|p| p.size * 8)
+            .sum::<usize>();
+        let bitrate_sent = bitrate(bits_sent, send_duration.as_micros() as i64);
+
+        let mut bitrate_recv = 0;
+        let mut avg_delta_of_delta = 0;
+        if let (Some((_, first_arrival)), Some((_, last_arrival))) =
+            (received.first(), received.last())
+        {
+            let bits_recv = received.iter().skip(1).map(|(p, _)| p.size * 8).sum();
+            bitrate_recv = bitrate(bits_recv, last_arrival - first_arrival);
+
+            if received.len() > 1 {
+                let sum = received
+                    .windows(2)
+                    .map(|w| {
+                        let (prev, prev_arrival) = w[0];
+                        let (cur, cur_arrival) = w[1];
+                        let send_delta = if cur.sent >= prev.sent {
+                            (cur.sent - prev.sent).as_nanos() as i64
+                        } else {
+                            -((prev.sent - cur.sent).as_nanos() as i64)
+                        };
+                        (cur_arrival - prev_arrival) * 1_000 - send_delta
+                    })
+                    .sum::<i64>();
+                avg_delta_of_delta = sum / (received.len() as i64 - 1);
+            }
+        }
+
+        TwccStats {
+            bitrate_sent,
+            bitrate_recv,
+            packets_sent: packets_sent as u32,
+            packets_recv: packets_recv as u32,
+            packet_loss_pct: (packets_sent - packets_recv) as f64 * 100. / packets_sent as f64,
+            avg_delta_of_delta,
+        }
+    }
+
+    /// Statistics as of the last received feedback
+    pub fn stats(&self) -> Option<TwccStats> {
+        self.stats
+    }
+}
+
+fn bitrate(bits: usize, duration_us: i64) -> u32 {
+    if duration_us <= 0 {
+        return 0;
+    }
+
+    (bits as u64 * 1_000_000 / duration_us as u64).min(u32::MAX as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_feedback() {
+        #[rustfmt::skip]
+        let data = [
+            // base seqnum 10, 8 packets, reference time 1 (64ms), feedback count 3
+            0x00, 0x0a, 0x00, 0x08, 0x00, 0x00, 0x01, 0x03,
+            // run length chunk: 3 small deltas
+            0x20, 0x03,
+            // two-bit status vector chunk: not received, large delta, small delta, small delta,
+            // not received and two unused symbols
+            0xc9, 0x40,
+            // small deltas
+            0x04, 0x08, 0x00,
+            // large negative delta
+            0xff, 0xfc,
+            // small deltas
+            0x01, 0x02,
+        ];
+
+        let feedback = TwccFeedback::parse(&data).unwrap();
+        assert_eq!(feedback.feedback_count, 3);
+        assert_eq!(
+            feedback.packets,
+            [
+                (10, Some(65_000)),
+                (11, Some(67_000)),
+                (12, Some(67_000)),
+                (13, None),
+                (14, Some(66_000)),
+                (15, Some(66_250)),
+                (16, Some(66_750)),
+                (17, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_truncated() {
+        // One received packet but no delta
+        let data = [0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x20, 0x01];
+        assert!(TwccFeedback::parse(&data).is_err());
+        assert!(TwccFeedback::parse(&data[..4]).is_err());
+    }
+
+    #[test]
+    fn stats() {
+        let start = Instant::now();
+        let mut twcc = Twcc::default();
+        assert_eq!(twcc.stats(), None);
+
+        // 10 packets of 1000 bytes every 10ms
+        for i in 0..10u16 {
+            twcc.packet_sent(i, 1000, start + Duration::from_millis(10 * i as u64));
+        }
+
+        // The second packet is lost and the packets arrive every 11ms
+        let packets = (0..10u16)
+            .map(|i| (i, (i != 1).then_some(i as i64 * 11_000)))
+            .collect::<Vec<_>>();
+
+        assert!(twcc.handle_feedback(&TwccFeedback {
+            feedback_count: 0,
+            packets: packets.clone(),
+        }));
+        // Duplicate feedback is ignored
+        assert!(!twcc.handle_feedback(&TwccFeedback {
+            feedback_count: 0,
+            packets,
+        }));
+
+        let stats = twcc.stats().unwrap();
+        assert_eq!(stats.packets_sent, 10);
+        assert_eq!(stats.packets_recv, 9);
+        assert_eq!(stats.packet_loss_pct, 10.);
+        // 9 packets over 90ms
+        assert_eq!(stats.bitrate_sent, 800_000);
+        // 8 packets over 99ms
+        assert_eq!(stats.bitrate_recv, 646_464);
+        // 1ms more per packet than sent, 2ms for the packet after the lost one
+        assert_eq!(stats.avg_delta_of_delta, 9_000_000 / 8);
+    }
+
+    #[test]
+    fn reordered_feedback() {
+        let start = Instant::now();
+        let mut twcc = Twcc::default();
+
+        for i in 0..10u16 {
+            twcc.packet_sent(i, 1000, start + Duration::from_millis(10 * i as u64));
+        }
+
+        // Feedback for the later packets arrives first
+        assert!(twcc.handle_feedback(&TwccFeedback {
+            feedback_count: 1,
+            packets: (5..10u16).map(|i| (i, Some(i as i64 * 10_000))).collect(),
+        }));
+        assert_eq!(twcc.stats().unwrap().packets_sent, 5);
+
+        // The earlier packets are still known
+        assert!(twcc.handle_feedback(&TwccFeedback {
+            feedback_count: 0,
+            packets: (0..5u16).map(|i| (i, Some(i as i64 * 10_000))).collect(),
+        }));
+        assert_eq!(twcc.stats().unwrap().packets_sent, 10);
+        assert_eq!(twcc.stats().unwrap().packets_recv, 10);
+
+        // Nothing is left to report
+        assert!(!twcc.handle_feedback(&TwccFeedback {
+            feedback_count: 2,
+            packets: (0..10u16).map(|i| (i, Some(i as i64 * 10_000))).collect(),
+        }));
+    }
+}
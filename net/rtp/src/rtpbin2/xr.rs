@@ -0,0 +1,230 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Minimal RTCP Extended Reports (RFC 3611) support for the Receiver Reference Time and DLRR
+//! report blocks.
+
+use rtcp_types::{RtcpPacketWriter, RtcpWriteError};
+
+/// RTCP packet type of an XR packet
+pub const XR_PACKET_TYPE: u8 = 207;
+
+const RRTR_BLOCK_TYPE: u8 = 4;
+const DLRR_BLOCK_TYPE: u8 = 5;
+
+const XR_HEADER_LEN: usize = 8;
+const BLOCK_HEADER_LEN: usize = 4;
+const RRTR_BLOCK_LEN: usize = BLOCK_HEADER_LEN + 8;
+const DLRR_SUB_BLOCK_LEN: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DlrrSubBlock {
+    pub ssrc: u32,
+    /// middle 32 bits of the NTP timestamp of the last received RRTR block
+    pub last_rr: u32,
+    /// 16.16 fixed point delay since the last received RRTR block
+    pub delay_since_last_rr: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum XrBlock {
+    /// Receiver Reference Time with a 64-bit NTP timestamp
+    Rrtr(u64),
+    Dlrr(Vec<DlrrSubBlock>),
+}
+
+/// A parsed XR packet. Unsupported report blocks are skipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xr {
+    pub ssrc: u32,
+    pub blocks: Vec<XrBlock>,
+}
+
+impl Xr {
+    /// Parse a complete XR packet including the RTCP header
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < XR_HEADER_LEN || data[0] >> 6 != 2 || data[1] != XR_PACKET_TYPE {
+            return None;
+        }
+
+        let len = (u16::from_be_bytes([data[2], data[3]]) as usize + 1) * 4;
+        if data.len() < len {
+            return None;
+        }
+        let mut payload_len = len;
+        if data[0] & 0x20 != 0 {
+            let padding = data[len - 1] as usize;
+            payload_len = payload_len.checked_sub(padding)?;
+        }
+        if payload_len < XR_HEADER_LEN {
+            return None;
+        }
+
+        let ssrc = u32::from_be_bytes(data[4..8].try_into().unwrap());
+        let mut blocks = vec![];
+        let mut data = &data[XR_HEADER_LEN..payload_len];
+        while data.len() >= BLOCK_HEADER_LEN {
+            let block_type = data[0];
+            let block_len = BLOCK_HEADER_LEN + u16::from_be_bytes([data[2], data[3]]) as usize * 4;
+            if data.len() < block_len {
+                return None;
+            }
+            let content = &data[BLOCK_HEADER_LEN..block_len];
+
+            match block_type {
+                RRTR_BLOCK_TYPE if content.len() == 8 => {
+                    blocks.push(XrBlock::Rrtr(u64::from_be_bytes(
+                        content.try_into().unwrap(),
+                    )));
+                }
+                DLRR_BLOCK_TYPE => {
+                    blocks.push(XrBlock::Dlrr(
+                        content
+                            .chunks_exact(DLRR_SUB_BLOCK_LEN)
+                            .map(|sub_block| DlrrSubBlock {
+                                ssrc: u32::from_be_bytes(sub_block[0..4].try_into().unwrap()),
+                                last_rr: u32::from_be_bytes(sub_block[4..8].try_into().unwrap()),
+                                delay_since_last_rr: u32::from_be_bytes(
+                                    sub_block[8..12].try_into().unwrap(),
+                                ),
+                            })
+                            .collect(),
+                    ));
+                }
+                _ => (),
+            }
+
+            data = &data[block_len..];
+        }
+
+        Some(Self { ssrc, blocks })
+    }
+}
+
+/// Writer for an XR packet that can be added to a compound RTCP packet
+#[derive(Debug, Clone)]
+pub struct XrBuilder {
+    ssrc: u32,
+    blocks: Vec<XrBlock>,
+}
+
+impl XrBuilder {
+    pub fn new(ssrc: u32) -> Self {
+        Self {
+            ssrc,
+            blocks: vec![],
+        }
+    }
+
+    pub fn add_block(mut self, block: XrBlock) -> Self {
+        self.blocks.push(block);
+        self
+    }
+
+    fn block_len(block: &XrBlock) -> usize {
+        match block {
+            XrBlock::Rrtr(_) => RRTR_BLOCK_LEN,
+            XrBlock::Dlrr(sub_blocks) => BLOCK_HEADER_LEN + sub_blocks.len() * DLRR_SUB_BLOCK_LEN,
+        }
+    }
+}
+
+impl RtcpPacketWriter for XrBuilder {
+    fn calculate_size(&self) -> Result<usize, RtcpWriteError> {
+        Ok(XR_HEADER_LEN + self.blocks.iter().map(Self::block_len).sum::<usize>())
+    }
+
+    fn write_into_unchecked(&self, buf: &mut [u8]) -> usize {
+        let len = XR_HEADER_LEN + self.blocks.iter().map(Self::block_len).sum::<usize>();
+
+        buf[0] = 0x80;
+        buf[1] = XR_PACKET_TYPE;
+        buf[2..4].copy_from_slice(&((len / 4 - 1) as u16).to_be_bytes());
+        buf[4..8].copy_from_slice(&self.ssrc.to_be_bytes());
+
+        let mut offset = XR_HEADER_LEN;
+        for block in &self.blocks {
+            let block_len = Self::block_len(block);
+            let block_type = match block {
+                XrBlock::Rrtr(_) => RRTR_BLOCK_TYPE,
+                XrBlock::Dlrr(_) => DLRR_BLOCK_TYPE,
+            };
+            buf[offset] = block_type;
+            buf[offset + 1] = 0;
+            buf[offset + 2..offset + 4]
+                .copy_from_slice(&(((block_len - BLOCK_HEADER_LEN) / 4) as u16).to_be_bytes());
+            offset += BLOCK_HEADER_LEN;
+
+            match block {
+                XrBlock::Rrtr(ntp_timestamp) => {
+                    buf[offset..offset + 8].copy_from_slice(&ntp_timestamp.to_be_bytes());
+                    offset += 8;
+                }
+                XrBlock::Dlrr(sub_blocks) => {
+                    for sub_block in sub_blocks {
+                        buf[offset..offset + 4].copy_from_slice(&sub_block.ssrc.to_be_bytes());
+                        buf[offset + 4..offset + 8]
+                            .copy_from_slice(&sub_block.last_rr.to_be_bytes());
+                        buf[offset + 8..offset + 12]
+                            .copy_from_slice(&sub_block.delay_since_last_rr.to_be_bytes());
+                        offset += DLRR_SUB_BLOCK_LEN;
+                    }
+                }
+            }
+        }
+
+        offset
+    }
+
+    fn get_padding(&self) -> Option<u8> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xr_roundtrip() {
+        let xr = XrBuilder::new(0x1234_5678)
+            .add_block(XrBlock::Rrtr(0x0102_0304_0506_0708))
+            .add_block(XrBlock::Dlrr(vec![
+                DlrrSubBlock {
+                    ssrc: 1,
+                    last_rr: 0x0304_0506,
+                    delay_since_last_rr: 0x0001_0000,
+                },
+                DlrrSubBlock {
+                    ssrc: 2,
+                    last_rr: 0,
+                    delay_since_last_rr: 0,
+                },
+            ]));
+
+        let size = xr.calculate_size().unwrap();
+        assert_eq!(size, 8 + 12 + 4 + 24);
+        let mut data = vec![0; size];
+        assert_eq!(xr.write_into_unchecked(&mut data), size);
+        assert_eq!(&data[..4], &[0x80, XR_PACKET_TYPE, 0x00, 0x0b]);
+
+        let parsed = Xr::parse(&data).unwrap();
+        assert_eq!(parsed.ssrc, 0x1234_5678);
+        assert_eq!(parsed.blocks, xr.blocks);
+    }
+
+    #[test]
+    fn xr_skip_unknown_blocks() {
+        let data = [
+            0x80, 0xcf, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, // header
+            0x06, 0x00, 0x00, 0x01, 0xff, 0xff, 0xff, 0xff, // unknown block type 6
+            0x04, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, // rrtr
+        ];
+
+        let parsed = Xr::parse(&data).unwrap();
+        assert_eq!(parsed.ssrc, 1);
+        assert_eq!(parsed.blocks, vec![XrBlock::Rrtr(0x0000_0001_0000_0002)]);
+
+        // truncated block
+        assert!(Xr::parse(&data[..24]).is_none());
+    }
+}
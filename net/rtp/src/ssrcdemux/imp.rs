@@ -0,0 +1,477 @@
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-rtpssrcdemux2
+ * @see_also: rtprecv, rtpptdemux2
+ *
+ * Demultiplexes RTP and RTCP packets by their SSRC, without requiring a full `rtprecv` and its
+ * jitterbuffers.
+ *
+ * For every SSRC a pair of source pads named `src_<ssrc>` and `rtcp_src_<ssrc>` is added the
+ * first time an RTP or RTCP packet of that SSRC arrives on the `sink` or `rtcp_sink` pad. RTCP
+ * packets are assigned to the SSRC of the sender of the first packet of a compound packet.
+ *
+ * The caps of the RTP source pads are the caps of the `sink` pad with the `ssrc` field set.
+ *
+ * The source pads of an SSRC can be removed with the `clear-ssrc` action signal, they are added
+ * again when another packet of that SSRC arrives.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 udpsrc port=5004 caps='application/x-rtp,media=video,encoding-name=VP8,clock-rate=90000' ! \
+ *   rtpssrcdemux2 name=d d.src_1234 ! queue ! rtpvp8depay2 ! vp8dec ! videoconvert ! autovideosink
+ * ]|
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use gst::{glib, prelude::*, subclass::prelude::*};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "rtpssrcdemux2",
+        gst::DebugColorFlags::empty(),
+        Some("RTP SSRC demuxer"),
+    )
+});
+
+#[derive(Clone)]
+struct SsrcPads {
+    rtp_pad: gst::Pad,
+    rtcp_pad: gst::Pad,
+}
+
+#[derive(Default)]
+struct State {
+    pads: HashMap<u32, SsrcPads>,
+}
+
+pub struct RtpSsrcDemux {
+    rtp_sinkpad: gst::Pad,
+    rtcp_sinkpad: gst::Pad,
+    state: Mutex<State>,
+    /// Serializes the creation of new source pads between the RTP and RTCP streaming threads.
+    pad_creation_lock: Mutex<()>,
+    rtp_flow_combiner: Mutex<gst_base::UniqueFlowCombiner>,
+    rtcp_flow_combiner: Mutex<gst_base::UniqueFlowCombiner>,
+}
+
+/// Returns the SSRC of the sender of the first packet of a compound RTCP packet.
+fn rtcp_sender_ssrc(data: &[u8]) -> Option<u32> {
+    let mut compound = rtcp_types::Compound::parse(data).ok()?;
+
+    match compound.next()?.ok()? {
+        rtcp_types::Packet::Sr(sr) => Some(sr.ssrc()),
+        rtcp_types::Packet::Rr(rr) => Some(rr.ssrc()),
+        rtcp_types::Packet::Sdes(sdes) => sdes.chunks().next().map(|chunk| chunk.ssrc()),
+        rtcp_types::Packet::Bye(bye) => bye.ssrcs().next(),
+        rtcp_types::Packet::App(app) => Some(app.ssrc()),
+        rtcp_types::Packet::TransportFeedback(fb) => Some(fb.sender_ssrc()),
+        rtcp_types::Packet::PayloadFeedback(fb) => Some(fb.sender_ssrc()),
+        rtcp_types::Packet::Unknown(_) => None,
+    }
+}
+
+impl RtpSsrcDemux {
+    fn rtp_caps(&self, ssrc: u32) -> gst::Caps {
+        let mut caps = self
+            .rtp_sinkpad
+            .current_caps()
+            .unwrap_or_else(|| gst::Caps::new_empty_simple("application/x-rtp"));
+        caps.make_mut().set("ssrc", ssrc);
+        caps
+    }
+
+    fn rtcp_caps(&self) -> gst::Caps {
+        self.rtcp_sinkpad
+            .current_caps()
+            .unwrap_or_else(|| gst::Caps::new_empty_simple("application/x-rtcp"))
+    }
+
+    /// Creates a new source pad and stores the sticky events of the corresponding sink pad on it.
+    fn create_pad(
+        &self,
+        sinkpad: &gst::Pad,
+        templ_name: &str,
+        ssrc: u32,
+        caps: &gst::Caps,
+    ) -> gst::Pad {
+        let templ = self.obj().pad_template(templ_name).unwrap();
+        let name = templ_name.replace("%u", &ssrc.to_string());
+        let pad = gst::Pad::builder_from_template(&templ)
+            .name(name.as_str())
+            .build();
+
+        pad.set_active(true).unwrap();
+
+        let stream_id = pad.create_stream_id(&*self.obj(), Some(&name));
+        let mut stored_caps = false;
+        let mut stored_stream_start = false;
+        sinkpad.sticky_events_foreach(|ev| {
+            if let gst::EventView::StreamStart(ev) = ev.view() {
+                let mut builder = gst::event::StreamStart::builder(&stream_id)
+                    .seqnum(ev.seqnum())
+                    .flags(ev.stream_flags());
+                if let Some(group_id) = ev.group_id() {
+                    builder = builder.group_id(group_id);
+                }
+                let _ = pad.store_sticky_event(&builder.build());
+                stored_stream_start = true;
+            } else if ev.type_() < gst::EventType::Caps {
+                let _ = pad.store_sticky_event(ev);
+            } else if ev.type_() > gst::EventType::Caps {
+                if !stored_caps {
+                    let _ = pad.store_sticky_event(&gst::event::Caps::new(caps));
+                    stored_caps = true;
+                }
+                let _ = pad.store_sticky_event(ev);
+            }
+
+            std::ops::ControlFlow::Continue(gst::EventForeachAction::Keep)
+        });
+
+        if !stored_stream_start {
+            let _ = pad.store_sticky_event(&gst::event::StreamStart::new(&stream_id));
+        }
+        if !stored_caps {
+            let _ = pad.store_sticky_event(&gst::event::Caps::new(caps));
+        }
+
+        pad
+    }
+
+    fn ensure_pads(&self, ssrc: u32) -> SsrcPads {
+        if let Some(pads) = self.state.lock().unwrap().pads.get(&ssrc) {
+            return pads.clone();
+        }
+
+        let _guard = self.pad_creation_lock.lock().unwrap();
+
+        // Might have been created by the other streaming thread in the meantime
+        if let Some(pads) = self.state.lock().unwrap().pads.get(&ssrc) {
+            return pads.clone();
+        }
+
+        gst::debug!(CAT, imp = self, "Adding pads for SSRC {ssrc:#010x}");
+
+        let pads = SsrcPads {
+            rtp_pad: self.create_pad(&self.rtp_sinkpad, "src_%u", ssrc, &self.rtp_caps(ssrc)),
+            rtcp_pad: self.create_pad(&self.rtcp_sinkpad, "rtcp_src_%u", ssrc, &self.rtcp_caps()),
+        };
+
+        self.rtp_flow_combiner
+            .lock()
+            .unwrap()
+            .add_pad(&pads.rtp_pad);
+        self.rtcp_flow_combiner
+            .lock()
+            .unwrap()
+            .add_pad(&pads.rtcp_pad);
+        self.state.lock().unwrap().pads.insert(ssrc, pads.clone());
+
+        self.obj().add_pad(&pads.rtp_pad).unwrap();
+        self.obj().add_pad(&pads.rtcp_pad).unwrap();
+
+        pads
+    }
+
+    fn remove_ssrc(&self, ssrc: u32) {
+        let _guard = self.pad_creation_lock.lock().unwrap();
+
+        let Some(pads) = self.state.lock().unwrap().pads.remove(&ssrc) else {
+            gst::debug!(CAT, imp = self, "No pads for SSRC {ssrc:#010x}");
+            return;
+        };
+
+        gst::debug!(CAT, imp = self, "Removing pads for SSRC {ssrc:#010x}");
+
+        self.rtp_flow_combiner
+            .lock()
+            .unwrap()
+            .remove_pad(&pads.rtp_pad);
+        self.rtcp_flow_combiner
+            .lock()
+            .unwrap()
+            .remove_pad(&pads.rtcp_pad);
+
+        for pad in [pads.rtp_pad, pads.rtcp_pad] {
+            let _ = pad.set_active(false);
+            let _ = self.obj().remove_pad(&pad);
+        }
+    }
+
+    fn remove_pads(&self) {
+        let ssrcs = self
+            .state
+            .lock()
+            .unwrap()
+            .pads
+            .keys()
+            .copied()
+            .collect::<Vec<_>>();
+        for ssrc in ssrcs {
+            self.remove_ssrc(ssrc);
+        }
+
+        self.rtp_flow_combiner.lock().unwrap().reset();
+        self.rtcp_flow_combiner.lock().unwrap().reset();
+    }
+
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+        is_rtcp: bool,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let ssrc = {
+            let map = buffer.map_readable().map_err(|_| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Read,
+                    ["Failed to map buffer readable"]
+                );
+                gst::FlowError::Error
+            })?;
+
+            let ssrc = if is_rtcp {
+                rtcp_sender_ssrc(&map)
+            } else {
+                rtp_types::RtpPacket::parse(&map).ok().map(|rtp| rtp.ssrc())
+            };
+
+            match ssrc {
+                Some(ssrc) => ssrc,
+                None => {
+                    gst::warning!(CAT, obj = pad, "Dropping invalid packet");
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+            }
+        };
+
+        let pads = self.ensure_pads(ssrc);
+
+        if is_rtcp {
+            let res = pads.rtcp_pad.push(buffer);
+            self.rtcp_flow_combiner
+                .lock()
+                .unwrap()
+                .update_pad_flow(&pads.rtcp_pad, res)
+        } else {
+            let res = pads.rtp_pad.push(buffer);
+            self.rtp_flow_combiner
+                .lock()
+                .unwrap()
+                .update_pad_flow(&pads.rtp_pad, res)
+        }
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event, is_rtcp: bool) -> bool {
+        gst::log!(CAT, obj = pad, "Handling event {event:?}");
+
+        let pads = self
+            .state
+            .lock()
+            .unwrap()
+            .pads
+            .iter()
+            .map(|(ssrc, pads)| {
+                (
+                    *ssrc,
+                    if is_rtcp {
+                        pads.rtcp_pad.clone()
+                    } else {
+                        pads.rtp_pad.clone()
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+
+        match event.view() {
+            // Every source pad has its own stream id
+            gst::EventView::StreamStart(_) => return true,
+            gst::EventView::Caps(ev) if !is_rtcp => {
+                for (ssrc, srcpad) in pads {
+                    let mut caps = ev.caps_owned();
+                    caps.make_mut().set("ssrc", ssrc);
+                    srcpad.push_event(gst::event::Caps::new(&caps));
+                }
+                return true;
+            }
+            gst::EventView::FlushStop(_) => {
+                if is_rtcp {
+                    self.rtcp_flow_combiner.lock().unwrap().reset();
+                } else {
+                    self.rtp_flow_combiner.lock().unwrap().reset();
+                }
+            }
+            _ => (),
+        }
+
+        if pads.is_empty() {
+            return true;
+        }
+
+        let mut ret = false;
+        for (_, srcpad) in pads {
+            ret |= srcpad.push_event(event.clone());
+        }
+
+        // Sticky events are stored on the source pads even if pushing failed
+        ret || event.is_sticky()
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for RtpSsrcDemux {
+    const NAME: &'static str = "GstRtpSsrcDemux2";
+    type Type = super::RtpSsrcDemux;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let rtp_sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                RtpSsrcDemux::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer, false),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                RtpSsrcDemux::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event, false),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("rtcp_sink").unwrap();
+        let rtcp_sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                RtpSsrcDemux::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer, true),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                RtpSsrcDemux::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event, true),
+                )
+            })
+            .build();
+
+        Self {
+            rtp_sinkpad,
+            rtcp_sinkpad,
+            state: Mutex::new(State::default()),
+            pad_creation_lock: Mutex::new(()),
+            rtp_flow_combiner: Mutex::new(gst_base::UniqueFlowCombiner::new()),
+            rtcp_flow_combiner: Mutex::new(gst_base::UniqueFlowCombiner::new()),
+        }
+    }
+}
+
+impl ObjectImpl for RtpSsrcDemux {
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: LazyLock<Vec<glib::subclass::Signal>> = LazyLock::new(|| {
+            vec![glib::subclass::Signal::builder("clear-ssrc")
+                .param_types([u32::static_type()])
+                .action()
+                .class_handler(|_token, args| {
+                    let demux = args[0].get::<super::RtpSsrcDemux>().unwrap();
+                    let ssrc = args[1].get::<u32>().unwrap();
+                    demux.imp().remove_ssrc(ssrc);
+
+                    None
+                })
+                .build()]
+        });
+
+        SIGNALS.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.rtp_sinkpad).unwrap();
+        obj.add_pad(&self.rtcp_sinkpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for RtpSsrcDemux {}
+
+impl ElementImpl for RtpSsrcDemux {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "RTP SSRC Demuxer",
+                "Demux/Network/RTP",
+                "Demultiplexes RTP and RTCP packets by their SSRC",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let rtp_caps = gst::Caps::builder("application/x-rtp").build();
+            let rtcp_caps = gst::Caps::builder("application/x-rtcp").build();
+
+            vec![
+                gst::PadTemplate::new(
+                    "sink",
+                    gst::PadDirection::Sink,
+                    gst::PadPresence::Always,
+                    &rtp_caps,
+                )
+                .unwrap(),
+                gst::PadTemplate::new(
+                    "rtcp_sink",
+                    gst::PadDirection::Sink,
+                    gst::PadPresence::Always,
+                    &rtcp_caps,
+                )
+                .unwrap(),
+                gst::PadTemplate::new(
+                    "src_%u",
+                    gst::PadDirection::Src,
+                    gst::PadPresence::Sometimes,
+                    &rtp_caps,
+                )
+                .unwrap(),
+                gst::PadTemplate::new(
+                    "rtcp_src_%u",
+                    gst::PadDirection::Src,
+                    gst::PadPresence::Sometimes,
+                    &rtcp_caps,
+                )
+                .unwrap(),
+            ]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        let res = self.parent_change_state(transition)?;
+
+        if transition == gst::StateChange::PausedToReady {
+            self.remove_pads();
+        }
+
+        Ok(res)
+    }
+}
@@ -313,6 +313,11 @@ pub(crate) struct ExtendedTimestamp {
 }
 
 impl ExtendedTimestamp {
+    /// The current extended timestamp
+    pub(crate) fn current(&self) -> Option<u64> {
+        self.last_ext
+    }
+
     /// Produces the next extended timestamp from a new RTP timestamp
     pub(crate) fn next(&mut self, rtp_timestamp: u32) -> u64 {
         let ext = match self.last_ext {
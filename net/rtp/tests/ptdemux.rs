@@ -0,0 +1,191 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+
+use gst::prelude::*;
+use gst_check::Harness;
+use rtp_types::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsrtp::plugin_register_static().expect("rtpptdemux2 test");
+    });
+}
+
+fn generate_rtp_buffer(pt: u8, seqno: u16) -> gst::Buffer {
+    let payload = [pt; 8];
+    let packet = RtpPacketBuilder::new()
+        .ssrc(0x12345678)
+        .payload_type(pt)
+        .sequence_number(seqno)
+        .timestamp(seqno as u32 * 960)
+        .payload(payload.as_slice());
+    let size = packet.calculate_size().unwrap();
+    let mut data = vec![0; size];
+    packet.write_into(&mut data).unwrap();
+    gst::Buffer::from_mut_slice(data)
+}
+
+type SrcHarnesses = Arc<Mutex<Vec<(String, Harness)>>>;
+
+/// Creates a harness for the demuxer sink pad and links a new harness to every added source pad
+fn demux_init(demux: &gst::Element) -> (Harness, SrcHarnesses) {
+    init();
+
+    let src_harnesses = SrcHarnesses::default();
+    demux.connect_pad_added({
+        let src_harnesses = src_harnesses.clone();
+        move |_demux, pad| {
+            let mut h = Harness::new_empty();
+            h.add_element_src_pad(pad);
+            src_harnesses
+                .lock()
+                .unwrap()
+                .push((pad.name().to_string(), h));
+        }
+    });
+
+    let mut h = Harness::with_element(demux, Some("sink"), None);
+    h.set_src_caps(
+        gst::Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("clock-rate", 48_000i32)
+            .build(),
+    );
+    h.play();
+
+    (h, src_harnesses)
+}
+
+fn pad_caps(src_harnesses: &SrcHarnesses, name: &str) -> gst::Caps {
+    let src_harnesses = src_harnesses.lock().unwrap();
+    let (_, h) = src_harnesses.iter().find(|(n, _)| n == name).unwrap();
+    h.sinkpad().unwrap().current_caps().unwrap()
+}
+
+#[test]
+fn test_pt_demux() {
+    init();
+
+    let opus_caps = gst::Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("payload", 97i32)
+        .field("clock-rate", 48_000i32)
+        .field("encoding-name", "OPUS")
+        .build();
+    let demux = gst::ElementFactory::make("rtpptdemux2")
+        .property(
+            "pt-map",
+            gst::Structure::builder("application/x-rtp2-pt-map")
+                .field("97", &opus_caps)
+                .build(),
+        )
+        .build()
+        .unwrap();
+    let (mut h, src_harnesses) = demux_init(&demux);
+
+    for (seqno, pt) in [96, 97, 96, 96].into_iter().enumerate() {
+        assert_eq!(
+            h.push(generate_rtp_buffer(pt, seqno as u16)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+
+    // Not a valid RTP packet
+    assert_eq!(
+        h.push(gst::Buffer::from_slice([0u8; 4])),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    let names = src_harnesses
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["src_96", "src_97"]);
+
+    let caps_96 = pad_caps(&src_harnesses, "src_96");
+    let s = caps_96.structure(0).unwrap();
+    assert_eq!(s.get::<i32>("payload").unwrap(), 96);
+    assert_eq!(s.get::<i32>("clock-rate").unwrap(), 48_000);
+    assert_eq!(pad_caps(&src_harnesses, "src_97"), opus_caps);
+
+    let mut src_harnesses = src_harnesses.lock().unwrap();
+    for (name, h) in src_harnesses.iter_mut() {
+        let expected = if name == "src_96" { 3 } else { 1 };
+        assert_eq!(h.buffers_in_queue(), expected);
+        for _ in 0..expected {
+            let buffer = h.pull().unwrap();
+            let map = buffer.map_readable().unwrap();
+            let packet = RtpPacket::parse(&map).unwrap();
+            assert_eq!(format!("src_{}", packet.payload_type()), *name);
+        }
+    }
+}
+
+#[test]
+fn test_request_pt_map() {
+    init();
+
+    let demux = gst::ElementFactory::make("rtpptdemux2").build().unwrap();
+
+    let requests = Arc::new(AtomicUsize::new(0));
+    demux.connect("request-pt-map", false, {
+        let requests = requests.clone();
+        move |args| {
+            let pt = args[1].get::<u32>().unwrap();
+            let n = requests.fetch_add(1, Ordering::SeqCst);
+            let caps = gst::Caps::builder("application/x-rtp")
+                .field("media", "video")
+                .field("payload", pt as i32)
+                .field("clock-rate", 90_000i32)
+                .field("encoding-name", if n == 0 { "VP8" } else { "VP9" })
+                .build();
+            Some(caps.to_value())
+        }
+    });
+
+    let (mut h, src_harnesses) = demux_init(&demux);
+
+    assert_eq!(
+        h.push(generate_rtp_buffer(100, 0)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        h.push(generate_rtp_buffer(100, 1)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    // The result of the signal is cached in the pt-map
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+    let caps = pad_caps(&src_harnesses, "src_100");
+    let s = caps.structure(0).unwrap();
+    assert_eq!(s.get::<&str>("encoding-name").unwrap(), "VP8");
+    let pt_map = demux.property::<gst::Structure>("pt-map");
+    assert_eq!(pt_map.get::<gst::Caps>("100").unwrap(), caps);
+
+    demux.emit_by_name::<()>("clear-pt-map", &[]);
+    assert_eq!(
+        h.push(generate_rtp_buffer(100, 2)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+    let caps = pad_caps(&src_harnesses, "src_100");
+    let s = caps.structure(0).unwrap();
+    assert_eq!(s.get::<&str>("encoding-name").unwrap(), "VP9");
+
+    let src_harnesses = src_harnesses.lock().unwrap();
+    assert_eq!(src_harnesses.len(), 1);
+    assert_eq!(src_harnesses[0].1.buffers_in_queue(), 3);
+}
@@ -151,6 +151,30 @@ where
         source_stats.get::<u64>("octets-sent").unwrap(),
         n_bytes as u64
     );
+
+    let pacing_stats = session_stats.get::<gst::Structure>("pacing-stats").unwrap();
+    let send_deltas = pacing_stats
+        .get::<gst::Array>("send-delta-histogram")
+        .unwrap();
+    assert_eq!(
+        send_deltas.len(),
+        pacing_stats
+            .get::<gst::Array>("send-delta-bounds")
+            .unwrap()
+            .len()
+            + 1
+    );
+    assert!(pacing_stats.get::<u64>("current-burst-size").unwrap() >= 1);
+
+    let element = h.element().unwrap();
+    let source_stats = element
+        .emit_by_name::<Option<gst::Structure>>("get-source-stats", &[&0u32, &TEST_SSRC])
+        .unwrap();
+    assert_eq!(source_stats.get::<u32>("ssrc").unwrap(), TEST_SSRC);
+    assert_eq!(source_stats.get::<u64>("packets-sent").unwrap(), n_packets);
+    assert!(element
+        .emit_by_name::<Option<gst::Structure>>("get-source-stats", &[&0u32, &(TEST_SSRC + 1)])
+        .is_none());
 }
 
 #[test]
@@ -164,6 +188,94 @@ fn test_send() {
     send_check_stats(&mut h, PACKETS_TEST_1);
 }
 
+#[test]
+fn test_send_abs_send_time() {
+    init();
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtpsend")
+        .property("rtp-id", id.to_string())
+        .build()
+        .unwrap();
+    let mut h = Harness::with_element(&elem, Some("rtp_sink_0"), Some("rtp_src_0"));
+    h.play();
+
+    let caps = Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("payload", TEST_PT as i32)
+        .field("clock-rate", TEST_CLOCK_RATE as i32)
+        .field("encoding-name", "custom-test")
+        .field(
+            "extmap-3",
+            "http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time",
+        )
+        .build();
+    h.set_src_caps(caps);
+
+    send_push(&mut h, PACKETS_TEST_1, false);
+
+    for packet in PACKETS_TEST_1 {
+        let buffer = h.pull().unwrap();
+        let mapped = buffer.map_readable().unwrap();
+        let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+        assert_eq!(rtp.sequence_number(), packet.seq_no);
+        assert_eq!(rtp.payload().len(), packet.payload_len);
+        let (pattern, data) = rtp.extension().unwrap();
+        assert_eq!(pattern, 0xBEDE);
+        // One-byte header with ID 3 and a length of 3 bytes
+        assert_eq!(data[0], 0x32);
+    }
+}
+
+#[test]
+fn test_send_twcc_seqnum() {
+    init();
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtpsend")
+        .property("rtp-id", id.to_string())
+        .build()
+        .unwrap();
+    let mut h = Harness::with_element(&elem, Some("rtp_sink_0"), Some("rtp_src_0"));
+    h.play();
+
+    let caps = Caps::builder("application/x-rtp")
+        .field("media", "video")
+        .field("payload", TEST_PT as i32)
+        .field("clock-rate", TEST_CLOCK_RATE as i32)
+        .field("encoding-name", "custom-test")
+        .field(
+            "extmap-5",
+            "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01",
+        )
+        .build();
+    h.set_src_caps(caps);
+
+    // Packets of two SSRCs share the transport-wide sequence numbers
+    for i in 0..4u16 {
+        let mut buffer = generate_rtp_buffer(500 + i / 2, 20 * (i / 2) as u32, 10);
+        if i % 2 == 1 {
+            let mut mapped = buffer.make_mut().map_writable().unwrap();
+            mapped[8..12].copy_from_slice(&(TEST_SSRC + 1).to_be_bytes());
+        }
+        h.push(buffer).unwrap();
+    }
+
+    for i in 0..4u16 {
+        let buffer = h.pull().unwrap();
+        let mapped = buffer.map_readable().unwrap();
+        let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+        assert_eq!(rtp.ssrc(), TEST_SSRC + (i % 2) as u32);
+        let (pattern, data) = rtp.extension().unwrap();
+        assert_eq!(pattern, 0xBEDE);
+        // One-byte header with ID 5 and a length of 2 bytes
+        assert_eq!(data[0], 0x51);
+        assert_eq!(u16::from_be_bytes([data[1], data[2]]), i);
+    }
+}
+
 #[test]
 fn test_send_list() {
     let mut h = send_init();
@@ -173,6 +285,142 @@ fn test_send_list() {
     send_check_stats(&mut h, PACKETS_TEST_1);
 }
 
+#[test]
+fn test_send_keepalive() {
+    init();
+
+    for method in ["padding", "last-packet", "rtcp-app"] {
+        let id = next_element_counter();
+
+        let elem = gst::ElementFactory::make("rtpsend")
+            .property("rtp-id", id.to_string())
+            .property("keepalive-interval", 50u32)
+            .property_from_str("keepalive-method", method)
+            .build()
+            .unwrap();
+        let mut h = Harness::with_element(&elem, Some("rtp_sink_0"), Some("rtp_src_0"));
+        h.play();
+
+        let caps = Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("payload", TEST_PT as i32)
+            .field("clock-rate", TEST_CLOCK_RATE as i32)
+            .field("encoding-name", "custom-test")
+            .build();
+        h.set_src_caps(caps);
+
+        let packet = PACKETS_TEST_1[0];
+        send_push(&mut h, [packet], false);
+        send_pull(&mut h, [packet]);
+
+        // No more RTP packets, so keepalives are sent instead
+        for _ in 0..2 {
+            let buffer = h.pull().unwrap();
+            let mapped = buffer.map_readable().unwrap();
+            match method {
+                "rtcp-app" => {
+                    assert_eq!(mapped[1], 204);
+                    assert_eq!(&mapped[4..8], &TEST_SSRC.to_be_bytes());
+                }
+                _ => {
+                    let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+                    assert_eq!(rtp.ssrc(), TEST_SSRC);
+                    assert_eq!(rtp.sequence_number(), packet.seq_no);
+                    if method == "padding" {
+                        assert!(rtp.payload().is_empty());
+                        assert_ne!(mapped[0] & 0x20, 0);
+                    } else {
+                        assert_eq!(rtp.payload().len(), packet.payload_len);
+                    }
+                }
+            }
+        }
+
+        // Keepalives are not counted as sent RTP packets
+        send_check_stats(&mut h, [packet]);
+
+        // Nothing is sent anymore after EOS
+        assert!(h.push_event(gst::event::Eos::new()));
+        while h.try_pull().is_some() {}
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(h.try_pull().is_none());
+    }
+}
+
+#[test]
+fn test_send_dtmf() {
+    let mut h = send_init();
+
+    const DTMF_PT: u8 = 101;
+    let dtmf_event = |start: bool| {
+        gst::event::CustomUpstream::new(
+            gst::Structure::builder("dtmf-event")
+                .field("type", 1i32)
+                .field("number", 3i32)
+                .field("volume", 10i32)
+                .field("start", start)
+                .field("pt", DTMF_PT as i32)
+                .build(),
+        )
+    };
+
+    let packets = [
+        PacketInfo {
+            seq_no: 100,
+            rtp_ts: 0,
+            payload_len: 8,
+        },
+        PacketInfo {
+            seq_no: 101,
+            rtp_ts: 960,
+            payload_len: 8,
+        },
+        PacketInfo {
+            seq_no: 102,
+            rtp_ts: 1920,
+            payload_len: 8,
+        },
+        PacketInfo {
+            seq_no: 103,
+            rtp_ts: 2880,
+            payload_len: 8,
+        },
+    ];
+
+    // Returns (seqnum, pt, timestamp, marker) of the next output packet
+    fn pull(h: &mut Harness) -> (u16, u8, u32, bool) {
+        let buffer = h.pull().unwrap();
+        let mapped = buffer.map_readable().unwrap();
+        let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+        assert_eq!(rtp.ssrc(), TEST_SSRC);
+        (
+            rtp.sequence_number(),
+            rtp.payload_type(),
+            rtp.timestamp(),
+            rtp.marker_bit(),
+        )
+    }
+
+    send_push(&mut h, [packets[0]], false);
+    assert_eq!(pull(&mut h), (100, TEST_PT, 0, false));
+
+    // The event starts with the next packet and is sent with the following one
+    assert!(h.push_upstream_event(dtmf_event(true)));
+    send_push(&mut h, [packets[1]], false);
+    assert_eq!(pull(&mut h), (101, TEST_PT, 960, false));
+    send_push(&mut h, [packets[2]], false);
+    assert_eq!(pull(&mut h), (102, DTMF_PT, 960, true));
+    assert_eq!(pull(&mut h), (103, TEST_PT, 1920, false));
+
+    // The end of the event is sent three times, and audio packets continue shifted
+    assert!(h.push_upstream_event(dtmf_event(false)));
+    send_push(&mut h, [packets[3]], false);
+    for seq_no in 104..107 {
+        assert_eq!(pull(&mut h), (seq_no, DTMF_PT, 960, false));
+    }
+    assert_eq!(pull(&mut h), (107, TEST_PT, 2880, false));
+}
+
 #[test]
 fn test_send_benchmark() {
     init();
@@ -280,6 +528,10 @@ fn receive_init() -> Arc<Mutex<gst_check::Harness>> {
         .property("rtp-id", id.to_string())
         .build()
         .unwrap();
+    receive_init_with_element(elem)
+}
+
+fn receive_init_with_element(elem: gst::Element) -> Arc<Mutex<gst_check::Harness>> {
     let h = Arc::new(Mutex::new(Harness::with_element(
         &elem,
         Some("rtp_sink_0"),
@@ -368,8 +620,9 @@ where
     }
 
     let inner = h.lock().unwrap();
-    let stats = inner.element().unwrap().property::<gst::Structure>("stats");
+    let element = inner.element().unwrap();
     drop(inner);
+    let stats = element.property::<gst::Structure>("stats");
 
     let session_stats = stats.get::<gst::Structure>("0").unwrap();
     let source_stats = session_stats
@@ -399,6 +652,15 @@ where
         source_stats.get::<u64>("octets-received").unwrap(),
         n_bytes as u64
     );
+    assert_eq!(source_stats.get::<u64>("packets-duplicated").unwrap(), 0);
+    assert_eq!(source_stats.get::<u64>("packets-late").unwrap(), 0);
+    assert_eq!(source_stats.get::<u64>("packets-recovered").unwrap(), 0);
+    assert_eq!(source_stats.get::<u64>("jitterbuffer-packets").unwrap(), 0);
+    assert_eq!(source_stats.get::<u64>("jitterbuffer-bytes").unwrap(), 0);
+    assert_eq!(
+        source_stats.get::<u64>("jitterbuffer-fill-level").unwrap(),
+        0
+    );
     assert_eq!(jitterbuffer_stats.get::<u64>("num-late").unwrap(), 0);
     assert_eq!(jitterbuffer_stats.get::<u64>("num-lost").unwrap(), 0);
     assert_eq!(jitterbuffer_stats.get::<u64>("num-duplicates").unwrap(), 0);
@@ -411,6 +673,21 @@ where
         jitterbuffer_stats.get::<i32>("ssrc").unwrap(),
         TEST_SSRC as i32
     );
+
+    let source_stats = element
+        .emit_by_name::<Option<gst::Structure>>("get-source-stats", &[&0u32, &TEST_SSRC])
+        .unwrap();
+    assert_eq!(source_stats.get::<u32>("ssrc").unwrap(), TEST_SSRC);
+    assert_eq!(
+        source_stats.get::<u64>("packets-received").unwrap(),
+        n_packets
+    );
+    assert_eq!(source_stats.get::<u64>("packets-duplicated").unwrap(), 0);
+    let jitterbuffers_stats = source_stats.get::<gst::List>("jitterbuffer-stats").unwrap();
+    assert_eq!(jitterbuffers_stats.len(), 1);
+    assert!(element
+        .emit_by_name::<Option<gst::Structure>>("get-source-stats", &[&1u32, &TEST_SSRC])
+        .is_none());
 }
 
 static PACKETS_TEST_1: [PacketInfo; 2] = [
@@ -437,118 +714,872 @@ fn test_receive() {
 }
 
 #[test]
-fn test_receive_list() {
+fn test_receive_negative_running_time() {
     init();
 
     let h = receive_init();
-    receive_push(h.clone(), PACKETS_TEST_1, true);
-    receive_pull(h.clone(), PACKETS_TEST_1);
-    receive_check_stats(h, PACKETS_TEST_1);
+    let push_pad = h
+        .lock()
+        .unwrap()
+        .element()
+        .unwrap()
+        .static_pad("rtp_sink_0")
+        .unwrap()
+        .peer()
+        .unwrap();
+
+    let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    segment.set_start(gst::ClockTime::from_seconds(10));
+    assert!(push_pad.push_event(gst::event::Segment::new(&segment)));
+
+    // DTS before the segment start, resulting in negative running times
+    for (i, packet) in PACKETS_TEST_1.iter().enumerate() {
+        push_pad
+            .push(packet.generate_buffer(Some(gst::ClockTime::from_mseconds(i as u64 * 20))))
+            .unwrap();
+    }
+
+    // The relative timing of the packets is kept instead of clamping them all to 0
+    let mut inner = h.lock().unwrap();
+    let mut last_pts = None;
+    for packet in PACKETS_TEST_1 {
+        let buffer = inner.pull().unwrap();
+        let mapped = buffer.map_readable().unwrap();
+        let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+        assert_eq!(rtp.sequence_number(), packet.seq_no);
+        let pts = buffer.pts().unwrap();
+        if let Some(last_pts) = last_pts {
+            assert!(pts > last_pts);
+        }
+        last_pts = Some(pts);
+    }
 }
 
 #[test]
-fn test_receive_flush() {
+fn test_receive_ts_offset() {
     init();
+
     let h = receive_init();
+    let session = h
+        .lock()
+        .unwrap()
+        .element()
+        .unwrap()
+        .emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+    session.set_property(
+        "ts-offset",
+        gst::ClockTime::from_seconds(100).nseconds() as i64,
+    );
 
     receive_push(h.clone(), PACKETS_TEST_1, false);
 
     let mut inner = h.lock().unwrap();
-    let seqnum = gst::Seqnum::next();
-    inner.push_event(gst::event::FlushStart::builder().seqnum(seqnum).build());
-    inner.push_event(gst::event::FlushStop::builder(false).seqnum(seqnum).build());
+    for packet in PACKETS_TEST_1 {
+        let buffer = inner.pull().unwrap();
+        let mapped = buffer.map_readable().unwrap();
+        let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+        assert_eq!(rtp.sequence_number(), packet.seq_no);
+        assert!(buffer.pts().unwrap() >= gst::ClockTime::from_seconds(100));
+    }
+}
 
-    let event = inner.pull_event().unwrap();
-    let gst::EventView::FlushStart(fs) = event.view() else {
-        unreachable!();
-    };
-    assert_eq!(fs.seqnum(), seqnum);
-    let event = inner.pull_event().unwrap();
-    let gst::EventView::FlushStop(fs) = event.view() else {
-        unreachable!();
+#[test]
+fn test_receive_caps_timing() {
+    init();
+
+    let h = receive_init();
+    h.lock().unwrap().set_src_caps(
+        Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("payload", TEST_PT as i32)
+            .field("clock-rate", TEST_CLOCK_RATE as i32)
+            .field("encoding-name", "custom-test")
+            .field("seqnum-base", 500u32)
+            .field("clock-base", 20u32)
+            .field("npt-start", gst::ClockTime::from_seconds(10).nseconds())
+            .field("play-speed", 1.0f64)
+            .build(),
+    );
+
+    // Sent before the stream was started and dropped
+    let early = PacketInfo {
+        seq_no: 499,
+        rtp_ts: 20,
+        payload_len: 5,
     };
-    assert_eq!(fs.seqnum(), seqnum);
+    receive_push(
+        h.clone(),
+        std::iter::once(early).chain(PACKETS_TEST_1),
+        false,
+    );
+    receive_pull(h.clone(), PACKETS_TEST_1);
+
+    let inner = h.lock().unwrap();
+    let segment = inner
+        .sinkpad()
+        .unwrap()
+        .sticky_event::<gst::event::Segment>(0)
+        .unwrap();
+    let segment = segment.segment().downcast_ref::<gst::ClockTime>().unwrap();
+    assert_eq!(segment.time(), Some(gst::ClockTime::from_seconds(10)));
+    assert_eq!(segment.applied_rate(), 1.0);
+
+    let stats = inner.element().unwrap().property::<gst::Structure>("stats");
+    drop(inner);
+
+    let jitterbuffer_stats = stats
+        .get::<gst::Structure>("0")
+        .unwrap()
+        .get::<gst::List>("jitterbuffer-stats")
+        .unwrap()
+        .first()
+        .unwrap()
+        .get::<gst::Structure>()
+        .unwrap();
+    assert_eq!(jitterbuffer_stats.get::<u64>("num-late").unwrap(), 1);
+    assert_eq!(jitterbuffer_stats.get::<u64>("num-pushed").unwrap(), 2);
 }
 
 #[test]
-fn test_receive_benchmark() {
+fn test_receive_packet_stats() {
     init();
-    let clock = gst::SystemClock::obtain();
-    //const N_PACKETS: usize = 1024 * 1024;
-    const N_PACKETS: usize = 1024;
-    let mut packets = Vec::with_capacity(N_PACKETS);
-    for i in 0..N_PACKETS {
-        packets.push(
-            PacketInfo {
-                seq_no: (i % u16::MAX as usize) as u16,
-                rtp_ts: i as u32,
-                payload_len: 8,
-            }
-            .generate_buffer(None),
-        )
-    }
 
     let h = receive_init();
-    let inner = h.lock().unwrap();
-    let push_pad = inner
+    let push_pad = h
+        .lock()
+        .unwrap()
         .element()
         .unwrap()
         .static_pad("rtp_sink_0")
         .unwrap()
         .peer()
         .unwrap();
-    drop(inner);
 
-    let start = clock.time();
+    let packet = |seq_no: u16| PacketInfo {
+        seq_no,
+        rtp_ts: 20 * (seq_no as u32 - 499),
+        payload_len: 5,
+    };
 
-    for packet in packets {
-        push_pad.push(packet).unwrap();
-    }
+    receive_push(h.clone(), [packet(500), packet(501)], false);
+    receive_pull(h.clone(), [packet(500), packet(501)]);
 
-    let pushed = clock.time();
+    // 503 waits for the missing 502 and is then received a second time
+    receive_push(h.clone(), [packet(503), packet(503)], false);
 
-    let mut inner = h.lock().unwrap();
-    for i in 0..N_PACKETS {
-        let buffer = inner.pull().unwrap();
-        let mapped = buffer.map_readable().unwrap();
-        let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
-        assert_eq!(rtp.sequence_number(), (i % u16::MAX as usize) as u16);
-    }
+    // 502 is recovered, e.g. by a RED decoder
+    let mut recovered = packet(502).generate_buffer(None);
+    recovered
+        .make_mut()
+        .set_flags(gst::BufferFlags::from_bits_retain(
+            gst_rtp::ffi::GST_RTP_BUFFER_FLAG_RETRANSMISSION,
+        ));
+    push_pad.push(recovered).unwrap();
+    receive_pull(h.clone(), [packet(502), packet(503)]);
 
-    let end = clock.time();
-    drop(inner);
+    // 500 was already forwarded
+    receive_push(h.clone(), [packet(500)], false);
 
-    let test_time = end.opt_sub(start);
-    let pull_time = end.opt_sub(pushed);
-    let push_time = pushed.opt_sub(start);
-    println!(
-        "test took {} (push {}, pull {})",
-        test_time.display(),
-        push_time.display(),
-        pull_time.display()
-    );
+    let stats = h
+        .lock()
+        .unwrap()
+        .element()
+        .unwrap()
+        .property::<gst::Structure>("stats");
+    let source_stats = stats
+        .get::<gst::Structure>("0")
+        .unwrap()
+        .get::<gst::Structure>(TEST_SSRC.to_string())
+        .unwrap();
+    assert_eq!(source_stats.get::<u64>("packets-duplicated").unwrap(), 1);
+    assert_eq!(source_stats.get::<u64>("packets-late").unwrap(), 1);
+    assert_eq!(source_stats.get::<u64>("packets-recovered").unwrap(), 1);
+    assert_eq!(source_stats.get::<u64>("jitterbuffer-packets").unwrap(), 0);
 }
 
 #[test]
-fn test_receive_list_benchmark() {
+fn test_receive_state_snapshot() {
     init();
-    let clock = gst::SystemClock::obtain();
-    const N_PACKETS: usize = 32 * 1024;
-    const N_PUSHES: usize = 1024 / 32;
+
     let h = receive_init();
-    let inner = h.lock().unwrap();
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+    receive_pull(h.clone(), PACKETS_TEST_1);
 
-    let push_pad = inner
-        .element()
+    let elem = h.lock().unwrap().element().unwrap();
+    let snapshot = elem.emit_by_name::<gst::Structure>("get-state-snapshot", &[]);
+    drop(elem);
+    drop(h);
+
+    let stream = snapshot
+        .get::<gst::List>("streams")
         .unwrap()
-        .static_pad("rtp_sink_0")
+        .first()
         .unwrap()
-        .peer()
+        .get::<gst::Structure>()
         .unwrap();
-    drop(inner);
+    assert_eq!(stream.get::<u32>("ssrc").unwrap(), TEST_SSRC);
+    assert_eq!(stream.get::<u32>("next-seqnum").unwrap(), 502);
+    let ssrc = snapshot
+        .get::<gst::List>("ssrcs")
+        .unwrap()
+        .first()
+        .unwrap()
+        .get::<gst::Structure>()
+        .unwrap();
+    assert_eq!(ssrc.get::<u32>("clock-rate").unwrap(), TEST_CLOCK_RATE);
 
-    let mut lists = Vec::with_capacity(N_PUSHES);
-    for p in 0..N_PUSHES {
+    // Continue in a new element from the serialized snapshot
+    let snapshot = snapshot.to_string().parse::<gst::Structure>().unwrap();
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .build()
+        .unwrap();
+    assert!(elem.emit_by_name::<bool>("restore-state-snapshot", &[&snapshot]));
+    let h = receive_init_with_element(elem);
+
+    let next = PacketInfo {
+        seq_no: 502,
+        rtp_ts: 40,
+        payload_len: 3,
+    };
+    // The last packet already forwarded before the handover is dropped
+    receive_push(h.clone(), [PACKETS_TEST_1[1], next], false);
+    receive_pull(h.clone(), [next]);
+
+    let stats = h
+        .lock()
+        .unwrap()
+        .element()
+        .unwrap()
+        .property::<gst::Structure>("stats");
+    let jitterbuffer_stats = stats
+        .get::<gst::Structure>("0")
+        .unwrap()
+        .get::<gst::List>("jitterbuffer-stats")
+        .unwrap()
+        .first()
+        .unwrap()
+        .get::<gst::Structure>()
+        .unwrap();
+    assert_eq!(jitterbuffer_stats.get::<u64>("num-late").unwrap(), 1);
+    assert_eq!(jitterbuffer_stats.get::<u64>("num-pushed").unwrap(), 1);
+}
+
+#[test]
+fn test_receive_rtcp_mux() {
+    init();
+
+    let h = receive_init();
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+
+    let mut data = vec![0; 128];
+    let len = rtcp_types::Compound::builder()
+        .add_packet(
+            rtcp_types::SenderReport::builder(TEST_SSRC)
+                .ntp_timestamp(1000 << 32)
+                .rtp_timestamp(0)
+                .packet_count(2)
+                .octet_count(20),
+        )
+        .write_into(&mut data)
+        .unwrap();
+    data.truncate(len);
+
+    // RTCP muxed with the RTP stream is handled on the RTP sink pad
+    let push_pad = h
+        .lock()
+        .unwrap()
+        .element()
+        .unwrap()
+        .static_pad("rtp_sink_0")
+        .unwrap()
+        .peer()
+        .unwrap();
+    push_pad.push(gst::Buffer::from_mut_slice(data)).unwrap();
+
+    receive_pull(h.clone(), PACKETS_TEST_1);
+    receive_check_stats(h, PACKETS_TEST_1);
+}
+
+#[test]
+fn test_receive_reference_timestamp_meta() {
+    init();
+
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("add-reference-timestamp-meta", true)
+        .build()
+        .unwrap();
+    let h = receive_init_with_element(elem.clone());
+
+    let rtcp_sinkpad = elem.request_pad_simple("rtcp_sink_0").unwrap();
+    rtcp_sinkpad.send_event(gst::event::StreamStart::new("rtcp"));
+    rtcp_sinkpad.send_event(gst::event::Caps::new(
+        &Caps::builder("application/x-rtcp").build(),
+    ));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    rtcp_sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    // Sender report mapping RTP timestamp 0 to 1000s NTP time
+    let mut data = vec![0; 128];
+    let len = rtcp_types::Compound::builder()
+        .add_packet(
+            rtcp_types::SenderReport::builder(TEST_SSRC)
+                .ntp_timestamp(1000 << 32)
+                .rtp_timestamp(0)
+                .packet_count(1)
+                .octet_count(8),
+        )
+        .write_into(&mut data)
+        .unwrap();
+    data.truncate(len);
+    rtcp_sinkpad
+        .chain(gst::Buffer::from_mut_slice(data))
+        .unwrap();
+
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+
+    let mut inner = h.lock().unwrap();
+    for _ in PACKETS_TEST_1 {
+        let buffer = inner.pull().unwrap();
+        let meta = buffer
+            .meta::<gst::ReferenceTimestampMeta>()
+            .expect("no reference timestamp meta");
+        assert_eq!(
+            meta.reference(),
+            Caps::builder("timestamp/x-ntp").build().as_ref()
+        );
+        assert!(meta.timestamp() >= gst::ClockTime::from_seconds(1000));
+        assert!(meta.timestamp() < gst::ClockTime::from_seconds(1001));
+    }
+    drop(inner);
+
+    elem.release_request_pad(&rtcp_sinkpad);
+}
+
+#[test]
+fn test_receive_auto_remove_on_bye() {
+    init();
+
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("auto-remove", true)
+        .build()
+        .unwrap();
+    let h = receive_init_with_element(elem.clone());
+
+    let rtcp_sinkpad = elem.request_pad_simple("rtcp_sink_0").unwrap();
+    rtcp_sinkpad.send_event(gst::event::StreamStart::new("rtcp"));
+    rtcp_sinkpad.send_event(gst::event::Caps::new(
+        &Caps::builder("application/x-rtcp").build(),
+    ));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    rtcp_sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+    receive_pull(h.clone(), PACKETS_TEST_1);
+
+    let (bye_sender, bye_receiver) = std::sync::mpsc::sync_channel(16);
+    let session = elem.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+    session.connect("bye-ssrc", false, move |args| {
+        let ssrc = args[1].get::<u32>().unwrap();
+        bye_sender.send(ssrc).unwrap();
+        None
+    });
+    let (removed_sender, removed_receiver) = std::sync::mpsc::sync_channel(16);
+    elem.connect_pad_removed(move |_elem, pad| {
+        removed_sender.send(pad.name()).unwrap();
+    });
+
+    let mut data = vec![0; 128];
+    let len = rtcp_types::Compound::builder()
+        .add_packet(rtcp_types::Bye::builder().add_source(TEST_SSRC))
+        .write_into(&mut data)
+        .unwrap();
+    data.truncate(len);
+    rtcp_sinkpad
+        .chain(gst::Buffer::from_mut_slice(data))
+        .unwrap();
+    assert_eq!(bye_receiver.recv().unwrap(), TEST_SSRC);
+
+    let mut inner = h.lock().unwrap();
+    loop {
+        let event = inner.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+    drop(inner);
+
+    let removed = removed_receiver
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(
+        removed.as_str(),
+        format!("rtp_src_0_{TEST_PT}_{TEST_SSRC}").as_str()
+    );
+
+    elem.release_request_pad(&rtcp_sinkpad);
+}
+
+#[test]
+fn test_receive_auto_remove_on_timeout() {
+    init();
+
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("auto-remove", true)
+        .build()
+        .unwrap();
+    let h = receive_init_with_element(elem.clone());
+
+    let session = elem.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+    session.set_property("member-timeout", gst::ClockTime::SECOND.nseconds());
+    let (timeout_sender, timeout_receiver) = std::sync::mpsc::sync_channel(16);
+    session.connect("on-timeout", false, move |args| {
+        let ssrc = args[1].get::<u32>().unwrap();
+        timeout_sender.send(ssrc).unwrap();
+        None
+    });
+    let (removed_sender, removed_receiver) = std::sync::mpsc::sync_channel(16);
+    elem.connect_pad_removed(move |_elem, pad| {
+        removed_sender.send(pad.name()).unwrap();
+    });
+
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+    receive_pull(h.clone(), PACKETS_TEST_1);
+
+    // There is no rtpsend producing RTCP for this session, the receiver detects the timeout
+    assert_eq!(
+        timeout_receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .unwrap(),
+        TEST_SSRC
+    );
+
+    let mut inner = h.lock().unwrap();
+    loop {
+        let event = inner.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+    drop(inner);
+
+    let removed = removed_receiver
+        .recv_timeout(std::time::Duration::from_secs(5))
+        .unwrap();
+    assert_eq!(
+        removed.as_str(),
+        format!("rtp_src_0_{TEST_PT}_{TEST_SSRC}").as_str()
+    );
+}
+
+#[test]
+fn test_receive_list() {
+    init();
+
+    let h = receive_init();
+    receive_push(h.clone(), PACKETS_TEST_1, true);
+    receive_pull(h.clone(), PACKETS_TEST_1);
+    receive_check_stats(h, PACKETS_TEST_1);
+}
+
+#[test]
+fn test_receive_drop_messages() {
+    init();
+
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("post-drop-messages", true)
+        .property("drop-messages-interval", 0u32)
+        .build()
+        .unwrap();
+    let bus = gst::Bus::new();
+    elem.set_bus(Some(&bus));
+    let h = receive_init_with_element(elem);
+
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+    // Push the first packet again, it is either still queued or was already forwarded
+    receive_push(h.clone(), PACKETS_TEST_1.iter().take(1).cloned(), false);
+    receive_pull(h.clone(), PACKETS_TEST_1);
+
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::from_seconds(5),
+            &[gst::MessageType::Element],
+        )
+        .unwrap();
+    let s = msg.structure().unwrap();
+    assert_eq!(s.name(), "rtp-packet-dropped");
+    assert_eq!(s.get::<u32>("session").unwrap(), 0);
+    assert_eq!(s.get::<u32>("ssrc").unwrap(), TEST_SSRC);
+    assert_eq!(s.get::<u32>("pt").unwrap(), TEST_PT as u32);
+    assert_eq!(
+        s.get::<u32>("seqnum").unwrap(),
+        PACKETS_TEST_1[0].seq_no as u32
+    );
+    let reason = s.get::<&str>("reason").unwrap();
+    assert!(["late", "duplicate"].contains(&reason));
+    assert_eq!(
+        s.get::<u64>("num-late").unwrap() + s.get::<u64>("num-duplicates").unwrap(),
+        1
+    );
+}
+
+#[test]
+fn test_receive_flush() {
+    init();
+    let h = receive_init();
+
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+
+    let mut inner = h.lock().unwrap();
+    let seqnum = gst::Seqnum::next();
+    inner.push_event(gst::event::FlushStart::builder().seqnum(seqnum).build());
+    inner.push_event(gst::event::FlushStop::builder(false).seqnum(seqnum).build());
+
+    let event = inner.pull_event().unwrap();
+    let gst::EventView::FlushStart(fs) = event.view() else {
+        unreachable!();
+    };
+    assert_eq!(fs.seqnum(), seqnum);
+    let event = inner.pull_event().unwrap();
+    let gst::EventView::FlushStop(fs) = event.view() else {
+        unreachable!();
+    };
+    assert_eq!(fs.seqnum(), seqnum);
+}
+
+#[test]
+fn test_receive_eos_immediate() {
+    init();
+
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("latency", 10_000u32)
+        .property_from_str("eos-handling", "immediate")
+        .build()
+        .unwrap();
+    let h = receive_init_with_element(elem);
+
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+
+    let mut inner = h.lock().unwrap();
+    inner.push_event(gst::event::Eos::new());
+
+    // The packets are still waiting for the latency to expire but EOS must not wait for them
+    loop {
+        let event = inner.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+    assert_eq!(inner.buffers_in_queue(), 0);
+}
+
+#[test]
+fn test_receive_eos_drain() {
+    init();
+
+    // Uses the default EOS handling
+    let h = receive_init();
+
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+
+    let mut inner = h.lock().unwrap();
+    inner.push_event(gst::event::Eos::new());
+
+    // EOS is forwarded after the packets waiting in the jitterbuffer
+    loop {
+        let event = inner.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+    assert_eq!(inner.buffers_in_queue(), PACKETS_TEST_1.len() as u32);
+    drop(inner);
+
+    receive_pull(h, PACKETS_TEST_1);
+}
+
+fn receive_init_wait_bye(
+    configure: impl FnOnce(&gst::Element),
+) -> (gst::Element, Arc<Mutex<gst_check::Harness>>, gst::Pad) {
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property_from_str("eos-handling", "wait-bye")
+        .build()
+        .unwrap();
+    configure(&elem);
+    let h = receive_init_with_element(elem.clone());
+
+    // Without RTCP there would be no BYE to wait for
+    let rtcp_sinkpad = elem.request_pad_simple("rtcp_sink_0").unwrap();
+    rtcp_sinkpad.send_event(gst::event::StreamStart::new("rtcp"));
+    rtcp_sinkpad.send_event(gst::event::Caps::new(
+        &Caps::builder("application/x-rtcp").build(),
+    ));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    rtcp_sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    receive_push(h.clone(), PACKETS_TEST_1, false);
+    receive_pull(h.clone(), PACKETS_TEST_1);
+
+    (elem, h, rtcp_sinkpad)
+}
+
+fn receive_push_bye(rtcp_sinkpad: &gst::Pad) {
+    let mut data = vec![0; 128];
+    let len = rtcp_types::Compound::builder()
+        .add_packet(rtcp_types::Bye::builder().add_source(TEST_SSRC))
+        .write_into(&mut data)
+        .unwrap();
+    data.truncate(len);
+    rtcp_sinkpad
+        .chain(gst::Buffer::from_mut_slice(data))
+        .unwrap();
+}
+
+fn receive_pull_eos(h: &Arc<Mutex<gst_check::Harness>>) -> gst::Event {
+    let mut inner = h.lock().unwrap();
+    loop {
+        let event = inner.pull_event().unwrap();
+        if event.type_() == gst::EventType::Eos {
+            return event;
+        }
+    }
+}
+
+fn receive_assert_no_eos(h: &Arc<Mutex<gst_check::Harness>>) {
+    std::thread::sleep(std::time::Duration::from_millis(100));
+
+    let mut inner = h.lock().unwrap();
+    while let Some(event) = inner.try_pull_event() {
+        assert_ne!(event.type_(), gst::EventType::Eos);
+    }
+}
+
+#[test]
+fn test_receive_eos_wait_bye() {
+    init();
+
+    let (elem, h, rtcp_sinkpad) = receive_init_wait_bye(|_| ());
+
+    let eos = gst::event::Eos::new();
+    let seqnum = eos.seqnum();
+    h.lock().unwrap().push_event(eos);
+
+    // The remote sender is still active
+    receive_assert_no_eos(&h);
+
+    receive_push_bye(&rtcp_sinkpad);
+    assert_eq!(receive_pull_eos(&h).seqnum(), seqnum);
+
+    elem.release_request_pad(&rtcp_sinkpad);
+}
+
+#[test]
+fn test_receive_eos_wait_bye_timeout() {
+    init();
+
+    let (elem, h, rtcp_sinkpad) = receive_init_wait_bye(|elem| {
+        let session = elem.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+        session.set_property("member-timeout", gst::ClockTime::SECOND.nseconds());
+    });
+
+    let (timeout_sender, timeout_receiver) = std::sync::mpsc::sync_channel(16);
+    let session = elem.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+    session.connect("on-timeout", false, move |args| {
+        let ssrc = args[1].get::<u32>().unwrap();
+        timeout_sender.send(ssrc).unwrap();
+        None
+    });
+
+    let eos = gst::event::Eos::new();
+    let seqnum = eos.seqnum();
+    h.lock().unwrap().push_event(eos);
+
+    receive_assert_no_eos(&h);
+
+    // The remote sender never sends a BYE, EOS is released once it timed out
+    assert_eq!(
+        timeout_receiver
+            .recv_timeout(std::time::Duration::from_secs(5))
+            .unwrap(),
+        TEST_SSRC
+    );
+    assert_eq!(receive_pull_eos(&h).seqnum(), seqnum);
+
+    elem.release_request_pad(&rtcp_sinkpad);
+}
+
+#[test]
+fn test_receive_eos_wait_bye_flush() {
+    init();
+
+    let (elem, h, rtcp_sinkpad) = receive_init_wait_bye(|_| ());
+
+    let mut inner = h.lock().unwrap();
+    inner.push_event(gst::event::Eos::new());
+
+    // Flushing discards the held back EOS
+    inner.push_event(gst::event::FlushStart::new());
+    inner.push_event(gst::event::FlushStop::new(false));
+    drop(inner);
+
+    receive_push_bye(&rtcp_sinkpad);
+    receive_assert_no_eos(&h);
+
+    // Without remote senders left, a new EOS is forwarded right away
+    let eos = gst::event::Eos::new();
+    let seqnum = eos.seqnum();
+    h.lock().unwrap().push_event(eos);
+    assert_eq!(receive_pull_eos(&h).seqnum(), seqnum);
+
+    elem.release_request_pad(&rtcp_sinkpad);
+}
+
+#[test]
+fn test_receive_session_forward() {
+    init();
+
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("latency", 10_000u32)
+        .build()
+        .unwrap();
+    let h = receive_init_with_element(elem.clone());
+
+    let session = elem.emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+    session.set_property("forward", true);
+
+    // Packets are neither reordered nor held back for the latency
+    let packets = [500, 501, 503, 502].map(|seq_no| PacketInfo {
+        seq_no,
+        rtp_ts: 20,
+        payload_len: 8,
+    });
+    receive_push(h.clone(), packets, false);
+    receive_pull(h.clone(), packets);
+
+    let inner = h.lock().unwrap();
+    assert_eq!(inner.query_latency(), Some(gst::ClockTime::ZERO));
+}
+
+#[test]
+fn test_receive_sparse_pt() {
+    init();
+
+    let id = next_element_counter();
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("latency", 10_000u32)
+        .property("sparse-pts", gst::Array::new([TEST_PT as u32]))
+        .build()
+        .unwrap();
+    let h = receive_init_with_element(elem);
+
+    // Packets of the sparse payload type don't wait for the latency
+    let packets = [500, 502, 501].map(|seq_no| PacketInfo {
+        seq_no,
+        rtp_ts: 20,
+        payload_len: 8,
+    });
+    receive_push(h.clone(), packets, false);
+    receive_pull(h.clone(), packets);
+
+    let inner = h.lock().unwrap();
+    assert_eq!(inner.query_latency(), Some(gst::ClockTime::ZERO));
+}
+
+#[test]
+fn test_receive_benchmark() {
+    init();
+    let clock = gst::SystemClock::obtain();
+    //const N_PACKETS: usize = 1024 * 1024;
+    const N_PACKETS: usize = 1024;
+    let mut packets = Vec::with_capacity(N_PACKETS);
+    for i in 0..N_PACKETS {
+        packets.push(
+            PacketInfo {
+                seq_no: (i % u16::MAX as usize) as u16,
+                rtp_ts: i as u32,
+                payload_len: 8,
+            }
+            .generate_buffer(None),
+        )
+    }
+
+    let h = receive_init();
+    let inner = h.lock().unwrap();
+    let push_pad = inner
+        .element()
+        .unwrap()
+        .static_pad("rtp_sink_0")
+        .unwrap()
+        .peer()
+        .unwrap();
+    drop(inner);
+
+    let start = clock.time();
+
+    for packet in packets {
+        push_pad.push(packet).unwrap();
+    }
+
+    let pushed = clock.time();
+
+    let mut inner = h.lock().unwrap();
+    for i in 0..N_PACKETS {
+        let buffer = inner.pull().unwrap();
+        let mapped = buffer.map_readable().unwrap();
+        let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+        assert_eq!(rtp.sequence_number(), (i % u16::MAX as usize) as u16);
+    }
+
+    let end = clock.time();
+    drop(inner);
+
+    let test_time = end.opt_sub(start);
+    let pull_time = end.opt_sub(pushed);
+    let push_time = pushed.opt_sub(start);
+    println!(
+        "test took {} (push {}, pull {})",
+        test_time.display(),
+        push_time.display(),
+        pull_time.display()
+    );
+}
+
+#[test]
+fn test_receive_list_benchmark() {
+    init();
+    let clock = gst::SystemClock::obtain();
+    const N_PACKETS: usize = 32 * 1024;
+    const N_PUSHES: usize = 1024 / 32;
+    let h = receive_init();
+    let inner = h.lock().unwrap();
+
+    let push_pad = inner
+        .element()
+        .unwrap()
+        .static_pad("rtp_sink_0")
+        .unwrap()
+        .peer()
+        .unwrap();
+    drop(inner);
+
+    let mut lists = Vec::with_capacity(N_PUSHES);
+    for p in 0..N_PUSHES {
         let mut list = gst::BufferList::new();
         let list_mut = list.make_mut();
         for i in 0..N_PACKETS {
@@ -564,55 +1595,413 @@ fn test_receive_list_benchmark() {
         lists.push(list);
     }
 
-    let start = clock.time();
+    let start = clock.time();
+
+    for list in lists {
+        push_pad.push_list(list).unwrap();
+    }
+
+    let pushed = clock.time();
+
+    let mut inner = h.lock().unwrap();
+    for p in 0..N_PUSHES {
+        for i in 0..N_PACKETS {
+            let buffer = inner.pull().unwrap();
+            let mapped = buffer.map_readable().unwrap();
+            let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
+            assert_eq!(
+                rtp.sequence_number(),
+                ((p * N_PACKETS + i) % u16::MAX as usize) as u16
+            );
+        }
+    }
+
+    let end = clock.time();
+    drop(inner);
+
+    let test_time = end.opt_sub(start);
+    let pull_time = end.opt_sub(pushed);
+    let push_time = pushed.opt_sub(start);
+    println!(
+        "test took {} (push {}, pull {})",
+        test_time.display(),
+        push_time.display(),
+        pull_time.display()
+    );
+}
+
+#[test]
+fn recv_release_sink_pad() {
+    init();
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .build()
+        .unwrap();
+    elem.set_state(gst::State::Playing).unwrap();
+    let sinkpad = elem.request_pad_simple("rtp_sink_0").unwrap();
+    let stream_start = gst::event::StreamStart::new("random");
+    sinkpad.send_event(stream_start);
+    let caps = Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("payload", TEST_PT as i32)
+        .field("clock-rate", TEST_CLOCK_RATE as i32)
+        .field("encoding-name", "custom-test")
+        .build();
+    sinkpad.send_event(gst::event::Caps::new(&caps));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    let (sender, recv) = std::sync::mpsc::sync_channel(1);
+    elem.connect_pad_added({
+        let sender = sender.clone();
+        move |_elem, pad| {
+            let other_pad = gst::Pad::builder(gst::PadDirection::Sink)
+                .chain_function(|_pad, _parent, _buffer| Ok(gst::FlowSuccess::Ok))
+                .build();
+            other_pad.set_active(true).unwrap();
+            pad.link(&other_pad).unwrap();
+            sender.send(other_pad).unwrap();
+        }
+    });
+    // push two buffers to get past the rtpsource validation
+    sinkpad
+        .chain(
+            PacketInfo {
+                seq_no: 30,
+                rtp_ts: 10,
+                payload_len: 4,
+            }
+            .generate_buffer(Some(gst::ClockTime::from_mseconds(50))),
+        )
+        .unwrap();
+    sinkpad
+        .chain(
+            PacketInfo {
+                seq_no: 31,
+                rtp_ts: 10,
+                payload_len: 4,
+            }
+            .generate_buffer(Some(gst::ClockTime::from_mseconds(100))),
+        )
+        .unwrap();
+    let _other_pad = recv.recv().unwrap();
+
+    elem.release_request_pad(&sinkpad);
+    elem.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn recv_bundle_mid_pad() {
+    init();
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .build()
+        .unwrap();
+    elem.set_state(gst::State::Playing).unwrap();
+    let sinkpad = elem.request_pad_simple("rtp_sink_0").unwrap();
+    let stream_start = gst::event::StreamStart::new("random");
+    sinkpad.send_event(stream_start);
+    let caps = Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("payload", TEST_PT as i32)
+        .field("clock-rate", TEST_CLOCK_RATE as i32)
+        .field("encoding-name", "custom-test")
+        .field("extmap-1", "urn:ietf:params:rtp-hdrext:sdes:mid")
+        .build();
+    sinkpad.send_event(gst::event::Caps::new(&caps));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    let (sender, recv) = std::sync::mpsc::sync_channel(1);
+    elem.connect_pad_added(move |_elem, pad| {
+        let other_pad = gst::Pad::builder(gst::PadDirection::Sink)
+            .chain_function(|_pad, _parent, _buffer| Ok(gst::FlowSuccess::Ok))
+            .build();
+        other_pad.set_active(true).unwrap();
+        pad.link(&other_pad).unwrap();
+        sender.send(pad.name()).unwrap();
+    });
+
+    // One-byte header extension with ID 1 and the MID "a"
+    let extension = [0x10, b'a', 0, 0];
+    // push two buffers to get past the rtpsource validation
+    for seq_no in [30, 31] {
+        let packet = RtpPacketBuilder::new()
+            .ssrc(TEST_SSRC)
+            .payload_type(TEST_PT)
+            .sequence_number(seq_no)
+            .timestamp(10)
+            .extension(0xBEDE, extension.as_slice())
+            .payload([4u8; 4].as_slice());
+        let mut data = vec![0; packet.calculate_size().unwrap()];
+        packet.write_into(&mut data).unwrap();
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        buffer
+            .make_mut()
+            .set_dts(gst::ClockTime::from_mseconds(50 * seq_no as u64));
+        sinkpad.chain(buffer).unwrap();
+    }
+
+    let pad_name = recv.recv().unwrap();
+    assert_eq!(pad_name, "rtp_mid_src_0_a");
 
-    for list in lists {
-        push_pad.push_list(list).unwrap();
+    elem.release_request_pad(&sinkpad);
+    elem.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn recv_bundle_mid_pad_multiple_ssrcs() {
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    init();
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("latency", 0u32)
+        .build()
+        .unwrap();
+    elem.set_state(gst::State::Playing).unwrap();
+    let sinkpad = elem.request_pad_simple("rtp_sink_0").unwrap();
+    sinkpad.send_event(gst::event::StreamStart::new("random"));
+    let caps = Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("payload", TEST_PT as i32)
+        .field("clock-rate", TEST_CLOCK_RATE as i32)
+        .field("encoding-name", "custom-test")
+        .field("extmap-1", "urn:ietf:params:rtp-hdrext:sdes:mid")
+        .build();
+    sinkpad.send_event(gst::event::Caps::new(&caps));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    let (pad_sender, pad_recv) = std::sync::mpsc::channel();
+    let (buffer_sender, buffer_recv) = std::sync::mpsc::channel();
+    elem.connect_pad_added(move |_elem, pad| {
+        pad_sender.send(pad.name().to_string()).unwrap();
+
+        let buffer_sender = Mutex::new(buffer_sender.clone());
+        let other_pad = gst::Pad::builder(gst::PadDirection::Sink)
+            .chain_function(move |_pad, _parent, buffer| {
+                let map = buffer.map_readable().unwrap();
+                let rtp = RtpPacket::parse(&map).unwrap();
+                buffer_sender
+                    .lock()
+                    .unwrap()
+                    .send((rtp.ssrc(), rtp.sequence_number()))
+                    .unwrap();
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build();
+        other_pad.set_active(true).unwrap();
+        pad.link(&other_pad).unwrap();
+    });
+
+    let mut dts = gst::ClockTime::ZERO;
+    let mut push = |ssrc: u32, seq_no: u16| {
+        // One-byte header extension with ID 1 and the MID "a"
+        let extension = [0x10, b'a', 0, 0];
+        let packet = RtpPacketBuilder::new()
+            .ssrc(ssrc)
+            .payload_type(TEST_PT)
+            .sequence_number(seq_no)
+            .timestamp(seq_no as u32 * 10)
+            .extension(0xBEDE, extension.as_slice())
+            .payload([4u8; 4].as_slice());
+        let mut data = vec![0; packet.calculate_size().unwrap()];
+        packet.write_into(&mut data).unwrap();
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        buffer.make_mut().set_dts(dts);
+        dts += gst::ClockTime::from_mseconds(10);
+        sinkpad.chain(buffer).unwrap();
+    };
+
+    // Two senders of the same MID with unrelated seqnums, interleaved
+    for (seq_a, seq_b) in [(30, 1000), (31, 1001), (32, 1002)] {
+        push(0x1000, seq_a);
+        push(0x2000, seq_b);
     }
 
-    let pushed = clock.time();
+    assert_eq!(pad_recv.recv_timeout(TIMEOUT).unwrap(), "rtp_mid_src_0_a");
 
-    let mut inner = h.lock().unwrap();
-    for p in 0..N_PUSHES {
-        for i in 0..N_PACKETS {
-            let buffer = inner.pull().unwrap();
-            let mapped = buffer.map_readable().unwrap();
-            let rtp = rtp_types::RtpPacket::parse(&mapped).unwrap();
-            assert_eq!(
-                rtp.sequence_number(),
-                ((p * N_PACKETS + i) % u16::MAX as usize) as u16
-            );
-        }
+    // Each ssrc has its own jitterbuffer, none of the packets is dropped as duplicate or late
+    let mut received = (0..6)
+        .map(|_| buffer_recv.recv_timeout(TIMEOUT).unwrap())
+        .collect::<Vec<_>>();
+    received.sort();
+    assert_eq!(
+        received,
+        [
+            (0x1000, 30),
+            (0x1000, 31),
+            (0x1000, 32),
+            (0x2000, 1000),
+            (0x2000, 1001),
+            (0x2000, 1002),
+        ]
+    );
+    assert!(pad_recv.try_recv().is_err());
+
+    elem.release_request_pad(&sinkpad);
+    elem.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn recv_bundle_rid_pads() {
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    init();
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("latency", 0u32)
+        .build()
+        .unwrap();
+    elem.set_state(gst::State::Playing).unwrap();
+    let sinkpad = elem.request_pad_simple("rtp_sink_0").unwrap();
+    sinkpad.send_event(gst::event::StreamStart::new("random"));
+    let caps = Caps::builder("application/x-rtp")
+        .field("media", "video")
+        .field("payload", TEST_PT as i32)
+        .field("clock-rate", TEST_CLOCK_RATE as i32)
+        .field("encoding-name", "custom-test")
+        .field("extmap-1", "urn:ietf:params:rtp-hdrext:sdes:mid")
+        .field("extmap-2", "urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id")
+        .build();
+    sinkpad.send_event(gst::event::Caps::new(&caps));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    let (pad_sender, pad_recv) = std::sync::mpsc::channel();
+    let (buffer_sender, buffer_recv) = std::sync::mpsc::channel();
+    elem.connect_pad_added(move |_elem, pad| {
+        let name = pad.name().to_string();
+        let caps = pad
+            .sticky_event::<gst::event::Caps>(0)
+            .unwrap()
+            .caps_owned();
+        let rid = caps.structure(0).unwrap().get::<String>("rid").unwrap();
+        pad_sender.send((name.clone(), rid)).unwrap();
+
+        let buffer_sender = Mutex::new(buffer_sender.clone());
+        let other_pad = gst::Pad::builder(gst::PadDirection::Sink)
+            .chain_function(move |_pad, _parent, buffer| {
+                let map = buffer.map_readable().unwrap();
+                let rtp = RtpPacket::parse(&map).unwrap();
+                buffer_sender
+                    .lock()
+                    .unwrap()
+                    .send((
+                        name.clone(),
+                        rtp.ssrc(),
+                        rtp.sequence_number(),
+                        buffer.flags().contains(gst::BufferFlags::DISCONT),
+                    ))
+                    .unwrap();
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build();
+        other_pad.set_active(true).unwrap();
+        pad.link(&other_pad).unwrap();
+    });
+
+    let mut dts = gst::ClockTime::ZERO;
+    let mut push = |ssrc: u32, rid: u8, seq_no: u16| {
+        // One-byte header extensions with the MID "a" and the given RID
+        let extension = [0x10, b'a', 0x20, rid];
+        let packet = RtpPacketBuilder::new()
+            .ssrc(ssrc)
+            .payload_type(TEST_PT)
+            .sequence_number(seq_no)
+            .timestamp(seq_no as u32 * 10)
+            .extension(0xBEDE, extension.as_slice())
+            .payload([4u8; 4].as_slice());
+        let mut data = vec![0; packet.calculate_size().unwrap()];
+        packet.write_into(&mut data).unwrap();
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        buffer.make_mut().set_dts(dts);
+        dts += gst::ClockTime::from_mseconds(10);
+        sinkpad.chain(buffer).unwrap();
+    };
+
+    // push two buffers per layer to get past the rtpsource validation
+    for seq_no in [30, 31] {
+        push(0x1000, b'h', seq_no);
+    }
+    for seq_no in [100, 101] {
+        push(0x2000, b'l', seq_no);
     }
 
-    let end = clock.time();
-    drop(inner);
+    let mut pads = vec![
+        pad_recv.recv_timeout(TIMEOUT).unwrap(),
+        pad_recv.recv_timeout(TIMEOUT).unwrap(),
+    ];
+    pads.sort();
+    assert_eq!(
+        pads,
+        [
+            ("rtp_mid_src_0_a_h".to_string(), "h".to_string()),
+            ("rtp_mid_src_0_a_l".to_string(), "l".to_string()),
+        ]
+    );
 
-    let test_time = end.opt_sub(start);
-    let pull_time = end.opt_sub(pushed);
-    let push_time = pushed.opt_sub(start);
-    println!(
-        "test took {} (push {}, pull {})",
-        test_time.display(),
-        push_time.display(),
-        pull_time.display()
+    for _ in 0..4 {
+        buffer_recv.recv_timeout(TIMEOUT).unwrap();
+    }
+
+    // The high layer is resumed with a new SSRC and lower seqnums
+    for seq_no in [10, 11] {
+        push(0x3000, b'h', seq_no);
+    }
+
+    assert_eq!(
+        buffer_recv.recv_timeout(TIMEOUT).unwrap(),
+        ("rtp_mid_src_0_a_h".to_string(), 0x3000, 10, true)
+    );
+    assert_eq!(
+        buffer_recv.recv_timeout(TIMEOUT).unwrap(),
+        ("rtp_mid_src_0_a_h".to_string(), 0x3000, 11, false)
+    );
+
+    // A straggler of the previous SSRC is dropped without switching back
+    push(0x1000, b'h', 32);
+    push(0x3000, b'h', 12);
+    assert_eq!(
+        buffer_recv.recv_timeout(TIMEOUT).unwrap(),
+        ("rtp_mid_src_0_a_h".to_string(), 0x3000, 12, false)
     );
+    assert!(buffer_recv.try_recv().is_err());
+    assert!(pad_recv.try_recv().is_err());
+
+    elem.release_request_pad(&sinkpad);
+    elem.set_state(gst::State::Null).unwrap();
 }
 
 #[test]
-fn recv_release_sink_pad() {
+fn recv_many_ssrcs() {
     init();
 
+    const N_SSRCS: u32 = 32;
+
     let id = next_element_counter();
 
     let elem = gst::ElementFactory::make("rtprecv")
         .property("rtp-id", id.to_string())
+        .property("latency", 0u32)
         .build()
         .unwrap();
     elem.set_state(gst::State::Playing).unwrap();
     let sinkpad = elem.request_pad_simple("rtp_sink_0").unwrap();
-    let stream_start = gst::event::StreamStart::new("random");
-    sinkpad.send_event(stream_start);
+    sinkpad.send_event(gst::event::StreamStart::new("random"));
     let caps = Caps::builder("application/x-rtp")
         .field("media", "audio")
         .field("payload", TEST_PT as i32)
@@ -623,41 +2012,377 @@ fn recv_release_sink_pad() {
     let segment = gst::FormattedSegment::<gst::ClockTime>::new();
     sinkpad.send_event(gst::event::Segment::new(&segment));
 
-    let (sender, recv) = std::sync::mpsc::sync_channel(1);
-    elem.connect_pad_added({
+    let (sender, recv) = std::sync::mpsc::channel();
+    elem.connect_pad_added(move |_elem, pad| {
         let sender = sender.clone();
-        move |_elem, pad| {
-            let other_pad = gst::Pad::builder(gst::PadDirection::Sink)
-                .chain_function(|_pad, _parent, _buffer| Ok(gst::FlowSuccess::Ok))
-                .build();
-            other_pad.set_active(true).unwrap();
-            pad.link(&other_pad).unwrap();
-            sender.send(other_pad).unwrap();
-        }
+        let other_pad = gst::Pad::builder(gst::PadDirection::Sink)
+            .chain_function(move |_pad, _parent, buffer| {
+                let map = buffer.map_readable().unwrap();
+                let rtp = RtpPacket::parse(&map).unwrap();
+                let _ = sender.send(rtp.ssrc());
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build();
+        other_pad.set_active(true).unwrap();
+        pad.link(&other_pad).unwrap();
     });
-    // push two buffers to get past the rtpsource validation
-    sinkpad
-        .chain(
-            PacketInfo {
-                seq_no: 30,
-                rtp_ts: 10,
-                payload_len: 4,
-            }
-            .generate_buffer(Some(gst::ClockTime::from_mseconds(50))),
-        )
+
+    // push two buffers per ssrc to get past the rtpsource validation
+    for seq_no in [30, 31] {
+        for ssrc in 0..N_SSRCS {
+            let packet = RtpPacketBuilder::new()
+                .ssrc(TEST_SSRC + ssrc)
+                .payload_type(TEST_PT)
+                .sequence_number(seq_no)
+                .timestamp(10)
+                .payload([4u8; 4].as_slice());
+            let mut data = vec![0; packet.calculate_size().unwrap()];
+            packet.write_into(&mut data).unwrap();
+            let mut buffer = gst::Buffer::from_mut_slice(data);
+            buffer
+                .make_mut()
+                .set_dts(gst::ClockTime::from_mseconds(50 * seq_no as u64));
+            sinkpad.chain(buffer).unwrap();
+        }
+    }
+
+    let mut received = std::collections::BTreeSet::new();
+    while received.len() < N_SSRCS as usize {
+        let ssrc = recv
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .unwrap();
+        received.insert(ssrc);
+    }
+    assert_eq!(elem.src_pads().len(), N_SSRCS as usize);
+
+    elem.release_request_pad(&sinkpad);
+    elem.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn recv_many_blocking_pushes() {
+    init();
+
+    // More ssrcs than there are CPUs or than a fixed thread pool would provide
+    const N_SSRCS: u32 = 100;
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("latency", 0u32)
+        .build()
         .unwrap();
-    sinkpad
-        .chain(
-            PacketInfo {
-                seq_no: 31,
-                rtp_ts: 10,
-                payload_len: 4,
-            }
-            .generate_buffer(Some(gst::ClockTime::from_mseconds(100))),
-        )
+    elem.set_state(gst::State::Playing).unwrap();
+    let sinkpad = elem.request_pad_simple("rtp_sink_0").unwrap();
+    sinkpad.send_event(gst::event::StreamStart::new("random"));
+    let caps = Caps::builder("application/x-rtp")
+        .field("media", "audio")
+        .field("payload", TEST_PT as i32)
+        .field("clock-rate", TEST_CLOCK_RATE as i32)
+        .field("encoding-name", "custom-test")
+        .build();
+    sinkpad.send_event(gst::event::Caps::new(&caps));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    // Like an aggregator downstream, the first buffer of each pad is only accepted once
+    // all pads have one
+    let barrier = Arc::new(std::sync::Barrier::new(N_SSRCS as usize));
+    let (sender, recv) = std::sync::mpsc::channel();
+    elem.connect_pad_added(move |_elem, pad| {
+        let sender = sender.clone();
+        let barrier = barrier.clone();
+        let waited = std::sync::atomic::AtomicBool::new(false);
+        let other_pad = gst::Pad::builder(gst::PadDirection::Sink)
+            .chain_function(move |_pad, _parent, buffer| {
+                if !waited.swap(true, std::sync::atomic::Ordering::SeqCst) {
+                    barrier.wait();
+                }
+                let map = buffer.map_readable().unwrap();
+                let rtp = RtpPacket::parse(&map).unwrap();
+                let _ = sender.send(rtp.ssrc());
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .build();
+        other_pad.set_active(true).unwrap();
+        pad.link(&other_pad).unwrap();
+    });
+
+    // push two buffers per ssrc to get past the rtpsource validation
+    for seq_no in [30, 31] {
+        for ssrc in 0..N_SSRCS {
+            let packet = RtpPacketBuilder::new()
+                .ssrc(TEST_SSRC + ssrc)
+                .payload_type(TEST_PT)
+                .sequence_number(seq_no)
+                .timestamp(10)
+                .payload([4u8; 4].as_slice());
+            let mut data = vec![0; packet.calculate_size().unwrap()];
+            packet.write_into(&mut data).unwrap();
+            let mut buffer = gst::Buffer::from_mut_slice(data);
+            buffer
+                .make_mut()
+                .set_dts(gst::ClockTime::from_mseconds(50 * seq_no as u64));
+            sinkpad.chain(buffer).unwrap();
+        }
+    }
+
+    let mut received = std::collections::BTreeSet::new();
+    while received.len() < N_SSRCS as usize {
+        let ssrc = recv
+            .recv_timeout(std::time::Duration::from_secs(10))
+            .expect("pushes starved while other pads were blocked");
+        received.insert(ssrc);
+    }
+
+    elem.release_request_pad(&sinkpad);
+    elem.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn recv_ignore_pt() {
+    init();
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", id.to_string())
+        .property("latency", 0u32)
+        .property("ignore-pt", true)
+        .build()
         .unwrap();
-    let _other_pad = recv.recv().unwrap();
+    elem.set_state(gst::State::Playing).unwrap();
+    let sinkpad = elem.request_pad_simple("rtp_sink_0").unwrap();
+    sinkpad.send_event(gst::event::StreamStart::new("random"));
+    let caps_for_pt = |pt: u8| {
+        Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("payload", pt as i32)
+            .field("clock-rate", TEST_CLOCK_RATE as i32)
+            .field("encoding-name", "custom-test")
+            .build()
+    };
+    sinkpad.send_event(gst::event::Caps::new(&caps_for_pt(TEST_PT)));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    #[derive(Debug, PartialEq)]
+    enum Item {
+        Caps(i32),
+        Buffer(u8),
+    }
+
+    let (sender, recv) = std::sync::mpsc::channel();
+    elem.connect_pad_added(move |_elem, pad| {
+        let buffer_sender = sender.clone();
+        let event_sender = sender.clone();
+        let other_pad = gst::Pad::builder(gst::PadDirection::Sink)
+            .chain_function(move |_pad, _parent, buffer| {
+                let map = buffer.map_readable().unwrap();
+                let rtp = RtpPacket::parse(&map).unwrap();
+                let _ = buffer_sender.send(Item::Buffer(rtp.payload_type()));
+                Ok(gst::FlowSuccess::Ok)
+            })
+            .event_function(move |_pad, _parent, event| {
+                if let gst::EventView::Caps(caps) = event.view() {
+                    let pt = caps
+                        .caps()
+                        .structure(0)
+                        .unwrap()
+                        .get::<i32>("payload")
+                        .unwrap();
+                    let _ = event_sender.send(Item::Caps(pt));
+                }
+                true
+            })
+            .build();
+        other_pad.set_active(true).unwrap();
+        pad.link(&other_pad).unwrap();
+    });
+
+    let push = |pt: u8, seq_no: u16| {
+        let packet = RtpPacketBuilder::new()
+            .ssrc(TEST_SSRC)
+            .payload_type(pt)
+            .sequence_number(seq_no)
+            .timestamp(10 * seq_no as u32)
+            .payload([4u8; 4].as_slice());
+        let mut data = vec![0; packet.calculate_size().unwrap()];
+        packet.write_into(&mut data).unwrap();
+        let mut buffer = gst::Buffer::from_mut_slice(data);
+        buffer
+            .make_mut()
+            .set_dts(gst::ClockTime::from_mseconds(50 * seq_no as u64));
+        sinkpad.chain(buffer).unwrap();
+    };
+
+    // two buffers to get past the rtpsource validation, then switch to another payload type
+    push(TEST_PT, 30);
+    push(TEST_PT, 31);
+    sinkpad.send_event(gst::event::Caps::new(&caps_for_pt(TEST_PT + 1)));
+    push(TEST_PT + 1, 32);
+    push(TEST_PT + 1, 33);
+
+    let items = (0..6)
+        .map(|_| {
+            recv.recv_timeout(std::time::Duration::from_secs(10))
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        items,
+        [
+            Item::Caps(TEST_PT as i32),
+            Item::Buffer(TEST_PT),
+            Item::Buffer(TEST_PT),
+            Item::Caps(TEST_PT as i32 + 1),
+            Item::Buffer(TEST_PT + 1),
+            Item::Buffer(TEST_PT + 1),
+        ]
+    );
+    assert_eq!(elem.src_pads().len(), 1);
 
     elem.release_request_pad(&sinkpad);
     elem.set_state(gst::State::Null).unwrap();
 }
+
+#[test]
+fn send_periodic_stats() {
+    init();
+
+    let id = next_element_counter();
+
+    let elem = gst::ElementFactory::make("rtpsend")
+        .property("rtp-id", id.to_string())
+        .property("stats-interval", 50u32)
+        .build()
+        .unwrap();
+    let pipeline = gst::Pipeline::new();
+    pipeline.add(&elem).unwrap();
+    let bus = pipeline.bus().unwrap();
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    for _ in 0..2 {
+        let msg = bus
+            .timed_pop_filtered(
+                gst::ClockTime::from_seconds(5),
+                &[gst::MessageType::Element],
+            )
+            .expect("no stats message posted");
+        assert_eq!(msg.src(), Some(elem.upcast_ref::<gst::Object>()));
+        let s = msg.structure().unwrap();
+        assert_eq!(s.name(), "application/x-rtp2-stats");
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn send_recv_shared_session() {
+    let mut h = send_init();
+    let id = h.element().unwrap().property::<String>("rtp-id");
+
+    // A receive-only element in a different bin shares the session through the rtp-id
+    let recv = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", &id)
+        .build()
+        .unwrap();
+    let bin = gst::Bin::new();
+    bin.add(&recv).unwrap();
+    let rtcp_sinkpad = recv.request_pad_simple("rtcp_sink_0").unwrap();
+    bin.set_state(gst::State::Playing).unwrap();
+
+    let packets = [PacketInfo {
+        seq_no: 100,
+        rtp_ts: 0,
+        payload_len: 8,
+    }];
+    send_push(&mut h, packets, false);
+    send_pull(&mut h, packets);
+
+    let stats = recv.property::<gst::Structure>("stats");
+    let session_stats = stats.get::<gst::Structure>("0").unwrap();
+    let source_stats = session_stats
+        .get::<gst::Structure>(TEST_SSRC.to_string())
+        .unwrap();
+    assert!(source_stats.get::<bool>("sender").unwrap());
+    assert!(source_stats.get::<bool>("local").unwrap());
+    assert_eq!(source_stats.get::<u64>("packets-sent").unwrap(), 1);
+
+    recv.release_request_pad(&rtcp_sinkpad);
+    bin.set_state(gst::State::Null).unwrap();
+}
+
+#[test]
+fn send_ssrc_collision() {
+    let mut h = send_init();
+    let id = h.element().unwrap().property::<String>("rtp-id");
+
+    let recv = gst::ElementFactory::make("rtprecv")
+        .property("rtp-id", &id)
+        .build()
+        .unwrap();
+    let bin = gst::Bin::new();
+    bin.add(&recv).unwrap();
+    let rtcp_sinkpad = recv.request_pad_simple("rtcp_sink_0").unwrap();
+    bin.set_state(gst::State::Playing).unwrap();
+    rtcp_sinkpad.send_event(gst::event::StreamStart::new("rtcp"));
+    rtcp_sinkpad.send_event(gst::event::Caps::new(
+        &Caps::builder("application/x-rtcp").build(),
+    ));
+    let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    rtcp_sinkpad.send_event(gst::event::Segment::new(&segment));
+
+    // Another participant already sends with our SSRC
+    let mut data = vec![0; 128];
+    let len = rtcp_types::Compound::builder()
+        .add_packet(
+            rtcp_types::SenderReport::builder(TEST_SSRC)
+                .ntp_timestamp(1000 << 32)
+                .rtp_timestamp(0)
+                .packet_count(1)
+                .octet_count(8),
+        )
+        .write_into(&mut data)
+        .unwrap();
+    data.truncate(len);
+    let mut buffer = gst::Buffer::from_mut_slice(data);
+    let addr =
+        gio::InetSocketAddress::new(&gio::InetAddress::from_string("127.0.0.1").unwrap(), 5005);
+    gst_net::NetAddressMeta::add(buffer.get_mut().unwrap(), &addr);
+    rtcp_sinkpad.chain(buffer).unwrap();
+
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let session = h
+        .element()
+        .unwrap()
+        .emit_by_name::<gst::glib::Object>("get-session", &[&0u32]);
+    session.connect("on-ssrc-collision", false, move |args| {
+        let ssrc = args[1].get::<u32>().unwrap();
+        let new_ssrc = args[2].get::<u32>().unwrap();
+        sender.send((ssrc, new_ssrc)).unwrap();
+        None
+    });
+
+    // The colliding packet is sent with the new SSRC instead of being dropped
+    let packets = [PacketInfo {
+        seq_no: 100,
+        rtp_ts: 0,
+        payload_len: 8,
+    }];
+    send_push(&mut h, packets, false);
+    let (ssrc, new_ssrc) = receiver.try_recv().unwrap();
+    assert_eq!(ssrc, TEST_SSRC);
+    assert_ne!(new_ssrc, TEST_SSRC);
+
+    let buffer = h.pull().unwrap();
+    let mapped = buffer.map_readable().unwrap();
+    let rtp = RtpPacket::parse(&mapped).unwrap();
+    assert_eq!(rtp.ssrc(), new_ssrc);
+    assert_eq!(rtp.sequence_number(), 100);
+
+    recv.release_request_pad(&rtcp_sinkpad);
+    bin.set_state(gst::State::Null).unwrap();
+}
@@ -0,0 +1,195 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::{Arc, Mutex};
+
+use gst::prelude::*;
+use gst_check::Harness;
+use rtp_types::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsrtp::plugin_register_static().expect("rtpssrcdemux2 test");
+    });
+}
+
+fn generate_rtp_buffer(ssrc: u32, seqno: u16) -> gst::Buffer {
+    let payload = [0u8; 8];
+    let packet = RtpPacketBuilder::new()
+        .ssrc(ssrc)
+        .payload_type(96)
+        .sequence_number(seqno)
+        .timestamp(seqno as u32 * 960)
+        .payload(payload.as_slice());
+    let size = packet.calculate_size().unwrap();
+    let mut data = vec![0; size];
+    packet.write_into(&mut data).unwrap();
+    gst::Buffer::from_mut_slice(data)
+}
+
+fn generate_rtcp_buffer(ssrc: u32) -> gst::Buffer {
+    let mut data = vec![0; 128];
+    let len = rtcp_types::Compound::builder()
+        .add_packet(
+            rtcp_types::SenderReport::builder(ssrc)
+                .ntp_timestamp(1000 << 32)
+                .rtp_timestamp(0)
+                .packet_count(2)
+                .octet_count(20),
+        )
+        .write_into(&mut data)
+        .unwrap();
+    data.truncate(len);
+    gst::Buffer::from_mut_slice(data)
+}
+
+type SrcHarnesses = Arc<Mutex<Vec<(String, Harness)>>>;
+
+/// Returns harnesses for the RTP and RTCP sink pads and links a new harness to every added
+/// source pad
+fn demux_init() -> (gst::Element, Harness, Harness, SrcHarnesses) {
+    init();
+
+    let demux = gst::ElementFactory::make("rtpssrcdemux2").build().unwrap();
+
+    let src_harnesses = SrcHarnesses::default();
+    demux.connect_pad_added({
+        let src_harnesses = src_harnesses.clone();
+        move |_demux, pad| {
+            let mut h = Harness::new_empty();
+            h.add_element_src_pad(pad);
+            src_harnesses
+                .lock()
+                .unwrap()
+                .push((pad.name().to_string(), h));
+        }
+    });
+
+    let mut h_rtp = Harness::with_element(&demux, Some("sink"), None);
+    h_rtp.set_src_caps(
+        gst::Caps::builder("application/x-rtp")
+            .field("media", "audio")
+            .field("payload", 96i32)
+            .field("clock-rate", 48_000i32)
+            .build(),
+    );
+    h_rtp.play();
+
+    let mut h_rtcp = Harness::with_element(&demux, Some("rtcp_sink"), None);
+    h_rtcp.set_src_caps(gst::Caps::builder("application/x-rtcp").build());
+    h_rtcp.play();
+
+    (demux, h_rtp, h_rtcp, src_harnesses)
+}
+
+fn pad_names(src_harnesses: &SrcHarnesses) -> Vec<String> {
+    src_harnesses
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+#[test]
+fn test_ssrc_demux() {
+    let (_demux, mut h_rtp, mut h_rtcp, src_harnesses) = demux_init();
+
+    for (seqno, ssrc) in [1234, 5678, 1234].into_iter().enumerate() {
+        assert_eq!(
+            h_rtp.push(generate_rtp_buffer(ssrc, seqno as u16)),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    assert_eq!(
+        h_rtcp.push(generate_rtcp_buffer(5678)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    // Neither valid RTP nor RTCP
+    assert_eq!(
+        h_rtp.push(gst::Buffer::from_slice([0u8; 4])),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        h_rtcp.push(gst::Buffer::from_slice([0u8; 4])),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    assert_eq!(
+        pad_names(&src_harnesses),
+        ["src_1234", "rtcp_src_1234", "src_5678", "rtcp_src_5678"]
+    );
+
+    let mut src_harnesses = src_harnesses.lock().unwrap();
+    for (name, h) in src_harnesses.iter_mut() {
+        let expected = match name.as_str() {
+            "src_1234" => 2,
+            "src_5678" | "rtcp_src_5678" => 1,
+            _ => 0,
+        };
+        assert_eq!(h.buffers_in_queue(), expected, "{name}");
+
+        if name.starts_with("rtcp_") {
+            continue;
+        }
+
+        let ssrc = name.strip_prefix("src_").unwrap().parse::<u32>().unwrap();
+        let caps = h.sinkpad().unwrap().current_caps().unwrap();
+        let s = caps.structure(0).unwrap();
+        assert_eq!(s.get::<u32>("ssrc").unwrap(), ssrc);
+        assert_eq!(s.get::<i32>("payload").unwrap(), 96);
+
+        for _ in 0..expected {
+            let buffer = h.pull().unwrap();
+            let map = buffer.map_readable().unwrap();
+            let packet = RtpPacket::parse(&map).unwrap();
+            assert_eq!(packet.ssrc(), ssrc);
+        }
+    }
+}
+
+#[test]
+fn test_rtcp_first() {
+    let (_demux, _h_rtp, mut h_rtcp, src_harnesses) = demux_init();
+
+    assert_eq!(
+        h_rtcp.push(generate_rtcp_buffer(1234)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(pad_names(&src_harnesses), ["src_1234", "rtcp_src_1234"]);
+
+    let src_harnesses = src_harnesses.lock().unwrap();
+    assert_eq!(src_harnesses[0].1.buffers_in_queue(), 0);
+    assert_eq!(src_harnesses[1].1.buffers_in_queue(), 1);
+}
+
+#[test]
+fn test_clear_ssrc() {
+    let (demux, mut h_rtp, _h_rtcp, src_harnesses) = demux_init();
+
+    assert_eq!(
+        h_rtp.push(generate_rtp_buffer(1234, 0)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert!(demux.static_pad("src_1234").is_some());
+
+    demux.emit_by_name::<()>("clear-ssrc", &[&1234u32]);
+    assert!(demux.static_pad("src_1234").is_none());
+    assert!(demux.static_pad("rtcp_src_1234").is_none());
+
+    // Pads are added again for new packets
+    src_harnesses.lock().unwrap().clear();
+    assert_eq!(
+        h_rtp.push(generate_rtp_buffer(1234, 1)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(pad_names(&src_harnesses), ["src_1234", "rtcp_src_1234"]);
+}
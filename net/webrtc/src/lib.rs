@@ -20,6 +20,7 @@ mod aws_kvs_signaller;
 mod janusvr_signaller;
 #[cfg(feature = "livekit")]
 mod livekit_signaller;
+pub mod preflight;
 pub mod signaller;
 pub mod utils;
 pub mod webrtcsink;
@@ -0,0 +1,537 @@
+// SPDX-License-Identifier: MPL-2.0
+
+//! Connectivity pre-flight check
+//!
+//! Connects two local webrtcbin instances configured with the STUN and TURN servers
+//! of the element and measures how long ICE gathering takes. When TURN servers are
+//! configured, only relay candidates are used and the round-trip time and the
+//! throughput of a data channel between them are measured, which then reflect the
+//! network path to the TURN server. Without TURN servers the connection stays on the
+//! local host and measuring it would say nothing about the network.
+
+use gst::glib;
+use gst::prelude::*;
+use gst_webrtc::WebRTCDataChannel;
+use std::sync::mpsc;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::utils::make_element;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "webrtc-preflight",
+        gst::DebugColorFlags::empty(),
+        Some("WebRTC connectivity pre-flight check"),
+    )
+});
+
+/// Name of the element message posted with the results
+pub const RESULT_MESSAGE_NAME: &str = "webrtc-preflight-result";
+
+const GATHERING_TIMEOUT: Duration = Duration::from_secs(10);
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+const NUM_PINGS: u32 = 5;
+const PROBE_CHUNK_SIZE: usize = 16 * 1024;
+const MAX_BUFFERED_AMOUNT: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Default)]
+pub struct PreflightConfig {
+    pub stun_server: Option<String>,
+    pub turn_servers: Vec<String>,
+    /// How long to send data for estimating the throughput, zero to skip probing
+    pub probe_duration: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PreflightResult {
+    pub gathering_time: Option<gst::ClockTime>,
+    pub host_candidates: u32,
+    pub srflx_candidates: u32,
+    pub relay_candidates: u32,
+    pub rtt: Option<gst::ClockTime>,
+    /// Estimated throughput in bits per second
+    pub throughput: Option<u64>,
+    pub error: Option<String>,
+}
+
+impl PreflightResult {
+    pub fn to_structure(&self) -> gst::Structure {
+        gst::Structure::builder(RESULT_MESSAGE_NAME)
+            .field("gathering-time", self.gathering_time)
+            .field("host-candidates", self.host_candidates)
+            .field("srflx-candidates", self.srflx_candidates)
+            .field("relay-candidates", self.relay_candidates)
+            .field("rtt", self.rtt)
+            .field_if_some("throughput", self.throughput)
+            .field_if_some("error", self.error.as_deref())
+            .build()
+    }
+}
+
+enum Event {
+    GatheringComplete(Instant),
+    ChannelOpen,
+    Reply(String),
+}
+
+/// Runs the check with the STUN and TURN servers of `element`, as done by the
+/// `run-preflight-check` action signal of webrtcsink and webrtcsrc
+pub fn run_for_element(
+    element: &gst::Element,
+    stun_server: Option<String>,
+    turn_servers: &gst::Array,
+    probe_duration: u32,
+) {
+    let config = PreflightConfig {
+        stun_server,
+        turn_servers: turn_servers
+            .iter()
+            .filter_map(|v| v.get::<String>().ok())
+            .collect(),
+        probe_duration: Duration::from_millis(probe_duration as u64),
+    };
+
+    gst::info!(
+        CAT,
+        obj = element,
+        "Running pre-flight check with {config:?}"
+    );
+
+    spawn(element, config);
+}
+
+/// Runs the check in a new thread and posts the result as element message of `element`
+pub fn spawn(element: &gst::Element, config: PreflightConfig) {
+    let element_weak = element.downgrade();
+
+    let res = std::thread::Builder::new()
+        .name("webrtc-preflight".into())
+        .spawn(move || {
+            let result = run(&config);
+
+            if let Some(element) = element_weak.upgrade() {
+                gst::info!(CAT, obj = element, "Pre-flight check finished: {result:?}");
+                let _ = element.post_message(
+                    gst::message::Element::builder(result.to_structure())
+                        .src(&element)
+                        .build(),
+                );
+            }
+        });
+
+    if let Err(err) = res {
+        gst::error!(
+            CAT,
+            obj = element,
+            "Failed to start pre-flight check: {err}"
+        );
+    }
+}
+
+/// Runs the check and blocks until it is finished
+pub fn run(config: &PreflightConfig) -> PreflightResult {
+    let mut result = PreflightResult::default();
+    let pipeline = gst::Pipeline::with_name("webrtc-preflight");
+
+    if let Err(err) = run_in_pipeline(&pipeline, config, &mut result) {
+        gst::warning!(CAT, "Pre-flight check failed: {err}");
+        result.error = Some(err);
+    }
+
+    let _ = pipeline.set_state(gst::State::Null);
+
+    result
+}
+
+fn make_webrtcbin(config: &PreflightConfig, name: &str) -> Result<gst::Element, String> {
+    let webrtcbin = make_element("webrtcbin", Some(name)).map_err(|err| err.to_string())?;
+
+    webrtcbin.set_property_from_str("bundle-policy", "max-bundle");
+
+    if let Some(stun_server) = config.stun_server.as_ref() {
+        webrtcbin.set_property("stun-server", stun_server);
+    }
+
+    for turn_server in config.turn_servers.iter() {
+        webrtcbin.emit_by_name::<bool>("add-turn-server", &[turn_server]);
+    }
+
+    if !config.turn_servers.is_empty() {
+        webrtcbin.set_property_from_str("ice-transport-policy", "relay");
+    }
+
+    Ok(webrtcbin)
+}
+
+fn candidate_type(candidate: &str) -> Option<&str> {
+    candidate
+        .split_whitespace()
+        .skip_while(|s| *s != "typ")
+        .nth(1)
+}
+
+fn forward_candidates(
+    from: &gst::Element,
+    to: &gst::Element,
+    counts: Option<Arc<Mutex<PreflightResult>>>,
+) {
+    let to_weak = to.downgrade();
+    from.connect("on-ice-candidate", false, move |values| {
+        let mline = values[1].get::<u32>().expect("signal arg");
+        let candidate = values[2].get::<String>().expect("signal arg");
+
+        if let Some(counts) = counts.as_ref() {
+            let mut counts = counts.lock().unwrap();
+            match candidate_type(&candidate) {
+                Some("host") => counts.host_candidates += 1,
+                Some("srflx") | Some("prflx") => counts.srflx_candidates += 1,
+                Some("relay") => counts.relay_candidates += 1,
+                _ => (),
+            }
+        }
+
+        if let Some(to) = to_weak.upgrade() {
+            to.emit_by_name::<()>("add-ice-candidate", &[&mline, &candidate]);
+        }
+
+        None
+    });
+}
+
+fn wait_description(
+    promise: &gst::Promise,
+    field: &str,
+) -> Result<gst_webrtc::WebRTCSessionDescription, String> {
+    match promise.wait() {
+        gst::PromiseResult::Replied => (),
+        res => return Err(format!("Promise for {field} returned {res:?}")),
+    }
+
+    promise
+        .get_reply()
+        .and_then(|reply| {
+            reply
+                .value(field)
+                .ok()
+                .and_then(|v| v.get::<gst_webrtc::WebRTCSessionDescription>().ok())
+        })
+        .ok_or_else(|| format!("No {field} in promise reply"))
+}
+
+fn negotiate(offerer: &gst::Element, answerer: &gst::Element) -> Result<(), String> {
+    let promise = gst::Promise::new();
+    offerer.emit_by_name::<()>("create-offer", &[&None::<gst::Structure>, &promise]);
+    let offer = wait_description(&promise, "offer")?;
+
+    offerer.emit_by_name::<()>("set-local-description", &[&offer, &None::<gst::Promise>]);
+    answerer.emit_by_name::<()>("set-remote-description", &[&offer, &None::<gst::Promise>]);
+
+    let promise = gst::Promise::new();
+    answerer.emit_by_name::<()>("create-answer", &[&None::<gst::Structure>, &promise]);
+    let answer = wait_description(&promise, "answer")?;
+
+    answerer.emit_by_name::<()>("set-local-description", &[&answer, &None::<gst::Promise>]);
+    offerer.emit_by_name::<()>("set-remote-description", &[&answer, &None::<gst::Promise>]);
+
+    Ok(())
+}
+
+/// Echoes pings back and reports the amount of probing data received when asked for it
+fn setup_echo(answerer: &gst::Element) {
+    answerer.connect("on-data-channel", false, |values| {
+        let channel = values[1].get::<WebRTCDataChannel>().expect("signal arg");
+
+        // Bytes received and time of the first and last chunk
+        let received = Arc::new(Mutex::new((0u64, None::<Instant>, None::<Instant>)));
+
+        let received_clone = received.clone();
+        channel.connect_closure(
+            "on-message-data",
+            false,
+            glib::closure!(
+                move |_channel: &WebRTCDataChannel, data: Option<glib::Bytes>| {
+                    let now = Instant::now();
+                    let mut received = received_clone.lock().unwrap();
+                    received.0 += data.map_or(0, |data| data.len() as u64);
+                    received.1.get_or_insert(now);
+                    received.2 = Some(now);
+                }
+            ),
+        );
+
+        channel.connect_closure(
+            "on-message-string",
+            false,
+            glib::closure!(move |channel: &WebRTCDataChannel, msg: Option<&str>| {
+                let Some(msg) = msg else {
+                    return;
+                };
+
+                if msg == "end" {
+                    let (bytes, first, last) = *received.lock().unwrap();
+                    let elapsed = first.zip(last).map_or(0, |(first, last)| {
+                        last.duration_since(first).as_nanos() as u64
+                    });
+                    channel.send_string(Some(&format!("received {bytes} {elapsed}")));
+                } else {
+                    channel.send_string(Some(msg));
+                }
+            }),
+        );
+
+        None
+    });
+}
+
+fn run_in_pipeline(
+    pipeline: &gst::Pipeline,
+    config: &PreflightConfig,
+    result: &mut PreflightResult,
+) -> Result<(), String> {
+    let offerer = make_webrtcbin(config, "offerer")?;
+    let answerer = make_webrtcbin(config, "answerer")?;
+
+    pipeline
+        .add_many([&offerer, &answerer])
+        .map_err(|err| err.to_string())?;
+
+    let counts = Arc::new(Mutex::new(PreflightResult::default()));
+    forward_candidates(&offerer, &answerer, Some(counts.clone()));
+    forward_candidates(&answerer, &offerer, None);
+
+    let (sender, receiver) = mpsc::channel();
+
+    let gathering_sender = Mutex::new(sender.clone());
+    offerer.connect_notify(Some("ice-gathering-state"), move |webrtcbin, _| {
+        if webrtcbin.property::<gst_webrtc::WebRTCICEGatheringState>("ice-gathering-state")
+            == gst_webrtc::WebRTCICEGatheringState::Complete
+        {
+            let _ = gathering_sender
+                .lock()
+                .unwrap()
+                .send(Event::GatheringComplete(Instant::now()));
+        }
+    });
+
+    setup_echo(&answerer);
+
+    pipeline
+        .set_state(gst::State::Playing)
+        .map_err(|err| format!("Failed to start pipeline: {err}"))?;
+
+    let channel = offerer.emit_by_name::<WebRTCDataChannel>(
+        "create-data-channel",
+        &[&"preflight", &None::<gst::Structure>],
+    );
+
+    let open_sender = Mutex::new(sender.clone());
+    channel.connect_closure(
+        "on-open",
+        false,
+        glib::closure!(move |_channel: &WebRTCDataChannel| {
+            let _ = open_sender.lock().unwrap().send(Event::ChannelOpen);
+        }),
+    );
+
+    let reply_sender = Mutex::new(sender);
+    channel.connect_closure(
+        "on-message-string",
+        false,
+        glib::closure!(move |_channel: &WebRTCDataChannel, msg: Option<&str>| {
+            if let Some(msg) = msg {
+                let _ = reply_sender
+                    .lock()
+                    .unwrap()
+                    .send(Event::Reply(msg.to_string()));
+            }
+        }),
+    );
+
+    let start = Instant::now();
+    negotiate(&offerer, &answerer)?;
+
+    // Wait for gathering to finish and the data channel to be opened, in any order
+    let mut channel_open = false;
+    while result.gathering_time.is_none() || !channel_open {
+        let deadline = if result.gathering_time.is_none() {
+            start + GATHERING_TIMEOUT
+        } else {
+            start + CONNECTION_TIMEOUT
+        };
+
+        match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+            Ok(Event::GatheringComplete(at)) => {
+                result.gathering_time = Some(gst::ClockTime::from_nseconds(
+                    at.duration_since(start).as_nanos() as u64,
+                ));
+            }
+            Ok(Event::ChannelOpen) => channel_open = true,
+            Ok(Event::Reply(_)) => (),
+            Err(_) if result.gathering_time.is_none() => {
+                gst::warning!(CAT, "ICE gathering did not complete in time");
+                break;
+            }
+            Err(_) => break,
+        }
+    }
+
+    {
+        let counts = counts.lock().unwrap();
+        result.host_candidates = counts.host_candidates;
+        result.srflx_candidates = counts.srflx_candidates;
+        result.relay_candidates = counts.relay_candidates;
+    }
+
+    if !channel_open {
+        return Err("Failed to establish a connection".into());
+    }
+
+    if config.turn_servers.is_empty() {
+        gst::debug!(CAT, "No TURN servers, not measuring the local connection");
+        channel.close();
+        return Ok(());
+    }
+
+    let wait_message = |prefix: &str| -> Result<String, String> {
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        loop {
+            match receiver.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(Event::Reply(msg)) if msg.starts_with(prefix) => return Ok(msg),
+                Ok(_) => (),
+                Err(_) => return Err("Timed out waiting for a reply".into()),
+            }
+        }
+    };
+
+    // Round-trip time, take the minimum to ignore scheduling hiccups
+    let mut rtt = None::<Duration>;
+    for i in 0..NUM_PINGS {
+        let ping = format!("ping {i}");
+        let sent = Instant::now();
+        channel.send_string(Some(&ping));
+        wait_message(&ping)?;
+        let elapsed = sent.elapsed();
+        rtt = Some(rtt.map_or(elapsed, |rtt| rtt.min(elapsed)));
+    }
+    result.rtt = rtt.map(|rtt| gst::ClockTime::from_nseconds(rtt.as_nanos() as u64));
+
+    if !config.probe_duration.is_zero() {
+        let chunk = glib::Bytes::from_owned(vec![0u8; PROBE_CHUNK_SIZE]);
+        let probe_start = Instant::now();
+        while probe_start.elapsed() < config.probe_duration {
+            if channel.property::<u64>("buffered-amount") > MAX_BUFFERED_AMOUNT {
+                std::thread::sleep(Duration::from_millis(1));
+                continue;
+            }
+            channel.send_data(Some(&chunk));
+        }
+
+        channel.send_string(Some("end"));
+        let reply = wait_message("received ")?;
+
+        let mut fields = reply.split_whitespace().skip(1);
+        let bytes = fields.next().and_then(|v| v.parse::<u64>().ok());
+        let elapsed = fields.next().and_then(|v| v.parse::<u64>().ok());
+        if let (Some(bytes), Some(elapsed)) = (bytes, elapsed) {
+            if elapsed > 0 {
+                result.throughput =
+                    Some((bytes as u128 * 8 * 1_000_000_000 / elapsed as u128) as u64);
+            }
+        }
+    }
+
+    channel.close();
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init() {
+        use std::sync::Once;
+        static INIT: Once = Once::new();
+
+        INIT.call_once(|| {
+            gst::init().unwrap();
+            crate::plugin_register_static().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_candidate_type() {
+        assert_eq!(
+            candidate_type("candidate:1 1 UDP 2015363327 192.168.1.2 47793 typ host"),
+            Some("host")
+        );
+        assert_eq!(
+            candidate_type(
+                "candidate:2 1 UDP 1677721855 203.0.113.1 47793 typ srflx raddr 192.168.1.2 rport 47793"
+            ),
+            Some("srflx")
+        );
+        assert_eq!(
+            candidate_type("candidate:3 1 UDP 2015363327 192.168.1.2"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_run_without_turn() {
+        init();
+
+        if gst::ElementFactory::find("webrtcbin").is_none() {
+            return;
+        }
+
+        let result = run(&PreflightConfig {
+            probe_duration: Duration::from_millis(100),
+            ..Default::default()
+        });
+
+        // The local connection is not measured
+        assert_eq!(result.rtt, None);
+        assert_eq!(result.throughput, None);
+        assert_eq!(result.relay_candidates, 0);
+        if result.error.is_none() {
+            assert!(result.gathering_time.is_some());
+            assert!(result.host_candidates > 0);
+        }
+    }
+
+    #[test]
+    fn test_action_signal() {
+        init();
+
+        if gst::ElementFactory::find("webrtcbin").is_none() {
+            return;
+        }
+
+        for factory in ["webrtcsink", "webrtcsrc"] {
+            let pipeline = gst::Pipeline::new();
+            let element = gst::ElementFactory::make(factory).build().unwrap();
+            pipeline.add(&element).unwrap();
+
+            element.emit_by_name::<()>("run-preflight-check", &[&0u32]);
+
+            let msg = pipeline
+                .bus()
+                .unwrap()
+                .timed_pop_filtered(
+                    gst::ClockTime::from_seconds(30),
+                    &[gst::MessageType::Element],
+                )
+                .expect("no pre-flight result");
+            assert_eq!(msg.src(), Some(element.upcast_ref::<gst::Object>()));
+
+            let s = msg.structure().unwrap();
+            assert_eq!(s.name(), RESULT_MESSAGE_NAME);
+            assert!(s.has_field("host-candidates"));
+            assert_eq!(s.get::<Option<gst::ClockTime>>("rtt").unwrap(), None);
+            assert!(!s.has_field("throughput"));
+        }
+    }
+}
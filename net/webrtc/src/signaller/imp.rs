@@ -25,6 +25,9 @@ use url::Url;
 use super::CAT;
 
 const DEFAULT_INSECURE_TLS: bool = false;
+const DEFAULT_RECONNECT_ATTEMPTS: i32 = 0;
+const DEFAULT_RECONNECT_INTERVAL: u32 = 1000;
+const DEFAULT_MAX_RECONNECT_INTERVAL: u32 = 30000;
 
 #[derive(Debug, Eq, PartialEq, Clone, Copy, glib::Enum, Default)]
 #[repr(u32)]
@@ -36,6 +39,17 @@ pub enum WebRTCSignallerRole {
     Listener,
 }
 
+#[derive(Debug, Eq, PartialEq, Clone, Copy, glib::Enum, Default)]
+#[repr(u32)]
+#[enum_type(name = "GstRSWebRTCSignallerConnectionState")]
+pub enum WebRTCSignallerConnectionState {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    Reconnecting,
+}
+
 pub struct Settings {
     uri: Url,
     producer_peer_id: Option<String>,
@@ -43,6 +57,11 @@ pub struct Settings {
     role: WebRTCSignallerRole,
     headers: Option<gst::Structure>,
     insecure_tls: bool,
+    client_certificate: Option<String>,
+    client_key: Option<String>,
+    reconnect_attempts: i32,
+    reconnect_interval: u32,
+    max_reconnect_interval: u32,
 }
 
 impl Default for Settings {
@@ -54,6 +73,11 @@ impl Default for Settings {
             role: Default::default(),
             headers: None,
             insecure_tls: DEFAULT_INSECURE_TLS,
+            client_certificate: None,
+            client_key: None,
+            reconnect_attempts: DEFAULT_RECONNECT_ATTEMPTS,
+            reconnect_interval: DEFAULT_RECONNECT_INTERVAL,
+            max_reconnect_interval: DEFAULT_MAX_RECONNECT_INTERVAL,
         }
     }
 }
@@ -72,7 +96,12 @@ struct State {
     send_task_handle: Option<task::JoinHandle<Result<(), Error>>>,
     receive_task_handle: Option<task::JoinHandle<()>>,
     producers: HashSet<String>,
+    /// Sessions started through the current connection
+    sessions: HashSet<String>,
     client_id: Option<String>,
+    connection_state: WebRTCSignallerConnectionState,
+    /// Whether the signaller was started and should stay connected
+    running: bool,
 }
 
 impl Signaller {
@@ -111,12 +140,145 @@ impl Signaller {
         Ok(())
     }
 
+    fn reconnect_enabled(&self) -> bool {
+        self.settings.lock().unwrap().reconnect_attempts != 0
+    }
+
+    fn set_connection_state(&self, connection_state: WebRTCSignallerConnectionState) {
+        {
+            let mut state = self.state.lock().unwrap();
+            if state.connection_state == connection_state {
+                return;
+            }
+            state.connection_state = connection_state;
+        }
+
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Connection state changed to {connection_state:?}"
+        );
+
+        self.obj()
+            .emit_by_name::<()>("connection-state-changed", &[&connection_state]);
+    }
+
+    fn spawn_connect_task(&self, reconnecting: bool) -> task::JoinHandle<()> {
+        RUNTIME.spawn(glib::clone!(
+            #[to_owned(rename_to = this)]
+            self,
+            async move {
+                this.connect_with_retries(reconnecting).await;
+            }
+        ))
+    }
+
+    /// Connects to the server, retrying with an exponential backoff up to `reconnect-attempts`
+    /// times. When `reconnecting`, the first attempt is made after `reconnect-interval`.
+    async fn connect_with_retries(&self, reconnecting: bool) {
+        let (reconnect_attempts, reconnect_interval, max_reconnect_interval) = {
+            let settings = self.settings.lock().unwrap();
+            (
+                settings.reconnect_attempts,
+                Duration::from_millis(settings.reconnect_interval as u64),
+                Duration::from_millis(settings.max_reconnect_interval as u64),
+            )
+        };
+
+        let mut interval = reconnect_interval;
+        let mut attempt = if reconnecting { 1 } else { 0 };
+        loop {
+            if attempt > 0 {
+                gst::info!(
+                    CAT,
+                    imp = self,
+                    "Reconnecting in {}ms (attempt {attempt})",
+                    interval.as_millis()
+                );
+                self.set_connection_state(WebRTCSignallerConnectionState::Reconnecting);
+                tokio::time::sleep(interval).await;
+                interval = (interval * 2).min(max_reconnect_interval);
+            } else {
+                self.set_connection_state(WebRTCSignallerConnectionState::Connecting);
+            }
+
+            let Err(err) = self.connect().await else {
+                return;
+            };
+
+            if reconnect_attempts >= 0 && attempt >= reconnect_attempts {
+                self.set_connection_state(WebRTCSignallerConnectionState::Disconnected);
+                self.obj()
+                    .emit_by_name::<()>("error", &[&format!("Error connecting: {}", err)]);
+                return;
+            }
+
+            gst::warning!(CAT, imp = self, "Failed to connect: {err}");
+            attempt += 1;
+        }
+    }
+
+    /// Called once the websocket connection ended, reconnects if enabled unless the signaller
+    /// was stopped.
+    ///
+    /// The server forgets about our sessions and peer id with the connection, so all active
+    /// sessions are ended and the known producers removed before reconnecting.
+    fn connection_lost(&self) {
+        let (sessions, producers) = {
+            let mut state = self.state.lock().unwrap();
+            if !state.running {
+                return;
+            }
+
+            // Closing the channel terminates the send task, the receive task is the caller
+            state.websocket_sender = None;
+            state.send_task_handle = None;
+            state.receive_task_handle = None;
+            state.client_id = None;
+
+            (
+                std::mem::take(&mut state.sessions),
+                std::mem::take(&mut state.producers),
+            )
+        };
+
+        for session_id in sessions {
+            gst::info!(
+                CAT,
+                imp = self,
+                "Ending session {session_id} on connection loss"
+            );
+            self.obj()
+                .emit_by_name::<bool>("session-ended", &[&session_id]);
+        }
+
+        for peer_id in producers {
+            self.obj()
+                .emit_by_name::<()>("producer-removed", &[&peer_id, &None::<gst::Structure>]);
+        }
+
+        if !self.reconnect_enabled() {
+            self.set_connection_state(WebRTCSignallerConnectionState::Disconnected);
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if !state.running {
+            return;
+        }
+
+        gst::info!(CAT, imp = self, "Connection lost, reconnecting");
+        state.connect_task_handle = Some(self.spawn_connect_task(true));
+    }
+
     async fn connect(&self) -> Result<(), Error> {
-        let (cafile, insecure_tls, role) = {
+        let (cafile, insecure_tls, client_certificate, client_key, role) = {
             let settings = self.settings.lock().unwrap();
             (
                 settings.cafile.clone(),
                 settings.insecure_tls,
+                settings.client_certificate.clone(),
+                settings.client_key.clone(),
                 settings.role,
             )
         };
@@ -139,6 +301,15 @@ impl Signaller {
             gst::warning!(CAT, imp = self, "insecure tls connections are allowed");
         }
 
+        if let Some(path) = client_certificate {
+            let key_path =
+                client_key.ok_or_else(|| anyhow!("No private key set for client certificate"))?;
+            let cert = tokio::fs::read(&path).await?;
+            let key = tokio::fs::read(&key_path).await?;
+            let identity = tokio_native_tls::native_tls::Identity::from_pkcs8(&cert, &key)?;
+            connector_builder.identity(identity);
+        }
+
         let connector = Some(tokio_native_tls::TlsConnector::from(
             connector_builder.build()?,
         ));
@@ -150,13 +321,11 @@ impl Signaller {
 
         let mut req = uri.into_client_request()?;
         let req_headers = req.headers_mut();
-        if let Some(headers) = self.headers() {
-            for (key, value) in headers {
-                req_headers.insert(
-                    HeaderName::from_bytes(key.as_bytes()).unwrap(),
-                    HeaderValue::from_bytes(value.as_bytes()).unwrap(),
-                );
-            }
+        for (key, value) in self.headers() {
+            req_headers.insert(
+                HeaderName::from_bytes(key.as_bytes())?,
+                HeaderValue::from_bytes(value.as_bytes())?,
+            );
         }
 
         let (ws, _) = timeout(
@@ -207,6 +376,11 @@ impl Signaller {
                 None
             };
 
+        self.set_connection_state(WebRTCSignallerConnectionState::Connected);
+
+        // Hold the state lock until the task handles are stored, in case the connection is lost
+        // right away
+        let mut state = self.state.lock().unwrap();
         let receive_task_handle = RUNTIME.spawn(glib::clone!(
             #[to_owned(rename_to = this)]
             self,
@@ -219,10 +393,11 @@ impl Signaller {
 
                 let msg = "Stopped websocket receiving";
                 gst::info!(CAT, imp = this, "{msg}");
+
+                this.connection_lost();
             }
         ));
 
-        let mut state = self.state.lock().unwrap();
         state.websocket_sender = Some(websocket_sender);
         state.send_task_handle = Some(send_task_handle);
         state.receive_task_handle = Some(receive_task_handle);
@@ -263,32 +438,33 @@ impl Signaller {
         settings.producer_peer_id.clone()
     }
 
-    fn headers(&self) -> Option<HashMap<String, String>> {
-        self.settings
-            .lock()
-            .unwrap()
-            .headers
-            .as_ref()
-            .map(|structure| {
-                let mut hash = HashMap::new();
-
-                for (key, value) in structure.iter() {
-                    if let Ok(Ok(value_str)) = value.transform::<String>().map(|v| v.get()) {
-                        gst::log!(CAT, imp = self, "headers '{}' -> '{}'", key, value_str);
-                        hash.insert(key.to_string(), value_str);
-                    } else {
-                        gst::warning!(
-                            CAT,
-                            imp = self,
-                            "Failed to convert headers '{}' to string ('{:?}')",
-                            key,
-                            value
-                        );
-                    }
+    /// Headers of the `headers` property, extended with the ones returned by the
+    /// `request-headers` signal for this connection attempt
+    fn headers(&self) -> HashMap<String, String> {
+        let headers = self.settings.lock().unwrap().headers.clone();
+        let request_headers = self
+            .obj()
+            .emit_by_name::<Option<gst::Structure>>("request-headers", &[]);
+
+        let mut hash = HashMap::new();
+        for structure in headers.iter().chain(request_headers.iter()) {
+            for (key, value) in structure.iter() {
+                if let Ok(Ok(value_str)) = value.transform::<String>().map(|v| v.get()) {
+                    gst::log!(CAT, imp = self, "headers '{}' -> '{}'", key, value_str);
+                    hash.insert(key.to_string(), value_str);
+                } else {
+                    gst::warning!(
+                        CAT,
+                        imp = self,
+                        "Failed to convert headers '{}' to string ('{:?}')",
+                        key,
+                        value
+                    );
                 }
+            }
+        }
 
-                hash
-            })
+        hash
     }
 
     fn send(&self, msg: p::IncomingMessage) {
@@ -377,6 +553,12 @@ impl Signaller {
                             peer_id,
                             session_id,
                         } => {
+                            self.state
+                                .lock()
+                                .unwrap()
+                                .sessions
+                                .insert(session_id.clone());
+
                             self.obj()
                                 .emit_by_name::<()>("session-started", &[&session_id, &peer_id]);
                         }
@@ -408,6 +590,12 @@ impl Signaller {
                                 }
                             };
 
+                            self.state
+                                .lock()
+                                .unwrap()
+                                .sessions
+                                .insert(session_id.clone());
+
                             self.obj().emit_by_name::<()>(
                                 "session-requested",
                                 &[
@@ -425,6 +613,8 @@ impl Signaller {
                         p::OutgoingMessage::EndSession(p::EndSessionMessage { session_id }) => {
                             gst::info!(CAT, imp = self, "Session {session_id} ended");
 
+                            self.state.lock().unwrap().sessions.remove(&session_id);
+
                             self.obj()
                                 .emit_by_name::<bool>("session-ended", &[&session_id]);
                         }
@@ -521,8 +711,12 @@ impl Signaller {
             }
             Ok(_) => (),
             Err(err) => {
-                self.obj()
-                    .emit_by_name::<()>("error", &[&format!("Error receiving: {}", err)]);
+                if self.reconnect_enabled() {
+                    gst::warning!(CAT, imp = self, "Error receiving: {err}");
+                } else {
+                    self.obj()
+                        .emit_by_name::<()>("error", &[&format!("Error receiving: {}", err)]);
+                }
                 return ControlFlow::Break(());
             }
         }
@@ -589,12 +783,86 @@ impl ObjectImpl for Signaller {
                     .default_value(DEFAULT_INSECURE_TLS)
                     .flags(glib::ParamFlags::READWRITE)
                     .build(),
+                glib::ParamSpecString::builder("client-certificate")
+                    .nick("Client certificate file")
+                    .blurb("PEM certificate file used for TLS client authentication")
+                    .flags(glib::ParamFlags::READWRITE)
+                    .build(),
+                glib::ParamSpecString::builder("client-key")
+                    .nick("Client key file")
+                    .blurb("PEM PKCS #8 private key file of the client certificate")
+                    .flags(glib::ParamFlags::READWRITE)
+                    .build(),
+                /**
+                 * GstWebRTCSignaller::reconnect-attempts:
+                 *
+                 * Number of times to try reconnecting after failing to connect or losing the
+                 * connection to the server. While reconnecting, errors are only reported once
+                 * all attempts failed.
+                 */
+                glib::ParamSpecInt::builder("reconnect-attempts")
+                    .nick("Reconnect attempts")
+                    .blurb("Number of reconnection attempts, 0 to disable and -1 for unlimited attempts")
+                    .minimum(-1)
+                    .default_value(DEFAULT_RECONNECT_ATTEMPTS)
+                    .flags(glib::ParamFlags::READWRITE)
+                    .build(),
+                glib::ParamSpecUInt::builder("reconnect-interval")
+                    .nick("Reconnect interval")
+                    .blurb("Interval before the first reconnection attempt in milliseconds, doubled after each failed attempt")
+                    .minimum(1)
+                    .default_value(DEFAULT_RECONNECT_INTERVAL)
+                    .flags(glib::ParamFlags::READWRITE)
+                    .build(),
+                glib::ParamSpecUInt::builder("max-reconnect-interval")
+                    .nick("Maximum reconnect interval")
+                    .blurb("Maximum interval between reconnection attempts in milliseconds")
+                    .minimum(1)
+                    .default_value(DEFAULT_MAX_RECONNECT_INTERVAL)
+                    .flags(glib::ParamFlags::READWRITE)
+                    .build(),
+                glib::ParamSpecEnum::builder::<WebRTCSignallerConnectionState>("connection-state")
+                    .nick("Connection state")
+                    .blurb("State of the connection to the signalling server")
+                    .read_only()
+                    .build(),
             ]
         });
 
         PROPS.as_ref()
     }
 
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: LazyLock<Vec<glib::subclass::Signal>> = LazyLock::new(|| {
+            vec![
+                /**
+                 * GstWebRTCSignaller::request-headers:
+                 *
+                 * Emitted before each connection attempt to request HTTP headers to send
+                 * during the handshake in addition to the ones of the `headers` property,
+                 * e.g. refreshed authentication tokens.
+                 *
+                 * Returns: (nullable): A #GstStructure with the additional headers
+                 */
+                glib::subclass::Signal::builder("request-headers")
+                    .return_type::<Option<gst::Structure>>()
+                    .build(),
+                /**
+                 * GstWebRTCSignaller::connection-state-changed:
+                 * @state: the new connection state.
+                 *
+                 * This signal is emitted when the state of the connection to the signalling
+                 * server changes.
+                 */
+                glib::subclass::Signal::builder("connection-state-changed")
+                    .param_types([WebRTCSignallerConnectionState::static_type()])
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
+
     fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
         match pspec.name() {
             "uri" => {
@@ -636,6 +904,28 @@ impl ObjectImpl for Signaller {
                 self.settings.lock().unwrap().insecure_tls =
                     value.get::<bool>().expect("type checked upstream")
             }
+            "client-certificate" => {
+                self.settings.lock().unwrap().client_certificate = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+            }
+            "client-key" => {
+                self.settings.lock().unwrap().client_key = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream")
+            }
+            "reconnect-attempts" => {
+                self.settings.lock().unwrap().reconnect_attempts =
+                    value.get::<i32>().expect("type checked upstream")
+            }
+            "reconnect-interval" => {
+                self.settings.lock().unwrap().reconnect_interval =
+                    value.get::<u32>().expect("type checked upstream")
+            }
+            "max-reconnect-interval" => {
+                self.settings.lock().unwrap().max_reconnect_interval =
+                    value.get::<u32>().expect("type checked upstream")
+            }
             _ => unimplemented!(),
         }
     }
@@ -661,6 +951,12 @@ impl ObjectImpl for Signaller {
             "client-id" => self.state.lock().unwrap().client_id.to_value(),
             "headers" => settings.headers.to_value(),
             "insecure-tls" => settings.insecure_tls.to_value(),
+            "client-certificate" => settings.client_certificate.to_value(),
+            "client-key" => settings.client_key.to_value(),
+            "reconnect-attempts" => settings.reconnect_attempts.to_value(),
+            "reconnect-interval" => settings.reconnect_interval.to_value(),
+            "max-reconnect-interval" => settings.max_reconnect_interval.to_value(),
+            "connection-state" => self.state.lock().unwrap().connection_state.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -671,28 +967,28 @@ impl SignallableImpl for Signaller {
         gst::info!(CAT, imp = self, "Starting");
 
         let mut state = self.state.lock().unwrap();
-        let connect_task_handle = RUNTIME.spawn(glib::clone!(
-            #[to_owned(rename_to = this)]
-            self,
-            async move {
-                if let Err(err) = this.connect().await {
-                    this.obj()
-                        .emit_by_name::<()>("error", &[&format!("Error receiving: {}", err)]);
-                }
-            }
-        ));
-
-        state.connect_task_handle = Some(connect_task_handle);
+        state.running = true;
+        state.connect_task_handle = Some(self.spawn_connect_task(false));
     }
 
     fn stop(&self) {
         gst::info!(CAT, imp = self, "Stopping now");
 
-        let mut state = self.state.lock().unwrap();
+        // The tasks are joined without holding the state lock as they can take it, e.g. when
+        // the connection is lost
+        let (connect_task_handle, send_task_handle, receive_task_handle, websocket_sender) = {
+            let mut state = self.state.lock().unwrap();
+            state.running = false;
+            (
+                state.connect_task_handle.take(),
+                state.send_task_handle.take(),
+                state.receive_task_handle.take(),
+                state.websocket_sender.take(),
+            )
+        };
 
         // First make sure the connect task is stopped if it is still
         // running
-        let connect_task_handle = state.connect_task_handle.take();
         if let Some(handle) = connect_task_handle {
             RUNTIME.block_on(async move {
                 handle.abort();
@@ -700,9 +996,7 @@ impl SignallableImpl for Signaller {
             });
         }
 
-        let send_task_handle = state.send_task_handle.take();
-        let receive_task_handle = state.receive_task_handle.take();
-        if let Some(mut sender) = state.websocket_sender.take() {
+        if let Some(mut sender) = websocket_sender {
             RUNTIME.block_on(async move {
                 sender.close_channel();
 
@@ -718,8 +1012,14 @@ impl SignallableImpl for Signaller {
                 }
             });
         }
+
+        let mut state = self.state.lock().unwrap();
         state.producers.clear();
+        state.sessions.clear();
         state.client_id = None;
+        drop(state);
+
+        self.set_connection_state(WebRTCSignallerConnectionState::Disconnected);
     }
 
     fn send_sdp(&self, session_id: &str, sdp: &gst_webrtc::WebRTCSessionDescription) {
@@ -770,7 +1070,8 @@ impl SignallableImpl for Signaller {
     fn end_session(&self, session_id: &str) {
         gst::debug!(CAT, imp = self, "Signalling session done {}", session_id);
 
-        let state = self.state.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+        state.sessions.remove(session_id);
         let session_id = session_id.to_string();
         if let Some(mut sender) = state.websocket_sender.clone() {
             RUNTIME.spawn(glib::clone!(
@@ -791,3 +1092,151 @@ impl SignallableImpl for Signaller {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc as std_mpsc;
+    use std::time::Instant;
+
+    fn init() {
+        use std::sync::Once;
+        static INIT: Once = Once::new();
+
+        INIT.call_once(|| {
+            gst::init().unwrap();
+        });
+    }
+
+    #[test]
+    fn test_reconnect_ends_sessions() {
+        init();
+
+        let listener = RUNTIME
+            .block_on(tokio::net::TcpListener::bind("127.0.0.1:0"))
+            .unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (conn_tx, conn_rx) = std_mpsc::channel();
+
+        // The first connection starts a session and is closed right away, the second one
+        // stays open
+        RUNTIME.spawn(async move {
+            for i in 0.. {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut ws = async_tungstenite::tokio::accept_async(stream)
+                    .await
+                    .unwrap();
+                conn_tx.send(i).unwrap();
+
+                let welcome = p::OutgoingMessage::Welcome {
+                    peer_id: format!("producer-{i}"),
+                };
+                ws.send(WsMessage::text(serde_json::to_string(&welcome).unwrap()))
+                    .await
+                    .unwrap();
+
+                if i == 0 {
+                    let start = p::OutgoingMessage::StartSession {
+                        peer_id: "consumer".to_string(),
+                        session_id: "session-0".to_string(),
+                        offer: None,
+                    };
+                    ws.send(WsMessage::text(serde_json::to_string(&start).unwrap()))
+                        .await
+                        .unwrap();
+                    let _ = ws.close(None).await;
+                } else {
+                    RUNTIME.spawn(async move { while let Some(Ok(_)) = ws.next().await {} });
+                }
+            }
+        });
+
+        let signaller = super::super::Signaller::new(WebRTCSignallerRole::Producer);
+        signaller.set_property("uri", format!("ws://{addr}"));
+        signaller.set_property("reconnect-attempts", 1i32);
+        signaller.set_property("reconnect-interval", 50u32);
+
+        let (session_tx, session_rx) = std_mpsc::channel();
+        let session_tx = Mutex::new(session_tx);
+        signaller.connect_closure(
+            "session-ended",
+            false,
+            glib::closure!(move |_: &super::super::Signaller, session_id: &str| {
+                session_tx
+                    .lock()
+                    .unwrap()
+                    .send(session_id.to_string())
+                    .unwrap();
+                true
+            }),
+        );
+
+        signaller.start();
+
+        let timeout = Duration::from_secs(5);
+        assert_eq!(conn_rx.recv_timeout(timeout).unwrap(), 0);
+        assert_eq!(session_rx.recv_timeout(timeout).unwrap(), "session-0");
+        assert_eq!(conn_rx.recv_timeout(timeout).unwrap(), 1);
+        assert!(session_rx.try_recv().is_err());
+
+        signaller.stop();
+    }
+
+    #[test]
+    fn test_connect_backoff() {
+        init();
+
+        // Bind and drop a listener to get a port nobody listens on
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let signaller = super::super::Signaller::new(WebRTCSignallerRole::Producer);
+        signaller.set_property("uri", format!("ws://{addr}"));
+        signaller.set_property("reconnect-attempts", 3i32);
+        signaller.set_property("reconnect-interval", 50u32);
+        signaller.set_property("max-reconnect-interval", 80u32);
+
+        let (error_tx, error_rx) = std_mpsc::channel();
+        let error_tx = Mutex::new(error_tx);
+        signaller.connect_closure(
+            "error",
+            false,
+            glib::closure!(move |_: &super::super::Signaller, error: &str| {
+                error_tx.lock().unwrap().send(error.to_string()).unwrap();
+            }),
+        );
+
+        let reconnecting = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        signaller.connect_closure(
+            "connection-state-changed",
+            false,
+            glib::closure!(
+                #[strong]
+                reconnecting,
+                move |_: &super::super::Signaller, state: WebRTCSignallerConnectionState| {
+                    if state == WebRTCSignallerConnectionState::Reconnecting {
+                        reconnecting.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    }
+                }
+            ),
+        );
+
+        let start = Instant::now();
+        signaller.start();
+
+        let error = error_rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        // Waited 50ms, then 80ms twice as the interval is capped
+        assert!(start.elapsed() >= Duration::from_millis(210));
+        assert!(error.starts_with("Error connecting"), "{error}");
+        // Only the first transition to reconnecting is notified
+        assert_eq!(reconnecting.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert_eq!(
+            signaller.property::<WebRTCSignallerConnectionState>("connection-state"),
+            WebRTCSignallerConnectionState::Disconnected
+        );
+
+        signaller.stop();
+    }
+}
@@ -11,7 +11,7 @@ use gst::glib;
 use std::sync::LazyLock;
 // Expose traits and objects from the module itself so it exactly looks like
 // generated bindings
-pub use imp::WebRTCSignallerRole;
+pub use imp::{WebRTCSignallerConnectionState, WebRTCSignallerRole};
 pub mod prelude {
     pub use {super::SignallableExt, super::SignallableImpl};
 }
@@ -428,6 +428,8 @@ impl Clone for DecodingInfo {
 #[derive(Clone, Debug)]
 struct EncodingInfo {
     encoder: Option<gst::ElementFactory>,
+    /// Other encoders for the same caps, in the order they should be tried if `encoder` fails
+    fallback_encoders: Vec<gst::ElementFactory>,
     payloader: gst::ElementFactory,
     output_filter: Option<gst::Caps>,
 }
@@ -465,12 +467,21 @@ impl Codec {
             None
         };
 
-        let encoder = Self::get_encoder_for_caps(caps, encoders);
+        let mut encoders = Self::get_encoders_for_caps(caps, encoders);
+        let encoder = (!encoders.is_empty()).then(|| encoders.remove(0));
+        // Hardware encoders first, software encoders as the last resort
+        encoders.sort_by_key(|factory| {
+            (
+                std::cmp::Reverse(factory.has_type(gst::ElementFactoryType::HARDWARE)),
+                std::cmp::Reverse(factory.rank()),
+            )
+        });
         let payloader = Self::get_payloader_for_codec(name, payloaders);
 
         let encoding_info = if let (Some(encoder), Some(payloader)) = (encoder, payloader) {
             Some(EncodingInfo {
                 encoder: Some(encoder),
+                fallback_encoders: encoders,
                 payloader,
                 output_filter: None,
             })
@@ -506,6 +517,7 @@ impl Codec {
         let payloader = Self::get_payloader_for_codec(name, payloaders);
         let encoding_info = payloader.map(|payloader| EncodingInfo {
             encoder: None,
+            fallback_encoders: vec![],
             payloader,
             output_filter: None,
         });
@@ -568,13 +580,13 @@ impl Codec {
         }
     }
 
-    fn get_encoder_for_caps(
+    fn get_encoders_for_caps(
         caps: &gst::Caps,
         encoders: &glib::List<gst::ElementFactory>,
-    ) -> Option<gst::ElementFactory> {
+    ) -> Vec<gst::ElementFactory> {
         encoders
             .iter()
-            .find(|factory| {
+            .filter(|factory| {
                 factory.static_pad_templates().iter().any(|template| {
                     let template_caps = template.caps();
                     template.direction() == gst::PadDirection::Src
@@ -583,6 +595,7 @@ impl Codec {
                 })
             })
             .cloned()
+            .collect()
     }
 
     fn get_payloader_for_codec(
@@ -730,6 +743,13 @@ impl Codec {
             .and_then(|info| info.encoder.clone())
     }
 
+    /// Encoders that can replace the selected encoder without changing the codec
+    pub fn fallback_encoders(&self) -> &[gst::ElementFactory] {
+        self.encoding_info
+            .as_ref()
+            .map_or(&[], |info| info.fallback_encoders.as_slice())
+    }
+
     pub fn encoder_name(&self) -> Option<String> {
         self.encoding_info.as_ref().and_then(|info| {
             info.encoder
@@ -108,6 +108,12 @@ impl CongestionController {
         }
     }
 
+    /// Update the per-encoder bitrate range the controller operates in
+    pub fn set_bitrate_limits(&mut self, min_bitrate: u32, max_bitrate: u32) {
+        self.min_bitrate = min_bitrate;
+        self.max_bitrate = max_bitrate;
+    }
+
     fn update_delay(
         &mut self,
         element: &super::BaseWebRTCSink,
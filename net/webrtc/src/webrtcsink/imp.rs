@@ -30,7 +30,8 @@ use std::sync::{mpsc, Arc, Condvar, Mutex};
 
 use super::homegrown_cc::CongestionController;
 use super::{
-    WebRTCSinkCongestionControl, WebRTCSinkError, WebRTCSinkMitigationMode, WebRTCSinkPad,
+    WebRTCSinkBitrateAllocation, WebRTCSinkCongestionControl, WebRTCSinkError,
+    WebRTCSinkMitigationMode, WebRTCSinkNtpTimeSource, WebRTCSinkPad,
 };
 use crate::signaller::{prelude::*, Signallable, Signaller, WebRTCSignallerRole};
 use crate::{utils, RUNTIME};
@@ -70,10 +71,14 @@ const DEFAULT_CONGESTION_CONTROL: WebRTCSinkCongestionControl = if cfg!(feature
 const DEFAULT_DO_FEC: bool = true;
 const DEFAULT_DO_RETRANSMISSION: bool = true;
 const DEFAULT_DO_CLOCK_SIGNALLING: bool = false;
+const DEFAULT_NTP_TIME_SOURCE: WebRTCSinkNtpTimeSource = WebRTCSinkNtpTimeSource::System;
 const DEFAULT_ENABLE_DATA_CHANNEL_NAVIGATION: bool = false;
 const DEFAULT_ENABLE_CONTROL_DATA_CHANNEL: bool = false;
 const DEFAULT_ICE_TRANSPORT_POLICY: WebRTCICETransportPolicy = WebRTCICETransportPolicy::All;
+const DEFAULT_AUDIO_PRIORITY: gst_webrtc::WebRTCPriorityType = gst_webrtc::WebRTCPriorityType::Low;
+const DEFAULT_VIDEO_PRIORITY: gst_webrtc::WebRTCPriorityType = gst_webrtc::WebRTCPriorityType::Low;
 const DEFAULT_START_BITRATE: u32 = 2048000;
+const DEFAULT_BITRATE_ALLOCATION: WebRTCSinkBitrateAllocation = WebRTCSinkBitrateAllocation::Equal;
 #[cfg(feature = "web_server")]
 const DEFAULT_RUN_WEB_SERVER: bool = false;
 #[cfg(feature = "web_server")]
@@ -87,6 +92,14 @@ const DEFAULT_WEB_SERVER_DIRECTORY: &str = "gstwebrtc-api/dist";
 #[cfg(feature = "web_server")]
 const DEFAULT_WEB_SERVER_HOST_ADDR: &str = "http://127.0.0.1:8080";
 const DEFAULT_FORWARD_METAS: &str = "";
+const DEFAULT_MAX_SESSION_DURATION: u32 = 0;
+const DEFAULT_SESSION_VALIDATION_INTERVAL: u32 = 0;
+const DEFAULT_SESSION_EXPIRED_MESSAGE: Option<&str> = None;
+/* How often sessions are checked for expiry */
+const SESSION_EXPIRY_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/* How long to wait for the session expired message to be sent before
+ * tearing down the session */
+const SESSION_EXPIRED_MESSAGE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
 /* Start adding some FEC when the bitrate > 2Mbps as we found experimentally
  * that it is not worth it below that threshold */
 #[cfg(feature = "v1_22")]
@@ -108,13 +121,17 @@ struct Settings {
     turn_servers: gst::Array,
     stun_server: Option<String>,
     cc_info: CCInfo,
+    bitrate_allocation: WebRTCSinkBitrateAllocation,
     do_fec: bool,
     do_retransmission: bool,
     do_clock_signalling: bool,
+    ntp_time_source: WebRTCSinkNtpTimeSource,
     enable_data_channel_navigation: bool,
     enable_control_data_channel: bool,
     meta: Option<gst::Structure>,
     ice_transport_policy: WebRTCICETransportPolicy,
+    audio_priority: gst_webrtc::WebRTCPriorityType,
+    video_priority: gst_webrtc::WebRTCPriorityType,
     signaller: Signallable,
     #[cfg(feature = "web_server")]
     run_web_server: bool,
@@ -129,6 +146,9 @@ struct Settings {
     #[cfg(feature = "web_server")]
     web_server_host_addr: url::Url,
     forward_metas: HashSet<String>,
+    max_session_duration: u32,
+    session_validation_interval: u32,
+    session_expired_message: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -242,6 +262,33 @@ impl futures::stream::FusedStream for CustomBusStream {
     }
 }
 
+/// Splits `total` between streams described by their (weight, minimum bitrate):
+/// minimums are guaranteed first, and the remainder is shared according to the
+/// weights. When the minimums can't be honoured, `total` is split proportionally
+/// to them instead.
+#[cfg(feature = "v1_22")]
+fn allocate_weighted_bitrates(total: f64, constraints: &[(u32, u32)]) -> Vec<i32> {
+    let total_min = constraints.iter().map(|(_, min)| *min as f64).sum::<f64>();
+
+    if total_min >= total {
+        return constraints
+            .iter()
+            .map(|(_, min)| (total * *min as f64 / total_min) as i32)
+            .collect();
+    }
+
+    let total_weight = constraints
+        .iter()
+        .map(|(weight, _)| *weight as f64)
+        .sum::<f64>();
+    let remainder = total - total_min;
+
+    constraints
+        .iter()
+        .map(|(weight, min)| (*min as f64 + remainder * *weight as f64 / total_weight) as i32)
+        .collect()
+}
+
 /// Wrapper around our sink pads
 #[derive(Debug, Clone)]
 struct InputStream {
@@ -294,6 +341,11 @@ pub struct VideoEncoder {
     pub transceiver: gst_webrtc::WebRTCRTPTransceiver,
     /// name of the sink pad feeding this encoder
     stream_name: String,
+    /// encoders of the same codec to try if this encoder fails at runtime
+    fallback_encoders: Vec<gst::ElementFactory>,
+    /// set while replacing a failed encoder, the error of the appsrc
+    /// feeding it is expected and ignored
+    replacing: bool,
 }
 
 struct SessionInner {
@@ -324,6 +376,17 @@ struct SessionInner {
     codecs: Option<BTreeMap<i32, Codec>>,
 
     stats_collection_handle: Option<tokio::task::JoinHandle<()>>,
+
+    started_at: std::time::Instant,
+    // The session is ended once it has been running for that long
+    max_duration: Option<std::time::Duration>,
+    // Used to notify the consumer before ending an expired session
+    expiry_channel: Option<WebRTCDataChannel>,
+    expiry_handle: Option<tokio::task::JoinHandle<()>>,
+
+    // Set when an input stream was removed and the consumer was not
+    // renegotiated with yet
+    needs_renegotiation: bool,
 }
 
 #[derive(Clone)]
@@ -528,13 +591,17 @@ impl Default for Settings {
                 max_bitrate: DEFAULT_MAX_BITRATE,
                 start_bitrate: DEFAULT_START_BITRATE,
             },
+            bitrate_allocation: DEFAULT_BITRATE_ALLOCATION,
             do_fec: DEFAULT_DO_FEC,
             do_retransmission: DEFAULT_DO_RETRANSMISSION,
             do_clock_signalling: DEFAULT_DO_CLOCK_SIGNALLING,
+            ntp_time_source: DEFAULT_NTP_TIME_SOURCE,
             enable_data_channel_navigation: DEFAULT_ENABLE_DATA_CHANNEL_NAVIGATION,
             enable_control_data_channel: DEFAULT_ENABLE_CONTROL_DATA_CHANNEL,
             meta: None,
             ice_transport_policy: DEFAULT_ICE_TRANSPORT_POLICY,
+            audio_priority: DEFAULT_AUDIO_PRIORITY,
+            video_priority: DEFAULT_VIDEO_PRIORITY,
             signaller: signaller.upcast(),
             #[cfg(feature = "web_server")]
             run_web_server: DEFAULT_RUN_WEB_SERVER,
@@ -549,6 +616,9 @@ impl Default for Settings {
             #[cfg(feature = "web_server")]
             web_server_host_addr: url::Url::parse(DEFAULT_WEB_SERVER_HOST_ADDR).unwrap(),
             forward_metas: HashSet::new(),
+            max_session_duration: DEFAULT_MAX_SESSION_DURATION,
+            session_validation_interval: DEFAULT_SESSION_VALIDATION_INTERVAL,
+            session_expired_message: DEFAULT_SESSION_EXPIRED_MESSAGE.map(String::from),
         }
     }
 }
@@ -1006,6 +1076,7 @@ impl VideoEncoder {
         codec_name: &str,
         transceiver: gst_webrtc::WebRTCRTPTransceiver,
         stream_name: String,
+        fallback_encoders: Vec<gst::ElementFactory>,
     ) -> Option<Self> {
         let halved_framerate = video_info.fps().mul(gst::Fraction::new(1, 2));
         Some(Self {
@@ -1019,6 +1090,8 @@ impl VideoEncoder {
             mitigation_mode: WebRTCSinkMitigationMode::NONE,
             transceiver,
             stream_name,
+            fallback_encoders,
+            replacing: false,
         })
     }
 
@@ -1159,6 +1232,79 @@ impl VideoEncoder {
         Ok(())
     }
 
+    /// Create the next working fallback encoder
+    fn create_fallback(&mut self, element: &super::BaseWebRTCSink) -> Result<gst::Element, Error> {
+        loop {
+            if self.fallback_encoders.is_empty() {
+                return Err(anyhow!(
+                    "No fallback encoder left for {}",
+                    self.factory_name
+                ));
+            }
+
+            let factory = self.fallback_encoders.remove(0);
+            match factory.create().build() {
+                Ok(encoder) => return Ok(encoder),
+                Err(err) => {
+                    gst::warning!(
+                        CAT,
+                        obj = element,
+                        "session {}: failed to create fallback encoder {}: {err}",
+                        self.session_id,
+                        factory.name(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Replace the encoder element with `new_encoder`, keeping the rest of
+    /// the pipeline in place. Returns the name of the factory of the new
+    /// encoder.
+    fn replace(
+        &mut self,
+        element: &super::BaseWebRTCSink,
+        pipeline: &gst::Pipeline,
+        new_encoder: gst::Element,
+    ) -> Result<String, Error> {
+        let old_sinkpad = self.element.static_pad("sink").unwrap();
+        let old_srcpad = self.element.static_pad("src").unwrap();
+        let upstream = old_sinkpad
+            .peer()
+            .ok_or_else(|| anyhow!("Encoder {} is not linked", self.factory_name))?;
+        let downstream = old_srcpad
+            .peer()
+            .ok_or_else(|| anyhow!("Encoder {} is not linked", self.factory_name))?;
+
+        let bitrate = self.bitrate().ok();
+
+        let _ = self.element.set_state(gst::State::Null);
+        upstream.unlink(&old_sinkpad)?;
+        old_srcpad.unlink(&downstream)?;
+        pipeline.remove(&self.element)?;
+
+        pipeline.add(&new_encoder)?;
+        upstream.link(&new_encoder.static_pad("sink").unwrap())?;
+        new_encoder.static_pad("src").unwrap().link(&downstream)?;
+
+        self.factory_name = new_encoder.factory().unwrap().name().into();
+        self.element = new_encoder;
+        if let Some(bitrate) = bitrate {
+            let _ = self.set_bitrate(element, bitrate);
+        }
+
+        self.element.sync_state_with_parent()?;
+
+        // The appsrc feeding the failed encoder stopped streaming, restart it
+        self.replacing = true;
+        if let Some(appsrc) = pipeline.by_name(&self.stream_name) {
+            let _ = appsrc.set_state(gst::State::Ready);
+            appsrc.sync_state_with_parent()?;
+        }
+
+        Ok(self.factory_name.clone())
+    }
+
     fn gather_stats(&self) -> gst::Structure {
         gst::Structure::builder("application/x-webrtcsink-video-encoder-stats")
             .field("bitrate", self.bitrate().unwrap_or(0i32))
@@ -1186,6 +1332,11 @@ impl State {
 
         let stats_collection_handle = session.stats_collection_handle.take();
 
+        // Might be called from the expiry task itself, don't wait for it
+        if let Some(expiry_handle) = session.expiry_handle.take() {
+            expiry_handle.abort();
+        }
+
         let finalizing_sessions = self.finalizing_sessions.clone();
         let session_id = session.id.clone();
         let (sessions, _cvar) = &*finalizing_sessions;
@@ -1280,6 +1431,11 @@ impl SessionInner {
             stats_sigid: None,
             codecs: None,
             stats_collection_handle: None,
+            started_at: std::time::Instant::now(),
+            max_duration: None,
+            expiry_channel: None,
+            expiry_handle: None,
+            needs_renegotiation: false,
         }
     }
 
@@ -1445,6 +1601,7 @@ impl SessionInner {
                 codec.caps.structure(0).unwrap().name(),
                 transceiver,
                 stream_name.clone(),
+                codec.fallback_encoders().to_vec(),
             ) {
                 match self.cc_info.heuristic {
                     WebRTCSinkCongestionControl::Disabled => {
@@ -1474,8 +1631,11 @@ impl SessionInner {
                 self.encoders.push(enc);
 
                 if let Some(rtpgccbwe) = self.rtpgccbwe.as_ref() {
-                    let max_bitrate = self.cc_info.max_bitrate * (self.encoders.len() as u32);
-                    rtpgccbwe.set_property("max-bitrate", max_bitrate);
+                    let n_encoders = self.encoders.len() as u32;
+                    rtpgccbwe.set_properties(&[
+                        ("min-bitrate", &(self.cc_info.min_bitrate * n_encoders)),
+                        ("max-bitrate", &(self.cc_info.max_bitrate * n_encoders)),
+                    ]);
                 }
             }
         }
@@ -1502,6 +1662,53 @@ impl SessionInner {
             Err(err) => Err(anyhow!("Could not link producer: {:?}", err)),
         }
     }
+
+    /// Called when a sink pad was released, disconnects the InputStream
+    /// and marks the corresponding media as inactive
+    fn remove_input_stream(&mut self, element: &super::BaseWebRTCSink, stream_name: &str) {
+        let Some(webrtc_pad) = self
+            .webrtc_pads
+            .values_mut()
+            .find(|pad| pad.stream_name.as_deref() == Some(stream_name))
+        else {
+            return;
+        };
+
+        gst::info!(
+            CAT,
+            obj = element,
+            "Removing input stream {} for consumer {} and media {}",
+            stream_name,
+            self.peer_id,
+            webrtc_pad.media_idx
+        );
+
+        webrtc_pad.stream_name = None;
+        webrtc_pad
+            .pad
+            .property::<gst_webrtc::WebRTCRTPTransceiver>("transceiver")
+            .set_property(
+                "direction",
+                gst_webrtc::WebRTCRTPTransceiverDirection::Inactive,
+            );
+
+        // Dropping the link stops feeding the appsrc of this stream
+        self.links.remove(&webrtc_pad.ssrc);
+        self.encoders
+            .retain(|encoder| encoder.stream_name != stream_name);
+
+        if let Some(rtpgccbwe) = self.rtpgccbwe.as_ref() {
+            let n_encoders = self.encoders.len() as u32;
+            if n_encoders > 0 {
+                rtpgccbwe.set_properties(&[
+                    ("min-bitrate", &(self.cc_info.min_bitrate * n_encoders)),
+                    ("max-bitrate", &(self.cc_info.max_bitrate * n_encoders)),
+                ]);
+            }
+        }
+
+        self.needs_renegotiation = true;
+    }
 }
 
 impl Drop for PipelineWrapper {
@@ -1524,6 +1731,7 @@ impl InputStream {
         clocksync
             .link(&appsink)
             .with_context(|| format!("Linking input stream {}", self.sink_pad.name()))?;
+        self.clocksync = Some(clocksync.clone());
 
         element
             .sync_children_states()
@@ -2069,6 +2277,15 @@ impl BaseWebRTCSink {
 
             transceiver.set_property("codec-preferences", &payloader_caps);
 
+            let priority = if stream.is_video {
+                settings.video_priority
+            } else {
+                settings.audio_priority
+            };
+            transceiver
+                .property::<gst_webrtc::WebRTCRTPSender>("sender")
+                .set_property("priority", priority);
+
             if stream.sink_pad.name().starts_with("video_") {
                 if settings.do_fec {
                     transceiver.set_property("fec-type", gst_webrtc::WebRTCFECType::UlpRed);
@@ -2292,6 +2509,8 @@ impl BaseWebRTCSink {
 
                         if session_description.type_() == gst_webrtc::WebRTCSDPType::Answer {
                             instance.imp().handle_sdp_answer(session_id, session_description);
+                        } else if session_description.type_() == gst_webrtc::WebRTCSDPType::Offer {
+                            instance.imp().handle_sdp_offer(session_id, session_description);
                         } else {
                             gst::error!(CAT, obj = instance, "Unsupported SDP Type");
                         }
@@ -2702,6 +2921,106 @@ impl BaseWebRTCSink {
         }
     }
 
+    /// Adds the input streams that were added since the session was started and
+    /// renegotiates with the consumer if the streams of the session changed.
+    ///
+    /// Only sessions for which we made the offer and that are not negotiating
+    /// at the moment are updated, otherwise this is tried again once the current
+    /// negotiation is done.
+    async fn update_session_streams(&self, session_id: &str) {
+        let (webrtcbin, mut webrtc_pads, streams) = {
+            let state = self.state.lock().unwrap();
+            let Some(session) = state.sessions.get(session_id) else {
+                return;
+            };
+            let session = session.0.lock().unwrap();
+
+            if session.codecs.is_some() {
+                if session.needs_renegotiation {
+                    gst::warning!(
+                        CAT,
+                        imp = self,
+                        "Can't renegotiate session {session_id} started from a remote offer"
+                    );
+                }
+                return;
+            }
+
+            if session.sdp.is_none()
+                || session
+                    .webrtcbin
+                    .property::<gst_webrtc::WebRTCSignalingState>("signaling-state")
+                    != gst_webrtc::WebRTCSignalingState::Stable
+            {
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Session {session_id} is negotiating, not updating streams yet"
+                );
+                return;
+            }
+
+            let mut streams = state
+                .streams
+                .values()
+                .filter(|stream| {
+                    stream.out_caps.is_some()
+                        && !session.webrtc_pads.values().any(|pad| {
+                            pad.stream_name.as_deref() == Some(stream.sink_pad.name().as_str())
+                        })
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            streams.sort_by_key(|s| s.serial);
+
+            if streams.is_empty() && !session.needs_renegotiation {
+                return;
+            }
+
+            (
+                session.webrtcbin.clone(),
+                session.webrtc_pads.clone(),
+                streams,
+            )
+        };
+
+        let settings = self.settings.lock().unwrap().clone();
+        let mut codecs = BTreeMap::new();
+        for mut stream in streams {
+            gst::info!(
+                CAT,
+                imp = self,
+                "Adding stream {} to session {session_id}",
+                stream.sink_pad.name()
+            );
+            self.request_webrtcbin_pad(
+                &webrtcbin,
+                &mut stream,
+                None,
+                &settings,
+                &mut webrtc_pads,
+                &mut codecs,
+            )
+            .await;
+        }
+        drop(settings);
+
+        {
+            let state = self.state.lock().unwrap();
+            let Some(session) = state.sessions.get(session_id) else {
+                return;
+            };
+            let mut session = session.0.lock().unwrap();
+            for (ssrc, webrtc_pad) in webrtc_pads {
+                session.webrtc_pads.entry(ssrc).or_insert(webrtc_pad);
+            }
+            session.needs_renegotiation = false;
+        }
+
+        gst::info!(CAT, imp = self, "Renegotiating session {session_id}");
+        self.negotiate(session_id, None);
+    }
+
     fn on_ice_candidate(&self, session_id: String, sdp_m_line_index: u32, candidate: String) {
         let settings = self.settings.lock().unwrap();
         let signaller = settings.signaller.clone();
@@ -2748,6 +3067,21 @@ impl BaseWebRTCSink {
         }
     }
 
+    /// Select the timebase of the NTP timestamps in the RTCP sender reports of a session
+    fn configure_ntp_time_source(
+        &self,
+        rtpbin: &glib::Object,
+        ntp_time_source: WebRTCSinkNtpTimeSource,
+    ) {
+        let ntp_time_source = match ntp_time_source {
+            WebRTCSinkNtpTimeSource::System => "ntp",
+            WebRTCSinkNtpTimeSource::PipelineClock => "clock-time",
+        };
+
+        gst::debug!(CAT, imp = self, "Using NTP time source {ntp_time_source}");
+        rtpbin.set_property_from_str("ntp-time-source", ntp_time_source);
+    }
+
     /// Called by the signaller to add a new session
     fn start_session(
         &self,
@@ -3089,7 +3423,7 @@ impl BaseWebRTCSink {
             ),
         );
 
-        let session = SessionInner::new(
+        let mut session = SessionInner::new(
             session_id.clone(),
             pipeline.clone(),
             webrtcbin.clone(),
@@ -3105,6 +3439,9 @@ impl BaseWebRTCSink {
             rtpgccbwe,
             settings.cc_info,
         );
+        session.max_duration = (settings.max_session_duration > 0)
+            .then(|| std::time::Duration::from_secs(settings.max_session_duration as u64));
+        session.expiry_handle = Some(self.spawn_session_expiry_task(&session_id));
 
         let rtpbin = webrtcbin
             .dynamic_cast_ref::<gst::ChildProxy>()
@@ -3112,6 +3449,8 @@ impl BaseWebRTCSink {
             .child_by_name("rtpbin")
             .unwrap();
 
+        self.configure_ntp_time_source(&rtpbin, settings.ntp_time_source);
+
         if session.congestion_controller.is_some() {
             let session_id_str = session_id.to_string();
             rtpbin.connect_closure(
@@ -3178,6 +3517,10 @@ impl BaseWebRTCSink {
                 let this = element.imp();
                 match msg.view() {
                     gst::MessageView::Error(err) => {
+                        if this.handle_encoder_error(&session_id_clone, &msg) {
+                            continue;
+                        }
+
                         gst::error!(
                             CAT,
                             "session {} error: {}, details: {:?}",
@@ -3241,7 +3584,14 @@ impl BaseWebRTCSink {
             Session(Arc::new(Mutex::new(session))),
         );
 
-        let mut streams: Vec<InputStream> = state.streams.values().cloned().collect();
+        // Streams that were added while running and are still being discovered
+        // are added to the session once ready
+        let mut streams: Vec<InputStream> = state
+            .streams
+            .values()
+            .filter(|s| s.out_caps.is_some())
+            .cloned()
+            .collect();
 
         streams.sort_by_key(|s| s.serial);
 
@@ -3312,6 +3662,7 @@ impl BaseWebRTCSink {
 
                 let enable_data_channel_navigation = settings_clone.enable_data_channel_navigation;
                 let enable_control_data_channel = settings_clone.enable_control_data_channel;
+                let enable_expiry_channel = settings_clone.session_expired_message.is_some();
 
                 drop(settings_clone);
 
@@ -3355,6 +3706,17 @@ impl BaseWebRTCSink {
                     ));
                 }
 
+                if enable_expiry_channel {
+                    let channel = webrtcbin.emit_by_name::<WebRTCDataChannel>(
+                        "create-data-channel",
+                        &[&"session", &None::<gst::Structure>],
+                    );
+                    let state = this.state.lock().unwrap();
+                    if let Some(session) = state.sessions.get(&session_id) {
+                        session.0.lock().unwrap().expiry_channel = Some(channel);
+                    }
+                }
+
                 // This is intentionally emitted with the pipeline in the Ready state,
                 // so that application code can create data channels at the correct
                 // moment.
@@ -3381,84 +3743,381 @@ impl BaseWebRTCSink {
         Ok(())
     }
 
-    /// Called by the signaller to remove a consumer
-    fn remove_session(&self, session_id: &str, signal: bool) -> Result<(), WebRTCSinkError> {
-        let settings = self.settings.lock().unwrap();
-        let signaller = settings.signaller.clone();
-        drop(settings);
-        let mut state = self.state.lock().unwrap();
+    /// Try to recover from an error of a video encoder by switching to a fallback
+    /// encoder of the same codec. Returns true if the error was handled.
+    fn handle_encoder_error(&self, session_id: &str, msg: &gst::MessageRef) -> bool {
+        let Some(src) = msg.src() else {
+            return false;
+        };
 
-        if !state.sessions.contains_key(session_id) {
-            return Err(WebRTCSinkError::NoSessionWithId(session_id.to_string()));
+        let session = {
+            let state = self.state.lock().unwrap();
+            let Some(session) = state.sessions.get(session_id) else {
+                return false;
+            };
+            session.0.clone()
+        };
+        let mut session = session.lock().unwrap();
+        let SessionInner {
+            ref pipeline,
+            ref peer_id,
+            ref mut encoders,
+            ..
+        } = *session;
+
+        // Error of the appsrc that was feeding a failed encoder
+        if let Some(encoder) = encoders.iter_mut().find(|encoder| {
+            encoder.replacing
+                && src.is::<gst_app::AppSrc>()
+                && src.name() == encoder.stream_name.as_str()
+        }) {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "session {session_id}: ignoring error of input stream {} after encoder failure",
+                encoder.stream_name
+            );
+            encoder.replacing = false;
+            return true;
         }
 
-        if let Some(session) = state.end_session(&self.obj(), session_id) {
-            drop(state);
-            let session = session.0.lock().unwrap();
-            signaller
-                .emit_by_name::<()>("consumer-removed", &[&session.peer_id, &session.webrtcbin]);
-            if signal {
-                signaller.end_session(session_id);
+        let Some(encoder) = encoders.iter_mut().find(|encoder| {
+            src == encoder.element.upcast_ref::<gst::Object>()
+                || src.has_as_ancestor(&encoder.element)
+        }) else {
+            return false;
+        };
+
+        let failed_encoder = encoder.factory_name.clone();
+        let stream_name = encoder.stream_name.clone();
+        gst::warning!(
+            CAT,
+            imp = self,
+            "session {session_id}: encoder {failed_encoder} for stream {stream_name} failed, trying fallback",
+        );
+
+        let new_encoder = match encoder.create_fallback(&self.obj()) {
+            Ok(new_encoder) => new_encoder,
+            Err(err) => {
+                gst::warning!(
+                    CAT,
+                    imp = self,
+                    "session {session_id}: failed to replace encoder {failed_encoder}: {err:?}"
+                );
+                return false;
             }
-            self.obj()
-                .emit_by_name::<()>("consumer-removed", &[&session.peer_id, &session.webrtcbin]);
-        }
+        };
 
-        Ok(())
-    }
+        // The application can configure the new encoder, without the session locked
+        let peer_id = peer_id.clone();
+        let pipeline = pipeline.clone();
+        drop(session);
+        self.obj()
+            .emit_by_name::<bool>("encoder-setup", &[&peer_id, &stream_name, &new_encoder]);
 
-    fn process_loss_stats(&self, session_id: &str, stats: &gst::Structure) {
-        let mut state = self.state.lock().unwrap();
-        if let Some(session) = state.sessions.get_mut(session_id) {
-            /* We need this two-step approach for split-borrowing */
-            let mut session_guard = session.0.lock().unwrap();
-            let session = session_guard.deref_mut();
-            if let Some(congestion_controller) = session.congestion_controller.as_mut() {
-                congestion_controller.loss_control(&self.obj(), stats, &mut session.encoders);
+        let session = {
+            let state = self.state.lock().unwrap();
+            let Some(session) = state.sessions.get(session_id) else {
+                return false;
+            };
+            session.0.clone()
+        };
+        let mut session = session.lock().unwrap();
+        let Some(encoder) = session
+            .encoders
+            .iter_mut()
+            .find(|encoder| encoder.stream_name == stream_name)
+        else {
+            return false;
+        };
+
+        match encoder.replace(&self.obj(), &pipeline, new_encoder) {
+            Ok(new_encoder) => {
+                gst::info!(
+                    CAT,
+                    imp = self,
+                    "session {session_id}: replaced encoder {failed_encoder} with {new_encoder}"
+                );
+
+                let s = gst::Structure::builder("webrtcsink-encoder-fallback")
+                    .field("session-id", session_id)
+                    .field("stream-name", &encoder.stream_name)
+                    .field("failed-encoder", failed_encoder)
+                    .field("encoder", new_encoder)
+                    .build();
+                drop(session);
+
+                let _ = self
+                    .obj()
+                    .post_message(gst::message::Element::builder(s).src(&*self.obj()).build());
+
+                true
+            }
+            Err(err) => {
+                gst::warning!(
+                    CAT,
+                    imp = self,
+                    "session {session_id}: failed to replace encoder {failed_encoder}: {err:?}"
+                );
+                false
             }
-            stats.clone_into(&mut session.stats);
         }
     }
 
-    fn process_stats(&self, webrtcbin: gst::Element, session_id: &str) {
+    fn spawn_session_expiry_task(&self, session_id: &str) -> tokio::task::JoinHandle<()> {
+        let this_weak = self.downgrade();
         let session_id = session_id.to_string();
-        let promise = gst::Promise::with_change_func(glib::clone!(
-            #[weak(rename_to = this)]
-            self,
-            #[strong]
-            session_id,
-            move |reply| {
-                if let Ok(Some(stats)) = reply {
-                    let mut state = this.state.lock().unwrap();
-                    if let Some(session) = state.sessions.get_mut(&session_id) {
-                        /* We need this two-step approach for split-borrowing */
-                        let mut session_guard = session.0.lock().unwrap();
-                        let session = session_guard.deref_mut();
-                        if let Some(congestion_controller) = session.congestion_controller.as_mut()
-                        {
-                            congestion_controller.delay_control(
-                                &this.obj(),
-                                stats,
-                                &mut session.encoders,
-                            );
-                        }
-                        session.stats = stats.to_owned();
+
+        RUNTIME.spawn(async move {
+            let mut interval = tokio::time::interval(SESSION_EXPIRY_CHECK_INTERVAL);
+            let mut last_validation = std::time::Instant::now();
+
+            loop {
+                interval.tick().await;
+                let Some(this) = this_weak.upgrade() else {
+                    break;
+                };
+
+                if !this.session_expired(&session_id, &mut last_validation) {
+                    continue;
+                }
+
+                if let Some((channel, message)) = this.session_expired_notification(&session_id) {
+                    gst::debug!(
+                        CAT,
+                        imp = this,
+                        "Notifying session {session_id} about expiry"
+                    );
+                    channel.send_string(Some(&message));
+
+                    let deadline = std::time::Instant::now() + SESSION_EXPIRED_MESSAGE_TIMEOUT;
+                    while channel.property::<u64>("buffered-amount") > 0
+                        && std::time::Instant::now() < deadline
+                    {
+                        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
                     }
+                    channel.close();
                 }
-            }
-        ));
 
-        webrtcbin.emit_by_name::<()>("get-stats", &[&None::<gst::Pad>, &promise]);
+                let _ = this.remove_session(&session_id, true);
+                break;
+            }
+        })
     }
 
-    #[cfg(feature = "v1_22")]
-    fn set_rtptrxsend(&self, session_id: &str, rtprtxsend: gst::Element) {
-        let mut state = self.state.lock().unwrap();
+    /// Whether the session exceeded its maximum duration or was rejected by
+    /// the application when validating it
+    fn session_expired(&self, session_id: &str, last_validation: &mut std::time::Instant) -> bool {
+        let validation_interval = self.settings.lock().unwrap().session_validation_interval;
 
-        if let Some(session) = state.sessions.get_mut(session_id) {
-            session.0.lock().unwrap().rtprtxsend = Some(rtprtxsend);
+        let state = self.state.lock().unwrap();
+        let Some(session) = state.sessions.get(session_id) else {
+            return false;
+        };
+        let session = session.0.lock().unwrap();
+        let elapsed = session.started_at.elapsed();
+        let max_duration = session.max_duration;
+        let peer_id = session.peer_id.clone();
+        drop(session);
+        drop(state);
+
+        if max_duration.is_some_and(|max_duration| elapsed >= max_duration) {
+            gst::info!(
+                CAT,
+                imp = self,
+                "Session {session_id} reached its maximum duration of {max_duration:?}"
+            );
+            return true;
         }
-    }
+
+        if validation_interval > 0
+            && last_validation.elapsed()
+                >= std::time::Duration::from_secs(validation_interval as u64)
+        {
+            *last_validation = std::time::Instant::now();
+
+            if !self
+                .obj()
+                .emit_by_name::<bool>("validate-session", &[&session_id, &peer_id])
+            {
+                gst::info!(CAT, imp = self, "Session {session_id} is not valid anymore");
+                return true;
+            }
+        }
+
+        false
+    }
+
+    fn session_expired_notification(
+        &self,
+        session_id: &str,
+    ) -> Option<(WebRTCDataChannel, String)> {
+        let message = self
+            .settings
+            .lock()
+            .unwrap()
+            .session_expired_message
+            .clone()?;
+
+        let state = self.state.lock().unwrap();
+        let session = state.sessions.get(session_id)?;
+        let channel = session.0.lock().unwrap().expiry_channel.clone()?;
+
+        Some((channel, message))
+    }
+
+    fn set_session_max_duration(&self, session_id: &str, max_duration: u32) -> bool {
+        let state = self.state.lock().unwrap();
+
+        let Some(session) = state.sessions.get(session_id) else {
+            gst::warning!(CAT, imp = self, "No session with id {session_id}");
+            return false;
+        };
+
+        gst::info!(
+            CAT,
+            imp = self,
+            "Limiting session {session_id} to {max_duration} seconds"
+        );
+
+        session.0.lock().unwrap().max_duration =
+            (max_duration > 0).then(|| std::time::Duration::from_secs(max_duration as u64));
+
+        true
+    }
+
+    fn run_preflight_check(&self, probe_duration: u32) {
+        let (stun_server, turn_servers) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.stun_server.clone(), settings.turn_servers.clone())
+        };
+
+        crate::preflight::run_for_element(
+            self.obj().upcast_ref(),
+            stun_server,
+            &turn_servers,
+            probe_duration,
+        );
+    }
+
+    fn set_session_priority(
+        &self,
+        session_id: &str,
+        media: &str,
+        priority: gst_webrtc::WebRTCPriorityType,
+    ) -> bool {
+        let state = self.state.lock().unwrap();
+
+        let Some(session) = state.sessions.get(session_id) else {
+            gst::warning!(CAT, imp = self, "No session with id {session_id}");
+            return false;
+        };
+
+        gst::info!(
+            CAT,
+            imp = self,
+            "Setting {media} priority of session {session_id} to {priority:?}"
+        );
+
+        let session = session.0.lock().unwrap();
+        let prefix = format!("{media}_");
+        for webrtc_pad in session.webrtc_pads.values() {
+            if !webrtc_pad
+                .stream_name
+                .as_ref()
+                .is_some_and(|name| name.starts_with(&prefix))
+            {
+                continue;
+            }
+
+            webrtc_pad
+                .pad
+                .property::<gst_webrtc::WebRTCRTPTransceiver>("transceiver")
+                .property::<gst_webrtc::WebRTCRTPSender>("sender")
+                .set_property("priority", priority);
+        }
+
+        true
+    }
+
+    /// Called by the signaller to remove a consumer
+    fn remove_session(&self, session_id: &str, signal: bool) -> Result<(), WebRTCSinkError> {
+        let settings = self.settings.lock().unwrap();
+        let signaller = settings.signaller.clone();
+        drop(settings);
+        let mut state = self.state.lock().unwrap();
+
+        if !state.sessions.contains_key(session_id) {
+            return Err(WebRTCSinkError::NoSessionWithId(session_id.to_string()));
+        }
+
+        if let Some(session) = state.end_session(&self.obj(), session_id) {
+            drop(state);
+            let session = session.0.lock().unwrap();
+            signaller
+                .emit_by_name::<()>("consumer-removed", &[&session.peer_id, &session.webrtcbin]);
+            if signal {
+                signaller.end_session(session_id);
+            }
+            self.obj()
+                .emit_by_name::<()>("consumer-removed", &[&session.peer_id, &session.webrtcbin]);
+        }
+
+        Ok(())
+    }
+
+    fn process_loss_stats(&self, session_id: &str, stats: &gst::Structure) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(session) = state.sessions.get_mut(session_id) {
+            /* We need this two-step approach for split-borrowing */
+            let mut session_guard = session.0.lock().unwrap();
+            let session = session_guard.deref_mut();
+            if let Some(congestion_controller) = session.congestion_controller.as_mut() {
+                congestion_controller.loss_control(&self.obj(), stats, &mut session.encoders);
+            }
+            stats.clone_into(&mut session.stats);
+        }
+    }
+
+    fn process_stats(&self, webrtcbin: gst::Element, session_id: &str) {
+        let session_id = session_id.to_string();
+        let promise = gst::Promise::with_change_func(glib::clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[strong]
+            session_id,
+            move |reply| {
+                if let Ok(Some(stats)) = reply {
+                    let mut state = this.state.lock().unwrap();
+                    if let Some(session) = state.sessions.get_mut(&session_id) {
+                        /* We need this two-step approach for split-borrowing */
+                        let mut session_guard = session.0.lock().unwrap();
+                        let session = session_guard.deref_mut();
+                        if let Some(congestion_controller) = session.congestion_controller.as_mut()
+                        {
+                            congestion_controller.delay_control(
+                                &this.obj(),
+                                stats,
+                                &mut session.encoders,
+                            );
+                        }
+                        session.stats = stats.to_owned();
+                    }
+                }
+            }
+        ));
+
+        webrtcbin.emit_by_name::<()>("get-stats", &[&None::<gst::Pad>, &promise]);
+    }
+
+    #[cfg(feature = "v1_22")]
+    fn set_rtptrxsend(&self, session_id: &str, rtprtxsend: gst::Element) {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(session) = state.sessions.get_mut(session_id) {
+            session.0.lock().unwrap().rtprtxsend = Some(rtprtxsend);
+        }
+    }
 
     #[cfg(feature = "v1_22")]
     fn set_bitrate(&self, session_id: &str, bitrate: u32) {
@@ -3485,13 +4144,31 @@ impl BaseWebRTCSink {
 
             let encoder_bitrate = (encoders_bitrate / (n_encoders as f64)) as i32;
 
+            let allocated_bitrates = match settings.bitrate_allocation {
+                WebRTCSinkBitrateAllocation::Equal => vec![encoder_bitrate; n_encoders],
+                WebRTCSinkBitrateAllocation::Weighted => {
+                    let obj = self.obj();
+                    let constraints = session
+                        .encoders
+                        .iter()
+                        .map(|encoder| {
+                            obj.static_pad(&encoder.stream_name).map_or((1, 0), |pad| {
+                                (pad.property("bitrate-weight"), pad.property("min-bitrate"))
+                            })
+                        })
+                        .collect::<Vec<(u32, u32)>>();
+
+                    allocate_weighted_bitrates(encoders_bitrate, &constraints)
+                }
+            };
+
             if let Some(rtpxsend) = session.rtprtxsend.as_ref() {
                 rtpxsend.set_property("stuffing-kbps", (bitrate as f64 / 1000.) as i32);
             }
 
             let mut s_builder = gst::Structure::builder("webrtcsink/encoder-bitrates");
-            for encoder in session.encoders.iter() {
-                s_builder = s_builder.field(&encoder.stream_name, encoder_bitrate);
+            for (encoder, bitrate) in session.encoders.iter().zip(&allocated_bitrates) {
+                s_builder = s_builder.field(&encoder.stream_name, *bitrate);
             }
             let s = s_builder.build();
 
@@ -3500,7 +4177,7 @@ impl BaseWebRTCSink {
                 &[&session.peer_id, &(encoders_bitrate as i32), &s],
             );
 
-            for encoder in session.encoders.iter_mut() {
+            for (encoder, encoder_bitrate) in session.encoders.iter_mut().zip(allocated_bitrates) {
                 let defined_encoder_bitrate =
                     match updated_bitrates.get::<i32>(&encoder.stream_name) {
                         Ok(bitrate) => {
@@ -3536,6 +4213,71 @@ impl BaseWebRTCSink {
         }
     }
 
+    fn set_session_bitrate_limits(
+        &self,
+        session_id: &str,
+        min_bitrate: u32,
+        max_bitrate: u32,
+    ) -> bool {
+        if min_bitrate > max_bitrate {
+            gst::warning!(
+                CAT,
+                imp = self,
+                "Invalid bitrate limits for session {session_id}: {min_bitrate} > {max_bitrate}"
+            );
+            return false;
+        }
+
+        let state = self.state.lock().unwrap();
+
+        let Some(session) = state.sessions.get(session_id) else {
+            gst::warning!(CAT, imp = self, "No session with id {session_id}");
+            return false;
+        };
+
+        /* We need this two-step approach for split-borrowing */
+        let mut session_guard = session.0.lock().unwrap();
+        let session = session_guard.deref_mut();
+
+        gst::info!(
+            CAT,
+            imp = self,
+            "Constraining session {session_id} to bitrates between {min_bitrate} and {max_bitrate}"
+        );
+
+        session.cc_info.min_bitrate = min_bitrate;
+        session.cc_info.max_bitrate = max_bitrate;
+
+        if let Some(congestion_controller) = session.congestion_controller.as_mut() {
+            congestion_controller.set_bitrate_limits(min_bitrate, max_bitrate);
+        }
+
+        let n_encoders = session.encoders.len().max(1) as u32;
+        if let Some(rtpgccbwe) = session.rtpgccbwe.as_ref() {
+            rtpgccbwe.set_properties(&[
+                ("min-bitrate", &(min_bitrate * n_encoders)),
+                ("max-bitrate", &(max_bitrate * n_encoders)),
+            ]);
+        }
+
+        // Apply the new limits right away instead of waiting for the
+        // congestion controller to produce a new estimate
+        for encoder in session.encoders.iter_mut() {
+            let bitrate = if session.cc_info.heuristic == WebRTCSinkCongestionControl::Disabled {
+                max_bitrate as i32
+            } else {
+                match encoder.bitrate() {
+                    Ok(bitrate) => bitrate.clamp(min_bitrate as i32, max_bitrate as i32),
+                    Err(_) => continue,
+                }
+            };
+
+            let _ = encoder.set_bitrate(&self.obj(), bitrate);
+        }
+
+        true
+    }
+
     fn on_remote_description_set(&self, session_id: &str) {
         let mut state_guard = self.state.lock().unwrap();
         let mut state = state_guard.deref_mut();
@@ -3556,6 +4298,11 @@ impl BaseWebRTCSink {
                 continue;
             };
 
+            // Already connected during a previous negotiation
+            if session.links.contains_key(&webrtc_pad.ssrc) {
+                continue;
+            }
+
             if let Some(mid) = transceiver.mid() {
                 state
                     .session_mids
@@ -3612,21 +4359,25 @@ impl BaseWebRTCSink {
             format!("webrtcsink-peer-{}-remote-description-set", session.id),
         );
 
-        let this_weak = self.downgrade();
-        let webrtcbin = session.webrtcbin.downgrade();
-        let session_id_clone = session.id.clone();
-        session.stats_collection_handle = Some(RUNTIME.spawn(async move {
-            let mut interval = tokio::time::interval(std::time::Duration::from_millis(100));
-
-            loop {
-                interval.tick().await;
-                if let (Some(webrtcbin), Some(this)) = (webrtcbin.upgrade(), this_weak.upgrade()) {
-                    this.process_stats(webrtcbin, &session_id_clone);
-                } else {
-                    break;
+        if session.stats_collection_handle.is_none() {
+            let this_weak = self.downgrade();
+            let webrtcbin = session.webrtcbin.downgrade();
+            let session_id_clone = session.id.clone();
+            session.stats_collection_handle = Some(RUNTIME.spawn(async move {
+                let mut interval = tokio::time::interval(std::time::Duration::from_millis(100));
+
+                loop {
+                    interval.tick().await;
+                    if let (Some(webrtcbin), Some(this)) =
+                        (webrtcbin.upgrade(), this_weak.upgrade())
+                    {
+                        this.process_stats(webrtcbin, &session_id_clone);
+                    } else {
+                        break;
+                    }
                 }
-            }
-        }));
+            }));
+        }
 
         if remove {
             let _ = state.sessions.remove(&session.id);
@@ -3636,6 +4387,16 @@ impl BaseWebRTCSink {
             let signaller = settings.signaller.clone();
             drop(settings);
             signaller.end_session(&session.id);
+        } else {
+            // Streams might have been added or removed during the negotiation
+            let session_id = session.id.clone();
+            RUNTIME.spawn(glib::clone!(
+                #[to_owned(rename_to = this)]
+                self,
+                async move {
+                    this.update_session_streams(&session_id).await;
+                }
+            ));
         }
     }
 
@@ -3683,6 +4444,11 @@ impl BaseWebRTCSink {
             session.sdp = Some(sdp.to_owned());
 
             for webrtc_pad in session.webrtc_pads.values_mut() {
+                // Media of a removed stream, inactive on purpose
+                if webrtc_pad.stream_name.is_none() {
+                    continue;
+                }
+
                 let media_idx = webrtc_pad.media_idx;
                 /* TODO: support partial answer, webrtcbin doesn't seem
                  * very well equipped to deal with this at the moment */
@@ -3759,6 +4525,81 @@ impl BaseWebRTCSink {
         }
     }
 
+    /// Handles an offer received for an already running session, for instance
+    /// when an SFU renegotiates the session.
+    ///
+    /// The existing transceivers are reused for the media they were negotiated
+    /// for, media added by the remote end are answered as inactive.
+    fn handle_sdp_offer(&self, session_id: &str, desc: &gst_webrtc::WebRTCSessionDescription) {
+        let (webrtcbin, mut webrtc_pads) = {
+            let state = self.state.lock().unwrap();
+
+            let Some(session) = state.sessions.get(session_id) else {
+                gst::warning!(CAT, imp = self, "No consumer with ID {session_id}");
+                return;
+            };
+            let session = session.0.lock().unwrap();
+
+            let signaling_state = session
+                .webrtcbin
+                .property::<gst_webrtc::WebRTCSignalingState>("signaling-state");
+            if signaling_state != gst_webrtc::WebRTCSignalingState::Stable {
+                gst::warning!(
+                    CAT,
+                    imp = self,
+                    "Ignoring offer for session {session_id} in signaling state {signaling_state:?}"
+                );
+                return;
+            }
+
+            (session.webrtcbin.clone(), session.webrtc_pads.clone())
+        };
+
+        gst::info!(
+            CAT,
+            imp = self,
+            "Renegotiating session {session_id} from remote offer"
+        );
+
+        let sdp = desc.sdp();
+        for media_idx in webrtc_pads.len() as u32..sdp.medias_len() {
+            let media = sdp.media(media_idx).unwrap();
+            let is_video = match media.media() {
+                Some("audio") => false,
+                Some("video") => true,
+                _ => continue,
+            };
+
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Answering new media {media_idx} of session {session_id} as inactive"
+            );
+            self.request_inactive_webrtcbin_pad(&webrtcbin, &mut webrtc_pads, is_video);
+        }
+
+        {
+            let state = self.state.lock().unwrap();
+            let Some(session) = state.sessions.get(session_id) else {
+                return;
+            };
+            session.0.lock().unwrap().webrtc_pads = webrtc_pads;
+        }
+
+        let promise = gst::Promise::with_change_func(glib::clone!(
+            #[weak(rename_to = this)]
+            self,
+            #[to_owned]
+            session_id,
+            move |reply| {
+                gst::debug!(CAT, imp = this, "received reply {:?}", reply);
+                this.on_remote_description_offer_set(session_id);
+            }
+        ));
+
+        webrtcbin.emit_by_name::<()>("set-remote-description", &[desc, &promise]);
+    }
+
     async fn run_discovery_pipeline(
         &self,
         stream_name: &str,
@@ -4213,6 +5054,7 @@ impl BaseWebRTCSink {
                     state.codecs_done_receivers.push(codecs_done_receiver);
                 }
 
+                let mut running_session_ids = vec![];
                 match fut.await {
                     Ok(Err(err)) => {
                         gst::error!(CAT, imp = this, "Error running discovery: {err:?}");
@@ -4235,12 +5077,19 @@ impl BaseWebRTCSink {
                             state.signaller_state = SignallerState::Started;
                             drop(state);
                             signaller.start();
+                        } else if state.signaller_state == SignallerState::Started {
+                            // The stream was added while running
+                            running_session_ids = state.sessions.keys().cloned().collect();
                         }
                     }
                     _ => (),
                 }
 
                 let _ = codecs_done_sender.send(());
+
+                for session_id in running_session_ids {
+                    this.update_session_streams(&session_id).await;
+                }
             }
         ));
     }
@@ -4355,6 +5204,12 @@ impl ObjectImpl for BaseWebRTCSink {
                     .blurb("Defines how congestion is controlled, if at all")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecEnum::builder_with_default("bitrate-allocation", DEFAULT_BITRATE_ALLOCATION)
+                    .nick("Bitrate allocation")
+                    .blurb("Defines how the estimated bitrate of a session is split between its video streams, \
+                        see the bitrate-weight and min-bitrate sink pad properties")
+                    .mutable_playing()
+                    .build(),
                 glib::ParamSpecUInt::builder("min-bitrate")
                     .nick("Minimal Bitrate")
                     .blurb("Minimal bitrate to use (in bit/sec) when computing it through the congestion control algorithm")
@@ -4402,6 +5257,23 @@ impl ObjectImpl for BaseWebRTCSink {
                     .default_value(DEFAULT_DO_CLOCK_SIGNALLING)
                     .mutable_ready()
                     .build(),
+                /**
+                 * GstBaseWebRTCSink:ntp-time-source:
+                 *
+                 * The timebase of the NTP timestamps in the RTCP sender reports. Receivers
+                 * synchronizing multiple senders based on the sender reports need all senders
+                 * to use a common timebase, e.g. the same network synchronized pipeline clock.
+                 *
+                 * For PTP, select `pipeline-clock` and use a #GstPtpClock as the pipeline clock,
+                 * the same applies to NTP with a #GstNtpClock.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecEnum::builder_with_default("ntp-time-source", DEFAULT_NTP_TIME_SOURCE)
+                    .nick("NTP Time Source")
+                    .blurb("Timebase of the NTP timestamps in the RTCP sender reports")
+                    .mutable_ready()
+                    .build(),
                 /**
                  * GstBaseWebRTCSink:enable-data-channel-navigation:
                  *
@@ -4437,6 +5309,36 @@ impl ObjectImpl for BaseWebRTCSink {
                     .blurb("The policy to apply for ICE transport")
                     .mutable_ready()
                     .build(),
+                /**
+                 * GstBaseWebRTCSink:audio-priority:
+                 *
+                 * Network priority of the audio streams of new sessions, used
+                 * by the transport to set the DSCP marking of the outgoing
+                 * packets as per RFC 8837. Can be overridden per session with
+                 * #GstBaseWebRTCSink::set-session-priority.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecEnum::builder_with_default("audio-priority", DEFAULT_AUDIO_PRIORITY)
+                    .nick("Audio priority")
+                    .blurb("Network priority (DSCP marking) of the outgoing audio packets")
+                    .mutable_ready()
+                    .build(),
+                /**
+                 * GstBaseWebRTCSink:video-priority:
+                 *
+                 * Network priority of the video streams of new sessions, used
+                 * by the transport to set the DSCP marking of the outgoing
+                 * packets as per RFC 8837. Can be overridden per session with
+                 * #GstBaseWebRTCSink::set-session-priority.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecEnum::builder_with_default("video-priority", DEFAULT_VIDEO_PRIORITY)
+                    .nick("Video priority")
+                    .blurb("Network priority (DSCP marking) of the outgoing video packets")
+                    .mutable_ready()
+                    .build(),
                 glib::ParamSpecObject::builder::<Signallable>("signaller")
                     .flags(glib::ParamFlags::READABLE | gst::PARAM_FLAG_MUTABLE_READY)
                     .blurb("The Signallable object to use to handle WebRTC Signalling")
@@ -4548,6 +5450,51 @@ impl ObjectImpl for BaseWebRTCSink {
                     .default_value(DEFAULT_FORWARD_METAS)
                     .mutable_playing()
                     .build(),
+                /**
+                 * GstBaseWebRTCSink:max-session-duration:
+                 *
+                 * Maximum duration of new sessions in seconds, after which they
+                 * are ended. Can be overridden per session with
+                 * #GstBaseWebRTCSink::set-session-max-duration.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecUInt::builder("max-session-duration")
+                    .nick("Max session duration")
+                    .blurb("Maximum duration of new sessions in seconds (0 = unlimited)")
+                    .default_value(DEFAULT_MAX_SESSION_DURATION)
+                    .mutable_playing()
+                    .build(),
+                /**
+                 * GstBaseWebRTCSink:session-validation-interval:
+                 *
+                 * Interval in seconds at which #GstBaseWebRTCSink::validate-session
+                 * is emitted for each session, for example to check whether the
+                 * token of the consumer is still valid.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecUInt::builder("session-validation-interval")
+                    .nick("Session validation interval")
+                    .blurb("Interval in seconds at which sessions are validated with the validate-session signal (0 = disabled)")
+                    .default_value(DEFAULT_SESSION_VALIDATION_INTERVAL)
+                    .mutable_playing()
+                    .build(),
+                /**
+                 * GstBaseWebRTCSink:session-expired-message:
+                 *
+                 * Message sent to the consumer over a dedicated "session" data
+                 * channel before ending a session that expired. If not set, no
+                 * data channel is created and sessions are ended right away.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecString::builder("session-expired-message")
+                    .nick("Session expired message")
+                    .blurb("Message sent over the session data channel before ending an expired session")
+                    .default_value(DEFAULT_SESSION_EXPIRED_MESSAGE)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -4586,6 +5533,12 @@ impl ObjectImpl for BaseWebRTCSink {
                     .get::<WebRTCSinkCongestionControl>()
                     .expect("type checked upstream");
             }
+            "bitrate-allocation" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.bitrate_allocation = value
+                    .get::<WebRTCSinkBitrateAllocation>()
+                    .expect("type checked upstream");
+            }
             "min-bitrate" => {
                 let mut settings = self.settings.lock().unwrap();
                 settings.cc_info.min_bitrate = value.get::<u32>().expect("type checked upstream");
@@ -4610,6 +5563,12 @@ impl ObjectImpl for BaseWebRTCSink {
                 let mut settings = self.settings.lock().unwrap();
                 settings.do_clock_signalling = value.get::<bool>().expect("type checked upstream");
             }
+            "ntp-time-source" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.ntp_time_source = value
+                    .get::<WebRTCSinkNtpTimeSource>()
+                    .expect("type checked upstream");
+            }
             "enable-data-channel-navigation" => {
                 let mut settings = self.settings.lock().unwrap();
                 settings.enable_data_channel_navigation =
@@ -4689,6 +5648,33 @@ impl ObjectImpl for BaseWebRTCSink {
                     .map(String::from)
                     .collect();
             }
+            "max-session-duration" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.max_session_duration = value.get::<u32>().expect("type checked upstream");
+            }
+            "session-validation-interval" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.session_validation_interval =
+                    value.get::<u32>().expect("type checked upstream");
+            }
+            "session-expired-message" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.session_expired_message = value
+                    .get::<Option<String>>()
+                    .expect("type checked upstream");
+            }
+            "audio-priority" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.audio_priority = value
+                    .get::<gst_webrtc::WebRTCPriorityType>()
+                    .expect("type checked upstream");
+            }
+            "video-priority" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.video_priority = value
+                    .get::<gst_webrtc::WebRTCPriorityType>()
+                    .expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -4707,6 +5693,10 @@ impl ObjectImpl for BaseWebRTCSink {
                 let settings = self.settings.lock().unwrap();
                 settings.cc_info.heuristic.to_value()
             }
+            "bitrate-allocation" => {
+                let settings = self.settings.lock().unwrap();
+                settings.bitrate_allocation.to_value()
+            }
             "stun-server" => {
                 let settings = self.settings.lock().unwrap();
                 settings.stun_server.to_value()
@@ -4739,6 +5729,10 @@ impl ObjectImpl for BaseWebRTCSink {
                 let settings = self.settings.lock().unwrap();
                 settings.do_clock_signalling.to_value()
             }
+            "ntp-time-source" => {
+                let settings = self.settings.lock().unwrap();
+                settings.ntp_time_source.to_value()
+            }
             "enable-data-channel-navigation" => {
                 let settings = self.settings.lock().unwrap();
                 settings.enable_data_channel_navigation.to_value()
@@ -4791,6 +5785,26 @@ impl ObjectImpl for BaseWebRTCSink {
                 let settings = self.settings.lock().unwrap();
                 settings.forward_metas.iter().join(",").to_value()
             }
+            "max-session-duration" => {
+                let settings = self.settings.lock().unwrap();
+                settings.max_session_duration.to_value()
+            }
+            "session-validation-interval" => {
+                let settings = self.settings.lock().unwrap();
+                settings.session_validation_interval.to_value()
+            }
+            "session-expired-message" => {
+                let settings = self.settings.lock().unwrap();
+                settings.session_expired_message.to_value()
+            }
+            "audio-priority" => {
+                let settings = self.settings.lock().unwrap();
+                settings.audio_priority.to_value()
+            }
+            "video-priority" => {
+                let settings = self.settings.lock().unwrap();
+                settings.video_priority.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -4863,6 +5877,139 @@ impl ObjectImpl for BaseWebRTCSink {
                     })
                     .return_type::<Vec<String>>()
                     .build(),
+                /**
+                 * GstBaseWebRTCSink::set-session-bitrate-limits:
+                 * @session_id: Identifier of the session
+                 * @min_bitrate: Minimal bitrate to use per video encoder
+                 * @max_bitrate: Maximal bitrate to use per video encoder
+                 *
+                 * Constrain the bitrate of the video encoders of a specific
+                 * session, overriding #GstBaseWebRTCSink:min-bitrate and
+                 * #GstBaseWebRTCSink:max-bitrate for that session only.
+                 *
+                 * The congestion control mechanism will only pick bitrates
+                 * within these limits. When congestion control is disabled,
+                 * @max_bitrate is used.
+                 *
+                 * Returns: %TRUE if the limits were applied, %FALSE if the
+                 * session does not exist or @min_bitrate > @max_bitrate.
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::subclass::Signal::builder("set-session-bitrate-limits")
+                    .param_types([
+                        String::static_type(),
+                        u32::static_type(),
+                        u32::static_type(),
+                    ])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let element = args[0].get::<super::BaseWebRTCSink>().expect("signal arg");
+                        let session_id = args[1].get::<&str>().expect("signal arg");
+                        let min_bitrate = args[2].get::<u32>().expect("signal arg");
+                        let max_bitrate = args[3].get::<u32>().expect("signal arg");
+
+                        Some(
+                            element
+                                .imp()
+                                .set_session_bitrate_limits(session_id, min_bitrate, max_bitrate)
+                                .to_value(),
+                        )
+                    })
+                    .build(),
+                /**
+                 * GstBaseWebRTCSink::set-session-max-duration:
+                 * @session_id: Identifier of the session
+                 * @max_duration: Maximum duration of the session in seconds,
+                 *   counted from its start, or 0 for no limit
+                 *
+                 * Override #GstBaseWebRTCSink:max-session-duration for a
+                 * specific session, for example from a
+                 * #GstBaseWebRTCSink::consumer-added handler.
+                 *
+                 * Returns: %TRUE if the duration was applied, %FALSE if the
+                 * session does not exist.
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::subclass::Signal::builder("set-session-max-duration")
+                    .param_types([String::static_type(), u32::static_type()])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let element = args[0].get::<super::BaseWebRTCSink>().expect("signal arg");
+                        let session_id = args[1].get::<&str>().expect("signal arg");
+                        let max_duration = args[2].get::<u32>().expect("signal arg");
+
+                        Some(
+                            element
+                                .imp()
+                                .set_session_max_duration(session_id, max_duration)
+                                .to_value(),
+                        )
+                    })
+                    .build(),
+                /**
+                 * GstBaseWebRTCSink::set-session-priority:
+                 * @session_id: Identifier of the session
+                 * @media: "audio" or "video"
+                 * @priority: Network priority of the streams
+                 *
+                 * Override #GstBaseWebRTCSink:audio-priority or
+                 * #GstBaseWebRTCSink:video-priority for the streams of a
+                 * specific session, for example from a
+                 * #GstBaseWebRTCSink::consumer-added handler.
+                 *
+                 * Returns: %TRUE if the priority was applied, %FALSE if the
+                 * session does not exist.
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::subclass::Signal::builder("set-session-priority")
+                    .param_types([
+                        String::static_type(),
+                        String::static_type(),
+                        gst_webrtc::WebRTCPriorityType::static_type(),
+                    ])
+                    .return_type::<bool>()
+                    .action()
+                    .class_handler(|_, args| {
+                        let element = args[0].get::<super::BaseWebRTCSink>().expect("signal arg");
+                        let session_id = args[1].get::<&str>().expect("signal arg");
+                        let media = args[2].get::<&str>().expect("signal arg");
+                        let priority = args[3]
+                            .get::<gst_webrtc::WebRTCPriorityType>()
+                            .expect("signal arg");
+
+                        Some(
+                            element
+                                .imp()
+                                .set_session_priority(session_id, media, priority)
+                                .to_value(),
+                        )
+                    })
+                    .build(),
+                /**
+                 * GstBaseWebRTCSink::validate-session:
+                 * @session_id: Identifier of the session
+                 * @peer_id: Identifier of the consumer
+                 *
+                 * Emitted every #GstBaseWebRTCSink:session-validation-interval
+                 * seconds for each session. Handlers can for example check
+                 * whether the token of the consumer expired.
+                 *
+                 * Returns: %FALSE to end the session, after sending
+                 * #GstBaseWebRTCSink:session-expired-message to the consumer.
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::subclass::Signal::builder("validate-session")
+                    .param_types([String::static_type(), String::static_type()])
+                    .return_type::<bool>()
+                    .class_handler(|_, _args| Some(true.to_value()))
+                    .accumulator(|_hint, acc, value| {
+                        let valid = value.get::<bool>().unwrap_or(true);
+                        *acc = value.clone();
+                        valid
+                    })
+                    .build(),
                 /**
                  * GstBaseWebRTCSink::encoder-setup:
                  * @consumer_id: Identifier of the consumer, or "discovery"
@@ -4960,6 +6107,48 @@ impl ObjectImpl for BaseWebRTCSink {
                     ])
                     .return_type::<gst::Element>()
                     .build(),
+                /**
+                 * GstBaseWebRTCSink::run-preflight-check:
+                 * @probe_duration: How long to send data for estimating the
+                 *   throughput in milliseconds, or 0 to skip the estimation
+                 *
+                 * Checks the connectivity with the configured STUN and TURN servers
+                 * by connecting two local webrtcbin instances, for example to warn
+                 * users about bad networks before media starts.
+                 *
+                 * When TURN servers are configured, only relay candidates are used so
+                 * that the results reflect the network path to the TURN servers. Without
+                 * TURN servers the connection is local to the host, so only the ICE
+                 * gathering is checked and no round-trip time or throughput is measured.
+                 *
+                 * The check runs asynchronously. Once finished, an element message
+                 * named `webrtc-preflight-result` is posted on the bus with the
+                 * following fields:
+                 *
+                 * - `gathering-time` (#GstClockTime): time for gathering ICE candidates,
+                 *   or %GST_CLOCK_TIME_NONE if gathering did not complete
+                 * - `host-candidates`, `srflx-candidates`, `relay-candidates` (#guint):
+                 *   number of gathered candidates per type
+                 * - `rtt` (#GstClockTime): round-trip time over the relayed connection,
+                 *   or %GST_CLOCK_TIME_NONE without TURN servers
+                 * - `throughput` (#guint64, optional): estimated throughput in bits per
+                 *   second over the relayed connection
+                 * - `error` (#gchararray, optional): description of the failure
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::subclass::Signal::builder("run-preflight-check")
+                    .param_types([u32::static_type()])
+                    .action()
+                    .class_handler(|_, args| {
+                        let element = args[0].get::<super::BaseWebRTCSink>().expect("signal arg");
+                        let probe_duration = args[1].get::<u32>().expect("signal arg");
+
+                        element.imp().run_preflight_check(probe_duration);
+
+                        None
+                    })
+                    .build(),
                 /**
                  * GstBaseWebRTCSink::define-encoder-bitrates:
                  * @consumer_id: Identifier of the consumer
@@ -5086,15 +6275,6 @@ impl ElementImpl for BaseWebRTCSink {
         _caps: Option<&gst::Caps>,
     ) -> Option<gst::Pad> {
         let element = self.obj();
-        if element.current_state() > gst::State::Ready {
-            gst::error!(
-                CAT,
-                imp = self,
-                "element pads can only be requested before starting"
-            );
-            return None;
-        }
-
         let mut state = self.state.lock().unwrap();
 
         let serial;
@@ -5134,23 +6314,82 @@ impl ElementImpl for BaseWebRTCSink {
         sink_pad.use_fixed_caps();
         element.add_pad(&sink_pad).unwrap();
 
-        state.streams.insert(
-            name,
-            InputStream {
-                sink_pad: sink_pad.clone(),
-                producer: None,
-                in_caps: None,
-                out_caps: None,
-                clocksync: None,
-                is_video,
-                serial,
-                initial_discovery_started: false,
-            },
-        );
+        let mut stream = InputStream {
+            sink_pad: sink_pad.clone(),
+            producer: None,
+            in_caps: None,
+            out_caps: None,
+            clocksync: None,
+            is_video,
+            serial,
+            initial_discovery_started: false,
+        };
+
+        // The stream is added to running sessions once its codec discovery is done
+        if element.current_state() > gst::State::Ready {
+            gst::info!(CAT, imp = self, "Adding stream {name} while running");
+
+            if let Err(err) = stream.prepare(&element) {
+                gst::error!(CAT, imp = self, "Failed to prepare stream {name}: {err}");
+                stream.unprepare(&element);
+                let _ = sink_pad.set_active(false);
+                let _ = element.remove_pad(&sink_pad);
+                return None;
+            }
+            state.codec_discovery_done = false;
+        }
+
+        state.streams.insert(name, stream);
 
         Some(sink_pad.upcast())
     }
 
+    fn release_pad(&self, pad: &gst::Pad) {
+        let element = self.obj();
+        let name = pad.name().to_string();
+
+        let mut state = self.state.lock().unwrap();
+        let Some(mut stream) = state.streams.remove(&name) else {
+            return;
+        };
+
+        gst::info!(CAT, imp = self, "Removing stream {name}");
+
+        state.discoveries.remove(&name);
+        for mids in state.session_mids.values_mut() {
+            mids.retain(|_, stream_name| stream_name != &name);
+        }
+        for stream_names in state.session_stream_names.values_mut() {
+            stream_names.remove(&name);
+        }
+        state.codec_discovery_done = state
+            .streams
+            .values()
+            .all(|stream| stream.out_caps.is_some());
+        let sessions = state.sessions.values().cloned().collect::<Vec<_>>();
+        drop(state);
+
+        let _ = pad.set_active(false);
+        stream.unprepare(&element);
+        let _ = element.remove_pad(pad);
+
+        for session in sessions {
+            let session_id = {
+                let mut session = session.0.lock().unwrap();
+                session.remove_input_stream(&element, &name);
+                session.id.clone()
+            };
+
+            RUNTIME.spawn(glib::clone!(
+                #[to_owned(rename_to = this)]
+                self,
+                async move {
+                    this.update_session_streams(&session_id).await;
+                }
+            ));
+        }
+    }
+
     fn change_state(
         &self,
         transition: gst::StateChange,
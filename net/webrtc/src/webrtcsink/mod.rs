@@ -124,6 +124,40 @@ pub enum WebRTCSinkCongestionControl {
     GoogleCongestionControl,
 }
 
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstWebRTCSinkBitrateAllocation")]
+pub enum WebRTCSinkBitrateAllocation {
+    #[default]
+    #[enum_value(
+        name = "Equal: the estimated bitrate is split evenly between video streams",
+        nick = "equal"
+    )]
+    Equal,
+    #[enum_value(
+        name = "Weighted: minimum bitrates of the sink pads are guaranteed first, the rest is split according to their weights",
+        nick = "weighted"
+    )]
+    Weighted,
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstWebRTCSinkNtpTimeSource")]
+pub enum WebRTCSinkNtpTimeSource {
+    #[default]
+    #[enum_value(
+        name = "System: the system clock as NTP time, independent of the pipeline clock",
+        nick = "system"
+    )]
+    System,
+    #[enum_value(
+        name = "Pipeline clock: the time of the pipeline clock, e.g. a GstNtpClock or a GstPtpClock for PTP",
+        nick = "pipeline-clock"
+    )]
+    PipelineClock,
+}
+
 #[glib::flags(name = "GstWebRTCSinkMitigationMode")]
 enum WebRTCSinkMitigationMode {
     #[flags_value(name = "No mitigation applied", nick = "none")]
@@ -158,6 +192,8 @@ pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     WebRTCSinkPad::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     BaseWebRTCSink::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     WebRTCSinkCongestionControl::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    WebRTCSinkBitrateAllocation::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    WebRTCSinkNtpTimeSource::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     gst::Element::register(
         Some(plugin),
         "webrtcsink",
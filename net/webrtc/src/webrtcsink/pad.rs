@@ -9,9 +9,24 @@ pub struct WebRTCSinkPad {
     settings: Mutex<Settings>,
 }
 
-#[derive(Debug, Default)]
+const DEFAULT_BITRATE_WEIGHT: u32 = 1;
+const DEFAULT_MIN_BITRATE: u32 = 0;
+
+#[derive(Debug)]
 struct Settings {
     msid: Option<String>,
+    bitrate_weight: u32,
+    min_bitrate: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            msid: None,
+            bitrate_weight: DEFAULT_BITRATE_WEIGHT,
+            min_bitrate: DEFAULT_MIN_BITRATE,
+        }
+    }
 }
 
 #[glib::object_subclass]
@@ -24,10 +39,25 @@ impl ObjectSubclass for WebRTCSinkPad {
 impl ObjectImpl for WebRTCSinkPad {
     fn properties() -> &'static [glib::ParamSpec] {
         static PROPS: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
-            vec![glib::ParamSpecString::builder("msid")
-                .flags(glib::ParamFlags::READWRITE | gst::PARAM_FLAG_MUTABLE_READY)
-                .blurb("Remote MediaStream ID in use for this pad")
-                .build()]
+            vec![
+                glib::ParamSpecString::builder("msid")
+                    .flags(glib::ParamFlags::READWRITE | gst::PARAM_FLAG_MUTABLE_READY)
+                    .blurb("Remote MediaStream ID in use for this pad")
+                    .build(),
+                glib::ParamSpecUInt::builder("bitrate-weight")
+                    .nick("Bitrate weight")
+                    .blurb("Share of the estimated bitrate given to this stream relative to the other video streams, when bitrate-allocation=weighted")
+                    .minimum(1)
+                    .default_value(DEFAULT_BITRATE_WEIGHT)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("min-bitrate")
+                    .nick("Minimum bitrate")
+                    .blurb("Bitrate (in bit/sec) guaranteed to this stream before the rest is split, when bitrate-allocation=weighted")
+                    .default_value(DEFAULT_MIN_BITRATE)
+                    .mutable_playing()
+                    .build(),
+            ]
         });
         PROPS.as_ref()
     }
@@ -39,6 +69,12 @@ impl ObjectImpl for WebRTCSinkPad {
                     .get::<Option<String>>()
                     .expect("type checked upstream")
             }
+            "bitrate-weight" => {
+                settings.bitrate_weight = value.get::<u32>().expect("type checked upstream")
+            }
+            "min-bitrate" => {
+                settings.min_bitrate = value.get::<u32>().expect("type checked upstream")
+            }
             name => panic!("no writable property {name:?}"),
         }
     }
@@ -46,6 +82,8 @@ impl ObjectImpl for WebRTCSinkPad {
         let settings = self.settings.lock().unwrap();
         match pspec.name() {
             "msid" => settings.msid.to_value(),
+            "bitrate-weight" => settings.bitrate_weight.to_value(),
+            "min-bitrate" => settings.min_bitrate.to_value(),
             name => panic!("no readable property {name:?}"),
         }
     }
@@ -4,7 +4,7 @@ use gst::prelude::*;
 
 use crate::signaller::{prelude::*, Signallable, Signaller};
 use crate::utils::{self, Codec, Codecs, NavigationEvent, AUDIO_CAPS, RTP_CAPS, VIDEO_CAPS};
-use crate::webrtcsrc::WebRTCSrcPad;
+use crate::webrtcsrc::{WebRTCSrcLatencyProfile, WebRTCSrcPad};
 use anyhow::{Context, Error};
 use gst::glib;
 use gst::subclass::prelude::*;
@@ -24,6 +24,9 @@ const DEFAULT_STUN_SERVER: Option<&str> = Some("stun://stun.l.google.com:19302")
 const DEFAULT_ENABLE_DATA_CHANNEL_NAVIGATION: bool = false;
 const DEFAULT_ENABLE_CONTROL_DATA_CHANNEL: bool = false;
 const DEFAULT_DO_RETRANSMISSION: bool = true;
+const DEFAULT_LATENCY_PROFILE: WebRTCSrcLatencyProfile = WebRTCSrcLatencyProfile::Balanced;
+// Labels of the data channels created by webrtcsink for its own purposes
+const RESERVED_DATA_CHANNEL_LABELS: &[&str] = &["input", "control"];
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -43,6 +46,61 @@ struct Settings {
     enable_data_channel_navigation: bool,
     enable_control_data_channel: bool,
     do_retransmission: bool,
+    latency_profile: WebRTCSrcLatencyProfile,
+}
+
+/// Settings of the receiving elements for a latency profile
+#[derive(Debug, Clone, Copy)]
+struct LatencyProfileSettings {
+    // Jitterbuffer latency in milliseconds
+    latency: u32,
+    do_nack: bool,
+    drop_on_latency: bool,
+    // Delay of the first retransmission request in milliseconds, -1 for automatic
+    rtx_delay: i32,
+    // Maximum number of retransmission requests per packet, -1 for unlimited
+    rtx_max_retries: i32,
+    // Whether decoders drop corrupted frames and request a new key unit instead
+    discard_corrupted_frames: bool,
+}
+
+impl WebRTCSrcLatencyProfile {
+    fn settings(self) -> LatencyProfileSettings {
+        match self {
+            Self::UltraLow => LatencyProfileSettings {
+                latency: 30,
+                do_nack: false,
+                drop_on_latency: true,
+                rtx_delay: -1,
+                rtx_max_retries: -1,
+                discard_corrupted_frames: true,
+            },
+            Self::Low => LatencyProfileSettings {
+                latency: 100,
+                do_nack: true,
+                drop_on_latency: true,
+                rtx_delay: 10,
+                rtx_max_retries: 2,
+                discard_corrupted_frames: true,
+            },
+            Self::Balanced => LatencyProfileSettings {
+                latency: 200,
+                do_nack: true,
+                drop_on_latency: false,
+                rtx_delay: -1,
+                rtx_max_retries: -1,
+                discard_corrupted_frames: false,
+            },
+            Self::Robust => LatencyProfileSettings {
+                latency: 1000,
+                do_nack: true,
+                drop_on_latency: false,
+                rtx_delay: -1,
+                rtx_max_retries: -1,
+                discard_corrupted_frames: false,
+            },
+        }
+    }
 }
 
 #[derive(Default)]
@@ -149,6 +207,35 @@ impl ObjectImpl for BaseWebRTCSrc {
                    .default_value(DEFAULT_DO_RETRANSMISSION)
                    .mutable_ready()
                    .build(),
+               /**
+                * GstBaseWebRTCSrc:latency-profile:
+                *
+                * Configures the jitterbuffer latency, retransmission requests and
+                * decoder error handling for a latency / robustness trade-off.
+                * Retransmissions are only requested if #GstBaseWebRTCSrc:do-retransmission
+                * is also enabled.
+                *
+                * Since: plugins-rs-0.14.0
+                */
+               glib::ParamSpecEnum::builder_with_default("latency-profile", DEFAULT_LATENCY_PROFILE)
+                   .nick("Latency profile")
+                   .blurb("Trade-off between latency and robustness of the received streams")
+                   .mutable_ready()
+                   .build(),
+               /**
+                * GstBaseWebRTCSrc:stats:
+                *
+                * The latency profile in use and, for each source pad, the latency
+                * currently reported by the pipeline from the network to that pad, covering
+                * jitterbuffering, depayloading and decoding.
+                *
+                * Since: plugins-rs-0.14.0
+                */
+               glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                   .nick("Statistics")
+                   .blurb("Latency profile and estimated latency of each source pad")
+                   .read_only()
+                   .build(),
             ]
         });
 
@@ -215,6 +302,10 @@ impl ObjectImpl for BaseWebRTCSrc {
                 let mut settings = self.settings.lock().unwrap();
                 settings.do_retransmission = value.get::<bool>().unwrap();
             }
+            "latency-profile" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.latency_profile = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -252,6 +343,8 @@ impl ObjectImpl for BaseWebRTCSrc {
                 settings.enable_control_data_channel.to_value()
             }
             "do-retransmission" => self.settings.lock().unwrap().do_retransmission.to_value(),
+            "latency-profile" => self.settings.lock().unwrap().latency_profile.to_value(),
+            "stats" => self.stats().to_value(),
             name => panic!("{} getter not implemented", name),
         }
     }
@@ -282,6 +375,29 @@ impl ObjectImpl for BaseWebRTCSrc {
                     ])
                     .return_type::<gst::Element>()
                     .build(),
+                /**
+                 * GstBaseWebRTCSrc::run-preflight-check:
+                 * @probe_duration: How long to send data for estimating the
+                 *   throughput in milliseconds, or 0 to skip the estimation
+                 *
+                 * Checks the connectivity with the configured STUN and TURN servers
+                 * and posts a `webrtc-preflight-result` element message with the
+                 * results, see #GstBaseWebRTCSink::run-preflight-check for details.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::subclass::Signal::builder("run-preflight-check")
+                    .param_types([u32::static_type()])
+                    .action()
+                    .class_handler(|_, args| {
+                        let element = args[0].get::<super::BaseWebRTCSrc>().expect("signal arg");
+                        let probe_duration = args[1].get::<u32>().expect("signal arg");
+
+                        element.imp().run_preflight_check(probe_duration);
+
+                        None
+                    })
+                    .build(),
             ]
         });
 
@@ -321,6 +437,7 @@ impl Default for Settings {
             enable_data_channel_navigation: DEFAULT_ENABLE_DATA_CHANNEL_NAVIGATION,
             enable_control_data_channel: DEFAULT_ENABLE_CONTROL_DATA_CHANNEL,
             do_retransmission: DEFAULT_DO_RETRANSMISSION,
+            latency_profile: DEFAULT_LATENCY_PROFILE,
         }
     }
 }
@@ -352,6 +469,7 @@ impl Session {
             data_channel: None,
             n_video_pads: AtomicU16::new(0),
             n_audio_pads: AtomicU16::new(0),
+            n_data_pads: AtomicU16::new(0),
             flow_combiner: Mutex::new(gst_base::UniqueFlowCombiner::new()),
             request_counter: 0,
             pending_srcpads: HashMap::new(),
@@ -787,7 +905,7 @@ impl Session {
                         .chain(settings.audio_codecs.iter())
                         .map(|codec| codec.name.clone())
                         .collect::<HashSet<String>>(),
-                    settings.do_retransmission,
+                    settings.do_retransmission && settings.latency_profile.settings().do_nack,
                 )
             };
             let caps = media
@@ -994,9 +1112,132 @@ impl Session {
         signaller.send_sdp(&self.id, &answer);
     }
 
-    fn on_data_channel(&mut self, data_channel: glib::Object, element: &super::BaseWebRTCSrc) {
+    // Returns the session bin ghost pad and our source pad to add for data channels
+    // which are exposed, this must be done without the state lock held
+    fn on_data_channel(
+        &mut self,
+        data_channel: glib::Object,
+        element: &super::BaseWebRTCSrc,
+    ) -> Option<(gst::Bin, gst::GhostPad, WebRTCSrcPad)> {
         gst::info!(CAT, obj = element, "Received data channel {data_channel:?}");
-        self.data_channel = data_channel.dynamic_cast::<WebRTCDataChannel>().ok();
+        let data_channel = data_channel.dynamic_cast::<WebRTCDataChannel>().ok()?;
+
+        let label = data_channel.label();
+        if label
+            .as_deref()
+            .map_or(true, |label| RESERVED_DATA_CHANNEL_LABELS.contains(&label))
+        {
+            self.data_channel = Some(data_channel);
+            return None;
+        }
+
+        self.expose_data_channel(&data_channel, element)
+            .inspect_err(|err| {
+                gst::error!(
+                    CAT,
+                    obj = element,
+                    "Failed to expose data channel {label:?}: {err:?}"
+                );
+            })
+            .ok()
+    }
+
+    // Creates a source pad outputting the messages received on a data channel
+    // negotiated by the peer
+    fn expose_data_channel(
+        &self,
+        data_channel: &WebRTCDataChannel,
+        element: &super::BaseWebRTCSrc,
+    ) -> Result<(gst::Bin, gst::GhostPad, WebRTCSrcPad), Error> {
+        let bin = self
+            .webrtcbin
+            .parent()
+            .and_downcast::<gst::Bin>()
+            .context("webrtcbin is not in a bin")?;
+
+        let mut caps = gst::Caps::builder("application/x-data");
+        if let Some(label) = data_channel.label() {
+            caps = caps.field("label", label);
+        }
+        if let Some(protocol) = data_channel
+            .protocol()
+            .filter(|protocol| !protocol.is_empty())
+        {
+            caps = caps.field("protocol", protocol);
+        }
+        let caps = caps.build();
+
+        let n_pad = self.n_data_pads.fetch_add(1, Ordering::SeqCst);
+        gst::info!(
+            CAT,
+            obj = element,
+            "Exposing data channel with caps {caps:?} for session {}",
+            self.id
+        );
+
+        let appsrc = gst_app::AppSrc::builder()
+            .caps(&caps)
+            .format(gst::Format::Time)
+            .is_live(true)
+            .do_timestamp(true)
+            .build();
+        bin.add(&appsrc)?;
+        appsrc.sync_state_with_parent()?;
+
+        let bin_ghostpad = gst::GhostPad::with_target(&appsrc.static_pad("src").unwrap())?;
+
+        let mut cs = glib::Checksum::new(glib::ChecksumType::Sha256).unwrap();
+        cs.update(self.id.as_bytes());
+        let stream_id = format!("{}:data:{n_pad}", cs.string().unwrap());
+
+        let template = element.pad_template("data_%s_%u").unwrap();
+        let srcpad = gst::GhostPad::builder_from_template(&template)
+            .name(format!("data_{}_{n_pad}", self.id))
+            .build()
+            .downcast::<WebRTCSrcPad>()
+            .unwrap();
+        srcpad.imp().set_stream_id(&stream_id);
+        srcpad.set_target(Some(&bin_ghostpad))?;
+
+        data_channel.connect_closure(
+            "on-message-data",
+            false,
+            glib::closure!(
+                #[weak]
+                appsrc,
+                move |_channel: &WebRTCDataChannel, data: Option<glib::Bytes>| {
+                    if let Some(data) = data {
+                        let _ = appsrc.push_buffer(gst::Buffer::from_slice(data));
+                    }
+                }
+            ),
+        );
+        data_channel.connect_closure(
+            "on-message-string",
+            false,
+            glib::closure!(
+                #[weak]
+                appsrc,
+                move |_channel: &WebRTCDataChannel, msg: Option<&str>| {
+                    if let Some(msg) = msg {
+                        let _ = appsrc.push_buffer(gst::Buffer::from_slice(msg.to_owned()));
+                    }
+                }
+            ),
+        );
+        data_channel.connect_closure(
+            "on-close",
+            false,
+            glib::closure!(
+                #[weak]
+                appsrc,
+                move |_channel: &WebRTCDataChannel| {
+                    let _ = appsrc.end_of_stream();
+                }
+            ),
+        );
+
+        Ok((bin, bin_ghostpad, srcpad))
     }
 
     fn on_ice_candidate(
@@ -1037,6 +1278,20 @@ impl Session {
 }
 
 impl BaseWebRTCSrc {
+    fn run_preflight_check(&self, probe_duration: u32) {
+        let (stun_server, turn_servers) = {
+            let settings = self.settings.lock().unwrap();
+            (settings.stun_server.clone(), settings.turn_servers.clone())
+        };
+
+        crate::preflight::run_for_element(
+            self.obj().upcast_ref(),
+            stun_server,
+            &turn_servers,
+            probe_duration,
+        );
+    }
+
     fn signaller(&self) -> Signallable {
         self.settings.lock().unwrap().signaller.clone()
     }
@@ -1337,7 +1592,7 @@ impl BaseWebRTCSrc {
 
         let webrtcbin = session.webrtcbin();
 
-        {
+        let profile = {
             let settings = self.settings.lock().unwrap();
 
             if let Some(stun_server) = settings.stun_server.as_ref() {
@@ -1347,10 +1602,27 @@ impl BaseWebRTCSrc {
             for turn_server in settings.turn_servers.iter() {
                 webrtcbin.emit_by_name::<bool>("add-turn-server", &[&turn_server]);
             }
-        }
+
+            settings.latency_profile.settings()
+        };
+
+        webrtcbin.set_property("latency", profile.latency);
 
         let bin = gst::Bin::new();
 
+        let element = self.obj();
+        bin.connect_closure(
+            "deep-element-added",
+            false,
+            glib::closure!(
+                #[watch]
+                element,
+                move |_bin: gst::Bin, _parent: gst::Bin, e: gst::Element| {
+                    element.imp().configure_for_latency_profile(&e, &profile);
+                }
+            ),
+        );
+
         bin.connect_pad_removed(glib::clone!(
             #[weak(rename_to = this)]
             self,
@@ -1452,7 +1724,17 @@ impl BaseWebRTCSrc {
                         gst::error!(CAT, imp = this, "session {session_id:?} not found");
                         return;
                     };
-                    session.on_data_channel(data_channel, &this.obj());
+                    let data_pads = session.on_data_channel(data_channel, &this.obj());
+                    drop(state);
+
+                    if let Some((bin, bin_ghostpad, srcpad)) = data_pads {
+                        bin.add_pad(&bin_ghostpad)
+                            .expect("Adding ghostpad to the bin should always work");
+                        srcpad.set_active(true).unwrap();
+                        this.obj()
+                            .add_pad(&srcpad)
+                            .expect("Adding ghost pad should never fail");
+                    }
                 }
             ),
         );
@@ -1470,6 +1752,69 @@ impl BaseWebRTCSrc {
         Ok(())
     }
 
+    fn configure_for_latency_profile(
+        &self,
+        element: &gst::Element,
+        profile: &LatencyProfileSettings,
+    ) {
+        let Some(factory) = element.factory() else {
+            return;
+        };
+
+        if factory.name() == "rtpjitterbuffer" {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Configuring {} for {profile:?}",
+                element.name()
+            );
+            element.set_property("drop-on-latency", profile.drop_on_latency);
+            element.set_property("rtx-delay", profile.rtx_delay);
+            element.set_property("rtx-max-retries", profile.rtx_max_retries);
+        } else if factory.has_type(gst::ElementFactoryType::DECODER)
+            && element.has_property("discard-corrupted-frames", Some(bool::static_type()))
+            && element.has_property("automatic-request-sync-points", Some(bool::static_type()))
+        {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Configuring {} for {profile:?}",
+                element.name()
+            );
+            element.set_property("discard-corrupted-frames", profile.discard_corrupted_frames);
+            element.set_property(
+                "automatic-request-sync-points",
+                profile.discard_corrupted_frames,
+            );
+        }
+    }
+
+    fn stats(&self) -> gst::Structure {
+        let mut stats = gst::Structure::builder("application/x-webrtcsrc-stats")
+            .field(
+                "latency-profile",
+                self.settings.lock().unwrap().latency_profile,
+            )
+            .build();
+
+        for pad in self.obj().src_pads() {
+            let mut query = gst::query::Latency::new();
+            let pad_stats = if pad.query(&mut query) {
+                let (live, min, _max) = query.result();
+                gst::Structure::builder("application/x-webrtcsrc-pad-stats")
+                    .field("estimated-latency", min.nseconds())
+                    .field("live", live)
+                    .build()
+            } else {
+                gst::Structure::new_empty("application/x-webrtcsrc-pad-stats")
+            };
+
+            stats.set(pad.name().as_str(), pad_stats);
+        }
+
+        stats
+    }
+
     fn end_session(&self, id: &str, bin: &gst::Bin) -> Result<(), Error> {
         let obj = self.obj();
 
@@ -1531,6 +1876,14 @@ impl ElementImpl for BaseWebRTCSrc {
                     WebRTCSrcPad::static_type(),
                 )
                 .unwrap(),
+                gst::PadTemplate::with_gtype(
+                    "data_%s_%u",
+                    gst::PadDirection::Src,
+                    gst::PadPresence::Sometimes,
+                    &gst::Caps::builder("application/x-data").build(),
+                    WebRTCSrcPad::static_type(),
+                )
+                .unwrap(),
             ]
         });
 
@@ -1664,6 +2017,7 @@ struct Session {
     data_channel: Option<WebRTCDataChannel>,
     n_video_pads: AtomicU16,
     n_audio_pads: AtomicU16,
+    n_data_pads: AtomicU16,
     flow_combiner: Mutex<gst_base::UniqueFlowCombiner>,
     request_counter: u64,
     pending_srcpads: HashMap<String, (WebRTCSrcPad, gst::Caps)>,
@@ -31,16 +31,59 @@
  * in `decodebinX` but for the case where a `videoconvert` is placed after a `video_XX` pad,
  * decoding will happen inside `webrtcsrc`.
  *
+ * ## Data channels
+ *
+ * Data channels negotiated by the remote peer are exposed as `data_XX` source
+ * pads outputting `application/x-data` buffers, with the `label` and `protocol`
+ * of the channel in the caps. The `input` and `control` data channels created
+ * by #webrtcsink are used internally and not exposed.
+ *
+ * ## Latency
+ *
+ * The `latency-profile` property configures the jitterbuffer latency, the
+ * retransmission requests and the error handling of the decoders used by
+ * `webrtcsrc` consistently for a given latency / robustness trade-off. The
+ * latency currently reported by the pipeline for each source pad can be
+ * retrieved with the `stats` property.
+ *
  * Since: 0.10
  */
 mod imp;
 mod pad;
 
 use crate::signaller::Signallable;
-use crate::signaller::WebRTCSignallerRole;
+use crate::signaller::{WebRTCSignallerConnectionState, WebRTCSignallerRole};
 use gst::prelude::*;
 use gst::{glib, prelude::StaticType};
 
+/// Trade-off between latency and robustness of the received streams
+#[derive(Debug, Default, Eq, PartialEq, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstWebRTCSrcLatencyProfile")]
+pub enum WebRTCSrcLatencyProfile {
+    #[enum_value(
+        name = "Ultra low: minimal buffering, no retransmissions, late packets are dropped",
+        nick = "ultra-low"
+    )]
+    UltraLow,
+    #[enum_value(
+        name = "Low: little buffering, quick and limited retransmissions",
+        nick = "low"
+    )]
+    Low,
+    #[default]
+    #[enum_value(
+        name = "Balanced: default buffering and retransmissions",
+        nick = "balanced"
+    )]
+    Balanced,
+    #[enum_value(
+        name = "Robust: large buffering to recover from network issues",
+        nick = "robust"
+    )]
+    Robust,
+}
+
 glib::wrapper! {
     pub struct BaseWebRTCSrc(ObjectSubclass<imp::BaseWebRTCSrc>) @extends gst::Bin, gst::Element, gst::Object, @implements gst::ChildProxy;
 }
@@ -71,6 +114,8 @@ glib::wrapper! {
 pub fn register(plugin: Option<&gst::Plugin>) -> Result<(), glib::BoolError> {
     BaseWebRTCSrc::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     WebRTCSignallerRole::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    WebRTCSignallerConnectionState::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    WebRTCSrcLatencyProfile::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     WebRTCSrcPad::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
     #[cfg(feature = "livekit")]
     LiveKitWebRTCSrcPad::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
@@ -0,0 +1,570 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-asrunlogsink
+ *
+ * #asrunlogsink records what was actually played out and when into an as-run log.
+ *
+ * Every stream that reaches the sink, delimited by stream-start events, is logged as one `item`
+ * record once it is finished. The record contains the running time and the wall-clock time (UTC,
+ * ISO 8601) of the first and the last rendered buffer, the stream id, the title from the stream
+ * tags and the URI that was current when the stream started.
+ *
+ * In addition the sink watches the pipeline it is part of for changes of the properties listed in
+ * #asrunlogsink:watch-properties and logs each change as a `switch` record with the path of the
+ * element, the new value and the time of the change. By default the `active-pad` property of
+ * switch elements like #fallbackswitch and the `current-uri` property of #uridecodebin, as used
+ * by #uriplaylistbin, are watched. Changes of a `current-uri` or `uri` property also set the URI
+ * of the next item.
+ *
+ * Records are either written as one JSON object per line or as CSV with a header line. The file is
+ * flushed after every record so that the log is complete even if the application is killed.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 uriplaylistbin uris="<file:///tmp/a.mp4,file:///tmp/b.mp4>" ! videoconvert ! asrunlogsink location=asrun.jsonl
+ * ]|
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use serde::Serialize;
+
+use super::AsRunLogFormat;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "asrunlogsink",
+        gst::DebugColorFlags::empty(),
+        Some("As-Run Log Sink"),
+    )
+});
+
+const DEFAULT_LOCATION: Option<String> = None;
+const DEFAULT_FORMAT: AsRunLogFormat = AsRunLogFormat::JsonLines;
+const DEFAULT_WATCH_PROPERTIES: &[&str] = &["active-pad", "current-uri"];
+
+const CSV_COLUMNS: &[&str] = &[
+    "event",
+    "start_wallclock",
+    "end_wallclock",
+    "start_running_time",
+    "end_running_time",
+    "uri",
+    "title",
+    "stream_id",
+    "element",
+    "property",
+    "value",
+];
+
+#[derive(Debug, Clone)]
+struct Settings {
+    location: Option<String>,
+    format: AsRunLogFormat,
+    watch_properties: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            location: DEFAULT_LOCATION,
+            format: DEFAULT_FORMAT,
+            watch_properties: DEFAULT_WATCH_PROPERTIES
+                .iter()
+                .map(|s| String::from(*s))
+                .collect(),
+        }
+    }
+}
+
+/// One record of the as-run log
+#[derive(Debug, Default, Clone, Serialize)]
+struct Record {
+    event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_wallclock: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_wallclock: Option<String>,
+    /// In nanoseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_running_time: Option<u64>,
+    /// In nanoseconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_running_time: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    element: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    property: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+}
+
+impl Record {
+    fn to_csv(&self) -> String {
+        fn field(value: Option<&str>) -> String {
+            match value {
+                None => String::new(),
+                Some(value) if value.contains([',', '"', '\n', '\r']) => {
+                    format!("\"{}\"", value.replace('"', "\"\""))
+                }
+                Some(value) => String::from(value),
+            }
+        }
+
+        let start_running_time = self.start_running_time.map(|t| t.to_string());
+        let end_running_time = self.end_running_time.map(|t| t.to_string());
+
+        [
+            Some(self.event),
+            self.start_wallclock.as_deref(),
+            self.end_wallclock.as_deref(),
+            start_running_time.as_deref(),
+            end_running_time.as_deref(),
+            self.uri.as_deref(),
+            self.title.as_deref(),
+            self.stream_id.as_deref(),
+            self.element.as_deref(),
+            self.property.as_deref(),
+            self.value.as_deref(),
+        ]
+        .into_iter()
+        .map(field)
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+struct State {
+    writer: BufWriter<File>,
+    format: AsRunLogFormat,
+    segment: Option<gst::FormattedSegment<gst::ClockTime>>,
+    /// URI reported by the last change of a watched URI property
+    current_uri: Option<String>,
+    /// Currently playing item, logged once the next stream starts or at EOS
+    item: Option<Record>,
+}
+
+impl State {
+    fn write_record(&mut self, record: &Record) -> Result<(), std::io::Error> {
+        match self.format {
+            AsRunLogFormat::JsonLines => {
+                serde_json::to_writer(&mut self.writer, record)?;
+                self.writer.write_all(b"\n")?;
+            }
+            AsRunLogFormat::Csv => {
+                writeln!(self.writer, "{}", record.to_csv())?;
+            }
+        }
+
+        self.writer.flush()
+    }
+
+    /// Logs the current item if anything of it was rendered
+    fn finish_item(&mut self) -> Result<(), std::io::Error> {
+        match self.item.take() {
+            Some(item) if item.start_running_time.is_some() => self.write_record(&item),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn now() -> Option<String> {
+    glib::DateTime::now_utc()
+        .and_then(|now| now.format_iso8601())
+        .ok()
+        .map(String::from)
+}
+
+#[derive(Default)]
+pub struct AsRunLogSink {
+    settings: Mutex<Settings>,
+    state: Mutex<Option<State>>,
+    /// Bin that is watched for property changes and the `deep-notify` handler
+    deep_notify: Mutex<Option<(glib::WeakRef<gst::Object>, glib::SignalHandlerId)>>,
+}
+
+impl AsRunLogSink {
+    fn watch_properties(&self) {
+        let obj = self.obj();
+
+        let mut top = obj.upcast_ref::<gst::Object>().clone();
+        while let Some(parent) = top.parent() {
+            top = parent;
+        }
+
+        if &top == obj.upcast_ref::<gst::Object>() {
+            gst::debug!(CAT, imp = self, "Not inside a bin, not watching properties");
+            return;
+        }
+
+        gst::debug!(CAT, imp = self, "Watching properties of {}", top.name());
+
+        let sink_weak = obj.downgrade();
+        let handler_id = top.connect_deep_notify(None, move |_, prop_object, pspec| {
+            let Some(sink) = sink_weak.upgrade() else {
+                return;
+            };
+            sink.imp().property_changed(prop_object, pspec);
+        });
+
+        *self.deep_notify.lock().unwrap() = Some((top.downgrade(), handler_id));
+    }
+
+    fn unwatch_properties(&self) {
+        if let Some((top, handler_id)) = self.deep_notify.lock().unwrap().take() {
+            if let Some(top) = top.upgrade() {
+                top.disconnect(handler_id);
+            }
+        }
+    }
+
+    fn property_changed(&self, prop_object: &gst::Object, pspec: &glib::ParamSpec) {
+        let property = pspec.name();
+
+        if !self
+            .settings
+            .lock()
+            .unwrap()
+            .watch_properties
+            .iter()
+            .any(|p| p == property)
+        {
+            return;
+        }
+
+        // Read the value before taking any of our locks, the notifying element might be
+        // holding its own locks while notifying
+        let value = prop_object.property_value(property);
+        let value = if let Ok(Some(object)) = value.get::<Option<gst::Object>>() {
+            Some(String::from(object.path_string()))
+        } else {
+            value.serialize().ok().map(String::from)
+        };
+
+        let record = Record {
+            event: "switch",
+            start_wallclock: now(),
+            start_running_time: self.obj().current_running_time().map(|t| t.nseconds()),
+            element: Some(String::from(prop_object.path_string())),
+            property: Some(String::from(property)),
+            value: value.clone(),
+            ..Default::default()
+        };
+
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Property {} of {} changed to {:?}",
+            property,
+            prop_object.path_string(),
+            value,
+        );
+
+        let mut state = self.state.lock().unwrap();
+        let Some(state) = state.as_mut() else {
+            return;
+        };
+
+        if matches!(property, "current-uri" | "uri") {
+            state.current_uri = value;
+            // The URI is usually only known after the stream started
+            if let Some(item) = state
+                .item
+                .as_mut()
+                .filter(|item| item.start_running_time.is_none())
+            {
+                item.uri = state.current_uri.clone();
+            }
+        }
+
+        if let Err(err) = state.write_record(&record) {
+            gst::warning!(CAT, imp = self, "Failed to write record: {err}");
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for AsRunLogSink {
+    const NAME: &'static str = "GstAsRunLogSink";
+    type Type = super::AsRunLogSink;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for AsRunLogSink {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecString::builder("location")
+                    .nick("File Location")
+                    .blurb("Location of the as-run log file to write")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("format", DEFAULT_FORMAT)
+                    .nick("Format")
+                    .blurb("Format of the as-run log")
+                    .mutable_ready()
+                    .build(),
+                gst::ParamSpecArray::builder("watch-properties")
+                    .nick("Watch Properties")
+                    .blurb("Names of the properties of elements in the pipeline whose changes are logged")
+                    .element_spec(
+                        &glib::ParamSpecString::builder("property-name")
+                            .nick("Property Name")
+                            .blurb("Name of a property to watch")
+                            .build(),
+                    )
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => {
+                settings.location = value.get().expect("type checked upstream");
+            }
+            "format" => {
+                settings.format = value.get().expect("type checked upstream");
+            }
+            "watch-properties" => {
+                settings.watch_properties = value
+                    .get::<gst::ArrayRef>()
+                    .expect("type checked upstream")
+                    .as_slice()
+                    .iter()
+                    .filter_map(|p| p.get::<Option<String>>().expect("type checked upstream"))
+                    .collect();
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "location" => settings.location.to_value(),
+            "format" => settings.format.to_value(),
+            "watch-properties" => {
+                gst::Array::new(settings.watch_properties.iter().map(|p| p.to_send_value()))
+                    .to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for AsRunLogSink {}
+
+impl ElementImpl for AsRunLogSink {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "As-Run Log Sink",
+                "Sink",
+                "Records what was played when into an as-run log",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::new_any(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        if transition == gst::StateChange::NullToReady {
+            self.watch_properties();
+        }
+
+        let res = self.parent_change_state(transition);
+
+        if transition == gst::StateChange::ReadyToNull
+            || (transition == gst::StateChange::NullToReady && res.is_err())
+        {
+            self.unwatch_properties();
+        }
+
+        res
+    }
+}
+
+impl BaseSinkImpl for AsRunLogSink {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let Some(location) = settings.location else {
+            return Err(gst::error_msg!(
+                gst::ResourceError::Settings,
+                ["No location set"]
+            ));
+        };
+
+        let file = File::create(&location).map_err(|err| {
+            gst::error_msg!(
+                gst::ResourceError::OpenWrite,
+                ["Could not open file {location} for writing: {err}"]
+            )
+        })?;
+
+        let mut writer = BufWriter::new(file);
+        if settings.format == AsRunLogFormat::Csv {
+            writeln!(writer, "{}", CSV_COLUMNS.join(",")).map_err(|err| {
+                gst::error_msg!(
+                    gst::ResourceError::Write,
+                    ["Failed to write CSV header: {err}"]
+                )
+            })?;
+        }
+
+        gst::debug!(CAT, imp = self, "Writing as-run log to {location}");
+
+        *self.state.lock().unwrap() = Some(State {
+            writer,
+            format: settings.format,
+            segment: None,
+            current_uri: None,
+            item: None,
+        });
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        if let Some(mut state) = self.state.lock().unwrap().take() {
+            if let Err(err) = state.finish_item() {
+                gst::warning!(CAT, imp = self, "Failed to write last item: {err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        let res = {
+            let mut state = self.state.lock().unwrap();
+
+            match (state.as_mut(), event.view()) {
+                (Some(state), gst::EventView::StreamStart(ev)) => {
+                    gst::debug!(CAT, imp = self, "Stream {} started", ev.stream_id());
+
+                    let res = state.finish_item();
+                    state.item = Some(Record {
+                        event: "item",
+                        uri: state.current_uri.clone(),
+                        stream_id: Some(String::from(ev.stream_id())),
+                        ..Default::default()
+                    });
+                    res
+                }
+                (Some(state), gst::EventView::Segment(ev)) => {
+                    state.segment = ev.segment().downcast_ref::<gst::ClockTime>().cloned();
+                    Ok(())
+                }
+                (Some(state), gst::EventView::Tag(ev)) => {
+                    if let (Some(item), Some(title)) =
+                        (state.item.as_mut(), ev.tag().get::<gst::tags::Title>())
+                    {
+                        item.title = Some(String::from(title.get()));
+                    }
+                    Ok(())
+                }
+                (Some(state), gst::EventView::Eos(_)) => state.finish_item(),
+                _ => Ok(()),
+            }
+        };
+
+        if let Err(err) = res {
+            gst::element_imp_error!(
+                self,
+                gst::ResourceError::Write,
+                ["Failed to write item: {err}"]
+            );
+        }
+
+        self.parent_event(event)
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let Some(state) = state.as_mut() else {
+            return Err(gst::FlowError::Flushing);
+        };
+
+        let Some(segment) = state.segment.as_ref() else {
+            gst::element_imp_error!(self, gst::CoreError::Clock, ["Have no time segment"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        let start = buffer.pts().and_then(|pts| segment.to_running_time(pts));
+        let end = buffer
+            .pts()
+            .opt_add(buffer.duration())
+            .and_then(|end| segment.to_running_time(end))
+            .or(start);
+
+        // Streams without stream-start events are logged as a single item
+        let item = state.item.get_or_insert_with(|| Record {
+            event: "item",
+            ..Default::default()
+        });
+
+        let now = now();
+        if item.start_running_time.is_none() {
+            item.start_running_time = Some(start.unwrap_or(gst::ClockTime::ZERO).nseconds());
+            item.start_wallclock.clone_from(&now);
+            if item.uri.is_none() {
+                item.uri.clone_from(&state.current_uri);
+            }
+        }
+        if let Some(end) = end {
+            item.end_running_time = Some(end.nseconds());
+        }
+        item.end_wallclock = now;
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,38 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstAsRunLogFormat")]
+#[non_exhaustive]
+pub enum AsRunLogFormat {
+    #[default]
+    #[enum_value(name = "JSON Lines: one JSON object per record", nick = "json-lines")]
+    JsonLines = 0,
+    #[enum_value(name = "CSV: one comma separated line per record", nick = "csv")]
+    Csv = 1,
+}
+
+glib::wrapper! {
+    pub struct AsRunLogSink(ObjectSubclass<imp::AsRunLogSink>) @extends gst_base::BaseSink, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    AsRunLogFormat::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "asrunlogsink",
+        gst::Rank::NONE,
+        AsRunLogSink::static_type(),
+    )
+}
@@ -0,0 +1,169 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+//
+
+use gst::prelude::*;
+
+use std::path::PathBuf;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstasrunlog::plugin_register_static().unwrap();
+    });
+}
+
+fn log_location(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("asrunlog-{}-{name}", std::process::id()))
+}
+
+fn read_log(location: &PathBuf) -> Vec<String> {
+    let log = std::fs::read_to_string(location).unwrap();
+    std::fs::remove_file(location).unwrap();
+
+    log.lines().map(String::from).collect()
+}
+
+/// Pushes two streams of 10 buffers of 100ms each, the second one with a title
+fn push_streams(h: &mut gst_check::Harness) {
+    h.set_src_caps_str("test/test");
+    h.play();
+
+    for (i, stream_id) in ["first", "second"].into_iter().enumerate() {
+        assert!(h.push_event(gst::event::StreamStart::new(stream_id)));
+        if i == 0 {
+            assert!(h.push_event(
+                gst::event::Segment::builder(&gst::FormattedSegment::<gst::ClockTime>::new())
+                    .build()
+            ));
+        } else {
+            let tags = gst::TagList::builder::<gst::tags::Title>("Second, \"B\"").build();
+            assert!(h.push_event(gst::event::Tag::new(tags)));
+        }
+
+        for j in 0..10 {
+            let mut buffer = gst::Buffer::with_size(1).unwrap();
+            {
+                let buffer = buffer.get_mut().unwrap();
+                buffer.set_pts(gst::ClockTime::from_mseconds((i as u64 * 10 + j) * 100));
+                buffer.set_duration(gst::ClockTime::from_mseconds(100));
+            }
+            assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+        }
+    }
+
+    assert!(h.push_event(gst::event::Eos::new()));
+}
+
+#[test]
+fn test_items_json() {
+    init();
+
+    let location = log_location("items.jsonl");
+
+    let mut h = gst_check::Harness::new("asrunlogsink");
+    {
+        let sink = h.element().unwrap();
+        sink.set_property("sync", false);
+        sink.set_property("location", location.to_str().unwrap());
+    }
+
+    push_streams(&mut h);
+    drop(h);
+
+    let records = read_log(&location)
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(records.len(), 2);
+
+    assert_eq!(records[0]["event"], "item");
+    assert_eq!(records[0]["stream_id"], "first");
+    assert_eq!(records[0]["start_running_time"], 0);
+    assert_eq!(records[0]["end_running_time"], 1_000_000_000u64);
+    assert!(records[0]["start_wallclock"].is_string());
+    assert!(records[0].get("title").is_none());
+
+    assert_eq!(records[1]["event"], "item");
+    assert_eq!(records[1]["stream_id"], "second");
+    assert_eq!(records[1]["title"], "Second, \"B\"");
+    assert_eq!(records[1]["start_running_time"], 1_000_000_000u64);
+    assert_eq!(records[1]["end_running_time"], 2_000_000_000u64);
+}
+
+#[test]
+fn test_items_csv() {
+    init();
+
+    let location = log_location("items.csv");
+
+    let mut h = gst_check::Harness::new("asrunlogsink");
+    {
+        let sink = h.element().unwrap();
+        sink.set_property("sync", false);
+        sink.set_property("location", location.to_str().unwrap());
+        sink.set_property_from_str("format", "csv");
+    }
+
+    push_streams(&mut h);
+    drop(h);
+
+    let lines = read_log(&location);
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        "event,start_wallclock,end_wallclock,start_running_time,end_running_time,uri,title,stream_id,element,property,value"
+    );
+    assert!(lines[1].starts_with("item,"));
+    assert!(lines[1].ends_with(",0,1000000000,,,first,,,"));
+    assert!(lines[2].ends_with(",1000000000,2000000000,,\"Second, \"\"B\"\"\",second,,,"));
+}
+
+#[test]
+fn test_switch() {
+    init();
+
+    let location = log_location("switch.jsonl");
+
+    let pipeline = gst::parse::launch(&format!(
+        "fakesrc num-buffers=0 ! identity name=id ! asrunlogsink sync=false location={} watch-properties=\"<silent>\"",
+        location.to_str().unwrap()
+    ))
+    .unwrap()
+    .downcast::<gst::Pipeline>()
+    .unwrap();
+
+    pipeline.set_state(gst::State::Paused).unwrap();
+    assert_eq!(
+        pipeline.state(gst::ClockTime::NONE).0,
+        Ok(gst::StateChangeSuccess::Success)
+    );
+
+    let identity = pipeline.by_name("id").unwrap();
+    identity.set_property("silent", false);
+    // Not watched
+    identity.set_property("single-segment", true);
+
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    let records = read_log(&location)
+        .iter()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(records.len(), 1);
+
+    assert_eq!(records[0]["event"], "switch");
+    assert!(records[0]["element"]
+        .as_str()
+        .unwrap()
+        .ends_with("/GstIdentity:id"));
+    assert_eq!(records[0]["property"], "silent");
+    assert_eq!(records[0]["value"], "false");
+    assert!(records[0]["start_wallclock"].is_string());
+}
@@ -31,6 +31,7 @@ static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
 struct Stats {
     num_retry: u64,
     num_fallback_retry: u64,
+    num_stream_restart: u64,
     last_retry_reason: RetryReason,
     last_fallback_retry_reason: RetryReason,
     buffering_percent: i32,
@@ -42,6 +43,7 @@ impl Default for Stats {
         Self {
             num_retry: 0,
             num_fallback_retry: 0,
+            num_stream_restart: 0,
             last_retry_reason: RetryReason::None,
             last_fallback_retry_reason: RetryReason::None,
             buffering_percent: 100,
@@ -55,6 +57,7 @@ impl Stats {
         gst::Structure::builder("application/x-fallbacksrc-stats")
             .field("num-retry", self.num_retry)
             .field("num-fallback-retry", self.num_fallback_retry)
+            .field("num-stream-restart", self.num_stream_restart)
             .field("last-retry-reason", self.last_retry_reason)
             .field(
                 "last-fallback-retry-reason",
@@ -80,6 +83,8 @@ struct Settings {
     restart_timeout: gst::ClockTime,
     retry_timeout: gst::ClockTime,
     restart_on_eos: bool,
+    restart_failed_streams: bool,
+    stream_restart_backoff: gst::ClockTime,
     min_latency: gst::ClockTime,
     buffer_duration: i64,
     immediate_fallback: bool,
@@ -100,6 +105,8 @@ impl Default for Settings {
             restart_timeout: 5.seconds(),
             retry_timeout: 60.seconds(),
             restart_on_eos: false,
+            restart_failed_streams: false,
+            stream_restart_backoff: gst::ClockTime::SECOND,
             min_latency: gst::ClockTime::ZERO,
             buffer_duration: -1,
             immediate_fallback: false,
@@ -164,6 +171,17 @@ struct Stream {
     filter_caps: gst::Caps,
 }
 
+// Decoder of the main source that failed and is restarted without restarting the whole source
+struct FailedDecoder {
+    decoder: gst::Element,
+    // Pad upstream of the decoder, dropping all data until the decoder is restarted
+    upstream_srcpad: gst::Pad,
+    probe_id: gst::PadProbeId,
+    // Sticky events from upstream for configuring the decoder after restarting it
+    sticky_events: Vec<gst::Event>,
+    restart_timeout: gst::SingleShotClockId,
+}
+
 struct SourceBin {
     // uridecodebin3 or custom source element inside a bin.
     //
@@ -183,6 +201,9 @@ struct SourceBin {
 
     // Stream collection posted by source
     streams: Option<gst::StreamCollection>,
+
+    // Decoders that are restarted on their own, see `restart-failed-streams`
+    failed_decoders: Vec<FailedDecoder>,
 }
 
 struct State {
@@ -286,6 +307,19 @@ impl ObjectImpl for FallbackSrc {
                     .default_value(false)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoolean::builder("restart-failed-streams")
+                    .nick("Restart Failed Streams")
+                    .blurb("Only restart the decoder of a failed stream instead of the whole source")
+                    .default_value(false)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("stream-restart-backoff")
+                    .nick("Stream Restart Backoff")
+                    .blurb("Time to wait before restarting the decoder of a failed stream")
+                    .maximum(u64::MAX - 1)
+                    .default_value(*gst::ClockTime::SECOND)
+                    .mutable_ready()
+                    .build(),
                 glib::ParamSpecEnum::builder_with_default("status", Status::Stopped)
                     .nick("Status")
                     .blurb("Current source status")
@@ -474,6 +508,30 @@ impl ObjectImpl for FallbackSrc {
                 );
                 settings.buffer_duration = new_value;
             }
+            "restart-failed-streams" => {
+                let mut settings = self.settings.lock();
+                let new_value = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp = self,
+                    "Changing restart-failed-streams from {:?} to {:?}",
+                    settings.restart_failed_streams,
+                    new_value,
+                );
+                settings.restart_failed_streams = new_value;
+            }
+            "stream-restart-backoff" => {
+                let mut settings = self.settings.lock();
+                let new_value = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp = self,
+                    "Changing Stream Restart Backoff from {:?} to {:?}",
+                    settings.stream_restart_backoff,
+                    new_value,
+                );
+                settings.stream_restart_backoff = new_value;
+            }
             "immediate-fallback" => {
                 let mut settings = self.settings.lock();
                 let new_value = value.get().expect("type checked upstream");
@@ -573,6 +631,14 @@ impl ObjectImpl for FallbackSrc {
                 let settings = self.settings.lock();
                 settings.restart_on_eos.to_value()
             }
+            "restart-failed-streams" => {
+                let settings = self.settings.lock();
+                settings.restart_failed_streams.to_value()
+            }
+            "stream-restart-backoff" => {
+                let settings = self.settings.lock();
+                settings.stream_restart_backoff.to_value()
+            }
             "status" => {
                 let state_guard = self.state.lock();
 
@@ -1077,6 +1143,7 @@ impl FallbackSrc {
             pending_restart_timeout: None,
             retry_timeout: None,
             streams: None,
+            failed_decoders: Vec::new(),
         }
     }
 
@@ -1150,6 +1217,7 @@ impl FallbackSrc {
             pending_restart_timeout: None,
             retry_timeout: None,
             streams: None,
+            failed_decoders: Vec::new(),
         })
     }
 
@@ -1409,6 +1477,10 @@ impl FallbackSrc {
             if let Some(timeout) = source.restart_timeout.take() {
                 timeout.unschedule();
             }
+
+            for failed_decoder in source.failed_decoders.drain(..) {
+                failed_decoder.restart_timeout.unschedule();
+            }
         }
 
         for source in [
@@ -2803,6 +2875,12 @@ impl FallbackSrc {
         );
 
         if src == &state.source.source || src.has_as_ancestor(&state.source.source) {
+            if state.settings.restart_failed_streams && self.handle_decoder_error(state, src) {
+                drop(state_guard);
+                self.obj().notify("statistics");
+                return true;
+            }
+
             self.handle_source_error(state, RetryReason::Error, false);
             drop(state_guard);
             self.obj().notify("status");
@@ -2831,6 +2909,166 @@ impl FallbackSrc {
         false
     }
 
+    // Handles an error of a decoder inside the main source by restarting only that decoder after
+    // the stream restart backoff. Returns `false` if the whole source has to be restarted instead.
+    fn handle_decoder_error(&self, state: &mut State, src: &gst::Element) -> bool {
+        if !src
+            .factory()
+            .is_some_and(|factory| factory.has_type(gst::ElementFactoryType::DECODER))
+        {
+            return false;
+        }
+
+        if state.source.pending_restart {
+            return false;
+        }
+
+        if state
+            .source
+            .failed_decoders
+            .iter()
+            .any(|failed_decoder| &failed_decoder.decoder == src)
+        {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Decoder {} is already pending restart",
+                src.path_string()
+            );
+            return true;
+        }
+
+        let Some(upstream_srcpad) = src.static_pad("sink").and_then(|pad| pad.peer()) else {
+            return false;
+        };
+
+        let mut sticky_events = Vec::new();
+        upstream_srcpad.sticky_events_foreach(|event| {
+            if event.type_() != gst::EventType::Eos {
+                sticky_events.push(event.clone());
+            }
+            std::ops::ControlFlow::Continue(gst::EventForeachAction::Keep)
+        });
+
+        // Drop everything for the decoder until it is restarted so upstream keeps running
+        let Some(probe_id) = upstream_srcpad
+            .add_probe(gst::PadProbeType::DATA_DOWNSTREAM, |_, _| {
+                gst::PadProbeReturn::Drop
+            })
+        else {
+            return false;
+        };
+
+        // Upstream got an error flow return from the decoder and needs to be flushed to recover
+        if let Some(upstream_sinkpad) = upstream_srcpad
+            .iterate_internal_links()
+            .into_iter()
+            .flatten()
+            .next()
+        {
+            self.obj().call_async(move |_element| {
+                upstream_sinkpad.send_event(gst::event::FlushStart::new());
+                upstream_sinkpad.send_event(gst::event::FlushStop::new(false));
+            });
+        }
+
+        let clock = gst::SystemClock::obtain();
+        let wait_time = clock.time().unwrap() + state.settings.stream_restart_backoff;
+        gst::info!(
+            CAT,
+            imp = self,
+            "Decoder {} failed, restarting it at {}",
+            src.path_string(),
+            wait_time,
+        );
+
+        let timeout = clock.new_single_shot_id(wait_time);
+        let element_weak = self.obj().downgrade();
+        let decoder_weak = src.downgrade();
+        timeout
+            .wait_async(move |_clock, _time, _id| {
+                let (Some(element), Some(decoder)) =
+                    (element_weak.upgrade(), decoder_weak.upgrade())
+                else {
+                    return;
+                };
+
+                element.call_async(move |element| {
+                    element.imp().restart_decoder(&decoder);
+                });
+            })
+            .expect("Failed to wait async");
+
+        state.stats.num_stream_restart += 1;
+        state.source.failed_decoders.push(FailedDecoder {
+            decoder: src.clone(),
+            upstream_srcpad,
+            probe_id,
+            sticky_events,
+            restart_timeout: timeout,
+        });
+
+        true
+    }
+
+    fn restart_decoder(&self, decoder: &gst::Element) {
+        let mut state_guard = self.state.lock();
+        let Some(state) = &mut *state_guard else {
+            return;
+        };
+        let Some(idx) = state
+            .source
+            .failed_decoders
+            .iter()
+            .position(|failed_decoder| &failed_decoder.decoder == decoder)
+        else {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Restarting decoder {} not needed anymore",
+                decoder.path_string()
+            );
+            return;
+        };
+        let failed_decoder = state.source.failed_decoders.remove(idx);
+        drop(state_guard);
+
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Restarting decoder {}",
+            decoder.path_string()
+        );
+
+        if decoder.set_state(gst::State::Null).is_err() || decoder.sync_state_with_parent().is_err()
+        {
+            gst::warning!(
+                CAT,
+                imp = self,
+                "Failed to restart decoder {}, restarting source",
+                decoder.path_string()
+            );
+
+            let mut state_guard = self.state.lock();
+            if let Some(state) = &mut *state_guard {
+                self.handle_source_error(state, RetryReason::StateChangeFailure, false);
+            }
+            drop(state_guard);
+            self.obj().notify("status");
+            self.obj().notify("statistics");
+            return;
+        }
+
+        // Configure the new decoder before letting data flow again
+        let sinkpad = decoder.static_pad("sink").unwrap();
+        for event in failed_decoder.sticky_events {
+            let _ = sinkpad.send_event(event);
+        }
+        failed_decoder
+            .upstream_srcpad
+            .remove_probe(failed_decoder.probe_id);
+    }
+
     fn handle_source_error(&self, state: &mut State, reason: RetryReason, fallback_source: bool) {
         gst::debug!(
             CAT,
@@ -2874,6 +3112,14 @@ impl FallbackSrc {
             timeout.unschedule();
         }
 
+        // The whole source is restarted, including all failed decoders
+        for failed_decoder in source.failed_decoders.drain(..) {
+            failed_decoder.restart_timeout.unschedule();
+            failed_decoder
+                .upstream_srcpad
+                .remove_probe(failed_decoder.probe_id);
+        }
+
         // Prevent state changes from changing the state in an uncoordinated way
         source.pending_restart = true;
 
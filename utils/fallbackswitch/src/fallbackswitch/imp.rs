@@ -19,9 +19,12 @@ use std::sync::atomic::{AtomicU32, Ordering};
 
 const PROP_PRIORITY: &str = "priority";
 const PROP_IS_HEALTHY: &str = "is-healthy";
+const PROP_LATENCY_OFFSET: &str = "latency-offset";
+const PROP_MEASURED_OFFSET: &str = "measured-offset";
 
 const PROP_ACTIVE_PAD: &str = "active-pad";
 const PROP_AUTO_SWITCH: &str = "auto-switch";
+const PROP_AUTO_ALIGN: &str = "auto-align";
 const PROP_IMMEDIATE_FALLBACK: &str = "immediate-fallback";
 const PROP_LATENCY: &str = "latency";
 const PROP_MIN_UPSTREAM_LATENCY: &str = "min-upstream-latency";
@@ -59,6 +62,7 @@ struct Settings {
     min_upstream_latency: gst::ClockTime,
     immediate_fallback: bool,
     auto_switch: bool,
+    auto_align: bool,
     stop_on_eos: bool,
 }
 
@@ -70,6 +74,7 @@ impl Default for Settings {
             min_upstream_latency: gst::ClockTime::ZERO,
             immediate_fallback: false,
             auto_switch: true,
+            auto_align: false,
             stop_on_eos: false,
         }
     }
@@ -166,6 +171,18 @@ impl ObjectImpl for FallbackSwitchSinkPad {
                     .default_value(false)
                     .read_only()
                     .build(),
+                glib::ParamSpecInt64::builder(PROP_LATENCY_OFFSET)
+                    .nick("Latency Offset")
+                    .blurb("Offset applied to the running time of this stream, e.g. to delay an input that is ahead of the others. Positive offsets require enough buffering upstream (in nanoseconds)")
+                    .default_value(SinkSettings::default().latency_offset)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecInt64::builder(PROP_MEASURED_OFFSET)
+                    .nick("Measured Offset")
+                    .blurb("Offset of this stream relative to the most delayed stream, measured from the timecodes of both streams. Applied in addition to latency-offset if auto-align is enabled (in nanoseconds)")
+                    .default_value(0)
+                    .read_only()
+                    .build(),
             ]
         });
 
@@ -179,6 +196,11 @@ impl ObjectImpl for FallbackSwitchSinkPad {
                 let priority = value.get().expect("type checked upstream");
                 settings.priority = priority;
             }
+            PROP_LATENCY_OFFSET => {
+                let mut settings = self.settings.lock();
+                let latency_offset = value.get().expect("type checked upstream");
+                settings.latency_offset = latency_offset;
+            }
             _ => unimplemented!(),
         }
     }
@@ -193,6 +215,17 @@ impl ObjectImpl for FallbackSwitchSinkPad {
                 let state = self.state.lock();
                 state.is_healthy.to_value()
             }
+            PROP_LATENCY_OFFSET => {
+                let settings = self.settings.lock();
+                settings.latency_offset.to_value()
+            }
+            PROP_MEASURED_OFFSET => {
+                let obj = self.obj();
+                obj.parent()
+                    .and_downcast::<super::FallbackSwitch>()
+                    .map_or(0, |parent| parent.imp().alignment_offset(&obj))
+                    .to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -205,6 +238,7 @@ impl FallbackSwitchSinkPad {}
 #[derive(Clone, Debug, Default)]
 struct SinkSettings {
     priority: u32,
+    latency_offset: i64,
 }
 
 #[derive(Debug)]
@@ -215,6 +249,9 @@ struct SinkState {
     caps_info: CapsInfo,
 
     current_running_time: Option<gst::ClockTime>,
+    /// Difference between the running time and the timecode of the first buffer with a
+    /// timecode. Only measured once so that the alignment does not jitter.
+    timecode_offset: Option<i64>,
     flushing: bool,
     clock_id: Option<gst::SingleShotClockId>,
     /// true if the sink pad has received eos
@@ -230,6 +267,7 @@ impl Default for SinkState {
             caps_info: CapsInfo::None,
 
             current_running_time: gst::ClockTime::NONE,
+            timecode_offset: None,
             flushing: false,
             clock_id: None,
             eos: false,
@@ -252,9 +290,25 @@ impl SinkState {
     fn reset(&mut self) {
         self.flushing = false;
         self.caps_info = CapsInfo::None;
+        self.timecode_offset = None;
         self.eos = false;
     }
 
+    fn measure_timecode_offset(&mut self, buffer: &gst::BufferRef) {
+        if self.timecode_offset.is_some() {
+            return;
+        }
+        let Some(meta) = buffer.meta::<gst_video::VideoTimeCodeMeta>() else {
+            return;
+        };
+        let Some(running_time) = self.segment.to_running_time(buffer.pts()) else {
+            return;
+        };
+
+        let timecode = meta.tc().time_since_daily_jam();
+        self.timecode_offset = Some(running_time.nseconds() as i64 - timecode.nseconds() as i64);
+    }
+
     fn clip_buffer(&self, mut buffer: gst::Buffer) -> Option<gst::Buffer> {
         match &self.caps_info {
             CapsInfo::Audio(audio_info) => gst_audio::audio_buffer_clip(
@@ -311,6 +365,7 @@ impl SinkState {
     fn get_sync_time(
         &self,
         buffer: &gst::Buffer,
+        offset: i64,
     ) -> (Option<gst::ClockTime>, Option<gst::ClockTime>) {
         let last_ts = self.current_running_time;
         let duration = buffer.duration().unwrap_or(gst::ClockTime::ZERO);
@@ -323,13 +378,29 @@ impl SinkState {
 
         match self.segment.clip(start_ts, end_ts) {
             Some((start_ts, end_ts)) => (
-                self.segment.to_running_time(start_ts),
-                self.segment.to_running_time(end_ts),
+                offset_running_time(self.segment.to_running_time(start_ts), offset),
+                offset_running_time(self.segment.to_running_time(end_ts), offset),
             ),
             None => (None, None),
         }
     }
 
+    /// Shift the timestamps of an outgoing buffer by `offset` in running time
+    fn apply_offset(&self, buffer: &mut gst::BufferRef, offset: i64) {
+        if offset == 0 {
+            return;
+        }
+
+        let shift = |ts: Option<gst::ClockTime>| {
+            let running_time = offset_running_time(self.segment.to_running_time(ts), offset);
+            self.segment.position_from_running_time(running_time).or(ts)
+        };
+
+        let (pts, dts) = (shift(buffer.pts()), shift(buffer.dts()));
+        buffer.set_pts(pts);
+        buffer.set_dts(dts);
+    }
+
     fn schedule_clock(
         &mut self,
         imp: &FallbackSwitch,
@@ -423,6 +494,31 @@ impl SinkState {
     }
 }
 
+/// Timecodes are relative to the daily jam and wrap around at midnight
+const TIMECODE_DAY_NS: i64 = 24 * 60 * 60 * 1_000_000_000;
+
+/// Difference of two timecode offsets, taking the shortest way around midnight
+fn timecode_offset_diff(a: i64, b: i64) -> i64 {
+    let diff = (a - b).rem_euclid(TIMECODE_DAY_NS);
+    if diff > TIMECODE_DAY_NS / 2 {
+        diff - TIMECODE_DAY_NS
+    } else {
+        diff
+    }
+}
+
+fn offset_running_time(
+    running_time: Option<gst::ClockTime>,
+    offset: i64,
+) -> Option<gst::ClockTime> {
+    let running_time = running_time?;
+    if offset >= 0 {
+        Some(running_time.saturating_add(gst::ClockTime::from_nseconds(offset as u64)))
+    } else {
+        Some(running_time.saturating_sub(gst::ClockTime::from_nseconds(offset.unsigned_abs())))
+    }
+}
+
 #[derive(Debug)]
 pub struct FallbackSwitch {
     state: Mutex<State>,
@@ -457,6 +553,29 @@ impl FallbackSwitch {
         debug!(CAT, obj = pad, "Now active pad");
     }
 
+    /// Offset needed to align the given pad with the most delayed pad, based on the
+    /// timecodes measured on all pads. Pads without timecodes are not aligned.
+    fn alignment_offset(&self, pad: &super::FallbackSwitchSinkPad) -> i64 {
+        let Some(pad_offset) = pad.imp().state.lock().timecode_offset else {
+            return 0;
+        };
+
+        self.obj()
+            .sink_pads()
+            .iter()
+            .filter_map(|other| {
+                other
+                    .downcast_ref::<super::FallbackSwitchSinkPad>()
+                    .unwrap()
+                    .imp()
+                    .state
+                    .lock()
+                    .timecode_offset
+            })
+            .map(|other_offset| timecode_offset_diff(other_offset, pad_offset))
+            .fold(0, i64::max)
+    }
+
     fn handle_timeout(&self, state: &mut State, settings: &Settings) {
         debug!(
             CAT,
@@ -663,7 +782,7 @@ impl FallbackSwitch {
         }
 
         let mut buffer = {
-            let pad_state = pad_imp.state.lock();
+            let mut pad_state = pad_imp.state.lock();
             trace!(
                 CAT,
                 obj = pad,
@@ -672,7 +791,10 @@ impl FallbackSwitch {
                 pad_state.segment,
             );
             match pad_state.clip_buffer(buffer) {
-                Some(buffer) => buffer,
+                Some(buffer) => {
+                    pad_state.measure_timecode_offset(&buffer);
+                    buffer
+                }
                 None => {
                     log!(
                         CAT,
@@ -728,9 +850,14 @@ impl FallbackSwitch {
             }
         }
 
+        let mut offset = pad_imp.settings.lock().latency_offset;
+        if settings.auto_align {
+            offset = offset.saturating_add(self.alignment_offset(pad));
+        }
+
         let mut pad_state = pad_imp.state.lock();
         let raw_pad = !matches!(pad_state.caps_info, CapsInfo::None);
-        let (start_running_time, end_running_time) = pad_state.get_sync_time(&buffer);
+        let (start_running_time, end_running_time) = pad_state.get_sync_time(&buffer, offset);
 
         if let Some(running_time) = start_running_time {
             pad_state.current_running_time = Some(running_time);
@@ -969,6 +1096,11 @@ impl FallbackSwitch {
             self.obj().notify(PROP_ACTIVE_PAD);
         }
 
+        if offset != 0 {
+            let pad_state = pad_imp.state.lock();
+            pad_state.apply_offset(buffer.make_mut(), offset);
+        }
+
         if discont_pending && !buffer.flags().contains(gst::BufferFlags::DISCONT) {
             let buffer = buffer.make_mut();
             buffer.set_flags(gst::BufferFlags::DISCONT);
@@ -1359,6 +1491,12 @@ impl ObjectImpl for FallbackSwitch {
                     .default_value(Settings::default().auto_switch)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecBoolean::builder(PROP_AUTO_ALIGN)
+                    .nick("Automatically align inputs")
+                    .blurb("Automatically delay inputs that are ahead of the others, as measured from their timecodes, so that switching between them does not jump in time")
+                    .default_value(Settings::default().auto_align)
+                    .mutable_playing()
+                    .build(),
                 glib::ParamSpecBoolean::builder(PROP_STOP_ON_EOS)
                     .nick("stop on EOS")
                     .blurb("Stop forwarding buffers as soon as one input pad is eos")
@@ -1438,6 +1576,11 @@ impl ObjectImpl for FallbackSwitch {
                 let new_value = value.get().expect("type checked upstream");
                 settings.auto_switch = new_value;
             }
+            PROP_AUTO_ALIGN => {
+                let mut settings = self.settings.lock();
+                let new_value = value.get().expect("type checked upstream");
+                settings.auto_align = new_value;
+            }
             PROP_STOP_ON_EOS => {
                 let mut settings = self.settings.lock();
                 let new_value = value.get().expect("type checked upstream");
@@ -1473,6 +1616,10 @@ impl ObjectImpl for FallbackSwitch {
                 let settings = self.settings.lock();
                 settings.auto_switch.to_value()
             }
+            PROP_AUTO_ALIGN => {
+                let settings = self.settings.lock();
+                settings.auto_align.to_value()
+            }
             PROP_STOP_ON_EOS => {
                 let settings = self.settings.lock();
                 settings.stop_on_eos.to_value()
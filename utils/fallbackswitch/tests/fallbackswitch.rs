@@ -441,6 +441,101 @@ fn test_manual_switch(live: bool) {
     stop_pipeline(pipeline);
 }
 
+#[test]
+fn test_latency_offset() {
+    let pipeline = setup_pipeline(None, None, None);
+    let switch = pipeline.by_name("switch").unwrap();
+    let mainsink = switch.static_pad("sink_0").unwrap();
+    mainsink.set_property("latency-offset", 1.seconds().nseconds() as i64);
+
+    push_buffer(&pipeline, gst::ClockTime::ZERO);
+    set_time(&pipeline, 1.seconds() + LATENCY);
+    let buffer = pull_buffer(&pipeline);
+    assert_buffer!(buffer, Some(gst::ClockTime::SECOND));
+
+    push_buffer(&pipeline, 1.seconds());
+    set_time(&pipeline, 2.seconds() + LATENCY);
+    let buffer = pull_buffer(&pipeline);
+    assert_buffer!(buffer, Some(2.seconds()));
+
+    // No timecodes, so nothing to align
+    assert_eq!(mainsink.property::<i64>("measured-offset"), 0);
+
+    drop(mainsink);
+    drop(switch);
+    push_eos(&pipeline);
+    wait_eos(&pipeline);
+
+    stop_pipeline(pipeline);
+}
+
+#[test]
+fn test_timecode_alignment() {
+    // The fallback is one second ahead of the main stream
+    test_timecode_alignment_with((10, 0, 0), (10, 0, 1));
+}
+
+#[test]
+fn test_timecode_alignment_midnight() {
+    // The fallback is one second ahead of the main stream, across midnight
+    test_timecode_alignment_with((23, 59, 59), (0, 0, 0));
+}
+
+fn test_timecode_alignment_with(
+    main_timecode: (u32, u32, u32),
+    fallback_timecode: (u32, u32, u32),
+) {
+    let pipeline = setup_pipeline(Some(true), None, None);
+    let switch = pipeline.by_name("switch").unwrap();
+    switch.set_property("auto-align", true);
+    let mainsink = switch.static_pad("sink_0").unwrap();
+    let fallbacksink = switch.static_pad("sink_1").unwrap();
+
+    push_timecode_buffer(&pipeline, "src", gst::ClockTime::ZERO, main_timecode);
+    push_timecode_buffer(
+        &pipeline,
+        "fallback-src",
+        gst::ClockTime::ZERO,
+        fallback_timecode,
+    );
+    set_time(&pipeline, LATENCY);
+    let buffer = pull_buffer(&pipeline);
+    assert_buffer!(buffer, Some(gst::ClockTime::ZERO));
+
+    // Wait for the fallback buffer to be handled
+    for _ in 0..100 {
+        if fallbacksink.property::<i64>("measured-offset") != 0 {
+            break;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    assert_eq!(
+        fallbacksink.property::<i64>("measured-offset"),
+        gst::ClockTime::SECOND.nseconds() as i64
+    );
+    assert_eq!(mainsink.property::<i64>("measured-offset"), 0);
+
+    // Later timecodes are not measured again, this one is one second off
+    push_timecode_buffer(&pipeline, "src", 1.seconds(), main_timecode);
+    set_time(&pipeline, 1.seconds() + LATENCY);
+    let buffer = pull_buffer(&pipeline);
+    assert_buffer!(buffer, Some(gst::ClockTime::SECOND));
+    assert_eq!(
+        fallbacksink.property::<i64>("measured-offset"),
+        gst::ClockTime::SECOND.nseconds() as i64
+    );
+    assert_eq!(mainsink.property::<i64>("measured-offset"), 0);
+
+    drop(mainsink);
+    drop(fallbacksink);
+    drop(switch);
+    push_eos(&pipeline);
+    push_fallback_eos(&pipeline);
+    wait_eos(&pipeline);
+
+    stop_pipeline(pipeline);
+}
+
 struct Pipeline {
     pipeline: gst::Pipeline,
     clock_join_handle: Option<std::thread::JoinHandle<()>>,
@@ -622,6 +717,41 @@ fn push_fallback_buffer(pipeline: &Pipeline, time: gst::ClockTime) {
     src.push_buffer(buffer).unwrap();
 }
 
+fn push_timecode_buffer(
+    pipeline: &Pipeline,
+    src: &str,
+    time: gst::ClockTime,
+    (hours, minutes, seconds): (u32, u32, u32),
+) {
+    let src = pipeline
+        .by_name(src)
+        .unwrap()
+        .downcast::<gst_app::AppSrc>()
+        .unwrap();
+    let size = if src.name() == "src" {
+        320 * 240 * 4
+    } else {
+        160 * 120 * 4
+    };
+    let timecode = gst_video::VideoTimeCode::new(
+        gst::Fraction::new(25, 1),
+        None,
+        gst_video::VideoTimeCodeFlags::empty(),
+        hours,
+        minutes,
+        seconds,
+        0,
+        0,
+    );
+    let mut buffer = gst::Buffer::with_size(size).unwrap();
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(time);
+        gst_video::VideoTimeCodeMeta::add(buffer, &timecode.try_into().unwrap());
+    }
+    src.push_buffer(buffer).unwrap();
+}
+
 fn push_eos(pipeline: &Pipeline) {
     let src = pipeline
         .by_name("src")
@@ -0,0 +1,519 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-segmentlooper
+ *
+ * #segmentlooper plays back a range of the stream, delimited by running times, in a loop
+ * without requiring any seeking support from upstream.
+ *
+ * All buffers inside the configured range are stored in memory while they pass through the
+ * element for the first time. Once the end of the range is reached, or upstream is EOS, the
+ * element stops accepting data from upstream and repeats the stored buffers from a separate
+ * streaming thread. The timestamps of every repetition are offset by the duration of the range
+ * so that the output is continuous.
+ *
+ * The range always starts at a buffer without the DELTA_UNIT flag so that encoded streams can
+ * be looped too. The output is always in a TIME segment that starts at zero and the timestamps
+ * of all buffers are running times.
+ *
+ * Caps changes inside the looped range are not supported.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 videotestsrc is-live=true ! x264enc ! segmentlooper start=2000000000 stop=7000000000 ! decodebin ! autovideosink
+ * ]|
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "segmentlooper",
+        gst::DebugColorFlags::empty(),
+        Some("Segment Looper Element"),
+    )
+});
+
+const DEFAULT_START: gst::ClockTime = gst::ClockTime::ZERO;
+const DEFAULT_STOP: Option<gst::ClockTime> = None;
+const DEFAULT_ITERATIONS: u32 = 0;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    start: gst::ClockTime,
+    stop: Option<gst::ClockTime>,
+    iterations: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            start: DEFAULT_START,
+            stop: DEFAULT_STOP,
+            iterations: DEFAULT_ITERATIONS,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Upstream segment
+    segment: Option<gst::FormattedSegment<gst::ClockTime>>,
+    /// Whether our own segment was already pushed downstream
+    segment_pushed: bool,
+    /// Buffers of the looped range, with running time timestamps
+    buffers: Vec<gst::Buffer>,
+    /// Running time of the first stored buffer
+    loop_start: Option<gst::ClockTime>,
+    /// Running time of the end of the range, set once looping started
+    loop_end: Option<gst::ClockTime>,
+    /// Number of times the range was played so far, including the current one
+    iteration: u32,
+    /// Index of the next stored buffer to push
+    position: usize,
+}
+
+impl State {
+    fn is_looping(&self) -> bool {
+        self.loop_end.is_some()
+    }
+
+    fn stored_end(&self) -> Option<gst::ClockTime> {
+        self.buffers
+            .iter()
+            .filter_map(|buffer| {
+                buffer
+                    .pts()
+                    .or(buffer.dts())
+                    .map(|ts| ts + buffer.duration().unwrap_or(gst::ClockTime::ZERO))
+            })
+            .max()
+    }
+}
+
+pub struct SegmentLooper {
+    sinkpad: gst::Pad,
+    srcpad: gst::Pad,
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl SegmentLooper {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        mut buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+        let mut state = self.state.lock().unwrap();
+
+        if state.is_looping() {
+            gst::trace!(CAT, obj = pad, "Already looping, dropping {buffer:?}");
+            return Err(gst::FlowError::Eos);
+        }
+
+        let Some(segment) = state.segment.as_ref() else {
+            gst::element_imp_error!(self, gst::StreamError::Failed, ["Received no segment"]);
+            return Err(gst::FlowError::Error);
+        };
+
+        let pts = buffer.pts().and_then(|pts| segment.to_running_time(pts));
+        let dts = buffer.dts().and_then(|dts| segment.to_running_time(dts));
+        let running_time = dts.or(pts);
+
+        {
+            let buffer = buffer.make_mut();
+            buffer.set_pts(pts);
+            buffer.set_dts(dts);
+        }
+
+        if let Some((stop, running_time)) = Option::zip(settings.stop, running_time) {
+            if running_time >= stop && state.loop_start.is_some() {
+                gst::debug!(
+                    CAT,
+                    obj = pad,
+                    "Reached end of range at {running_time}, starting to loop"
+                );
+                state.loop_end = Some(stop);
+                drop(state);
+
+                self.start_task()?;
+                return Err(gst::FlowError::Eos);
+            }
+        }
+
+        if state.loop_start.is_none()
+            && running_time.is_some_and(|running_time| running_time >= settings.start)
+            && !buffer.flags().contains(gst::BufferFlags::DELTA_UNIT)
+        {
+            gst::debug!(
+                CAT,
+                obj = pad,
+                "Starting range at {}",
+                running_time.display()
+            );
+            state.loop_start = running_time;
+        }
+
+        if state.loop_start.is_some() {
+            state.buffers.push(buffer.clone());
+        }
+
+        let segment_pushed = std::mem::replace(&mut state.segment_pushed, true);
+        drop(state);
+
+        if !segment_pushed {
+            let segment = gst::FormattedSegment::<gst::ClockTime>::new();
+            self.srcpad
+                .push_event(gst::event::Segment::builder(&segment).build());
+        }
+
+        gst::trace!(CAT, obj = pad, "Forwarding {buffer:?}");
+        self.srcpad.push(buffer)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {event:?}");
+
+        match event.view() {
+            EventView::FlushStart(_) => {
+                let _ = self.srcpad.pause_task();
+            }
+            EventView::FlushStop(_) => {
+                let _ = self.srcpad.stop_task();
+                *self.state.lock().unwrap() = State::default();
+            }
+            EventView::Segment(e) => {
+                let mut state = self.state.lock().unwrap();
+                let Some(segment) = e.segment().downcast_ref::<gst::ClockTime>() else {
+                    drop(state);
+                    gst::element_imp_error!(
+                        self,
+                        gst::StreamError::Format,
+                        ["Only TIME segments are supported"]
+                    );
+                    return false;
+                };
+
+                if !state.is_looping() {
+                    state.segment = Some(segment.clone());
+                }
+
+                return true;
+            }
+            EventView::Eos(_) => {
+                let mut state = self.state.lock().unwrap();
+                if state.is_looping() {
+                    return true;
+                }
+
+                if state.loop_start.is_some() {
+                    let end = state.stored_end();
+                    gst::debug!(
+                        CAT,
+                        obj = pad,
+                        "Received EOS, starting to loop until {}",
+                        end.display()
+                    );
+                    state.loop_end = end.or(state.loop_start);
+                    drop(state);
+
+                    return self.start_task().is_ok();
+                }
+            }
+            _ => {
+                if event.is_serialized() && self.state.lock().unwrap().is_looping() {
+                    gst::debug!(CAT, obj = pad, "Already looping, dropping {event:?}");
+                    return true;
+                }
+            }
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    fn src_activatemode(
+        &self,
+        _pad: &gst::Pad,
+        _mode: gst::PadMode,
+        active: bool,
+    ) -> Result<(), gst::LoggableError> {
+        if !active {
+            self.srcpad.stop_task()?;
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        Ok(())
+    }
+
+    fn start_task(&self) -> Result<(), gst::FlowError> {
+        {
+            let mut state = self.state.lock().unwrap();
+            state.iteration = 1;
+            state.position = 0;
+        }
+
+        let imp = self.ref_counted();
+        self.srcpad
+            .start_task(move || imp.src_loop())
+            .map_err(|err| {
+                gst::error!(CAT, imp = self, "Failed to start task: {err}");
+                gst::FlowError::Error
+            })
+    }
+
+    fn src_loop(&self) {
+        let iterations = self.settings.lock().unwrap().iterations;
+        let mut state = self.state.lock().unwrap();
+
+        let (Some(loop_start), Some(loop_end)) = (state.loop_start, state.loop_end) else {
+            drop(state);
+            let _ = self.srcpad.pause_task();
+            return;
+        };
+        let duration = loop_end.saturating_sub(loop_start);
+
+        if state.position >= state.buffers.len() {
+            state.position = 0;
+            state.iteration = state.iteration.saturating_add(1);
+        }
+
+        if duration.is_zero() || (iterations != 0 && state.iteration >= iterations) {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Finished looping after {} iterations",
+                state.iteration
+            );
+            drop(state);
+            self.srcpad.push_event(gst::event::Eos::new());
+            let _ = self.srcpad.pause_task();
+            return;
+        }
+
+        let offset = duration * state.iteration as u64;
+        let mut buffer = state.buffers[state.position].clone();
+        let discont = state.position == 0;
+        state.position += 1;
+        drop(state);
+
+        {
+            let buffer = buffer.make_mut();
+            buffer.set_pts(buffer.pts().map(|pts| pts + offset));
+            buffer.set_dts(buffer.dts().map(|dts| dts + offset));
+            if discont {
+                buffer.set_flags(gst::BufferFlags::DISCONT);
+            }
+        }
+
+        gst::trace!(CAT, imp = self, "Pushing looped {buffer:?}");
+
+        match self.srcpad.push(buffer) {
+            Ok(_) => (),
+            Err(gst::FlowError::Flushing) => {
+                gst::debug!(CAT, imp = self, "Flushing, pausing task");
+                let _ = self.srcpad.pause_task();
+            }
+            Err(gst::FlowError::Eos) => {
+                gst::debug!(CAT, imp = self, "Downstream is EOS, pausing task");
+                self.srcpad.push_event(gst::event::Eos::new());
+                let _ = self.srcpad.pause_task();
+            }
+            Err(err) => {
+                gst::element_imp_error!(
+                    self,
+                    gst::StreamError::Failed,
+                    ["Streaming stopped, reason: {err}"]
+                );
+                self.srcpad.push_event(gst::event::Eos::new());
+                let _ = self.srcpad.pause_task();
+            }
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for SegmentLooper {
+    const NAME: &'static str = "GstSegmentLooper";
+    type Type = super::SegmentLooper;
+    type ParentType = gst::Element;
+
+    fn with_class(class: &Self::Class) -> Self {
+        let sinkpad = gst::Pad::builder_from_template(&class.pad_template("sink").unwrap())
+            .chain_function(|pad, parent, buffer| {
+                Self::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |looper| looper.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Self::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |looper| looper.sink_event(pad, event),
+                )
+            })
+            .flags(gst::PadFlags::PROXY_CAPS | gst::PadFlags::PROXY_ALLOCATION)
+            .build();
+
+        let srcpad = gst::Pad::builder_from_template(&class.pad_template("src").unwrap())
+            .activatemode_function(|pad, parent, mode, active| {
+                Self::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::loggable_error!(CAT, "src_activatemode panicked")),
+                    |looper| looper.src_activatemode(pad, mode, active),
+                )
+            })
+            .flags(gst::PadFlags::PROXY_CAPS | gst::PadFlags::PROXY_ALLOCATION)
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            settings: Mutex::new(Settings::default()),
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for SegmentLooper {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecUInt64::builder("start")
+                    .nick("Start")
+                    .blurb("Running time at which the looped range starts")
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_START.nseconds())
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("stop")
+                    .nick("Stop")
+                    .blurb("Running time at which the looped range stops (-1 = until EOS)")
+                    .default_value(DEFAULT_STOP.map_or(u64::MAX, gst::ClockTime::nseconds))
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("iterations")
+                    .nick("Iterations")
+                    .blurb("Number of times the range is played (0 = infinite)")
+                    .default_value(DEFAULT_ITERATIONS)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "start" => {
+                settings.start = value.get().expect("type checked upstream");
+            }
+            "stop" => {
+                settings.stop = value.get().expect("type checked upstream");
+            }
+            "iterations" => {
+                settings.iterations = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "start" => settings.start.to_value(),
+            "stop" => settings.stop.to_value(),
+            "iterations" => settings.iterations.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for SegmentLooper {}
+
+impl ElementImpl for SegmentLooper {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Segment Looper",
+                "Generic",
+                "Loops a running time range of the stream without upstream seeking",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst::Caps::new_any();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![sink_pad_template, src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {transition:?}");
+
+        if transition == gst::StateChange::ReadyToPaused {
+            let settings = self.settings.lock().unwrap();
+            if settings.stop.is_some_and(|stop| stop <= settings.start) {
+                gst::element_imp_error!(
+                    self,
+                    gst::CoreError::StateChange,
+                    ["Configured stop is not after the start"]
+                );
+                return Err(gst::StateChangeError);
+            }
+        }
+
+        self.parent_change_state(transition)
+    }
+}
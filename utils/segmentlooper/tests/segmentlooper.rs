@@ -0,0 +1,117 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+//
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstsegmentlooper::plugin_register_static().unwrap();
+    });
+}
+
+fn setup_harness(h: &mut gst_check::Harness) {
+    h.set_src_caps_str("test/test");
+
+    let mut segment = gst::FormattedSegment::<gst::ClockTime>::new();
+    segment.set_start(gst::ClockTime::from_seconds(10));
+    segment.set_time(gst::ClockTime::ZERO);
+    assert!(h.push_event(gst::event::Segment::builder(&segment).build()));
+
+    h.play();
+}
+
+fn push_buffer(
+    h: &mut gst_check::Harness,
+    idx: u64,
+    delta_unit: bool,
+) -> Result<gst::FlowSuccess, gst::FlowError> {
+    let mut buffer = gst::Buffer::with_size(1).unwrap();
+    {
+        let buffer = buffer.get_mut().unwrap();
+        buffer.set_pts(gst::ClockTime::from_seconds(10) + gst::ClockTime::from_mseconds(idx * 100));
+        buffer.set_duration(gst::ClockTime::from_mseconds(100));
+        if delta_unit {
+            buffer.set_flags(gst::BufferFlags::DELTA_UNIT);
+        }
+    }
+    h.push(buffer)
+}
+
+fn pull_buffer(h: &mut gst_check::Harness, pts_ms: u64, discont: bool) {
+    let buffer = h.pull().unwrap();
+    assert_eq!(buffer.pts(), Some(gst::ClockTime::from_mseconds(pts_ms)));
+    assert_eq!(buffer.flags().contains(gst::BufferFlags::DISCONT), discont);
+}
+
+fn pull_eos(h: &mut gst_check::Harness) {
+    loop {
+        let ev = h.pull_event().unwrap();
+        if ev.type_() == gst::EventType::Eos {
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_loop_until_stop() {
+    init();
+
+    let mut h = gst_check::Harness::new("segmentlooper");
+    let element = h.element().unwrap();
+    element.set_property("stop", gst::ClockTime::from_mseconds(300));
+    element.set_property("iterations", 3u32);
+    setup_harness(&mut h);
+
+    for i in 0..3 {
+        assert_eq!(push_buffer(&mut h, i, false), Ok(gst::FlowSuccess::Ok));
+    }
+    // Reaching the end of the range starts looping and upstream is not needed anymore
+    assert_eq!(push_buffer(&mut h, 3, false), Err(gst::FlowError::Eos));
+
+    for i in 0..9 {
+        pull_buffer(&mut h, i * 100, i > 0 && i % 3 == 0);
+    }
+
+    pull_eos(&mut h);
+    assert_eq!(h.buffers_in_queue(), 0);
+}
+
+#[test]
+fn test_loop_from_keyframe_until_eos() {
+    init();
+
+    let mut h = gst_check::Harness::new("segmentlooper");
+    let element = h.element().unwrap();
+    element.set_property("start", gst::ClockTime::from_mseconds(150));
+    element.set_property("iterations", 2u32);
+    setup_harness(&mut h);
+
+    for i in 0..6 {
+        assert_eq!(
+            push_buffer(&mut h, i, i != 0 && i != 3),
+            Ok(gst::FlowSuccess::Ok)
+        );
+    }
+    assert!(h.push_event(gst::event::Eos::new()));
+
+    // First pass through the element
+    for i in 0..6 {
+        pull_buffer(&mut h, i * 100, false);
+    }
+
+    // The range starts at the first keyframe after the configured start
+    pull_buffer(&mut h, 600, true);
+    pull_buffer(&mut h, 700, false);
+    pull_buffer(&mut h, 800, false);
+
+    pull_eos(&mut h);
+    assert_eq!(h.buffers_in_queue(), 0);
+}
@@ -0,0 +1,461 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+//! Layout of the test signals shared between `testsignalsrc` and `testsignalverifier`.
+//!
+//! Every video frame and every audio frame starts with a [`Header`] carrying a sequence number
+//! and the clock time at which the frame was produced, followed by content that is completely
+//! determined by the sequence number so that the verifier can recreate and compare it.
+
+use gst::prelude::*;
+
+use std::f64::consts::PI;
+
+/// Marks the start of a header
+const MAGIC: u16 = 0x5a3c;
+
+/// Size of a serialized header in bytes
+pub const HEADER_SIZE: usize = 16;
+
+/// Number of bits of a serialized header
+pub const HEADER_BITS: usize = HEADER_SIZE * 8;
+
+/// Size of the square luma blocks carrying one header bit in video frames
+pub const VIDEO_BLOCK_SIZE: u32 = 8;
+
+/// Minimum frame width and height so that the header fits into the top of the frame with some
+/// content below
+pub const VIDEO_MIN_WIDTH: i32 = 128;
+pub const VIDEO_MIN_HEIGHT: i32 = 96;
+
+/// Luma values of header blocks
+const VIDEO_BIT_ZERO: u8 = 16;
+const VIDEO_BIT_ONE: u8 = 235;
+
+/// Value of all chroma samples of video frames
+pub const VIDEO_NEUTRAL_CHROMA: u8 = 128;
+
+/// Minimum sample rate so that the header fits into an audio frame with some tone after it
+pub const AUDIO_MIN_RATE: i32 = 8000;
+
+/// Number of audio frames per second
+const AUDIO_FRAMES_PER_SECOND: u32 = 50;
+
+/// Sample values of header bits in audio frames
+const AUDIO_BIT_AMPLITUDE: i16 = 16384;
+
+/// Frequency and amplitude of the tone following the header in audio frames
+const AUDIO_TONE_FREQ: u64 = 1000;
+const AUDIO_TONE_AMPLITUDE: f64 = 8192.0;
+
+/// Caps of the test signals: native endian S16 audio and 8 bit YUV / gray video
+pub fn caps() -> gst::Caps {
+    gst::Caps::builder_full()
+        .structure(
+            gst::Structure::builder("video/x-raw")
+                .field(
+                    "format",
+                    gst::List::new([
+                        gst_video::VideoFormat::I420.to_str(),
+                        gst_video::VideoFormat::Nv12.to_str(),
+                        gst_video::VideoFormat::Gray8.to_str(),
+                    ]),
+                )
+                .field("width", gst::IntRange::new(VIDEO_MIN_WIDTH, i32::MAX))
+                .field("height", gst::IntRange::new(VIDEO_MIN_HEIGHT, i32::MAX))
+                .field(
+                    "framerate",
+                    gst::FractionRange::new(
+                        gst::Fraction::new(1, i32::MAX),
+                        gst::Fraction::new(i32::MAX, 1),
+                    ),
+                )
+                .build(),
+        )
+        .structure(
+            gst::Structure::builder("audio/x-raw")
+                .field("format", gst_audio::AUDIO_FORMAT_S16.to_str())
+                .field("rate", gst::IntRange::new(AUDIO_MIN_RATE, i32::MAX))
+                .field("channels", gst::IntRange::new(1, i32::MAX))
+                .field("layout", "interleaved")
+                .build(),
+        )
+        .build()
+}
+
+/// Negotiated format of a test signal
+#[derive(Debug, Clone)]
+pub enum Format {
+    Audio(gst_audio::AudioInfo),
+    Video(gst_video::VideoInfo),
+}
+
+impl Format {
+    pub fn from_caps(caps: &gst::CapsRef) -> Option<Self> {
+        let s = caps.structure(0)?;
+
+        if s.name() == "audio/x-raw" {
+            gst_audio::AudioInfo::from_caps(caps)
+                .ok()
+                .filter(|info| info.format() == gst_audio::AUDIO_FORMAT_S16)
+                .map(Format::Audio)
+        } else if s.name() == "video/x-raw" {
+            gst_video::VideoInfo::from_caps(caps)
+                .ok()
+                .filter(|info| info.fps().numer() > 0 && info.fps().denom() > 0)
+                .map(Format::Video)
+        } else {
+            None
+        }
+    }
+
+    /// Start and end of the frame with `seqnum` relative to the start of the stream
+    pub fn frame_times(&self, seqnum: u32) -> (gst::ClockTime, gst::ClockTime) {
+        let frame_time = |n: u64| match self {
+            Format::Audio(info) => (n * audio_frame_len(info.rate()) as u64)
+                .mul_div_floor(*gst::ClockTime::SECOND, info.rate() as u64),
+            Format::Video(info) => (n * info.fps().denom() as u64)
+                .mul_div_floor(*gst::ClockTime::SECOND, info.fps().numer() as u64),
+        };
+
+        let start = frame_time(seqnum as u64).map(gst::ClockTime::from_nseconds);
+        let end = frame_time(seqnum as u64 + 1).map(gst::ClockTime::from_nseconds);
+
+        (start.unwrap(), end.unwrap())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub seqnum: u32,
+    /// Clock time at which the frame was produced, if known
+    pub timestamp: Option<gst::ClockTime>,
+}
+
+impl Header {
+    pub fn to_bytes(self) -> [u8; HEADER_SIZE] {
+        let mut data = [0u8; HEADER_SIZE];
+
+        data[0..2].copy_from_slice(&MAGIC.to_be_bytes());
+        data[2..6].copy_from_slice(&self.seqnum.to_be_bytes());
+        data[6..14].copy_from_slice(
+            &self
+                .timestamp
+                .map_or(u64::MAX, gst::ClockTime::nseconds)
+                .to_be_bytes(),
+        );
+        let checksum = fletcher16(&data[..14]);
+        data[14..16].copy_from_slice(&checksum.to_be_bytes());
+
+        data
+    }
+
+    pub fn from_bytes(data: &[u8; HEADER_SIZE]) -> Option<Self> {
+        if u16::from_be_bytes([data[0], data[1]]) != MAGIC
+            || u16::from_be_bytes([data[14], data[15]]) != fletcher16(&data[..14])
+        {
+            return None;
+        }
+
+        let seqnum = u32::from_be_bytes(data[2..6].try_into().unwrap());
+        let timestamp = u64::from_be_bytes(data[6..14].try_into().unwrap());
+
+        Some(Header {
+            seqnum,
+            timestamp: (timestamp != u64::MAX).then(|| gst::ClockTime::from_nseconds(timestamp)),
+        })
+    }
+
+    /// Header bits, most significant bit first
+    pub fn to_bits(self) -> impl Iterator<Item = bool> {
+        let data = self.to_bytes();
+        (0..HEADER_BITS).map(move |i| data[i / 8] & (0x80 >> (i % 8)) != 0)
+    }
+
+    /// Parses a header from `HEADER_BITS` bits, most significant bit first
+    pub fn from_bits(bits: impl IntoIterator<Item = bool>) -> Option<Self> {
+        let mut data = [0u8; HEADER_SIZE];
+        let mut n_bits = 0;
+        for (i, bit) in bits.into_iter().take(HEADER_BITS).enumerate() {
+            if bit {
+                data[i / 8] |= 0x80 >> (i % 8);
+            }
+            n_bits += 1;
+        }
+
+        if n_bits < HEADER_BITS {
+            return None;
+        }
+
+        Header::from_bytes(&data)
+    }
+}
+
+fn fletcher16(data: &[u8]) -> u16 {
+    let (sum1, sum2) = data.iter().fold((0u16, 0u16), |(sum1, sum2), byte| {
+        let sum1 = (sum1 + *byte as u16) % 255;
+        let sum2 = (sum2 + sum1) % 255;
+        (sum1, sum2)
+    });
+
+    (sum2 << 8) | sum1
+}
+
+/// Number of header bits per row of blocks for frames of `width`
+fn video_bits_per_row(width: u32) -> u32 {
+    width / VIDEO_BLOCK_SIZE
+}
+
+/// Height of the area at the top of frames of `width` that carries the header
+pub fn video_header_height(width: u32) -> u32 {
+    (HEADER_BITS as u32).div_ceil(video_bits_per_row(width)) * VIDEO_BLOCK_SIZE
+}
+
+/// Top left position of the block carrying header bit `bit`
+fn video_bit_position(width: u32, bit: usize) -> (u32, u32) {
+    let bits_per_row = video_bits_per_row(width);
+    let bit = bit as u32;
+
+    (
+        (bit % bits_per_row) * VIDEO_BLOCK_SIZE,
+        (bit / bits_per_row) * VIDEO_BLOCK_SIZE,
+    )
+}
+
+/// Luma of the moving diagonal gradient below the header
+fn video_content_luma(x: u32, y: u32, seqnum: u32) -> u8 {
+    let v = (x as u64 + y as u64 + 4 * seqnum as u64) % 512;
+    if v < 256 {
+        v as u8
+    } else {
+        (511 - v) as u8
+    }
+}
+
+/// Fills the luma plane of a frame with the header and content for `header`
+pub fn write_video_luma(data: &mut [u8], stride: usize, width: u32, height: u32, header: Header) {
+    let header_height = video_header_height(width);
+
+    for y in 0..height {
+        let line = &mut data[y as usize * stride..][..width as usize];
+        if y < header_height {
+            line.fill(VIDEO_BIT_ZERO);
+        } else {
+            for (x, luma) in line.iter_mut().enumerate() {
+                *luma = video_content_luma(x as u32, y, header.seqnum);
+            }
+        }
+    }
+
+    for (i, bit) in header.to_bits().enumerate() {
+        if !bit {
+            continue;
+        }
+
+        let (x, y) = video_bit_position(width, i);
+        for y in y..y + VIDEO_BLOCK_SIZE {
+            data[y as usize * stride + x as usize..][..VIDEO_BLOCK_SIZE as usize]
+                .fill(VIDEO_BIT_ONE);
+        }
+    }
+}
+
+/// Reads the header from the luma plane of a frame
+///
+/// Only the center of each block is considered to be robust against blurred block edges.
+pub fn read_video_header(data: &[u8], stride: usize, width: u32) -> Option<Header> {
+    let threshold = (VIDEO_BIT_ZERO as u32 + VIDEO_BIT_ONE as u32) / 2;
+    let margin = VIDEO_BLOCK_SIZE / 4;
+
+    Header::from_bits((0..HEADER_BITS).map(|i| {
+        let (x, y) = video_bit_position(width, i);
+
+        let mut sum = 0;
+        let mut count = 0;
+        for y in y + margin..y + VIDEO_BLOCK_SIZE - margin {
+            for x in x + margin..x + VIDEO_BLOCK_SIZE - margin {
+                sum += data[y as usize * stride + x as usize] as u32;
+                count += 1;
+            }
+        }
+
+        sum / count > threshold
+    }))
+}
+
+/// Mean absolute difference of the content below the header to the expected content for
+/// `seqnum`, relative to the full luma range
+pub fn video_content_error(
+    data: &[u8],
+    stride: usize,
+    width: u32,
+    height: u32,
+    seqnum: u32,
+) -> f64 {
+    let header_height = video_header_height(width);
+
+    let mut sum = 0u64;
+    for y in header_height..height {
+        let line = &data[y as usize * stride..][..width as usize];
+        for (x, luma) in line.iter().enumerate() {
+            sum += luma.abs_diff(video_content_luma(x as u32, y, seqnum)) as u64;
+        }
+    }
+
+    let count = (height - header_height) as u64 * width as u64;
+    sum as f64 / count as f64 / 255.0
+}
+
+/// Mean absolute difference of a chroma component to neutral chroma, relative to the full
+/// chroma range
+///
+/// The component starts at `offset` in each line and has `pixel_stride` bytes between samples.
+pub fn video_chroma_error(
+    data: &[u8],
+    stride: usize,
+    offset: usize,
+    pixel_stride: usize,
+    width: u32,
+    height: u32,
+) -> f64 {
+    let mut sum = 0u64;
+    for y in 0..height {
+        let line = &data[y as usize * stride + offset..];
+        for x in 0..width {
+            sum += line[x as usize * pixel_stride].abs_diff(VIDEO_NEUTRAL_CHROMA) as u64;
+        }
+    }
+
+    sum as f64 / (width as u64 * height as u64) as f64 / 255.0
+}
+
+/// Number of samples per audio frame
+pub fn audio_frame_len(rate: u32) -> usize {
+    (rate / AUDIO_FRAMES_PER_SECOND) as usize
+}
+
+/// Sample value of the tone at `sample_offset`
+///
+/// The phase is calculated from the sample offset with integer arithmetic so that it is exactly
+/// reproducible independent of where in the stream the calculation starts.
+pub fn audio_tone(sample_offset: u64, rate: u32) -> i16 {
+    let phase = (sample_offset * AUDIO_TONE_FREQ % rate as u64) as f64 / rate as f64;
+    (AUDIO_TONE_AMPLITUDE * (2.0 * PI * phase).sin()).round() as i16
+}
+
+/// Sample value of the header `bit` in audio frames
+fn audio_header_sample(bit: bool) -> i16 {
+    if bit {
+        AUDIO_BIT_AMPLITUDE
+    } else {
+        -AUDIO_BIT_AMPLITUDE
+    }
+}
+
+/// Reads a header from the first `HEADER_BITS` samples of an audio frame
+pub fn read_audio_header(samples: &[i16]) -> Option<Header> {
+    Header::from_bits(samples.iter().map(|sample| *sample > 0))
+}
+
+/// Samples of one audio frame of a single channel: `header` followed by the tone
+pub fn audio_frame(header: Header, frame_len: usize, rate: u32) -> Vec<i16> {
+    let first_sample = header.seqnum as u64 * frame_len as u64;
+
+    header
+        .to_bits()
+        .map(audio_header_sample)
+        .chain((HEADER_BITS..frame_len).map(|i| audio_tone(first_sample + i as u64, rate)))
+        .collect()
+}
+
+/// Mean absolute difference of the tone after the header of an audio frame to the expected tone
+/// for `seqnum`, relative to the full sample range
+pub fn audio_content_error(samples: &[i16], frame_len: usize, seqnum: u32, rate: u32) -> f64 {
+    let first_sample = seqnum as u64 * frame_len as u64;
+
+    let mut sum = 0u64;
+    for (i, sample) in samples.iter().enumerate().skip(HEADER_BITS) {
+        sum += sample.abs_diff(audio_tone(first_sample + i as u64, rate)) as u64;
+    }
+
+    sum as f64 / (samples.len() - HEADER_BITS) as f64 / 32768.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_roundtrip() {
+        for header in [
+            Header {
+                seqnum: 0,
+                timestamp: None,
+            },
+            Header {
+                seqnum: 0x1234_5678,
+                timestamp: Some(gst::ClockTime::from_seconds(12345)),
+            },
+        ] {
+            assert_eq!(Header::from_bytes(&header.to_bytes()), Some(header));
+            assert_eq!(Header::from_bits(header.to_bits()), Some(header));
+
+            let mut data = header.to_bytes();
+            data[5] ^= 0x01;
+            assert_eq!(Header::from_bytes(&data), None);
+        }
+
+        assert_eq!(Header::from_bits([true; 10]), None);
+    }
+
+    #[test]
+    fn video_roundtrip() {
+        let (width, height, stride) = (160, 120, 176);
+        let header = Header {
+            seqnum: 42,
+            timestamp: Some(gst::ClockTime::from_mseconds(1500)),
+        };
+
+        let mut data = vec![0u8; stride * height as usize];
+        write_video_luma(&mut data, stride, width, height, header);
+
+        assert_eq!(read_video_header(&data, stride, width), Some(header));
+        assert_eq!(video_content_error(&data, stride, width, height, 42), 0.0);
+        assert!(video_content_error(&data, stride, width, height, 43) > 0.0);
+
+        // Interleaved chroma with a single wrong sample
+        let mut chroma = vec![VIDEO_NEUTRAL_CHROMA; stride * height as usize / 2];
+        assert_eq!(
+            video_chroma_error(&chroma, stride, 1, 2, width / 2, height / 2),
+            0.0
+        );
+        chroma[stride + 3] = 0;
+        assert_eq!(
+            video_chroma_error(&chroma, stride, 0, 2, width / 2, height / 2),
+            0.0
+        );
+        assert!(video_chroma_error(&chroma, stride, 1, 2, width / 2, height / 2) > 0.0);
+    }
+
+    #[test]
+    fn audio_roundtrip() {
+        let rate = 8000;
+        let frame_len = audio_frame_len(rate);
+        let header = Header {
+            seqnum: 7,
+            timestamp: None,
+        };
+
+        let mut samples = audio_frame(header, frame_len, rate);
+        assert_eq!(samples.len(), frame_len);
+
+        assert_eq!(read_audio_header(&samples), Some(header));
+        assert_eq!(audio_content_error(&samples, frame_len, 7, rate), 0.0);
+
+        samples[frame_len - 1] = i16::MAX;
+        assert!(audio_content_error(&samples, frame_len, 7, rate) > 0.0);
+    }
+}
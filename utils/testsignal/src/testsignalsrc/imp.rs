@@ -0,0 +1,443 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-testsignalsrc
+ * @see_also: testsignalverifier
+ *
+ * #testsignalsrc generates audio or video test signals that can be checked by
+ * #testsignalverifier at the other end of a pipeline or transport for dropped, corrupted and
+ * delayed frames.
+ *
+ * Every frame starts with a header carrying a sequence number and, in live mode, the clock time
+ * at which the frame was produced.
+ *
+ * Video frames carry the header as black and white blocks of 8x8 pixels in the luma plane at the
+ * top of the frame. Below follows a diagonal gradient that moves with each frame.
+ *
+ * Audio is produced in frames of 20ms. Each frame starts with one full scale positive or negative
+ * sample per header bit, followed by a 1kHz tone. All channels carry the same signal.
+ *
+ * The header is designed to survive lossless transports and mild lossy compression of video.
+ * Lossy audio codecs will generally destroy it.
+ *
+ * ## Example pipeline
+ *
+ * |[
+ * gst-launch-1.0 testsignalsrc is-live=true ! video/x-raw,width=640,height=480 ! x264enc tune=zerolatency ! rtph264pay ! rtph264depay ! avdec_h264 ! testsignalverifier
+ * ]|
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::prelude::*;
+use gst_base::subclass::base_src::CreateSuccess;
+use gst_base::subclass::prelude::*;
+
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use crate::signal::{self, Format, Header};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "testsignalsrc",
+        gst::DebugColorFlags::empty(),
+        Some("Test Signal Source"),
+    )
+});
+
+const DEFAULT_IS_LIVE: bool = false;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    is_live: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            is_live: DEFAULT_IS_LIVE,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    format: Option<Format>,
+    seqnum: u32,
+}
+
+struct ClockWait {
+    clock_id: Option<gst::SingleShotClockId>,
+    flushing: bool,
+}
+
+impl Default for ClockWait {
+    fn default() -> ClockWait {
+        ClockWait {
+            clock_id: None,
+            flushing: true,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TestSignalSrc {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+    clock_wait: Mutex<ClockWait>,
+}
+
+impl TestSignalSrc {
+    fn create_audio_frame(
+        &self,
+        info: &gst_audio::AudioInfo,
+        header: Header,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let frame_len = signal::audio_frame_len(info.rate());
+        let samples = signal::audio_frame(header, frame_len, info.rate());
+
+        let mut buffer = gst::Buffer::with_size(frame_len * info.bpf() as usize).map_err(|_| {
+            gst::error!(CAT, imp = self, "Failed to allocate buffer");
+            gst::FlowError::Error
+        })?;
+        {
+            let buffer = buffer.get_mut().unwrap();
+            let mut map = buffer.map_writable().unwrap();
+
+            for (frame, sample) in map
+                .as_mut_slice()
+                .chunks_exact_mut(info.bpf() as usize)
+                .zip(samples)
+            {
+                for channel in frame.chunks_exact_mut(2) {
+                    channel.copy_from_slice(&sample.to_ne_bytes());
+                }
+            }
+        }
+
+        Ok(buffer)
+    }
+
+    fn create_video_frame(
+        &self,
+        info: &gst_video::VideoInfo,
+        header: Header,
+    ) -> Result<gst::Buffer, gst::FlowError> {
+        let buffer = gst::Buffer::with_size(info.size()).map_err(|_| {
+            gst::error!(CAT, imp = self, "Failed to allocate buffer");
+            gst::FlowError::Error
+        })?;
+
+        let mut frame =
+            gst_video::VideoFrame::from_buffer_writable(buffer, info).map_err(|_| {
+                gst::error!(CAT, imp = self, "Failed to map video frame");
+                gst::FlowError::Error
+            })?;
+
+        let stride = frame.plane_stride()[0] as usize;
+        signal::write_video_luma(
+            frame.plane_data_mut(0).unwrap(),
+            stride,
+            info.width(),
+            info.height(),
+            header,
+        );
+
+        // Neutral chroma, if any
+        for plane in 1..frame.n_planes() {
+            frame
+                .plane_data_mut(plane)
+                .unwrap()
+                .fill(signal::VIDEO_NEUTRAL_CHROMA);
+        }
+
+        Ok(frame.into_buffer())
+    }
+
+    /// Waits until the clock reached `running_time`
+    fn wait(&self, running_time: gst::ClockTime) -> Result<(), gst::FlowError> {
+        let Some((clock, base_time)) = Option::zip(self.obj().clock(), self.obj().base_time())
+        else {
+            return Ok(());
+        };
+        let wait_until = running_time + base_time;
+
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if clock_wait.flushing {
+            gst::debug!(CAT, imp = self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        let id = clock.new_single_shot_id(wait_until);
+        clock_wait.clock_id = Some(id.clone());
+        drop(clock_wait);
+
+        gst::log!(
+            CAT,
+            imp = self,
+            "Waiting until {wait_until}, now {}",
+            clock.time().display(),
+        );
+        let (res, jitter) = id.wait();
+        gst::log!(CAT, imp = self, "Waited res {res:?} jitter {jitter}");
+        self.clock_wait.lock().unwrap().clock_id.take();
+
+        if res == Err(gst::ClockError::Unscheduled) {
+            gst::debug!(CAT, imp = self, "Flushing");
+            return Err(gst::FlowError::Flushing);
+        }
+
+        Ok(())
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TestSignalSrc {
+    const NAME: &'static str = "GstTestSignalSrc";
+    type Type = super::TestSignalSrc;
+    type ParentType = gst_base::PushSrc;
+}
+
+impl ObjectImpl for TestSignalSrc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![glib::ParamSpecBoolean::builder("is-live")
+                .nick("Is Live")
+                .blurb("Produce frames in real time and embed the clock time they were produced at")
+                .default_value(DEFAULT_IS_LIVE)
+                .mutable_ready()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.set_live(DEFAULT_IS_LIVE);
+        obj.set_format(gst::Format::Time);
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "is-live" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.is_live = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "is-live" => {
+                let settings = self.settings.lock().unwrap();
+                settings.is_live.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for TestSignalSrc {}
+
+impl ElementImpl for TestSignalSrc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Test Signal Source",
+                "Source/Audio/Video",
+                "Generates audio and video test signals with embedded sequence numbers and timestamps",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &signal::caps(),
+            )
+            .unwrap();
+
+            vec![src_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        if let gst::StateChange::ReadyToPaused = transition {
+            self.obj().set_live(self.settings.lock().unwrap().is_live);
+        }
+
+        self.parent_change_state(transition)
+    }
+}
+
+impl BaseSrcImpl for TestSignalSrc {
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let format = Format::from_caps(caps)
+            .ok_or_else(|| gst::loggable_error!(CAT, "Unsupported caps {}", caps))?;
+
+        gst::debug!(CAT, imp = self, "Configuring for caps {caps}");
+
+        // Keep counting sequence numbers so that the verifier does not see a discontinuity
+        self.state.lock().unwrap().format = Some(format);
+
+        let _ = self
+            .obj()
+            .post_message(gst::message::Latency::builder().src(&*self.obj()).build());
+
+        Ok(())
+    }
+
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+        self.unlock_stop()?;
+
+        gst::info!(CAT, imp = self, "Started");
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+        self.unlock()?;
+
+        gst::info!(CAT, imp = self, "Stopped");
+
+        Ok(())
+    }
+
+    fn query(&self, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        match query.view_mut() {
+            // In live mode frames are output once they are complete
+            QueryViewMut::Latency(q) => {
+                let is_live = self.settings.lock().unwrap().is_live;
+                let state = self.state.lock().unwrap();
+
+                if let Some(ref format) = state.format {
+                    let (start, end) = format.frame_times(0);
+                    let latency = end - start;
+                    gst::debug!(CAT, imp = self, "Returning latency {latency}");
+                    q.set(is_live, latency, gst::ClockTime::NONE);
+                    true
+                } else {
+                    false
+                }
+            }
+            _ => BaseSrcImplExt::parent_query(self, query),
+        }
+    }
+
+    fn fixate(&self, mut caps: gst::Caps) -> gst::Caps {
+        caps.truncate();
+        {
+            let caps = caps.make_mut();
+            let s = caps.structure_mut(0).unwrap();
+            if s.name() == "audio/x-raw" {
+                s.fixate_field_nearest_int("rate", 48_000);
+                s.fixate_field_nearest_int("channels", 1);
+            } else {
+                s.fixate_field_nearest_int("width", 320);
+                s.fixate_field_nearest_int("height", 240);
+                s.fixate_field_nearest_fraction("framerate", gst::Fraction::new(30, 1));
+            }
+        }
+
+        self.parent_fixate(caps)
+    }
+
+    fn unlock(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp = self, "Unlocking");
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        if let Some(clock_id) = clock_wait.clock_id.take() {
+            clock_id.unschedule();
+        }
+        clock_wait.flushing = true;
+
+        Ok(())
+    }
+
+    fn unlock_stop(&self) -> Result<(), gst::ErrorMessage> {
+        gst::debug!(CAT, imp = self, "Unlock stop");
+        let mut clock_wait = self.clock_wait.lock().unwrap();
+        clock_wait.flushing = false;
+
+        Ok(())
+    }
+}
+
+impl PushSrcImpl for TestSignalSrc {
+    fn create(
+        &self,
+        _buffer: Option<&mut gst::BufferRef>,
+    ) -> Result<CreateSuccess, gst::FlowError> {
+        let mut state = self.state.lock().unwrap();
+        let Some(format) = state.format.clone() else {
+            gst::element_imp_error!(self, gst::CoreError::Negotiation, ["Have no caps yet"]);
+            return Err(gst::FlowError::NotNegotiated);
+        };
+        let seqnum = state.seqnum;
+        state.seqnum = state.seqnum.wrapping_add(1);
+        drop(state);
+
+        let (pts, end) = format.frame_times(seqnum);
+
+        let is_live = self.obj().is_live();
+        let segment = self
+            .obj()
+            .segment()
+            .downcast::<gst::format::Time>()
+            .unwrap();
+        let running_time = segment.to_running_time(pts);
+
+        // Only in live mode frames are produced at the clock time of their running time
+        let timestamp = if is_live {
+            running_time.opt_add(self.obj().base_time())
+        } else {
+            None
+        };
+
+        let header = Header { seqnum, timestamp };
+        let mut buffer = match format {
+            Format::Audio(ref info) => self.create_audio_frame(info, header)?,
+            Format::Video(ref info) => self.create_video_frame(info, header)?,
+        };
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(pts);
+            buffer.set_duration(end - pts);
+        }
+
+        if is_live {
+            if let Some(running_time) = segment.to_running_time(end) {
+                self.wait(running_time)?;
+            }
+        }
+
+        gst::log!(CAT, imp = self, "Produced frame {seqnum}: {buffer:?}");
+
+        Ok(CreateSuccess::NewBuffer(buffer))
+    }
+}
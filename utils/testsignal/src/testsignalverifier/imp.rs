@@ -0,0 +1,559 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-testsignalverifier
+ * @see_also: testsignalsrc
+ *
+ * #testsignalverifier checks the audio or video test signal generated by #testsignalsrc for
+ * dropped, corrupted and out of order frames and measures the latency of each frame.
+ *
+ * A frame is considered corrupted if its header can't be read or if the mean absolute difference
+ * of its content to the expected content is above #testsignalverifier:tolerance, relative to the
+ * full sample range. For audio, every channel has to carry the same header and the error of the
+ * worst channel is used. For video, the error of the worst plane is used, with chroma expected to
+ * be neutral. Audio frames are found in the stream independent of buffer boundaries. If an audio
+ * frame does not start where it is expected, the verifier searches for the next valid header in
+ * the first channel.
+ *
+ * Frames with the same sequence number as the previous frame are counted as duplicated, frames
+ * with a lower sequence number as out of order.
+ *
+ * The latency of a frame is the difference between the current clock time when the frame is
+ * received and the clock time at which #testsignalsrc produced it. This is only available if
+ * the source runs in live mode, and the source and the verifier need to use the same clock, e.g.
+ * by running in the same pipeline or by using a network synchronized clock.
+ *
+ * If #testsignalverifier:post-messages is enabled, the following element messages are posted:
+ *
+ * - `testsignal-dropped` with the `seqnum` of the first missing frame and the `count` of
+ *   missing frames.
+ * - `testsignal-corrupted` with the `seqnum` of the frame, if the header could be read, and the
+ *   content `error`.
+ * - `testsignal-stats` with the same fields as #testsignalverifier:stats on EOS.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use crate::signal::{self, Format, Header, HEADER_BITS};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "testsignalverifier",
+        gst::DebugColorFlags::empty(),
+        Some("Test Signal Verifier"),
+    )
+});
+
+const DEFAULT_TOLERANCE: f64 = 0.05;
+const DEFAULT_POST_MESSAGES: bool = true;
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    tolerance: f64,
+    post_messages: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            tolerance: DEFAULT_TOLERANCE,
+            post_messages: DEFAULT_POST_MESSAGES,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Stats {
+    received: u64,
+    dropped: u64,
+    corrupted: u64,
+    duplicated: u64,
+    out_of_order: u64,
+    min_latency: Option<gst::ClockTime>,
+    max_latency: Option<gst::ClockTime>,
+    latency_sum: gst::ClockTime,
+    latency_count: u64,
+}
+
+impl Stats {
+    fn to_structure(self, name: &str) -> gst::Structure {
+        gst::Structure::builder(name)
+            .field("received", self.received)
+            .field("dropped", self.dropped)
+            .field("corrupted", self.corrupted)
+            .field("duplicated", self.duplicated)
+            .field("out-of-order", self.out_of_order)
+            .field_if_some("min-latency", self.min_latency)
+            .field_if_some("max-latency", self.max_latency)
+            .field_if_some(
+                "average-latency",
+                (self.latency_count > 0).then(|| self.latency_sum / self.latency_count),
+            )
+            .build()
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    format: Option<Format>,
+    /// Audio samples that were not checked yet, per channel
+    pending_samples: Vec<Vec<i16>>,
+    /// Whether the next audio frame is expected at the start of `pending_samples`
+    audio_synced: bool,
+    last_seqnum: Option<u32>,
+    stats: Stats,
+}
+
+impl State {
+    /// Drops the first `n` pending audio samples of every channel
+    fn drain_samples(&mut self, n: usize) {
+        for channel in &mut self.pending_samples {
+            channel.drain(..n);
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct TestSignalVerifier {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl TestSignalVerifier {
+    /// Checks the audio frames that were completed by `buffer`
+    fn check_audio(
+        &self,
+        state: &mut State,
+        info: &gst_audio::AudioInfo,
+        buffer: &gst::BufferRef,
+        now: Option<gst::ClockTime>,
+        settings: &Settings,
+        messages: &mut Vec<gst::Structure>,
+    ) -> Result<(), gst::FlowError> {
+        let map = buffer.map_readable().map_err(|_| {
+            gst::error!(CAT, imp = self, "Failed to map buffer readable");
+            gst::FlowError::Error
+        })?;
+
+        state
+            .pending_samples
+            .resize_with(info.channels() as usize, Vec::new);
+        for frame in map.as_slice().chunks_exact(info.bpf() as usize) {
+            for (channel, sample) in state.pending_samples.iter_mut().zip(frame.chunks_exact(2)) {
+                channel.push(i16::from_ne_bytes([sample[0], sample[1]]));
+            }
+        }
+
+        let frame_len = signal::audio_frame_len(info.rate());
+        loop {
+            if !state.audio_synced {
+                let Some(n_candidates) = state.pending_samples[0].len().checked_sub(HEADER_BITS)
+                else {
+                    break;
+                };
+
+                match (0..=n_candidates).find(|offset| {
+                    signal::read_audio_header(&state.pending_samples[0][*offset..]).is_some()
+                }) {
+                    Some(offset) => {
+                        gst::debug!(CAT, imp = self, "Found audio frame after {offset} samples");
+                        state.drain_samples(offset);
+                        state.audio_synced = true;
+                    }
+                    None => {
+                        state.drain_samples(n_candidates + 1);
+                        break;
+                    }
+                }
+            }
+
+            if state.pending_samples[0].len() < frame_len {
+                break;
+            }
+
+            match signal::read_audio_header(&state.pending_samples[0][..frame_len]) {
+                Some(header) => {
+                    // All channels carry the same signal, a channel with a different header is
+                    // completely wrong
+                    let error = state
+                        .pending_samples
+                        .iter()
+                        .map(|channel| {
+                            let samples = &channel[..frame_len];
+                            if signal::read_audio_header(samples) == Some(header) {
+                                signal::audio_content_error(
+                                    samples,
+                                    frame_len,
+                                    header.seqnum,
+                                    info.rate(),
+                                )
+                            } else {
+                                1.0
+                            }
+                        })
+                        .fold(0.0, f64::max);
+                    self.check_frame(state, header, error, now, settings, messages);
+                    state.drain_samples(frame_len);
+                }
+                None => {
+                    gst::debug!(CAT, imp = self, "Lost audio frame synchronization");
+                    state.audio_synced = false;
+                    self.corrupted_frame(state, None, 1.0, settings, messages);
+                    state.drain_samples(1);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_video(
+        &self,
+        state: &mut State,
+        info: &gst_video::VideoInfo,
+        buffer: &gst::BufferRef,
+        now: Option<gst::ClockTime>,
+        settings: &Settings,
+        messages: &mut Vec<gst::Structure>,
+    ) -> Result<(), gst::FlowError> {
+        let frame =
+            gst_video::VideoFrameRef::from_buffer_ref_readable(buffer, info).map_err(|_| {
+                gst::error!(CAT, imp = self, "Failed to map video frame");
+                gst::FlowError::Error
+            })?;
+
+        let stride = frame.plane_stride()[0] as usize;
+        let data = frame.plane_data(0).unwrap();
+
+        match signal::read_video_header(data, stride, info.width()) {
+            Some(header) => {
+                let luma_error = signal::video_content_error(
+                    data,
+                    stride,
+                    info.width(),
+                    info.height(),
+                    header.seqnum,
+                );
+
+                let finfo = frame.format_info();
+                let error = (1..finfo.n_components())
+                    .map(|component| {
+                        let plane = finfo.plane()[component as usize];
+                        signal::video_chroma_error(
+                            frame.plane_data(plane).unwrap(),
+                            frame.plane_stride()[plane as usize] as usize,
+                            finfo.poffset()[component as usize] as usize,
+                            finfo.pixel_stride()[component as usize] as usize,
+                            finfo.scale_width(component as u8, info.width()),
+                            finfo.scale_height(component as u8, info.height()),
+                        )
+                    })
+                    .fold(luma_error, f64::max);
+
+                self.check_frame(state, header, error, now, settings, messages);
+            }
+            None => {
+                state.stats.received += 1;
+                self.corrupted_frame(state, None, 1.0, settings, messages);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_frame(
+        &self,
+        state: &mut State,
+        header: Header,
+        error: f64,
+        now: Option<gst::ClockTime>,
+        settings: &Settings,
+        messages: &mut Vec<gst::Structure>,
+    ) {
+        gst::trace!(
+            CAT,
+            imp = self,
+            "Frame {} with timestamp {} and error {error}",
+            header.seqnum,
+            header.timestamp.display(),
+        );
+
+        state.stats.received += 1;
+
+        if error > settings.tolerance {
+            self.corrupted_frame(state, Some(header.seqnum), error, settings, messages);
+        }
+
+        match state.last_seqnum {
+            Some(last_seqnum) if header.seqnum == last_seqnum => {
+                gst::debug!(CAT, imp = self, "Frame {} duplicated", header.seqnum);
+                state.stats.duplicated += 1;
+                return;
+            }
+            Some(last_seqnum) if header.seqnum.wrapping_sub(last_seqnum) > u32::MAX / 2 => {
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Frame {} out of order after {last_seqnum}",
+                    header.seqnum
+                );
+                state.stats.out_of_order += 1;
+                return;
+            }
+            Some(last_seqnum) if header.seqnum.wrapping_sub(last_seqnum) > 1 => {
+                let first_missing = last_seqnum.wrapping_add(1);
+                let count = header.seqnum.wrapping_sub(first_missing) as u64;
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Dropped {count} frames starting at {first_missing}"
+                );
+                state.stats.dropped += count;
+                if settings.post_messages {
+                    messages.push(
+                        gst::Structure::builder("testsignal-dropped")
+                            .field("seqnum", first_missing)
+                            .field("count", count)
+                            .build(),
+                    );
+                }
+            }
+            _ => (),
+        }
+        state.last_seqnum = Some(header.seqnum);
+
+        if let Some(latency) = now.opt_checked_sub(header.timestamp).ok().flatten() {
+            let stats = &mut state.stats;
+            stats.min_latency = Some(stats.min_latency.map_or(latency, |min| min.min(latency)));
+            stats.max_latency = Some(stats.max_latency.map_or(latency, |max| max.max(latency)));
+            stats.latency_sum += latency;
+            stats.latency_count += 1;
+        }
+    }
+
+    fn corrupted_frame(
+        &self,
+        state: &mut State,
+        seqnum: Option<u32>,
+        error: f64,
+        settings: &Settings,
+        messages: &mut Vec<gst::Structure>,
+    ) {
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Corrupted frame {seqnum:?} with error {error}"
+        );
+        state.stats.corrupted += 1;
+
+        if settings.post_messages {
+            messages.push(
+                gst::Structure::builder("testsignal-corrupted")
+                    .field_if_some("seqnum", seqnum)
+                    .field("error", error)
+                    .build(),
+            );
+        }
+    }
+
+    fn post_messages(&self, messages: Vec<gst::Structure>) {
+        for s in messages {
+            let _ = self
+                .obj()
+                .post_message(gst::message::Element::builder(s).src(&*self.obj()).build());
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TestSignalVerifier {
+    const NAME: &'static str = "GstTestSignalVerifier";
+    type Type = super::TestSignalVerifier;
+    type ParentType = gst_base::BaseSink;
+}
+
+impl ObjectImpl for TestSignalVerifier {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecDouble::builder("tolerance")
+                    .nick("Tolerance")
+                    .blurb("Maximum mean absolute difference of the frame content, relative to the full sample range, before a frame is considered corrupted")
+                    .minimum(0.0)
+                    .maximum(1.0)
+                    .default_value(DEFAULT_TOLERANCE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoolean::builder("post-messages")
+                    .nick("Post Messages")
+                    .blurb("Post element messages for dropped and corrupted frames and the final statistics")
+                    .default_value(DEFAULT_POST_MESSAGES)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecBoxed::builder::<gst::Structure>("stats")
+                    .nick("Statistics")
+                    .blurb("Statistics about the received frames")
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "tolerance" => {
+                settings.tolerance = value.get().expect("type checked upstream");
+            }
+            "post-messages" => {
+                settings.post_messages = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "tolerance" => {
+                let settings = self.settings.lock().unwrap();
+                settings.tolerance.to_value()
+            }
+            "post-messages" => {
+                let settings = self.settings.lock().unwrap();
+                settings.post_messages.to_value()
+            }
+            "stats" => {
+                let state = self.state.lock().unwrap();
+                state.stats.to_structure("testsignal-stats").to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for TestSignalVerifier {}
+
+impl ElementImpl for TestSignalVerifier {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Test Signal Verifier",
+                "Sink/Audio/Video",
+                "Checks test signals for dropped, corrupted and delayed frames",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &signal::caps(),
+            )
+            .unwrap();
+
+            vec![sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseSinkImpl for TestSignalVerifier {
+    fn start(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        let mut state = self.state.lock().unwrap();
+        state.format = None;
+        state.pending_samples.clear();
+
+        Ok(())
+    }
+
+    fn set_caps(&self, caps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        let format = Format::from_caps(caps)
+            .ok_or_else(|| gst::loggable_error!(CAT, "Unsupported caps {}", caps))?;
+
+        gst::debug!(CAT, imp = self, "Configuring for caps {caps}");
+
+        let mut state = self.state.lock().unwrap();
+        state.format = Some(format);
+        state.pending_samples.clear();
+        state.audio_synced = false;
+
+        Ok(())
+    }
+
+    fn event(&self, event: gst::Event) -> bool {
+        match event.view() {
+            gst::EventView::FlushStop(_) => {
+                let mut state = self.state.lock().unwrap();
+                state.pending_samples.clear();
+                state.audio_synced = false;
+                state.last_seqnum = None;
+            }
+            gst::EventView::Eos(_) => {
+                let settings = *self.settings.lock().unwrap();
+                let stats = self.state.lock().unwrap().stats;
+
+                gst::info!(CAT, imp = self, "Final statistics {stats:?}");
+
+                if settings.post_messages {
+                    self.post_messages(vec![stats.to_structure("testsignal-stats")]);
+                }
+            }
+            _ => (),
+        }
+
+        self.parent_event(event)
+    }
+
+    fn render(&self, buffer: &gst::Buffer) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let settings = *self.settings.lock().unwrap();
+        let now = self.obj().clock().and_then(|clock| clock.time());
+
+        let mut messages = vec![];
+        let mut state = self.state.lock().unwrap();
+        let Some(format) = state.format.clone() else {
+            gst::element_imp_error!(self, gst::CoreError::Negotiation, ["Have no caps yet"]);
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        match format {
+            Format::Audio(ref info) => {
+                self.check_audio(&mut state, info, buffer, now, &settings, &mut messages)?
+            }
+            Format::Video(ref info) => {
+                self.check_video(&mut state, info, buffer, now, &settings, &mut messages)?
+            }
+        }
+        drop(state);
+
+        self.post_messages(messages);
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,241 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+//
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gsttestsignal::plugin_register_static().unwrap();
+    });
+}
+
+/// Runs `testsignalsrc ! caps ! testsignalverifier` for 20 frames, calling `modify` for every
+/// frame before it reaches the verifier. Frames for which `modify` returns `false` are dropped.
+///
+/// Returns the element messages of the verifier and its final statistics.
+fn run_pipeline(
+    caps: &str,
+    modify: impl Fn(u64, &mut gst::BufferRef) -> bool + Send + Sync + 'static,
+) -> (Vec<gst::Structure>, gst::Structure) {
+    init();
+
+    let pipeline = gst::parse::launch(&format!(
+        "testsignalsrc num-buffers=20 ! {caps} ! testsignalverifier name=verifier sync=false"
+    ))
+    .unwrap()
+    .downcast::<gst::Pipeline>()
+    .unwrap();
+    let verifier = pipeline.by_name("verifier").unwrap();
+
+    let sinkpad = verifier.static_pad("sink").unwrap();
+    let counter = std::sync::atomic::AtomicU64::new(0);
+    sinkpad.add_probe(gst::PadProbeType::BUFFER, move |_pad, info| {
+        let n = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let Some(buffer) = info.buffer_mut() else {
+            return gst::PadProbeReturn::Ok;
+        };
+
+        if modify(n, buffer.make_mut()) {
+            gst::PadProbeReturn::Ok
+        } else {
+            gst::PadProbeReturn::Drop
+        }
+    });
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let mut messages = vec![];
+    let bus = pipeline.bus().unwrap();
+    for msg in bus.iter_timed(gst::ClockTime::NONE) {
+        match msg.view() {
+            gst::MessageView::Element(m) if m.src() == Some(verifier.upcast_ref()) => {
+                messages.push(m.structure().unwrap().to_owned());
+            }
+            gst::MessageView::Eos(..) => break,
+            gst::MessageView::Error(err) => panic!("{err:?}"),
+            _ => (),
+        }
+    }
+
+    pipeline.set_state(gst::State::Null).unwrap();
+
+    let stats = verifier.property::<gst::Structure>("stats");
+
+    (messages, stats)
+}
+
+fn stats_field(stats: &gst::Structure, field: &str) -> u64 {
+    stats.get::<u64>(field).unwrap()
+}
+
+#[test]
+fn test_video() {
+    let (messages, stats) = run_pipeline(
+        "video/x-raw,format=I420,width=320,height=240,framerate=30/1",
+        |_, _| true,
+    );
+
+    assert_eq!(stats_field(&stats, "received"), 20);
+    assert_eq!(stats_field(&stats, "dropped"), 0);
+    assert_eq!(stats_field(&stats, "corrupted"), 0);
+    assert_eq!(stats_field(&stats, "out-of-order"), 0);
+
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].name(), "testsignal-stats");
+    assert_eq!(stats_field(&messages[0], "received"), 20);
+}
+
+#[test]
+fn test_video_drop_and_corruption() {
+    let (messages, stats) = run_pipeline(
+        "video/x-raw,format=GRAY8,width=160,height=120,framerate=30/1",
+        |n, buffer| {
+            if n == 10 {
+                // Overwrite the bottom half of the frame
+                let mut map = buffer.map_writable().unwrap();
+                let len = map.len();
+                map[len / 2..].fill(0);
+            }
+
+            !(5..8).contains(&n)
+        },
+    );
+
+    assert_eq!(stats_field(&stats, "received"), 17);
+    assert_eq!(stats_field(&stats, "dropped"), 3);
+    assert_eq!(stats_field(&stats, "corrupted"), 1);
+
+    let dropped = messages
+        .iter()
+        .find(|s| s.name() == "testsignal-dropped")
+        .unwrap();
+    assert_eq!(dropped.get::<u32>("seqnum").unwrap(), 5);
+    assert_eq!(dropped.get::<u64>("count").unwrap(), 3);
+
+    let corrupted = messages
+        .iter()
+        .find(|s| s.name() == "testsignal-corrupted")
+        .unwrap();
+    assert_eq!(corrupted.get::<u32>("seqnum").unwrap(), 10);
+    assert!(corrupted.get::<f64>("error").unwrap() > 0.05);
+}
+
+#[test]
+fn test_video_duplicate() {
+    // Replace the 7th frame with a copy of the 6th frame
+    let previous = std::sync::Mutex::new(Vec::new());
+    let (_messages, stats) = run_pipeline(
+        "video/x-raw,format=GRAY8,width=160,height=120,framerate=30/1",
+        move |n, buffer| {
+            let mut map = buffer.map_writable().unwrap();
+            let mut previous = previous.lock().unwrap();
+            match n {
+                5 => *previous = map.to_vec(),
+                6 => map.copy_from_slice(&previous),
+                _ => (),
+            }
+
+            true
+        },
+    );
+
+    assert_eq!(stats_field(&stats, "received"), 20);
+    assert_eq!(stats_field(&stats, "duplicated"), 1);
+    assert_eq!(stats_field(&stats, "dropped"), 1);
+    assert_eq!(stats_field(&stats, "corrupted"), 0);
+    assert_eq!(stats_field(&stats, "out-of-order"), 0);
+}
+
+#[test]
+fn test_video_chroma_corruption() {
+    let (messages, stats) = run_pipeline(
+        "video/x-raw,format=I420,width=320,height=240,framerate=30/1",
+        |n, buffer| {
+            if n == 3 {
+                // Overwrite the V plane
+                let mut map = buffer.map_writable().unwrap();
+                let len = map.len();
+                map[len - len / 6..].fill(0);
+            }
+
+            true
+        },
+    );
+
+    assert_eq!(stats_field(&stats, "received"), 20);
+    assert_eq!(stats_field(&stats, "corrupted"), 1);
+
+    let corrupted = messages
+        .iter()
+        .find(|s| s.name() == "testsignal-corrupted")
+        .unwrap();
+    assert_eq!(corrupted.get::<u32>("seqnum").unwrap(), 3);
+}
+
+#[test]
+fn test_audio_drop() {
+    let (messages, stats) = run_pipeline("audio/x-raw,rate=48000,channels=2", |n, _| n != 3);
+
+    assert_eq!(stats_field(&stats, "received"), 19);
+    assert_eq!(stats_field(&stats, "dropped"), 1);
+    assert_eq!(stats_field(&stats, "corrupted"), 0);
+
+    let dropped = messages
+        .iter()
+        .find(|s| s.name() == "testsignal-dropped")
+        .unwrap();
+    assert_eq!(dropped.get::<u32>("seqnum").unwrap(), 3);
+    assert_eq!(dropped.get::<u64>("count").unwrap(), 1);
+}
+
+#[test]
+fn test_audio_resync() {
+    // Cut the 5th frame in half so that the verifier loses the frame boundaries and has to search
+    // for the next frame
+    let (_messages, stats) = run_pipeline("audio/x-raw,rate=8000,channels=1", |n, buffer| {
+        if n == 4 {
+            let size = buffer.size();
+            buffer.set_size(size / 2);
+        }
+
+        true
+    });
+
+    assert_eq!(stats_field(&stats, "received"), 19);
+    assert_eq!(stats_field(&stats, "dropped"), 1);
+    assert_eq!(stats_field(&stats, "corrupted"), 1);
+}
+
+#[test]
+fn test_audio_channel_corruption() {
+    // Silence the second channel of the 5th frame
+    let (messages, stats) = run_pipeline("audio/x-raw,rate=8000,channels=2", |n, buffer| {
+        if n == 4 {
+            let mut map = buffer.map_writable().unwrap();
+            for frame in map.chunks_exact_mut(4) {
+                frame[2..].fill(0);
+            }
+        }
+
+        true
+    });
+
+    assert_eq!(stats_field(&stats, "received"), 20);
+    assert_eq!(stats_field(&stats, "dropped"), 0);
+    assert_eq!(stats_field(&stats, "corrupted"), 1);
+
+    let corrupted = messages
+        .iter()
+        .find(|s| s.name() == "testsignal-corrupted")
+        .unwrap();
+    assert_eq!(corrupted.get::<u32>("seqnum").unwrap(), 4);
+    assert_eq!(corrupted.get::<f64>("error").unwrap(), 1.0);
+}
@@ -29,12 +29,16 @@ static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
 enum PlaylistError {
     #[error("plugin missing: {error}")]
     PluginMissing { error: anyhow::Error },
+    #[error("{start_times} start times set for {uris} URIs")]
+    InvalidSchedule { uris: usize, start_times: usize },
 }
 
 #[derive(Debug, Clone)]
 struct Settings {
     uris: Vec<String>,
     iterations: u32,
+    start_times: Vec<gst::ClockTime>,
+    fill_gaps: bool,
 }
 
 impl Default for Settings {
@@ -42,6 +46,8 @@ impl Default for Settings {
         Self {
             uris: vec![],
             iterations: 1,
+            start_times: vec![],
+            fill_gaps: true,
         }
     }
 }
@@ -55,6 +61,13 @@ struct State {
     current_item: Option<Item>,
     /// key are src pads from uridecodebin
     pads: HashMap<gst::Pad, Pads>,
+    /// items started in schedule mode whose first segment has not been output yet
+    scheduled_items: VecDeque<Item>,
+    /// schedule of the items being output, key are the group id of their streams
+    schedules: HashMap<gst::GroupId, ItemSchedule>,
+    /// difference between the actual output running time and the one computed by streamsynchronizer,
+    /// which does not know about the adjustments done on the previous items
+    schedule_correction: i64,
 
     // read-only properties
     current_iteration: u32,
@@ -69,20 +82,43 @@ struct Pads {
     ghost_src: gst::GhostPad,
 }
 
+#[derive(Debug, Clone, Copy)]
+struct ItemSchedule {
+    /// running time at which the item is output
+    start: gst::ClockTime,
+    /// duration skipped at the beginning of the item as it started late
+    trim: gst::ClockTime,
+    /// running time and duration of the gap before the item
+    gap: Option<(gst::ClockTime, gst::ClockTime)>,
+}
+
 impl State {
-    fn new(uris: Vec<String>, iterations: u32, uridecodebin: gst::Element) -> Self {
+    fn new(
+        uris: Vec<String>,
+        iterations: u32,
+        start_times: Vec<gst::ClockTime>,
+        uridecodebin: gst::Element,
+    ) -> Self {
         Self {
             uridecodebin,
-            playlist: Playlist::new(uris, iterations),
+            playlist: Playlist::new(uris, iterations, start_times),
             pending_current_items: VecDeque::new(),
             current_item: None,
             pads: HashMap::new(),
+            scheduled_items: VecDeque::new(),
+            schedules: HashMap::new(),
+            schedule_correction: 0,
             current_iteration: 0,
             current_uri_index: 0,
         }
     }
 
     fn update_iterations(&mut self, iterations: u32) {
+        if self.playlist.is_scheduled() {
+            // scheduled items are played only once
+            return;
+        }
+
         self.playlist.iterations = iterations;
     }
 }
@@ -99,8 +135,12 @@ struct Item {
 }
 
 impl Item {
-    fn new(uri: String, index: usize) -> Self {
-        let inner = ItemInner { uri, index };
+    fn new(uri: String, index: usize, start_time: Option<gst::ClockTime>) -> Self {
+        let inner = ItemInner {
+            uri,
+            index,
+            start_time,
+        };
 
         Self {
             inner: Arc::new(Mutex::new(inner)),
@@ -116,30 +156,50 @@ impl Item {
         let inner = self.inner.lock().unwrap();
         inner.index
     }
+
+    fn start_time(&self) -> Option<gst::ClockTime> {
+        let inner = self.inner.lock().unwrap();
+        inner.start_time
+    }
 }
 
 #[derive(Debug, Clone)]
 struct ItemInner {
     uri: String,
     index: usize,
+    /// clock time at which the item is scheduled to start, in schedule mode
+    start_time: Option<gst::ClockTime>,
 }
 
 struct Playlist {
     uris: Vec<String>,
     iterations: u32,
+    start_times: Vec<gst::ClockTime>,
 
     next_index: usize,
 }
 
 impl Playlist {
-    fn new(uris: Vec<String>, iterations: u32) -> Self {
+    fn new(uris: Vec<String>, iterations: u32, start_times: Vec<gst::ClockTime>) -> Self {
+        // scheduled items are played only once
+        let iterations = if start_times.is_empty() {
+            iterations
+        } else {
+            1
+        };
+
         Self {
             uris,
             iterations,
+            start_times,
             next_index: 0,
         }
     }
 
+    fn is_scheduled(&self) -> bool {
+        !self.start_times.is_empty()
+    }
+
     fn next(&mut self) -> Option<Item> {
         let uris_len = self.uris.len();
         let (iteration, uri_index) = (
@@ -153,7 +213,11 @@ impl Playlist {
         }
 
         let uri = self.uris[uri_index].clone();
-        let item = Item::new(uri, self.next_index);
+        let item = Item::new(
+            uri,
+            self.next_index,
+            self.start_times.get(uri_index).copied(),
+        );
 
         self.next_index += 1;
         if self.next_index == usize::MAX {
@@ -169,6 +233,7 @@ impl std::fmt::Debug for Playlist {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Playlist")
             .field("uris", &self.uris)
+            .field("start_times", &self.start_times)
             .finish()
     }
 }
@@ -205,6 +270,17 @@ impl ObjectImpl for UriPlaylistBin {
                     .blurb("The index from the uris property of the current URI being played")
                     .read_only()
                     .build(),
+                glib::ParamSpecBoxed::builder::<gst::Array>("start-times")
+                    .nick("Start times")
+                    .blurb("Clock times at which each of the URIs should start playing, enabling the schedule mode if not empty")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecBoolean::builder("fill-gaps")
+                    .nick("Fill gaps")
+                    .blurb("Fill the gap before scheduled items starting after the end of the previous one with silence for raw audio, the last frame for raw video and gap events otherwise")
+                    .default_value(Settings::default().fill_gaps)
+                    .mutable_playing()
+                    .build(),
             ]
         });
 
@@ -246,6 +322,43 @@ impl ObjectImpl for UriPlaylistBin {
                     }
                 }
             }
+            "start-times" => {
+                let array = value.get::<gst::Array>().expect("type checked upstream");
+                let Ok(new_value) = array
+                    .iter()
+                    .map(|v| v.get::<u64>().map(gst::ClockTime::from_nseconds))
+                    .collect::<Result<Vec<_>, _>>()
+                else {
+                    gst::error!(
+                        CAT,
+                        imp = self,
+                        "Ignoring start times {array:?}, they must be guint64 clock times",
+                    );
+                    return;
+                };
+
+                let mut settings = self.settings.lock().unwrap();
+                gst::info!(
+                    CAT,
+                    imp = self,
+                    "Changing start times from {:?} to {:?}",
+                    settings.start_times,
+                    new_value,
+                );
+                settings.start_times = new_value;
+            }
+            "fill-gaps" => {
+                let mut settings = self.settings.lock().unwrap();
+                let new_value = value.get().expect("type checked upstream");
+                gst::info!(
+                    CAT,
+                    imp = self,
+                    "Changing fill-gaps from {:?} to {:?}",
+                    settings.fill_gaps,
+                    new_value,
+                );
+                settings.fill_gaps = new_value;
+            }
             _ => unimplemented!(),
         }
     }
@@ -276,6 +389,14 @@ impl ObjectImpl for UriPlaylistBin {
                     .unwrap_or(0)
                     .to_value()
             }
+            "start-times" => {
+                let settings = self.settings.lock().unwrap();
+                gst::Array::new(settings.start_times.iter().map(|t| t.nseconds())).to_value()
+            }
+            "fill-gaps" => {
+                let settings = self.settings.lock().unwrap();
+                settings.fill_gaps.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -411,6 +532,18 @@ impl UriPlaylistBin {
             let mut state_guard = self.state.lock().unwrap();
             assert!(state_guard.is_none());
 
+            {
+                let settings = self.settings.lock().unwrap();
+                if !settings.start_times.is_empty()
+                    && settings.start_times.len() != settings.uris.len()
+                {
+                    return Err(PlaylistError::InvalidSchedule {
+                        uris: settings.uris.len(),
+                        start_times: settings.start_times.len(),
+                    });
+                }
+            }
+
             let uridecodebin = gst::ElementFactory::make("uridecodebin3")
                 .name("playlist-uridecodebin")
                 .build()
@@ -453,6 +586,57 @@ impl UriPlaylistBin {
                     .name(src_pad.name().as_str())
                     .build();
 
+                // keep the last raw video frame to fill gaps before scheduled items
+                let last_video_frame = Arc::new(Mutex::new(None));
+                ghost_src.add_probe(gst::PadProbeType::BUFFER, {
+                    let last_video_frame = last_video_frame.clone();
+                    move |pad, info| {
+                        if let Some(buffer) = info.buffer() {
+                            let caps = pad.current_caps().filter(|caps| {
+                                caps.structure(0).is_some_and(|s| s.name() == "video/x-raw")
+                            });
+                            *last_video_frame.lock().unwrap() =
+                                caps.map(|caps| (caps, buffer.clone()));
+                        }
+                        gst::PadProbeReturn::Ok
+                    }
+                });
+
+                // adjust the segment of each item to its scheduled start time
+                let bin_weak = bin.downgrade();
+                let group_id = Mutex::new(None);
+                ghost_src.add_probe(gst::PadProbeType::EVENT_DOWNSTREAM, move |pad, info| {
+                    let Some(gst::PadProbeData::Event(ref mut event)) = info.data else {
+                        return gst::PadProbeReturn::Ok;
+                    };
+
+                    let new_event = match event.view() {
+                        gst::EventView::StreamStart(ev) => {
+                            *group_id.lock().unwrap() = ev.group_id();
+                            None
+                        }
+                        gst::EventView::Segment(ev) => {
+                            // only the first segment of each item is scheduled
+                            let group_id = group_id.lock().unwrap().take();
+                            match (group_id, bin_weak.upgrade()) {
+                                (Some(group_id), Some(bin)) => {
+                                    let last_video_frame = last_video_frame.lock().unwrap().take();
+                                    bin.imp()
+                                        .schedule_segment(pad, group_id, ev, last_video_frame)
+                                }
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(new_event) = new_event {
+                        *event = new_event;
+                    }
+
+                    gst::PadProbeReturn::Ok
+                });
+
                 ghost_src.set_active(true).unwrap();
                 bin.add_pad(&ghost_src).unwrap();
 
@@ -526,6 +710,7 @@ impl UriPlaylistBin {
             *state_guard = Some(State::new(
                 settings.uris.clone(),
                 settings.iterations,
+                settings.start_times.clone(),
                 uridecodebin,
             ));
         }
@@ -561,6 +746,9 @@ impl UriPlaylistBin {
         let uridecodebin = state.uridecodebin.clone();
         let uri = item.uri();
 
+        if state.playlist.is_scheduled() {
+            state.scheduled_items.push_back(item.clone());
+        }
         state.pending_current_items.push_back(Some(item));
 
         drop(state_guard);
@@ -569,6 +757,228 @@ impl UriPlaylistBin {
         Ok(())
     }
 
+    /// Compute the schedule of the item whose streams are in `group_id`, `running_time` being the
+    /// running time of its first segment as output by streamsynchronizer.
+    fn item_schedule(
+        &self,
+        group_id: gst::GroupId,
+        running_time: gst::ClockTime,
+    ) -> Option<ItemSchedule> {
+        let base_time = self.obj().base_time();
+
+        let mut state_guard = self.state.lock().unwrap();
+        let state = state_guard.as_mut()?;
+
+        if let Some(schedule) = state.schedules.get(&group_id) {
+            return Some(*schedule);
+        }
+
+        let item = state.scheduled_items.pop_front()?;
+
+        // running time at which the previous item actually ended
+        let output_end = gst::ClockTime::from_nseconds(
+            (running_time.nseconds() as i64 + state.schedule_correction).max(0) as u64,
+        );
+        let scheduled = match (item.start_time(), base_time) {
+            (Some(start_time), Some(base_time)) => start_time.saturating_sub(base_time),
+            _ => {
+                gst::warning!(
+                    CAT,
+                    imp = self,
+                    "No base time yet, item #{} can't be scheduled",
+                    item.index()
+                );
+                output_end
+            }
+        };
+
+        let start = scheduled.max(output_end);
+        let trim = output_end.saturating_sub(scheduled);
+        let gap = scheduled.saturating_sub(output_end);
+
+        if trim > gst::ClockTime::ZERO {
+            gst::info!(
+                CAT,
+                imp = self,
+                "item #{} ({}) started {} late, trimming",
+                item.index(),
+                item.uri(),
+                trim
+            );
+        } else {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "item #{} ({}) starting at running time {}, gap {}",
+                item.index(),
+                item.uri(),
+                start,
+                gap
+            );
+        }
+
+        state.schedule_correction =
+            start.nseconds() as i64 - running_time.nseconds() as i64 - trim.nseconds() as i64;
+
+        let schedule = ItemSchedule {
+            start,
+            trim,
+            gap: (gap > gst::ClockTime::ZERO).then_some((output_end, gap)),
+        };
+        state.schedules.insert(group_id, schedule);
+
+        Some(schedule)
+    }
+
+    /// Fill the gap of `duration` starting at the running time `start` before a scheduled item.
+    ///
+    /// Raw audio is filled with silence in the caps of the item and raw video by repeating
+    /// `last_video_frame`, the last frame of the previous item, if its caps did not change.
+    /// Otherwise a gap event is sent.
+    fn fill_gap(
+        &self,
+        pad: &gst::Pad,
+        start: gst::ClockTime,
+        duration: gst::ClockTime,
+        last_video_frame: Option<(gst::Caps, gst::Buffer)>,
+    ) {
+        gst::log!(
+            CAT,
+            obj = pad,
+            "filling gap of {} at running time {}",
+            duration,
+            start
+        );
+
+        let mut gap_segment = gst::FormattedSegment::<gst::ClockTime>::new();
+        gap_segment.set_base(start);
+        let _ = pad.push_event(gst::event::Segment::new(&gap_segment));
+
+        let caps = pad.current_caps();
+        let audio_info = caps
+            .as_ref()
+            .and_then(|caps| gst_audio::AudioInfo::from_caps(caps).ok());
+        let last_video_frame =
+            last_video_frame.filter(|(frame_caps, _)| caps.as_ref() == Some(frame_caps));
+
+        if let Some(audio_info) = audio_info {
+            // 100ms of silence per buffer
+            let rate = audio_info.rate() as u64;
+            let bpf = audio_info.bpf() as usize;
+            let total_samples = duration
+                .nseconds()
+                .mul_div_floor(rate, gst::ClockTime::SECOND.nseconds())
+                .unwrap_or(0);
+            let mut offset = 0;
+            while offset < total_samples {
+                let n_samples = (rate / 10).max(1).min(total_samples - offset);
+                let mut buffer = gst::Buffer::with_size(n_samples as usize * bpf).unwrap();
+                {
+                    let buffer = buffer.get_mut().unwrap();
+                    let pts = offset
+                        .mul_div_floor(gst::ClockTime::SECOND.nseconds(), rate)
+                        .map(gst::ClockTime::from_nseconds);
+                    let end = (offset + n_samples)
+                        .mul_div_floor(gst::ClockTime::SECOND.nseconds(), rate)
+                        .map(gst::ClockTime::from_nseconds);
+                    buffer.set_pts(pts);
+                    buffer.set_duration(end.opt_saturating_sub(pts));
+                    buffer.set_flags(gst::BufferFlags::GAP);
+                    let mut map = buffer.map_writable().unwrap();
+                    audio_info.format_info().fill_silence(map.as_mut_slice());
+                }
+                if pad.push(buffer).is_err() {
+                    return;
+                }
+                offset += n_samples;
+            }
+        } else if let Some((caps, frame)) = last_video_frame {
+            // one frame per frame duration, or a single frame for the whole gap
+            let frame_duration = caps
+                .structure(0)
+                .and_then(|s| s.get::<gst::Fraction>("framerate").ok())
+                .filter(|fps| fps.numer() > 0 && fps.denom() > 0)
+                .and_then(|fps| {
+                    gst::ClockTime::SECOND.mul_div_floor(fps.denom() as u64, fps.numer() as u64)
+                })
+                .filter(|d| *d > gst::ClockTime::ZERO)
+                .unwrap_or(duration);
+
+            let mut pts = gst::ClockTime::ZERO;
+            while pts < duration {
+                let mut buffer = frame.copy();
+                {
+                    let buffer = buffer.get_mut().unwrap();
+                    buffer.set_pts(pts);
+                    buffer.set_dts(gst::ClockTime::NONE);
+                    buffer.set_duration(frame_duration.min(duration - pts));
+                    buffer.set_flags(gst::BufferFlags::GAP);
+                    buffer.unset_flags(gst::BufferFlags::DISCONT);
+                }
+                if pad.push(buffer).is_err() {
+                    return;
+                }
+                pts += frame_duration;
+            }
+        } else {
+            let _ = pad.push_event(
+                gst::event::Gap::builder(gst::ClockTime::ZERO)
+                    .duration(duration)
+                    .build(),
+            );
+        }
+    }
+
+    /// Adjust the first segment of a scheduled item so it starts at its scheduled time,
+    /// returning the segment event to push instead of `ev`.
+    fn schedule_segment(
+        &self,
+        pad: &gst::Pad,
+        group_id: gst::GroupId,
+        ev: &gst::event::Segment,
+        last_video_frame: Option<(gst::Caps, gst::Buffer)>,
+    ) -> Option<gst::Event> {
+        let segment = ev.segment().downcast_ref::<gst::ClockTime>()?;
+        if segment.rate() < 0.0 {
+            return None;
+        }
+
+        let running_time = segment.to_running_time(segment.start())?;
+        let schedule = self.item_schedule(group_id, running_time)?;
+
+        let fill_gaps = self.settings.lock().unwrap().fill_gaps;
+        if let Some((gap_start, gap_duration)) = schedule.gap.filter(|_| fill_gaps) {
+            self.fill_gap(pad, gap_start, gap_duration, last_video_frame);
+        }
+
+        let mut segment = segment.clone();
+        if schedule.trim > gst::ClockTime::ZERO {
+            let start = segment.start()? + schedule.trim;
+            let start = segment.stop().map_or(start, |stop| start.min(stop));
+
+            segment.set_start(start);
+            segment.set_position(start);
+            if let Some(time) = segment.time() {
+                segment.set_time(time + schedule.trim);
+            }
+        }
+
+        let running_time = segment.to_running_time(segment.start())?;
+        let base = segment.base()?;
+        let base = if schedule.start >= running_time {
+            base + (schedule.start - running_time)
+        } else {
+            base.saturating_sub(running_time - schedule.start)
+        };
+        segment.set_base(base);
+
+        Some(
+            gst::event::Segment::builder(&segment)
+                .seqnum(ev.seqnum())
+                .build(),
+        )
+    }
+
     fn update_current(&self, mut state_guard: MutexGuard<Option<State>>, current: Option<Item>) {
         let (uris_len, infinite) = {
             let settings = self.settings.lock().unwrap();
@@ -624,6 +1034,9 @@ impl UriPlaylistBin {
             PlaylistError::PluginMissing { .. } => {
                 gst::element_imp_error!(self, gst::CoreError::MissingPlugin, ["{}", &error_msg]);
             }
+            PlaylistError::InvalidSchedule { .. } => {
+                gst::element_imp_error!(self, gst::LibraryError::Settings, ["{}", &error_msg]);
+            }
         }
 
         self.update_current(self.state.lock().unwrap(), None);
@@ -7,10 +7,11 @@
 // SPDX-License-Identifier: MPL-2.0
 
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
 use gst::prelude::*;
 use gst::MessageView;
-use more_asserts::assert_ge;
+use more_asserts::{assert_ge, assert_le};
 
 struct TestMedia {
     uri: String,
@@ -458,3 +459,167 @@ fn infinite_to_finite() {
     assert_eq!(current_iteration, 3);
     assert_eq!(current_uri_index, 0);
 }
+
+#[test]
+fn schedule() {
+    init();
+
+    let media = TestMedia::ogg();
+    let uris = vec![media.uri.clone(), media.uri.clone()];
+
+    let pipeline = Pipeline(gst::Pipeline::default());
+    let clock = gst::SystemClock::obtain();
+    let base_time = clock.time().unwrap();
+    pipeline.use_clock(Some(&clock));
+    pipeline.set_start_time(gst::ClockTime::NONE);
+    pipeline.set_base_time(base_time);
+
+    // leave a gap of 1 second between the two items
+    let second_start = base_time + media.len + gst::ClockTime::SECOND;
+    let playlist = gst::ElementFactory::make("uriplaylistbin")
+        .property("uris", &uris)
+        .property(
+            "start-times",
+            gst::Array::new([base_time.nseconds(), second_start.nseconds()]),
+        )
+        .build()
+        .unwrap();
+    let sink = gst::ElementFactory::make("fakesink")
+        .property("sync", true)
+        .build()
+        .unwrap();
+
+    pipeline.add_many([&playlist, &sink]).unwrap();
+
+    let sink_pad = sink.static_pad("sink").unwrap();
+    playlist.connect_pad_added(move |_playlist, src_pad| {
+        src_pad.link(&sink_pad).unwrap();
+    });
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let bus = pipeline.bus().unwrap();
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::NONE,
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        )
+        .unwrap();
+    assert_eos(msg);
+
+    let sample: gst::Sample = sink.property("last-sample");
+    let buffer = sample.buffer().unwrap();
+    let segment = sample.segment().unwrap();
+    let segment = segment.downcast_ref::<gst::ClockTime>().unwrap();
+    let end = segment.to_running_time(buffer.pts().unwrap()).unwrap() + buffer.duration().unwrap();
+
+    assert_ge!(end, second_start - base_time + media.len);
+}
+
+#[test]
+fn schedule_invalid() {
+    init();
+
+    let playlist = gst::ElementFactory::make("uriplaylistbin")
+        .property("uris", vec![TestMedia::ogg().uri, TestMedia::mkv().uri])
+        .property("start-times", gst::Array::new([0u64]))
+        .build()
+        .unwrap();
+
+    assert!(playlist.set_state(gst::State::Ready).is_err());
+}
+
+#[test]
+fn schedule_fill_gap() {
+    init();
+
+    let media = TestMedia::ogg();
+    let uris = vec![media.uri.clone(), media.uri.clone()];
+
+    let pipeline = Pipeline(gst::Pipeline::default());
+    let clock = gst::SystemClock::obtain();
+    let base_time = clock.time().unwrap();
+    pipeline.use_clock(Some(&clock));
+    pipeline.set_start_time(gst::ClockTime::NONE);
+    pipeline.set_base_time(base_time);
+
+    let second_start = base_time + media.len + gst::ClockTime::SECOND;
+    let playlist = gst::ElementFactory::make("uriplaylistbin")
+        .property("uris", &uris)
+        .property(
+            "start-times",
+            gst::Array::new([base_time.nseconds(), second_start.nseconds()]),
+        )
+        .build()
+        .unwrap();
+    let sink = gst::ElementFactory::make("fakesink")
+        .property("sync", true)
+        .build()
+        .unwrap();
+
+    pipeline.add_many([&playlist, &sink]).unwrap();
+
+    // running time ranges of the buffers filling the gap
+    let gap_buffers = Arc::new(Mutex::new(Vec::new()));
+    let sink_pad = sink.static_pad("sink").unwrap();
+    sink_pad.add_probe(gst::PadProbeType::BUFFER, {
+        let gap_buffers = gap_buffers.clone();
+        move |pad, info| {
+            let buffer = info.buffer().unwrap();
+            if buffer.flags().contains(gst::BufferFlags::GAP) {
+                let segment = pad.sticky_event::<gst::event::Segment>(0).unwrap();
+                let segment = segment.segment().downcast_ref::<gst::ClockTime>().unwrap();
+                let start = segment.to_running_time(buffer.pts().unwrap()).unwrap();
+                gap_buffers
+                    .lock()
+                    .unwrap()
+                    .push((start, start + buffer.duration().unwrap()));
+            }
+            gst::PadProbeReturn::Ok
+        }
+    });
+    playlist.connect_pad_added(move |_playlist, src_pad| {
+        src_pad.link(&sink_pad).unwrap();
+    });
+
+    pipeline.set_state(gst::State::Playing).unwrap();
+
+    let bus = pipeline.bus().unwrap();
+    let msg = bus
+        .timed_pop_filtered(
+            gst::ClockTime::NONE,
+            &[gst::MessageType::Eos, gst::MessageType::Error],
+        )
+        .unwrap();
+    assert_eos(msg);
+
+    // the gap between both items was filled with actual buffers
+    let gap_buffers = gap_buffers.lock().unwrap();
+    assert!(!gap_buffers.is_empty());
+    let (first_start, _) = gap_buffers.first().unwrap();
+    let (_, last_end) = gap_buffers.last().unwrap();
+    assert_ge!(*first_start, media.len);
+    // silence can end up to one sample before the end of the gap
+    assert_le!(*last_end, second_start - base_time);
+    assert_ge!(
+        *last_end + gst::ClockTime::MSECOND,
+        second_start - base_time
+    );
+}
+
+#[test]
+fn schedule_invalid_start_times() {
+    init();
+
+    let playlist = gst::ElementFactory::make("uriplaylistbin")
+        .property("uris", vec![TestMedia::ogg().uri])
+        .build()
+        .unwrap();
+
+    // start times must be unsigned 64 bits clock times
+    playlist.set_property("start-times", gst::Array::new([1i32]));
+    assert!(playlist.property::<gst::Array>("start-times").is_empty());
+
+    playlist.set_property("start-times", gst::Array::new([1u64]));
+    assert_eq!(playlist.property::<gst::Array>("start-times").len(), 1);
+}
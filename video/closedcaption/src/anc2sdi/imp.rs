@@ -0,0 +1,483 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use crate::st2038anc_utils::{write_vanc_packet, AncDataHeader, VancFormat, VancPacket};
+
+#[derive(Default)]
+struct State {
+    video_info: Option<gst_video::VideoInfo>,
+    format: Option<VancFormat>,
+    video_segment: gst::FormattedSegment<gst::ClockTime>,
+    anc_segment: gst::FormattedSegment<gst::ClockTime>,
+    /// ST-2038 buffers waiting to be inserted, with their running time
+    pending: VecDeque<(Option<gst::ClockTime>, gst::Buffer)>,
+}
+
+#[derive(Clone)]
+struct Settings {
+    line_offset: u16,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { line_offset: 1 }
+    }
+}
+
+pub struct Anc2Sdi {
+    sinkpad: gst::Pad,
+    anc_sinkpad: gst::Pad,
+    srcpad: gst::Pad,
+
+    state: Mutex<State>,
+    settings: Mutex<Settings>,
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "anc2sdi",
+        gst::DebugColorFlags::empty(),
+        Some("ST-2038 ANC to SDI video Element"),
+    )
+});
+
+impl Anc2Sdi {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        mut buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, obj = pad, "Handling buffer {:?}", buffer);
+
+        let mut state = self.state.lock().unwrap();
+        let settings = self.settings.lock().unwrap().clone();
+
+        let (Some(info), Some(format)) = (state.video_info.clone(), state.format) else {
+            gst::error!(CAT, imp = self, "No caps set");
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        // Take all ANC that is due until the end of this frame
+        let end = buffer
+            .pts()
+            .map(|pts| pts + buffer.duration().unwrap_or(gst::ClockTime::ZERO));
+        let end_running_time = state.video_segment.to_running_time(end);
+
+        let mut anc_buffers = Vec::new();
+        while let Some((running_time, _)) = state.pending.front() {
+            let due = match (running_time, end_running_time) {
+                (Some(running_time), Some(end_running_time)) => {
+                    *running_time < end_running_time
+                        || (buffer.duration().is_none() && *running_time == end_running_time)
+                }
+                _ => true,
+            };
+            if !due {
+                break;
+            }
+            anc_buffers.push(state.pending.pop_front().unwrap().1);
+        }
+        drop(state);
+
+        if anc_buffers.is_empty() {
+            return self.srcpad.push(buffer);
+        }
+
+        {
+            let buffer = buffer.make_mut();
+            let mut frame = gst_video::VideoFrameRef::from_buffer_ref_writable(buffer, &info)
+                .map_err(|_| {
+                    gst::error!(CAT, obj = pad, "Can't map buffer writable");
+
+                    gst::FlowError::Error
+                })?;
+
+            let width = frame.width() as usize;
+            let height = frame.height() as usize;
+            let stride = frame.plane_stride()[0] as usize;
+            let data = frame.plane_data_mut(0).unwrap();
+
+            // Next free sample per line and channel
+            let mut offsets = HashMap::<(usize, bool), usize>::new();
+
+            for anc in anc_buffers {
+                let map = anc.map_readable().map_err(|_| {
+                    gst::error!(CAT, obj = pad, "Can't map buffer readable");
+
+                    gst::FlowError::Error
+                })?;
+
+                let mut slice = map.as_slice();
+                while !slice.is_empty() {
+                    // Stop on stuffing bytes
+                    if slice[0] == 0b1111_1111 {
+                        break;
+                    }
+
+                    let header = match AncDataHeader::from_slice(slice) {
+                        Ok(anc_hdr) => anc_hdr,
+                        Err(err) => {
+                            gst::debug!(
+                                CAT,
+                                imp = self,
+                                "Failed to parse ancillary data header: {err:?}"
+                            );
+                            break;
+                        }
+                    };
+
+                    gst::trace!(CAT, imp = self, "Parsed ST2038 header {header:?}");
+
+                    let payload = match header.payload(slice) {
+                        Ok(payload) => payload,
+                        Err(err) => {
+                            gst::debug!(CAT, imp = self, "Failed to parse ANC payload: {err:?}");
+                            break;
+                        }
+                    };
+                    slice = &slice[header.len..];
+
+                    let Some(line) = (header.line_number as usize)
+                        .checked_sub(settings.line_offset as usize)
+                        .filter(|line| *line < height)
+                    else {
+                        gst::debug!(
+                            CAT,
+                            imp = self,
+                            "Line {} is not part of the video frame",
+                            header.line_number
+                        );
+                        continue;
+                    };
+
+                    let packet = VancPacket {
+                        c_not_y_channel: header.c_not_y_channel_flag,
+                        horizontal_offset: header.horizontal_offset,
+                        did: header.did,
+                        sdid: header.sdid,
+                        payload,
+                    };
+
+                    let offset = offsets.entry((line, packet.c_not_y_channel)).or_default();
+                    let start = usize::max(*offset, packet.horizontal_offset as usize);
+
+                    match write_vanc_packet(
+                        format,
+                        &mut data[line * stride..][..stride],
+                        width,
+                        start,
+                        &packet,
+                    ) {
+                        Some(end) => *offset = end,
+                        None => {
+                            gst::warning!(
+                                CAT,
+                                imp = self,
+                                "ANC packet with DID {} and SDID {} does not fit into line {}",
+                                packet.did,
+                                packet.sdid,
+                                header.line_number,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        self.srcpad.push(buffer)
+    }
+
+    fn anc_sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, obj = pad, "Handling ANC buffer {:?}", buffer);
+
+        let mut state = self.state.lock().unwrap();
+        let running_time = state.anc_segment.to_running_time(buffer.pts());
+        state.pending.push_back((running_time, buffer));
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    #[allow(clippy::single_match)]
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {:?}", event);
+        match event.view() {
+            EventView::Caps(ev) => {
+                let caps = ev.caps();
+
+                let info = match gst_video::VideoInfo::from_caps(caps) {
+                    Ok(info) => info,
+                    Err(_) => {
+                        gst::error!(CAT, imp = self, "Invalid caps {caps:?}");
+                        return false;
+                    }
+                };
+
+                let Some(format) = VancFormat::from_video_format(info.format()) else {
+                    gst::error!(CAT, imp = self, "Unsupported caps {caps:?}");
+                    return false;
+                };
+
+                gst::debug!(CAT, imp = self, "Configuring format {format:?}");
+
+                let mut state = self.state.lock().unwrap();
+                state.video_info = Some(info);
+                state.format = Some(format);
+            }
+            EventView::Segment(ev) => match ev.segment().downcast_ref::<gst::ClockTime>() {
+                Some(segment) => {
+                    self.state.lock().unwrap().video_segment = segment.clone();
+                }
+                None => {
+                    gst::error!(CAT, imp = self, "Non-TIME segment");
+                    return false;
+                }
+            },
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    fn anc_sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling ANC event {:?}", event);
+        match event.view() {
+            EventView::Segment(ev) => match ev.segment().downcast_ref::<gst::ClockTime>() {
+                Some(segment) => {
+                    self.state.lock().unwrap().anc_segment = segment.clone();
+                }
+                None => {
+                    gst::error!(CAT, imp = self, "Non-TIME segment");
+                    return false;
+                }
+            },
+            EventView::FlushStop(..) => {
+                self.state.lock().unwrap().pending.clear();
+            }
+            _ => (),
+        }
+
+        // Everything else is only forwarded from the video stream
+        true
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj = pad, "Handling query {:?}", query);
+        match query.view_mut() {
+            QueryViewMut::Caps(q) => {
+                let caps = self
+                    .sinkpad
+                    .peer_query_caps(q.filter())
+                    .intersect(&self.sinkpad.pad_template_caps());
+                q.set_result(&caps);
+                true
+            }
+            _ => self.sinkpad.peer_query(query),
+        }
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Anc2Sdi {
+    const NAME: &'static str = "GstAnc2Sdi";
+    type Type = super::Anc2Sdi;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                Anc2Sdi::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Anc2Sdi::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .flags(gst::PadFlags::PROXY_CAPS | gst::PadFlags::PROXY_ALLOCATION)
+            .build();
+
+        let templ = klass.pad_template("anc_sink").unwrap();
+        let anc_sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                Anc2Sdi::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.anc_sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Anc2Sdi::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.anc_sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                Anc2Sdi::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.src_query(pad, query),
+                )
+            })
+            .build();
+
+        Self {
+            sinkpad,
+            anc_sinkpad,
+            srcpad,
+            state: Mutex::new(State::default()),
+            settings: Mutex::new(Settings::default()),
+        }
+    }
+}
+
+impl ObjectImpl for Anc2Sdi {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![glib::ParamSpecUInt::builder("line-offset")
+                .nick("Line Offset")
+                .blurb("Line number of the first line of the video frames")
+                .default_value(Settings::default().line_offset as u32)
+                .maximum(2047)
+                .mutable_playing()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "line-offset" => {
+                let mut settings = self.settings.lock().unwrap();
+
+                settings.line_offset = value.get::<u32>().expect("type checked upstream") as u16;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "line-offset" => {
+                let settings = self.settings.lock().unwrap();
+                (settings.line_offset as u32).to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.anc_sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for Anc2Sdi {}
+
+impl ElementImpl for Anc2Sdi {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "ST-2038 ANC to SDI",
+                "Generic",
+                "Inserts ST-2038 ANC packets into the lines of raw SDI video",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &VancFormat::caps(),
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &VancFormat::caps(),
+            )
+            .unwrap();
+
+            let anc_sink_pad_template = gst::PadTemplate::new(
+                "anc_sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("meta/x-st-2038").build(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template, anc_sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                *self.state.lock().unwrap() = State::default();
+            }
+            _ => (),
+        }
+
+        let ret = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                *self.state.lock().unwrap() = State::default();
+            }
+            _ => (),
+        }
+
+        Ok(ret)
+    }
+}
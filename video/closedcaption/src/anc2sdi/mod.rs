@@ -0,0 +1,35 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-anc2sdi
+ *
+ * Inserts ST-2038 ANC packets received on the `anc_sink` pad into the lines of raw
+ * v210 or UYVY video frames, for output via SDI playout cards.
+ *
+ * Packets are inserted into the first video frame ending after their running time.
+ * The line of each packet is its ST-2038 line number minus #GstAnc2Sdi:line-offset.
+ * Packets for the same line and channel are placed one after another.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct Anc2Sdi(ObjectSubclass<imp::Anc2Sdi>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "anc2sdi",
+        gst::Rank::NONE,
+        Anc2Sdi::static_type(),
+    )
+}
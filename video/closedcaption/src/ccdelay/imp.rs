@@ -0,0 +1,330 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+use gst_base::subclass::prelude::*;
+
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "ccdelay",
+        gst::DebugColorFlags::empty(),
+        Some("Closed Caption Delay"),
+    )
+});
+
+const DEFAULT_EXTRA_DELAY: gst::ClockTime = gst::ClockTime::ZERO;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    video_pad: Option<gst::Pad>,
+    extra_delay: gst::ClockTime,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            video_pad: None,
+            extra_delay: DEFAULT_EXTRA_DELAY,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    frame_duration: Option<gst::ClockTime>,
+    /// Delay applied to the caption buffers, `None` until the latencies were queried
+    delay: Option<gst::ClockTime>,
+    last_pts: Option<gst::ClockTime>,
+}
+
+#[derive(Default)]
+pub struct CCDelay {
+    settings: Mutex<Settings>,
+    state: Mutex<State>,
+}
+
+impl CCDelay {
+    fn query_min_latency(pad: &gst::Pad) -> Option<gst::ClockTime> {
+        let mut q = gst::query::Latency::new();
+        let res = match pad.direction() {
+            gst::PadDirection::Sink => pad.peer_query(&mut q),
+            _ => pad.query(&mut q),
+        };
+
+        res.then(|| q.result().1)
+    }
+
+    /// Query the latencies of the video and caption paths and update the delay
+    fn update_delay(&self) -> gst::ClockTime {
+        let settings = self.settings.lock().unwrap().clone();
+
+        let video_latency = settings
+            .video_pad
+            .as_ref()
+            .and_then(Self::query_min_latency);
+        let cc_latency = Self::query_min_latency(&self.obj().sink_pad());
+
+        let mut delay = match (video_latency, cc_latency) {
+            (Some(video_latency), Some(cc_latency)) => video_latency.saturating_sub(cc_latency),
+            _ => {
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Latency unknown, video {video_latency:?} captions {cc_latency:?}"
+                );
+                gst::ClockTime::ZERO
+            }
+        } + settings.extra_delay;
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(frame_duration) = state.frame_duration.filter(|d| !d.is_zero()) {
+            delay = frame_duration * delay.nseconds().div_ceil(frame_duration.nseconds());
+        }
+
+        let changed = state.delay != Some(delay);
+        state.delay = Some(delay);
+        drop(state);
+
+        if changed {
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Delaying captions by {delay}, video latency {video_latency:?} captions latency {cc_latency:?}"
+            );
+            self.obj().notify("delay");
+        }
+
+        delay
+    }
+
+    fn delay(&self) -> gst::ClockTime {
+        let delay = self.state.lock().unwrap().delay;
+
+        delay.unwrap_or_else(|| self.update_delay())
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for CCDelay {
+    const NAME: &'static str = "GstCCDelay";
+    type Type = super::CCDelay;
+    type ParentType = gst_base::BaseTransform;
+}
+
+impl ObjectImpl for CCDelay {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                /**
+                 * GstCCDelay:video-pad:
+                 *
+                 * A pad on the video path, for example the video sink pad of the element
+                 * the captions are combined with. The captions are delayed by the difference
+                 * between the latency upstream of this pad and the latency of the captions.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecObject::builder::<gst::Pad>("video-pad")
+                    .nick("Video Pad")
+                    .blurb("Pad on the video path whose latency the captions are delayed to match")
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt64::builder("extra-delay")
+                    .nick("Extra Delay")
+                    .blurb("Additional delay (in ns) applied to the captions")
+                    .maximum(u64::MAX - 1)
+                    .default_value(DEFAULT_EXTRA_DELAY.nseconds())
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt64::builder("delay")
+                    .nick("Delay")
+                    .blurb("Delay (in ns) currently applied to the captions")
+                    .maximum(u64::MAX - 1)
+                    .read_only()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "video-pad" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.video_pad = value.get().expect("type checked upstream");
+            }
+            "extra-delay" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.extra_delay = value.get::<u64>().unwrap().nseconds();
+                drop(settings);
+
+                // Apply on the next buffer
+                self.state.lock().unwrap().delay = None;
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "video-pad" => {
+                let settings = self.settings.lock().unwrap();
+                settings.video_pad.to_value()
+            }
+            "extra-delay" => {
+                let settings = self.settings.lock().unwrap();
+                settings.extra_delay.nseconds().to_value()
+            }
+            "delay" => {
+                let state = self.state.lock().unwrap();
+                state
+                    .delay
+                    .unwrap_or(gst::ClockTime::ZERO)
+                    .nseconds()
+                    .to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for CCDelay {}
+
+impl ElementImpl for CCDelay {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Closed Caption Delay",
+                "Filter/ClosedCaption",
+                "Delays closed captions to match the latency of a video path",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst::Caps::builder_full()
+                .structure(gst::Structure::new_empty("closedcaption/x-cea-608"))
+                .structure(gst::Structure::new_empty("closedcaption/x-cea-708"))
+                .build();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for CCDelay {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::AlwaysInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn transform_ip(&self, buf: &mut gst::BufferRef) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let delay = self.delay();
+
+        let mut state = self.state.lock().unwrap();
+        if let Some(pts) = buf.pts() {
+            // Keep the output timestamps monotonic when the delay is reduced
+            let pts = (pts + delay).max(state.last_pts.unwrap_or(gst::ClockTime::ZERO));
+            buf.set_pts(pts);
+            state.last_pts = Some(pts);
+        }
+        buf.set_dts(buf.dts().map(|dts| dts + delay));
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn sink_event(&self, event: gst::Event) -> bool {
+        match event.view() {
+            gst::EventView::Gap(gap) => {
+                let delay = self.delay();
+                let (timestamp, duration) = gap.get();
+
+                let event = gst::event::Gap::builder(timestamp + delay)
+                    .duration(duration)
+                    .seqnum(event.seqnum())
+                    .build();
+                self.parent_sink_event(event)
+            }
+            gst::EventView::FlushStop(_) => {
+                self.state.lock().unwrap().last_pts = None;
+                self.parent_sink_event(event)
+            }
+            _ => self.parent_sink_event(event),
+        }
+    }
+
+    fn query(&self, direction: gst::PadDirection, query: &mut gst::QueryRef) -> bool {
+        let res = self.parent_query(direction, query);
+
+        // Latency queries from downstream are sent whenever the pipeline latency
+        // is recalculated, including after latency changes on the video path
+        if res && direction == gst::PadDirection::Src && query.type_() == gst::QueryType::Latency {
+            self.update_delay();
+        }
+
+        res
+    }
+
+    fn set_caps(&self, incaps: &gst::Caps, outcaps: &gst::Caps) -> Result<(), gst::LoggableError> {
+        if incaps != outcaps {
+            return Err(gst::loggable_error!(
+                CAT,
+                "Input and output caps are not the same"
+            ));
+        }
+
+        let frame_duration = incaps
+            .structure(0)
+            .and_then(|s| s.get::<gst::Fraction>("framerate").ok())
+            .filter(|framerate| framerate.numer() > 0 && framerate.denom() > 0)
+            .and_then(|framerate| {
+                gst::ClockTime::SECOND
+                    .mul_div_ceil(framerate.denom() as u64, framerate.numer() as u64)
+            });
+
+        let mut state = self.state.lock().unwrap();
+        if state.frame_duration != frame_duration {
+            state.frame_duration = frame_duration;
+            state.delay = None;
+        }
+
+        Ok(())
+    }
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        *self.state.lock().unwrap() = State::default();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,36 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-ccdelay
+ *
+ * Delays closed caption buffers by the difference between the latency of a video path
+ * and the latency of the caption path, so that captions don't lead the video when
+ * processing adding latency is inserted on the video path.
+ *
+ * The video path is selected with the #GstCCDelay:video-pad property. Its latency is
+ * queried again whenever the pipeline recalculates its latency. The delay is rounded up
+ * to whole frames when the caption caps have a framerate.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct CCDelay(ObjectSubclass<imp::CCDelay>) @extends gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "ccdelay",
+        gst::Rank::NONE,
+        CCDelay::static_type(),
+    )
+}
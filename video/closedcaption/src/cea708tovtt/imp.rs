@@ -0,0 +1,509 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use atomic_refcell::AtomicRefCell;
+
+use std::sync::{LazyLock, Mutex};
+
+use crate::ccutils::extract_cdp;
+
+use super::screen::{Cue, Screen};
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "cea708tovtt",
+        gst::DebugColorFlags::empty(),
+        Some("CEA-708 to WebVTT Element"),
+    )
+});
+
+const DEFAULT_SERVICE: i32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    CcData,
+    Cdp,
+}
+
+#[derive(Debug, Clone)]
+struct Settings {
+    service: i32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            service: DEFAULT_SERVICE,
+        }
+    }
+}
+
+struct State {
+    format: Option<Format>,
+    /// Whether fragmented WebVTT with inline headers was negotiated downstream
+    fragmented: bool,
+    wrote_header: bool,
+    cc_data_parser: cea708_types::CCDataParser,
+    screen: Screen,
+    previous_cues: Option<(gst::ClockTime, Vec<Cue>)>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            format: None,
+            fragmented: false,
+            wrote_header: false,
+            cc_data_parser: cea708_types::CCDataParser::default(),
+            screen: Screen::default(),
+            previous_cues: None,
+        }
+    }
+}
+
+pub struct Cea708ToVtt {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+
+    settings: Mutex<Settings>,
+    state: AtomicRefCell<State>,
+}
+
+impl Cea708ToVtt {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj = pad, "Handling buffer {:?}", buffer);
+
+        let service_no = self.settings.lock().unwrap().service as u8;
+
+        let mut state = self.state.borrow_mut();
+        let Some(format) = state.format else {
+            gst::error!(CAT, obj = pad, "Not negotiated yet");
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        let buffer_pts = buffer.pts().ok_or_else(|| {
+            gst::error!(CAT, obj = pad, "Require timestamped buffers");
+            gst::FlowError::Error
+        })?;
+
+        let data = buffer.map_readable().map_err(|_| {
+            gst::error!(CAT, obj = pad, "Can't map buffer readable");
+
+            gst::FlowError::Error
+        })?;
+
+        let data = match format {
+            Format::Cdp => match extract_cdp(&data) {
+                Ok(data) => data,
+                Err(e) => {
+                    gst::warning!(CAT, imp = self, "{e}");
+                    gst::element_imp_warning!(self, gst::StreamError::Decode, ["{e}"]);
+                    return Ok(gst::FlowSuccess::Ok);
+                }
+            },
+            Format::CcData => &data,
+        };
+
+        let mut cc_data = vec![0x80 | 0x40 | ((data.len() / 3) & 0x1f) as u8, 0xFF];
+        cc_data.extend(data);
+        if let Err(e) = state.cc_data_parser.push(&cc_data) {
+            gst::warning!(CAT, imp = self, "Failed to parse incoming data: {e}");
+            gst::element_imp_warning!(
+                self,
+                gst::StreamError::Decode,
+                ["Failed to parse incoming data: {e}"]
+            );
+            state.cc_data_parser.flush();
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let mut changed = false;
+        while let Some(packet) = state.cc_data_parser.pop_packet() {
+            for service in packet.services() {
+                if service.number() != service_no {
+                    continue;
+                }
+
+                for code in service.codes() {
+                    state.screen.handle_code(code);
+                    changed = true;
+                }
+            }
+        }
+
+        if !changed {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let cues = state.screen.cues();
+        if state
+            .previous_cues
+            .as_ref()
+            .map_or(cues.is_empty(), |(_, previous)| *previous == cues)
+        {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        gst::trace!(CAT, imp = self, "generated cues: {cues:?}");
+
+        let previous_cues = if cues.is_empty() {
+            state.previous_cues.take()
+        } else {
+            state.previous_cues.replace((buffer_pts, cues))
+        };
+
+        let Some((timestamp, cues)) = previous_cues else {
+            gst::debug!(CAT, imp = self, "Have no previous cues");
+            return Ok(gst::FlowSuccess::Ok);
+        };
+
+        let duration = buffer_pts.saturating_sub(timestamp);
+        let buffers = Self::create_buffers(&mut state, timestamp, duration, &cues);
+        drop(state);
+
+        for buffer in buffers {
+            self.srcpad.push(buffer)?;
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
+    fn create_buffers(
+        state: &mut State,
+        timestamp: gst::ClockTime,
+        duration: gst::ClockTime,
+        cues: &[Cue],
+    ) -> Vec<gst::Buffer> {
+        let mut buffers = Vec::new();
+
+        // With fragmented output every cue starts a new fragment
+        if !state.wrote_header || state.fragmented {
+            state.wrote_header = true;
+            buffers.push(Self::create_vtt_header(timestamp));
+        }
+
+        buffers.push(Self::create_vtt_buffer(timestamp, duration, cues));
+
+        buffers
+    }
+
+    fn create_vtt_header(timestamp: gst::ClockTime) -> gst::Buffer {
+        use std::fmt::Write;
+
+        let mut headers = String::new();
+        writeln!(&mut headers, "WEBVTT\r").unwrap();
+        writeln!(&mut headers, "\r").unwrap();
+
+        let mut buffer = gst::Buffer::from_mut_slice(headers.into_bytes());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(timestamp);
+        }
+
+        buffer
+    }
+
+    fn split_time(time: gst::ClockTime) -> (u64, u8, u8, u16) {
+        let time = time.nseconds();
+
+        let mut s = time / 1_000_000_000;
+        let mut m = s / 60;
+        let h = m / 60;
+        s %= 60;
+        m %= 60;
+        let ns = time % 1_000_000_000;
+
+        (h, m as u8, s as u8, (ns / 1_000_000) as u16)
+    }
+
+    fn create_vtt_buffer(
+        timestamp: gst::ClockTime,
+        duration: gst::ClockTime,
+        cues: &[Cue],
+    ) -> gst::Buffer {
+        use std::fmt::Write;
+
+        let mut data = String::new();
+
+        let (h1, m1, s1, ms1) = Self::split_time(timestamp);
+        let (h2, m2, s2, ms2) = Self::split_time(timestamp + duration);
+
+        // One cue per visible window, all sharing the same timing
+        for cue in cues {
+            writeln!(
+                &mut data,
+                "{h1:02}:{m1:02}:{s1:02}.{ms1:03} --> {h2:02}:{m2:02}:{s2:02}.{ms2:03} {}\r",
+                cue.settings
+            )
+            .unwrap();
+            for line in cue.text.lines() {
+                writeln!(&mut data, "{line}\r").unwrap();
+            }
+            writeln!(&mut data, "\r").unwrap();
+        }
+
+        let mut buffer = gst::Buffer::from_mut_slice(data.into_bytes());
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(timestamp);
+            buffer.set_duration(duration);
+        }
+
+        buffer
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {:?}", event);
+        match event.view() {
+            EventView::Caps(ev) => {
+                let s = ev.caps().structure(0).unwrap();
+                let format = match s.get::<&str>("format") {
+                    Ok("cdp") => Format::Cdp,
+                    Ok("cc_data") => Format::CcData,
+                    _ => {
+                        gst::error!(CAT, obj = pad, "Unsupported caps {}", ev.caps());
+                        return false;
+                    }
+                };
+
+                let mut downstream_caps = self
+                    .srcpad
+                    .peer_query_caps(Some(&self.srcpad.pad_template_caps()));
+
+                if downstream_caps.is_empty() {
+                    gst::error!(CAT, obj = pad, "Empty downstream caps");
+                    return false;
+                }
+
+                downstream_caps.fixate();
+
+                gst::debug!(
+                    CAT,
+                    obj = pad,
+                    "Negotiating for downstream caps {}",
+                    downstream_caps
+                );
+
+                let s = downstream_caps.structure(0).unwrap();
+                let fragmented = s.name() == "application/x-subtitle-vtt-fragmented";
+                let new_caps = if fragmented {
+                    gst::Caps::builder("application/x-subtitle-vtt-fragmented")
+                        .field("inline-headers", true)
+                        .build()
+                } else {
+                    gst::Caps::builder("application/x-subtitle-vtt").build()
+                };
+
+                let mut state = self.state.borrow_mut();
+                state.format = Some(format);
+                state.fragmented = fragmented;
+                drop(state);
+
+                return self.srcpad.push_event(gst::event::Caps::new(&new_caps));
+            }
+            EventView::FlushStop(..) => {
+                let mut state = self.state.borrow_mut();
+                state.cc_data_parser.flush();
+                state.screen = Screen::default();
+                state.previous_cues = None;
+            }
+            EventView::Eos(..) => {
+                let mut state = self.state.borrow_mut();
+                if let Some((timestamp, cues)) = state.previous_cues.take() {
+                    gst::debug!(CAT, obj = pad, "Outputting final cues on EOS");
+
+                    let buffers =
+                        Self::create_buffers(&mut state, timestamp, gst::ClockTime::ZERO, &cues);
+                    drop(state);
+
+                    for buffer in buffers {
+                        let _ = self.srcpad.push(buffer);
+                    }
+                }
+            }
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Cea708ToVtt {
+    const NAME: &'static str = "GstCea708ToVtt";
+    type Type = super::Cea708ToVtt;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                Cea708ToVtt::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Cea708ToVtt::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            srcpad,
+            sinkpad,
+            settings: Mutex::new(Settings::default()),
+            state: AtomicRefCell::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for Cea708ToVtt {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![glib::ParamSpecInt::builder("service")
+                .nick("Service")
+                .blurb("The CEA-708 service to convert")
+                .minimum(1)
+                .maximum(63)
+                .default_value(DEFAULT_SERVICE)
+                .mutable_playing()
+                .build()]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "service" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.service = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "service" => {
+                let settings = self.settings.lock().unwrap();
+                settings.service.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for Cea708ToVtt {}
+
+impl ElementImpl for Cea708ToVtt {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "CEA-708 to WebVTT",
+                "Generic",
+                "Converts a CEA-708 Closed Caption service to positioned WebVTT cues",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst::Caps::builder_full()
+                .structure(gst::Structure::new_empty("application/x-subtitle-vtt"))
+                .structure(
+                    gst::Structure::builder("application/x-subtitle-vtt-fragmented")
+                        .field("inline-headers", true)
+                        .build(),
+                )
+                .build();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("closedcaption/x-cea-708")
+                .field("format", gst::List::new(["cc_data", "cdp"]))
+                .build();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                let mut state = self.state.borrow_mut();
+                *state = State::default();
+            }
+            _ => (),
+        }
+
+        let ret = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                let mut state = self.state.borrow_mut();
+                *state = State::default();
+            }
+            _ => (),
+        }
+
+        Ok(ret)
+    }
+}
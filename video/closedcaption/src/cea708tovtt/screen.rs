@@ -0,0 +1,489 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use cea708_types::tables::*;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "cea708tovtt-screen",
+        gst::DebugColorFlags::empty(),
+        Some("CEA-708 to WebVTT screen"),
+    )
+});
+
+/// Number of columns and anchor positions of a 16:9 screen when not using relative positioning
+const SCREEN_COLUMNS: u32 = 42;
+const SCREEN_HORIZONTAL_ANCHORS: u32 = 210;
+const SCREEN_VERTICAL_ANCHORS: u32 = 75;
+
+/// A WebVTT cue for a visible window, without timing information
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) struct Cue {
+    pub settings: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Style {
+    italics: bool,
+    underline: bool,
+    // WebVTT default color class, if not white
+    color: Option<&'static str>,
+}
+
+impl Style {
+    fn new(pen_attrs: &SetPenAttributesArgs, pen_color: &SetPenColorArgs) -> Self {
+        Self {
+            italics: pen_attrs.italics,
+            underline: pen_attrs.underline,
+            color: color_class(&pen_color.foreground_color),
+        }
+    }
+
+    fn open(&self, text: &mut String) {
+        if let Some(color) = self.color {
+            write!(text, "<c.{color}>").unwrap();
+        }
+        if self.italics {
+            text.push_str("<i>");
+        }
+        if self.underline {
+            text.push_str("<u>");
+        }
+    }
+
+    fn close(&self, text: &mut String) {
+        if self.underline {
+            text.push_str("</u>");
+        }
+        if self.italics {
+            text.push_str("</i>");
+        }
+        if self.color.is_some() {
+            text.push_str("</c>");
+        }
+    }
+}
+
+/// Maps a CEA-708 color to the closest WebVTT default color class
+fn color_class(color: &Color) -> Option<&'static str> {
+    let on = |value: ColorValue| matches!(value, ColorValue::TwoThirds | ColorValue::Full);
+
+    match (on(color.r), on(color.g), on(color.b)) {
+        (true, true, true) => None,
+        (false, true, false) => Some("lime"),
+        (false, true, true) => Some("cyan"),
+        (true, false, false) => Some("red"),
+        (true, true, false) => Some("yellow"),
+        (true, false, true) => Some("magenta"),
+        (false, false, true) => Some("blue"),
+        (false, false, false) => Some("black"),
+    }
+}
+
+struct Window {
+    define: DefineWindowArgs,
+    visible: bool,
+    attrs: SetWindowAttributesArgs,
+    pen_attrs: SetPenAttributesArgs,
+    pen_color: SetPenColorArgs,
+    pen_row: u8,
+    pen_column: u8,
+    rows: BTreeMap<u8, BTreeMap<u8, (char, Style)>>,
+}
+
+impl Window {
+    fn new(define: &DefineWindowArgs) -> Self {
+        Self {
+            define: *define,
+            visible: define.visible,
+            attrs: define.window_attributes(),
+            pen_attrs: define.pen_attributes(),
+            pen_color: define.pen_color(),
+            pen_row: 0,
+            pen_column: 0,
+            rows: BTreeMap::new(),
+        }
+    }
+
+    fn row_count(&self) -> u8 {
+        self.define.row_count + 1
+    }
+
+    fn column_count(&self) -> u8 {
+        self.define.column_count + 1
+    }
+
+    fn clear(&mut self) {
+        self.rows.clear();
+        self.pen_row = 0;
+        self.pen_column = 0;
+    }
+
+    fn push_char(&mut self, ch: char) {
+        if self.pen_row >= self.row_count() || self.pen_column >= self.column_count() {
+            gst::trace!(
+                CAT,
+                "dropping char '{ch}' outside of window at {},{}",
+                self.pen_row,
+                self.pen_column
+            );
+            return;
+        }
+
+        let style = Style::new(&self.pen_attrs, &self.pen_color);
+        self.rows
+            .entry(self.pen_row)
+            .or_default()
+            .insert(self.pen_column, (ch, style));
+        self.pen_column += 1;
+    }
+
+    fn backspace(&mut self) {
+        self.pen_column = self.pen_column.saturating_sub(1);
+        if let Some(row) = self.rows.get_mut(&self.pen_row) {
+            row.remove(&self.pen_column);
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        if !matches!(self.attrs.print_direction, Direction::LeftToRight)
+            || !matches!(self.attrs.scroll_direction, Direction::BottomToTop)
+        {
+            gst::fixme!(
+                CAT,
+                "Print direction {:?} and scroll direction {:?} are handled as left-to-right and bottom-to-top",
+                self.attrs.print_direction,
+                self.attrs.scroll_direction
+            );
+        }
+
+        if self.pen_row + 1 >= self.row_count() {
+            // Scroll all rows up by one
+            self.rows = std::mem::take(&mut self.rows)
+                .into_iter()
+                .filter(|(row, _)| *row > 0)
+                .map(|(row, cells)| (row - 1, cells))
+                .collect();
+        } else {
+            self.pen_row += 1;
+        }
+        self.pen_column = 0;
+    }
+
+    fn horizontal_carriage_return(&mut self) {
+        self.rows.remove(&self.pen_row);
+        self.pen_column = 0;
+    }
+
+    fn cue_settings(&self) -> String {
+        let define = &self.define;
+
+        let (line, position) = if define.relative_positioning {
+            (
+                (define.anchor_vertical as u32).min(100),
+                (define.anchor_horizontal as u32).min(100),
+            )
+        } else {
+            (
+                (define.anchor_vertical as u32 * 100 / SCREEN_VERTICAL_ANCHORS).min(100),
+                (define.anchor_horizontal as u32 * 100 / SCREEN_HORIZONTAL_ANCHORS).min(100),
+            )
+        };
+
+        let (line_align, position_align) = match define.anchor_point {
+            Anchor::TopLeft => ("start", "line-left"),
+            Anchor::TopMiddle => ("start", "center"),
+            Anchor::TopRight => ("start", "line-right"),
+            Anchor::CenterLeft => ("center", "line-left"),
+            Anchor::CenterMiddle => ("center", "center"),
+            Anchor::CenterRight => ("center", "line-right"),
+            Anchor::BottomLeft => ("end", "line-left"),
+            Anchor::BottomRight => ("end", "line-right"),
+            _ => ("end", "center"),
+        };
+
+        let size = (self.column_count() as u32 * 100 / SCREEN_COLUMNS).min(100);
+
+        let align = match self.attrs.justify {
+            Justify::Right => "right",
+            Justify::Center => "center",
+            _ => "left",
+        };
+
+        format!(
+            "line:{line}%,{line_align} position:{position}%,{position_align} size:{size}% align:{align}"
+        )
+    }
+
+    fn text(&self) -> String {
+        let mut text = String::new();
+
+        for cells in self.rows.values() {
+            let Some(&last_column) = cells
+                .iter()
+                .rev()
+                .find(|(_, (ch, _))| !ch.is_whitespace())
+                .map(|(column, _)| column)
+            else {
+                // WebVTT cue text can't contain empty lines
+                continue;
+            };
+
+            if !text.is_empty() {
+                text.push('\n');
+            }
+
+            let mut style: Option<Style> = None;
+            for column in 0..=last_column {
+                let (ch, cell_style) = cells.get(&column).copied().unwrap_or((
+                    ' ',
+                    style.unwrap_or(Style::new(&self.pen_attrs, &self.pen_color)),
+                ));
+
+                if style != Some(cell_style) {
+                    if let Some(style) = style {
+                        style.close(&mut text);
+                    }
+                    cell_style.open(&mut text);
+                    style = Some(cell_style);
+                }
+
+                match ch {
+                    '&' => text.push_str("&amp;"),
+                    '<' => text.push_str("&lt;"),
+                    '>' => text.push_str("&gt;"),
+                    ch => text.push(ch),
+                }
+            }
+            if let Some(style) = style {
+                style.close(&mut text);
+            }
+        }
+
+        text
+    }
+
+    fn cue(&self) -> Option<Cue> {
+        if !self.visible {
+            return None;
+        }
+
+        let text = self.text();
+        if text.is_empty() {
+            return None;
+        }
+
+        Some(Cue {
+            settings: self.cue_settings(),
+            text,
+        })
+    }
+}
+
+/// Text content of the windows of a single CEA-708 service
+#[derive(Default)]
+pub(super) struct Screen {
+    windows: BTreeMap<u8, Window>,
+    current_window: Option<u8>,
+}
+
+impl Screen {
+    fn current_window_mut(&mut self) -> Option<&mut Window> {
+        self.windows.get_mut(&self.current_window?)
+    }
+
+    fn windows_mut(&mut self, bits: WindowBits) -> impl Iterator<Item = &mut Window> {
+        self.windows.values_mut().filter(move |window| {
+            (WindowBits::from_window_id(window.define.window_id) & bits) != WindowBits::NONE
+        })
+    }
+
+    fn define_window(&mut self, args: &DefineWindowArgs) {
+        if let Some(window) = self.windows.get_mut(&args.window_id) {
+            if &window.define != args {
+                window.attrs = args.window_attributes();
+                window.pen_attrs = args.pen_attributes();
+                window.pen_color = args.pen_color();
+            }
+            window.define = *args;
+            window.visible = args.visible;
+        } else {
+            self.windows.insert(args.window_id, Window::new(args));
+        }
+        self.current_window = Some(args.window_id);
+    }
+
+    pub fn handle_code(&mut self, code: &Code) {
+        gst::trace!(CAT, "handling code {code:?}");
+
+        match code {
+            Code::DefineWindow(args) => self.define_window(args),
+            Code::SetCurrentWindow0 => self.current_window = Some(0),
+            Code::SetCurrentWindow1 => self.current_window = Some(1),
+            Code::SetCurrentWindow2 => self.current_window = Some(2),
+            Code::SetCurrentWindow3 => self.current_window = Some(3),
+            Code::SetCurrentWindow4 => self.current_window = Some(4),
+            Code::SetCurrentWindow5 => self.current_window = Some(5),
+            Code::SetCurrentWindow6 => self.current_window = Some(6),
+            Code::SetCurrentWindow7 => self.current_window = Some(7),
+            Code::ClearWindows(bits) => self.windows_mut(*bits).for_each(Window::clear),
+            Code::DeleteWindows(bits) => {
+                let bits = *bits;
+                self.windows.retain(|_, window| {
+                    (WindowBits::from_window_id(window.define.window_id) & bits) == WindowBits::NONE
+                });
+            }
+            Code::DisplayWindows(bits) => {
+                self.windows_mut(*bits)
+                    .for_each(|window| window.visible = true);
+            }
+            Code::HideWindows(bits) => {
+                self.windows_mut(*bits)
+                    .for_each(|window| window.visible = false);
+            }
+            Code::ToggleWindows(bits) => {
+                self.windows_mut(*bits)
+                    .for_each(|window| window.visible = !window.visible);
+            }
+            Code::SetWindowAttributes(args) => {
+                if let Some(window) = self.current_window_mut() {
+                    window.attrs = *args;
+                }
+            }
+            Code::SetPenAttributes(args) => {
+                if let Some(window) = self.current_window_mut() {
+                    window.pen_attrs = *args;
+                }
+            }
+            Code::SetPenColor(args) => {
+                if let Some(window) = self.current_window_mut() {
+                    window.pen_color = *args;
+                }
+            }
+            Code::SetPenLocation(args) => {
+                if let Some(window) = self.current_window_mut() {
+                    window.pen_row = args.row;
+                    window.pen_column = args.column;
+                }
+            }
+            Code::BS => {
+                if let Some(window) = self.current_window_mut() {
+                    window.backspace();
+                }
+            }
+            Code::CR => {
+                if let Some(window) = self.current_window_mut() {
+                    window.carriage_return();
+                }
+            }
+            Code::HCR => {
+                if let Some(window) = self.current_window_mut() {
+                    window.horizontal_carriage_return();
+                }
+            }
+            Code::FF => {
+                if let Some(window) = self.current_window_mut() {
+                    window.clear();
+                }
+            }
+            Code::Reset => *self = Screen::default(),
+            _ => {
+                if let Some(ch) = code.char() {
+                    if let Some(window) = self.current_window_mut() {
+                        window.push_char(ch);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Cues for all visible windows, in order of their priority
+    pub fn cues(&self) -> Vec<Cue> {
+        let mut windows = self.windows.values().collect::<Vec<_>>();
+        windows.sort_by_key(|window| window.define.priority);
+
+        windows.into_iter().filter_map(Window::cue).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn define_window(visible: bool) -> DefineWindowArgs {
+        DefineWindowArgs::new(
+            0,
+            0,
+            Anchor::BottomMiddle,
+            true,
+            90,
+            50,
+            1,
+            31,
+            true,
+            true,
+            visible,
+            1,
+            1,
+        )
+    }
+
+    fn push_str(screen: &mut Screen, s: &str) {
+        for ch in s.chars() {
+            screen.handle_code(&Code::from_char(ch).unwrap());
+        }
+    }
+
+    #[test]
+    fn positioned_cue() {
+        gst::init().unwrap();
+
+        let mut screen = Screen::default();
+        screen.handle_code(&Code::DefineWindow(define_window(true)));
+        push_str(&mut screen, "Hello");
+        screen.handle_code(&Code::CR);
+        push_str(&mut screen, "World");
+
+        assert_eq!(
+            screen.cues(),
+            vec![Cue {
+                settings: "line:90%,end position:50%,center size:76% align:left".to_string(),
+                text: "Hello\nWorld".to_string(),
+            }]
+        );
+
+        // Rolls up once the last row is reached
+        screen.handle_code(&Code::CR);
+        push_str(&mut screen, "again");
+        assert_eq!(screen.cues()[0].text, "World\nagain");
+    }
+
+    #[test]
+    fn styled_popon() {
+        gst::init().unwrap();
+
+        let mut screen = Screen::default();
+        screen.handle_code(&Code::DefineWindow(define_window(false)));
+        push_str(&mut screen, "a ");
+        screen.handle_code(&Code::SetPenAttributes(SetPenAttributesArgs {
+            italics: true,
+            ..define_window(false).pen_attributes()
+        }));
+        push_str(&mut screen, "<b>");
+        assert!(screen.cues().is_empty());
+
+        screen.handle_code(&Code::DisplayWindows(WindowBits::ZERO));
+        assert_eq!(screen.cues()[0].text, "a <i>&lt;b&gt;</i>");
+
+        screen.handle_code(&Code::ClearWindows(WindowBits::ZERO));
+        assert!(screen.cues().is_empty());
+    }
+}
@@ -17,6 +17,8 @@ use gst::glib;
 #[cfg(feature = "doc")]
 use gst::prelude::*;
 
+mod anc2sdi;
+mod ccdelay;
 mod ccdetect;
 mod cctost2038anc;
 mod ccutils;
@@ -27,6 +29,7 @@ mod cea608tott;
 mod cea608utils;
 mod cea708mux;
 mod cea708overlay;
+mod cea708tovtt;
 mod cea708utils;
 mod jsontovtt;
 mod line_reader;
@@ -35,11 +38,14 @@ mod mcc_parse;
 mod parser_utils;
 mod scc_enc;
 mod scc_parse;
+mod sdi2anc;
 mod st2038anc_utils;
 mod st2038ancdemux;
 mod st2038ancmux;
 mod st2038anctocc;
+mod texttranscode;
 mod transcriberbin;
+mod ttsmoother;
 mod tttocea608;
 mod tttocea708;
 mod tttojson;
@@ -59,6 +65,7 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     tttocea608::register(plugin)?;
     cea608overlay::register(plugin)?;
     ccdetect::register(plugin)?;
+    ccdelay::register(plugin)?;
     tttojson::register(plugin)?;
     cea608tojson::register(plugin)?;
     jsontovtt::register(plugin)?;
@@ -67,10 +74,15 @@ fn plugin_init(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
     cea708mux::register(plugin)?;
     tttocea708::register(plugin)?;
     cea708overlay::register(plugin)?;
+    cea708tovtt::register(plugin)?;
     st2038ancdemux::register(plugin)?;
     st2038ancmux::register(plugin)?;
     st2038anctocc::register(plugin)?;
     cctost2038anc::register(plugin)?;
+    sdi2anc::register(plugin)?;
+    anc2sdi::register(plugin)?;
+    texttranscode::register(plugin)?;
+    ttsmoother::register(plugin)?;
     Ok(())
 }
 
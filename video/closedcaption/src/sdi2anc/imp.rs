@@ -0,0 +1,357 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::{LazyLock, Mutex};
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use atomic_refcell::AtomicRefCell;
+
+use crate::st2038anc_utils::{convert_to_st2038_buffer, find_vanc_packets, VancFormat};
+
+#[derive(Default)]
+struct State {
+    video_info: Option<gst_video::VideoInfo>,
+    format: Option<VancFormat>,
+}
+
+#[derive(Clone)]
+struct Settings {
+    line_offset: u16,
+    lines: u32,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            line_offset: 1,
+            lines: 0,
+        }
+    }
+}
+
+pub struct Sdi2Anc {
+    sinkpad: gst::Pad,
+    srcpad: gst::Pad,
+
+    state: AtomicRefCell<State>,
+    settings: Mutex<Settings>,
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "sdi2anc",
+        gst::DebugColorFlags::empty(),
+        Some("SDI video to ST-2038 ANC Element"),
+    )
+});
+
+impl Sdi2Anc {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::trace!(CAT, obj = pad, "Handling buffer {:?}", buffer);
+
+        let state = self.state.borrow_mut();
+        let settings = self.settings.lock().unwrap().clone();
+
+        let (Some(info), Some(format)) = (state.video_info.as_ref(), state.format) else {
+            gst::error!(CAT, imp = self, "No caps set");
+            return Err(gst::FlowError::NotNegotiated);
+        };
+
+        let frame = gst_video::VideoFrameRef::from_buffer_ref_readable(buffer.as_ref(), info)
+            .map_err(|_| {
+                gst::error!(CAT, obj = pad, "Can't map buffer readable");
+
+                gst::FlowError::Error
+            })?;
+
+        let width = frame.width() as usize;
+        let height = frame.height() as usize;
+        let n_lines = if settings.lines == 0 {
+            height
+        } else {
+            usize::min(settings.lines as usize, height)
+        };
+        let stride = frame.plane_stride()[0] as usize;
+        let data = frame.plane_data(0).unwrap();
+
+        let mut output = Vec::new();
+        for (idx, line) in data.chunks(stride).take(n_lines).enumerate() {
+            let line_number = settings.line_offset as usize + idx;
+            if line_number > 2047 {
+                break;
+            }
+
+            for c_not_y_channel in [false, true] {
+                for packet in find_vanc_packets(format, line, width, c_not_y_channel) {
+                    gst::trace!(
+                        CAT,
+                        imp = self,
+                        "Found ANC packet with DID {} and SDID {} at line {line_number} offset {}",
+                        packet.did,
+                        packet.sdid,
+                        packet.horizontal_offset,
+                    );
+
+                    match convert_to_st2038_buffer(
+                        packet.c_not_y_channel,
+                        line_number as u16,
+                        packet.horizontal_offset,
+                        packet.did,
+                        packet.sdid,
+                        &packet.payload,
+                    ) {
+                        Ok(anc) => {
+                            output.extend_from_slice(&anc.map_readable().unwrap());
+                        }
+                        Err(err) => {
+                            gst::warning!(CAT, imp = self, "Can't convert ANC packet: {err}");
+                        }
+                    }
+                }
+            }
+        }
+        drop(frame);
+        drop(state);
+
+        if output.is_empty() {
+            gst::trace!(CAT, imp = self, "No ANC packets in frame");
+
+            if let Some(pts) = buffer.pts() {
+                let _ = self.srcpad.push_event(
+                    gst::event::Gap::builder(pts)
+                        .duration(buffer.duration())
+                        .build(),
+                );
+            }
+
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let mut outbuf = gst::Buffer::from_mut_slice(output);
+        {
+            let outbuf = outbuf.get_mut().unwrap();
+            let _ = buffer.copy_into(outbuf, gst::BUFFER_COPY_METADATA, ..);
+        }
+
+        self.srcpad.push(outbuf)
+    }
+
+    #[allow(clippy::single_match)]
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {:?}", event);
+        match event.view() {
+            EventView::Caps(ev) => {
+                let caps = ev.caps();
+
+                let info = match gst_video::VideoInfo::from_caps(caps) {
+                    Ok(info) => info,
+                    Err(_) => {
+                        gst::error!(CAT, imp = self, "Invalid caps {caps:?}");
+                        return false;
+                    }
+                };
+
+                let Some(format) = VancFormat::from_video_format(info.format()) else {
+                    gst::error!(CAT, imp = self, "Unsupported caps {caps:?}");
+                    return false;
+                };
+
+                gst::debug!(CAT, imp = self, "Configuring format {format:?}");
+
+                let mut state = self.state.borrow_mut();
+                state.video_info = Some(info);
+                state.format = Some(format);
+                drop(state);
+
+                return self.srcpad.push_event(
+                    gst::event::Caps::builder(&self.srcpad.pad_template_caps())
+                        .seqnum(ev.seqnum())
+                        .build(),
+                );
+            }
+            _ => (),
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Sdi2Anc {
+    const NAME: &'static str = "GstSdi2Anc";
+    type Type = super::Sdi2Anc;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                Sdi2Anc::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                Sdi2Anc::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::builder_from_template(&templ)
+            .flags(gst::PadFlags::FIXED_CAPS)
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            state: AtomicRefCell::new(State::default()),
+            settings: Mutex::new(Settings::default()),
+        }
+    }
+}
+
+impl ObjectImpl for Sdi2Anc {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecUInt::builder("line-offset")
+                    .nick("Line Offset")
+                    .blurb("Line number of the first line of the video frames")
+                    .default_value(Settings::default().line_offset as u32)
+                    .maximum(2047)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("lines")
+                    .nick("Lines")
+                    .blurb("Number of lines at the top of the video frames to scan for ANC packets (0 = all)")
+                    .default_value(Settings::default().lines)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        match pspec.name() {
+            "line-offset" => {
+                let mut settings = self.settings.lock().unwrap();
+
+                settings.line_offset = value.get::<u32>().expect("type checked upstream") as u16;
+            }
+            "lines" => {
+                let mut settings = self.settings.lock().unwrap();
+
+                settings.lines = value.get().expect("type checked upstream");
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        match pspec.name() {
+            "line-offset" => {
+                let settings = self.settings.lock().unwrap();
+                (settings.line_offset as u32).to_value()
+            }
+            "lines" => {
+                let settings = self.settings.lock().unwrap();
+                settings.lines.to_value()
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for Sdi2Anc {}
+
+impl ElementImpl for Sdi2Anc {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "SDI to ST-2038 ANC",
+                "Generic",
+                "Extracts ANC packets from the lines of raw SDI video as ST-2038 ANC",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &gst::Caps::builder("meta/x-st-2038").build(),
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &VancFormat::caps(),
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {:?}", transition);
+
+        match transition {
+            gst::StateChange::ReadyToPaused => {
+                *self.state.borrow_mut() = State::default();
+            }
+            _ => (),
+        }
+
+        let ret = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                *self.state.borrow_mut() = State::default();
+            }
+            _ => (),
+        }
+
+        Ok(ret)
+    }
+}
@@ -0,0 +1,34 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-sdi2anc
+ *
+ * Extracts ancillary data packets embedded in the lines of raw v210 or UYVY video
+ * frames, as delivered by SDI capture cards, and outputs them as ST-2038 ANC.
+ *
+ * All packets found in a frame are output as a single buffer with the timestamps of
+ * the frame. A gap event is sent for frames without any packets.
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct Sdi2Anc(ObjectSubclass<imp::Sdi2Anc>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "sdi2anc",
+        gst::Rank::NONE,
+        Sdi2Anc::static_type(),
+    )
+}
@@ -67,6 +67,26 @@ impl AncDataHeader {
             len,
         })
     }
+
+    /// Extracts the user data words of the packet at the start of `slice`, with the
+    /// parity bits stripped off.
+    pub(crate) fn payload(&self, slice: &[u8]) -> anyhow::Result<Vec<u8>> {
+        use anyhow::Context;
+        use bitstream_io::{BigEndian, BitRead, BitReader};
+        use std::io::Cursor;
+
+        let mut r = BitReader::endian(Cursor::new(slice), BigEndian);
+
+        // Skip header portion
+        r.skip(6 + 1 + 11 + 12 + 10 + 10 + 10).context("header")?;
+
+        let mut payload = Vec::with_capacity(self.data_count as usize);
+        for _ in 0..self.data_count {
+            payload.push((r.read::<u16>(10).context("data")? & 0xff) as u8);
+        }
+
+        Ok(payload)
+    }
 }
 
 fn extend_with_even_odd_parity(v: u8, checksum: &mut u16) -> u16 {
@@ -140,3 +160,196 @@ pub(crate) fn convert_to_st2038_buffer(
 
     Ok(gst::Buffer::from_mut_slice(output))
 }
+
+/// Returns the 10 bit words of an ANC packet from the DID up to and including the
+/// checksum, with parity bits set. The ancillary data flag is not included.
+pub(crate) fn anc_packet_words(did: u8, sdid: u8, payload: &[u8]) -> Vec<u16> {
+    let mut checksum = 0u16;
+
+    let mut words = Vec::with_capacity(payload.len() + 4);
+    words.push(extend_with_even_odd_parity(did, &mut checksum));
+    words.push(extend_with_even_odd_parity(sdid, &mut checksum));
+    words.push(extend_with_even_odd_parity(
+        payload.len() as u8,
+        &mut checksum,
+    ));
+    for &b in payload {
+        words.push(extend_with_even_odd_parity(b, &mut checksum));
+    }
+
+    checksum &= 0x1_ff;
+    checksum |= ((!(checksum >> 8)) & 0x0_01) << 9;
+    words.push(checksum);
+
+    words
+}
+
+/// Raw video formats that can carry ancillary data packets inside their lines.
+///
+/// Lines are accessed as a sequence of interleaved chroma and luma components
+/// (Cb Y Cr Y ...), with luma at odd and chroma at even indices.
+///
+/// 8 bit formats can't represent the two parity bits of each ANC word. The ancillary
+/// data flag is then stored as `0x00 0xff 0xff` and all other words with their 8 data
+/// bits, and the parity bits are recalculated when extracting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum VancFormat {
+    V210,
+    Uyvy,
+}
+
+impl VancFormat {
+    pub(crate) fn from_video_format(format: gst_video::VideoFormat) -> Option<Self> {
+        match format {
+            gst_video::VideoFormat::V210 => Some(VancFormat::V210),
+            gst_video::VideoFormat::Uyvy => Some(VancFormat::Uyvy),
+            _ => None,
+        }
+    }
+
+    /// Caps for all supported formats
+    pub(crate) fn caps() -> gst::Caps {
+        gst_video::VideoCapsBuilder::new()
+            .format_list([gst_video::VideoFormat::V210, gst_video::VideoFormat::Uyvy])
+            .build()
+    }
+
+    /// Ancillary data flag in front of each packet
+    pub(crate) fn ancillary_data_flag(self) -> [u16; 3] {
+        match self {
+            VancFormat::V210 => [0x000, 0x3ff, 0x3ff],
+            VancFormat::Uyvy => [0x00, 0xff, 0xff],
+        }
+    }
+
+    /// Mask of the bits of each word that can be represented
+    pub(crate) fn word_mask(self) -> u16 {
+        match self {
+            VancFormat::V210 => 0x3ff,
+            VancFormat::Uyvy => 0xff,
+        }
+    }
+
+    pub(crate) fn read_component(self, line: &[u8], idx: usize) -> u16 {
+        match self {
+            VancFormat::V210 => {
+                // 3 components per little endian 32 bit word
+                let offset = (idx / 3) * 4;
+                let word = u32::from_le_bytes(line[offset..][..4].try_into().unwrap());
+                ((word >> ((idx % 3) * 10)) & 0x3ff) as u16
+            }
+            VancFormat::Uyvy => line[idx] as u16,
+        }
+    }
+
+    pub(crate) fn write_component(self, line: &mut [u8], idx: usize, value: u16) {
+        match self {
+            VancFormat::V210 => {
+                let offset = (idx / 3) * 4;
+                let shift = (idx % 3) * 10;
+                let bytes = &mut line[offset..][..4];
+                let mut word = u32::from_le_bytes((&*bytes).try_into().unwrap());
+                word &= !(0x3ff << shift);
+                word |= ((value & 0x3ff) as u32) << shift;
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+            VancFormat::Uyvy => line[idx] = (value & 0xff) as u8,
+        }
+    }
+}
+
+/// An ANC packet found in a line of raw video
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct VancPacket {
+    pub(crate) c_not_y_channel: bool,
+    /// Offset of the ancillary data flag in samples of the channel
+    pub(crate) horizontal_offset: u16,
+    pub(crate) did: u8,
+    pub(crate) sdid: u8,
+    pub(crate) payload: Vec<u8>,
+}
+
+/// Scans one channel of a line of `width` pixels for ANC packets with a valid checksum.
+pub(crate) fn find_vanc_packets(
+    format: VancFormat,
+    line: &[u8],
+    width: usize,
+    c_not_y_channel: bool,
+) -> Vec<VancPacket> {
+    let component = |i: usize| {
+        let idx = 2 * i + if c_not_y_channel { 0 } else { 1 };
+        format.read_component(line, idx)
+    };
+
+    let adf = format.ancillary_data_flag();
+    let mask = format.word_mask();
+
+    let mut packets = Vec::new();
+
+    // Ancillary data flag, DID, SDID, data count and checksum
+    let mut i = 0;
+    while i + 7 <= width {
+        if (0..3).any(|j| component(i + j) != adf[j]) {
+            i += 1;
+            continue;
+        }
+
+        let did = (component(i + 3) & 0xff) as u8;
+        let sdid = (component(i + 4) & 0xff) as u8;
+        let data_count = (component(i + 5) & 0xff) as usize;
+
+        if i + 7 + data_count > width {
+            i += 1;
+            continue;
+        }
+
+        let payload = (0..data_count)
+            .map(|j| (component(i + 6 + j) & 0xff) as u8)
+            .collect::<Vec<_>>();
+        let checksum = component(i + 6 + data_count);
+
+        let words = anc_packet_words(did, sdid, &payload);
+        if words.last().unwrap() & mask != checksum {
+            i += 1;
+            continue;
+        }
+
+        packets.push(VancPacket {
+            c_not_y_channel,
+            horizontal_offset: i as u16,
+            did,
+            sdid,
+            payload,
+        });
+
+        i += 7 + data_count;
+    }
+
+    packets
+}
+
+/// Writes an ANC packet into its channel of a line of `width` pixels at the given
+/// sample offset instead of the packet's own horizontal offset. Returns the offset
+/// after the packet, or `None` if it doesn't fit.
+pub(crate) fn write_vanc_packet(
+    format: VancFormat,
+    line: &mut [u8],
+    width: usize,
+    offset: usize,
+    packet: &VancPacket,
+) -> Option<usize> {
+    let words = anc_packet_words(packet.did, packet.sdid, &packet.payload);
+    let adf = format.ancillary_data_flag();
+
+    let end = offset + adf.len() + words.len();
+    if end > width {
+        return None;
+    }
+
+    for (i, &word) in adf.iter().chain(words.iter()).enumerate() {
+        let idx = 2 * (offset + i) + if packet.c_not_y_channel { 0 } else { 1 };
+        format.write_component(line, idx, word & format.word_mask());
+    }
+
+    Some(end)
+}
@@ -0,0 +1,372 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::{LazyLock, Mutex};
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use crate::ttutils::TimedTextFormat;
+
+struct Chain {
+    input: TimedTextFormat,
+    output: TimedTextFormat,
+    elements: Vec<gst::Element>,
+}
+
+#[derive(Default)]
+struct State {
+    chain: Option<Chain>,
+}
+
+pub struct TextTranscode {
+    sinkpad: gst::GhostPad,
+    srcpad: gst::GhostPad,
+
+    state: Mutex<State>,
+}
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "texttranscode",
+        gst::DebugColorFlags::empty(),
+        Some("Timed text transcoding bin"),
+    )
+});
+
+impl TextTranscode {
+    /// Formats accepted downstream, in order of preference
+    fn downstream_formats(&self) -> Vec<TimedTextFormat> {
+        let peer_caps = self.srcpad.peer_query_caps(None);
+
+        TimedTextFormat::ALL
+            .into_iter()
+            .filter(|format| peer_caps.can_intersect(&format.caps()))
+            .collect()
+    }
+
+    fn select_output(&self, input: TimedTextFormat) -> Option<TimedTextFormat> {
+        let downstream_formats = self.downstream_formats();
+
+        // Prefer passthrough
+        if downstream_formats.contains(&input) {
+            return Some(input);
+        }
+
+        downstream_formats
+            .into_iter()
+            .find(|output| input.conversion_path(*output).is_some())
+    }
+
+    fn teardown_chain(&self, chain: Chain) {
+        let _ = self.sinkpad.set_target(None::<&gst::Pad>);
+        let _ = self.srcpad.set_target(None::<&gst::Pad>);
+
+        for element in chain.elements {
+            let _ = element.set_state(gst::State::Null);
+            let _ = self.obj().remove(&element);
+        }
+    }
+
+    fn setup_chain(
+        &self,
+        input: TimedTextFormat,
+        output: TimedTextFormat,
+    ) -> Result<Chain, glib::BoolError> {
+        let path = input.conversion_path(output).unwrap();
+
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Converting from {input:?} to {output:?} with {path:?}"
+        );
+
+        let elements = path
+            .iter()
+            .map(|factory| gst::ElementFactory::make(factory).build())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let obj = self.obj();
+        obj.add_many(&elements)?;
+        gst::Element::link_many(&elements)?;
+
+        for element in &elements {
+            element.sync_state_with_parent()?;
+        }
+
+        self.sinkpad
+            .set_target(Some(&elements.first().unwrap().static_pad("sink").unwrap()))?;
+        self.srcpad
+            .set_target(Some(&elements.last().unwrap().static_pad("src").unwrap()))?;
+
+        Ok(Chain {
+            input,
+            output,
+            elements,
+        })
+    }
+
+    fn handle_caps(&self, pad: &gst::Pad, event: gst::Event, caps: &gst::CapsRef) -> bool {
+        let Some(input) = TimedTextFormat::from_caps(caps) else {
+            gst::error!(CAT, imp = self, "Unsupported caps {caps:?}");
+            return false;
+        };
+
+        let Some(output) = self.select_output(input) else {
+            gst::error!(
+                CAT,
+                imp = self,
+                "No conversion from {input:?} to any format accepted downstream"
+            );
+            return false;
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if state
+            .chain
+            .as_ref()
+            .is_some_and(|chain| chain.input == input && chain.output == output)
+        {
+            drop(state);
+            return gst::Pad::event_default(pad, Some(&*self.obj()), event);
+        }
+
+        if let Some(chain) = state.chain.take() {
+            self.teardown_chain(chain);
+        }
+
+        match self.setup_chain(input, output) {
+            Ok(chain) => state.chain = Some(chain),
+            Err(err) => {
+                gst::error!(CAT, imp = self, "Failed to set up conversion: {err}");
+                return false;
+            }
+        }
+        drop(state);
+
+        // The new elements need the sticky events around the new caps
+        let mut before = Vec::new();
+        let mut after = Vec::new();
+        pad.sticky_events_foreach(|sticky| {
+            if sticky.type_() < gst::EventType::Caps {
+                before.push(sticky.clone());
+            } else if sticky.type_() > gst::EventType::Caps {
+                after.push(sticky.clone());
+            }
+            std::ops::ControlFlow::Continue(gst::EventForeachAction::Keep)
+        });
+
+        for sticky in before {
+            let _ = gst::Pad::event_default(pad, Some(&*self.obj()), sticky);
+        }
+        let ret = gst::Pad::event_default(pad, Some(&*self.obj()), event);
+        for sticky in after {
+            let _ = gst::Pad::event_default(pad, Some(&*self.obj()), sticky);
+        }
+
+        ret
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {:?}", event);
+
+        if let EventView::Caps(ev) = event.view() {
+            let caps = ev.caps_owned();
+            return self.handle_caps(pad, event, &caps);
+        }
+
+        if self.state.lock().unwrap().chain.is_none() {
+            // Sticky events are stored and sent once the conversion is set up
+            gst::debug!(CAT, obj = pad, "Not negotiated yet, holding back {event:?}");
+            return true;
+        }
+
+        gst::Pad::event_default(pad, Some(&*self.obj()), event)
+    }
+
+    fn sink_caps(&self, filter: Option<&gst::Caps>) -> gst::Caps {
+        let downstream_formats = self.downstream_formats();
+
+        let mut caps = TimedTextFormat::ALL
+            .into_iter()
+            .filter(|input| {
+                downstream_formats
+                    .iter()
+                    .any(|output| input.conversion_path(*output).is_some())
+            })
+            .map(TimedTextFormat::caps)
+            .collect::<gst::Caps>();
+
+        if let Some(filter) = filter {
+            caps = filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First);
+        }
+
+        caps
+    }
+
+    fn sink_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj = pad, "Handling query {:?}", query);
+
+        match query.view_mut() {
+            QueryViewMut::Caps(q) => {
+                let caps = self.sink_caps(q.filter_owned().as_ref());
+                q.set_result(&caps);
+                true
+            }
+            QueryViewMut::AcceptCaps(q) => {
+                let caps = q.caps_owned();
+                q.set_result(caps.is_subset(&self.sink_caps(None)));
+                true
+            }
+            _ => gst::Pad::query_default(pad, Some(&*self.obj()), query),
+        }
+    }
+
+    fn src_query(&self, pad: &gst::Pad, query: &mut gst::QueryRef) -> bool {
+        use gst::QueryViewMut;
+
+        gst::log!(CAT, obj = pad, "Handling query {:?}", query);
+
+        if self.state.lock().unwrap().chain.is_none() {
+            if let QueryViewMut::Caps(q) = query.view_mut() {
+                let mut caps = pad.pad_template_caps();
+                if let Some(filter) = q.filter() {
+                    caps = filter.intersect_with_mode(&caps, gst::CapsIntersectMode::First);
+                }
+                q.set_result(&caps);
+                return true;
+            }
+        }
+
+        gst::Pad::query_default(pad, Some(&*self.obj()), query)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TextTranscode {
+    const NAME: &'static str = "GstTextTranscode";
+    type Type = super::TextTranscode;
+    type ParentType = gst::Bin;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::GhostPad::builder_from_template(&templ)
+            .event_function(|pad, parent, event| {
+                TextTranscode::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad.upcast_ref(), event),
+                )
+            })
+            .query_function(|pad, parent, query| {
+                TextTranscode::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_query(pad.upcast_ref(), query),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::GhostPad::builder_from_template(&templ)
+            .query_function(|pad, parent, query| {
+                TextTranscode::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.src_query(pad.upcast_ref(), query),
+                )
+            })
+            .build();
+
+        Self {
+            sinkpad,
+            srcpad,
+            state: Mutex::new(State::default()),
+        }
+    }
+}
+
+impl ObjectImpl for TextTranscode {
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+}
+
+impl GstObjectImpl for TextTranscode {}
+
+impl ElementImpl for TextTranscode {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Timed text transcoder",
+                "Generic/Bin/Converter/ClosedCaption",
+                "Converts between timed text formats",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = TimedTextFormat::all_caps();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    #[allow(clippy::single_match)]
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {:?}", transition);
+
+        let ret = self.parent_change_state(transition)?;
+
+        match transition {
+            gst::StateChange::PausedToReady => {
+                let chain = self.state.lock().unwrap().chain.take();
+                if let Some(chain) = chain {
+                    self.teardown_chain(chain);
+                }
+            }
+            _ => (),
+        }
+
+        Ok(ret)
+    }
+}
+
+impl BinImpl for TextTranscode {}
@@ -0,0 +1,38 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-texttranscode
+ *
+ * Converts between the timed text formats used by the elements of this plugin:
+ * UTF-8 text, JSON lines, raw CEA-608, CEA-708 cc_data and fragmented WebVTT.
+ *
+ * The conversion path is selected when receiving caps, based on the input format and
+ * the formats accepted downstream, for example:
+ *
+ * ``` shell
+ * ... ! text/x-raw,format=utf8 ! texttranscode ! application/x-subtitle-vtt-fragmented ! ...
+ * ```
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+glib::wrapper! {
+    pub struct TextTranscode(ObjectSubclass<imp::TextTranscode>) @extends gst::Bin, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "texttranscode",
+        gst::Rank::NONE,
+        TextTranscode::static_type(),
+    )
+}
@@ -0,0 +1,557 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::glib;
+use gst::prelude::*;
+use gst::subclass::prelude::*;
+
+use std::sync::{LazyLock, Mutex};
+
+use crate::cea608utils::*;
+use crate::ttutils::{Chunk, Line, Lines};
+
+use super::TtSmootherGranularity;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "ttsmoother",
+        gst::DebugColorFlags::empty(),
+        Some("Timed Text Smoother"),
+    )
+});
+
+const DEFAULT_WORDS_PER_MINUTE: u32 = 180;
+const DEFAULT_GRANULARITY: TtSmootherGranularity = TtSmootherGranularity::Word;
+const DEFAULT_MAX_DELAY: u32 = 2000;
+
+/// Rows the captions are painted on
+const FIRST_ROW: u32 = 13;
+const LAST_ROW: u32 = 14;
+const MAX_COLUMNS: u32 = 32;
+
+#[derive(Debug, Clone)]
+struct Settings {
+    words_per_minute: u32,
+    granularity: TtSmootherGranularity,
+    max_delay: gst::ClockTime,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            words_per_minute: DEFAULT_WORDS_PER_MINUTE,
+            granularity: DEFAULT_GRANULARITY,
+            max_delay: gst::ClockTime::from_mseconds(DEFAULT_MAX_DELAY as u64),
+        }
+    }
+}
+
+/// Position of the next word on the screen
+#[derive(Debug)]
+struct Cursor {
+    row: u32,
+    col: u32,
+    /// Whether nothing was painted yet
+    empty: bool,
+}
+
+impl Cursor {
+    fn new() -> Self {
+        Cursor {
+            row: FIRST_ROW,
+            col: 0,
+            empty: true,
+        }
+    }
+
+    /// Returns the row and column to paint a word of `len` characters at, and whether the
+    /// display has to be cleared first
+    fn place(&mut self, len: u32) -> (u32, u32, bool) {
+        let mut clear = std::mem::take(&mut self.empty);
+
+        if self.col > 0 && self.col + 1 + len > MAX_COLUMNS {
+            if self.row == LAST_ROW {
+                self.row = FIRST_ROW;
+                clear = true;
+            } else {
+                self.row += 1;
+            }
+            self.col = 0;
+        }
+
+        let col = if self.col == 0 { 0 } else { self.col + 1 };
+        self.col = (col + len).min(MAX_COLUMNS);
+
+        (self.row, col, clear)
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    cursor: Cursor,
+    /// End of the rollout of the previous text
+    last_end: Option<gst::ClockTime>,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        State {
+            cursor: Cursor::new(),
+            last_end: None,
+        }
+    }
+}
+
+pub struct TtSmoother {
+    srcpad: gst::Pad,
+    sinkpad: gst::Pad,
+
+    // Ordered by locking order
+    state: Mutex<State>,
+    settings: Mutex<Settings>,
+}
+
+/// Splits `words` into the units that are rolled out at once
+fn split_units<'a>(words: &[&'a str], granularity: TtSmootherGranularity) -> Vec<Vec<&'a str>> {
+    match granularity {
+        TtSmootherGranularity::Phrase => {
+            let mut units = vec![];
+            let mut unit = vec![];
+            for word in words {
+                unit.push(*word);
+                if word.ends_with(['.', ',', '?', '!', ';', ':']) {
+                    units.push(std::mem::take(&mut unit));
+                }
+            }
+            if !unit.is_empty() {
+                units.push(unit);
+            }
+            units
+        }
+        _ => words.iter().map(|word| vec![*word]).collect(),
+    }
+}
+
+/// Lays out the words of a unit on the display. This results in a single [`Lines`] unless the
+/// unit doesn't fit on the display at once.
+fn layout_unit(cursor: &mut Cursor, unit: &[&str]) -> Vec<Lines> {
+    let new_lines = || Lines {
+        lines: Vec::new(),
+        mode: Some(Cea608Mode::PaintOn),
+        clear: Some(false),
+    };
+
+    let mut ret = vec![];
+    let mut lines = new_lines();
+
+    for word in unit {
+        let len = word.chars().count() as u32;
+        let (mut row, mut col, clear) = cursor.place(len);
+
+        if clear {
+            if !lines.lines.is_empty() {
+                // Move the beginning of the unit to the cleared display too
+                *cursor = Cursor::new();
+                cursor.empty = false;
+                for line in &mut lines.lines {
+                    let (row, col, _) = cursor.place(line.chunks[0].text.chars().count() as u32);
+                    line.row = Some(row);
+                    line.column = Some(col);
+                }
+
+                let again;
+                (row, col, again) = cursor.place(len);
+                // Longer than the whole display
+                if again {
+                    ret.push(std::mem::replace(&mut lines, new_lines()));
+                }
+            }
+            lines.clear = Some(true);
+        }
+
+        lines.lines.push(Line {
+            carriage_return: None,
+            column: Some(col),
+            row: Some(row),
+            chunks: vec![Chunk {
+                style: TextStyle::White,
+                underline: false,
+                text: word.to_string(),
+            }],
+        });
+    }
+
+    ret.push(lines);
+    ret
+}
+
+impl TtSmoother {
+    fn sink_chain(
+        &self,
+        pad: &gst::Pad,
+        buffer: gst::Buffer,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        gst::log!(CAT, obj = pad, "Handling {:?}", buffer);
+
+        let pts = buffer.pts().ok_or_else(|| {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Format,
+                ["Stream with timestamped buffers required"]
+            );
+            gst::FlowError::Error
+        })?;
+        let duration = buffer.duration().unwrap_or(gst::ClockTime::ZERO);
+
+        let data = buffer.map_readable().map_err(|_| {
+            gst::error!(CAT, obj = pad, "Can't map buffer readable");
+
+            gst::FlowError::Error
+        })?;
+
+        let text = std::str::from_utf8(&data).map_err(|err| {
+            gst::element_imp_error!(
+                self,
+                gst::StreamError::Decode,
+                ["Failed to decode input as utf8: {}", err]
+            );
+
+            gst::FlowError::Error
+        })?;
+
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        if words.is_empty() {
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        let settings = self.settings.lock().unwrap().clone();
+        let mut state = self.state.lock().unwrap();
+
+        let start = state.last_end.map_or(pts, |last_end| last_end.max(pts));
+        let deadline = pts
+            .saturating_add(duration)
+            .saturating_add(settings.max_delay);
+        let n_words = words.len() as u64;
+
+        // Speed up if the words can't be rolled out at the configured rate before the deadline
+        let mut word_duration = gst::ClockTime::from_seconds(60) / settings.words_per_minute as u64;
+        if start.saturating_add(word_duration * n_words) > deadline {
+            word_duration = deadline.saturating_sub(start) / n_words;
+            gst::debug!(
+                CAT,
+                imp = self,
+                "Rolling out {n_words} words at {pts} with {word_duration} per word"
+            );
+        }
+
+        let mut bufferlist = gst::BufferList::new();
+        let mut_list = bufferlist.get_mut().unwrap();
+        let mut time = start;
+
+        for unit in split_units(&words, settings.granularity) {
+            let unit_duration = word_duration * unit.len() as u64;
+            let mut all_lines = layout_unit(&mut state.cursor, &unit).into_iter().peekable();
+
+            while let Some(lines) = all_lines.next() {
+                let json = serde_json::to_vec(&lines).map_err(|err| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::ResourceError::Write,
+                        ["Failed to serialize as json {}", err]
+                    );
+
+                    gst::FlowError::Error
+                })?;
+
+                let mut buf = gst::Buffer::from_mut_slice(json);
+                {
+                    let buf = buf.get_mut().unwrap();
+                    buf.set_pts(time);
+                    // Only the last part of a unit that didn't fit on the display stays visible
+                    buf.set_duration(if all_lines.peek().is_none() {
+                        unit_duration
+                    } else {
+                        gst::ClockTime::ZERO
+                    });
+                }
+                mut_list.add(buf);
+            }
+
+            time += unit_duration;
+        }
+
+        state.last_end = Some(time);
+        drop(state);
+        drop(data);
+
+        gst::trace!(
+            CAT,
+            imp = self,
+            "Rolling out {n_words} words from {start} to {time}"
+        );
+
+        self.srcpad.push_list(bufferlist)
+    }
+
+    fn sink_event(&self, pad: &gst::Pad, event: gst::Event) -> bool {
+        use gst::EventView;
+
+        gst::log!(CAT, obj = pad, "Handling event {:?}", event);
+
+        match event.view() {
+            EventView::Caps(_) => {
+                // We send our own caps downstream
+                let caps = gst::Caps::builder("application/x-json")
+                    .field("format", "cea608")
+                    .build();
+                self.srcpad.push_event(gst::event::Caps::new(&caps))
+            }
+            EventView::Gap(e) => {
+                let (timestamp, duration) = e.get();
+                let end = timestamp.opt_add(duration).unwrap_or(timestamp);
+
+                // Gaps that overlap with text that is still being rolled out are shortened
+                let last_end = self.state.lock().unwrap().last_end;
+                match last_end {
+                    Some(last_end) if end <= last_end => true,
+                    Some(last_end) if timestamp < last_end => {
+                        let gap = gst::event::Gap::builder(last_end)
+                            .duration(end - last_end)
+                            .seqnum(event.seqnum())
+                            .build();
+                        self.srcpad.push_event(gap)
+                    }
+                    _ => self.srcpad.push_event(event),
+                }
+            }
+            EventView::FlushStop(_) => {
+                *self.state.lock().unwrap() = State::default();
+                gst::Pad::event_default(pad, Some(&*self.obj()), event)
+            }
+            _ => gst::Pad::event_default(pad, Some(&*self.obj()), event),
+        }
+    }
+}
+
+impl GstObjectImpl for TtSmoother {}
+
+impl ElementImpl for TtSmoother {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Timed text smoother",
+                "Filter/ClosedCaption",
+                "Re-times timed text into paint-on captions rolled out at a natural reading rate",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst::Caps::builder("text/x-raw")
+                .field("format", "utf8")
+                .build();
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let caps = gst::Caps::builder("application/x-json")
+                .field("format", "cea608")
+                .build();
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+
+    fn change_state(
+        &self,
+        transition: gst::StateChange,
+    ) -> Result<gst::StateChangeSuccess, gst::StateChangeError> {
+        gst::trace!(CAT, imp = self, "Changing state {:?}", transition);
+
+        if transition == gst::StateChange::ReadyToPaused {
+            *self.state.lock().unwrap() = State::default();
+        }
+
+        self.parent_change_state(transition)
+    }
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for TtSmoother {
+    const NAME: &'static str = "GstTtSmoother";
+    type Type = super::TtSmoother;
+    type ParentType = gst::Element;
+
+    fn with_class(klass: &Self::Class) -> Self {
+        let templ = klass.pad_template("sink").unwrap();
+        let sinkpad = gst::Pad::builder_from_template(&templ)
+            .chain_function(|pad, parent, buffer| {
+                TtSmoother::catch_panic_pad_function(
+                    parent,
+                    || Err(gst::FlowError::Error),
+                    |this| this.sink_chain(pad, buffer),
+                )
+            })
+            .event_function(|pad, parent, event| {
+                TtSmoother::catch_panic_pad_function(
+                    parent,
+                    || false,
+                    |this| this.sink_event(pad, event),
+                )
+            })
+            .build();
+
+        let templ = klass.pad_template("src").unwrap();
+        let srcpad = gst::Pad::from_template(&templ);
+
+        Self {
+            srcpad,
+            sinkpad,
+            state: Mutex::new(State::default()),
+            settings: Mutex::new(Settings::default()),
+        }
+    }
+}
+
+impl ObjectImpl for TtSmoother {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecUInt::builder("words-per-minute")
+                    .nick("Words per minute")
+                    .blurb("Rate at which words are rolled out")
+                    .minimum(1)
+                    .default_value(DEFAULT_WORDS_PER_MINUTE)
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("granularity", DEFAULT_GRANULARITY)
+                    .nick("Granularity")
+                    .blurb("Whether to roll out words or phrases at once")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecUInt::builder("max-delay")
+                    .nick("Maximum delay")
+                    .blurb("Maximum delay (in ms) of the end of the rollout relative to the end of the input, the rollout is sped up to keep up")
+                    .default_value(DEFAULT_MAX_DELAY)
+                    .mutable_playing()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn constructed(&self) {
+        self.parent_constructed();
+
+        let obj = self.obj();
+        obj.add_pad(&self.sinkpad).unwrap();
+        obj.add_pad(&self.srcpad).unwrap();
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "words-per-minute" => {
+                settings.words_per_minute = value.get().expect("type checked upstream");
+            }
+            "granularity" => {
+                settings.granularity = value.get().expect("type checked upstream");
+            }
+            "max-delay" => {
+                settings.max_delay =
+                    gst::ClockTime::from_mseconds(value.get::<u32>().unwrap() as u64);
+            }
+            _ => unimplemented!(),
+        }
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+
+        match pspec.name() {
+            "words-per-minute" => settings.words_per_minute.to_value(),
+            "granularity" => settings.granularity.to_value(),
+            "max-delay" => (settings.max_delay.mseconds() as u32).to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_wraps() {
+        let mut cursor = Cursor::new();
+
+        assert_eq!(cursor.place(10), (FIRST_ROW, 0, true));
+        assert_eq!(cursor.place(10), (FIRST_ROW, 11, false));
+        // Doesn't fit on the first row anymore
+        assert_eq!(cursor.place(11), (LAST_ROW, 0, false));
+        assert_eq!(cursor.place(20), (LAST_ROW, 12, false));
+        // Display is full
+        assert_eq!(cursor.place(5), (FIRST_ROW, 0, true));
+    }
+
+    #[test]
+    fn layout_moves_unit() {
+        let mut cursor = Cursor::new();
+
+        let lines = layout_unit(
+            &mut cursor,
+            &["a".repeat(30).as_str(), "b".repeat(30).as_str()],
+        );
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].clear, Some(true));
+
+        // The second word doesn't fit on the display anymore, the whole unit is painted on the
+        // cleared display
+        let lines = layout_unit(&mut cursor, &["c", "d".repeat(10).as_str()]);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].clear, Some(true));
+        let positions = lines[0]
+            .lines
+            .iter()
+            .map(|line| (line.row.unwrap(), line.column.unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(positions, [(FIRST_ROW, 0), (FIRST_ROW, 2)]);
+    }
+
+    #[test]
+    fn phrase_units() {
+        let words = ["Hello", "world,", "this", "is", "a", "test.", "More"];
+
+        assert_eq!(
+            split_units(&words, TtSmootherGranularity::Phrase),
+            vec![
+                vec!["Hello", "world,"],
+                vec!["this", "is", "a", "test."],
+                vec!["More"]
+            ]
+        );
+        assert_eq!(split_units(&words, TtSmootherGranularity::Word).len(), 7);
+    }
+}
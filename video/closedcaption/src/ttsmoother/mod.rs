@@ -0,0 +1,62 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+/**
+ * SECTION:element-ttsmoother
+ * @see_also: tttocea608
+ *
+ * Re-times sentence-at-once timed text, as output by live transcribers, into word-by-word or
+ * phrase-by-phrase paint-on captions at a natural reading rate.
+ *
+ * Every input buffer is split into words that are rolled out at the configured words per minute,
+ * starting at the timestamp of the buffer or once the previous text is rolled out. If the rollout
+ * would end more than #ttsmoother:max-delay after the end of the input buffer, the words of that
+ * buffer are rolled out faster.
+ *
+ * The output is in the JSON format accepted by `tttocea608`, with the words placed on the two
+ * bottom rows of the screen in paint-on mode:
+ *
+ * ``` shell
+ * ... ! text/x-raw,format=utf8 ! ttsmoother words-per-minute=160 ! tttocea608 ! ...
+ * ```
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+mod imp;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstTtSmootherGranularity")]
+#[non_exhaustive]
+pub enum TtSmootherGranularity {
+    #[default]
+    #[enum_value(name = "Word: Roll out captions word by word", nick = "word")]
+    Word,
+    #[enum_value(
+        name = "Phrase: Roll out captions phrase by phrase, split at punctuation",
+        nick = "phrase"
+    )]
+    Phrase,
+}
+
+glib::wrapper! {
+    pub struct TtSmoother(ObjectSubclass<imp::TtSmoother>) @extends gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    #[cfg(feature = "doc")]
+    TtSmootherGranularity::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+
+    gst::Element::register(
+        Some(plugin),
+        "ttsmoother",
+        gst::Rank::NONE,
+        TtSmoother::static_type(),
+    )
+}
@@ -50,3 +50,85 @@ impl Cea608Mode {
         *self == Cea608Mode::RollUp2 || *self == Cea608Mode::RollUp3 || *self == Cea608Mode::RollUp4
     }
 }
+
+/// Timed text formats exchanged between the elements of this plugin
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TimedTextFormat {
+    /// UTF-8 text lines
+    Text,
+    /// JSON serialized [`Lines`]
+    Json,
+    /// Raw CEA-608 byte pairs of field 1
+    Cea608,
+    /// CEA-708 cc_data
+    Cea708,
+    /// Fragmented WebVTT
+    Vtt,
+}
+
+impl TimedTextFormat {
+    /// All formats, in order of preference
+    pub const ALL: [TimedTextFormat; 5] = [
+        TimedTextFormat::Text,
+        TimedTextFormat::Json,
+        TimedTextFormat::Cea608,
+        TimedTextFormat::Cea708,
+        TimedTextFormat::Vtt,
+    ];
+
+    pub fn caps(self) -> gst::Caps {
+        match self {
+            TimedTextFormat::Text => gst::Caps::builder("text/x-raw")
+                .field("format", "utf8")
+                .build(),
+            TimedTextFormat::Json => gst::Caps::builder("application/x-json")
+                .field("format", "cea608")
+                .build(),
+            TimedTextFormat::Cea608 => gst::Caps::builder("closedcaption/x-cea-608")
+                .field("format", "raw")
+                .build(),
+            TimedTextFormat::Cea708 => gst::Caps::builder("closedcaption/x-cea-708")
+                .field("format", "cc_data")
+                .build(),
+            TimedTextFormat::Vtt => {
+                gst::Caps::builder("application/x-subtitle-vtt-fragmented").build()
+            }
+        }
+    }
+
+    /// Caps of all formats
+    pub fn all_caps() -> gst::Caps {
+        Self::ALL.into_iter().map(Self::caps).collect()
+    }
+
+    pub fn from_caps(caps: &gst::CapsRef) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|format| caps.can_intersect(&format.caps()))
+    }
+
+    /// Names of the elements that have to be chained to convert from `self` to `to`
+    pub fn conversion_path(self, to: TimedTextFormat) -> Option<&'static [&'static str]> {
+        use TimedTextFormat::*;
+
+        let path: &'static [&'static str] = match (self, to) {
+            (from, to) if from == to => &["identity"],
+            (Text, Json) => &["tttojson"],
+            (Text, Cea608) => &["tttocea608"],
+            (Text, Cea708) => &["tttocea708"],
+            (Text, Vtt) => &["tttojson", "jsontovtt"],
+            (Json, Text) => &["tttocea608", "cea608tott"],
+            (Json, Cea608) => &["tttocea608"],
+            (Json, Cea708) => &["tttocea608", "cea608tocea708"],
+            (Json, Vtt) => &["jsontovtt"],
+            (Cea608, Text) => &["cea608tott"],
+            (Cea608, Json) => &["cea608tojson"],
+            (Cea608, Cea708) => &["cea608tocea708"],
+            (Cea608, Vtt) => &["cea608tojson", "jsontovtt"],
+            (Cea708, Vtt) => &["cea708tovtt"],
+            _ => return None,
+        };
+
+        Some(path)
+    }
+}
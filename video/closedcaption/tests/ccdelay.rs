@@ -0,0 +1,83 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst::ClockTime;
+
+use pretty_assertions::assert_eq;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsclosedcaption::plugin_register_static().unwrap();
+    });
+}
+
+fn video_pad(latency: ClockTime) -> gst::Pad {
+    gst::Pad::builder(gst::PadDirection::Src)
+        .query_function(move |_pad, _parent, query| match query.view_mut() {
+            gst::QueryViewMut::Latency(q) => {
+                q.set(true, latency, gst::ClockTime::NONE);
+                true
+            }
+            _ => false,
+        })
+        .build()
+}
+
+fn push_pull(h: &mut gst_check::Harness, pts: ClockTime) -> Option<ClockTime> {
+    let mut buf = gst::Buffer::from_mut_slice(vec![0xfc, 0x80, 0x80]);
+    buf.get_mut().unwrap().set_pts(pts);
+    assert_eq!(h.push(buf), Ok(gst::FlowSuccess::Ok));
+
+    h.pull().unwrap().pts()
+}
+
+#[test]
+fn test_delay_to_video_latency() {
+    init();
+
+    let mut h = gst_check::Harness::new("ccdelay");
+    h.element()
+        .unwrap()
+        .set_property("video-pad", video_pad(ClockTime::from_mseconds(100)));
+    h.set_upstream_latency(ClockTime::from_mseconds(20));
+    h.set_src_caps_str("closedcaption/x-cea-608,format=raw,framerate=25/1");
+
+    assert_eq!(
+        push_pull(&mut h, ClockTime::ZERO),
+        Some(ClockTime::from_mseconds(80))
+    );
+    assert_eq!(
+        h.element().unwrap().property::<u64>("delay"),
+        ClockTime::from_mseconds(80).nseconds()
+    );
+
+    // Rounded up to whole frames
+    h.element()
+        .unwrap()
+        .set_property("extra-delay", ClockTime::from_mseconds(30).nseconds());
+    assert_eq!(
+        push_pull(&mut h, ClockTime::from_mseconds(40)),
+        Some(ClockTime::from_mseconds(160))
+    );
+}
+
+#[test]
+fn test_no_video_pad() {
+    init();
+
+    let mut h = gst_check::Harness::new("ccdelay");
+    h.set_src_caps_str("closedcaption/x-cea-708,format=cc_data,framerate=30/1");
+
+    assert_eq!(
+        push_pull(&mut h, ClockTime::from_mseconds(100)),
+        Some(ClockTime::from_mseconds(100))
+    );
+}
@@ -0,0 +1,169 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use pretty_assertions::assert_eq;
+
+use cea708_types::tables::*;
+use cea708_types::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsclosedcaption::plugin_register_static().unwrap();
+    });
+}
+
+fn gen_cc_data(seq: u8, service: u8, codes: &[Code], pts: gst::ClockTime) -> gst::Buffer {
+    assert!(seq < 4);
+    assert!(service < 64);
+
+    let fps = Framerate::new(30, 1);
+    let mut writer = CCDataWriter::default();
+    let mut packet = DTVCCPacket::new(seq);
+    let mut service = Service::new(service);
+    for c in codes {
+        service.push_code(c).unwrap();
+    }
+    packet.push_service(service).unwrap();
+    writer.push_packet(packet);
+    let mut data = vec![];
+    writer.write(fps, &mut data).unwrap();
+    let data = data.split_off(2);
+    let mut buf = gst::Buffer::from_mut_slice(data);
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.set_pts(pts);
+    }
+    buf
+}
+
+fn text_codes(text: &str) -> Vec<Code> {
+    text.chars().map(|c| Code::from_char(c).unwrap()).collect()
+}
+
+fn define_window(anchor_point: Anchor, visible: bool) -> DefineWindowArgs {
+    DefineWindowArgs::new(
+        0,
+        0,
+        anchor_point,
+        true,
+        10,
+        20,
+        1,
+        20,
+        true,
+        true,
+        visible,
+        1,
+        1,
+    )
+}
+
+fn pull_text(h: &mut gst_check::Harness) -> (gst::ClockTime, Option<gst::ClockTime>, String) {
+    let buffer = h.pull().unwrap();
+    let text = std::str::from_utf8(&buffer.map_readable().unwrap())
+        .unwrap()
+        .to_string();
+    (buffer.pts().unwrap(), buffer.duration(), text)
+}
+
+#[test]
+fn test_cea708tovtt_popon() {
+    init();
+
+    let mut h = gst_check::Harness::new("cea708tovtt");
+    h.set_src_caps_str("closedcaption/x-cea-708,format=cc_data,framerate=30/1");
+
+    let mut codes = vec![Code::DefineWindow(define_window(Anchor::TopLeft, false))];
+    codes.extend(text_codes("Hello"));
+    h.push(gen_cc_data(0, 1, &codes, gst::ClockTime::ZERO))
+        .unwrap();
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    // Text on another service is ignored
+    h.push(gen_cc_data(
+        1,
+        2,
+        &text_codes("ignored"),
+        gst::ClockTime::from_mseconds(100),
+    ))
+    .unwrap();
+
+    h.push(gen_cc_data(
+        2,
+        1,
+        &[Code::DisplayWindows(WindowBits::ZERO)],
+        gst::ClockTime::SECOND,
+    ))
+    .unwrap();
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    h.push(gen_cc_data(
+        3,
+        1,
+        &[Code::ClearWindows(WindowBits::ZERO)],
+        gst::ClockTime::from_seconds(3),
+    ))
+    .unwrap();
+
+    let (pts, _, header) = pull_text(&mut h);
+    assert_eq!(pts, gst::ClockTime::SECOND);
+    assert_eq!(header, "WEBVTT\r\n\r\n");
+
+    let (pts, duration, cue) = pull_text(&mut h);
+    assert_eq!(pts, gst::ClockTime::SECOND);
+    assert_eq!(duration, Some(gst::ClockTime::from_seconds(2)));
+    assert_eq!(
+        cue,
+        "00:00:01.000 --> 00:00:03.000 line:10%,start position:20%,line-left size:50% align:left\r\nHello\r\n\r\n"
+    );
+
+    let caps = h.srcpad().unwrap().current_caps().unwrap();
+    assert_eq!(
+        caps.structure(0).unwrap().name(),
+        "application/x-subtitle-vtt"
+    );
+}
+
+#[test]
+fn test_cea708tovtt_styled_text_on_eos() {
+    init();
+
+    let mut h = gst_check::Harness::new("cea708tovtt");
+    h.set_src_caps_str("closedcaption/x-cea-708,format=cc_data,framerate=30/1");
+
+    let window = define_window(Anchor::BottomMiddle, true);
+    let mut codes = vec![Code::DefineWindow(window)];
+    codes.extend(text_codes("a"));
+    codes.push(Code::SetPenColor(SetPenColorArgs {
+        foreground_color: Color {
+            r: ColorValue::Full,
+            g: ColorValue::Full,
+            b: ColorValue::None,
+        },
+        ..window.pen_color()
+    }));
+    codes.extend(text_codes("b&"));
+    h.push(gen_cc_data(0, 1, &codes, gst::ClockTime::SECOND))
+        .unwrap();
+
+    h.push_event(gst::event::Eos::new());
+
+    let (_, _, header) = pull_text(&mut h);
+    assert_eq!(header, "WEBVTT\r\n\r\n");
+
+    let (pts, duration, cue) = pull_text(&mut h);
+    assert_eq!(pts, gst::ClockTime::SECOND);
+    assert_eq!(duration, Some(gst::ClockTime::ZERO));
+    assert_eq!(
+        cue,
+        "00:00:01.000 --> 00:00:01.000 line:10%,end position:20%,center size:50% align:left\r\na<c.yellow>b&amp;</c>\r\n\r\n"
+    );
+}
@@ -0,0 +1,108 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use pretty_assertions::assert_eq;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsclosedcaption::plugin_register_static().unwrap();
+    });
+}
+
+/// Creates an ST-2038 buffer containing a CEA-608 packet on line 9
+fn st2038_buffer(pts: gst::ClockTime) -> gst::Buffer {
+    let mut h = gst_check::Harness::new("cctost2038anc");
+    h.set_src_caps_str("closedcaption/x-cea-608,format=s334-1a,framerate=30/1");
+
+    let mut buf = gst::Buffer::from_slice([0x80, 0x94, 0x2c]);
+    buf.get_mut().unwrap().set_pts(pts);
+    h.push(buf).unwrap();
+
+    h.pull().unwrap()
+}
+
+fn video_buffer(info: &gst_video::VideoInfo, pts: gst::ClockTime) -> gst::Buffer {
+    let mut buf = gst::Buffer::with_size(info.size()).unwrap();
+    {
+        let buf = buf.get_mut().unwrap();
+        buf.map_writable().unwrap().fill(0);
+        buf.set_pts(pts);
+        buf.set_duration(gst::ClockTime::from_mseconds(33));
+    }
+    buf
+}
+
+fn roundtrip(format: gst_video::VideoFormat) {
+    init();
+
+    let info = gst_video::VideoInfo::builder(format, 64, 4)
+        .fps(gst::Fraction::new(30, 1))
+        .build()
+        .unwrap();
+    let caps = info.to_caps().unwrap();
+
+    let mut h_insert = gst_check::Harness::with_padnames("anc2sdi", Some("sink"), Some("src"));
+    let element = h_insert.element().unwrap();
+    element.set_property("line-offset", 9u32);
+    let mut h_anc = gst_check::Harness::with_element(&element, Some("anc_sink"), None);
+    h_insert.set_src_caps(caps.clone());
+    h_anc.set_src_caps_str("meta/x-st-2038");
+
+    let mut h_extract = gst_check::Harness::new("sdi2anc");
+    h_extract
+        .element()
+        .unwrap()
+        .set_property("line-offset", 9u32);
+    h_extract.set_src_caps(caps);
+
+    let anc = st2038_buffer(gst::ClockTime::ZERO);
+    h_anc.push(anc.clone()).unwrap();
+
+    // First frame carries the ANC packet
+    h_insert
+        .push(video_buffer(&info, gst::ClockTime::ZERO))
+        .unwrap();
+    let video = h_insert.pull().unwrap();
+    h_extract.push(video).unwrap();
+
+    let out = h_extract.pull().unwrap();
+    assert_eq!(out.pts(), Some(gst::ClockTime::ZERO));
+    assert_eq!(
+        out.map_readable().unwrap().as_slice(),
+        anc.map_readable().unwrap().as_slice()
+    );
+
+    // Second frame carries nothing and results in a gap event
+    h_insert
+        .push(video_buffer(&info, gst::ClockTime::from_mseconds(33)))
+        .unwrap();
+    let video = h_insert.pull().unwrap();
+    h_extract.push(video).unwrap();
+    assert_eq!(h_extract.buffers_in_queue(), 0);
+
+    loop {
+        let ev = h_extract.pull_event().unwrap();
+        if let gst::EventView::Gap(gap) = ev.view() {
+            assert_eq!(gap.get().0, gst::ClockTime::from_mseconds(33));
+            break;
+        }
+    }
+}
+
+#[test]
+fn test_roundtrip_v210() {
+    roundtrip(gst_video::VideoFormat::V210);
+}
+
+#[test]
+fn test_roundtrip_uyvy() {
+    roundtrip(gst_video::VideoFormat::Uyvy);
+}
@@ -0,0 +1,156 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst::ClockTime;
+use pretty_assertions::assert_eq;
+
+use cea708_types::tables::*;
+use cea708_types::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsclosedcaption::plugin_register_static().unwrap();
+    });
+}
+
+fn new_timed_buffer<T: AsRef<[u8]> + Send + 'static>(
+    slice: T,
+    timestamp: ClockTime,
+    duration: ClockTime,
+) -> gst::buffer::Buffer {
+    let mut buf = gst::Buffer::from_slice(slice);
+    let buf_ref = buf.get_mut().unwrap();
+    buf_ref.set_pts(timestamp);
+    buf_ref.set_duration(duration);
+    buf
+}
+
+fn cc_data_buffer(seq: u8, codes: &[Code], pts: ClockTime) -> gst::Buffer {
+    let mut writer = CCDataWriter::default();
+    let mut packet = DTVCCPacket::new(seq);
+    let mut service = Service::new(1);
+    for c in codes {
+        service.push_code(c).unwrap();
+    }
+    packet.push_service(service).unwrap();
+    writer.push_packet(packet);
+    let mut data = vec![];
+    writer.write(Framerate::new(30, 1), &mut data).unwrap();
+    // Strip the cc_data header, only the triplets are part of the buffer
+    let data = data.split_off(2);
+
+    let mut buf = gst::Buffer::from_mut_slice(data);
+    buf.get_mut().unwrap().set_pts(pts);
+    buf
+}
+
+fn output_caps(h: &mut gst_check::Harness) -> gst::Caps {
+    loop {
+        let event = h.pull_event().unwrap();
+        if let gst::EventView::Caps(ev) = event.view() {
+            return ev.caps_owned();
+        }
+    }
+}
+
+#[test]
+fn test_text_to_json() {
+    init();
+
+    let mut h = gst_check::Harness::new("texttranscode");
+    h.set_sink_caps_str("application/x-json");
+    h.set_src_caps_str("text/x-raw,format=utf8");
+
+    let inbuf = new_timed_buffer("Hello", ClockTime::SECOND, ClockTime::SECOND);
+    assert_eq!(h.push(inbuf), Ok(gst::FlowSuccess::Ok));
+
+    let caps = output_caps(&mut h);
+    assert_eq!(caps.structure(0).unwrap().name(), "application/x-json");
+
+    let outbuf = h.pull().unwrap();
+    assert_eq!(outbuf.pts(), Some(ClockTime::SECOND));
+    let map = outbuf.map_readable().unwrap();
+    let json = std::str::from_utf8(&map).unwrap();
+    assert!(json.contains("Hello"));
+}
+
+#[test]
+fn test_passthrough() {
+    init();
+
+    let mut h = gst_check::Harness::new("texttranscode");
+    h.set_sink_caps_str("text/x-raw,format=utf8");
+    h.set_src_caps_str("text/x-raw,format=utf8");
+
+    let inbuf = new_timed_buffer("Hello", ClockTime::SECOND, ClockTime::SECOND);
+    assert_eq!(h.push(inbuf), Ok(gst::FlowSuccess::Ok));
+
+    let caps = output_caps(&mut h);
+    assert_eq!(caps.structure(0).unwrap().name(), "text/x-raw");
+
+    let outbuf = h.pull().unwrap();
+    assert_eq!(&*outbuf.map_readable().unwrap(), b"Hello");
+}
+
+#[test]
+fn test_cea708_to_vtt() {
+    init();
+
+    let mut h = gst_check::Harness::new("texttranscode");
+    h.set_sink_caps_str("application/x-subtitle-vtt-fragmented");
+    h.set_src_caps_str("closedcaption/x-cea-708,format=cc_data,framerate=30/1");
+
+    let mut codes = vec![Code::DefineWindow(DefineWindowArgs::new(
+        0,
+        0,
+        Anchor::TopLeft,
+        true,
+        10,
+        20,
+        1,
+        20,
+        true,
+        true,
+        true,
+        1,
+        1,
+    ))];
+    codes.extend("Hello".chars().map(|c| Code::from_char(c).unwrap()));
+    assert_eq!(
+        h.push(cc_data_buffer(0, &codes, ClockTime::SECOND)),
+        Ok(gst::FlowSuccess::Ok)
+    );
+    assert_eq!(
+        h.push(cc_data_buffer(
+            1,
+            &[Code::ClearWindows(WindowBits::ZERO)],
+            ClockTime::from_seconds(3)
+        )),
+        Ok(gst::FlowSuccess::Ok)
+    );
+
+    let caps = output_caps(&mut h);
+    let s = caps.structure(0).unwrap();
+    assert_eq!(s.name(), "application/x-subtitle-vtt-fragmented");
+    assert_eq!(s.get::<bool>("inline-headers"), Ok(true));
+
+    let header = h.pull().unwrap();
+    assert_eq!(header.pts(), Some(ClockTime::SECOND));
+    assert_eq!(&*header.map_readable().unwrap(), b"WEBVTT\r\n\r\n");
+
+    let cue = h.pull().unwrap();
+    assert_eq!(cue.pts(), Some(ClockTime::SECOND));
+    assert_eq!(cue.duration(), Some(ClockTime::from_seconds(2)));
+    let map = cue.map_readable().unwrap();
+    let cue = std::str::from_utf8(&map).unwrap();
+    assert!(cue.starts_with("00:00:01.000 --> 00:00:03.000 "));
+    assert!(cue.contains("\r\nHello\r\n"));
+}
@@ -0,0 +1,152 @@
+// This Source Code Form is subject to the terms of the Mozilla Public License, v2.0.
+// If a copy of the MPL was not distributed with this file, You can obtain one at
+// <https://mozilla.org/MPL/2.0/>.
+//
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+use gst::ClockTime;
+use pretty_assertions::assert_eq;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsclosedcaption::plugin_register_static().unwrap();
+    });
+}
+
+fn new_timed_buffer<T: AsRef<[u8]> + Send + 'static>(
+    slice: T,
+    timestamp: ClockTime,
+    duration: ClockTime,
+) -> gst::buffer::Buffer {
+    let mut buf = gst::Buffer::from_slice(slice);
+    let buf_ref = buf.get_mut().unwrap();
+    buf_ref.set_pts(timestamp);
+    buf_ref.set_duration(duration);
+    buf
+}
+
+/// Pulls all buffers and returns their timestamps, durations and words
+fn pull_words(h: &mut gst_check::Harness) -> Vec<(ClockTime, ClockTime, Vec<String>)> {
+    let mut ret = vec![];
+
+    while let Some(buffer) = h.try_pull() {
+        let map = buffer.map_readable().unwrap();
+        let lines: serde_json::Value = serde_json::from_slice(&map).unwrap();
+        assert_eq!(lines["mode"], "PaintOn");
+
+        let words = lines["lines"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|line| line["chunks"][0]["text"].as_str().unwrap().to_string())
+            .collect();
+
+        ret.push((buffer.pts().unwrap(), buffer.duration().unwrap(), words));
+    }
+
+    ret
+}
+
+#[test]
+fn test_word_rollout() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse("ttsmoother words-per-minute=120 max-delay=10000");
+    h.set_src_caps_str("text/x-raw,format=utf8");
+
+    let inbuf = new_timed_buffer("Hello world", ClockTime::SECOND, ClockTime::SECOND);
+    assert_eq!(h.push(inbuf), Ok(gst::FlowSuccess::Ok));
+
+    // Arrives before the previous sentence was rolled out
+    let inbuf = new_timed_buffer(
+        "how are you?",
+        ClockTime::from_mseconds(1500),
+        ClockTime::SECOND,
+    );
+    assert_eq!(h.push(inbuf), Ok(gst::FlowSuccess::Ok));
+
+    let word_duration = ClockTime::from_mseconds(500);
+    let expected = ["Hello", "world", "how", "are", "you?"]
+        .into_iter()
+        .enumerate()
+        .map(|(i, word)| {
+            (
+                ClockTime::SECOND + word_duration * i as u64,
+                word_duration,
+                vec![word.to_string()],
+            )
+        })
+        .collect::<Vec<_>>();
+
+    assert_eq!(pull_words(&mut h), expected);
+}
+
+#[test]
+fn test_phrase_rollout() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse(
+        "ttsmoother words-per-minute=60 granularity=phrase max-delay=10000",
+    );
+    h.set_src_caps_str("text/x-raw,format=utf8");
+
+    let inbuf = new_timed_buffer(
+        "Hello world, how are you?",
+        ClockTime::ZERO,
+        ClockTime::SECOND,
+    );
+    assert_eq!(h.push(inbuf), Ok(gst::FlowSuccess::Ok));
+
+    assert_eq!(
+        pull_words(&mut h),
+        vec![
+            (
+                ClockTime::ZERO,
+                ClockTime::from_seconds(2),
+                vec!["Hello".to_string(), "world,".to_string()]
+            ),
+            (
+                ClockTime::from_seconds(2),
+                ClockTime::from_seconds(3),
+                vec!["how".to_string(), "are".to_string(), "you?".to_string()]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_max_delay() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse("ttsmoother words-per-minute=60 max-delay=1000");
+    h.set_src_caps_str("text/x-raw,format=utf8");
+
+    // 4 words at 60 words per minute would take 4 seconds, but have to be rolled out within
+    // 2 seconds
+    let inbuf = new_timed_buffer("one two three four", ClockTime::ZERO, ClockTime::SECOND);
+    assert_eq!(h.push(inbuf), Ok(gst::FlowSuccess::Ok));
+
+    let words = pull_words(&mut h);
+    assert_eq!(words.len(), 4);
+    for (i, (pts, duration, _)) in words.into_iter().enumerate() {
+        assert_eq!(pts, ClockTime::from_mseconds(500) * i as u64);
+        assert_eq!(duration, ClockTime::from_mseconds(500));
+    }
+}
+
+#[test]
+fn test_non_timed_buffer() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse("ttsmoother");
+    h.set_src_caps_str("text/x-raw,format=utf8");
+
+    let inbuf = gst::Buffer::from_slice("Hello");
+
+    assert_eq!(h.push(inbuf), Err(gst::FlowError::Error));
+}
@@ -52,6 +52,7 @@ const DEFAULT_N_THREADS: u32 = 0;
 const DEFAULT_MAX_FRAME_DELAY: i64 = -1;
 const DEFAULT_APPLY_GRAIN: bool = false;
 const DEFAULT_INLOOP_FILTERS: InloopFilterType = InloopFilterType::empty();
+const DEFAULT_ERROR_RESILIENCE: bool = false;
 
 struct State {
     decoder: dav1d::Decoder,
@@ -59,6 +60,8 @@ struct State {
     output_info: Option<gst_video::VideoInfo>,
     video_meta_supported: bool,
     n_cpus: usize,
+    /// Time ranges of dropped corrupted frames that still have to be signalled downstream
+    pending_gaps: Vec<(gst::ClockTime, Option<gst::ClockTime>)>,
 }
 
 // We make our own settings object so we don't have to deal with a Sync impl for dav1d::Settings
@@ -67,6 +70,7 @@ struct Settings {
     max_frame_delay: i64,
     apply_grain: bool,
     inloop_filters: InloopFilterType,
+    error_resilience: bool,
 }
 
 impl Default for Settings {
@@ -76,6 +80,7 @@ impl Default for Settings {
             max_frame_delay: DEFAULT_MAX_FRAME_DELAY,
             apply_grain: DEFAULT_APPLY_GRAIN,
             inloop_filters: DEFAULT_INLOOP_FILTERS,
+            error_resilience: DEFAULT_ERROR_RESILIENCE,
         }
     }
 }
@@ -305,7 +310,10 @@ impl Dav1dDec {
         let instance = self.obj();
         let mut output_state =
             instance.set_output_state(format, pic.width(), pic.height(), Some(&input_state))?;
-        let mut info_builder = gst_video::VideoInfo::builder(format, pic.width(), pic.height());
+        // Start from the output state info so that pixel-aspect-ratio, framerate and interlacing
+        // from upstream are kept when updating the colorimetry / chroma-site below
+        let output_info = output_state.info();
+        let mut info_builder = gst_video::VideoInfo::builder_from_info(&output_info);
         let mut update_output_state = false;
 
         let colorimetry = self.colorimetry_from_dav1d_picture(pic);
@@ -369,7 +377,8 @@ impl Dav1dDec {
         let timestamp = frame.dts().map(|ts| *ts as i64);
         let duration = frame.duration().map(|d| *d as i64);
 
-        let frame_number = Some(frame.system_frame_number() as i64);
+        let system_frame_number = frame.system_frame_number();
+        let frame_number = Some(system_frame_number as i64);
 
         let input_data = input_buffer
             .into_mapped_buffer_readable()
@@ -389,6 +398,15 @@ impl Dav1dDec {
             }
             Err(dav1d::Error::InvalidArgument) => {
                 gst::trace!(CAT, imp = self, "Decoder returned EINVAL");
+                if self.settings.lock().unwrap().error_resilience {
+                    gst::warning!(
+                        CAT,
+                        imp = self,
+                        "Bitstream error, skipping frame {system_frame_number}"
+                    );
+                    self.drop_corrupted_frame(state, frame)?;
+                    return Ok(std::ops::ControlFlow::Break(()));
+                }
                 gst_video::video_decoder_error!(
                     &*self.obj(),
                     1,
@@ -398,6 +416,15 @@ impl Dav1dDec {
                 Ok(std::ops::ControlFlow::Continue(()))
             }
             Err(err) => {
+                if self.settings.lock().unwrap().error_resilience {
+                    gst::warning!(
+                        CAT,
+                        imp = self,
+                        "Sending data failed (error code {err}), skipping frame {system_frame_number}"
+                    );
+                    self.drop_corrupted_frame(state, frame)?;
+                    return Ok(std::ops::ControlFlow::Break(()));
+                }
                 gst::error!(CAT, "Sending data failed (error code: {})", err);
                 self.obj().release_frame(frame);
                 gst_video::video_decoder_error!(
@@ -414,6 +441,7 @@ impl Dav1dDec {
     fn send_pending_data(
         &self,
         state_guard: &mut MutexGuard<Option<State>>,
+        system_frame_number: u32,
     ) -> Result<std::ops::ControlFlow<(), ()>, gst::FlowError> {
         gst::trace!(CAT, imp = self, "Sending pending data to decoder");
 
@@ -429,6 +457,17 @@ impl Dav1dDec {
                 Ok(std::ops::ControlFlow::Continue(()))
             }
             Err(err) => {
+                if self.settings.lock().unwrap().error_resilience {
+                    gst::warning!(
+                        CAT,
+                        imp = self,
+                        "Sending data failed (error code {err}), skipping frame {system_frame_number}"
+                    );
+                    if let Some(frame) = self.obj().frame(system_frame_number as i32) {
+                        self.drop_corrupted_frame(state, frame)?;
+                    }
+                    return Ok(std::ops::ControlFlow::Break(()));
+                }
                 gst::error!(CAT, "Sending data failed (error code: {})", err);
                 gst_video::video_decoder_error!(
                     &*self.obj(),
@@ -582,6 +621,14 @@ impl Dav1dDec {
                 Ok(None)
             }
             Err(err) => {
+                if self.settings.lock().unwrap().error_resilience {
+                    gst::warning!(
+                        CAT,
+                        imp = self,
+                        "Retrieving decoded picture failed (error code {err}), skipping picture"
+                    );
+                    return Ok(None);
+                }
                 gst::error!(
                     CAT,
                     imp = self,
@@ -600,6 +647,37 @@ impl Dav1dDec {
         }
     }
 
+    fn drop_corrupted_frame(
+        &self,
+        state: &mut State,
+        frame: gst_video::VideoCodecFrame,
+    ) -> Result<(), gst::FlowError> {
+        if let Some(pts) = frame.pts() {
+            state.pending_gaps.push((pts, frame.duration()));
+        }
+
+        // Posts a QoS message for the dropped frame
+        self.obj().drop_frame(frame)?;
+
+        Ok(())
+    }
+
+    fn push_pending_gaps(&self) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let pending_gaps = {
+            let mut state_guard = self.state.lock().unwrap();
+            let state = state_guard.as_mut().ok_or(gst::FlowError::Flushing)?;
+            std::mem::take(&mut state.pending_gaps)
+        };
+
+        // Let downstream know that there is no data for the dropped frames' duration
+        let srcpad = self.obj().src_pad();
+        for (pts, duration) in pending_gaps {
+            let _ = srcpad.push_event(gst::event::Gap::builder(pts).duration(duration).build());
+        }
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+
     fn forward_pending_pictures<'s>(
         &'s self,
         mut state_guard: MutexGuard<'s, Option<State>>,
@@ -703,7 +781,21 @@ impl ObjectImpl for Dav1dDec {
                     .blurb("Flags to enable in-loop post processing filters")
                     .default_value(DEFAULT_INLOOP_FILTERS)
                     .mutable_ready()
-                    .build()
+                    .build(),
+                /**
+                 * GstDav1dDec:error-resilience:
+                 *
+                 * Skip frames that fail to decode instead of erroring out. Skipped frames
+                 * are dropped with a QoS message and replaced by a gap event downstream.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::ParamSpecBoolean::builder("error-resilience")
+                    .nick("Error resilience")
+                    .blurb("Skip corrupted frames instead of failing on decoding errors")
+                    .default_value(DEFAULT_ERROR_RESILIENCE)
+                    .mutable_playing()
+                    .build(),
 
             ]
         });
@@ -727,6 +819,9 @@ impl ObjectImpl for Dav1dDec {
             "inloop-filters" => {
                 settings.inloop_filters = value.get().expect("type checked upstream");
             }
+            "error-resilience" => {
+                settings.error_resilience = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -739,6 +834,7 @@ impl ObjectImpl for Dav1dDec {
             "max-frame-delay" => settings.max_frame_delay.to_value(),
             "apply-grain" => settings.apply_grain.to_value(),
             "inloop-filters" => settings.inloop_filters.to_value(),
+            "error-resilience" => settings.error_resilience.to_value(),
             _ => unimplemented!(),
         }
     }
@@ -911,6 +1007,7 @@ impl VideoDecoderImpl for Dav1dDec {
             output_info: None,
             video_meta_supported: false,
             n_cpus,
+            pending_gaps: Vec::new(),
         });
 
         self.parent_set_format(input_state)
@@ -923,6 +1020,7 @@ impl VideoDecoderImpl for Dav1dDec {
         let input_buffer = frame
             .input_buffer_owned()
             .expect("frame without input buffer");
+        let system_frame_number = frame.system_frame_number();
 
         {
             let mut state_guard = self.state.lock().unwrap();
@@ -931,7 +1029,8 @@ impl VideoDecoderImpl for Dav1dDec {
             {
                 loop {
                     state_guard = self.forward_pending_pictures(state_guard, false)?;
-                    if self.send_pending_data(&mut state_guard)? == std::ops::ControlFlow::Break(())
+                    if self.send_pending_data(&mut state_guard, system_frame_number)?
+                        == std::ops::ControlFlow::Break(())
                     {
                         break;
                     }
@@ -940,7 +1039,7 @@ impl VideoDecoderImpl for Dav1dDec {
             let _state_guard = self.forward_pending_pictures(state_guard, false)?;
         }
 
-        Ok(gst::FlowSuccess::Ok)
+        self.push_pending_gaps()
     }
 
     fn flush(&self) -> bool {
@@ -0,0 +1,70 @@
+// Copyright (C) 2019 Philippe Normand <philn@igalia.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+//
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+use gst::prelude::*;
+use gst_video::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstdav1d::plugin_register_static().expect("dav1d test");
+    });
+}
+
+#[test]
+fn test_error_resilience() {
+    init();
+
+    let mut h = gst_check::Harness::new("dav1ddec");
+    h.element().unwrap().set_property("error-resilience", true);
+    h.play();
+    h.set_src_caps_str("video/x-av1,stream-format=obu-stream,alignment=tu");
+
+    // OBUs with the forbidden bit set fail to parse
+    for i in 0..3 {
+        let mut buffer = gst::Buffer::from_slice([0x80, 0x00, 0x00, 0x00]);
+        {
+            let buffer = buffer.get_mut().unwrap();
+            buffer.set_pts(gst::ClockTime::from_mseconds(i * 40));
+            buffer.set_dts(gst::ClockTime::from_mseconds(i * 40));
+            buffer.set_duration(gst::ClockTime::from_mseconds(40));
+        }
+        assert_eq!(h.push(buffer), Ok(gst::FlowSuccess::Ok));
+    }
+
+    // All corrupted frames were dropped instead of staying in the decoder's queue
+    let decoder = h
+        .element()
+        .unwrap()
+        .downcast::<gst_video::VideoDecoder>()
+        .unwrap();
+    assert!(decoder.frames().is_empty());
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    // and signalled downstream as gaps
+    let mut gaps = Vec::new();
+    while let Some(event) = h.try_pull_event() {
+        if let gst::EventView::Gap(gap) = event.view() {
+            gaps.push(gap.get());
+        }
+    }
+    assert_eq!(
+        gaps,
+        (0..3)
+            .map(|i| (
+                gst::ClockTime::from_mseconds(i * 40),
+                Some(gst::ClockTime::from_mseconds(40))
+            ))
+            .collect::<Vec<_>>()
+    );
+}
@@ -14,6 +14,8 @@ use gst::subclass::prelude::*;
 use gst_video::prelude::*;
 use gst_video::subclass::prelude::*;
 use gst_video::VideoFormat;
+
+use gst_plugin_video_resize::Resize;
 use std::sync::LazyLock;
 use std::{
     io,
@@ -23,6 +25,13 @@ use std::{
 
 const DEFAULT_REPEAT: i32 = 0;
 const DEFAULT_SPEED: i32 = 10;
+const DEFAULT_ROI_X: u32 = 0;
+const DEFAULT_ROI_Y: u32 = 0;
+const DEFAULT_ROI_WIDTH: u32 = 0;
+const DEFAULT_ROI_HEIGHT: u32 = 0;
+const DEFAULT_OUTPUT_WIDTH: u32 = 0;
+const DEFAULT_OUTPUT_HEIGHT: u32 = 0;
+const DEFAULT_DECIMATION: u32 = 1;
 
 /// The gif::Encoder requires a std::io::Write implementation, to which it
 /// can save the generated gif. This struct is used as a temporary cache, into
@@ -74,6 +83,13 @@ impl Write for CacheBufferWriter {
 struct Settings {
     repeat: i32,
     speed: i32,
+    roi_x: u32,
+    roi_y: u32,
+    roi_width: u32,
+    roi_height: u32,
+    output_width: u32,
+    output_height: u32,
+    decimation: u32,
 }
 
 impl Default for Settings {
@@ -81,12 +97,23 @@ impl Default for Settings {
         Settings {
             repeat: DEFAULT_REPEAT,
             speed: DEFAULT_SPEED,
+            roi_x: DEFAULT_ROI_X,
+            roi_y: DEFAULT_ROI_Y,
+            roi_width: DEFAULT_ROI_WIDTH,
+            roi_height: DEFAULT_ROI_HEIGHT,
+            output_width: DEFAULT_OUTPUT_WIDTH,
+            output_height: DEFAULT_OUTPUT_HEIGHT,
+            decimation: DEFAULT_DECIMATION,
         }
     }
 }
 
 struct State {
     video_info: gst_video::VideoInfo,
+    /// Cropping and downscaling applied to every frame before encoding
+    resize: Resize,
+    /// Number of frames received since the last format change, for decimation
+    frame_count: u64,
     cache: Arc<CacheBuffer>,
     gif_pts: Option<gst::ClockTime>,
     last_actual_pts: Option<gst::ClockTime>,
@@ -94,9 +121,11 @@ struct State {
 }
 
 impl State {
-    pub fn new(video_info: gst_video::VideoInfo) -> Self {
+    pub fn new(video_info: gst_video::VideoInfo, resize: Resize) -> Self {
         Self {
             video_info,
+            resize,
+            frame_count: 0,
             cache: Arc::new(CacheBuffer::new()),
             gif_pts: None,
             last_actual_pts: None,
@@ -111,8 +140,8 @@ impl State {
         // to our CacheBuffer instance
         let mut encoder = gif::Encoder::new(
             CacheBufferWriter::new(self.cache.clone()),
-            self.video_info.width() as u16,
-            self.video_info.height() as u16,
+            self.resize.out_width as u16,
+            self.resize.out_height as u16,
             &[],
         )
         .expect("Failed to initialize GIF encoder");
@@ -162,6 +191,51 @@ impl ObjectImpl for GifEnc {
                     .default_value(DEFAULT_SPEED)
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecUInt::builder("roi-x")
+                    .nick("ROI X")
+                    .blurb("Left edge of the region of the input to encode")
+                    .default_value(DEFAULT_ROI_X)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("roi-y")
+                    .nick("ROI Y")
+                    .blurb("Top edge of the region of the input to encode")
+                    .default_value(DEFAULT_ROI_Y)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("roi-width")
+                    .nick("ROI Width")
+                    .blurb("Width of the region of the input to encode (0 = up to the right edge)")
+                    .default_value(DEFAULT_ROI_WIDTH)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("roi-height")
+                    .nick("ROI Height")
+                    .blurb("Height of the region of the input to encode (0 = up to the bottom edge)")
+                    .default_value(DEFAULT_ROI_HEIGHT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("output-width")
+                    .nick("Output Width")
+                    .blurb("Width to downscale the region to (0 = keep aspect ratio or region width)")
+                    .maximum(u16::MAX as u32)
+                    .default_value(DEFAULT_OUTPUT_WIDTH)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("output-height")
+                    .nick("Output Height")
+                    .blurb("Height to downscale the region to (0 = keep aspect ratio or region height)")
+                    .maximum(u16::MAX as u32)
+                    .default_value(DEFAULT_OUTPUT_HEIGHT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("decimation")
+                    .nick("Decimation")
+                    .blurb("Only encode every n-th input frame")
+                    .minimum(1)
+                    .default_value(DEFAULT_DECIMATION)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -178,6 +252,34 @@ impl ObjectImpl for GifEnc {
                 let mut settings = self.settings.lock().unwrap();
                 settings.speed = value.get().expect("type checked upstream");
             }
+            "roi-x" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.roi_x = value.get().expect("type checked upstream");
+            }
+            "roi-y" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.roi_y = value.get().expect("type checked upstream");
+            }
+            "roi-width" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.roi_width = value.get().expect("type checked upstream");
+            }
+            "roi-height" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.roi_height = value.get().expect("type checked upstream");
+            }
+            "output-width" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_width = value.get().expect("type checked upstream");
+            }
+            "output-height" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.output_height = value.get().expect("type checked upstream");
+            }
+            "decimation" => {
+                let mut settings = self.settings.lock().unwrap();
+                settings.decimation = value.get().expect("type checked upstream");
+            }
             _ => unimplemented!(),
         }
     }
@@ -192,6 +294,34 @@ impl ObjectImpl for GifEnc {
                 let settings = self.settings.lock().unwrap();
                 settings.speed.to_value()
             }
+            "roi-x" => {
+                let settings = self.settings.lock().unwrap();
+                settings.roi_x.to_value()
+            }
+            "roi-y" => {
+                let settings = self.settings.lock().unwrap();
+                settings.roi_y.to_value()
+            }
+            "roi-width" => {
+                let settings = self.settings.lock().unwrap();
+                settings.roi_width.to_value()
+            }
+            "roi-height" => {
+                let settings = self.settings.lock().unwrap();
+                settings.roi_height.to_value()
+            }
+            "output-width" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_width.to_value()
+            }
+            "output-height" => {
+                let settings = self.settings.lock().unwrap();
+                settings.output_height.to_value()
+            }
+            "decimation" => {
+                let settings = self.settings.lock().unwrap();
+                settings.decimation.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -268,17 +398,39 @@ impl VideoEncoderImpl for GifEnc {
         let video_info = state.info();
         gst::debug!(CAT, imp = self, "Setting format {:?}", video_info);
 
+        let settings = *self.settings.lock().unwrap();
+        let resize = Resize::new(
+            video_info.width(),
+            video_info.height(),
+            [
+                settings.roi_x,
+                settings.roi_y,
+                settings.roi_width,
+                settings.roi_height,
+            ],
+            settings.output_width,
+            settings.output_height,
+        );
+        gst::debug!(CAT, imp = self, "Encoding {:?}", resize);
+
+        let output_info =
+            gst_video::VideoInfo::builder(video_info.format(), resize.out_width, resize.out_height)
+                .fps(video_info.fps())
+                .par(video_info.par())
+                .build()
+                .map_err(|_| gst::loggable_error!(CAT, "Failed to create output info"))?;
+
         {
-            let mut state = State::new(video_info);
-            let settings = self.settings.lock().unwrap();
-            state.reset(*settings);
+            let mut state = State::new(video_info, resize);
+            state.reset(settings);
             *self.state.borrow_mut() = Some(state);
         }
 
         let instance = self.obj();
-        let output_state = instance
+        let mut output_state = instance
             .set_output_state(gst::Caps::builder("image/gif").build(), Some(state))
             .map_err(|_| gst::loggable_error!(CAT, "Failed to set output state"))?;
+        output_state.set_info(output_info);
         instance
             .negotiate(output_state)
             .map_err(|_| gst::loggable_error!(CAT, "Failed to negotiate"))?;
@@ -297,6 +449,21 @@ impl VideoEncoderImpl for GifEnc {
         let mut state_guard = self.state.borrow_mut();
         let state = state_guard.as_mut().ok_or(gst::FlowError::NotNegotiated)?;
 
+        let decimation = self.settings.lock().unwrap().decimation as u64;
+        let frame_count = state.frame_count;
+        state.frame_count += 1;
+        if frame_count % decimation != 0 {
+            gst::trace!(
+                CAT,
+                imp = self,
+                "Dropping frame {} for decimation",
+                frame.system_frame_number()
+            );
+            drop(state_guard);
+            // Finishing a frame without output buffer drops it
+            return self.obj().finish_frame(frame);
+        }
+
         gst::debug!(
             CAT,
             imp = self,
@@ -318,8 +485,8 @@ impl VideoEncoderImpl for GifEnc {
                         gst::FlowError::Error
                     })?;
 
-            let frame_width = in_frame.info().width();
-            let frame_height = in_frame.info().height();
+            let frame_width = state.resize.out_width;
+            let frame_height = state.resize.out_height;
 
             // Calculate delay to new frame by calculating the difference between the current actual
             // presentation timestamp of the last frame within the gif, and the pts of the new frame.
@@ -351,7 +518,19 @@ impl VideoEncoderImpl for GifEnc {
 
             let settings = self.settings.lock().unwrap();
 
-            let mut raw_frame = tightly_packed_framebuffer(&in_frame);
+            let mut raw_frame = if state
+                .resize
+                .is_identity(in_frame.width(), in_frame.height())
+            {
+                tightly_packed_framebuffer(&in_frame)
+            } else {
+                state.resize.process(
+                    in_frame.plane_data(0).unwrap(),
+                    in_frame.plane_stride()[0] as usize,
+                    in_frame.n_components() as usize,
+                    1,
+                )
+            };
             let mut gif_frame = match in_frame.info().format() {
                 gst_video::VideoFormat::Rgb => gif::Frame::from_rgb_speed(
                     frame_width as u16,
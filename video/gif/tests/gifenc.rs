@@ -70,3 +70,55 @@ fn test_encode(video_info: &gst_video::VideoInfo) {
         assert!(!buffer.flags().contains(gst::BufferFlags::DELTA_UNIT))
     }
 }
+
+#[test]
+fn test_encode_roi_decimation() {
+    init();
+
+    let video_info = gst_video::VideoInfo::builder(gst_video::VideoFormat::Rgb, 160, 120)
+        .fps((30, 1))
+        .build()
+        .unwrap();
+
+    let mut h = gst_check::Harness::new("gifenc");
+    {
+        let enc = h.element().unwrap();
+        enc.set_property("roi-x", 40u32);
+        enc.set_property("roi-width", 80u32);
+        enc.set_property("output-width", 40u32);
+        enc.set_property("decimation", 2u32);
+    }
+    h.set_src_caps(video_info.to_caps().unwrap());
+
+    for pts in 0..5 {
+        let buffer = {
+            let mut buffer = gst::Buffer::with_size(video_info.size()).unwrap();
+            {
+                let buffer = buffer.get_mut().unwrap();
+                buffer.set_pts(pts.seconds());
+            }
+            let mut vframe =
+                gst_video::VideoFrame::from_buffer_writable(buffer, &video_info).unwrap();
+            for v in vframe.plane_data_mut(0).unwrap() {
+                *v = 128;
+            }
+            vframe.into_buffer()
+        };
+        h.push(buffer.clone()).unwrap();
+    }
+    h.push_event(gst::event::Eos::new());
+
+    // Frames 0, 2 and 4 plus the GIF trailer
+    let first = h.pull().unwrap();
+    for _ in 0..3 {
+        h.pull().unwrap();
+    }
+    assert_eq!(h.buffers_in_queue(), 0);
+
+    // The logical screen size follows the 6 byte GIF signature, the 80x120 region is
+    // downscaled to 40x60
+    let map = first.map_readable().unwrap();
+    assert_eq!(&map[..6], b"GIF89a");
+    assert_eq!(u16::from_le_bytes([map[6], map[7]]), 40);
+    assert_eq!(u16::from_le_bytes([map[8], map[9]]), 60);
+}
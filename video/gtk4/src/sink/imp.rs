@@ -218,6 +218,38 @@ impl ObjectImpl for PaintableSink {
             _ => unimplemented!(),
         }
     }
+
+    fn signals() -> &'static [glib::subclass::Signal] {
+        static SIGNALS: LazyLock<Vec<glib::subclass::Signal>> = LazyLock::new(|| {
+            vec![
+                /**
+                 * GstGtk4PaintableSink::take-snapshot:
+                 * @promise: (nullable): a #GstPromise to reply to with the snapshot
+                 *
+                 * Renders the currently displayed frame, including overlays, orientation and any
+                 * GL color conversion, and encodes it as PNG. This happens asynchronously on the
+                 * main thread.
+                 *
+                 * The promise is replied to with a structure that has either a `sample` field
+                 * containing an `image/png` #GstSample, or an `error` field with a #GError.
+                 *
+                 * Since: plugins-rs-0.14.0
+                 */
+                glib::subclass::Signal::builder("take-snapshot")
+                    .param_types([gst::Promise::static_type()])
+                    .action()
+                    .class_handler(|_token, args| {
+                        let element = args[0].get::<super::PaintableSink>().expect("signal arg");
+                        let promise = args[1].get::<Option<gst::Promise>>().expect("signal arg");
+                        element.imp().take_snapshot(promise);
+                        None
+                    })
+                    .build(),
+            ]
+        });
+
+        SIGNALS.as_ref()
+    }
 }
 
 impl GstObjectImpl for PaintableSink {}
@@ -728,6 +760,50 @@ impl PaintableSink {
         glib::ControlFlow::Continue
     }
 
+    fn take_snapshot(&self, promise: Option<gst::Promise>) {
+        let self_ = self.to_owned();
+        glib::MainContext::default().invoke(move || {
+            let res = {
+                let paintable = self_.paintable.lock().unwrap();
+                match &*paintable {
+                    Some(paintable) => paintable.get_ref().snapshot_png(),
+                    None => Err(glib::Error::new(
+                        gst::CoreError::Failed,
+                        "No paintable to take a snapshot of",
+                    )),
+                }
+            };
+
+            let reply = match res {
+                Ok((data, width, height)) => {
+                    let caps = gst::Caps::builder("image/png")
+                        .field("width", width)
+                        .field("height", height)
+                        .build();
+                    let sample = gst::Sample::builder()
+                        .buffer(&gst::Buffer::from_slice(data))
+                        .caps(&caps)
+                        .build();
+
+                    gst::Structure::builder("snapshot")
+                        .field("sample", sample)
+                        .build()
+                }
+                Err(err) => {
+                    gst::warning!(CAT, imp = self_, "Failed to take snapshot: {err}");
+
+                    gst::Structure::builder("snapshot")
+                        .field("error", err)
+                        .build()
+                }
+            };
+
+            if let Some(promise) = promise {
+                promise.reply(Some(reply));
+            }
+        });
+    }
+
     fn configure_caps(&self) {
         #[allow(unused_mut)]
         let mut tmp_caps = Self::pad_templates()[0].caps().clone();
@@ -33,6 +33,9 @@
  * or if the environment variable `GST_GTK4_WINDOW=1` is set. Setting `GST_GTK4_WINDOW_FULLSCREEN=1`
  * will make the window launch in fullscreen mode.
  *
+ * The currently displayed frame can be retrieved as PNG with the `take-snapshot` action signal,
+ * e.g. for implementing a "save frame" feature in applications.
+ *
  * {{ videos/gtk4/examples/gtksink.rs }}
  */
 use gtk::glib;
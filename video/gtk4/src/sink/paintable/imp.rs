@@ -573,6 +573,38 @@ impl Paintable {
         self.obj().invalidate_contents();
     }
 
+    /// Renders the current frame including all overlays at its intrinsic size and encodes it
+    /// as PNG. Returns the PNG data and its size.
+    pub(super) fn snapshot_png(&self) -> Result<(glib::Bytes, i32, i32), glib::Error> {
+        let width = self.intrinsic_width();
+        let height = self.intrinsic_height();
+        if self.paintables.borrow().is_empty() || width <= 0 || height <= 0 {
+            return Err(glib::Error::new(
+                gst::CoreError::Failed,
+                "No frame to take a snapshot of",
+            ));
+        }
+
+        let snapshot = gtk::Snapshot::new();
+        self.obj().snapshot(&snapshot, width as f64, height as f64);
+        let Some(node) = snapshot.to_node() else {
+            return Err(glib::Error::new(
+                gst::CoreError::Failed,
+                "Failed to render frame",
+            ));
+        };
+
+        // GL textures are downloaded by the Cairo renderer, which does not need a surface
+        let renderer = gsk::CairoRenderer::new();
+        renderer.realize(None)?;
+        let texture = renderer.render_texture(&node, None);
+        renderer.unrealize();
+
+        gst::debug!(CAT, imp = self, "Took snapshot of {width}x{height}");
+
+        Ok((texture.save_to_png_bytes(), width, height))
+    }
+
     pub(super) fn handle_flush_frames(&self) {
         gst::debug!(CAT, imp = self, "Flushing frames");
         self.paintables.borrow_mut().clear();
@@ -37,4 +37,8 @@ impl Paintable {
     pub(crate) fn handle_flush_frames(&self) {
         self.imp().handle_flush_frames();
     }
+
+    pub(crate) fn snapshot_png(&self) -> Result<(glib::Bytes, i32, i32), glib::Error> {
+        self.imp().snapshot_png()
+    }
 }
@@ -14,6 +14,7 @@ use gst::subclass::prelude::*;
 use gst_video::prelude::*;
 use gst_video::subclass::prelude::*;
 
+use gst_plugin_video_resize::Resize;
 use parking_lot::Mutex;
 use std::sync::LazyLock;
 
@@ -22,6 +23,12 @@ use super::FilterType;
 
 const DEFAULT_COMPRESSION_LEVEL: CompressionLevel = CompressionLevel::Default;
 const DEFAULT_FILTER_TYPE: FilterType = FilterType::NoFilter;
+const DEFAULT_ROI_X: u32 = 0;
+const DEFAULT_ROI_Y: u32 = 0;
+const DEFAULT_ROI_WIDTH: u32 = 0;
+const DEFAULT_ROI_HEIGHT: u32 = 0;
+const DEFAULT_OUTPUT_WIDTH: u32 = 0;
+const DEFAULT_OUTPUT_HEIGHT: u32 = 0;
 
 static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
     gst::DebugCategory::new(
@@ -35,6 +42,12 @@ static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
 struct Settings {
     compression: CompressionLevel,
     filter: FilterType,
+    roi_x: u32,
+    roi_y: u32,
+    roi_width: u32,
+    roi_height: u32,
+    output_width: u32,
+    output_height: u32,
 }
 
 impl Default for Settings {
@@ -42,12 +55,20 @@ impl Default for Settings {
         Settings {
             compression: DEFAULT_COMPRESSION_LEVEL,
             filter: DEFAULT_FILTER_TYPE,
+            roi_x: DEFAULT_ROI_X,
+            roi_y: DEFAULT_ROI_Y,
+            roi_width: DEFAULT_ROI_WIDTH,
+            roi_height: DEFAULT_ROI_HEIGHT,
+            output_width: DEFAULT_OUTPUT_WIDTH,
+            output_height: DEFAULT_OUTPUT_HEIGHT,
         }
     }
 }
 
 struct State {
     video_info: gst_video::VideoInfo,
+    /// Cropping and downscaling applied to every frame before encoding
+    resize: Resize,
 }
 
 #[derive(Default)]
@@ -80,6 +101,42 @@ impl ObjectImpl for PngEncoder {
                     .blurb("Selects the filter type to applied")
                     .mutable_ready()
                     .build(),
+                glib::ParamSpecUInt::builder("roi-x")
+                    .nick("ROI X")
+                    .blurb("Left edge of the region of the input to encode")
+                    .default_value(DEFAULT_ROI_X)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("roi-y")
+                    .nick("ROI Y")
+                    .blurb("Top edge of the region of the input to encode")
+                    .default_value(DEFAULT_ROI_Y)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("roi-width")
+                    .nick("ROI Width")
+                    .blurb("Width of the region of the input to encode (0 = up to the right edge)")
+                    .default_value(DEFAULT_ROI_WIDTH)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("roi-height")
+                    .nick("ROI Height")
+                    .blurb("Height of the region of the input to encode (0 = up to the bottom edge)")
+                    .default_value(DEFAULT_ROI_HEIGHT)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("output-width")
+                    .nick("Output Width")
+                    .blurb("Width to downscale the region to (0 = keep aspect ratio or region width)")
+                    .default_value(DEFAULT_OUTPUT_WIDTH)
+                    .mutable_ready()
+                    .build(),
+                glib::ParamSpecUInt::builder("output-height")
+                    .nick("Output Height")
+                    .blurb("Height to downscale the region to (0 = keep aspect ratio or region height)")
+                    .default_value(DEFAULT_OUTPUT_HEIGHT)
+                    .mutable_ready()
+                    .build(),
             ]
         });
 
@@ -98,6 +155,30 @@ impl ObjectImpl for PngEncoder {
                 let mut settings = self.settings.lock();
                 settings.filter = value.get::<FilterType>().expect("type checked upstream");
             }
+            "roi-x" => {
+                let mut settings = self.settings.lock();
+                settings.roi_x = value.get().expect("type checked upstream");
+            }
+            "roi-y" => {
+                let mut settings = self.settings.lock();
+                settings.roi_y = value.get().expect("type checked upstream");
+            }
+            "roi-width" => {
+                let mut settings = self.settings.lock();
+                settings.roi_width = value.get().expect("type checked upstream");
+            }
+            "roi-height" => {
+                let mut settings = self.settings.lock();
+                settings.roi_height = value.get().expect("type checked upstream");
+            }
+            "output-width" => {
+                let mut settings = self.settings.lock();
+                settings.output_width = value.get().expect("type checked upstream");
+            }
+            "output-height" => {
+                let mut settings = self.settings.lock();
+                settings.output_height = value.get().expect("type checked upstream");
+            }
             _ => unreachable!(),
         }
     }
@@ -112,6 +193,30 @@ impl ObjectImpl for PngEncoder {
                 let settings = self.settings.lock();
                 settings.filter.to_value()
             }
+            "roi-x" => {
+                let settings = self.settings.lock();
+                settings.roi_x.to_value()
+            }
+            "roi-y" => {
+                let settings = self.settings.lock();
+                settings.roi_y.to_value()
+            }
+            "roi-width" => {
+                let settings = self.settings.lock();
+                settings.roi_width.to_value()
+            }
+            "roi-height" => {
+                let settings = self.settings.lock();
+                settings.roi_height.to_value()
+            }
+            "output-width" => {
+                let settings = self.settings.lock();
+                settings.output_width.to_value()
+            }
+            "output-height" => {
+                let settings = self.settings.lock();
+                settings.output_height.to_value()
+            }
             _ => unimplemented!(),
         }
     }
@@ -180,12 +285,35 @@ impl VideoEncoderImpl for PngEncoder {
         let video_info = state.info();
         gst::debug!(CAT, imp = self, "Setting format {:?}", video_info);
 
-        *self.state.lock() = Some(State { video_info });
+        let settings = *self.settings.lock();
+        let resize = Resize::new(
+            video_info.width(),
+            video_info.height(),
+            [
+                settings.roi_x,
+                settings.roi_y,
+                settings.roi_width,
+                settings.roi_height,
+            ],
+            settings.output_width,
+            settings.output_height,
+        );
+        gst::debug!(CAT, imp = self, "Encoding {:?}", resize);
+
+        let output_info =
+            gst_video::VideoInfo::builder(video_info.format(), resize.out_width, resize.out_height)
+                .fps(video_info.fps())
+                .par(video_info.par())
+                .build()
+                .map_err(|_| gst::loggable_error!(CAT, "Failed to create output info"))?;
+
+        *self.state.lock() = Some(State { video_info, resize });
 
         let instance = self.obj();
-        let output_state = instance
+        let mut output_state = instance
             .set_output_state(gst::Caps::builder("image/png").build(), Some(state))
             .map_err(|_| gst::loggable_error!(CAT, "Failed to set output state"))?;
+        output_state.set_info(output_info);
         instance
             .negotiate(output_state)
             .map_err(|_| gst::loggable_error!(CAT, "Failed to negotiate"))
@@ -207,11 +335,8 @@ impl VideoEncoderImpl for PngEncoder {
         );
 
         let mut buffer = Vec::with_capacity(4096);
-        let mut encoder = png::Encoder::new(
-            &mut buffer,
-            state.video_info.width(),
-            state.video_info.height(),
-        );
+        let mut encoder =
+            png::Encoder::new(&mut buffer, state.resize.out_width, state.resize.out_height);
         let color = match state.video_info.format() {
             gst_video::VideoFormat::Gray8 | gst_video::VideoFormat::Gray16Be => {
                 png::ColorType::Grayscale
@@ -239,8 +364,40 @@ impl VideoEncoderImpl for PngEncoder {
 
         {
             let input_buffer = frame.input_buffer().expect("frame without input buffer");
-            let input_map = input_buffer.map_readable().unwrap();
-            writer.write_image_data(&input_map).map_err(|e| {
+            let res = if state
+                .resize
+                .is_identity(state.video_info.width(), state.video_info.height())
+            {
+                let input_map = input_buffer.map_readable().unwrap();
+                writer.write_image_data(&input_map)
+            } else {
+                let in_frame = gst_video::VideoFrameRef::from_buffer_ref_readable(
+                    input_buffer,
+                    &state.video_info,
+                )
+                .map_err(|_| {
+                    gst::element_imp_error!(
+                        self,
+                        gst::CoreError::Failed,
+                        ["Failed to map input buffer readable"]
+                    );
+                    gst::FlowError::Error
+                })?;
+                let component_size =
+                    if state.video_info.format() == gst_video::VideoFormat::Gray16Be {
+                        2
+                    } else {
+                        1
+                    };
+                let data = state.resize.process(
+                    in_frame.plane_data(0).unwrap(),
+                    in_frame.plane_stride()[0] as usize,
+                    in_frame.n_components() as usize,
+                    component_size,
+                );
+                writer.write_image_data(&data)
+            };
+            res.map_err(|e| {
                 gst::error!(CAT, imp = self, "Failed to write image data: {e}");
                 gst::element_imp_error!(self, gst::CoreError::Failed, ["{e}"]);
                 gst::FlowError::Error
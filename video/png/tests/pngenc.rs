@@ -92,3 +92,51 @@ fn test_png_encode(video_info: &gst_video::VideoInfo) {
         assert!(!buffer.flags().contains(gst::BufferFlags::DELTA_UNIT))
     });
 }
+
+#[test]
+fn test_png_encode_roi() {
+    init();
+
+    let video_info = gst_video::VideoInfo::builder(gst_video::VideoFormat::Gray8, 160, 120)
+        .fps((30, 1))
+        .build()
+        .unwrap();
+
+    let mut h = gst_check::Harness::new("rspngenc");
+    {
+        let enc = h.element().unwrap();
+        enc.set_property("roi-x", 80u32);
+        enc.set_property("roi-y", 20u32);
+        enc.set_property("roi-height", 80u32);
+        enc.set_property("output-height", 40u32);
+    }
+    h.set_src_caps(video_info.to_caps().unwrap());
+    h.play();
+
+    // Left half black, right half white
+    let buffer = {
+        let mut buffer = gst::Buffer::with_size(video_info.size()).unwrap();
+        buffer.get_mut().unwrap().set_pts(gst::ClockTime::ZERO);
+        let mut vframe = gst_video::VideoFrame::from_buffer_writable(buffer, &video_info).unwrap();
+        let stride = vframe.plane_stride()[0] as usize;
+        for line in vframe.plane_data_mut(0).unwrap().chunks_exact_mut(stride) {
+            line[..80].fill(0);
+            line[80..160].fill(255);
+        }
+        vframe.into_buffer()
+    };
+    h.push(buffer).unwrap();
+    h.push_event(gst::event::Eos::new());
+
+    let buffer = h.pull().unwrap();
+    let map = buffer.map_readable().unwrap();
+
+    let decoder = png::Decoder::new(map.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+
+    // The 80x80 region of the right half is downscaled to 40x40
+    assert_eq!((info.width, info.height), (40, 40));
+    assert!(data[..info.buffer_size()].iter().all(|&v| v == 255));
+}
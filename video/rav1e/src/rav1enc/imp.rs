@@ -801,6 +801,40 @@ impl VideoEncoderImpl for Rav1Enc {
         let container_sequence_header =
             gst::Buffer::from_mut_slice(context.container_sequence_header());
 
+        let depth = video_info.format_info().depth()[0];
+        let (chroma_format, profile) = match video_info.format() {
+            gst_video::VideoFormat::Gray8 => ("4:0:0", "main"),
+            gst_video::VideoFormat::Y42b
+            | gst_video::VideoFormat::I42210le
+            | gst_video::VideoFormat::I42212le => ("4:2:2", "professional"),
+            gst_video::VideoFormat::Y444
+            | gst_video::VideoFormat::Y44410le
+            | gst_video::VideoFormat::Y44412le => {
+                ("4:4:4", if depth > 10 { "professional" } else { "high" })
+            }
+            _ => ("4:2:0", if depth > 10 { "professional" } else { "main" }),
+        };
+
+        let mut caps = gst::Caps::builder("video/x-av1")
+            .field("stream-format", "obu-stream")
+            .field("alignment", "tu")
+            .field("codec_data", container_sequence_header)
+            .field("profile", profile)
+            .field("chroma-format", chroma_format)
+            .field("bit-depth-luma", depth)
+            .field("bit-depth-chroma", depth)
+            .field("pixel-aspect-ratio", video_info.par());
+
+        // Pass through colorimetry and chroma siting as signalled in the sequence header so
+        // muxers can write them without parsing the bitstream
+        if let Some(s) = state.caps().and_then(|caps| caps.structure(0)) {
+            for field in ["colorimetry", "chroma-site"] {
+                if let Ok(value) = s.get::<&str>(field) {
+                    caps = caps.field(field, value);
+                }
+            }
+        }
+
         *self.state.borrow_mut() = Some(State {
             context,
             video_info,
@@ -808,14 +842,7 @@ impl VideoEncoderImpl for Rav1Enc {
 
         let instance = self.obj();
         let output_state = instance
-            .set_output_state(
-                gst::Caps::builder("video/x-av1")
-                    .field("stream-format", "obu-stream")
-                    .field("alignment", "tu")
-                    .field("codec_data", container_sequence_header)
-                    .build(),
-                Some(state),
-            )
+            .set_output_state(caps.build(), Some(state))
             .map_err(|_| gst::loggable_error!(CAT, "Failed to set output state"))?;
         instance
             .negotiate(output_state)
@@ -119,7 +119,43 @@ fn test_encode_i444_12() {
     test_encode(&video_info);
 }
 
-fn test_encode(video_info: &gst_video::VideoInfo) {
+#[test]
+fn test_encode_caps_metadata() {
+    init();
+
+    let video_info = gst_video::VideoInfo::builder(gst_video::VideoFormat::I42010le, 160, 120)
+        .fps((30, 1))
+        .par((4, 3))
+        .colorimetry(&gst_video::VideoColorimetry::new(
+            gst_video::VideoColorRange::Range0_255,
+            gst_video::VideoColorMatrix::Bt2020,
+            gst_video::VideoTransferFunction::Bt202010,
+            gst_video::VideoColorPrimaries::Bt2020,
+        ))
+        .build()
+        .unwrap();
+    let caps = test_encode(&video_info);
+
+    let s = caps.structure(0).unwrap();
+    assert_eq!(s.name(), "video/x-av1");
+    assert!(s.has_field("codec_data"));
+    assert_eq!(s.get::<&str>("profile").unwrap(), "main");
+    assert_eq!(s.get::<&str>("chroma-format").unwrap(), "4:2:0");
+    assert_eq!(s.get::<u32>("bit-depth-luma").unwrap(), 10);
+    assert_eq!(
+        s.get::<gst::Fraction>("pixel-aspect-ratio").unwrap(),
+        gst::Fraction::new(4, 3)
+    );
+    assert_eq!(
+        s.get::<&str>("colorimetry")
+            .unwrap()
+            .parse::<gst_video::VideoColorimetry>()
+            .unwrap(),
+        video_info.colorimetry()
+    );
+}
+
+fn test_encode(video_info: &gst_video::VideoInfo) -> gst::Caps {
     let mut h = gst_check::Harness::new("rav1enc");
     {
         let rav1enc = h.element().unwrap();
@@ -185,4 +221,6 @@ fn test_encode(video_info: &gst_video::VideoInfo) {
             assert!(!buffer.flags().contains(gst::BufferFlags::DELTA_UNIT))
         }
     }
+
+    h.sinkpad().unwrap().current_caps().unwrap()
 }
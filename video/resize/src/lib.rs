@@ -0,0 +1,126 @@
+// SPDX-License-Identifier: MIT OR Apache-2.0
+
+//! Cropping and downscaling of raw video frames, shared by the gifenc and pngenc elements.
+
+/// Rectangle of the input frame that is encoded, and the size it is downscaled to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resize {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    pub out_width: u32,
+    pub out_height: u32,
+}
+
+impl Resize {
+    /// Clamps the region of interest to the input frame and calculates the output size.
+    ///
+    /// A zero ROI width or height extends the region to the right or bottom edge of the frame.
+    /// If only one of the output dimensions is set the other one keeps the aspect ratio of the
+    /// region. The region is never upscaled.
+    pub fn new(
+        in_width: u32,
+        in_height: u32,
+        roi: [u32; 4],
+        out_width: u32,
+        out_height: u32,
+    ) -> Self {
+        let [x, y, width, height] = roi;
+
+        let x = x.min(in_width - 1);
+        let y = y.min(in_height - 1);
+        let width = if width == 0 {
+            in_width - x
+        } else {
+            width.min(in_width - x)
+        };
+        let height = if height == 0 {
+            in_height - y
+        } else {
+            height.min(in_height - y)
+        };
+
+        let (out_width, out_height) = match (out_width, out_height) {
+            (0, 0) => (width, height),
+            (w, 0) => (w, (height as u64 * w as u64 / width as u64) as u32),
+            (0, h) => ((width as u64 * h as u64 / height as u64) as u32, h),
+            (w, h) => (w, h),
+        };
+
+        Resize {
+            x,
+            y,
+            width,
+            height,
+            out_width: out_width.clamp(1, width),
+            out_height: out_height.clamp(1, height),
+        }
+    }
+
+    pub fn is_identity(&self, in_width: u32, in_height: u32) -> bool {
+        self.x == 0
+            && self.y == 0
+            && self.width == in_width
+            && self.height == in_height
+            && self.out_width == in_width
+            && self.out_height == in_height
+    }
+
+    /// Crops and downscales a frame of a packed format into a tightly packed buffer.
+    ///
+    /// Every output pixel is the average of the input pixels it covers. Components are either
+    /// 8 bits or 16 bits big endian wide.
+    pub fn process(
+        &self,
+        data: &[u8],
+        stride: usize,
+        n_components: usize,
+        component_size: usize,
+    ) -> Vec<u8> {
+        let pixel_size = n_components * component_size;
+        let out_width = self.out_width as usize;
+        let out_height = self.out_height as usize;
+        let width = self.width as usize;
+        let height = self.height as usize;
+
+        let mut out = Vec::with_capacity(out_width * out_height * pixel_size);
+        let mut sums = vec![0u64; n_components];
+
+        for oy in 0..out_height {
+            let y0 = oy * height / out_height;
+            let y1 = ((oy + 1) * height / out_height).max(y0 + 1);
+
+            for ox in 0..out_width {
+                let x0 = ox * width / out_width;
+                let x1 = ((ox + 1) * width / out_width).max(x0 + 1);
+
+                sums.fill(0);
+                for y in y0..y1 {
+                    let line = &data[(self.y as usize + y) * stride..];
+                    let pixels =
+                        &line[(self.x as usize + x0) * pixel_size..][..(x1 - x0) * pixel_size];
+                    for pixel in pixels.chunks_exact(pixel_size) {
+                        for (sum, c) in sums.iter_mut().zip(pixel.chunks_exact(component_size)) {
+                            *sum += match component_size {
+                                1 => c[0] as u64,
+                                _ => u16::from_be_bytes([c[0], c[1]]) as u64,
+                            };
+                        }
+                    }
+                }
+
+                let n = ((y1 - y0) * (x1 - x0)) as u64;
+                for sum in &sums {
+                    let v = (sum + n / 2) / n;
+                    match component_size {
+                        1 => out.push(v as u8),
+                        _ => out.extend_from_slice(&(v as u16).to_be_bytes()),
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,284 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::sync::mpsc;
+
+use super::remap::RemapTable;
+
+const WORKGROUP_SIZE: u32 = 16;
+
+/// Bilinear sampling of the input at the coordinates of the remap table, with the pixels
+/// packed into one `u32` each. The channel order does not matter.
+const SHADER: &str = r#"
+struct Params {
+    in_width: u32,
+    in_height: u32,
+    in_stride: u32,
+    out_width: u32,
+    out_height: u32,
+    out_stride: u32,
+    padding0: u32,
+    padding1: u32,
+}
+
+@group(0) @binding(0) var<uniform> params: Params;
+@group(0) @binding(1) var<storage, read> coords: array<vec2<f32>>;
+@group(0) @binding(2) var<storage, read> src: array<u32>;
+@group(0) @binding(3) var<storage, read_write> dst: array<u32>;
+
+fn fetch(x: u32, y: u32) -> vec4<f32> {
+    let cx = min(x, params.in_width - 1u);
+    let cy = min(y, params.in_height - 1u);
+    return unpack4x8unorm(src[cy * params.in_stride + cx]);
+}
+
+@compute @workgroup_size(16, 16)
+fn main(@builtin(global_invocation_id) id: vec3<u32>) {
+    if (id.x >= params.out_width || id.y >= params.out_height) {
+        return;
+    }
+
+    let pos = coords[id.y * params.out_width + id.x];
+    var color = vec4<f32>(0.0);
+    if (pos.x >= 0.0) {
+        let x0 = u32(pos.x);
+        let y0 = u32(pos.y);
+        let f = pos - floor(pos);
+        let top = mix(fetch(x0, y0), fetch(x0 + 1u, y0), f.x);
+        let bottom = mix(fetch(x0, y0 + 1u), fetch(x0 + 1u, y0 + 1u), f.x);
+        color = mix(top, bottom, f.y);
+    }
+
+    dst[id.y * params.out_stride + id.x] = pack4x8unorm(color);
+}
+"#;
+
+struct Buffers {
+    in_size: u64,
+    out_size: u64,
+    src: wgpu::Buffer,
+    dst: wgpu::Buffer,
+    readback: wgpu::Buffer,
+}
+
+pub struct GpuDewarp {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    pipeline: wgpu::ComputePipeline,
+    params: wgpu::Buffer,
+    coords: Option<wgpu::Buffer>,
+    buffers: Option<Buffers>,
+}
+
+fn to_bytes<const N: usize, T: Copy>(values: &[T], f: impl Fn(T) -> [u8; N]) -> Vec<u8> {
+    values.iter().flat_map(|&v| f(v)).collect()
+}
+
+impl GpuDewarp {
+    pub fn new() -> Result<Self, String> {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor::default());
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+        .ok_or("No GPU adapter available")?;
+
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("dewarp"),
+                required_features: wgpu::Features::empty(),
+                required_limits:
+                    wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+                memory_hints: wgpu::MemoryHints::Performance,
+            },
+            None,
+        ))
+        .map_err(|err| format!("Failed to create GPU device: {err}"))?;
+
+        let module = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("dewarp"),
+            source: wgpu::ShaderSource::Wgsl(SHADER.into()),
+        });
+        let pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("dewarp"),
+            layout: None,
+            module: &module,
+            entry_point: Some("main"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let params = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("dewarp params"),
+            size: 8 * 4,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Ok(GpuDewarp {
+            device,
+            queue,
+            pipeline,
+            params,
+            coords: None,
+            buffers: None,
+        })
+    }
+
+    pub fn set_table(&mut self, table: &RemapTable) {
+        let coords = to_bytes(&table.coords, |[x, y]| {
+            let mut bytes = [0u8; 8];
+            bytes[..4].copy_from_slice(&x.to_ne_bytes());
+            bytes[4..].copy_from_slice(&y.to_ne_bytes());
+            bytes
+        });
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("dewarp coords"),
+            size: coords.len() as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        self.queue.write_buffer(&buffer, 0, &coords);
+        self.coords = Some(buffer);
+    }
+
+    fn ensure_buffers(&mut self, in_size: u64, out_size: u64) {
+        if self
+            .buffers
+            .as_ref()
+            .map_or(true, |b| b.in_size != in_size || b.out_size != out_size)
+        {
+            let create = |label, size, usage| {
+                self.device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some(label),
+                    size,
+                    usage,
+                    mapped_at_creation: false,
+                })
+            };
+
+            self.buffers = Some(Buffers {
+                in_size,
+                out_size,
+                src: create(
+                    "dewarp input",
+                    in_size,
+                    wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+                ),
+                dst: create(
+                    "dewarp output",
+                    out_size,
+                    wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_SRC,
+                ),
+                readback: create(
+                    "dewarp readback",
+                    out_size,
+                    wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                ),
+            });
+        }
+    }
+
+    /// Processes a frame with 4 bytes per pixel, `set_table()` must have been called before
+    #[allow(clippy::too_many_arguments)]
+    pub fn process(
+        &mut self,
+        in_data: &[u8],
+        in_stride: usize,
+        in_width: usize,
+        in_height: usize,
+        out_data: &mut [u8],
+        out_stride: usize,
+        out_width: usize,
+        out_height: usize,
+    ) -> Result<(), String> {
+        let in_size = (in_stride * in_height) as u64;
+        let out_size = (out_stride * out_height) as u64;
+
+        let params = to_bytes(
+            &[
+                in_width as u32,
+                in_height as u32,
+                (in_stride / 4) as u32,
+                out_width as u32,
+                out_height as u32,
+                (out_stride / 4) as u32,
+                0,
+                0,
+            ],
+            u32::to_ne_bytes,
+        );
+        self.queue.write_buffer(&self.params, 0, &params);
+
+        self.ensure_buffers(in_size, out_size);
+        let buffers = self.buffers.as_ref().unwrap();
+        let coords = self.coords.as_ref().ok_or("No remap table")?;
+
+        // Uploads are queued before the submission below
+        self.queue
+            .write_buffer(&buffers.src, 0, &in_data[..in_size as usize]);
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("dewarp"),
+            layout: &self.pipeline.get_bind_group_layout(0),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.params.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: coords.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: buffers.src.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: buffers.dst.as_entire_binding(),
+                },
+            ],
+        });
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("dewarp"),
+            });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("dewarp"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_bind_group(0, &bind_group, &[]);
+            pass.dispatch_workgroups(
+                (out_width as u32).div_ceil(WORKGROUP_SIZE),
+                (out_height as u32).div_ceil(WORKGROUP_SIZE),
+                1,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&buffers.dst, 0, &buffers.readback, 0, out_size);
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffers.readback.slice(..);
+        let (sender, receiver) = mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |res| {
+            let _ = sender.send(res);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .map_err(|_| "GPU device lost".to_string())?
+            .map_err(|err| format!("Failed to read back output: {err}"))?;
+
+        {
+            let data = slice.get_mapped_range();
+            out_data[..out_size as usize].copy_from_slice(&data);
+        }
+        buffers.readback.unmap();
+
+        Ok(())
+    }
+}
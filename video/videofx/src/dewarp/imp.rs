@@ -0,0 +1,387 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use atomic_refcell::AtomicRefCell;
+use gst::{glib, prelude::*, subclass::prelude::*};
+use gst_base::prelude::*;
+use gst_video::{prelude::*, subclass::prelude::*};
+use std::sync::LazyLock;
+use std::sync::Mutex;
+
+use super::remap::{self, RemapTable, View};
+use super::{DewarpBackend, DewarpProjection};
+
+const DEFAULT_PROJECTION: DewarpProjection = DewarpProjection::Fisheye;
+const DEFAULT_FISHEYE_FOV: f64 = 180.0;
+const DEFAULT_PAN: f64 = 0.0;
+const DEFAULT_TILT: f64 = 0.0;
+const DEFAULT_ZOOM: f64 = 1.0;
+const DEFAULT_BACKEND: DewarpBackend = DewarpBackend::Auto;
+
+static CAT: LazyLock<gst::DebugCategory> = LazyLock::new(|| {
+    gst::DebugCategory::new(
+        "dewarp",
+        gst::DebugColorFlags::empty(),
+        Some("Fisheye and 360° video dewarping"),
+    )
+});
+
+#[derive(Debug, Clone, Copy)]
+struct Settings {
+    view: View,
+    backend: DewarpBackend,
+    /// Whether the view changed since the remap table was last built
+    changed: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            view: View {
+                projection: DEFAULT_PROJECTION,
+                fisheye_fov: DEFAULT_FISHEYE_FOV,
+                pan: DEFAULT_PAN,
+                tilt: DEFAULT_TILT,
+                zoom: DEFAULT_ZOOM,
+            },
+            backend: DEFAULT_BACKEND,
+            changed: true,
+        }
+    }
+}
+
+struct State {
+    in_info: gst_video::VideoInfo,
+    table: Option<RemapTable>,
+    #[cfg(feature = "wgpu")]
+    gpu: Option<super::gpu::GpuDewarp>,
+}
+
+#[derive(Default)]
+pub struct Dewarp {
+    settings: Mutex<Settings>,
+    state: AtomicRefCell<Option<State>>,
+}
+
+#[glib::object_subclass]
+impl ObjectSubclass for Dewarp {
+    const NAME: &'static str = "GstDewarp";
+    type Type = super::Dewarp;
+    type ParentType = gst_video::VideoFilter;
+}
+
+impl ObjectImpl for Dewarp {
+    fn properties() -> &'static [glib::ParamSpec] {
+        static PROPERTIES: LazyLock<Vec<glib::ParamSpec>> = LazyLock::new(|| {
+            vec![
+                glib::ParamSpecEnum::builder_with_default("projection", DEFAULT_PROJECTION)
+                    .nick("Projection")
+                    .blurb("Projection of the input video")
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("fisheye-fov")
+                    .nick("Fisheye FOV")
+                    .blurb("Field of view of the fisheye lens in degrees")
+                    .minimum(1.0)
+                    .maximum(360.0)
+                    .default_value(DEFAULT_FISHEYE_FOV)
+                    .controllable()
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("pan")
+                    .nick("Pan")
+                    .blurb("Horizontal view direction in degrees, positive values look right")
+                    .minimum(-180.0)
+                    .maximum(180.0)
+                    .default_value(DEFAULT_PAN)
+                    .controllable()
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("tilt")
+                    .nick("Tilt")
+                    .blurb("Vertical view direction in degrees, positive values look up")
+                    .minimum(-90.0)
+                    .maximum(90.0)
+                    .default_value(DEFAULT_TILT)
+                    .controllable()
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecDouble::builder("zoom")
+                    .nick("Zoom")
+                    .blurb("Zoom factor, 1.0 corresponds to a horizontal field of view of 90°")
+                    .minimum(0.1)
+                    .maximum(10.0)
+                    .default_value(DEFAULT_ZOOM)
+                    .controllable()
+                    .mutable_playing()
+                    .build(),
+                glib::ParamSpecEnum::builder_with_default("backend", DEFAULT_BACKEND)
+                    .nick("Backend")
+                    .blurb("Whether to process the frames on the CPU or on the GPU")
+                    .mutable_ready()
+                    .build(),
+            ]
+        });
+
+        PROPERTIES.as_ref()
+    }
+
+    fn set_property(&self, _id: usize, value: &glib::Value, pspec: &glib::ParamSpec) {
+        let mut settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "projection" => {
+                settings.view.projection = value.get().expect("type checked upstream");
+            }
+            "fisheye-fov" => {
+                settings.view.fisheye_fov = value.get().expect("type checked upstream");
+            }
+            "pan" => {
+                settings.view.pan = value.get().expect("type checked upstream");
+            }
+            "tilt" => {
+                settings.view.tilt = value.get().expect("type checked upstream");
+            }
+            "zoom" => {
+                settings.view.zoom = value.get().expect("type checked upstream");
+            }
+            "backend" => {
+                settings.backend = value.get().expect("type checked upstream");
+                return;
+            }
+            _ => unimplemented!(),
+        }
+
+        gst::debug!(CAT, imp = self, "Changing {} to {:?}", pspec.name(), value);
+        settings.changed = true;
+    }
+
+    fn property(&self, _id: usize, pspec: &glib::ParamSpec) -> glib::Value {
+        let settings = self.settings.lock().unwrap();
+        match pspec.name() {
+            "projection" => settings.view.projection.to_value(),
+            "fisheye-fov" => settings.view.fisheye_fov.to_value(),
+            "pan" => settings.view.pan.to_value(),
+            "tilt" => settings.view.tilt.to_value(),
+            "zoom" => settings.view.zoom.to_value(),
+            "backend" => settings.backend.to_value(),
+            _ => unimplemented!(),
+        }
+    }
+}
+
+impl GstObjectImpl for Dewarp {}
+
+impl ElementImpl for Dewarp {
+    fn metadata() -> Option<&'static gst::subclass::ElementMetadata> {
+        static ELEMENT_METADATA: LazyLock<gst::subclass::ElementMetadata> = LazyLock::new(|| {
+            gst::subclass::ElementMetadata::new(
+                "Dewarp",
+                "Filter/Effect/Converter/Video",
+                "Dewarps fisheye and equirectangular video into a rectilinear view",
+                "GStreamer Rust Plugins Developers",
+            )
+        });
+
+        Some(&*ELEMENT_METADATA)
+    }
+
+    fn pad_templates() -> &'static [gst::PadTemplate] {
+        static PAD_TEMPLATES: LazyLock<Vec<gst::PadTemplate>> = LazyLock::new(|| {
+            let caps = gst_video::VideoCapsBuilder::new()
+                .format_list([
+                    gst_video::VideoFormat::Rgbx,
+                    gst_video::VideoFormat::Xrgb,
+                    gst_video::VideoFormat::Bgrx,
+                    gst_video::VideoFormat::Xbgr,
+                    gst_video::VideoFormat::Rgba,
+                    gst_video::VideoFormat::Argb,
+                    gst_video::VideoFormat::Bgra,
+                    gst_video::VideoFormat::Abgr,
+                ])
+                .build();
+
+            let sink_pad_template = gst::PadTemplate::new(
+                "sink",
+                gst::PadDirection::Sink,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            let src_pad_template = gst::PadTemplate::new(
+                "src",
+                gst::PadDirection::Src,
+                gst::PadPresence::Always,
+                &caps,
+            )
+            .unwrap();
+
+            vec![src_pad_template, sink_pad_template]
+        });
+
+        PAD_TEMPLATES.as_ref()
+    }
+}
+
+impl BaseTransformImpl for Dewarp {
+    const MODE: gst_base::subclass::BaseTransformMode =
+        gst_base::subclass::BaseTransformMode::NeverInPlace;
+    const PASSTHROUGH_ON_SAME_CAPS: bool = false;
+    const TRANSFORM_IP_ON_PASSTHROUGH: bool = false;
+
+    fn stop(&self) -> Result<(), gst::ErrorMessage> {
+        // Drop state
+        let _ = self.state.borrow_mut().take();
+
+        gst::info!(CAT, imp = self, "Stopped");
+
+        Ok(())
+    }
+
+    fn before_transform(&self, inbuf: &gst::BufferRef) {
+        // Apply the values of controlled properties for this buffer
+        let segment = self.obj().segment();
+        let Some(stream_time) = segment
+            .downcast_ref::<gst::ClockTime>()
+            .and_then(|segment| segment.to_stream_time(inbuf.pts()))
+        else {
+            return;
+        };
+
+        let _ = self.obj().sync_values(stream_time);
+    }
+}
+
+impl VideoFilterImpl for Dewarp {
+    fn set_info(
+        &self,
+        incaps: &gst::Caps,
+        in_info: &gst_video::VideoInfo,
+        outcaps: &gst::Caps,
+        _out_info: &gst_video::VideoInfo,
+    ) -> Result<(), gst::LoggableError> {
+        gst::debug!(
+            CAT,
+            imp = self,
+            "Configured for caps {} to {}",
+            incaps,
+            outcaps
+        );
+
+        let backend = self.settings.lock().unwrap().backend;
+        let mut state = self.state.borrow_mut();
+
+        #[cfg(feature = "wgpu")]
+        let gpu = match (backend, state.take().and_then(|state| state.gpu)) {
+            (DewarpBackend::Cpu, _) => None,
+            (_, Some(gpu)) => Some(gpu),
+            (_, None) => match super::gpu::GpuDewarp::new() {
+                Ok(gpu) => {
+                    gst::info!(CAT, imp = self, "Processing frames on the GPU");
+                    Some(gpu)
+                }
+                Err(err) if backend == DewarpBackend::Gpu => {
+                    return Err(gst::loggable_error!(CAT, "Failed to initialize GPU: {err}"));
+                }
+                Err(err) => {
+                    gst::warning!(
+                        CAT,
+                        imp = self,
+                        "Failed to initialize GPU, falling back to CPU: {err}"
+                    );
+                    None
+                }
+            },
+        };
+
+        #[cfg(not(feature = "wgpu"))]
+        if backend == DewarpBackend::Gpu {
+            return Err(gst::loggable_error!(
+                CAT,
+                "GPU backend not available, built without wgpu support"
+            ));
+        }
+
+        *state = Some(State {
+            in_info: in_info.clone(),
+            table: None,
+            #[cfg(feature = "wgpu")]
+            gpu,
+        });
+
+        Ok(())
+    }
+
+    fn transform_frame(
+        &self,
+        in_frame: &gst_video::VideoFrameRef<&gst::BufferRef>,
+        out_frame: &mut gst_video::VideoFrameRef<&mut gst::BufferRef>,
+    ) -> Result<gst::FlowSuccess, gst::FlowError> {
+        let mut state_guard = self.state.borrow_mut();
+        let state = state_guard.as_mut().ok_or_else(|| {
+            gst::element_imp_error!(self, gst::CoreError::Negotiation, ["Have no state yet"]);
+            gst::FlowError::NotNegotiated
+        })?;
+
+        let in_width = state.in_info.width() as usize;
+        let in_height = state.in_info.height() as usize;
+        let out_width = out_frame.width() as usize;
+        let out_height = out_frame.height() as usize;
+
+        {
+            let mut settings = self.settings.lock().unwrap();
+            if settings.changed || state.table.is_none() {
+                gst::debug!(
+                    CAT,
+                    imp = self,
+                    "Updating remap table for {:?}",
+                    settings.view
+                );
+                settings.changed = false;
+                let table =
+                    RemapTable::new(&settings.view, in_width, in_height, out_width, out_height);
+
+                #[cfg(feature = "wgpu")]
+                if let Some(gpu) = state.gpu.as_mut() {
+                    gpu.set_table(&table);
+                }
+
+                state.table = Some(table);
+            }
+        }
+
+        let in_stride = in_frame.plane_stride()[0] as usize;
+        let out_stride = out_frame.plane_stride()[0] as usize;
+        let in_data = in_frame.plane_data(0).unwrap();
+        let out_data = out_frame.plane_data_mut(0).unwrap();
+
+        #[cfg(feature = "wgpu")]
+        if let Some(gpu) = state.gpu.as_mut() {
+            gpu.process(
+                in_data, in_stride, in_width, in_height, out_data, out_stride, out_width,
+                out_height,
+            )
+            .map_err(|err| {
+                gst::element_imp_error!(
+                    self,
+                    gst::ResourceError::Failed,
+                    ["Failed to process frame on the GPU: {err}"]
+                );
+                gst::FlowError::Error
+            })?;
+
+            return Ok(gst::FlowSuccess::Ok);
+        }
+
+        remap::remap_frame(
+            state.table.as_ref().unwrap(),
+            in_data,
+            in_stride,
+            in_width,
+            in_height,
+            out_data,
+            out_stride,
+        );
+
+        Ok(gst::FlowSuccess::Ok)
+    }
+}
@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: MPL-2.0
+/**
+ * element-dewarp:
+ * @short_description: Dewarps fisheye and equirectangular (360°) video into a rectilinear view.
+ *
+ * Renders a virtual perspective camera looking into the scene captured by a fisheye or a 360°
+ * camera, as commonly done for surveillance cameras. The view direction is set with the `pan`
+ * and `tilt` properties and the field of view with the `zoom` property. These can be changed
+ * while playing and are controllable, e.g. to implement a virtual PTZ camera.
+ *
+ * The output has the same size and format as the input. Areas that are not covered by the
+ * input are filled with transparent black.
+ *
+ * When built with the `wgpu` feature, the frames can be processed on the GPU, see the `backend`
+ * property. Otherwise, or if no suitable GPU is available, they are processed on the CPU.
+ *
+ * ## Example pipeline
+ * ```bash
+ * gst-launch-1.0 v4l2src ! videoconvert ! dewarp projection=fisheye fisheye-fov=180 tilt=-90 \
+ *   zoom=1.5 ! videoconvert ! autovideosink
+ * ```
+ *
+ * Since: plugins-rs-0.14.0
+ */
+use gst::glib;
+use gst::prelude::*;
+
+#[cfg(feature = "wgpu")]
+mod gpu;
+mod imp;
+mod remap;
+
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstDewarpProjection")]
+#[non_exhaustive]
+pub enum DewarpProjection {
+    #[default]
+    #[enum_value(name = "Fisheye: Equidistant fisheye lens", nick = "fisheye")]
+    Fisheye = 0,
+    #[enum_value(
+        name = "Equirectangular: 360° equirectangular panorama",
+        nick = "equirectangular"
+    )]
+    Equirectangular = 1,
+}
+
+#[derive(Debug, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Clone, Copy, glib::Enum)]
+#[repr(u32)]
+#[enum_type(name = "GstDewarpBackend")]
+#[non_exhaustive]
+pub enum DewarpBackend {
+    #[default]
+    #[enum_value(
+        name = "Auto: Use the GPU if available, the CPU otherwise",
+        nick = "auto"
+    )]
+    Auto = 0,
+    #[enum_value(name = "CPU: Always use the CPU", nick = "cpu")]
+    Cpu = 1,
+    #[enum_value(name = "GPU: Always use the GPU, fail if not available", nick = "gpu")]
+    Gpu = 2,
+}
+
+glib::wrapper! {
+    pub struct Dewarp(ObjectSubclass<imp::Dewarp>) @extends gst_video::VideoFilter, gst_base::BaseTransform, gst::Element, gst::Object;
+}
+
+pub fn register(plugin: &gst::Plugin) -> Result<(), glib::BoolError> {
+    gst::Element::register(
+        Some(plugin),
+        "dewarp",
+        gst::Rank::NONE,
+        Dewarp::static_type(),
+    )
+}
@@ -0,0 +1,140 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use std::f64::consts::PI;
+
+use super::DewarpProjection;
+
+/// Horizontal field of view of the output in degrees at zoom 1
+const BASE_FOV: f64 = 90.0;
+
+/// Input coordinate of output pixels that are not covered by the input
+pub const INVALID: f32 = -1.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct View {
+    pub projection: DewarpProjection,
+    /// Field of view of the fisheye lens in degrees
+    pub fisheye_fov: f64,
+    /// Horizontal view direction in degrees, positive values look right
+    pub pan: f64,
+    /// Vertical view direction in degrees, positive values look up
+    pub tilt: f64,
+    pub zoom: f64,
+}
+
+/// Input pixel coordinates to sample for each output pixel
+#[derive(Debug)]
+pub struct RemapTable {
+    pub width: usize,
+    pub height: usize,
+    pub coords: Vec<[f32; 2]>,
+}
+
+impl RemapTable {
+    pub fn new(
+        view: &View,
+        in_width: usize,
+        in_height: usize,
+        out_width: usize,
+        out_height: usize,
+    ) -> Self {
+        let in_w = in_width as f64;
+        let in_h = in_height as f64;
+
+        let hfov = (BASE_FOV / view.zoom).to_radians();
+        let focal = (out_width as f64 / 2.0) / (hfov / 2.0).tan();
+        let (sin_pan, cos_pan) = view.pan.to_radians().sin_cos();
+        let (sin_tilt, cos_tilt) = view.tilt.to_radians().sin_cos();
+        let half_fisheye_fov = (view.fisheye_fov / 2.0).to_radians();
+        let fisheye_radius = in_w.min(in_h) / 2.0;
+
+        let mut coords = Vec::with_capacity(out_width * out_height);
+        for y in 0..out_height {
+            for x in 0..out_width {
+                // Ray through the pixel center with x pointing right, y down and z forward
+                let dx = x as f64 + 0.5 - out_width as f64 / 2.0;
+                let dy = y as f64 + 0.5 - out_height as f64 / 2.0;
+                let dz = focal;
+                let norm = (dx * dx + dy * dy + dz * dz).sqrt();
+                let (dx, dy, dz) = (dx / norm, dy / norm, dz / norm);
+
+                let (dy, dz) = (dy * cos_tilt - dz * sin_tilt, dy * sin_tilt + dz * cos_tilt);
+                let (dx, dz) = (dx * cos_pan + dz * sin_pan, -dx * sin_pan + dz * cos_pan);
+
+                let (sx, sy) = match view.projection {
+                    DewarpProjection::Equirectangular => {
+                        let lon = dx.atan2(dz);
+                        let lat = (-dy).clamp(-1.0, 1.0).asin();
+                        ((lon / (2.0 * PI) + 0.5) * in_w, (0.5 - lat / PI) * in_h)
+                    }
+                    DewarpProjection::Fisheye => {
+                        // Equidistant projection around the optical axis pointing forward
+                        let theta = dz.clamp(-1.0, 1.0).acos();
+                        if theta > half_fisheye_fov {
+                            coords.push([INVALID, INVALID]);
+                            continue;
+                        }
+                        let r = theta / half_fisheye_fov * fisheye_radius;
+                        let phi = dy.atan2(dx);
+                        (in_w / 2.0 + r * phi.cos(), in_h / 2.0 + r * phi.sin())
+                    }
+                };
+
+                coords.push([
+                    (sx - 0.5).clamp(0.0, in_w - 1.0) as f32,
+                    (sy - 0.5).clamp(0.0, in_h - 1.0) as f32,
+                ]);
+            }
+        }
+
+        RemapTable {
+            width: out_width,
+            height: out_height,
+            coords,
+        }
+    }
+}
+
+/// Samples the input with bilinear interpolation, for formats with 4 bytes per pixel
+pub fn remap_frame(
+    table: &RemapTable,
+    in_data: &[u8],
+    in_stride: usize,
+    in_width: usize,
+    in_height: usize,
+    out_data: &mut [u8],
+    out_stride: usize,
+) {
+    for (y, out_line) in out_data
+        .chunks_mut(out_stride)
+        .take(table.height)
+        .enumerate()
+    {
+        let coords = &table.coords[y * table.width..][..table.width];
+
+        for (out_pixel, &[sx, sy]) in out_line[..table.width * 4].chunks_exact_mut(4).zip(coords) {
+            if sx < 0.0 {
+                out_pixel.fill(0);
+                continue;
+            }
+
+            let x0 = sx as usize;
+            let y0 = sy as usize;
+            let x1 = (x0 + 1).min(in_width - 1);
+            let y1 = (y0 + 1).min(in_height - 1);
+            let fx = sx - x0 as f32;
+            let fy = sy - y0 as f32;
+
+            let p00 = &in_data[y0 * in_stride + x0 * 4..][..4];
+            let p10 = &in_data[y0 * in_stride + x1 * 4..][..4];
+            let p01 = &in_data[y1 * in_stride + x0 * 4..][..4];
+            let p11 = &in_data[y1 * in_stride + x1 * 4..][..4];
+
+            for (c, out) in out_pixel.iter_mut().enumerate() {
+                let top = p00[c] as f32 * (1.0 - fx) + p10[c] as f32 * fx;
+                let bottom = p01[c] as f32 * (1.0 - fx) + p11[c] as f32 * fx;
+                *out = (top * (1.0 - fy) + bottom * fy + 0.5) as u8;
+            }
+        }
+    }
+}
@@ -18,16 +18,22 @@ use gst::prelude::*;
 
 mod border;
 mod colordetect;
+mod dewarp;
 mod videocompare;
 
 pub use videocompare::{HashAlgorithm, PadDistance, VideoCompareMessage};
 
 fn plugin_init(plugin: &gst::Plugin) -> Result<(), gst::glib::BoolError> {
     #[cfg(feature = "doc")]
-    HashAlgorithm::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    {
+        HashAlgorithm::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        dewarp::DewarpProjection::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+        dewarp::DewarpBackend::static_type().mark_as_plugin_api(gst::PluginAPIFlags::empty());
+    }
 
     border::register(plugin)?;
     colordetect::register(plugin)?;
+    dewarp::register(plugin)?;
     videocompare::register(plugin)
 }
 
@@ -0,0 +1,92 @@
+// SPDX-License-Identifier: MPL-2.0
+
+use gst::prelude::*;
+
+fn init() {
+    use std::sync::Once;
+    static INIT: Once = Once::new();
+
+    INIT.call_once(|| {
+        gst::init().unwrap();
+        gstrsvideofx::plugin_register_static().expect("Failed to register rsvideofx plugin");
+    });
+}
+
+fn dewarp_solid_color(projection: &str, pattern: &str) -> Vec<u8> {
+    let mut h = gst_check::Harness::new_parse(&format!(
+        "videotestsrc pattern={pattern} num-buffers=1 ! \
+         video/x-raw,format=RGBA,width=64,height=32 ! \
+         dewarp projection={projection} backend=cpu pan=45 tilt=10 zoom=2"
+    ));
+    h.play();
+
+    let buffer = h.pull().unwrap();
+    let map = buffer.map_readable().unwrap();
+    map.to_vec()
+}
+
+#[test]
+fn test_equirectangular_solid_color() {
+    init();
+
+    let data = dewarp_solid_color("equirectangular", "red");
+    assert_eq!(data.len(), 64 * 32 * 4);
+    for pixel in data.chunks_exact(4) {
+        assert_eq!(pixel, [255, 0, 0, 255]);
+    }
+}
+
+#[test]
+fn test_fisheye_solid_color() {
+    init();
+
+    // The default 180° fisheye covers the whole view at zoom 2
+    let data = dewarp_solid_color("fisheye", "blue");
+    assert_eq!(data.len(), 64 * 32 * 4);
+    for pixel in data.chunks_exact(4) {
+        assert_eq!(pixel, [0, 0, 255, 255]);
+    }
+}
+
+#[test]
+fn test_fisheye_outside_fov() {
+    init();
+
+    let mut h = gst_check::Harness::new_parse(
+        "videotestsrc pattern=white num-buffers=1 ! \
+         video/x-raw,format=RGBA,width=64,height=64 ! \
+         dewarp projection=fisheye fisheye-fov=10 backend=cpu",
+    );
+    h.play();
+
+    // The center of the view is covered by the input, the corners are not
+    let buffer = h.pull().unwrap();
+    let map = buffer.map_readable().unwrap();
+    let pixel = |x: usize, y: usize| &map[(y * 64 + x) * 4..][..4];
+    assert_eq!(pixel(32, 32), [255, 255, 255, 255]);
+    assert_eq!(pixel(0, 0), [0, 0, 0, 0]);
+    assert_eq!(pixel(63, 63), [0, 0, 0, 0]);
+}
+
+#[test]
+fn test_properties() {
+    init();
+
+    let dewarp = gst::ElementFactory::make("dewarp")
+        .property_from_str("projection", "equirectangular")
+        .property("pan", 90.0f64)
+        .property("tilt", -30.0f64)
+        .property("zoom", 1.5f64)
+        .build()
+        .unwrap();
+
+    assert_eq!(dewarp.property::<f64>("pan"), 90.0);
+    assert_eq!(dewarp.property::<f64>("tilt"), -30.0);
+    assert_eq!(dewarp.property::<f64>("zoom"), 1.5);
+
+    for name in ["fisheye-fov", "pan", "tilt", "zoom"] {
+        let pspec = dewarp.find_property(name).unwrap();
+        assert!(pspec.flags().contains(gst::PARAM_FLAG_CONTROLLABLE));
+        assert!(pspec.flags().contains(gst::PARAM_FLAG_MUTABLE_PLAYING));
+    }
+}